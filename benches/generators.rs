@@ -1,3 +1,4 @@
+use curve25519_dalek::scalar::Scalar;
 use webb_bulletproofs::{BulletproofGens, PedersenGens};
 
 #[macro_use]
@@ -8,6 +9,18 @@ fn pc_gens(c: &mut Criterion) {
 	c.bench_function("PedersenGens::new", |b| b.iter(PedersenGens::default));
 }
 
+fn pc_gens_commit(c: &mut Criterion) {
+	let pc_gens = PedersenGens::default();
+	let precomputed = pc_gens.precompute();
+	let (value, blinding) = (Scalar::from(1037u64), Scalar::from(7u64));
+	c.bench_function("PedersenGens::commit", move |b| {
+		b.iter(|| pc_gens.commit(value, blinding))
+	});
+	c.bench_function("PedersenGensPrecomputed::commit", move |b| {
+		b.iter(|| precomputed.commit(value, blinding))
+	});
+}
+
 fn bp_gens(c: &mut Criterion) {
 	c.bench_function_over_inputs(
 		"BulletproofGens::new",
@@ -20,6 +33,7 @@ criterion_group! {
 	bp,
 	bp_gens,
 	pc_gens,
+	pc_gens_commit,
 }
 
 criterion_main!(bp);
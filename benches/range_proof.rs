@@ -9,7 +9,14 @@ use curve25519_dalek::scalar::Scalar;
 
 use merlin::Transcript;
 
-use webb_bulletproofs::{BulletproofGens, PedersenGens, RangeProof};
+use webb_bulletproofs::{
+	range_proof_mpc::{
+		dealer::Dealer,
+		messages::{BitCommitment, PolyCommitment, ProofShare},
+		party::Party,
+	},
+	BulletproofGens, PedersenGens, RangeProof,
+};
 
 static AGGREGATION_SIZES: [usize; 6] = [1, 2, 4, 8, 16, 32];
 
@@ -54,6 +61,28 @@ fn create_aggregated_rangeproof_n_64(c: &mut Criterion) {
 	create_aggregated_rangeproof_helper(64, c);
 }
 
+fn create_aggregated_rangeproof_low_memory_n_64(c: &mut Criterion) {
+	let n = 64;
+	c.bench_function_over_inputs(
+		"Aggregated 64-bit low-memory rangeproof creation",
+		move |b, &&m| {
+			let pc_gens = PedersenGens::default();
+			let bp_gens = BulletproofGens::new(n, m);
+			let mut rng = rand::thread_rng();
+
+			let values: Vec<u64> = (0..m).map(|_| rng.gen()).collect();
+			let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+			b.iter(|| {
+				let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+
+				RangeProof::prove_multiple_low_memory(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, n)
+			})
+		},
+		&AGGREGATION_SIZES,
+	);
+}
+
 fn verify_aggregated_rangeproof_helper(n: usize, c: &mut Criterion) {
 	let label = format!("Aggregated {}-bit rangeproof verification", n);
 
@@ -99,6 +128,79 @@ fn verify_aggregated_rangeproof_n_64(c: &mut Criterion) {
 	verify_aggregated_rangeproof_helper(64, c);
 }
 
+static DEALER_PARTY_COUNTS: [usize; 3] = [64, 128, 256];
+
+/// Runs the parties of an aggregation once, returning their messages to
+/// the dealer.  The dealer's challenges depend only on the messages, so
+/// the dealer can be run on them again and again.
+fn dealer_messages(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	n: usize,
+	m: usize,
+) -> (Vec<BitCommitment>, Vec<PolyCommitment>, Vec<ProofShare>) {
+	let mut rng = rand::thread_rng();
+	let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+	let dealer = Dealer::start(bp_gens, pc_gens, &mut transcript, n, m).unwrap();
+
+	let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+		.map(|j| {
+			let v = rng.gen_range(0, u64::max_value() >> (64 - n));
+			let party = Party::new(bp_gens, pc_gens, v, Scalar::random(&mut rng), n).unwrap();
+			party.assign_position(j).unwrap()
+		})
+		.unzip();
+	let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments.clone()).unwrap();
+
+	let (parties, poly_commitments): (Vec<_>, Vec<_>) =
+		parties.into_iter().map(|p| p.apply_challenge(&bit_challenge)).unzip();
+	let (_, poly_challenge) = dealer.receive_poly_commitments(poly_commitments.clone()).unwrap();
+
+	let shares = parties
+		.into_iter()
+		.map(|p| p.apply_challenge(&poly_challenge).unwrap())
+		.collect();
+	(bit_commitments, poly_commitments, shares)
+}
+
+fn dealer_helper(n: usize, malformed: bool, c: &mut Criterion) {
+	let label = if malformed {
+		format!("Dealer of aggregated {}-bit rangeproof with malformed shares", n)
+	} else {
+		format!("Dealer of aggregated {}-bit rangeproof", n)
+	};
+
+	c.bench_function_over_inputs(
+		&label,
+		move |b, &&m| {
+			let pc_gens = PedersenGens::default();
+			let bp_gens = BulletproofGens::new(n, m);
+			let (bit_commitments, poly_commitments, mut shares) = dealer_messages(&bp_gens, &pc_gens, n, m);
+			if malformed {
+				// Swapping two shares makes both of them malformed.
+				shares.swap(0, m / 2);
+			}
+
+			b.iter(|| {
+				let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+				let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+				let (dealer, _) = dealer.receive_bit_commitments(bit_commitments.clone()).unwrap();
+				let (dealer, _) = dealer.receive_poly_commitments(poly_commitments.clone()).unwrap();
+				dealer.receive_shares(&shares)
+			})
+		},
+		&DEALER_PARTY_COUNTS,
+	);
+}
+
+fn dealer_n_64(c: &mut Criterion) {
+	dealer_helper(64, false, c);
+}
+
+fn dealer_malformed_shares_n_64(c: &mut Criterion) {
+	dealer_helper(64, true, c);
+}
+
 criterion_group! {
 	name = create_rp;
 	config = Criterion::default().sample_size(10);
@@ -107,6 +209,7 @@ criterion_group! {
 	create_aggregated_rangeproof_n_16,
 	create_aggregated_rangeproof_n_32,
 	create_aggregated_rangeproof_n_64,
+	create_aggregated_rangeproof_low_memory_n_64,
 }
 
 criterion_group! {
@@ -119,4 +222,12 @@ criterion_group! {
 	verify_aggregated_rangeproof_n_64,
 }
 
-criterion_main!(create_rp, verify_rp);
+criterion_group! {
+	name = dealer_rp;
+	config = Criterion::default().sample_size(10);
+	targets =
+	dealer_n_64,
+	dealer_malformed_shares_n_64,
+}
+
+criterion_main!(create_rp, verify_rp, dealer_rp);
@@ -0,0 +1,59 @@
+//! Benchmarks over the parameters of the proofs, using the fixtures of the
+//! `bench_utils` module, so that the same inputs are measured on every
+//! run.
+//!
+//! Run with `cargo bench --features "bench-utils yoloproofs" --bench suite`.
+
+#[macro_use]
+extern crate criterion;
+use criterion::{BenchmarkId, Criterion};
+
+use webb_bulletproofs::bench_utils::RangeProofFixture;
+
+/// The seed of the fixtures.
+const SEED: u64 = 0;
+
+static BITSIZES: [usize; 4] = [8, 16, 32, 64];
+static AGGREGATION_SIZES: [usize; 4] = [1, 2, 4, 8];
+
+fn rangeproof(c: &mut Criterion) {
+	let mut group = c.benchmark_group("rangeproof");
+	group.sample_size(10);
+	for &n in &BITSIZES {
+		for &m in &AGGREGATION_SIZES {
+			let fixture = RangeProofFixture::new(n, m, SEED).unwrap();
+			let parameter = format!("n={}/m={}", n, m);
+			group.bench_with_input(BenchmarkId::new("prove", &parameter), &fixture, |b, fixture| {
+				b.iter(|| fixture.prove().unwrap())
+			});
+			group.bench_with_input(BenchmarkId::new("verify", &parameter), &fixture, |b, fixture| {
+				b.iter(|| fixture.verify().unwrap())
+			});
+		}
+	}
+	group.finish();
+}
+
+#[cfg(feature = "yoloproofs")]
+fn circuit(c: &mut Criterion) {
+	use webb_bulletproofs::bench_utils::CircuitFixture;
+
+	let mut group = c.benchmark_group("circuit");
+	group.sample_size(10);
+	for size in (4..=12).step_by(2).map(|lg| 1 << lg) {
+		let fixture = CircuitFixture::new(size, SEED).unwrap();
+		group.bench_with_input(BenchmarkId::new("prove", size), &fixture, |b, fixture| {
+			b.iter(|| fixture.prove().unwrap())
+		});
+		group.bench_with_input(BenchmarkId::new("verify", size), &fixture, |b, fixture| {
+			b.iter(|| fixture.verify().unwrap())
+		});
+	}
+	group.finish();
+}
+
+#[cfg(not(feature = "yoloproofs"))]
+fn circuit(_: &mut Criterion) {}
+
+criterion_group!(suite, rangeproof, circuit);
+criterion_main!(suite);
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use webb_bulletproofs::InnerProductProof;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(proof) = InnerProductProof::from_bytes(data) {
+		assert_eq!(proof.to_bytes(), data);
+	}
+});
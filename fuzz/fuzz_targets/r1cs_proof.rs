@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use webb_bulletproofs::r1cs::R1CSProof;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = R1CSProof::from_bytes(data);
+	let _ = R1CSProof::from_bytes_versioned(data);
+	if let Ok(proof) = R1CSProof::from_bytes_strict(data) {
+		assert_eq!(proof.to_bytes(), data);
+	}
+});
@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use webb_bulletproofs::RangeProof;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = RangeProof::from_bytes(data);
+	let _ = RangeProof::from_bytes_versioned(data);
+	if let Ok(proof) = RangeProof::from_bytes_strict(data) {
+		assert_eq!(proof.to_bytes(), data);
+	}
+});
@@ -0,0 +1,224 @@
+//! Fixtures for benchmarking the proofs of this crate.
+//!
+//! Each fixture creates the generators, the witness and a valid proof for
+//! the given parameters from a seed, so that the same inputs are measured
+//! on every run and across versions of the crate.  The benchmarks in
+//! `benches/suite.rs` use them, and downstream crates can use them to
+//! track the cost of the proofs in their own benchmarks.
+//!
+//! This module is only available with the `bench-utils` feature, which
+//! requires the standard library.
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+use rand::{rngs::StdRng, CryptoRng, Rng, SeedableRng};
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	range_proof::RangeProof,
+};
+
+/// The transcript label of the proofs created by the fixtures.
+pub const TRANSCRIPT_LABEL: &[u8] = b"BulletproofsBenchmark";
+
+/// Returns `m` random values of `n` bits and their blinding factors,
+/// drawn from `rng`.
+pub fn random_values<R: Rng + CryptoRng>(n: usize, m: usize, rng: &mut R) -> (Vec<u64>, Vec<Scalar>) {
+	let max = ((1u128 << n) - 1) as u64;
+	let values = (0..m).map(|_| rng.gen_range(0, max)).collect();
+	let blindings = (0..m).map(|_| Scalar::random(rng)).collect();
+	(values, blindings)
+}
+
+/// An aggregated range proof for `m` values of `n` bits, with its inputs.
+pub struct RangeProofFixture {
+	/// The Pedersen generators.
+	pub pc_gens: PedersenGens,
+	/// The generators, with the capacity needed for the proof.
+	pub bp_gens: BulletproofGens,
+	/// The bitsize of the values.
+	pub n: usize,
+	/// The values.
+	pub values: Vec<u64>,
+	/// The blinding factors of the values.
+	pub blindings: Vec<Scalar>,
+	/// The proof.
+	pub proof: RangeProof,
+	/// The commitments to the values.
+	pub value_commitments: Vec<CompressedRistretto>,
+}
+
+impl RangeProofFixture {
+	/// Creates the inputs and a proof for `m` values of `n` bits, drawing
+	/// them from an RNG seeded with `seed`.
+	pub fn new(n: usize, m: usize, seed: u64) -> Result<Self, ProofError> {
+		let mut rng = StdRng::seed_from_u64(seed);
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n.next_power_of_two(), m.next_power_of_two());
+		let (values, blindings) = random_values(n, m, &mut rng);
+
+		let (proof, value_commitments) = RangeProof::prove_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(TRANSCRIPT_LABEL),
+			&values,
+			&blindings,
+			n,
+			&mut rng,
+		)?;
+
+		Ok(RangeProofFixture {
+			pc_gens,
+			bp_gens,
+			n,
+			values,
+			blindings,
+			proof,
+			value_commitments,
+		})
+	}
+
+	/// Creates a new proof for the values of the fixture.
+	pub fn prove(&self) -> Result<RangeProof, ProofError> {
+		RangeProof::prove_multiple(
+			&self.bp_gens,
+			&self.pc_gens,
+			&mut Transcript::new(TRANSCRIPT_LABEL),
+			&self.values,
+			&self.blindings,
+			self.n,
+		)
+		.map(|(proof, _)| proof)
+	}
+
+	/// Verifies the proof of the fixture.
+	pub fn verify(&self) -> Result<(), ProofError> {
+		self.proof.verify_multiple(
+			&self.bp_gens,
+			&self.pc_gens,
+			&mut Transcript::new(TRANSCRIPT_LABEL),
+			&self.value_commitments,
+			self.n,
+		)
+	}
+}
+
+#[cfg(feature = "yoloproofs")]
+pub use self::r1cs::CircuitFixture;
+
+#[cfg(feature = "yoloproofs")]
+mod r1cs {
+	use super::*;
+
+	use crate::r1cs::{ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Verifier};
+
+	/// A constraint system proof for a circuit of `size` multipliers, with
+	/// its inputs.
+	///
+	/// The circuit proves knowledge of the committed \\(x\\) such that
+	/// \\(x^{size + 1} = y\\) for the public \\(y\\), with a chain of
+	/// `size` multipliers.
+	pub struct CircuitFixture {
+		/// The Pedersen generators.
+		pub pc_gens: PedersenGens,
+		/// The generators, with a capacity of the padded circuit size.
+		pub bp_gens: BulletproofGens,
+		/// The number of multipliers of the circuit.
+		pub size: usize,
+		/// The secret input \\(x\\).
+		pub x: Scalar,
+		/// The blinding factor of the commitment to \\(x\\).
+		pub x_blinding: Scalar,
+		/// The public output \\(y = x^{size + 1}\\).
+		pub y: Scalar,
+		/// The proof.
+		pub proof: R1CSProof,
+		/// The commitment to \\(x\\).
+		pub commitment: CompressedRistretto,
+	}
+
+	impl CircuitFixture {
+		/// Creates the inputs and a proof for a circuit of `size`
+		/// multipliers, drawing them from an RNG seeded with `seed`.
+		pub fn new(size: usize, seed: u64) -> Result<Self, R1CSError> {
+			let mut rng = StdRng::seed_from_u64(seed);
+			let x = Scalar::random(&mut rng);
+			let x_blinding = Scalar::random(&mut rng);
+			let y = (0..size).fold(x, |acc, _| acc * x);
+			let pc_gens = PedersenGens::default();
+			let bp_gens = BulletproofGens::new(size.next_power_of_two(), 1);
+
+			let (proof, commitment) = prove(&pc_gens, &bp_gens, size, x, x_blinding, y)?;
+			Ok(CircuitFixture {
+				pc_gens,
+				bp_gens,
+				size,
+				x,
+				x_blinding,
+				y,
+				proof,
+				commitment,
+			})
+		}
+
+		/// Creates a new proof for the inputs of the fixture, and returns it
+		/// with the commitment to \\(x\\).
+		pub fn prove(&self) -> Result<(R1CSProof, CompressedRistretto), R1CSError> {
+			prove(&self.pc_gens, &self.bp_gens, self.size, self.x, self.x_blinding, self.y)
+		}
+
+		/// Verifies the proof of the fixture.
+		pub fn verify(&self) -> Result<(), R1CSError> {
+			let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+			let mut verifier = Verifier::new(&mut transcript);
+			let x = verifier.commit(self.commitment);
+			gadget(&mut verifier, x.into(), self.size, self.y);
+			verifier.verify(&self.proof, &self.pc_gens, &self.bp_gens)
+		}
+	}
+
+	fn prove(
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		size: usize,
+		x: Scalar,
+		x_blinding: Scalar,
+		y: Scalar,
+	) -> Result<(R1CSProof, CompressedRistretto), R1CSError> {
+		let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+		let mut prover = Prover::new(pc_gens, &mut transcript);
+		let (commitment, x) = prover.commit(x, x_blinding);
+		gadget(&mut prover, x.into(), size, y);
+		Ok((prover.prove(bp_gens)?, commitment))
+	}
+
+	/// Constrains \\(x^{size + 1} = y\\).
+	fn gadget<CS: ConstraintSystem>(cs: &mut CS, x: LinearCombination, size: usize, y: Scalar) {
+		let power = (0..size).fold(x.clone(), |acc, _| cs.multiply(acc, x.clone()).2.into());
+		cs.constrain(power - y);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fixtures_verify() {
+		let fixture = RangeProofFixture::new(16, 2, 7).unwrap();
+		assert!(fixture.verify().is_ok());
+		assert_eq!(fixture.values, RangeProofFixture::new(16, 2, 7).unwrap().values);
+		assert!(fixture.prove().is_ok());
+
+		#[cfg(feature = "yoloproofs")]
+		{
+			let fixture = CircuitFixture::new(5, 7).unwrap();
+			assert!(fixture.verify().is_ok());
+			assert_eq!(fixture.commitment, CircuitFixture::new(5, 7).unwrap().commitment);
+		}
+	}
+}
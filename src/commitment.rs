@@ -0,0 +1,105 @@
+//! The `commitment` module contains a wrapper around compressed Pedersen
+//! commitments for homomorphic arithmetic on them.
+
+#![deny(missing_docs)]
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+
+use crate::{errors::ProofError, generators::PedersenGens};
+
+/// A Pedersen commitment \\(vB + \tilde{v}\tilde{B}\\) to a value \\(v\\)
+/// with blinding factor \\(\tilde{v}\\), in compressed form.
+///
+/// Commitments are additively homomorphic: the sum of commitments to
+/// \\(v_1\\) and \\(v_2\\) with blinding factors \\(\tilde{v}_1\\) and
+/// \\(\tilde{v}_2\\) is a commitment to \\(v_1 + v_2\\) with blinding
+/// factor \\(\tilde{v}_1 + \tilde{v}_2\\).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Commitment(pub CompressedRistretto);
+
+impl Commitment {
+	/// Commits to `value` with the blinding factor `blinding`.
+	pub fn commit(pc_gens: &PedersenGens, value: Scalar, blinding: Scalar) -> Self {
+		Commitment(pc_gens.commit(value, blinding).compress())
+	}
+
+	/// Checks that this is a commitment to `value` with the blinding
+	/// factor `blinding`.
+	pub fn verify_opening(&self, pc_gens: &PedersenGens, value: Scalar, blinding: Scalar) -> bool {
+		pc_gens.open(&self.0, value, blinding)
+	}
+
+	/// Returns the commitment to the sum of the values and blinding
+	/// factors of `self` and `other`.
+	///
+	/// Returns an error if either commitment is not a valid point.
+	pub fn add(&self, other: &Commitment) -> Result<Commitment, ProofError> {
+		Ok(Commitment((self.decompress()? + other.decompress()?).compress()))
+	}
+
+	/// Returns the commitment to the difference of the values and blinding
+	/// factors of `self` and `other`.
+	///
+	/// Returns an error if either commitment is not a valid point.
+	pub fn sub(&self, other: &Commitment) -> Result<Commitment, ProofError> {
+		Ok(Commitment((self.decompress()? - other.decompress()?).compress()))
+	}
+
+	/// Returns the compressed point of the commitment.
+	pub fn as_compressed(&self) -> &CompressedRistretto {
+		&self.0
+	}
+
+	fn decompress(&self) -> Result<RistrettoPoint, ProofError> {
+		self.0.decompress().ok_or(ProofError::FormatError)
+	}
+}
+
+impl From<CompressedRistretto> for Commitment {
+	fn from(point: CompressedRistretto) -> Self {
+		Commitment(point)
+	}
+}
+
+impl From<Commitment> for CompressedRistretto {
+	fn from(commitment: Commitment) -> Self {
+		commitment.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn homomorphic_arithmetic() {
+		let pc_gens = PedersenGens::default();
+		let (v1, b1) = (Scalar::from(30u64), Scalar::from(5u64));
+		let (v2, b2) = (Scalar::from(12u64), Scalar::from(7u64));
+		let c1 = Commitment::commit(&pc_gens, v1, b1);
+		let c2 = Commitment::commit(&pc_gens, v2, b2);
+
+		assert!(c1.verify_opening(&pc_gens, v1, b1));
+		assert!(!c1.verify_opening(&pc_gens, v1, b2));
+		assert!(pc_gens.open(c1.as_compressed(), v1, b1));
+
+		let sum = c1.add(&c2).unwrap();
+		assert!(sum.verify_opening(&pc_gens, v1 + v2, b1 + b2));
+		let difference = c1.sub(&c2).unwrap();
+		assert!(difference.verify_opening(&pc_gens, v1 - v2, b1 - b2));
+		assert_eq!(difference.add(&c2).unwrap(), c1);
+	}
+
+	#[test]
+	fn invalid_points_are_rejected() {
+		let pc_gens = PedersenGens::default();
+		let c = Commitment::commit(&pc_gens, Scalar::one(), Scalar::one());
+		let invalid = Commitment(CompressedRistretto([0xff; 32]));
+
+		assert_eq!(c.add(&invalid), Err(ProofError::FormatError));
+		assert_eq!(invalid.sub(&c), Err(ProofError::FormatError));
+	}
+}
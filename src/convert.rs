@@ -0,0 +1,179 @@
+//! Conversions into the `curve25519-dalek` types used by the proofs.
+//!
+//! The [`Scalar`], [`RistrettoPoint`] and [`CompressedRistretto`] types are
+//! re-exported from the crate root, so that users can build the inputs of
+//! the proofs without depending on the same version of `curve25519-dalek`
+//! as this crate.
+
+#![deny(missing_docs)]
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+
+use crate::{errors::ProofError, util};
+
+/// Returns the scalar \\(v\\), for a value or a blinding factor given as
+/// an integer.
+pub fn scalar_from_u64(v: u64) -> Scalar {
+	Scalar::from(v)
+}
+
+/// Returns the scalar \\(v\\) for a signed integer.
+///
+/// A negative \\(v\\) is mapped to \\(\ell - |v|\\), its negation
+/// modulo the group order \\(\ell\\), and not to its 64-bit two's
+/// complement \\(2^{64} - |v|\\), which `Scalar::from(v as u64)` would
+/// give.
+pub fn scalar_from_i64(v: i64) -> Scalar {
+	let abs = Scalar::from(v.unsigned_abs());
+	if v < 0 {
+		-abs
+	} else {
+		abs
+	}
+}
+
+/// Parses a scalar from its decimal representation, optionally preceded
+/// by a `-` sign.
+///
+/// Returns [`ProofError::FormatError`] if `decimal` is not a decimal
+/// number, or if its absolute value is not less than the group order: the
+/// value is not reduced, so that a number too large to be a scalar is not
+/// silently replaced by another.
+pub fn scalar_from_decimal_str(decimal: &str) -> Result<Scalar, ProofError> {
+	let (negative, digits) = match decimal.strip_prefix('-') {
+		Some(digits) => (true, digits),
+		None => (false, decimal),
+	};
+	if digits.is_empty() {
+		return Err(ProofError::FormatError);
+	}
+
+	// Accumulate the little-endian bytes of the number, failing once it
+	// no longer fits in 256 bits.
+	let mut bytes = [0u8; 32];
+	for c in digits.bytes() {
+		if !c.is_ascii_digit() {
+			return Err(ProofError::FormatError);
+		}
+		let mut carry = u16::from(c - b'0');
+		for byte in bytes.iter_mut() {
+			let x = u16::from(*byte) * 10 + carry;
+			*byte = x as u8;
+			carry = x >> 8;
+		}
+		if carry != 0 {
+			return Err(ProofError::FormatError);
+		}
+	}
+
+	let abs = Scalar::from_canonical_bytes(bytes).ok_or(ProofError::FormatError)?;
+	Ok(if negative { -abs } else { abs })
+}
+
+/// Parses a scalar from the hex encoding of its 32 canonical
+/// little-endian bytes, as returned by `Scalar::to_bytes`.
+///
+/// Returns [`ProofError::FormatError`] if `hex` is not 64 hex digits, or
+/// if they do not encode a scalar less than the group order.
+pub fn scalar_from_hex(hex: &str) -> Result<Scalar, ProofError> {
+	let bytes = util::decode_hex(hex).ok_or(ProofError::FormatError)?;
+	if bytes.len() != 32 {
+		return Err(ProofError::FormatError);
+	}
+	Scalar::from_canonical_bytes(util::read32(&bytes)).ok_or(ProofError::FormatError)
+}
+
+/// Reduces 64 bytes modulo the group order, such as the output of a hash
+/// function or a key derivation function, to a uniformly distributed
+/// scalar.
+pub fn scalar_from_bytes_mod_order_wide(bytes: &[u8; 64]) -> Scalar {
+	Scalar::from_bytes_mod_order_wide(bytes)
+}
+
+/// Parses a commitment from the hex encoding of its 32 compressed bytes.
+///
+/// Returns [`ProofError::FormatError`] if `hex` is not 64 hex digits, or
+/// if they do not encode a valid Ristretto point.
+pub fn commitment_from_hex(hex: &str) -> Result<CompressedRistretto, ProofError> {
+	let bytes = util::decode_hex(hex).ok_or(ProofError::FormatError)?;
+	if bytes.len() != 32 {
+		return Err(ProofError::FormatError);
+	}
+	let commitment = CompressedRistretto(util::read32(&bytes));
+	commitment.decompress().ok_or(ProofError::FormatError)?;
+	Ok(commitment)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::generators::PedersenGens;
+
+	#[test]
+	fn commitment_hex_roundtrip() {
+		let pc_gens = PedersenGens::default();
+		let commitment = pc_gens
+			.commit(scalar_from_u64(1037), scalar_from_bytes_mod_order_wide(&[7u8; 64]))
+			.compress();
+		let hex: String = commitment.as_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+
+		assert_eq!(commitment_from_hex(&hex), Ok(commitment));
+		assert_eq!(commitment_from_hex(&hex.to_uppercase()), Ok(commitment));
+		assert_eq!(commitment_from_hex(&hex[2..]), Err(ProofError::FormatError));
+		assert_eq!(commitment_from_hex(&"zz".repeat(32)), Err(ProofError::FormatError));
+		// Not a valid Ristretto encoding.
+		assert_eq!(commitment_from_hex(&"ff".repeat(32)), Err(ProofError::FormatError));
+	}
+
+	#[test]
+	fn signed_scalars() {
+		assert_eq!(scalar_from_i64(5), Scalar::from(5u64));
+		assert_eq!(scalar_from_i64(-5) + Scalar::from(5u64), Scalar::zero());
+		assert_ne!(scalar_from_i64(-1), Scalar::from(-1i64 as u64));
+		assert_eq!(scalar_from_i64(i64::MIN), -Scalar::from(1u64 << 63));
+		assert_eq!(scalar_from_i64(i64::MAX), Scalar::from(i64::MAX as u64));
+	}
+
+	#[test]
+	fn decimal_scalars() {
+		assert_eq!(scalar_from_decimal_str("0"), Ok(Scalar::zero()));
+		assert_eq!(scalar_from_decimal_str("1037"), Ok(Scalar::from(1037u64)));
+		assert_eq!(scalar_from_decimal_str("-1037"), Ok(scalar_from_i64(-1037)));
+		assert_eq!(
+			scalar_from_decimal_str("340282366920938463463374607431768211456"),
+			Ok(Scalar::from(1u64 << 32)
+				* Scalar::from(1u64 << 32)
+				* Scalar::from(1u64 << 32)
+				* Scalar::from(1u64 << 32))
+		);
+
+		// The group order minus one is the largest scalar.
+		let l_minus_one = "7237005577332262213973186563042994240857116359379907606001950938285454250988";
+		let l = "7237005577332262213973186563042994240857116359379907606001950938285454250989";
+		assert_eq!(scalar_from_decimal_str(l_minus_one), Ok(-Scalar::one()));
+		assert_eq!(scalar_from_decimal_str(l), Err(ProofError::FormatError));
+		assert_eq!(scalar_from_decimal_str(&"9".repeat(80)), Err(ProofError::FormatError));
+
+		for malformed in &["", "-", "+1", "12a", " 1", "1.0", "--1"] {
+			assert_eq!(
+				scalar_from_decimal_str(malformed),
+				Err(ProofError::FormatError),
+				"{:?}",
+				malformed
+			);
+		}
+	}
+
+	#[test]
+	fn hex_scalars() {
+		let x = scalar_from_bytes_mod_order_wide(&[7u8; 64]);
+		let hex: String = x.as_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+
+		assert_eq!(scalar_from_hex(&hex), Ok(x));
+		assert_eq!(scalar_from_hex(&hex.to_uppercase()), Ok(x));
+		assert_eq!(scalar_from_hex(&hex[2..]), Err(ProofError::FormatError));
+		// Not reduced modulo the group order.
+		assert_eq!(scalar_from_hex(&"ff".repeat(32)), Err(ProofError::FormatError));
+	}
+}
@@ -0,0 +1,252 @@
+//! The `elgamal` module contains range proofs over ElGamal ciphertexts,
+//! for confidential transfer schemes where an auditor or the recipient
+//! can decrypt the amounts.
+//!
+//! A user with secret key \\(s\\) has the public key
+//! \\(P = s \tilde{B}\\).  A value \\(v\\) is encrypted to \\(P\\) with a
+//! random \\(r\\) as the ciphertext
+//! \\[
+//! (C, D) = (v B + r P, r \tilde{B}).
+//! \\]
+//! The commitment \\(C\\) is a Pedersen commitment with the value under
+//! \\(B\\) and the blinding factor under the public key, obtained with
+//! [`PedersenGens::for_public_key`], so a range proof for it shows that the
+//! encrypted value lies in a range.  Since nobody knows the discrete
+//! logarithm of \\(P\\) with respect to \\(B\\), not even the owner of the
+//! key, the commitment is binding.  The owner of the key recovers
+//! \\(v B = C - s D\\).
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	range_proof::RangeProof,
+	transcript::TranscriptProtocol,
+};
+
+/// An ElGamal public key \\(P = s \tilde{B}\\), in compressed form.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ElGamalPublicKey(pub CompressedRistretto);
+
+impl ElGamalPublicKey {
+	/// Returns the public key of the `secret_key`, under the
+	/// `B_blinding` base of `pc_gens`.
+	pub fn from_secret_key(pc_gens: &PedersenGens, secret_key: &Scalar) -> Self {
+		ElGamalPublicKey((secret_key * pc_gens.B_blinding).compress())
+	}
+
+	/// Returns the generators for range proofs over ciphertexts encrypted
+	/// to this key.
+	fn pedersen_gens(&self, pc_gens: &PedersenGens) -> Result<PedersenGens, ProofError> {
+		let P = self.0.decompress().ok_or(ProofError::FormatError)?;
+		pc_gens.for_public_key(&P)
+	}
+}
+
+/// An ElGamal ciphertext \\((C, D) = (v B + r P, r \tilde{B})\\) of a
+/// value \\(v\\), in compressed form.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ElGamalCiphertext {
+	/// The commitment \\(C\\) to the value, blinded under the public key
+	pub commitment: CompressedRistretto,
+	/// The decryption handle \\(D\\)
+	pub handle: CompressedRistretto,
+}
+
+impl ElGamalCiphertext {
+	/// Encrypts `v` to `public_key` with the randomness `r`.
+	pub fn encrypt(
+		pc_gens: &PedersenGens,
+		public_key: &ElGamalPublicKey,
+		v: Scalar,
+		r: Scalar,
+	) -> Result<Self, ProofError> {
+		Ok(ElGamalCiphertext {
+			commitment: public_key.pedersen_gens(pc_gens)?.commit(v, r).compress(),
+			handle: (r * pc_gens.B_blinding).compress(),
+		})
+	}
+
+	/// Decrypts the ciphertext with `secret_key`, and returns
+	/// \\(v B = C - s D\\).
+	///
+	/// Recovering \\(v\\) from \\(v B\\) takes a discrete logarithm, which
+	/// is only feasible for small values.
+	pub fn decrypt_point(&self, secret_key: &Scalar) -> Result<RistrettoPoint, ProofError> {
+		let C = self.commitment.decompress().ok_or(ProofError::FormatError)?;
+		let D = self.handle.decompress().ok_or(ProofError::FormatError)?;
+		Ok(C - secret_key * D)
+	}
+}
+
+impl RangeProof {
+	/// Encrypts the value `v` to `public_key` with the randomness `r`, and
+	/// proves that it lies in \\([0, 2^n)\\).
+	///
+	/// The proof is a single-value range proof over the commitment of the
+	/// ciphertext, with the generators from
+	/// [`PedersenGens::for_public_key`].  The public key and the
+	/// decryption handle are bound to the transcript.
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove_single_elgamal_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		public_key: &ElGamalPublicKey,
+		v: u64,
+		r: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, ElGamalCiphertext), ProofError> {
+		let key_gens = public_key.pedersen_gens(pc_gens)?;
+		let handle = (r * pc_gens.B_blinding).compress();
+
+		transcript.elgamal_domain_sep();
+		transcript.append_point(b"P", &public_key.0);
+		transcript.append_point(b"D", &handle);
+
+		let (proof, commitment) = RangeProof::prove_single_with_rng(bp_gens, &key_gens, transcript, v, r, n, rng)?;
+
+		Ok((proof, ElGamalCiphertext { commitment, handle }))
+	}
+
+	/// Encrypts the value `v` to `public_key`, and proves that it lies in
+	/// \\([0, 2^n)\\).
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_elgamal_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove_single_elgamal(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		public_key: &ElGamalPublicKey,
+		v: u64,
+		r: &Scalar,
+		n: usize,
+	) -> Result<(RangeProof, ElGamalCiphertext), ProofError> {
+		RangeProof::prove_single_elgamal_with_rng(bp_gens, pc_gens, transcript, public_key, v, r, n, &mut thread_rng())
+	}
+
+	/// Verifies a proof created by
+	/// [`RangeProof::prove_single_elgamal_with_rng`] that the value
+	/// encrypted to `public_key` in `ciphertext` lies in \\([0, 2^n)\\).
+	#[allow(clippy::too_many_arguments)]
+	pub fn verify_single_elgamal_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		public_key: &ElGamalPublicKey,
+		ciphertext: &ElGamalCiphertext,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let key_gens = public_key.pedersen_gens(pc_gens)?;
+
+		transcript.elgamal_domain_sep();
+		transcript.append_point(b"P", &public_key.0);
+		transcript.append_point(b"D", &ciphertext.handle);
+
+		self.verify_single_with_rng(bp_gens, &key_gens, transcript, &ciphertext.commitment, n, rng)
+	}
+
+	/// Verifies that the value encrypted to `public_key` in `ciphertext`
+	/// lies in \\([0, 2^n)\\).
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_elgamal_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_elgamal(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		public_key: &ElGamalPublicKey,
+		ciphertext: &ElGamalCiphertext,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_elgamal_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			public_key,
+			ciphertext,
+			n,
+			&mut thread_rng(),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn prove_and_verify_ciphertext() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 1);
+		let secret_key = Scalar::random(&mut thread_rng());
+		let public_key = ElGamalPublicKey::from_secret_key(&pc_gens, &secret_key);
+		let r = Scalar::random(&mut thread_rng());
+
+		let (proof, ciphertext) = RangeProof::prove_single_elgamal(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"ElGamalTest"),
+			&public_key,
+			1037,
+			&r,
+			32,
+		)
+		.unwrap();
+		assert_eq!(
+			ciphertext,
+			ElGamalCiphertext::encrypt(&pc_gens, &public_key, Scalar::from(1037u64), r).unwrap()
+		);
+		assert_eq!(
+			ciphertext.decrypt_point(&secret_key).unwrap(),
+			Scalar::from(1037u64) * pc_gens.B
+		);
+
+		let verify = |public_key: &ElGamalPublicKey, ciphertext: &ElGamalCiphertext| {
+			proof.verify_single_elgamal(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"ElGamalTest"),
+				public_key,
+				ciphertext,
+				32,
+			)
+		};
+		assert!(verify(&public_key, &ciphertext).is_ok());
+
+		// The proof is bound to the key and to the decryption handle.
+		let other_key = ElGamalPublicKey::from_secret_key(&pc_gens, &Scalar::from(7u64));
+		assert!(verify(&other_key, &ciphertext).is_err());
+		let other_handle = ElGamalCiphertext {
+			handle: pc_gens.B_blinding.compress(),
+			..ciphertext
+		};
+		assert!(verify(&public_key, &other_handle).is_err());
+
+		// The identity is not a valid key.
+		assert_eq!(
+			ElGamalCiphertext::encrypt(&pc_gens, &ElGamalPublicKey::default(), Scalar::one(), r),
+			Err(ProofError::InvalidGenerators)
+		);
+	}
+}
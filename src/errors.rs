@@ -1,49 +1,170 @@
 //! Errors related to proving and verifying proofs.
+//!
+//! The error types implement [`core::fmt::Display`] in all contexts, and
+//! `std::error::Error` when the `std` feature is enabled.
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "yoloproofs", not(feature = "std")))]
 use alloc::string::String;
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::vec::Vec;
+use core::fmt;
 
-#[cfg(feature = "std")]
-use thiserror::Error;
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, EncodeLike, Input, Output};
 
 /// Represents an error in proof creation, verification, or parsing.
 #[derive(Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "std", derive(Error))]
 pub enum ProofError {
 	/// This error occurs when a proof failed to verify.
-	#[cfg_attr(feature = "std", error("Proof verification failed."))]
 	VerificationError,
 	/// This error occurs when the proof encoding is malformed.
-	#[cfg_attr(feature = "std", error("Proof data could not be parsed."))]
 	FormatError,
 	/// This error occurs during proving if the number of blinding
 	/// factors does not match the number of values.
-	#[cfg_attr(feature = "std", error("Wrong number of blinding factors supplied."))]
-	WrongNumBlindingFactors,
+	WrongNumBlindingFactors {
+		/// The number of values, and so of blinding factors needed.
+		expected: usize,
+		/// The number of blinding factors supplied.
+		actual: usize,
+	},
 	/// This error occurs when attempting to create a proof with
-	/// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
-	#[cfg_attr(feature = "std", error("Invalid bitsize, must have n = 8,16,32,64."))]
+	/// bitsize outside of \\(1 \le n \le 64\\).
 	InvalidBitsize,
 	/// This error occurs when attempting to create an aggregated
 	/// proof with non-power-of-two aggregation size.
-	#[cfg_attr(feature = "std", error("Invalid aggregation size, m must be a power of 2."))]
 	InvalidAggregation,
 	/// This error occurs when there are insufficient generators for the proof.
-	#[cfg_attr(feature = "std", error("Invalid generators size, too few generators for proof"))]
 	InvalidGeneratorsLength,
+	/// This error occurs during batch verification if the number of
+	/// proofs, transcripts, commitment sets and bitsizes do not match.
+	WrongNumBatchInputs,
 	/// This error results from an internal error during proving.
 	///
 	/// The single-party prover is implemented by performing
 	/// multiparty computation with ourselves.  However, because the
 	/// MPC protocol is not exposed by the single-party API, we
 	/// consider its errors to be internal errors.
-	#[cfg_attr(feature = "std", error("Internal error during proof creation: {0}"))]
+	#[cfg(feature = "alloc")]
 	ProvingError(MPCError),
+	/// This error occurs when attempting to prove that a value lies in
+	/// an interval `[min, max]` with `min > max`, or which does not
+	/// contain the value, such as a signed value outside of
+	/// \\([-2^{n-1}, 2^{n-1})\\).
+	InvalidRange,
+	/// This error occurs when the bases of
+	/// [`PedersenGens`](crate::PedersenGens) are the identity or are not
+	/// independent of each other.
+	InvalidGenerators,
+	/// This error occurs when attempting to prove a
+	/// [`ConfidentialTx`](crate::ConfidentialTx) whose inputs do not add
+	/// up to its outputs and fee.
+	UnbalancedTransaction,
+}
+
+impl ProofError {
+	/// Returns the stable numeric code of the error variant.
+	///
+	/// The codes never change between releases, so that a pallet can map
+	/// proof failures to the variants of its on-chain error enum
+	/// deterministically.  They are the variant indexes of the SCALE
+	/// encoding of the error.
+	pub fn code(&self) -> u8 {
+		match self {
+			ProofError::VerificationError => 0,
+			ProofError::FormatError => 1,
+			ProofError::WrongNumBlindingFactors { .. } => 2,
+			ProofError::InvalidBitsize => 3,
+			ProofError::InvalidAggregation => 4,
+			ProofError::InvalidGeneratorsLength => 5,
+			ProofError::WrongNumBatchInputs => 6,
+			#[cfg(feature = "alloc")]
+			ProofError::ProvingError(_) => 7,
+			ProofError::InvalidRange => 8,
+			ProofError::InvalidGenerators => 9,
+			ProofError::UnbalancedTransaction => 10,
+		}
+	}
 }
 
+impl fmt::Display for ProofError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ProofError::VerificationError => write!(f, "Proof verification failed."),
+			ProofError::FormatError => write!(f, "Proof data could not be parsed."),
+			ProofError::WrongNumBlindingFactors { expected, actual } => write!(
+				f,
+				"Wrong number of blinding factors supplied: expected {}, got {}.",
+				expected, actual
+			),
+			ProofError::InvalidBitsize => write!(f, "Invalid bitsize, must have 1 <= n <= 64."),
+			ProofError::InvalidAggregation => write!(f, "Invalid aggregation size, m must be a power of 2."),
+			ProofError::InvalidGeneratorsLength => write!(f, "Invalid generators size, too few generators for proof"),
+			ProofError::WrongNumBatchInputs => write!(f, "Wrong number of inputs supplied for batch verification."),
+			#[cfg(feature = "alloc")]
+			ProofError::ProvingError(e) => write!(f, "Internal error during proof creation: {}", e),
+			ProofError::InvalidRange => write!(f, "Invalid interval, must have min <= value <= max."),
+			ProofError::InvalidGenerators => {
+				write!(f, "Invalid generators, bases must be distinct and not the identity.")
+			}
+			ProofError::UnbalancedTransaction => {
+				write!(f, "Unbalanced transaction, inputs must equal outputs plus fee.")
+			}
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProofError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ProofError::ProvingError(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+// SCALE has no encoding for `usize`, so lengths are encoded as `u64`.
+#[cfg(feature = "scale")]
+impl Encode for ProofError {
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		dest.push_byte(self.code());
+		match self {
+			ProofError::WrongNumBlindingFactors { expected, actual } => encode_lengths(*expected, *actual, dest),
+			ProofError::ProvingError(e) => e.encode_to(dest),
+			_ => {}
+		}
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for ProofError {}
+
+#[cfg(feature = "scale")]
+impl Decode for ProofError {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		match input.read_byte()? {
+			0 => Ok(ProofError::VerificationError),
+			1 => Ok(ProofError::FormatError),
+			2 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(ProofError::WrongNumBlindingFactors { expected, actual })
+			}
+			3 => Ok(ProofError::InvalidBitsize),
+			4 => Ok(ProofError::InvalidAggregation),
+			5 => Ok(ProofError::InvalidGeneratorsLength),
+			6 => Ok(ProofError::WrongNumBatchInputs),
+			7 => Ok(ProofError::ProvingError(MPCError::decode(input)?)),
+			8 => Ok(ProofError::InvalidRange),
+			9 => Ok(ProofError::InvalidGenerators),
+			10 => Ok(ProofError::UnbalancedTransaction),
+			_ => Err("Invalid ProofError variant".into()),
+		}
+	}
+}
+
+#[cfg(feature = "alloc")]
 impl From<MPCError> for ProofError {
 	fn from(e: MPCError) -> ProofError {
 		match e {
@@ -62,44 +183,260 @@ impl From<MPCError> for ProofError {
 /// API: although the MPC protocol is used internally for single-party
 /// proving, its API should not expose the complexity of the MPC
 /// protocol.
+#[cfg(feature = "alloc")]
 #[derive(Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "std", derive(Error))]
 pub enum MPCError {
 	/// This error occurs when the dealer gives a zero challenge,
 	/// which would annihilate the blinding factors.
-	#[cfg_attr(feature = "std", error("Dealer gave a malicious challenge value."))]
 	MaliciousDealer,
 	/// This error occurs when attempting to create a proof with
-	/// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
-	#[cfg_attr(feature = "std", error("Invalid bitsize, must have n = 8,16,32,64"))]
+	/// bitsize outside of \\(1 \le n \le 64\\).
 	InvalidBitsize,
 	/// This error occurs when attempting to create an aggregated
 	/// proof with non-power-of-two aggregation size.
-	#[cfg_attr(feature = "std", error("Invalid aggregation size, m must be a power of 2"))]
 	InvalidAggregation,
 	/// This error occurs when there are insufficient generators for the proof.
-	#[cfg_attr(feature = "std", error("Invalid generators size, too few generators for proof"))]
 	InvalidGeneratorsLength,
 	/// This error occurs when the dealer is given the wrong number of
 	/// value commitments.
-	#[cfg_attr(feature = "std", error("Wrong number of value commitments"))]
-	WrongNumBitCommitments,
+	WrongNumBitCommitments {
+		/// The number of parties the dealer was created for.
+		expected: usize,
+		/// The number of commitments the dealer was given.
+		actual: usize,
+	},
 	/// This error occurs when the dealer is given the wrong number of
 	/// polynomial commitments.
-	#[cfg_attr(feature = "std", error("Wrong number of value commitments"))]
-	WrongNumPolyCommitments,
+	WrongNumPolyCommitments {
+		/// The number of parties the dealer was created for.
+		expected: usize,
+		/// The number of commitments the dealer was given.
+		actual: usize,
+	},
 	/// This error occurs when the dealer is given the wrong number of
 	/// proof shares.
-	#[cfg_attr(feature = "std", error("Wrong number of proof shares"))]
-	WrongNumProofShares,
+	WrongNumProofShares {
+		/// The number of parties the dealer was created for.
+		expected: usize,
+		/// The number of proof shares the dealer was given.
+		actual: usize,
+	},
 	/// This error occurs when one or more parties submit malformed
 	/// proof shares.
-	#[cfg_attr(feature = "std", error("Malformed proof shares from parties {bad_shares:?}"))]
 	MalformedProofShares {
 		/// A vector with the indexes of the parties whose shares were
-		/// malformed.
-		bad_shares: Vec<usize>,
+		/// malformed, in increasing order, each with the first check
+		/// its share failed.
+		bad_shares: Vec<(usize, ShareError)>,
 	},
+	/// This error occurs when a protocol message has an invalid
+	/// encoding.
+	FormatError,
+	/// This error occurs when a [`session`](::range_proof_mpc::session)
+	/// receives a message from an unknown party, or after the protocol
+	/// has finished.
+	UnexpectedMessage,
+	/// This error occurs when the layout of an R1CS aggregation does not
+	/// cover exactly the committed variables and multipliers of the
+	/// circuit, when the circuit has vector-committed variables or public
+	/// inputs, or when
+	/// a party's witness or commitments do not match its part of the
+	/// layout.
+	InvalidLayout,
+	/// This error occurs when the aggregated R1CS proof does not verify
+	/// although every proof share passes the dealer's audit, so the
+	/// failure cannot be attributed to a party: either the parties'
+	/// witnesses do not satisfy the circuit, or a party sent polynomial
+	/// commitments which do not match its share.
+	VerificationError,
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for MPCError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			MPCError::MaliciousDealer => write!(f, "Dealer gave a malicious challenge value."),
+			MPCError::InvalidBitsize => write!(f, "Invalid bitsize, must have 1 <= n <= 64"),
+			MPCError::InvalidAggregation => write!(f, "Invalid aggregation size, m must be a power of 2"),
+			MPCError::InvalidGeneratorsLength => write!(f, "Invalid generators size, too few generators for proof"),
+			MPCError::WrongNumBitCommitments { expected, actual } => write!(
+				f,
+				"Wrong number of value commitments: expected {}, got {}",
+				expected, actual
+			),
+			MPCError::WrongNumPolyCommitments { expected, actual } => write!(
+				f,
+				"Wrong number of polynomial commitments: expected {}, got {}",
+				expected, actual
+			),
+			MPCError::WrongNumProofShares { expected, actual } => {
+				write!(f, "Wrong number of proof shares: expected {}, got {}", expected, actual)
+			}
+			MPCError::MalformedProofShares { bad_shares } => {
+				write!(f, "Malformed proof shares:")?;
+				for (j, e) in bad_shares {
+					write!(f, " party {}: {};", j, e)?;
+				}
+				Ok(())
+			}
+			MPCError::FormatError => write!(f, "Invalid protocol message encoding"),
+			MPCError::UnexpectedMessage => write!(f, "Unexpected protocol message"),
+			MPCError::InvalidLayout => write!(f, "Invalid layout of the circuit among the parties"),
+			MPCError::VerificationError => write!(f, "Aggregated proof verification failed"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MPCError {}
+
+// SCALE has no encoding for `usize`, so lengths and the party indexes
+// in `MalformedProofShares` are encoded as `u64`.
+#[cfg(feature = "scale")]
+impl Encode for MPCError {
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		match self {
+			MPCError::MaliciousDealer => dest.push_byte(0),
+			MPCError::InvalidBitsize => dest.push_byte(1),
+			MPCError::InvalidAggregation => dest.push_byte(2),
+			MPCError::InvalidGeneratorsLength => dest.push_byte(3),
+			MPCError::WrongNumBitCommitments { expected, actual } => {
+				dest.push_byte(4);
+				encode_lengths(*expected, *actual, dest);
+			}
+			MPCError::WrongNumPolyCommitments { expected, actual } => {
+				dest.push_byte(5);
+				encode_lengths(*expected, *actual, dest);
+			}
+			MPCError::WrongNumProofShares { expected, actual } => {
+				dest.push_byte(6);
+				encode_lengths(*expected, *actual, dest);
+			}
+			MPCError::MalformedProofShares { bad_shares } => {
+				dest.push_byte(7);
+				let bad_shares: Vec<(u64, ShareError)> = bad_shares.iter().map(|&(j, e)| (j as u64, e)).collect();
+				bad_shares.encode_to(dest);
+			}
+			MPCError::FormatError => dest.push_byte(8),
+			MPCError::UnexpectedMessage => dest.push_byte(9),
+			MPCError::InvalidLayout => dest.push_byte(10),
+			MPCError::VerificationError => dest.push_byte(11),
+		}
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for MPCError {}
+
+#[cfg(feature = "scale")]
+impl Decode for MPCError {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		match input.read_byte()? {
+			0 => Ok(MPCError::MaliciousDealer),
+			1 => Ok(MPCError::InvalidBitsize),
+			2 => Ok(MPCError::InvalidAggregation),
+			3 => Ok(MPCError::InvalidGeneratorsLength),
+			4 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(MPCError::WrongNumBitCommitments { expected, actual })
+			}
+			5 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(MPCError::WrongNumPolyCommitments { expected, actual })
+			}
+			6 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(MPCError::WrongNumProofShares { expected, actual })
+			}
+			7 => Ok(MPCError::MalformedProofShares {
+				bad_shares: Vec::<(u64, ShareError)>::decode(input)?
+					.into_iter()
+					.map(|(j, e)| (j as usize, e))
+					.collect(),
+			}),
+			8 => Ok(MPCError::FormatError),
+			9 => Ok(MPCError::UnexpectedMessage),
+			10 => Ok(MPCError::InvalidLayout),
+			11 => Ok(MPCError::VerificationError),
+			_ => Err("Invalid MPCError variant".into()),
+		}
+	}
+}
+
+/// The check of a party's proof share that failed when the dealer
+/// audited it, reported in [`MPCError::MalformedProofShares`].
+#[cfg(feature = "alloc")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ShareError {
+	/// The share's vectors do not have the length of the padded bitsize
+	/// (or of the party's multipliers, in an R1CS aggregation), or the
+	/// party's position is out of range of the generators.
+	WrongSize,
+	/// The party's value commitment is not a valid point.
+	InvalidValueCommitment,
+	/// The share's \(t_x\) is not the inner product of its vectors
+	/// \(\mathbf{l}\) and \(\mathbf{r}\).
+	InconsistentInnerProduct,
+	/// The share's vectors and \(\tilde{e}\) do not open the party's
+	/// bit commitments \(A_j, S_j\) at the challenges.
+	InconsistentBitCommitment,
+	/// The share's \(t_x\) and \(\tilde{t}_x\) do not match the party's
+	/// value commitment and polynomial commitments \(T_{1,j}, T_{2,j}\)
+	/// at the challenges.
+	InconsistentPolyCommitment,
+	/// The share's vectors and \(\tilde{e}\) do not open the party's
+	/// commitments \(A_{I,j}, A_{O,j}, S_j\) to its multipliers in an
+	/// R1CS aggregation at the challenges.
+	InconsistentGateCommitment,
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for ShareError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ShareError::WrongSize => write!(f, "share has the wrong size"),
+			ShareError::InvalidValueCommitment => write!(f, "value commitment is not a valid point"),
+			ShareError::InconsistentInnerProduct => write!(f, "t_x is not the inner product of l and r"),
+			ShareError::InconsistentBitCommitment => write!(f, "l and r do not match the bit commitment"),
+			ShareError::InconsistentPolyCommitment => write!(f, "t_x does not match the polynomial commitment"),
+			ShareError::InconsistentGateCommitment => write!(f, "l and r do not match the gate commitment"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ShareError {}
+
+#[cfg(feature = "scale")]
+impl Encode for ShareError {
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		dest.push_byte(match self {
+			ShareError::WrongSize => 0,
+			ShareError::InvalidValueCommitment => 1,
+			ShareError::InconsistentInnerProduct => 2,
+			ShareError::InconsistentBitCommitment => 3,
+			ShareError::InconsistentPolyCommitment => 4,
+			ShareError::InconsistentGateCommitment => 5,
+		})
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for ShareError {}
+
+#[cfg(feature = "scale")]
+impl Decode for ShareError {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		match input.read_byte()? {
+			0 => Ok(ShareError::WrongSize),
+			1 => Ok(ShareError::InvalidValueCommitment),
+			2 => Ok(ShareError::InconsistentInnerProduct),
+			3 => Ok(ShareError::InconsistentBitCommitment),
+			4 => Ok(ShareError::InconsistentPolyCommitment),
+			5 => Ok(ShareError::InconsistentGateCommitment),
+			_ => Err("Invalid ShareError variant".into()),
+		}
+	}
 }
 
 /// Represents an error during the proving or verifying of a constraint system.
@@ -107,30 +444,201 @@ pub enum MPCError {
 /// XXX: should this be separate from a `ProofError`?
 #[cfg(feature = "yoloproofs")]
 #[derive(Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "std", derive(Error))]
 pub enum R1CSError {
 	/// Occurs when there are insufficient generators for the proof.
-	#[cfg_attr(feature = "std", error("Invalid generators size, too few generators for proof"))]
 	InvalidGeneratorsLength,
 	/// This error occurs when the proof encoding is malformed.
-	#[cfg_attr(feature = "std", error("Proof data could not be parsed."))]
 	FormatError,
 	/// Occurs when verification of an
 	/// [`R1CSProof`](::r1cs::R1CSProof) fails.
-	#[cfg_attr(feature = "std", error("R1CSProof did not verify correctly."))]
 	VerificationError,
 
 	/// Occurs when trying to use a missing variable assignment.
 	/// Used by gadgets that build the constraint system to signal that
 	/// a variable assignment is not provided when the prover needs it.
-	#[cfg_attr(feature = "std", error("Variable does not have a value assignment."))]
-	MissingAssignment,
+	MissingAssignment {
+		/// The index of the multiplication gate whose input was missing.
+		gate: usize,
+	},
 	// Occurs when a gadget receives an inconsistent input.
-	#[cfg_attr(feature = "std", error("Gadget error: {description:?}"))]
 	GadgetError {
 		/// The description of the reasons for the error.
 		description: String,
 	},
+	/// Occurs when the number of commitments does not match the number of
+	/// committed variables of a [`Circuit`](::r1cs::Circuit), or when a
+	/// constraint refers to a commitment the verifier was not given.
+	WrongNumCommitments {
+		/// The number of commitments the circuit or constraints refer to.
+		expected: usize,
+		/// The number of commitments supplied.
+		actual: usize,
+	},
+	/// Occurs when the verifier's constraint system has a different
+	/// number of multipliers than the one the proof was created for, or
+	/// when a constraint refers to a multiplier that was not allocated.
+	///
+	/// The counts are padded to the size of the inner-product proof, so
+	/// they agree when only the randomized phase is missing on one side.
+	WrongNumMultipliers {
+		/// The number of multipliers the constraints refer to.
+		expected: usize,
+		/// The number of multipliers in the proof or constraint system.
+		actual: usize,
+	},
+	/// Occurs when exporting a [`Circuit`](::r1cs::Circuit) from a
+	/// constraint system with randomized constraints, or when proving or
+	/// verifying it in a combined proof.
+	RandomizedConstraints,
+	/// Occurs when proving or verifying a constraint system with vector
+	/// commitments in a combined proof, since the generators of its vector
+	/// commitments cannot be shared with the other constraint systems.
+	BatchedVectorCommitments,
+	/// Occurs when the number of public inputs does not match the number
+	/// of public inputs of a [`Circuit`](::r1cs::Circuit), or when a
+	/// constraint refers to a public input the verifier was not given.
+	WrongNumPublicInputs {
+		/// The number of public inputs the circuit or constraints refer to.
+		expected: usize,
+		/// The number of public inputs supplied.
+		actual: usize,
+	},
+	/// Occurs when a [`Witness`](::r1cs::Witness) imported into a prover
+	/// does not satisfy a constraint of the circuit.
+	UnsatisfiedConstraint {
+		/// The index of the first unsatisfied constraint.
+		index: usize,
+	},
+	/// Occurs when proving a constraint system with commitments whose
+	/// blinding factors are held by a delegating client, other than with
+	/// [`Prover::prove_delegated_with_rng`](::r1cs::Prover::prove_delegated_with_rng).
+	MissingBlindingFactors,
+}
+
+#[cfg(feature = "yoloproofs")]
+impl R1CSError {
+	/// Returns the stable numeric code of the error variant.
+	///
+	/// As for [`ProofError::code`], the codes never change between
+	/// releases, and are the variant indexes of the SCALE encoding of the
+	/// error.
+	pub fn code(&self) -> u8 {
+		match self {
+			R1CSError::InvalidGeneratorsLength => 0,
+			R1CSError::FormatError => 1,
+			R1CSError::VerificationError => 2,
+			R1CSError::MissingAssignment { .. } => 3,
+			R1CSError::GadgetError { .. } => 4,
+			R1CSError::WrongNumCommitments { .. } => 5,
+			R1CSError::WrongNumMultipliers { .. } => 6,
+			R1CSError::RandomizedConstraints => 7,
+			R1CSError::BatchedVectorCommitments => 8,
+			R1CSError::WrongNumPublicInputs { .. } => 9,
+			R1CSError::UnsatisfiedConstraint { .. } => 10,
+			R1CSError::MissingBlindingFactors => 11,
+		}
+	}
+}
+
+#[cfg(feature = "yoloproofs")]
+impl fmt::Display for R1CSError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			R1CSError::InvalidGeneratorsLength => write!(f, "Invalid generators size, too few generators for proof"),
+			R1CSError::FormatError => write!(f, "Proof data could not be parsed."),
+			R1CSError::VerificationError => write!(f, "R1CSProof did not verify correctly."),
+			R1CSError::MissingAssignment { gate } => {
+				write!(f, "Variable of multiplier {} does not have a value assignment.", gate)
+			}
+			R1CSError::GadgetError { description } => write!(f, "Gadget error: {:?}", description),
+			R1CSError::WrongNumCommitments { expected, actual } => write!(
+				f,
+				"Wrong number of commitments for the circuit: expected {}, got {}.",
+				expected, actual
+			),
+			R1CSError::WrongNumMultipliers { expected, actual } => write!(
+				f,
+				"Wrong number of multipliers for the proof: expected {}, got {}.",
+				expected, actual
+			),
+			R1CSError::RandomizedConstraints => write!(f, "Randomized constraints cannot be part of a circuit."),
+			R1CSError::BatchedVectorCommitments => {
+				write!(
+					f,
+					"Vector commitments cannot be part of a combined proof of constraint systems."
+				)
+			}
+			R1CSError::WrongNumPublicInputs { expected, actual } => write!(
+				f,
+				"Wrong number of public inputs for the circuit: expected {}, got {}.",
+				expected, actual
+			),
+			R1CSError::UnsatisfiedConstraint { index } => {
+				write!(f, "Constraint {} is not satisfied by the witness.", index)
+			}
+			R1CSError::MissingBlindingFactors => {
+				write!(f, "The blinding factors of delegated commitments are missing.")
+			}
+		}
+	}
+}
+
+#[cfg(all(feature = "yoloproofs", feature = "std"))]
+impl std::error::Error for R1CSError {}
+
+#[cfg(all(feature = "yoloproofs", feature = "scale"))]
+impl Encode for R1CSError {
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		dest.push_byte(self.code());
+		match self {
+			R1CSError::MissingAssignment { gate } => (*gate as u64).encode_to(dest),
+			R1CSError::GadgetError { description } => description.encode_to(dest),
+			R1CSError::WrongNumCommitments { expected, actual }
+			| R1CSError::WrongNumMultipliers { expected, actual }
+			| R1CSError::WrongNumPublicInputs { expected, actual } => encode_lengths(*expected, *actual, dest),
+			R1CSError::UnsatisfiedConstraint { index } => (*index as u64).encode_to(dest),
+			_ => {}
+		}
+	}
+}
+
+#[cfg(all(feature = "yoloproofs", feature = "scale"))]
+impl EncodeLike for R1CSError {}
+
+#[cfg(all(feature = "yoloproofs", feature = "scale"))]
+impl Decode for R1CSError {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		match input.read_byte()? {
+			0 => Ok(R1CSError::InvalidGeneratorsLength),
+			1 => Ok(R1CSError::FormatError),
+			2 => Ok(R1CSError::VerificationError),
+			3 => Ok(R1CSError::MissingAssignment {
+				gate: u64::decode(input)? as usize,
+			}),
+			4 => Ok(R1CSError::GadgetError {
+				description: String::decode(input)?,
+			}),
+			5 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(R1CSError::WrongNumCommitments { expected, actual })
+			}
+			6 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(R1CSError::WrongNumMultipliers { expected, actual })
+			}
+			7 => Ok(R1CSError::RandomizedConstraints),
+			8 => Ok(R1CSError::BatchedVectorCommitments),
+			9 => {
+				let (expected, actual) = decode_lengths(input)?;
+				Ok(R1CSError::WrongNumPublicInputs { expected, actual })
+			}
+			10 => Ok(R1CSError::UnsatisfiedConstraint {
+				index: u64::decode(input)? as usize,
+			}),
+			11 => Ok(R1CSError::MissingBlindingFactors),
+			_ => Err("Invalid R1CSError variant".into()),
+		}
+	}
 }
 
 #[cfg(feature = "yoloproofs")]
@@ -144,3 +652,95 @@ impl From<ProofError> for R1CSError {
 		}
 	}
 }
+
+/// Converts the error to its [`code`](ProofError::code), the `u8` a
+/// pallet reports in a `DispatchError::Module`.
+#[cfg(feature = "substrate")]
+impl From<ProofError> for u8 {
+	fn from(e: ProofError) -> u8 {
+		e.code()
+	}
+}
+
+/// Converts the error to its [`code`](R1CSError::code), the `u8` a
+/// pallet reports in a `DispatchError::Module`.
+#[cfg(all(feature = "yoloproofs", feature = "substrate"))]
+impl From<R1CSError> for u8 {
+	fn from(e: R1CSError) -> u8 {
+		e.code()
+	}
+}
+
+/// Encodes an expected and an actual length as a pair of `u64`s.
+#[cfg(feature = "scale")]
+fn encode_lengths<T: Output + ?Sized>(expected: usize, actual: usize, dest: &mut T) {
+	(expected as u64, actual as u64).encode_to(dest);
+}
+
+/// Decodes a pair of lengths written by [`encode_lengths`].
+#[cfg(feature = "scale")]
+fn decode_lengths<I: Input>(input: &mut I) -> Result<(usize, usize), codec::Error> {
+	let (expected, actual) = <(u64, u64)>::decode(input)?;
+	Ok((expected as usize, actual as usize))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn proof_error_codes() {
+		let errors = [
+			ProofError::VerificationError,
+			ProofError::FormatError,
+			ProofError::WrongNumBlindingFactors { expected: 2, actual: 1 },
+			ProofError::InvalidBitsize,
+			ProofError::InvalidAggregation,
+			ProofError::InvalidGeneratorsLength,
+			ProofError::WrongNumBatchInputs,
+			ProofError::ProvingError(MPCError::MaliciousDealer),
+			ProofError::InvalidRange,
+			ProofError::InvalidGenerators,
+			ProofError::UnbalancedTransaction,
+		];
+		for (i, e) in errors.iter().enumerate() {
+			assert_eq!(e.code() as usize, i);
+			#[cfg(feature = "scale")]
+			assert_eq!(e.encode()[0], e.code());
+			#[cfg(feature = "substrate")]
+			assert_eq!(u8::from(e.clone()), e.code());
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "yoloproofs")]
+	fn r1cs_error_codes() {
+		let errors = [
+			R1CSError::InvalidGeneratorsLength,
+			R1CSError::FormatError,
+			R1CSError::VerificationError,
+			R1CSError::MissingAssignment { gate: 3 },
+			R1CSError::GadgetError {
+				description: "gadget".into(),
+			},
+			R1CSError::WrongNumCommitments { expected: 2, actual: 1 },
+			R1CSError::WrongNumMultipliers { expected: 2, actual: 1 },
+			R1CSError::RandomizedConstraints,
+			R1CSError::BatchedVectorCommitments,
+			R1CSError::WrongNumPublicInputs { expected: 2, actual: 1 },
+			R1CSError::UnsatisfiedConstraint { index: 5 },
+			R1CSError::MissingBlindingFactors,
+		];
+		for (i, e) in errors.iter().enumerate() {
+			assert_eq!(e.code() as usize, i);
+			#[cfg(feature = "scale")]
+			{
+				let encoded = e.encode();
+				assert_eq!(encoded[0], e.code());
+				assert_eq!(R1CSError::decode(&mut &encoded[..]).as_ref(), Ok(e));
+			}
+			#[cfg(feature = "substrate")]
+			assert_eq!(u8::from(e.clone()), e.code());
+		}
+	}
+}
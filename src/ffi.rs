@@ -0,0 +1,338 @@
+//! A C interface for verifying proofs.
+//!
+//! The functions of this module take their inputs as byte buffers and
+//! return a [`BulletproofsError`], whose values are stable across
+//! releases:
+//!
+//! ```c
+//! int32_t bulletproofs_verify_range_proof(
+//!     const uint8_t *proof, size_t proof_len,
+//!     const uint8_t *commitments, size_t num_commitments,
+//!     size_t bitsize,
+//!     const uint8_t *context, size_t context_len);
+//! ```
+//!
+//! * proofs use the encodings of [`RangeProof::to_bytes`] and
+//!   [`R1CSProof::to_bytes`](crate::r1cs::R1CSProof::to_bytes);
+//! * circuits use the encoding of
+//!   [`Circuit::to_bytes`](crate::r1cs::Circuit::to_bytes);
+//! * commitments are arrays of 32-byte compressed Ristretto points, and public
+//!   inputs arrays of 32-byte canonical scalars, passed with the number of
+//!   elements.
+//!
+//! The verifiers start from a transcript created with
+//! [`TRANSCRIPT_LABEL`], to which the application-defined `context` is
+//! appended with [`TranscriptProtocol::context_domain_sep`], so proofs
+//! must be created the same way.  They use the default [`PedersenGens`].
+//!
+//! To link the verifiers into a C, C++ or Go program, build the crate as a
+//! static library:
+//!
+//! ```text
+//! cargo rustc --release --features cffi -- --crate-type staticlib
+//! ```
+
+use std::{panic, slice, vec::Vec};
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+
+use crate::{errors::ProofError, transcript::TranscriptProtocol, BulletproofGens, PedersenGens, RangeProof};
+#[cfg(feature = "yoloproofs")]
+use crate::{
+	errors::R1CSError,
+	r1cs::{Circuit, R1CSProof, Verifier},
+};
+
+/// The label of the transcripts the proofs are verified against.
+pub const TRANSCRIPT_LABEL: &[u8] = b"BulletproofsFFI";
+
+/// The result of a verification through the C interface.
+///
+/// The values of the variants are stable, and new variants only get new
+/// values.
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BulletproofsError {
+	/// The proof is valid.
+	Ok = 0,
+	/// The proof failed to verify.
+	VerificationFailed = 1,
+	/// The encoding of the proof is malformed.
+	MalformedProof = 2,
+	/// A public input is not a canonical scalar.
+	MalformedInput = 3,
+	/// The encoding of the circuit is malformed.
+	MalformedCircuit = 4,
+	/// The bitsize is not between 1 and 64.
+	InvalidBitsize = 5,
+	/// The number of commitments does not match the proof or the circuit.
+	WrongNumCommitments = 6,
+	/// The number of public inputs does not match the circuit.
+	WrongNumPublicInputs = 7,
+	/// A pointer to a non-empty buffer is null.
+	NullPointer = 8,
+	/// The inputs are invalid in another way.
+	InvalidArgument = 9,
+	/// The verifier panicked.
+	Panic = 10,
+}
+
+impl From<ProofError> for BulletproofsError {
+	fn from(e: ProofError) -> BulletproofsError {
+		match e {
+			ProofError::VerificationError => BulletproofsError::VerificationFailed,
+			ProofError::FormatError => BulletproofsError::MalformedProof,
+			ProofError::InvalidBitsize => BulletproofsError::InvalidBitsize,
+			ProofError::InvalidAggregation => BulletproofsError::WrongNumCommitments,
+			_ => BulletproofsError::InvalidArgument,
+		}
+	}
+}
+
+#[cfg(feature = "yoloproofs")]
+impl From<R1CSError> for BulletproofsError {
+	fn from(e: R1CSError) -> BulletproofsError {
+		match e {
+			R1CSError::VerificationError => BulletproofsError::VerificationFailed,
+			R1CSError::FormatError => BulletproofsError::MalformedProof,
+			R1CSError::WrongNumCommitments { .. } => BulletproofsError::WrongNumCommitments,
+			R1CSError::WrongNumPublicInputs { .. } => BulletproofsError::WrongNumPublicInputs,
+			_ => BulletproofsError::InvalidArgument,
+		}
+	}
+}
+
+/// Verifies an aggregated range proof that the values committed to by
+/// the `num_commitments` points at `commitments` are `bitsize`-bit
+/// numbers, bound to the `context_len` bytes at `context`.
+///
+/// # Safety
+///
+/// Each pointer must be valid for reads of its length in bytes, with
+/// `32 * num_commitments` bytes for `commitments`.  Pointers to empty
+/// buffers may be null.
+#[no_mangle]
+pub unsafe extern "C" fn bulletproofs_verify_range_proof(
+	proof: *const u8,
+	proof_len: usize,
+	commitments: *const u8,
+	num_commitments: usize,
+	bitsize: usize,
+	context: *const u8,
+	context_len: usize,
+) -> BulletproofsError {
+	catch(|| {
+		let proof = RangeProof::from_bytes(bytes(proof, proof_len)?)?;
+		let commitments = points(bytes(commitments, byte_len(num_commitments)?)?);
+		let context = bytes(context, context_len)?;
+
+		let bp_gens = BulletproofGens::new(64, commitments.len().next_power_of_two());
+		let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+		transcript.context_domain_sep(context);
+		proof.verify_multiple(
+			&bp_gens,
+			&PedersenGens::default(),
+			&mut transcript,
+			&commitments,
+			bitsize,
+		)?;
+		Ok(())
+	})
+}
+
+/// Verifies a constraint system proof for the circuit encoded in the
+/// `circuit_len` bytes at `circuit`, with the `num_commitments`
+/// commitments at `commitments` to its high-level variables and the
+/// `num_public_inputs` scalars at `public_inputs` as the values of its
+/// public inputs, bound to the `context_len` bytes at `context`.
+///
+/// # Safety
+///
+/// Each pointer must be valid for reads of its length in bytes, with
+/// `32 * num_commitments` bytes for `commitments` and
+/// `32 * num_public_inputs` bytes for `public_inputs`.  Pointers to empty
+/// buffers may be null.
+#[cfg(feature = "yoloproofs")]
+#[no_mangle]
+pub unsafe extern "C" fn bulletproofs_verify_r1cs_proof(
+	proof: *const u8,
+	proof_len: usize,
+	circuit: *const u8,
+	circuit_len: usize,
+	commitments: *const u8,
+	num_commitments: usize,
+	public_inputs: *const u8,
+	num_public_inputs: usize,
+	context: *const u8,
+	context_len: usize,
+) -> BulletproofsError {
+	catch(|| {
+		let proof = R1CSProof::from_bytes(bytes(proof, proof_len)?)?;
+		let circuit =
+			Circuit::from_bytes(bytes(circuit, circuit_len)?).map_err(|_| BulletproofsError::MalformedCircuit)?;
+		let commitments = points(bytes(commitments, byte_len(num_commitments)?)?);
+		let public_inputs = scalars(bytes(public_inputs, byte_len(num_public_inputs)?)?)?;
+		let context = bytes(context, context_len)?;
+
+		let bp_gens = BulletproofGens::new(circuit.multipliers().next_power_of_two(), 1);
+		let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+		transcript.context_domain_sep(context);
+		let mut verifier = Verifier::from_circuit(&mut transcript, &circuit, &commitments)?;
+		for x in public_inputs {
+			verifier.allocate_public(x);
+		}
+		verifier.verify(&proof, &PedersenGens::default(), &bp_gens)?;
+		Ok(())
+	})
+}
+
+/// Runs a verification, turning its error or panic into a result code.
+fn catch<F: FnOnce() -> Result<(), BulletproofsError> + panic::UnwindSafe>(f: F) -> BulletproofsError {
+	match panic::catch_unwind(f) {
+		Ok(Ok(())) => BulletproofsError::Ok,
+		Ok(Err(e)) => e,
+		Err(_) => BulletproofsError::Panic,
+	}
+}
+
+/// Borrows the `len` bytes at `ptr`, which may be null if `len` is zero.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], BulletproofsError> {
+	if len == 0 {
+		Ok(&[])
+	} else if ptr.is_null() {
+		Err(BulletproofsError::NullPointer)
+	} else {
+		Ok(slice::from_raw_parts(ptr, len))
+	}
+}
+
+/// Returns the length in bytes of `n` 32-byte elements.
+fn byte_len(n: usize) -> Result<usize, BulletproofsError> {
+	n.checked_mul(32).ok_or(BulletproofsError::InvalidArgument)
+}
+
+fn points(bytes: &[u8]) -> Vec<CompressedRistretto> {
+	bytes.chunks(32).map(CompressedRistretto::from_slice).collect()
+}
+
+fn scalars(bytes: &[u8]) -> Result<Vec<Scalar>, BulletproofsError> {
+	bytes
+		.chunks(32)
+		.map(|chunk| {
+			let mut buf = [0u8; 32];
+			buf.copy_from_slice(chunk);
+			Scalar::from_canonical_bytes(buf).ok_or(BulletproofsError::MalformedInput)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use core::ptr;
+
+	fn verify_range_proof(
+		proof: &[u8],
+		commitments: &[CompressedRistretto],
+		bitsize: usize,
+		context: &[u8],
+	) -> BulletproofsError {
+		let commitments: Vec<u8> = commitments
+			.iter()
+			.flat_map(|commitment| commitment.to_bytes().to_vec())
+			.collect();
+		unsafe {
+			bulletproofs_verify_range_proof(
+				proof.as_ptr(),
+				proof.len(),
+				commitments.as_ptr(),
+				commitments.len() / 32,
+				bitsize,
+				context.as_ptr(),
+				context.len(),
+			)
+		}
+	}
+
+	#[test]
+	fn range_proof_result_codes() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+		transcript.context_domain_sep(b"context");
+		let blindings = [Scalar::from(1u64), Scalar::from(2u64)];
+		let (proof, commitments) =
+			RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[3, 4], &blindings, 32).unwrap();
+		let proof = proof.to_bytes();
+
+		assert_eq!(
+			verify_range_proof(&proof, &commitments, 32, b"context"),
+			BulletproofsError::Ok
+		);
+		assert_eq!(
+			verify_range_proof(&proof, &commitments, 32, b"other"),
+			BulletproofsError::VerificationFailed
+		);
+		assert_eq!(
+			verify_range_proof(&proof[1..], &commitments, 32, b"context"),
+			BulletproofsError::MalformedProof
+		);
+		assert_eq!(
+			verify_range_proof(&proof, &commitments, 65, b"context"),
+			BulletproofsError::InvalidBitsize
+		);
+		assert_eq!(
+			unsafe { bulletproofs_verify_range_proof(proof.as_ptr(), proof.len(), ptr::null(), 2, 32, ptr::null(), 0) },
+			BulletproofsError::NullPointer
+		);
+	}
+
+	#[cfg(feature = "yoloproofs")]
+	#[test]
+	fn r1cs_proof_result_codes() {
+		use crate::r1cs::{ConstraintSystem, Prover};
+
+		// Prove that x * y = z for committed x and y and a public z.
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+		transcript.context_domain_sep(b"context");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (x_com, x) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+		let (y_com, y) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+		let z = prover.allocate_public(Scalar::from(12u64));
+		let (_, _, out) = prover.multiply(x.into(), y.into());
+		prover.constrain(out - z);
+		let circuit = prover.circuit().unwrap().to_bytes();
+		let proof = prover.prove(&bp_gens).unwrap().to_bytes();
+
+		let commitments: Vec<u8> = [x_com, y_com]
+			.iter()
+			.flat_map(|commitment| commitment.to_bytes().to_vec())
+			.collect();
+		let verify = |public: u64, num_commitments: usize, circuit: &[u8]| {
+			let public = Scalar::from(public).to_bytes();
+			unsafe {
+				bulletproofs_verify_r1cs_proof(
+					proof.as_ptr(),
+					proof.len(),
+					circuit.as_ptr(),
+					circuit.len(),
+					commitments.as_ptr(),
+					num_commitments,
+					public.as_ptr(),
+					1,
+					b"context".as_ptr(),
+					7,
+				)
+			}
+		};
+
+		assert_eq!(verify(12, 2, &circuit), BulletproofsError::Ok);
+		assert_eq!(verify(13, 2, &circuit), BulletproofsError::VerificationFailed);
+		assert_eq!(verify(12, 1, &circuit), BulletproofsError::WrongNumCommitments);
+		assert_eq!(verify(12, 2, &circuit[1..]), BulletproofsError::MalformedCircuit);
+	}
+}
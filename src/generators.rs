@@ -4,18 +4,39 @@
 #![allow(non_snake_case)]
 #![deny(missing_docs)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::borrow::Borrow;
 use curve25519_dalek::{
 	constants::{RISTRETTO_BASEPOINT_COMPRESSED, RISTRETTO_BASEPOINT_POINT},
-	ristretto::RistrettoPoint,
+	ristretto::{CompressedRistretto, RistrettoBasepointTable, RistrettoPoint},
 	scalar::Scalar,
-	traits::MultiscalarMul,
+	traits::IsIdentity,
+};
+#[cfg(feature = "alloc")]
+use curve25519_dalek::{
+	ristretto::VartimeRistrettoPrecomputation,
+	traits::{MultiscalarMul, VartimePrecomputedMultiscalarMul},
 };
 use digest::{ExtendableOutputDirty, Update, XofReader};
+#[cfg(feature = "static-gens")]
+use lazy_static::lazy_static;
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use sha3::{Sha3XofReader, Sha3_512, Shake256};
 
+use crate::{errors::ProofError, util::read32};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 /// Represents a pair of base points for Pedersen commitments.
 ///
 /// The Bulletproofs implementation and API is designed to support
@@ -27,6 +48,9 @@ use sha3::{Sha3XofReader, Sha3_512, Shake256};
 /// * `B`: the `ristretto255` basepoint;
 /// * `B_blinding`: the result of `ristretto255` SHA3-512
 /// hash-to-group on input `B_bytes`.
+///
+/// Independent generators can be derived from an application-specific
+/// label with [`PedersenGens::new_with_label`].
 #[derive(Copy, Clone)]
 pub struct PedersenGens {
 	/// Base for the committed value
@@ -36,10 +60,129 @@ pub struct PedersenGens {
 }
 
 impl PedersenGens {
+	/// Derives generators from an application-specific `label`, so that
+	/// applications using different labels cannot reuse each other's
+	/// commitments.
+	///
+	/// `B` is the result of `ristretto255` SHA3-512 hash-to-group on the
+	/// input `"PedersenGens" || label`, and `B_blinding` is derived from `B`
+	/// as for the default generators.
+	pub fn new_with_label(label: &[u8]) -> Self {
+		let mut hash = Sha3_512::default();
+		hash.update(b"PedersenGens");
+		hash.update(label);
+		let B = RistrettoPoint::from_hash(hash);
+		PedersenGens {
+			B,
+			B_blinding: RistrettoPoint::hash_from_bytes::<Sha3_512>(B.compress().as_bytes()),
+		}
+	}
+
+	/// Creates generators from existing bases, for instance to use the
+	/// same bases as an ElGamal key setup.
+	///
+	/// Returns [`ProofError::InvalidGenerators`] if either base is the
+	/// identity, or if `B_blinding` is \\(\pm B\\).  The `ristretto255`
+	/// group has prime order, so there are no small-order points to rule
+	/// out, but the discrete logarithm of `B_blinding` with respect to `B`
+	/// must still be unknown for the commitments to be binding.
+	pub fn with_bases(B: RistrettoPoint, B_blinding: RistrettoPoint) -> Result<Self, ProofError> {
+		if B.is_identity() || B_blinding.is_identity() || B == B_blinding || B == -B_blinding {
+			return Err(ProofError::InvalidGenerators);
+		}
+		Ok(PedersenGens { B, B_blinding })
+	}
+
+	/// Returns the generators committing to values under `B` and to
+	/// blinding factors under the ElGamal `public_key`, for range proofs
+	/// over [`ElGamalCiphertext`](crate::ElGamalCiphertext)s.
+	///
+	/// Returns [`ProofError::InvalidGenerators`] if the public key is
+	/// rejected by [`PedersenGens::with_bases`].
+	pub fn for_public_key(&self, public_key: &RistrettoPoint) -> Result<Self, ProofError> {
+		PedersenGens::with_bases(self.B, *public_key)
+	}
+
 	/// Creates a Pedersen commitment using the value scalar and a blinding
 	/// factor.
 	pub fn commit(&self, value: Scalar, blinding: Scalar) -> RistrettoPoint {
-		RistrettoPoint::multiscalar_mul(&[value, blinding], &[self.B, self.B_blinding])
+		#[cfg(feature = "alloc")]
+		return RistrettoPoint::multiscalar_mul(&[value, blinding], &[self.B, self.B_blinding]);
+
+		#[cfg(not(feature = "alloc"))]
+		return value * self.B + blinding * self.B_blinding;
+	}
+
+	/// Checks that `commitment` is a commitment to `value` with the
+	/// blinding factor `blinding`.
+	pub fn open(&self, commitment: &CompressedRistretto, value: Scalar, blinding: Scalar) -> bool {
+		self.commit(value, blinding).compress() == *commitment
+	}
+
+	/// Rerandomizes `commitment` by adding `delta` to its blinding factor,
+	/// so that the result commits to the same value under the blinding
+	/// factor plus `delta`, and cannot be linked to `commitment` without
+	/// `delta`.
+	///
+	/// Proofs cannot be rerandomized along with their commitments: every
+	/// challenge of a proof is bound to its commitments by the transcript,
+	/// so a proof for the rerandomized commitment must be created anew by
+	/// the holder of its opening.
+	///
+	/// Returns `None` if `commitment` is not a valid point.
+	pub fn rerandomize(&self, commitment: &CompressedRistretto, delta: Scalar) -> Option<CompressedRistretto> {
+		Some((commitment.decompress()? + delta * self.B_blinding).compress())
+	}
+
+	/// Rerandomizes `commitment` with a random `delta`, as by
+	/// [`PedersenGens::rerandomize`], and returns the result with `delta`.
+	pub fn rerandomize_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		commitment: &CompressedRistretto,
+		rng: &mut T,
+	) -> Option<(CompressedRistretto, Scalar)> {
+		let delta = Scalar::random(rng);
+		Some((self.rerandomize(commitment, delta)?, delta))
+	}
+
+	/// Precomputes multiplication tables for `B` and `B_blinding`, for
+	/// creating many commitments under the same generators.
+	pub fn precompute(&self) -> PedersenGensPrecomputed {
+		PedersenGensPrecomputed {
+			pc_gens: *self,
+			B_table: RistrettoBasepointTable::create(&self.B),
+			B_blinding_table: RistrettoBasepointTable::create(&self.B_blinding),
+		}
+	}
+
+	/// Serializes the generators as the 64-byte concatenation of the
+	/// compressed points `B` and `B_blinding`.
+	pub fn to_bytes(self) -> [u8; 64] {
+		let mut buf = [0u8; 64];
+		buf[..32].copy_from_slice(self.B.compress().as_bytes());
+		buf[32..].copy_from_slice(self.B_blinding.compress().as_bytes());
+		buf
+	}
+
+	/// Deserializes the generators from a byte slice.
+	///
+	/// Returns an error if the slice is not 64 bytes long or does not
+	/// contain two valid compressed Ristretto points, and
+	/// [`ProofError::InvalidGenerators`] if the points are rejected by
+	/// [`PedersenGens::with_bases`].
+	pub fn from_bytes(slice: &[u8]) -> Result<PedersenGens, ProofError> {
+		if slice.len() != 64 {
+			return Err(ProofError::FormatError);
+		}
+
+		let B = CompressedRistretto(read32(&slice[..32]))
+			.decompress()
+			.ok_or(ProofError::FormatError)?;
+		let B_blinding = CompressedRistretto(read32(&slice[32..]))
+			.decompress()
+			.ok_or(ProofError::FormatError)?;
+
+		PedersenGens::with_bases(B, B_blinding)
 	}
 }
 
@@ -52,16 +195,42 @@ impl Default for PedersenGens {
 	}
 }
 
+/// Pedersen generators with precomputed multiplication tables for their
+/// bases, created with [`PedersenGens::precompute`].
+///
+/// The tables take about 60 kilobytes, and make a commitment about four
+/// times faster to compute than with [`PedersenGens::commit`].  Like it,
+/// [`PedersenGensPrecomputed::commit`] runs in constant time.
+#[derive(Clone)]
+pub struct PedersenGensPrecomputed {
+	pc_gens: PedersenGens,
+	B_table: RistrettoBasepointTable,
+	B_blinding_table: RistrettoBasepointTable,
+}
+
+impl PedersenGensPrecomputed {
+	/// Returns the generators the tables were computed for.
+	pub fn pc_gens(&self) -> &PedersenGens {
+		&self.pc_gens
+	}
+
+	/// Creates a Pedersen commitment using the value scalar and a blinding
+	/// factor, equal to the one from [`PedersenGens::commit`].
+	pub fn commit(&self, value: Scalar, blinding: Scalar) -> RistrettoPoint {
+		&value * &self.B_table + &blinding * &self.B_blinding_table
+	}
+}
+
 /// The `GeneratorsChain` creates an arbitrary-long sequence of
 /// orthogonal generators.  The sequence can be deterministically
 /// produced starting with an arbitrary point.
-struct GeneratorsChain {
+pub(crate) struct GeneratorsChain {
 	reader: Sha3XofReader,
 }
 
 impl GeneratorsChain {
 	/// Creates a chain of generators, determined by the hash of `label`.
-	fn new(label: &[u8]) -> Self {
+	pub(crate) fn new(label: &[u8]) -> Self {
 		let mut shake = Shake256::default();
 		shake.update(b"GeneratorsChain");
 		shake.update(label);
@@ -71,8 +240,24 @@ impl GeneratorsChain {
 		}
 	}
 
+	/// Creates the chain of `G` or `H` generators, for `tag` equal to `b'G'`
+	/// or `b'H'`, of the party `party_index`, determined by the hash of
+	/// `prefix || tag || le32(party_index)`.
+	pub(crate) fn for_party(prefix: &[u8], tag: u8, party_index: u32) -> Self {
+		let mut shake = Shake256::default();
+		shake.update(b"GeneratorsChain");
+		shake.update(prefix);
+		shake.update(&[tag]);
+		shake.update(&party_index.to_le_bytes());
+
+		GeneratorsChain {
+			reader: shake.finalize_xof_dirty(),
+		}
+	}
+
 	/// Advances the reader n times, squeezing and discarding
 	/// the result.
+	#[cfg(feature = "alloc")]
 	fn fast_forward(mut self, n: usize) -> Self {
 		for _ in 0..n {
 			let mut buf = [0u8; 64];
@@ -129,7 +314,11 @@ impl Iterator for GeneratorsChain {
 /// chain, and even forward-compatible to multiparty aggregation of
 /// constraint system proofs, since the generators are namespaced by
 /// their party index.
-#[cfg_attr(feature = "serde", derive(serde_derive::Serialize, serde_derive::Deserialize))]
+///
+/// The labels of the chains can be prefixed with an application-specific
+/// label with [`BulletproofGens::new_with_label`], so that different
+/// applications derive independent generators.
+#[cfg(feature = "alloc")]
 #[derive(Clone)]
 pub struct BulletproofGens {
 	/// The maximum number of usable generators for each party.
@@ -140,8 +329,20 @@ pub struct BulletproofGens {
 	pub G_vec: Vec<Vec<RistrettoPoint>>,
 	/// Precomputed \\(\mathbf H\\) generators for each party.
 	pub H_vec: Vec<Vec<RistrettoPoint>>,
+	/// The prefix of the labels of the generator chains, empty for the
+	/// default generators.
+	label: Vec<u8>,
 }
 
+/// The largest number of generators per chain, `gens_capacity` times
+/// `party_capacity`, accepted by [`BulletproofGens::from_bytes`].
+///
+/// The encoding only holds the capacities, so this bounds the work and the
+/// memory an untrusted encoding can make the decoder spend.
+#[cfg(feature = "alloc")]
+pub const MAX_DESERIALIZED_GENERATORS: usize = 1 << 20;
+
+#[cfg(feature = "alloc")]
 impl BulletproofGens {
 	/// Create a new `BulletproofGens` object.
 	///
@@ -156,16 +357,78 @@ impl BulletproofGens {
 	/// * `party_capacity` is the maximum number of parties that can produce an
 	///   aggregated proof.
 	pub fn new(gens_capacity: usize, party_capacity: usize) -> Self {
+		BulletproofGens::new_with_label(gens_capacity, party_capacity, &[])
+	}
+
+	/// Create a new `BulletproofGens` object whose generators are derived
+	/// from an application-specific `label`, so that applications using
+	/// different labels derive independent generators.
+	///
+	/// The label prefixes the label of each party's generator chain, and
+	/// the empty label gives the generators of [`BulletproofGens::new`].
+	pub fn new_with_label(gens_capacity: usize, party_capacity: usize, label: &[u8]) -> Self {
 		let mut gens = BulletproofGens {
 			gens_capacity: 0,
 			party_capacity,
 			G_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
 			H_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
+			label: label.to_vec(),
 		};
 		gens.increase_capacity(gens_capacity);
 		gens
 	}
 
+	/// Returns the label the generators were derived from, which is empty
+	/// for the default generators.
+	pub fn label(&self) -> &[u8] {
+		&self.label
+	}
+
+	/// Serializes the generators as the 16-byte little-endian encoding of
+	/// `gens_capacity` followed by `party_capacity`, followed by the label
+	/// of the generators.
+	///
+	/// The generators themselves are deterministic, so only the capacities
+	/// and the label are encoded and [`BulletproofGens::from_bytes`]
+	/// recomputes the points.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		use byteorder::{ByteOrder, LittleEndian};
+
+		let mut buf = vec![0u8; 16];
+		LittleEndian::write_u64(&mut buf[..8], self.gens_capacity as u64);
+		LittleEndian::write_u64(&mut buf[8..], self.party_capacity as u64);
+		buf.extend_from_slice(&self.label);
+		buf
+	}
+
+	/// Deserializes the generators from a byte slice produced by
+	/// [`BulletproofGens::to_bytes`], recomputing the generator points.
+	///
+	/// The cost of this function is proportional to the encoded
+	/// capacities, so it returns a [`ProofError::FormatError`] if their
+	/// product exceeds [`MAX_DESERIALIZED_GENERATORS`].  Larger generators
+	/// must be created with [`BulletproofGens::new`].
+	pub fn from_bytes(slice: &[u8]) -> Result<BulletproofGens, ProofError> {
+		use byteorder::{ByteOrder, LittleEndian};
+
+		if slice.len() < 16 {
+			return Err(ProofError::FormatError);
+		}
+		let gens_capacity = LittleEndian::read_u64(&slice[..8]);
+		let party_capacity = LittleEndian::read_u64(&slice[8..16]);
+		match gens_capacity.checked_mul(party_capacity) {
+			Some(total) if total <= MAX_DESERIALIZED_GENERATORS as u64 => {}
+			_ => return Err(ProofError::FormatError),
+		}
+		let (gens_capacity, party_capacity) = (gens_capacity as usize, party_capacity as usize);
+
+		Ok(BulletproofGens::new_with_label(
+			gens_capacity,
+			party_capacity,
+			&slice[16..],
+		))
+	}
+
 	/// Returns j-th share of generators, with an appropriate
 	/// slice of vectors G and H for the j-th range proof.
 	pub fn share(&self, j: usize) -> BulletproofGensShare<'_> {
@@ -175,28 +438,60 @@ impl BulletproofGens {
 	/// Increases the generators' capacity to the amount specified.
 	/// If less than or equal to the current capacity, does nothing.
 	pub fn increase_capacity(&mut self, new_capacity: usize) {
-		use byteorder::{ByteOrder, LittleEndian};
-
 		if self.gens_capacity >= new_capacity {
 			return;
 		}
 
-		for i in 0..self.party_capacity {
+		let gens_capacity = self.gens_capacity;
+		let prefix = &self.label;
+		let extend = |i: usize, (G, H): (&mut Vec<RistrettoPoint>, &mut Vec<RistrettoPoint>)| {
 			let party_index = i as u32;
-			let mut label = [b'G', 0, 0, 0, 0];
-			LittleEndian::write_u32(&mut label[1..5], party_index);
-			self.G_vec[i].extend(
-				&mut GeneratorsChain::new(&label)
-					.fast_forward(self.gens_capacity)
-					.take(new_capacity - self.gens_capacity),
+			G.extend(
+				&mut GeneratorsChain::for_party(prefix, b'G', party_index)
+					.fast_forward(gens_capacity)
+					.take(new_capacity - gens_capacity),
 			);
 
-			label[0] = b'H';
-			self.H_vec[i].extend(
-				&mut GeneratorsChain::new(&label)
-					.fast_forward(self.gens_capacity)
-					.take(new_capacity - self.gens_capacity),
+			H.extend(
+				&mut GeneratorsChain::for_party(prefix, b'H', party_index)
+					.fast_forward(gens_capacity)
+					.take(new_capacity - gens_capacity),
 			);
+		};
+
+		// Each party's generators come from their own chains, so they can
+		// be derived independently.
+		#[cfg(feature = "rayon")]
+		self.G_vec
+			.par_iter_mut()
+			.zip(self.H_vec.par_iter_mut())
+			.enumerate()
+			.for_each(|(i, gens)| extend(i, gens));
+		#[cfg(not(feature = "rayon"))]
+		self.G_vec
+			.iter_mut()
+			.zip(self.H_vec.iter_mut())
+			.enumerate()
+			.for_each(|(i, gens)| extend(i, gens));
+
+		self.gens_capacity = new_capacity;
+	}
+
+	/// Decreases the generators' capacity to the amount specified,
+	/// dropping the generators beyond it and freeing their memory.
+	/// If greater than or equal to the current capacity, does nothing.
+	///
+	/// The remaining generators are unchanged, so a later call to
+	/// [`BulletproofGens::increase_capacity`] derives the same generators
+	/// as [`BulletproofGens::new`].
+	pub fn shrink_to(&mut self, new_capacity: usize) {
+		if self.gens_capacity <= new_capacity {
+			return;
+		}
+
+		for gens in self.G_vec.iter_mut().chain(self.H_vec.iter_mut()) {
+			gens.truncate(new_capacity);
+			gens.shrink_to_fit();
 		}
 		self.gens_capacity = new_capacity;
 	}
@@ -226,6 +521,7 @@ impl BulletproofGens {
 	}
 }
 
+#[cfg(feature = "alloc")]
 struct AggregatedGensIter<'a> {
 	array: &'a Vec<Vec<RistrettoPoint>>,
 	n: usize,
@@ -234,6 +530,7 @@ struct AggregatedGensIter<'a> {
 	gen_idx: usize,
 }
 
+#[cfg(feature = "alloc")]
 impl<'a> Iterator for AggregatedGensIter<'a> {
 	type Item = &'a RistrettoPoint;
 
@@ -258,6 +555,48 @@ impl<'a> Iterator for AggregatedGensIter<'a> {
 	}
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for PedersenGens {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for PedersenGens {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid PedersenGens")?;
+		PedersenGens::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for BulletproofGens {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for BulletproofGens {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid BulletproofGens")?;
+		BulletproofGens::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
 /// Represents a view of the generators used by a specific party in an
 /// aggregated proof.
 ///
@@ -266,6 +605,7 @@ impl<'a> Iterator for AggregatedGensIter<'a> {
 /// provides a view of the generators for one of the `m` parties' shares.
 ///
 /// The `BulletproofGensShare` is produced by [`BulletproofGens::share()`].
+#[cfg(feature = "alloc")]
 #[derive(Copy, Clone)]
 pub struct BulletproofGensShare<'a> {
 	/// The parent object that this is a view into
@@ -274,21 +614,227 @@ pub struct BulletproofGensShare<'a> {
 	share: usize,
 }
 
+#[cfg(feature = "alloc")]
 impl<'a> BulletproofGensShare<'a> {
 	/// Return an iterator over this party's G generators with given size `n`.
+	///
+	/// The iterator is empty if the party is out of range.
 	pub(crate) fn G(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
-		self.gens.G_vec[self.share].iter().take(n)
+		self.gens
+			.G_vec
+			.get(self.share)
+			.map_or(&[][..], |gens| &gens[..])
+			.iter()
+			.take(n)
 	}
 
 	/// Return an iterator over this party's H generators with given size `n`.
+	///
+	/// The iterator is empty if the party is out of range.
 	pub(crate) fn H(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
-		self.gens.H_vec[self.share].iter().take(n)
+		self.gens
+			.H_vec
+			.get(self.share)
+			.map_or(&[][..], |gens| &gens[..])
+			.iter()
+			.take(n)
 	}
 }
 
+/// Precomputed multiplication tables for the fixed bases of the
+/// verification equation.
+///
+/// Verification ends with a multiscalar multiplication over the Pedersen
+/// bases \\(B\\) and \\(\tilde{B}\\) and the \\(\mathbf{G}\\) and
+/// \\(\mathbf{H}\\) generators, which are the same for every proof.  A
+/// `VerificationKey` computes lookup tables for these bases once, so that
+/// each verification using it only pays for the points specific to the
+/// proof.
+///
+/// The key covers the first `n` generators of the first `m` parties, and
+/// can verify range proofs of padded bitsize at most `n` aggregating at
+/// most `m` values, and constraint system proofs with at most `n`
+/// multipliers after padding.  The tables take several kilobytes per
+/// generator, so the key should be sized for the proofs being verified.
+#[cfg(feature = "alloc")]
+pub struct VerificationKey {
+	pc_gens: PedersenGens,
+	n: usize,
+	m: usize,
+	precomputation: VartimeRistrettoPrecomputation,
+}
+
+#[cfg(feature = "alloc")]
+impl VerificationKey {
+	/// Precomputes the tables for `pc_gens` and the first `n` generators
+	/// of the first `m` parties of `bp_gens`.
+	///
+	/// Returns an error if `bp_gens` does not have that many generators.
+	pub fn new(pc_gens: &PedersenGens, bp_gens: &BulletproofGens, n: usize, m: usize) -> Result<Self, ProofError> {
+		if bp_gens.gens_capacity < n || bp_gens.party_capacity < m {
+			return Err(ProofError::InvalidGeneratorsLength);
+		}
+
+		let precomputation = VartimeRistrettoPrecomputation::new(
+			[pc_gens.B, pc_gens.B_blinding]
+				.iter()
+				.chain(bp_gens.G(n, m))
+				.chain(bp_gens.H(n, m)),
+		);
+
+		Ok(VerificationKey {
+			pc_gens: *pc_gens,
+			n,
+			m,
+			precomputation,
+		})
+	}
+
+	/// Returns the Pedersen generators of the key.
+	pub fn pc_gens(&self) -> &PedersenGens {
+		&self.pc_gens
+	}
+
+	/// Returns the number of generators per party covered by the key.
+	pub fn gens_capacity(&self) -> usize {
+		self.n
+	}
+
+	/// Returns the number of parties covered by the key.
+	pub fn party_capacity(&self) -> usize {
+		self.m
+	}
+
+	/// Computes the multiscalar multiplication of the verification
+	/// equation, or returns `None` if any of the dynamic points is `None`.
+	///
+	/// The `pedersen_scalars` are the scalars for \\(B\\) and
+	/// \\(\tilde{B}\\).  The `g_scalars` and `h_scalars` are the scalars for
+	/// the aggregated generators of a proof using `n` generators per party,
+	/// for as many parties as they fill.  The caller must check that `n` and
+	/// the number of parties fit in the key.
+	pub(crate) fn optional_mixed_multiscalar_mul<I, J>(
+		&self,
+		pedersen_scalars: [Scalar; 2],
+		g_scalars: &[Scalar],
+		h_scalars: &[Scalar],
+		n: usize,
+		dynamic_scalars: I,
+		dynamic_points: J,
+	) -> Option<RistrettoPoint>
+	where
+		I: IntoIterator,
+		I::Item: Borrow<Scalar>,
+		J: IntoIterator<Item = Option<RistrettoPoint>>,
+	{
+		debug_assert!(n <= self.n && g_scalars.len() <= n * self.m);
+
+		// Generators the proof does not use get a zero scalar.
+		let gens = self.n * self.m;
+		let mut static_scalars = vec![Scalar::zero(); 2 + 2 * gens];
+		static_scalars[..2].copy_from_slice(&pedersen_scalars);
+		for (i, (g, h)) in g_scalars.iter().zip(h_scalars.iter()).enumerate() {
+			let idx = (i / n) * self.n + (i % n);
+			static_scalars[2 + idx] = *g;
+			static_scalars[2 + gens + idx] = *h;
+		}
+
+		self.precomputation
+			.optional_mixed_multiscalar_mul(static_scalars, dynamic_scalars, dynamic_points)
+	}
+}
+
+/// The default number of generators per party of [`static_bp_gens`],
+/// enough for circuits of up to 1024 multipliers.
+#[cfg(feature = "static-gens")]
+pub const STATIC_GENS_CAPACITY: usize = 1024;
+
+/// The default number of parties of [`static_bp_gens`], enough for
+/// aggregated range proofs of up to 8 values.
+#[cfg(feature = "static-gens")]
+pub const STATIC_PARTY_CAPACITY: usize = 8;
+
+/// The environment variable overriding [`STATIC_GENS_CAPACITY`].
+#[cfg(feature = "static-gens")]
+pub const STATIC_GENS_CAPACITY_VAR: &str = "BULLETPROOFS_GENS_CAPACITY";
+
+/// The environment variable overriding [`STATIC_PARTY_CAPACITY`].
+#[cfg(feature = "static-gens")]
+pub const STATIC_PARTY_CAPACITY_VAR: &str = "BULLETPROOFS_PARTY_CAPACITY";
+
+#[cfg(feature = "static-gens")]
+lazy_static! {
+	static ref STATIC_BP_GENS: BulletproofGens = BulletproofGens::new(
+		capacity_from_env(STATIC_GENS_CAPACITY_VAR, STATIC_GENS_CAPACITY),
+		capacity_from_env(STATIC_PARTY_CAPACITY_VAR, STATIC_PARTY_CAPACITY),
+	);
+	static ref STATIC_PC_GENS: PedersenGens = PedersenGens::default();
+}
+
+/// Returns the process-wide default [`BulletproofGens`], created on first
+/// use, so that verifiers do not derive the generators on every call.
+///
+/// Their capacities are read from the environment variables
+/// [`STATIC_GENS_CAPACITY_VAR`] and [`STATIC_PARTY_CAPACITY_VAR`] when they
+/// are first used, and default to [`STATIC_GENS_CAPACITY`] and
+/// [`STATIC_PARTY_CAPACITY`] if a variable is unset or is not a positive
+/// integer.  Proofs that need more generators than the capacities fail with
+/// an `InvalidGeneratorsLength` error.
+#[cfg(feature = "static-gens")]
+pub fn static_bp_gens() -> &'static BulletproofGens {
+	&STATIC_BP_GENS
+}
+
+/// Returns the process-wide default [`PedersenGens`].
+#[cfg(feature = "static-gens")]
+pub fn static_pc_gens() -> &'static PedersenGens {
+	&STATIC_PC_GENS
+}
+
+/// Reads a capacity from the environment variable `var`, or returns
+/// `default` if it is unset or is not a positive integer.
+#[cfg(feature = "static-gens")]
+fn capacity_from_env(var: &str, default: usize) -> usize {
+	std::env::var(var)
+		.ok()
+		.and_then(|value| value.trim().parse().ok())
+		.filter(|&capacity| capacity > 0)
+		.unwrap_or(default)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use curve25519_dalek::traits::Identity;
+
+	#[test]
+	fn precomputed_commit_matches_commit() {
+		for pc_gens in &[PedersenGens::default(), PedersenGens::new_with_label(b"precompute")] {
+			let precomputed = pc_gens.precompute();
+			for &(value, blinding) in &[(0u64, 0u64), (1037, 7), (u64::max_value(), 1 << 40)] {
+				let (value, blinding) = (Scalar::from(value), Scalar::from(blinding));
+				assert_eq!(precomputed.commit(value, blinding), pc_gens.commit(value, blinding));
+			}
+			assert_eq!(precomputed.pc_gens().B, pc_gens.B);
+		}
+	}
+
+	#[test]
+	fn rerandomized_commitment_opens_with_shifted_blinding() {
+		let pc_gens = PedersenGens::default();
+		let (value, blinding) = (Scalar::from(1037u64), Scalar::from(7u64));
+		let commitment = pc_gens.commit(value, blinding).compress();
+
+		let (rerandomized, delta) = pc_gens
+			.rerandomize_with_rng(&commitment, &mut rand::thread_rng())
+			.unwrap();
+		assert_ne!(rerandomized, commitment);
+		assert!(pc_gens.open(&rerandomized, value, blinding + delta));
+		assert_eq!(pc_gens.rerandomize(&rerandomized, -delta), Some(commitment));
+
+		let invalid = CompressedRistretto([0xff; 32]);
+		assert_eq!(pc_gens.rerandomize(&invalid, delta), None);
+	}
 
 	#[test]
 	fn aggregated_gens_iter_matches_flat_map() {
@@ -353,4 +899,132 @@ mod tests {
 		helper(32, 8);
 		helper(16, 8);
 	}
+
+	#[test]
+	fn gens_with_bases() {
+		let pc_gens = PedersenGens::default();
+		let B = RistrettoPoint::hash_from_bytes::<Sha3_512>(b"elgamal key");
+		let custom = PedersenGens::with_bases(pc_gens.B, B).unwrap();
+		assert_eq!(custom.B_blinding, B);
+		assert_eq!(PedersenGens::from_bytes(&custom.to_bytes()).unwrap().B_blinding, B);
+
+		let identity = RistrettoPoint::identity();
+		for &(B, B_blinding) in &[(identity, B), (B, identity), (B, B), (B, -B)] {
+			assert_eq!(
+				PedersenGens::with_bases(B, B_blinding).err(),
+				Some(ProofError::InvalidGenerators)
+			);
+		}
+
+		let mut bytes = custom.to_bytes();
+		let (B_bytes, B_blinding_bytes) = bytes.split_at_mut(32);
+		B_blinding_bytes.copy_from_slice(B_bytes);
+		assert_eq!(
+			PedersenGens::from_bytes(&bytes).err(),
+			Some(ProofError::InvalidGenerators)
+		);
+	}
+
+	#[test]
+	fn labelled_gens_are_independent() {
+		let gens = BulletproofGens::new(8, 2);
+		assert_eq!(BulletproofGens::new_with_label(8, 2, b"").G_vec, gens.G_vec);
+
+		let labelled = BulletproofGens::new_with_label(8, 2, b"app");
+		assert_eq!(labelled.label(), b"app");
+		assert!(labelled.G_vec[0].iter().all(|G| !gens.G_vec[0].contains(G)));
+		assert_ne!(
+			labelled.H_vec[1],
+			BulletproofGens::new_with_label(8, 2, b"other").H_vec[1]
+		);
+
+		let decoded = BulletproofGens::from_bytes(&labelled.to_bytes()).unwrap();
+		assert_eq!(decoded.label(), b"app");
+		assert_eq!(decoded.G_vec, labelled.G_vec);
+		assert_eq!(decoded.H_vec, labelled.H_vec);
+		assert_eq!(BulletproofGens::from_bytes(&gens.to_bytes()).unwrap().G_vec, gens.G_vec);
+
+		let pc_gens = PedersenGens::new_with_label(b"app");
+		assert_ne!(pc_gens.B, PedersenGens::default().B);
+		assert_ne!(pc_gens.B_blinding, PedersenGens::default().B_blinding);
+		assert_eq!(pc_gens.B, PedersenGens::new_with_label(b"app").B);
+		assert_ne!(pc_gens.B, PedersenGens::new_with_label(b"other").B);
+	}
+
+	#[test]
+	fn huge_encoded_capacities_are_rejected() {
+		let encode = |gens_capacity: u64, party_capacity: u64| {
+			let mut bytes = vec![0u8; 16];
+			bytes[..8].copy_from_slice(&gens_capacity.to_le_bytes());
+			bytes[8..].copy_from_slice(&party_capacity.to_le_bytes());
+			bytes
+		};
+		let max = MAX_DESERIALIZED_GENERATORS as u64;
+		for &(gens_capacity, party_capacity) in &[
+			(1 << 60, 1),
+			(1, 1 << 60),
+			(max, 2),
+			(1 << 32, 1 << 32),
+			(u64::MAX, u64::MAX),
+		] {
+			assert_eq!(
+				BulletproofGens::from_bytes(&encode(gens_capacity, party_capacity)).err(),
+				Some(ProofError::FormatError)
+			);
+		}
+
+		let gens = BulletproofGens::from_bytes(&encode(4, 2)).unwrap();
+		assert_eq!((gens.gens_capacity, gens.party_capacity), (4, 2));
+	}
+
+	#[test]
+	fn shrinking_gens_keeps_prefix() {
+		let gens = BulletproofGens::new(64, 2);
+
+		let mut shrunk = gens.clone();
+		shrunk.shrink_to(128);
+		assert_eq!(shrunk.gens_capacity, 64);
+		shrunk.shrink_to(16);
+		assert_eq!(shrunk.gens_capacity, 16);
+		assert!(shrunk
+			.G_vec
+			.iter()
+			.chain(shrunk.H_vec.iter())
+			.all(|gens| gens.len() == 16));
+		assert_eq!(shrunk.to_bytes(), BulletproofGens::new(16, 2).to_bytes());
+		assert_eq!(shrunk.G(16, 2).collect::<Vec<_>>(), gens.G(16, 2).collect::<Vec<_>>());
+
+		shrunk.increase_capacity(64);
+		assert_eq!(shrunk.G_vec, gens.G_vec);
+		assert_eq!(shrunk.H_vec, gens.H_vec);
+	}
+
+	#[cfg(feature = "static-gens")]
+	#[test]
+	fn static_gens_are_created_once() {
+		let bp_gens = static_bp_gens();
+		assert!(core::ptr::eq(bp_gens, static_bp_gens()));
+		assert!(bp_gens.gens_capacity >= 1 && bp_gens.party_capacity >= 1);
+		assert!(bp_gens.label().is_empty());
+
+		let reference = BulletproofGens::new(bp_gens.gens_capacity, bp_gens.party_capacity);
+		assert_eq!(bp_gens.G_vec, reference.G_vec);
+		assert_eq!(bp_gens.H_vec, reference.H_vec);
+		assert_eq!(static_pc_gens().B_blinding, PedersenGens::default().B_blinding);
+	}
+
+	#[cfg(feature = "static-gens")]
+	#[test]
+	fn capacity_from_env_falls_back_to_default() {
+		let var = "BULLETPROOFS_TEST_CAPACITY";
+		std::env::remove_var(var);
+		assert_eq!(capacity_from_env(var, 64), 64);
+		std::env::set_var(var, " 256 ");
+		assert_eq!(capacity_from_env(var, 64), 256);
+		for invalid in &["0", "-1", "many", ""] {
+			std::env::set_var(var, invalid);
+			assert_eq!(capacity_from_env(var, 64), 64);
+		}
+		std::env::remove_var(var);
+	}
 }
@@ -0,0 +1,232 @@
+//! Traits abstracting over the prime-order group and its scalar field.
+//!
+//! The proofs of this crate are written for the `ristretto255` group of
+//! `curve25519-dalek`.  The [`Group`] and [`ScalarField`] traits capture
+//! the operations they need from it, so that code written against them can
+//! run over other prime-order groups, such as the G1 group of BLS12-381 or
+//! secp256k1, by implementing the traits for their types.
+//!
+//! Only [`inner_product`](crate::inner_product) is generic so far; the
+//! proofs themselves still use `ristretto255`, and are moved over to the
+//! traits one by one.  The traits are implemented for [`Scalar`] and
+//! [`RistrettoPoint`].
+
+use alloc::vec::Vec;
+use core::{
+	fmt::Debug,
+	iter::Sum,
+	ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{Identity, IsIdentity, VartimeMultiscalarMul},
+};
+use rand_core::{CryptoRng, RngCore};
+use sha3::Sha3_512;
+
+use crate::util::read32;
+
+/// The scalar field of a prime-order [`Group`].
+pub trait ScalarField:
+	Copy
+	+ Debug
+	+ Default
+	+ Eq
+	+ Send
+	+ Sync
+	+ Add<Output = Self>
+	+ Sub<Output = Self>
+	+ Mul<Output = Self>
+	+ Neg<Output = Self>
+	+ AddAssign
+	+ SubAssign
+	+ MulAssign
+	+ Sum
+	+ From<u64>
+	+ 'static
+{
+	/// The length in bytes of the canonical encoding of a scalar.
+	const ENCODED_LEN: usize;
+
+	/// Returns the additive identity.
+	fn zero() -> Self;
+
+	/// Returns the multiplicative identity.
+	fn one() -> Self;
+
+	/// Returns a uniformly random scalar.
+	fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self;
+
+	/// Returns the multiplicative inverse, or zero for zero.
+	fn invert(&self) -> Self;
+
+	/// Returns the canonical encoding of the scalar, of
+	/// [`ENCODED_LEN`](ScalarField::ENCODED_LEN) bytes.
+	fn to_bytes(&self) -> Vec<u8>;
+
+	/// Decodes a scalar from its canonical encoding, or returns `None` if
+	/// `bytes` is not one.
+	fn from_bytes(bytes: &[u8]) -> Option<Self>;
+
+	/// Reduces 64 uniformly random bytes, such as a transcript challenge,
+	/// to a scalar.
+	fn from_bytes_wide(bytes: &[u8; 64]) -> Self;
+}
+
+/// A prime-order group, written additively.
+pub trait Group:
+	Copy
+	+ Debug
+	+ Eq
+	+ Send
+	+ Sync
+	+ Add<Output = Self>
+	+ Sub<Output = Self>
+	+ Neg<Output = Self>
+	+ AddAssign
+	+ SubAssign
+	+ Mul<<Self as Group>::Scalar, Output = Self>
+	+ 'static
+{
+	/// The scalar field of the group.
+	type Scalar: ScalarField;
+
+	/// The length in bytes of the canonical encoding of a point.
+	const ENCODED_LEN: usize;
+
+	/// Returns the identity element.
+	fn identity() -> Self;
+
+	/// Returns whether the point is the identity element.
+	fn is_identity(&self) -> bool;
+
+	/// Returns the standard generator of the group.
+	fn generator() -> Self;
+
+	/// Hashes `bytes` to a point whose discrete logarithm with respect to
+	/// any other point is unknown.
+	fn hash_to_group(bytes: &[u8]) -> Self;
+
+	/// Computes \\(\sum_i s_i P_i\\), in variable time.
+	fn vartime_multiscalar_mul(scalars: &[Self::Scalar], points: &[Self]) -> Self;
+
+	/// Returns the canonical encoding of the point, of
+	/// [`ENCODED_LEN`](Group::ENCODED_LEN) bytes.
+	fn to_bytes(&self) -> Vec<u8>;
+
+	/// Decodes a point from its canonical encoding, or returns `None` if
+	/// `bytes` is not one.
+	fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl ScalarField for Scalar {
+	const ENCODED_LEN: usize = 32;
+
+	fn zero() -> Self {
+		Scalar::zero()
+	}
+
+	fn one() -> Self {
+		Scalar::one()
+	}
+
+	fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+		Scalar::random(rng)
+	}
+
+	fn invert(&self) -> Self {
+		if *self == Scalar::zero() {
+			Scalar::zero()
+		} else {
+			Scalar::invert(self)
+		}
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		self.as_bytes().to_vec()
+	}
+
+	fn from_bytes(bytes: &[u8]) -> Option<Self> {
+		if bytes.len() != 32 {
+			return None;
+		}
+		Scalar::from_canonical_bytes(read32(bytes))
+	}
+
+	fn from_bytes_wide(bytes: &[u8; 64]) -> Self {
+		Scalar::from_bytes_mod_order_wide(bytes)
+	}
+}
+
+impl Group for RistrettoPoint {
+	type Scalar = Scalar;
+
+	const ENCODED_LEN: usize = 32;
+
+	fn identity() -> Self {
+		<RistrettoPoint as Identity>::identity()
+	}
+
+	fn is_identity(&self) -> bool {
+		IsIdentity::is_identity(self)
+	}
+
+	fn generator() -> Self {
+		curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT
+	}
+
+	fn hash_to_group(bytes: &[u8]) -> Self {
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(bytes)
+	}
+
+	fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[Self]) -> Self {
+		<RistrettoPoint as VartimeMultiscalarMul>::vartime_multiscalar_mul(scalars, points)
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		self.compress().as_bytes().to_vec()
+	}
+
+	fn from_bytes(bytes: &[u8]) -> Option<Self> {
+		if bytes.len() != 32 {
+			return None;
+		}
+		CompressedRistretto(read32(bytes)).decompress()
+	}
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+	use super::*;
+
+	use rand::thread_rng;
+
+	/// Checks the group laws the proofs rely on, for any backend.
+	fn check_group<G: Group>() {
+		let mut rng = thread_rng();
+		let (a, b) = (G::Scalar::random(&mut rng), G::Scalar::random(&mut rng));
+		let (P, Q) = (G::hash_to_group(b"P"), G::hash_to_group(b"Q"));
+
+		assert_eq!(a * a.invert(), G::Scalar::one());
+		assert_eq!(G::Scalar::zero().invert(), G::Scalar::zero());
+		assert_eq!(G::Scalar::from(2) + G::Scalar::from(3), G::Scalar::from(5));
+		assert_eq!(G::Scalar::from_bytes(&a.to_bytes()), Some(a));
+		assert_eq!(a.to_bytes().len(), G::Scalar::ENCODED_LEN);
+
+		assert!(G::identity().is_identity());
+		assert!(!G::generator().is_identity());
+		assert_eq!(P * a + Q * b, G::vartime_multiscalar_mul(&[a, b], &[P, Q]));
+		assert_eq!(P + -P, G::identity());
+		assert_eq!(G::from_bytes(&P.to_bytes()), Some(P));
+		assert_eq!(P.to_bytes().len(), G::ENCODED_LEN);
+		assert_ne!(P, Q);
+	}
+
+	#[test]
+	fn ristretto_group() {
+		check_group::<RistrettoPoint>();
+	}
+}
@@ -11,10 +11,41 @@ use curve25519_dalek::{
 	scalar::Scalar,
 	traits::VartimeMultiscalarMul,
 };
-use merlin::Transcript;
-
-use crate::{errors::ProofError, transcript::TranscriptProtocol};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+	errors::ProofError,
+	group::ScalarField,
+	transcript::{ProofTranscript, TranscriptProtocol},
+	util,
+};
 
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A proof that the prover knows vectors \\(\mathbf{a}, \mathbf{b}\\)
+/// such that \\(P = \langle \mathbf{a}, \mathbf{G'} \rangle + \langle
+/// \mathbf{b}, \mathbf{H'} \rangle + \langle \mathbf{a}, \mathbf{b}
+/// \rangle Q\\), using \\(2 \lg n\\) points and two scalars for
+/// vectors of length \\(n\\).
+///
+/// This is the inner-product argument used by the range proofs and
+/// constraint system proofs of this crate.  It can be used directly by
+/// protocols that set up \\(P\\) themselves: a proof is created with
+/// [`InnerProductProof::create`] and checked with
+/// [`InnerProductProof::verify`], or its checks can be folded into a
+/// larger multiscalar multiplication using
+/// [`InnerProductProof::verification_scalars`].
+///
+/// The proof is not zero-knowledge: it reveals information about
+/// \\(\mathbf{a}\\) and \\(\mathbf{b}\\), which the calling protocol
+/// must blind if they are secret.
 #[derive(Clone, Debug)]
 pub struct InnerProductProof {
 	pub(crate) L_vec: Vec<CompressedRistretto>,
@@ -23,6 +54,10 @@ pub struct InnerProductProof {
 	pub(crate) b: Scalar,
 }
 
+/// The scalars \\([u\_{i}^{2}]\\), \\([u\_{i}^{-2}]\\) and \\([s\_{i}]\\)
+/// returned by [`InnerProductProof::verification_scalars`].
+pub(crate) type VerificationScalars = (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>);
+
 impl InnerProductProof {
 	/// Create an inner-product proof.
 	///
@@ -35,8 +70,14 @@ impl InnerProductProof {
 	///
 	/// The lengths of the vectors must all be the same, and must all be
 	/// either 0 or a power of 2.
+	///
+	/// # Panics
+	///
+	/// Panics if the lengths of the vectors differ or are not a power of
+	/// two.
+	#[allow(clippy::too_many_arguments)]
 	pub fn create(
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		Q: &RistrettoPoint,
 		G_factors: &[Scalar],
 		H_factors: &[Scalar],
@@ -85,7 +126,7 @@ impl InnerProductProof {
 			let c_L = inner_product(&a_L, &b_R);
 			let c_R = inner_product(&a_R, &b_L);
 
-			let L = RistrettoPoint::vartime_multiscalar_mul(
+			let L = util::vartime_multiscalar_mul(
 				a_L.iter()
 					.zip(G_factors_R.into_iter())
 					.map(|(a_L_i, g)| a_L_i * g)
@@ -95,7 +136,7 @@ impl InnerProductProof {
 			)
 			.compress();
 
-			let R = RistrettoPoint::vartime_multiscalar_mul(
+			let R = util::vartime_multiscalar_mul(
 				a_R.iter()
 					.zip(G_factors_L.into_iter())
 					.map(|(a_R_i, g)| a_R_i * g)
@@ -117,13 +158,17 @@ impl InnerProductProof {
 			for i in 0..n {
 				a_L[i] = a_L[i] * u + u_inv * a_R[i];
 				b_L[i] = b_L[i] * u_inv + u * b_R[i];
-				G_L[i] = RistrettoPoint::vartime_multiscalar_mul(&[u_inv * G_factors[i], u * G_factors[n + i]], &[
-					G_L[i], G_R[i],
-				]);
-				H_L[i] = RistrettoPoint::vartime_multiscalar_mul(&[u * H_factors[i], u_inv * H_factors[n + i]], &[
-					H_L[i], H_R[i],
-				])
 			}
+			util::fold_in_place(G_L, G_R, |i, G_L_i, G_R_i| {
+				RistrettoPoint::vartime_multiscalar_mul(&[u_inv * G_factors_L[i], u * G_factors_R[i]], &[
+					*G_L_i, *G_R_i,
+				])
+			});
+			util::fold_in_place(H_L, H_R, |i, H_L_i, H_R_i| {
+				RistrettoPoint::vartime_multiscalar_mul(&[u * H_factors_L[i], u_inv * H_factors_R[i]], &[
+					*H_L_i, *H_R_i,
+				])
+			});
 
 			a = a_L;
 			b = b_L;
@@ -141,13 +186,13 @@ impl InnerProductProof {
 			let c_L = inner_product(&a_L, &b_R);
 			let c_R = inner_product(&a_R, &b_L);
 
-			let L = RistrettoPoint::vartime_multiscalar_mul(
+			let L = util::vartime_multiscalar_mul(
 				a_L.iter().chain(b_R.iter()).chain(iter::once(&c_L)),
 				G_R.iter().chain(H_L.iter()).chain(iter::once(Q)),
 			)
 			.compress();
 
-			let R = RistrettoPoint::vartime_multiscalar_mul(
+			let R = util::vartime_multiscalar_mul(
 				a_R.iter().chain(b_L.iter()).chain(iter::once(&c_R)),
 				G_L.iter().chain(H_R.iter()).chain(iter::once(Q)),
 			)
@@ -165,9 +210,13 @@ impl InnerProductProof {
 			for i in 0..n {
 				a_L[i] = a_L[i] * u + u_inv * a_R[i];
 				b_L[i] = b_L[i] * u_inv + u * b_R[i];
-				G_L[i] = RistrettoPoint::vartime_multiscalar_mul(&[u_inv, u], &[G_L[i], G_R[i]]);
-				H_L[i] = RistrettoPoint::vartime_multiscalar_mul(&[u, u_inv], &[H_L[i], H_R[i]]);
 			}
+			util::fold_in_place(G_L, G_R, |_, G_L_i, G_R_i| {
+				RistrettoPoint::vartime_multiscalar_mul(&[u_inv, u], &[*G_L_i, *G_R_i])
+			});
+			util::fold_in_place(H_L, H_R, |_, H_L_i, H_R_i| {
+				RistrettoPoint::vartime_multiscalar_mul(&[u, u_inv], &[*H_L_i, *H_R_i])
+			});
 
 			a = a_L;
 			b = b_L;
@@ -185,15 +234,36 @@ impl InnerProductProof {
 
 	/// Computes three vectors of verification scalars \\([u\_{i}^{2}]\\),
 	/// \\([u\_{i}^{-2}]\\) and \\([s\_{i}]\\) for combined multiscalar
-	/// multiplication in a parent protocol. See [inner product protocol
-	/// notes](index.html#verification-equation) for details. The verifier must
+	/// multiplication in a parent protocol. See the inner product protocol
+	/// notes for the verification equation. The verifier must
 	/// provide the input length \\(n\\) explicitly to avoid unbounded
 	/// allocation within the inner product proof.
-	pub(crate) fn verification_scalars(
+	///
+	/// The `transcript` must be in the same state as the prover's when
+	/// it called [`InnerProductProof::create`].
+	pub fn verification_scalars(
+		&self,
+		n: usize,
+		transcript: &mut impl ProofTranscript,
+	) -> Result<VerificationScalars, ProofError> {
+		self.verification_scalars_with(n, transcript, |transcript, label, point| {
+			transcript.validate_and_append_point(label, point)
+		})
+	}
+
+	/// Computes the verification scalars as in
+	/// [`InnerProductProof::verification_scalars`], appending the
+	/// \(L\) and \(R\) points to the transcript with `append_point`.
+	pub(crate) fn verification_scalars_with<T, F>(
 		&self,
 		n: usize,
-		transcript: &mut Transcript,
-	) -> Result<(Vec<Scalar>, Vec<Scalar>, Vec<Scalar>), ProofError> {
+		transcript: &mut T,
+		mut append_point: F,
+	) -> Result<VerificationScalars, ProofError>
+	where
+		T: ProofTranscript,
+		F: FnMut(&mut T, &'static [u8], &CompressedRistretto) -> Result<(), ProofError>,
+	{
 		let lg_n = self.L_vec.len();
 		if lg_n >= 32 {
 			// 4 billion multiplications should be enough for anyone
@@ -210,8 +280,8 @@ impl InnerProductProof {
 
 		let mut challenges = Vec::with_capacity(lg_n);
 		for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
-			transcript.validate_and_append_point(b"L", L)?;
-			transcript.validate_and_append_point(b"R", R)?;
+			append_point(transcript, b"L", L)?;
+			append_point(transcript, b"R", R)?;
 			challenges.push(transcript.challenge_scalar(b"u"));
 		}
 
@@ -246,15 +316,22 @@ impl InnerProductProof {
 		Ok((challenges_sq, challenges_inv_sq, s))
 	}
 
-	/// This method is for testing that proof generation work,
-	/// but for efficiency the actual protocols would use `verification_scalars`
-	/// method to combine inner product verification with other checks
-	/// in a single multiscalar multiplication.
-	#[allow(dead_code)]
+	/// Verifies the proof for the point \\(P\\), with respect to the bases
+	/// \\(G'\_i = G\_i \cdot \texttt{G\\_factors}\_i\\),
+	/// \\(H'\_i = H\_i \cdot \texttt{H\\_factors}\_i\\) and \\(Q\\), for
+	/// vectors of length `n`.
+	///
+	/// The `transcript` must be in the same state as the prover's when
+	/// it called [`InnerProductProof::create`].
+	///
+	/// Protocols that perform other checks can instead combine them with
+	/// the inner product verification in a single multiscalar
+	/// multiplication, using [`InnerProductProof::verification_scalars`].
+	#[allow(clippy::too_many_arguments)]
 	pub fn verify<IG, IH>(
 		&self,
 		n: usize,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		G_factors: IG,
 		H_factors: IH,
 		P: &RistrettoPoint,
@@ -400,6 +477,66 @@ impl InnerProductProof {
 
 		Ok(InnerProductProof { L_vec, R_vec, a, b })
 	}
+
+	/// Returns whether the proof is in the form produced by
+	/// [`InnerProductProof::from_bytes`] on its own encoding: all of its
+	/// points decompress and its scalars are canonical.
+	///
+	/// Decompression of Ristretto points rejects non-canonical
+	/// encodings, so such a proof has a single byte encoding.
+	pub(crate) fn is_canonical(&self) -> bool {
+		self.L_vec
+			.iter()
+			.chain(self.R_vec.iter())
+			.all(|P| P.decompress().is_some())
+			&& self.a.is_canonical()
+			&& self.b.is_canonical()
+	}
+}
+
+// The SCALE encoding is the length-prefixed byte encoding from `to_bytes`.
+#[cfg(feature = "scale")]
+impl Encode for InnerProductProof {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for InnerProductProof {}
+
+#[cfg(feature = "scale")]
+impl Decode for InnerProductProof {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		InnerProductProof::from_bytes(&bytes).map_err(|_| "Invalid InnerProductProof encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for InnerProductProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for InnerProductProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid InnerProductProof")?;
+		InnerProductProof::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
 }
 
 /// Computes an inner product of two vectors
@@ -408,15 +545,23 @@ impl InnerProductProof {
 /// \cdot b\_i. \\]
 /// Panics if the lengths of \\(\mathbf{a}\\) and \\(\mathbf{b}\\) are not
 /// equal.
-pub fn inner_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
-	let mut out = Scalar::zero();
+///
+/// The vectors can be over any [`ScalarField`].
+pub fn inner_product<F: ScalarField>(a: &[F], b: &[F]) -> F {
 	if a.len() != b.len() {
 		panic!("inner_product(a,b): lengths of vectors do not match");
 	}
-	for i in 0..a.len() {
-		out += a[i] * b[i];
-	}
-	out
+
+	#[cfg(not(feature = "rayon"))]
+	return a.iter().zip(b.iter()).map(|(a_i, b_i)| *a_i * *b_i).sum();
+
+	#[cfg(feature = "rayon")]
+	return a
+		.par_iter()
+		.zip(b.par_iter())
+		.with_min_len(64)
+		.map(|(a_i, b_i)| *a_i * *b_i)
+		.sum();
 }
 
 #[cfg(test)]
@@ -424,6 +569,7 @@ mod tests {
 	use super::*;
 
 	use crate::util;
+	use merlin::Transcript;
 	use sha3::Sha3_512;
 
 	fn test_helper_create(n: usize) {
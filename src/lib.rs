@@ -3,11 +3,17 @@
 //#![deny(missing_docs)]
 #![doc(include = "../README.md")]
 #![doc(html_logo_url = "https://doc.dalek.rs/assets/dalek-logo-clear.png")]
-#![doc(html_root_url = "https://docs.rs/bulletproofs/2.0.0")]
+#![doc(html_root_url = "https://docs.rs/bulletproofs/3.0.0")]
 
+#[cfg(feature = "alloc")]
 #[macro_use]
 extern crate alloc;
 
+#[cfg(all(feature = "serde", not(feature = "alloc")))]
+compile_error!("the `serde` feature requires the `alloc` feature");
+#[cfg(all(feature = "rayon", not(feature = "alloc")))]
+compile_error!("the `rayon` feature requires the `alloc` feature");
+
 mod util;
 
 #[doc(include = "../docs/notes-intro.md")]
@@ -20,25 +26,86 @@ mod notes {
 	mod r1cs_proof {}
 }
 
+#[cfg(feature = "alloc")]
+mod commitment;
+#[cfg(feature = "alloc")]
+mod convert;
+#[cfg(feature = "alloc")]
+mod elgamal;
 mod errors;
 mod generators;
+#[cfg(feature = "alloc")]
+pub mod group;
+#[cfg(feature = "alloc")]
 mod inner_product_proof;
+#[cfg(feature = "alloc")]
+pub mod msm;
+#[cfg(feature = "alloc")]
+mod poly_commit;
 mod range_proof;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "alloc")]
+mod transaction;
 mod transcript;
+#[cfg(feature = "alloc")]
+mod vector_commitment;
 
+#[cfg(feature = "static-gens")]
+pub use crate::generators::{
+	static_bp_gens, static_pc_gens, STATIC_GENS_CAPACITY, STATIC_GENS_CAPACITY_VAR, STATIC_PARTY_CAPACITY,
+	STATIC_PARTY_CAPACITY_VAR,
+};
+#[cfg(feature = "evm-compat")]
+pub use crate::range_proof::EvmTranscript;
+#[cfg(feature = "static-gens")]
+pub use crate::range_proof::{verify_range_proof_raw, RAW_TRANSCRIPT_LABEL};
+#[cfg(feature = "alloc")]
+pub use crate::{
+	commitment::Commitment,
+	convert::{
+		commitment_from_hex, scalar_from_bytes_mod_order_wide, scalar_from_decimal_str, scalar_from_hex,
+		scalar_from_i64, scalar_from_u64,
+	},
+	elgamal::{ElGamalCiphertext, ElGamalPublicKey},
+	generators::{BulletproofGens, BulletproofGensShare, VerificationKey, MAX_DESERIALIZED_GENERATORS},
+	inner_product_proof::{inner_product, InnerProductProof},
+	poly_commit::{PolyCommitment, PolyOpeningProof},
+	range_proof::{RangeProof, U128RangeProof, VerifierBuilder},
+	transaction::ConfidentialTx,
+	vector_commitment::{VectorCommitment, VectorLinkProof},
+};
 pub use crate::{
 	errors::ProofError,
-	generators::{BulletproofGens, BulletproofGensShare, PedersenGens},
-	range_proof::RangeProof,
+	generators::{PedersenGens, PedersenGensPrecomputed},
+	range_proof::verify_single_64_from_bytes_with_rng,
+	transcript::{HashTranscript, ProofTranscript, TranscriptProtocol},
+};
+pub use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
 };
 
+#[cfg(feature = "alloc")]
 #[doc(include = "../docs/aggregation-api.md")]
 pub mod range_proof_mpc {
 	pub use crate::{
-		errors::MPCError,
-		range_proof::{dealer, messages, party},
+		errors::{MPCError, ShareError},
+		range_proof::{dealer, messages, party, session},
 	};
 }
 
 #[cfg(feature = "yoloproofs")]
 pub mod r1cs;
+
+#[cfg(feature = "transcript-debug")]
+pub mod transcript_debug;
+
+#[cfg(feature = "cffi")]
+pub mod ffi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "bench-utils")]
+pub mod bench_utils;
@@ -0,0 +1,190 @@
+//! Backends for the variable-time multiscalar multiplications of proof
+//! verification.
+//!
+//! Verifying a proof ends with a single large multiscalar multiplication,
+//! which dominates its cost.  The [`MsmBackend`] trait lets integrators
+//! choose how it is computed, for instance to offload it to a GPU or to a
+//! batching service, and pass it to
+//! [`RangeProof::verify_multiple_with_backend_with_rng`](crate::RangeProof::verify_multiple_with_backend_with_rng)
+//! or
+//! [`RangeProof::batch_verify_with_backend_with_rng`](crate::RangeProof::batch_verify_with_backend_with_rng).
+//!
+//! Two backends are provided: [`Vartime`], which uses the algorithm chosen
+//! by `curve25519-dalek` for the input size and is used by the other
+//! verification functions, and [`Pippenger`], with a configurable window
+//! size.
+//!
+//! Backends only ever receive public data, and do not need to run in
+//! constant time.
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+use alloc::{vec, vec::Vec};
+
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+
+use crate::util;
+
+/// A backend computing variable-time multiscalar multiplications.
+pub trait MsmBackend: Sync {
+	/// Computes \\(\sum_i s_i P_i\\).
+	///
+	/// `scalars` and `points` have the same length.
+	fn vartime_multiscalar_mul(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint;
+
+	/// Computes \\(\sum_i s_i P_i\\), or returns `None` if any of the points
+	/// is `None`.
+	fn optional_multiscalar_mul(
+		&self,
+		scalars: &[Scalar],
+		points: &[Option<RistrettoPoint>],
+	) -> Option<RistrettoPoint> {
+		let points: Vec<RistrettoPoint> = points.iter().cloned().collect::<Option<_>>()?;
+		Some(self.vartime_multiscalar_mul(scalars, &points))
+	}
+}
+
+/// The `curve25519-dalek` variable-time multiscalar multiplication, which
+/// uses Straus' method for small inputs and Pippenger's method for large
+/// ones.
+///
+/// With the `rayon` feature, the terms are split into chunks which are
+/// multiplied on separate threads.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Vartime;
+
+impl MsmBackend for Vartime {
+	fn vartime_multiscalar_mul(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+		util::vartime_multiscalar_mul(scalars, points)
+	}
+
+	fn optional_multiscalar_mul(
+		&self,
+		scalars: &[Scalar],
+		points: &[Option<RistrettoPoint>],
+	) -> Option<RistrettoPoint> {
+		util::optional_multiscalar_mul(scalars, points.iter().cloned())
+	}
+}
+
+/// Pippenger's bucket method with a fixed window of `w` bits.
+///
+/// Each of the \\(\lceil 256 / w \rceil\\) windows costs one point addition
+/// per term and about \\(2^{w+1}\\) additions to sum the buckets, so
+/// larger windows pay off for larger inputs.  `curve25519-dalek` picks a
+/// window between 6 and 8 for the sizes of typical proofs.
+#[derive(Copy, Clone, Debug)]
+pub struct Pippenger {
+	window: usize,
+}
+
+impl Pippenger {
+	/// The largest supported window size.
+	pub const MAX_WINDOW: usize = 16;
+
+	/// Creates a backend with a window of `window` bits, or returns `None`
+	/// if it is not between 1 and [`Pippenger::MAX_WINDOW`].
+	pub fn new(window: usize) -> Option<Self> {
+		if (1..=Self::MAX_WINDOW).contains(&window) {
+			Some(Pippenger { window })
+		} else {
+			None
+		}
+	}
+
+	/// Returns the window size in bits.
+	pub fn window(&self) -> usize {
+		self.window
+	}
+}
+
+impl MsmBackend for Pippenger {
+	fn vartime_multiscalar_mul(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+		assert_eq!(scalars.len(), points.len());
+		let w = self.window;
+		let scalars: Vec<[u8; 32]> = scalars.iter().map(|s| s.to_bytes()).collect();
+
+		let mut buckets = vec![RistrettoPoint::identity(); (1 << w) - 1];
+		let mut result = RistrettoPoint::identity();
+		for k in (0..(256 + w - 1) / w).rev() {
+			for _ in 0..w {
+				result += result;
+			}
+
+			for bucket in buckets.iter_mut() {
+				*bucket = RistrettoPoint::identity();
+			}
+			for (s, P) in scalars.iter().zip(points.iter()) {
+				let digit = window_digit(s, k * w, w);
+				if digit != 0 {
+					buckets[digit - 1] += P;
+				}
+			}
+
+			// Sum the buckets weighted by their digit, as
+			// \sum_d d B_d = \sum_d \sum_{e >= d} B_e.
+			let mut running = RistrettoPoint::identity();
+			let mut sum = RistrettoPoint::identity();
+			for bucket in buckets.iter().rev() {
+				running += bucket;
+				sum += running;
+			}
+			result += sum;
+		}
+		result
+	}
+}
+
+/// Returns the `w` bits of the little-endian `bytes` starting at bit
+/// `start`, for \\(w \le 16\\).
+fn window_digit(bytes: &[u8; 32], start: usize, w: usize) -> usize {
+	let mut buf = [0u8; 4];
+	let lo = start / 8;
+	let hi = core::cmp::min(lo + 3, 32);
+	buf[..hi - lo].copy_from_slice(&bytes[lo..hi]);
+	((u32::from_le_bytes(buf) >> (start % 8)) & ((1 << w) - 1)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use rand::thread_rng;
+
+	#[test]
+	fn backends_agree() {
+		let mut rng = thread_rng();
+		let scalars: Vec<Scalar> = (0..50)
+			.map(|_| Scalar::random(&mut rng))
+			.chain(vec![Scalar::zero(), -Scalar::one()])
+			.collect();
+		let points: Vec<RistrettoPoint> = (0..scalars.len()).map(|_| RistrettoPoint::random(&mut rng)).collect();
+		let expected: RistrettoPoint = scalars.iter().zip(points.iter()).map(|(s, P)| s * P).sum();
+
+		assert_eq!(Vartime.vartime_multiscalar_mul(&scalars, &points), expected);
+		for w in 1..=8 {
+			let pippenger = Pippenger::new(w).unwrap();
+			assert_eq!(pippenger.vartime_multiscalar_mul(&scalars, &points), expected);
+		}
+		assert_eq!(
+			Pippenger::new(5).unwrap().vartime_multiscalar_mul(&[], &[]),
+			RistrettoPoint::identity()
+		);
+
+		let mut optional: Vec<Option<RistrettoPoint>> = points.iter().cloned().map(Some).collect();
+		assert_eq!(
+			Pippenger::new(6).unwrap().optional_multiscalar_mul(&scalars, &optional),
+			Some(expected)
+		);
+		optional[3] = None;
+		assert_eq!(Vartime.optional_multiscalar_mul(&scalars, &optional), None);
+	}
+
+	#[test]
+	fn pippenger_window_bounds() {
+		assert!(Pippenger::new(0).is_none());
+		assert!(Pippenger::new(Pippenger::MAX_WINDOW + 1).is_none());
+		assert_eq!(Pippenger::new(Pippenger::MAX_WINDOW).unwrap().window(), 16);
+	}
+}
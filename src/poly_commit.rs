@@ -0,0 +1,337 @@
+//! The `poly_commit` module contains commitments to polynomials, which can
+//! be opened at an evaluation point with a logarithmic-size proof built on
+//! the inner-product argument.
+//!
+//! A [`PolyCommitment`] commits to the coefficients \\(\mathbf{c}\\) of
+//! \\(p(X) = \sum_i c_i X^i\\) as \\(C = \langle \mathbf{c}, \mathbf{G}
+//! \rangle\\), using the \\(\mathbf{G}\\) generators of the first party of a
+//! [`BulletproofGens`].  A [`PolyOpeningProof`] shows that \\(p(z) = v\\) by
+//! proving that \\(v\\) is the inner product of \\(\mathbf{c}\\) with the
+//! powers \\(\mathbf{z} = (1, z, z^2, \dots)\\) of the evaluation point.
+//!
+//! The commitments are binding but not hiding, and the opening proofs are
+//! not zero-knowledge: protocols that need to keep the polynomial secret
+//! must blind it themselves.
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+use merlin::Transcript;
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::{inner_product, InnerProductProof},
+	transcript::TranscriptProtocol,
+	util,
+};
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A commitment \\(\langle \mathbf{c}, \mathbf{G} \rangle\\) to the
+/// coefficients \\(\mathbf{c}\\) of a polynomial, in compressed form.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PolyCommitment(pub CompressedRistretto);
+
+impl PolyCommitment {
+	/// Commits to the polynomial with the given `coefficients`, starting
+	/// with the constant term.
+	///
+	/// Returns an error if `bp_gens` has fewer than `coefficients.len()`
+	/// generators per party.
+	pub fn commit(bp_gens: &BulletproofGens, coefficients: &[Scalar]) -> Result<Self, ProofError> {
+		if bp_gens.gens_capacity < coefficients.len() {
+			return Err(ProofError::InvalidGeneratorsLength);
+		}
+		Ok(PolyCommitment(commit_coefficients(bp_gens, coefficients).compress()))
+	}
+}
+
+/// A proof that the polynomial committed to by a [`PolyCommitment`]
+/// evaluates to a value \\(v\\) at a point \\(z\\).
+///
+/// The coefficients are padded with zeros to a power-of-two length
+/// \\(n\\), and the proof consists of \\(2 \lg n + 2\\) elements.
+#[derive(Clone, Debug)]
+pub struct PolyOpeningProof {
+	ipp_proof: InnerProductProof,
+}
+
+impl PolyOpeningProof {
+	/// Evaluates the polynomial with the given `coefficients` at `z`, and
+	/// proves that the polynomial committed to by
+	/// [`PolyCommitment::commit`] has this value at `z`.
+	///
+	/// Returns the proof and the value of the polynomial at `z`.
+	pub fn create(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		coefficients: &[Scalar],
+		z: &Scalar,
+	) -> Result<(PolyOpeningProof, Scalar), ProofError> {
+		let n = coefficients.len().next_power_of_two();
+		if bp_gens.gens_capacity < n {
+			return Err(ProofError::InvalidGeneratorsLength);
+		}
+
+		let c_vec: Vec<Scalar> = coefficients
+			.iter()
+			.cloned()
+			.chain(iter::repeat(Scalar::zero()))
+			.take(n)
+			.collect();
+		let z_vec: Vec<Scalar> = util::exp_iter(*z).take(n).collect();
+		let v = inner_product(&c_vec, &z_vec);
+
+		let C = commit_coefficients(bp_gens, coefficients).compress();
+		let Q = opening_base(pc_gens, transcript, n, &C, z, &v);
+
+		let gens = bp_gens.share(0);
+		let ones: Vec<Scalar> = iter::repeat(Scalar::one()).take(n).collect();
+		let ipp_proof = InnerProductProof::create(
+			transcript,
+			&Q,
+			&ones,
+			&ones,
+			gens.G(n).cloned().collect(),
+			gens.H(n).cloned().collect(),
+			c_vec,
+			z_vec,
+		);
+
+		Ok((PolyOpeningProof { ipp_proof }, v))
+	}
+
+	/// Verifies that the polynomial committed to by `commitment` evaluates
+	/// to `v` at `z`.
+	pub fn verify(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		commitment: &PolyCommitment,
+		z: &Scalar,
+		v: &Scalar,
+	) -> Result<(), ProofError> {
+		let lg_n = self.ipp_proof.L_vec.len();
+		if lg_n >= 32 {
+			return Err(ProofError::VerificationError);
+		}
+		let n = 1 << lg_n;
+		if bp_gens.gens_capacity < n {
+			return Err(ProofError::InvalidGeneratorsLength);
+		}
+
+		let C = commitment.0.decompress().ok_or(ProofError::VerificationError)?;
+		let Q = opening_base(pc_gens, transcript, n, &commitment.0, z, v);
+
+		// P = C + <z, H> + v Q
+		let gens = bp_gens.share(0);
+		let H: Vec<RistrettoPoint> = gens.H(n).cloned().collect();
+		let P = C + util::multiscalar_mul(util::exp_iter(*z).take(n), H.iter()) + v * Q;
+
+		let G: Vec<RistrettoPoint> = gens.G(n).cloned().collect();
+		self.ipp_proof.verify(
+			n,
+			transcript,
+			iter::repeat(Scalar::one()).take(n),
+			iter::repeat(Scalar::one()).take(n),
+			&P,
+			&Q,
+			&G,
+			&H,
+		)
+	}
+
+	/// Returns the size in bytes required to serialize the proof.
+	pub fn serialized_size(&self) -> usize {
+		self.ipp_proof.serialized_size()
+	}
+
+	/// Serializes the proof into a byte array of \\(2 \lg n + 2\\) 32-byte
+	/// elements, the encoding of its [`InnerProductProof`].
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.ipp_proof.to_bytes()
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `PolyOpeningProof`.
+	pub fn from_bytes(slice: &[u8]) -> Result<PolyOpeningProof, ProofError> {
+		Ok(PolyOpeningProof {
+			ipp_proof: InnerProductProof::from_bytes(slice)?,
+		})
+	}
+}
+
+/// Computes \\(\langle \mathbf{c}, \mathbf{G} \rangle\\) over the
+/// generators of the first party.
+fn commit_coefficients(bp_gens: &BulletproofGens, coefficients: &[Scalar]) -> RistrettoPoint {
+	util::multiscalar_mul(coefficients.iter(), bp_gens.share(0).G(coefficients.len()))
+}
+
+/// Binds the statement to the transcript and derives the base \\(Q\\) that
+/// the inner-product argument uses for the evaluation.
+fn opening_base(
+	pc_gens: &PedersenGens,
+	transcript: &mut Transcript,
+	n: usize,
+	C: &CompressedRistretto,
+	z: &Scalar,
+	v: &Scalar,
+) -> RistrettoPoint {
+	transcript.poly_commit_domain_sep(n as u64);
+	transcript.append_point(b"C", C);
+	transcript.append_scalar(b"z", z);
+	transcript.append_scalar(b"v", v);
+	transcript.challenge_scalar(b"w") * pc_gens.B
+}
+
+// The SCALE encoding is the length-prefixed byte encoding from `to_bytes`.
+#[cfg(feature = "scale")]
+impl Encode for PolyOpeningProof {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for PolyOpeningProof {}
+
+#[cfg(feature = "scale")]
+impl Decode for PolyOpeningProof {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		PolyOpeningProof::from_bytes(&bytes).map_err(|_| "Invalid PolyOpeningProof encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for PolyOpeningProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for PolyOpeningProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid PolyOpeningProof")?;
+		PolyOpeningProof::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scalars(values: &[u64]) -> Vec<Scalar> {
+		values.iter().map(|v| Scalar::from(*v)).collect()
+	}
+
+	#[test]
+	fn open_and_verify() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let z = Scalar::from(3u64);
+
+		// p(X) = 2 + 5X + 7X^3 + X^4
+		let coefficients = scalars(&[2, 5, 0, 7, 1]);
+		let C = PolyCommitment::commit(&bp_gens, &coefficients).unwrap();
+		let (proof, v) = PolyOpeningProof::create(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"PolyCommitTest"),
+			&coefficients,
+			&z,
+		)
+		.unwrap();
+		assert_eq!(v, Scalar::from(2u64 + 5 * 3 + 7 * 27 + 81));
+		assert_eq!(proof.serialized_size(), (2 * 3 + 2) * 32);
+
+		let verify = |proof: &PolyOpeningProof, C: &PolyCommitment, z: &Scalar, v: &Scalar| {
+			proof.verify(&bp_gens, &pc_gens, &mut Transcript::new(b"PolyCommitTest"), C, z, v)
+		};
+		assert!(verify(&proof, &C, &z, &v).is_ok());
+		assert!(verify(&proof, &C, &z, &(v + Scalar::one())).is_err());
+		assert!(verify(&proof, &C, &Scalar::from(4u64), &v).is_err());
+		let other = PolyCommitment::commit(&bp_gens, &scalars(&[2, 5, 0, 7, 2])).unwrap();
+		assert!(verify(&proof, &other, &z, &v).is_err());
+
+		let proof = PolyOpeningProof::from_bytes(&proof.to_bytes()).unwrap();
+		assert!(verify(&proof, &C, &z, &v).is_ok());
+	}
+
+	#[test]
+	fn constant_and_empty_polynomials() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(1, 1);
+		let z = Scalar::from(9u64);
+
+		for coefficients in &[scalars(&[6]), Vec::new()] {
+			let C = PolyCommitment::commit(&bp_gens, coefficients).unwrap();
+			let (proof, v) = PolyOpeningProof::create(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"PolyCommitTest"),
+				coefficients,
+				&z,
+			)
+			.unwrap();
+			assert_eq!(v, coefficients.get(0).cloned().unwrap_or_else(Scalar::zero));
+			assert!(proof
+				.verify(&bp_gens, &pc_gens, &mut Transcript::new(b"PolyCommitTest"), &C, &z, &v)
+				.is_ok());
+		}
+	}
+
+	#[test]
+	fn too_few_generators() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(4, 1);
+		let coefficients = scalars(&[1, 2, 3, 4, 5]);
+
+		assert_eq!(
+			PolyCommitment::commit(&bp_gens, &coefficients),
+			Err(ProofError::InvalidGeneratorsLength)
+		);
+		assert_eq!(
+			PolyOpeningProof::create(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"PolyCommitTest"),
+				&coefficients,
+				&Scalar::one()
+			)
+			.unwrap_err(),
+			ProofError::InvalidGeneratorsLength
+		);
+	}
+}
@@ -0,0 +1,498 @@
+#![allow(non_snake_case)]
+
+//! Definition of the chunked proof struct, a proof of a constraint system
+//! split into segments proven separately.
+
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{convert::TryInto, ops::Range};
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+
+use super::{Circuit, LinearCombination, R1CSError, R1CSProof, Variable, Witness};
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A proof of a constraint system split into segments, created by
+/// [`Prover::prove_chunked`](::r1cs::Prover::prove_chunked) and verified by
+/// [`Verifier::verify_chunked`](::r1cs::Verifier::verify_chunked).
+///
+/// The multipliers are split in allocation order into segments of a fixed
+/// size, and each constraint goes to the segment of the last multiplier it
+/// refers to.  A multiplier wire that a constraint of a later segment refers
+/// to is *linked*: the prover commits to its value with a fresh Pedersen
+/// commitment \\(W\\), which both segments take as a committed variable.
+/// The segment of the multiplier constrains \\(W\\) to the wire, and the
+/// other segments use \\(W\\) in its place.
+///
+/// Each segment is proven with its own [`R1CSProof`], on generators of the
+/// size of a segment, so the prover never holds the vectors of the whole
+/// constraint system at once.  The price is a proof per segment and a
+/// commitment per linked wire, which the `ChunkedProof` holds.
+#[derive(Clone, Debug)]
+pub struct ChunkedProof {
+	pub(super) links: Vec<CompressedRistretto>,
+	pub(super) segments: Vec<R1CSProof>,
+}
+
+impl ChunkedProof {
+	/// Returns the commitments to the multiplier wires shared between
+	/// segments.
+	pub fn links(&self) -> &[CompressedRistretto] {
+		&self.links
+	}
+
+	/// Returns the proofs of the segments, in order.
+	pub fn segments(&self) -> &[R1CSProof] {
+		&self.segments
+	}
+
+	/// Returns the size in bytes required to serialize the `ChunkedProof`.
+	pub fn serialized_size(&self) -> usize {
+		let segments = self.segments.iter().map(|proof| 8 + proof.serialized_size());
+		2 * 8 + 32 * self.links.len() + segments.sum::<usize>()
+	}
+
+	/// Serializes the proof into a byte array.
+	///
+	/// # Layout
+	///
+	/// All integers are 64-bit little-endian.  The layout of the chunked
+	/// proof encoding is:
+	///
+	/// * the number of links, followed by the compressed Ristretto points
+	///   committing to them,
+	/// * the number of segments, followed by, for each segment, the length of
+	///   the encoding of its [`R1CSProof`] and the encoding itself.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(self.serialized_size());
+		buf.extend_from_slice(&(self.links.len() as u64).to_le_bytes());
+		for W in self.links.iter() {
+			buf.extend_from_slice(W.as_bytes());
+		}
+		buf.extend_from_slice(&(self.segments.len() as u64).to_le_bytes());
+		for proof in self.segments.iter() {
+			let bytes = proof.to_bytes();
+			buf.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+			buf.extend_from_slice(&bytes);
+		}
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `ChunkedProof`.
+	pub fn from_bytes(slice: &[u8]) -> Result<ChunkedProof, R1CSError> {
+		let mut rest = slice;
+		let num_links = read_len(&mut rest)?;
+		if rest.len() < 32 * num_links {
+			return Err(R1CSError::FormatError);
+		}
+		let (links, tail) = rest.split_at(32 * num_links);
+		let links = links.chunks(32).map(CompressedRistretto::from_slice).collect();
+		rest = tail;
+
+		let num_segments = read_len(&mut rest)?;
+		let mut segments = Vec::with_capacity(num_segments.min(rest.len() / 8));
+		for _ in 0..num_segments {
+			let len = read_len(&mut rest)?;
+			let (proof, tail) = rest.split_at(len);
+			segments.push(R1CSProof::from_bytes(proof)?);
+			rest = tail;
+		}
+		if !rest.is_empty() {
+			return Err(R1CSError::FormatError);
+		}
+
+		Ok(ChunkedProof { links, segments })
+	}
+}
+
+/// Reads a 64-bit little-endian length from the front of `rest`, which must
+/// not be longer than what follows it.
+fn read_len(rest: &mut &[u8]) -> Result<usize, R1CSError> {
+	if rest.len() < 8 {
+		return Err(R1CSError::FormatError);
+	}
+	let (head, tail) = rest.split_at(8);
+	let value = u64::from_le_bytes(head.try_into().map_err(|_| R1CSError::FormatError)?);
+	if value > tail.len() as u64 {
+		return Err(R1CSError::FormatError);
+	}
+	*rest = tail;
+	Ok(value as usize)
+}
+
+/// A segment of a chunked constraint system.
+pub(super) struct Segment {
+	/// The multipliers of the constraint system in the segment.
+	multipliers: Range<usize>,
+	/// The committed variables of the constraint system the segment refers
+	/// to, which are its first committed variables.
+	committed: Vec<usize>,
+	/// The links the segment refers to, which are its committed variables
+	/// after those of `committed`.
+	links: Vec<usize>,
+	/// The circuit of the segment.
+	circuit: Circuit,
+}
+
+impl Segment {
+	/// Returns the circuit of the segment.
+	pub(super) fn circuit(&self) -> &Circuit {
+		&self.circuit
+	}
+
+	/// Returns the commitments to the committed variables of the segment,
+	/// given the commitments `V` of the constraint system and the
+	/// commitments `W` to the links.
+	pub(super) fn commitments(&self, V: &[CompressedRistretto], W: &[CompressedRistretto]) -> Vec<CompressedRistretto> {
+		let committed = self.committed.iter().map(|&i| V[i]);
+		committed.chain(self.links.iter().map(|&j| W[j])).collect()
+	}
+
+	/// Returns the witness of the segment, given the `witness` of the
+	/// constraint system and the values and blinding factors of the links.
+	pub(super) fn witness(&self, witness: &Witness, link_values: &[Scalar], link_blindings: &[Scalar]) -> Witness {
+		let select = |committed: &[Scalar], links: &[Scalar]| -> Vec<Scalar> {
+			let committed = self.committed.iter().map(|&i| committed[i]);
+			committed.chain(self.links.iter().map(|&j| links[j])).collect()
+		};
+		Witness {
+			v: select(&witness.v, link_values),
+			v_blinding: select(&witness.v_blinding, link_blindings),
+			public: witness.public.clone(),
+			a_L: witness.a_L[self.multipliers.clone()].to_vec(),
+			a_R: witness.a_R[self.multipliers.clone()].to_vec(),
+			a_O: witness.a_O[self.multipliers.clone()].to_vec(),
+		}
+	}
+}
+
+/// The split of a constraint system into segments.
+pub(super) struct ChunkPlan {
+	/// The linked multiplier wires, in order.
+	pub(super) links: Vec<Variable>,
+	pub(super) segments: Vec<Segment>,
+}
+
+impl ChunkPlan {
+	/// Splits `circuit` into segments of `chunk_size` multipliers, the last
+	/// of which may be smaller.
+	///
+	/// Returns [`R1CSError::InvalidGeneratorsLength`] if `chunk_size` is
+	/// zero, and [`R1CSError::WrongNumCommitments`] if the circuit has
+	/// vector-committed variables.
+	pub(super) fn new(circuit: &Circuit, chunk_size: usize) -> Result<Self, R1CSError> {
+		if chunk_size == 0 {
+			return Err(R1CSError::InvalidGeneratorsLength);
+		}
+		if circuit.vector_committed_variables != 0 {
+			return Err(R1CSError::WrongNumCommitments {
+				expected: circuit.committed_variables + circuit.vector_committed_variables,
+				actual: circuit.committed_variables,
+			});
+		}
+		let num_segments = ((circuit.multipliers + chunk_size - 1) / chunk_size).max(1);
+		let segment_of = |i: usize| i / chunk_size;
+
+		// Each constraint goes to the segment of its last multiplier, and
+		// the wires of earlier segments it refers to are linked.
+		let mut assigned = Vec::with_capacity(circuit.constraints.len());
+		let mut links = BTreeSet::new();
+		for lc in circuit.constraints.iter() {
+			let segment = lc
+				.terms
+				.iter()
+				.filter_map(|(var, _)| multiplier_index(var))
+				.map(segment_of)
+				.max()
+				.unwrap_or(0);
+			for (var, _) in lc.terms.iter() {
+				if multiplier_index(var).is_some_and(|i| segment_of(i) != segment) {
+					links.insert(*var);
+				}
+			}
+			assigned.push(segment);
+		}
+		let links: Vec<Variable> = links.into_iter().collect();
+
+		let segments = (0..num_segments)
+			.map(|segment| {
+				let multipliers = segment * chunk_size..((segment + 1) * chunk_size).min(circuit.multipliers);
+				let constraints = circuit
+					.constraints
+					.iter()
+					.zip(assigned.iter())
+					.filter(|(_, s)| **s == segment)
+					.map(|(lc, _)| lc);
+
+				let mut committed = BTreeSet::new();
+				let mut used_links = BTreeSet::new();
+				for (j, var) in links.iter().enumerate() {
+					if multiplier_index(var).is_some_and(|i| multipliers.contains(&i)) {
+						used_links.insert(j);
+					}
+				}
+				for (var, _) in constraints.clone().flat_map(|lc| lc.terms.iter()) {
+					match (*var, multiplier_index(var)) {
+						(Variable::Committed(i), _) => {
+							committed.insert(i);
+						}
+						(var, Some(i)) if !multipliers.contains(&i) => {
+							used_links.insert(position(&links, &var));
+						}
+						_ => {}
+					}
+				}
+				let committed: Vec<usize> = committed.into_iter().collect();
+				let used_links: Vec<usize> = used_links.into_iter().collect();
+
+				let start = multipliers.start;
+				let local = |var: Variable| -> Variable {
+					match (var, multiplier_index(&var)) {
+						(Variable::Committed(i), _) => Variable::Committed(position(&committed, &i)),
+						(Variable::MultiplierLeft(i), _) if multipliers.contains(&i) => {
+							Variable::MultiplierLeft(i - start)
+						}
+						(Variable::MultiplierRight(i), _) if multipliers.contains(&i) => {
+							Variable::MultiplierRight(i - start)
+						}
+						(Variable::MultiplierOutput(i), _) if multipliers.contains(&i) => {
+							Variable::MultiplierOutput(i - start)
+						}
+						(var, Some(_)) => {
+							let j = position(&links, &var);
+							Variable::Committed(committed.len() + position(&used_links, &j))
+						}
+						(var, None) => var,
+					}
+				};
+				let mut segment_constraints: Vec<LinearCombination> = constraints
+					.map(|lc| lc.terms.iter().map(|(var, coeff)| (local(*var), *coeff)).collect())
+					.collect();
+				// The segment owning a linked wire constrains its commitment
+				// to the wire.
+				for (k, j) in used_links.iter().enumerate() {
+					if multiplier_index(&links[*j]).is_some_and(|i| multipliers.contains(&i)) {
+						let W = Variable::Committed(committed.len() + k);
+						segment_constraints.push(W - local(links[*j]));
+					}
+				}
+
+				let circuit = Circuit::new(
+					committed.len() + used_links.len(),
+					0,
+					circuit.public_inputs,
+					multipliers.len(),
+					&segment_constraints,
+				);
+				Segment {
+					multipliers,
+					committed,
+					links: used_links,
+					circuit,
+				}
+			})
+			.collect();
+
+		Ok(ChunkPlan { links, segments })
+	}
+}
+
+/// Returns the index of the multiplier of `var`, if it is a multiplier wire.
+fn multiplier_index(var: &Variable) -> Option<usize> {
+	match *var {
+		Variable::MultiplierLeft(i) | Variable::MultiplierRight(i) | Variable::MultiplierOutput(i) => Some(i),
+		_ => None,
+	}
+}
+
+/// Returns the position of `x` in the sorted `items`, which contain it.
+fn position<T: Ord>(items: &[T], x: &T) -> usize {
+	items.binary_search(x).unwrap_or_else(|i| i)
+}
+
+/// Returns the value of the multiplier wire `var` in `witness`.
+pub(super) fn wire_value(witness: &Witness, var: &Variable) -> Scalar {
+	match *var {
+		Variable::MultiplierLeft(i) => witness.a_L[i],
+		Variable::MultiplierRight(i) => witness.a_R[i],
+		Variable::MultiplierOutput(i) => witness.a_O[i],
+		_ => Scalar::zero(),
+	}
+}
+
+#[cfg(feature = "scale")]
+impl Encode for ChunkedProof {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for ChunkedProof {}
+
+#[cfg(feature = "scale")]
+impl Decode for ChunkedProof {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		ChunkedProof::from_bytes(&bytes).map_err(|_| "Invalid ChunkedProof encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ChunkedProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ChunkedProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid ChunkedProof")?;
+		ChunkedProof::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		generators::{BulletproofGens, PedersenGens},
+		r1cs::{ConstraintSystem, Prover, Verifier},
+	};
+	use merlin::Transcript;
+
+	/// Constrains `power` to be `base` to the `exp`, with `exp - 1`
+	/// multipliers in a chain.
+	fn power_gadget<CS: ConstraintSystem>(cs: &mut CS, base: Variable, exp: usize, power: Variable) {
+		let mut acc: LinearCombination = base.into();
+		for _ in 1..exp {
+			let (_, _, o) = cs.multiply(acc, base.into());
+			acc = o.into();
+		}
+		cs.constrain(acc - power);
+	}
+
+	fn prove(exp: usize, chunk_size: usize) -> (CompressedRistretto, ChunkedProof) {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let mut transcript = Transcript::new(b"ChunkedTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+		let (V, base) = prover.commit(Scalar::from(3u64), Scalar::from(7u64));
+		let power = prover.allocate_public(Scalar::from(3u64.pow(exp as u32)));
+		power_gadget(&mut prover, base, exp, power);
+		(V, prover.prove_chunked(&bp_gens, chunk_size).unwrap())
+	}
+
+	fn verify(
+		exp: usize,
+		power: u64,
+		V: CompressedRistretto,
+		proof: &ChunkedProof,
+		chunk_size: usize,
+	) -> Result<(), R1CSError> {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let mut transcript = Transcript::new(b"ChunkedTest");
+		let mut verifier = Verifier::new(&mut transcript);
+
+		let base = verifier.commit(V);
+		let power = verifier.allocate_public(Scalar::from(power));
+		power_gadget(&mut verifier, base, exp, power);
+		verifier.verify_chunked(proof, &pc_gens, &bp_gens, chunk_size)
+	}
+
+	#[test]
+	fn chunked_proof_roundtrip() {
+		let (V, proof) = prove(20, 8);
+		assert_eq!(proof.segments().len(), 3);
+		// The output of the last multiplier of each of the first two
+		// segments feeds the next segment.
+		assert_eq!(proof.links().len(), 2);
+		assert!(verify(20, 3u64.pow(20), V, &proof, 8).is_ok());
+
+		let bytes = proof.to_bytes();
+		assert_eq!(bytes.len(), proof.serialized_size());
+		let decoded = ChunkedProof::from_bytes(&bytes).unwrap();
+		assert_eq!(decoded.to_bytes(), bytes);
+		assert!(verify(20, 3u64.pow(20), V, &decoded, 8).is_ok());
+	}
+
+	#[test]
+	fn single_chunk_has_no_links() {
+		let (V, proof) = prove(8, 8);
+		assert_eq!(proof.segments().len(), 1);
+		assert!(proof.links().is_empty());
+		assert!(verify(8, 3u64.pow(8), V, &proof, 8).is_ok());
+	}
+
+	#[test]
+	fn chunked_proof_rejects_tampering() {
+		let (V, proof) = prove(20, 8);
+		assert!(verify(20, 3u64.pow(20) + 1, V, &proof, 8).is_err());
+		assert!(verify(20, 3u64.pow(20), V, &proof, 4).is_err());
+
+		let mut tampered = proof.clone();
+		tampered.links.swap(0, 1);
+		assert!(verify(20, 3u64.pow(20), V, &tampered, 8).is_err());
+
+		let mut tampered = proof.clone();
+		tampered.segments.swap(0, 1);
+		assert!(verify(20, 3u64.pow(20), V, &tampered, 8).is_err());
+
+		let mut tampered = proof;
+		tampered.segments.pop();
+		assert_eq!(
+			verify(20, 3u64.pow(20), V, &tampered, 8),
+			Err(R1CSError::VerificationError)
+		);
+	}
+
+	#[test]
+	fn chunk_size_is_checked() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		for chunk_size in [0, 16].iter() {
+			let mut transcript = Transcript::new(b"ChunkedTest");
+			let mut prover = Prover::new(&pc_gens, &mut transcript);
+			let (_, base) = prover.commit(Scalar::from(3u64), Scalar::from(7u64));
+			let power = prover.allocate_public(Scalar::from(9u64));
+			power_gadget(&mut prover, base, 2, power);
+			assert_eq!(
+				prover.prove_chunked(&bp_gens, *chunk_size).unwrap_err(),
+				R1CSError::InvalidGeneratorsLength
+			);
+		}
+	}
+
+	#[test]
+	fn from_bytes_rejects_truncation() {
+		let (_, proof) = prove(20, 8);
+		let bytes = proof.to_bytes();
+		assert!(ChunkedProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(ChunkedProof::from_bytes(&bytes[..8]).is_err());
+		let mut extended = bytes;
+		extended.push(0);
+		assert!(ChunkedProof::from_bytes(&extended).is_err());
+	}
+}
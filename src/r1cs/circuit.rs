@@ -0,0 +1,503 @@
+//! Definition of the circuit struct, a serializable description of a
+//! constraint system.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use curve25519_dalek::scalar::Scalar;
+
+use sha3::{Digest, Sha3_256};
+
+use super::{LinearCombination, R1CSError, Variable};
+use crate::util;
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+const COMMITTED: u8 = 0;
+const MULTIPLIER_LEFT: u8 = 1;
+const MULTIPLIER_RIGHT: u8 = 2;
+const MULTIPLIER_OUTPUT: u8 = 3;
+const ONE: u8 = 4;
+const VECTOR_COMMITTED: u8 = 5;
+const PUBLIC: u8 = 6;
+
+/// The shape of a constraint system: its numbers of committed variables,
+/// vector-committed variables, public inputs and multipliers, and its list
+/// of linear constraints.
+///
+/// The values of the public inputs are not part of the circuit, so the same
+/// circuit is proven and verified for any values of its public inputs.
+///
+/// A `Circuit` is extracted from a fully built
+/// [`Prover`](::r1cs::Prover) or [`Verifier`](::r1cs::Verifier) with
+/// their `circuit` methods, and a [`Verifier`](::r1cs::Verifier) can be
+/// reconstructed from it with
+/// [`Verifier::from_circuit`](::r1cs::Verifier::from_circuit) instead of
+/// re-running the gadget code.
+///
+/// Constraints added within
+/// [`specify_randomized_constraints`](::r1cs::RandomizableConstraintSystem::specify_randomized_constraints)
+/// depend on the challenges and cannot be part of a `Circuit`, and neither
+/// can data appended to the transcript by gadgets.
+///
+/// Each constraint is stored in a canonical form, with its terms sorted by
+/// variable, duplicate variables merged, and zero terms dropped, so equal
+/// circuits have equal encodings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circuit {
+	pub(super) committed_variables: usize,
+	pub(super) vector_committed_variables: usize,
+	pub(super) public_inputs: usize,
+	pub(super) multipliers: usize,
+	pub(super) constraints: Vec<LinearCombination>,
+}
+
+impl Circuit {
+	/// Creates a circuit from the constraints of a constraint system,
+	/// putting them in canonical form.
+	pub(super) fn new(
+		committed_variables: usize,
+		vector_committed_variables: usize,
+		public_inputs: usize,
+		multipliers: usize,
+		constraints: &[LinearCombination],
+	) -> Self {
+		let constraints = constraints.iter().map(canonical).collect();
+
+		Circuit {
+			committed_variables,
+			vector_committed_variables,
+			public_inputs,
+			multipliers,
+			constraints,
+		}
+	}
+
+	/// Returns the number of committed high-level variables.
+	pub fn committed_variables(&self) -> usize {
+		self.committed_variables
+	}
+
+	/// Returns the total number of entries of the committed vectors.
+	pub fn vector_committed_variables(&self) -> usize {
+		self.vector_committed_variables
+	}
+
+	/// Returns the number of public inputs.
+	pub fn public_inputs(&self) -> usize {
+		self.public_inputs
+	}
+
+	/// Returns the number of multipliers.
+	pub fn multipliers(&self) -> usize {
+		self.multipliers
+	}
+
+	/// Returns the linear constraints, each of which is enforced to be
+	/// zero.
+	pub fn constraints(&self) -> &[LinearCombination] {
+		&self.constraints
+	}
+
+	/// Returns a 32-byte identifier of the circuit: the SHA3-256 hash of
+	/// the encodings of its constraints, followed by the numbers that
+	/// start its encoding.
+	///
+	/// The constraints come first so that a constraint system can hash
+	/// them as they are added, see [`CircuitHasher`].
+	pub fn digest(&self) -> [u8; 32] {
+		let mut hasher = CircuitHasher::new();
+		for lc in self.constraints.iter() {
+			hasher.append_canonical(lc);
+		}
+		hasher.digest(
+			self.committed_variables,
+			self.vector_committed_variables,
+			self.public_inputs,
+			self.multipliers,
+		)
+	}
+
+	/// Returns the size in bytes required to serialize the `Circuit`.
+	pub fn serialized_size(&self) -> usize {
+		let terms = self
+			.constraints
+			.iter()
+			.map(|lc| lc.terms.iter().map(term_size).sum::<usize>());
+		5 * 8 + terms.map(|size| 8 + size).sum::<usize>()
+	}
+
+	/// Serializes the circuit into a byte array.
+	///
+	/// # Layout
+	///
+	/// All integers are 64-bit little-endian.  The layout of the circuit
+	/// encoding is:
+	///
+	/// * the numbers of committed variables, multipliers, constraints,
+	///   vector-committed variables and public inputs,
+	/// * for each constraint, its number of terms followed by the terms.
+	///
+	/// Each term is a tag byte for the kind of variable (committed, left,
+	/// right and output of a multiplier, the constant one, vector-committed,
+	/// and public input, in that order), the index of the variable unless it is
+	/// the constant one, and the coefficient as a 32-byte scalar.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(self.serialized_size());
+		buf.extend_from_slice(&(self.committed_variables as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.multipliers as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.constraints.len() as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.vector_committed_variables as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.public_inputs as u64).to_le_bytes());
+		for lc in self.constraints.iter() {
+			encode_constraint(lc, &mut buf);
+		}
+		buf
+	}
+
+	/// Deserializes the circuit from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a `Circuit`,
+	/// if a variable is out of range, or if a constraint is not in canonical
+	/// form.
+	pub fn from_bytes(slice: &[u8]) -> Result<Circuit, R1CSError> {
+		let mut reader = Reader(slice);
+
+		let committed_variables = reader.read_usize()?;
+		let multipliers = reader.read_usize()?;
+		let num_constraints = reader.read_usize()?;
+		let vector_committed_variables = reader.read_usize()?;
+		let public_inputs = reader.read_usize()?;
+
+		// Don't trust the lengths for allocations: each constraint takes at
+		// least 8 bytes, and each term at least 33 bytes.
+		let mut constraints = Vec::with_capacity(num_constraints.min(reader.0.len() / 8));
+		for _ in 0..num_constraints {
+			let num_terms = reader.read_usize()?;
+			let mut terms: Vec<(Variable, Scalar)> = Vec::with_capacity(num_terms.min(reader.0.len() / 33));
+			for _ in 0..num_terms {
+				let tag = reader.read(1)?[0];
+				let var = match tag {
+					ONE => Variable::One(),
+					_ => {
+						let i = reader.read_usize()?;
+						let bound = match tag {
+							COMMITTED => committed_variables,
+							VECTOR_COMMITTED => vector_committed_variables,
+							PUBLIC => public_inputs,
+							_ => multipliers,
+						};
+						if i >= bound {
+							return Err(R1CSError::FormatError);
+						}
+						match tag {
+							COMMITTED => Variable::Committed(i),
+							VECTOR_COMMITTED => Variable::VectorCommitted(i),
+							PUBLIC => Variable::Public(i),
+							MULTIPLIER_LEFT => Variable::MultiplierLeft(i),
+							MULTIPLIER_RIGHT => Variable::MultiplierRight(i),
+							MULTIPLIER_OUTPUT => Variable::MultiplierOutput(i),
+							_ => return Err(R1CSError::FormatError),
+						}
+					}
+				};
+				let coeff =
+					Scalar::from_canonical_bytes(util::read32(reader.read(32)?)).ok_or(R1CSError::FormatError)?;
+
+				let sorted = terms.last().is_none_or(|(prev, _)| *prev < var);
+				if !sorted || coeff == Scalar::zero() {
+					return Err(R1CSError::FormatError);
+				}
+				terms.push((var, coeff));
+			}
+			constraints.push(LinearCombination::from_terms(terms));
+		}
+
+		if !reader.0.is_empty() {
+			return Err(R1CSError::FormatError);
+		}
+
+		Ok(Circuit {
+			committed_variables,
+			vector_committed_variables,
+			public_inputs,
+			multipliers,
+			constraints,
+		})
+	}
+}
+
+/// A running digest of the constraints of a constraint system, which the
+/// [`Prover`](::r1cs::Prover) and [`Verifier`](::r1cs::Verifier) update as
+/// constraints are added instead of hashing the whole circuit when proving
+/// and verifying.
+///
+/// Once frozen, it ignores the constraints added later, which are the
+/// randomized ones.
+#[derive(Clone)]
+pub(super) struct CircuitHasher {
+	hasher: Sha3_256,
+	constraints: usize,
+	frozen: Option<[u8; 32]>,
+}
+
+impl CircuitHasher {
+	pub(super) fn new() -> Self {
+		let mut hasher = Sha3_256::new();
+		hasher.update(b"R1CSCircuitDigest");
+		CircuitHasher {
+			hasher,
+			constraints: 0,
+			frozen: None,
+		}
+	}
+
+	/// Hashes a constraint, putting it in canonical form first.
+	pub(super) fn append(&mut self, lc: &LinearCombination) {
+		if self.frozen.is_none() {
+			self.append_canonical(&canonical(lc));
+		}
+	}
+
+	/// Hashes a constraint that is already in canonical form, such as the
+	/// constraints of a [`Circuit`].
+	pub(super) fn append_canonical(&mut self, lc: &LinearCombination) {
+		if self.frozen.is_none() {
+			let mut buf = Vec::with_capacity(8 + lc.terms.iter().map(term_size).sum::<usize>());
+			encode_constraint(lc, &mut buf);
+			self.hasher.update(&buf);
+			self.constraints += 1;
+		}
+	}
+
+	/// Returns the [`Circuit::digest`] of the constraints hashed so far,
+	/// with the given numbers of variables, or the frozen digest.
+	pub(super) fn digest(
+		&self,
+		committed_variables: usize,
+		vector_committed_variables: usize,
+		public_inputs: usize,
+		multipliers: usize,
+	) -> [u8; 32] {
+		if let Some(digest) = self.frozen {
+			return digest;
+		}
+		let mut hasher = self.hasher.clone();
+		hasher.update(&(committed_variables as u64).to_le_bytes());
+		hasher.update(&(multipliers as u64).to_le_bytes());
+		hasher.update(&(self.constraints as u64).to_le_bytes());
+		hasher.update(&(vector_committed_variables as u64).to_le_bytes());
+		hasher.update(&(public_inputs as u64).to_le_bytes());
+		hasher.finalize().into()
+	}
+
+	/// Fixes the digest to `digest`, computed before the randomized
+	/// constraints are added.
+	pub(super) fn freeze(&mut self, digest: [u8; 32]) {
+		self.frozen = Some(digest);
+	}
+}
+
+/// Puts a constraint in canonical form, with its terms sorted by variable,
+/// duplicate variables merged, and zero terms dropped.
+fn canonical(lc: &LinearCombination) -> LinearCombination {
+	lc.clone()
+		.simplify()
+		.terms
+		.into_iter()
+		.filter(|(_, coeff)| *coeff != Scalar::zero())
+		.collect()
+}
+
+/// Appends the encoding of a constraint, its number of terms followed by
+/// the terms, to `buf`.
+fn encode_constraint(lc: &LinearCombination, buf: &mut Vec<u8>) {
+	buf.extend_from_slice(&(lc.terms.len() as u64).to_le_bytes());
+	for (var, coeff) in lc.terms.iter() {
+		let (tag, index) = match var {
+			Variable::Committed(i) => (COMMITTED, Some(*i)),
+			Variable::VectorCommitted(i) => (VECTOR_COMMITTED, Some(*i)),
+			Variable::Public(i) => (PUBLIC, Some(*i)),
+			Variable::MultiplierLeft(i) => (MULTIPLIER_LEFT, Some(*i)),
+			Variable::MultiplierRight(i) => (MULTIPLIER_RIGHT, Some(*i)),
+			Variable::MultiplierOutput(i) => (MULTIPLIER_OUTPUT, Some(*i)),
+			Variable::One() => (ONE, None),
+		};
+		buf.push(tag);
+		if let Some(i) = index {
+			buf.extend_from_slice(&(i as u64).to_le_bytes());
+		}
+		buf.extend_from_slice(coeff.as_bytes());
+	}
+}
+
+fn term_size(term: &(Variable, Scalar)) -> usize {
+	match term.0 {
+		Variable::One() => 1 + 32,
+		_ => 1 + 8 + 32,
+	}
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+	fn read(&mut self, len: usize) -> Result<&'a [u8], R1CSError> {
+		if self.0.len() < len {
+			return Err(R1CSError::FormatError);
+		}
+		let (head, tail) = self.0.split_at(len);
+		self.0 = tail;
+		Ok(head)
+	}
+
+	fn read_usize(&mut self) -> Result<usize, R1CSError> {
+		let bytes = self.read(8)?.try_into().map_err(|_| R1CSError::FormatError)?;
+		let value = u64::from_le_bytes(bytes);
+		if value > usize::max_value() as u64 {
+			return Err(R1CSError::FormatError);
+		}
+		Ok(value as usize)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl Encode for Circuit {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for Circuit {}
+
+#[cfg(feature = "scale")]
+impl Decode for Circuit {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		Circuit::from_bytes(&bytes).map_err(|_| "Invalid Circuit encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Circuit {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Circuit {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid Circuit")?;
+		Circuit::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn example_circuit() -> Circuit {
+		let a = Variable::Committed(0);
+		let (l, r, o) = (
+			Variable::MultiplierLeft(0),
+			Variable::MultiplierRight(0),
+			Variable::MultiplierOutput(0),
+		);
+		Circuit::new(1, 0, 1, 1, &[
+			l - a,
+			r - a + a - a,
+			o - Variable::Public(0) * 9u64 + LinearCombination::from(Scalar::zero()),
+			[(a, Scalar::one()), (a, -Scalar::one())].iter().collect(),
+		])
+	}
+
+	#[test]
+	fn constraints_are_canonical() {
+		let circuit = example_circuit();
+		let a = Variable::Committed(0);
+
+		assert_eq!(circuit.constraints()[1], -a + Variable::MultiplierRight(0));
+		assert_eq!(circuit.constraints()[3], LinearCombination::default());
+		assert_eq!(circuit.constraints().len(), 4);
+	}
+
+	#[test]
+	fn hasher_matches_digest() {
+		let a = Variable::Committed(0);
+		let (l, r, o) = (
+			Variable::MultiplierLeft(0),
+			Variable::MultiplierRight(0),
+			Variable::MultiplierOutput(0),
+		);
+		let mut hasher = CircuitHasher::new();
+		hasher.append(&(l - a));
+		hasher.append(&(r - a + a - a));
+		hasher.append(&(o - Variable::Public(0) * 9u64 + LinearCombination::from(Scalar::zero())));
+		hasher.append(&[(a, Scalar::one()), (a, -Scalar::one())].iter().collect());
+		let digest = hasher.digest(1, 0, 1, 1);
+		assert_eq!(digest, example_circuit().digest());
+
+		// Constraints added once frozen are ignored.
+		hasher.freeze(digest);
+		hasher.append(&(r - a));
+		assert_eq!(hasher.digest(1, 0, 1, 2), digest);
+	}
+
+	#[test]
+	fn bytes_roundtrip() {
+		let circuit = example_circuit();
+		let bytes = circuit.to_bytes();
+
+		assert_eq!(bytes.len(), circuit.serialized_size());
+		assert_eq!(Circuit::from_bytes(&bytes), Ok(circuit));
+	}
+
+	#[test]
+	fn from_bytes_rejects_malformed_input() {
+		let bytes = example_circuit().to_bytes();
+
+		// Truncated and trailing data.
+		assert!(Circuit::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(Circuit::from_bytes(&[&bytes[..], &[0u8][..]].concat()).is_err());
+
+		// A multiplier index out of range.
+		let mut bad = bytes.clone();
+		bad[8] = 0;
+		assert!(Circuit::from_bytes(&bad).is_err());
+
+		// A public input index out of range.
+		let mut bad = bytes.clone();
+		bad[4 * 8] = 0;
+		assert!(Circuit::from_bytes(&bad).is_err());
+
+		// Terms out of order: swap the two terms of the first constraint.
+		let first_term = 6 * 8;
+		let mut bad = bytes.clone();
+		bad[first_term..first_term + 2 * 41].rotate_left(41);
+		assert!(Circuit::from_bytes(&bad).is_err());
+
+		// A zero coefficient.
+		let mut bad = bytes;
+		for byte in bad[first_term + 9..first_term + 41].iter_mut() {
+			*byte = 0;
+		}
+		assert!(Circuit::from_bytes(&bad).is_err());
+	}
+}
@@ -0,0 +1,598 @@
+//! The mixer circuit: a transfer out of an anonymity set of notes.
+//!
+//! A [`Note`] holds a `value` for its `owner`, the public key of a
+//! [`SpendingKey`], and is committed to by
+//! \\[
+//! cm = H(H(owner, value), blinding)
+//! \\]
+//! for the two-to-one hash \\(H\\) of a [`TreeHasher`].  Deposits insert
+//! note commitments into a
+//! [`MerkleTree`](crate::r1cs::gadgets::merkle::MerkleTree) of fixed depth,
+//! whose notes form the anonymity set.
+//!
+//! A transfer spends one note of the tree into a new output note, a public
+//! `amount` withdrawn to a `recipient` and a public `fee`.  Its proof shows,
+//! without revealing which note is spent, that:
+//!
+//! * the input note is in the tree with the public root;
+//! * the prover knows the spending key of the owner of the input note, and the
+//!   public nullifier is the nullifier of the input note, derived from its
+//!   blinding factor as in [`nullifier`](crate::r1cs::gadgets::nullifier);
+//! * the public output commitment commits to the output note;
+//! * the transfer conserves value: the value of the input note is the value of
+//!   the output note plus the amount and the fee, and the value of the output
+//!   note has at most `value_bits` bits, so that no sum wraps around.
+//!
+//! The application checks that the root is a root of its tree, rejects a
+//! nullifier it has seen before, and inserts the output commitment into the
+//! tree.  The recipient is a public input of the proof without constraints,
+//! which binds the proof to it: a proof cannot be replayed to withdraw to
+//! another recipient.
+
+use alloc::format;
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::{
+	generators::{BulletproofGens, PedersenGens},
+	r1cs::{
+		gadgets::{
+			constrain_equal,
+			merkle::{membership_gadget, MerklePath, TreeHasher, MAX_DEPTH},
+			nullifier::{nullifier_gadget, SpendingKey},
+			range_check,
+		},
+		ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Variable, Verifier,
+	},
+	transcript::TranscriptProtocol,
+};
+
+/// A note: a value owned by a public key.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Note {
+	/// The public key of the owner, see [`SpendingKey::public_key`].
+	pub owner: Scalar,
+	/// The value of the note.
+	pub value: u64,
+	/// The blinding factor of the commitment, from which the nullifier is
+	/// derived.  It must be unique to the note.
+	pub blinding: Scalar,
+}
+
+impl Note {
+	/// Creates a note of `value` for `owner`, with a random blinding factor.
+	pub fn random<T: RngCore + CryptoRng>(owner: Scalar, value: u64, rng: &mut T) -> Self {
+		Note {
+			owner,
+			value,
+			blinding: Scalar::random(rng),
+		}
+	}
+}
+
+/// The public inputs of a transfer, which the verifier checks against the
+/// state of the application.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MixerStatement {
+	/// The root of the tree containing the input note.
+	pub root: Scalar,
+	/// The nullifier of the input note.
+	pub nullifier: Scalar,
+	/// The commitment to the output note.
+	pub output_commitment: Scalar,
+	/// The amount withdrawn to the recipient.
+	pub amount: u64,
+	/// The fee of the transfer.
+	pub fee: u64,
+	/// The recipient of the amount, e.g. a hash of its account.
+	pub recipient: Scalar,
+}
+
+/// Parameters of the mixer circuit: the hash of the notes and of the tree,
+/// the depth of the tree, and the number of bits of the values.
+#[derive(Clone, Debug)]
+pub struct MixerParams<H: TreeHasher> {
+	hasher: H,
+	depth: usize,
+	value_bits: usize,
+}
+
+impl<H: TreeHasher> MixerParams<H> {
+	/// Creates parameters for trees of the given `depth` hashed with
+	/// `hasher`, and values of `value_bits` bits.
+	///
+	/// Returns a [`R1CSError::GadgetError`] if `depth` is not in
+	/// `1..=64` or `value_bits` is not in `1..=64`.
+	pub fn new(hasher: H, depth: usize, value_bits: usize) -> Result<Self, R1CSError> {
+		if !(1..=MAX_DEPTH).contains(&depth) {
+			return Err(gadget_error("depth must satisfy 1 <= depth <= 64"));
+		}
+		if !(1..=64).contains(&value_bits) {
+			return Err(gadget_error("value bits must satisfy 1 <= n <= 64"));
+		}
+		Ok(MixerParams {
+			hasher,
+			depth,
+			value_bits,
+		})
+	}
+
+	/// Returns the hash of the notes and of the tree.
+	pub fn hasher(&self) -> &H {
+		&self.hasher
+	}
+
+	/// Returns the depth of the tree.
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	/// Returns the number of bits of the values.
+	pub fn value_bits(&self) -> usize {
+		self.value_bits
+	}
+
+	/// Returns the commitment to `note`, its leaf in the tree.
+	pub fn note_commitment(&self, note: &Note) -> Scalar {
+		let owned = self.hasher.hash_pair(note.owner, Scalar::from(note.value));
+		self.hasher.hash_pair(owned, note.blinding)
+	}
+
+	/// Proves a transfer spending the `input` note, owned by `key` and at
+	/// `path` in the tree, into the `output` note, the `amount` withdrawn to
+	/// `recipient` and the `fee`.
+	///
+	/// The circuit uses `value_bits` multipliers and `depth + 7` hash
+	/// gadgets, for which `bp_gens` must have enough generators.
+	///
+	/// Returns the proof and its statement, or a [`R1CSError::GadgetError`]
+	/// if `key` does not own the input note, if the path does not have the
+	/// depth of the tree, if the value of the output note does not fit in
+	/// `value_bits` bits, or if the transfer does not conserve value.
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		key: &SpendingKey,
+		input: &Note,
+		path: &MerklePath,
+		output: &Note,
+		amount: u64,
+		fee: u64,
+		recipient: Scalar,
+		rng: &mut T,
+	) -> Result<(R1CSProof, MixerStatement), R1CSError> {
+		if key.public_key(&self.hasher) != input.owner {
+			return Err(gadget_error("the spending key does not own the input note"));
+		}
+		if path.siblings.len() != self.depth {
+			return Err(gadget_error("path length does not match the depth"));
+		}
+		if self.value_bits < 64 && output.value >> self.value_bits != 0 {
+			return Err(gadget_error("output value does not fit in the value bits"));
+		}
+		if u128::from(input.value) != u128::from(output.value) + u128::from(amount) + u128::from(fee) {
+			return Err(gadget_error("the transfer does not conserve value"));
+		}
+
+		let statement = MixerStatement {
+			root: path.compute_root(&self.hasher, self.note_commitment(input)),
+			nullifier: key.nullifier(&self.hasher, input.blinding),
+			output_commitment: self.note_commitment(output),
+			amount,
+			fee,
+			recipient,
+		};
+
+		transcript.mixer_domain_sep(self.depth as u64, self.value_bits as u64);
+		let mut prover = Prover::new(pc_gens, transcript);
+		let public = PublicInputs::allocate(&statement, |x| prover.allocate_public(x));
+		let assignment = Assignment {
+			key,
+			input,
+			path,
+			output,
+		};
+		transfer_gadget(&mut prover, self, &public, Some(&assignment))?;
+		let proof = prover.prove_with_rng(bp_gens, rng)?;
+
+		Ok((proof, statement))
+	}
+
+	/// Proves a transfer spending the `input` note into the `output` note,
+	/// the `amount` withdrawn to `recipient` and the `fee`.
+	///
+	/// This is a convenience wrapper around [`MixerParams::prove_with_rng`],
+	/// passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		key: &SpendingKey,
+		input: &Note,
+		path: &MerklePath,
+		output: &Note,
+		amount: u64,
+		fee: u64,
+		recipient: Scalar,
+	) -> Result<(R1CSProof, MixerStatement), R1CSError> {
+		self.prove_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			key,
+			input,
+			path,
+			output,
+			amount,
+			fee,
+			recipient,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies the proof of a transfer with the given `statement`.
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		proof: &R1CSProof,
+		statement: &MixerStatement,
+		rng: &mut T,
+	) -> Result<(), R1CSError> {
+		transcript.mixer_domain_sep(self.depth as u64, self.value_bits as u64);
+		let mut verifier = Verifier::new(transcript);
+		let public = PublicInputs::allocate(statement, |x| verifier.allocate_public(x));
+		transfer_gadget(&mut verifier, self, &public, None)?;
+		verifier.verify_with_rng(proof, pc_gens, bp_gens, rng)
+	}
+
+	/// Verifies the proof of a transfer with the given `statement`.
+	///
+	/// This is a convenience wrapper around [`MixerParams::verify_with_rng`],
+	/// passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		proof: &R1CSProof,
+		statement: &MixerStatement,
+	) -> Result<(), R1CSError> {
+		self.verify_with_rng(bp_gens, pc_gens, transcript, proof, statement, &mut thread_rng())
+	}
+}
+
+/// The variables of the public inputs of a transfer.
+struct PublicInputs {
+	root: Variable,
+	nullifier: Variable,
+	output_commitment: Variable,
+	amount: Variable,
+	fee: Variable,
+}
+
+impl PublicInputs {
+	/// Allocates the public inputs of `statement` in a fixed order.
+	fn allocate<F: FnMut(Scalar) -> Variable>(statement: &MixerStatement, mut allocate: F) -> Self {
+		let public = PublicInputs {
+			root: allocate(statement.root),
+			nullifier: allocate(statement.nullifier),
+			output_commitment: allocate(statement.output_commitment),
+			amount: allocate(Scalar::from(statement.amount)),
+			fee: allocate(Scalar::from(statement.fee)),
+		};
+		// The recipient is not constrained, but binds the proof.
+		allocate(statement.recipient);
+		public
+	}
+}
+
+/// The witness of a transfer.
+struct Assignment<'a> {
+	key: &'a SpendingKey,
+	input: &'a Note,
+	path: &'a MerklePath,
+	output: &'a Note,
+}
+
+/// Constrains a transfer with the `public` inputs.
+///
+/// The prover passes its witness, and the verifier passes `None`.
+fn transfer_gadget<CS: ConstraintSystem + ?Sized, H: TreeHasher>(
+	cs: &mut CS,
+	params: &MixerParams<H>,
+	public: &PublicInputs,
+	assignment: Option<&Assignment>,
+) -> Result<(), R1CSError> {
+	let hasher = &params.hasher;
+	let key = cs.allocate(assignment.map(|a| *a.key.as_scalar()))?;
+	let input_value = cs.allocate(assignment.map(|a| Scalar::from(a.input.value)))?;
+	let input_blinding = cs.allocate(assignment.map(|a| a.input.blinding))?;
+	let output_owner = cs.allocate(assignment.map(|a| a.output.owner))?;
+	let output_value = cs.allocate(assignment.map(|a| Scalar::from(a.output.value)))?;
+	let output_blinding = cs.allocate(assignment.map(|a| a.output.blinding))?;
+	let path = MerklePath::allocate(cs, params.depth, assignment.map(|a| a.path))?;
+
+	// Spend the input note.
+	let (owner, nullifier) = nullifier_gadget(cs, hasher, key.into(), input_blinding.into())?;
+	let leaf = commitment_gadget(cs, hasher, owner, input_value.into(), input_blinding.into())?;
+	membership_gadget(cs, hasher, public.root.into(), leaf, &path)?;
+	constrain_equal(cs, nullifier, public.nullifier.into());
+
+	// Create the output note.
+	let commitment = commitment_gadget(
+		cs,
+		hasher,
+		output_owner.into(),
+		output_value.into(),
+		output_blinding.into(),
+	)?;
+	constrain_equal(cs, commitment, public.output_commitment.into());
+
+	// Conserve value.  The amount and the fee are public 64-bit integers,
+	// so checking the range of the output value bounds the input value.
+	range_check(cs, output_value.into(), params.value_bits)?;
+	constrain_equal(cs, input_value.into(), output_value + public.amount + public.fee);
+
+	Ok(())
+}
+
+/// Constrains the commitment to a note and returns it.
+fn commitment_gadget<CS: ConstraintSystem + ?Sized, H: TreeHasher>(
+	cs: &mut CS,
+	hasher: &H,
+	owner: LinearCombination,
+	value: LinearCombination,
+	blinding: LinearCombination,
+) -> Result<LinearCombination, R1CSError> {
+	let owned = hasher.hash_pair_gadget(cs, owner, value)?;
+	hasher.hash_pair_gadget(cs, owned, blinding)
+}
+
+fn gadget_error(description: &str) -> R1CSError {
+	R1CSError::GadgetError {
+		description: format!("Mixer: {}", description),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::{merkle::MerkleTree, mimc::MimcParams, poseidon::PoseidonParams},
+		TestConstraintSystem,
+	};
+
+	const DEPTH: usize = 4;
+
+	fn gens() -> (BulletproofGens, PedersenGens) {
+		(BulletproofGens::new(2048, 1), PedersenGens::default())
+	}
+
+	/// A transfer of the note at index 5 of a tree of fixture notes.
+	struct Fixture<H: TreeHasher> {
+		params: MixerParams<H>,
+		key: SpendingKey,
+		input: Note,
+		path: MerklePath,
+		output: Note,
+	}
+
+	fn fixture<H: TreeHasher + Clone>(hasher: H) -> Fixture<H> {
+		let params = MixerParams::new(hasher.clone(), DEPTH, 64).unwrap();
+		let key = SpendingKey::from_scalar(Scalar::from(123_456u64));
+		let other = SpendingKey::from_scalar(Scalar::from(654_321u64)).public_key(&hasher);
+
+		let input = Note {
+			owner: key.public_key(&hasher),
+			value: 1_000,
+			blinding: Scalar::from(42u64),
+		};
+		let leaves: Vec<Scalar> = (0..8u64)
+			.map(|i| {
+				if i == 5 {
+					params.note_commitment(&input)
+				} else {
+					params.note_commitment(&Note {
+						owner: other,
+						value: 100 * i,
+						blinding: Scalar::from(i),
+					})
+				}
+			})
+			.collect();
+		let tree = MerkleTree::from_leaves(hasher, DEPTH, &leaves).unwrap();
+		let path = tree.path(5).unwrap();
+		assert_eq!(path.compute_root(params.hasher(), leaves[5]), tree.root());
+
+		let output = Note {
+			owner: other,
+			value: 600,
+			blinding: Scalar::from(43u64),
+		};
+		Fixture {
+			params,
+			key,
+			input,
+			path,
+			output,
+		}
+	}
+
+	fn prove<H: TreeHasher>(f: &Fixture<H>, amount: u64, fee: u64) -> Result<(R1CSProof, MixerStatement), R1CSError> {
+		let (bp_gens, pc_gens) = gens();
+		let mut transcript = Transcript::new(b"MixerTest");
+		f.params.prove(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&f.key,
+			&f.input,
+			&f.path,
+			&f.output,
+			amount,
+			fee,
+			Scalar::from(7u64),
+		)
+	}
+
+	fn verify<H: TreeHasher>(f: &Fixture<H>, proof: &R1CSProof, statement: &MixerStatement) -> Result<(), R1CSError> {
+		let (bp_gens, pc_gens) = gens();
+		let mut transcript = Transcript::new(b"MixerTest");
+		f.params.verify(&bp_gens, &pc_gens, &mut transcript, proof, statement)
+	}
+
+	fn check_transfer<H: TreeHasher + Clone>(hasher: H) {
+		let f = fixture(hasher);
+		let (proof, statement) = prove(&f, 390, 10).unwrap();
+		assert_eq!(
+			statement.nullifier,
+			f.key.nullifier(f.params.hasher(), f.input.blinding)
+		);
+		assert_eq!(statement.output_commitment, f.params.note_commitment(&f.output));
+		assert!(verify(&f, &proof, &statement).is_ok());
+
+		let tampered = [
+			MixerStatement {
+				root: statement.root + Scalar::one(),
+				..statement
+			},
+			MixerStatement {
+				nullifier: statement.nullifier + Scalar::one(),
+				..statement
+			},
+			MixerStatement {
+				output_commitment: statement.output_commitment + Scalar::one(),
+				..statement
+			},
+			MixerStatement {
+				amount: 391,
+				fee: 9,
+				..statement
+			},
+			MixerStatement {
+				recipient: Scalar::from(8u64),
+				..statement
+			},
+		];
+		for statement in tampered.iter() {
+			assert!(verify(&f, &proof, statement).is_err());
+		}
+	}
+
+	#[test]
+	fn transfer_roundtrip() {
+		check_transfer(PoseidonParams::generate(3, 8, 20).unwrap());
+		check_transfer(MimcParams::from_seed(b"test", 10).unwrap());
+	}
+
+	#[test]
+	fn nullifier_is_deterministic() {
+		let f = fixture(MimcParams::from_seed(b"test", 10).unwrap());
+		let (_, first) = prove(&f, 400, 0).unwrap();
+		let (_, second) = prove(&f, 0, 400).unwrap();
+		assert_eq!(first.nullifier, second.nullifier);
+		assert_eq!(first.root, second.root);
+	}
+
+	#[test]
+	fn invalid_transfers_are_rejected() {
+		let f = fixture(MimcParams::from_seed(b"test", 10).unwrap());
+		let expect_error = |result: Result<(R1CSProof, MixerStatement), R1CSError>| match result {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		};
+
+		// Unbalanced.
+		expect_error(prove(&f, 391, 10));
+		expect_error(prove(&f, u64::max_value(), 10));
+		// Not the owner.
+		expect_error(prove(
+			&Fixture {
+				key: SpendingKey::from_scalar(Scalar::from(1u64)),
+				..fixture(MimcParams::from_seed(b"test", 10).unwrap())
+			},
+			390,
+			10,
+		));
+		// Output value out of range.
+		let mut narrow = fixture(MimcParams::from_seed(b"test", 10).unwrap());
+		narrow.params = MixerParams::new(narrow.params.hasher().clone(), DEPTH, 8).unwrap();
+		expect_error(prove(&narrow, 390, 10));
+		// Wrong depth.
+		let mut short = fixture(MimcParams::from_seed(b"test", 10).unwrap());
+		short.path.siblings.pop();
+		expect_error(prove(&short, 390, 10));
+
+		assert!(MixerParams::new(MimcParams::from_seed(b"test", 10).unwrap(), 0, 64).is_err());
+		assert!(MixerParams::new(MimcParams::from_seed(b"test", 10).unwrap(), DEPTH, 65).is_err());
+	}
+
+	#[test]
+	fn circuit_rejects_a_forged_witness() {
+		let f = fixture(MimcParams::from_seed(b"test", 10).unwrap());
+		let (_, statement) = prove(&f, 390, 10).unwrap();
+
+		let satisfied = |statement: &MixerStatement, output: &Note| {
+			let mut cs = TestConstraintSystem::new();
+			let public = PublicInputs::allocate(statement, |x| cs.allocate_public(x));
+			let assignment = Assignment {
+				key: &f.key,
+				input: &f.input,
+				path: &f.path,
+				output,
+			};
+			transfer_gadget(&mut cs, &f.params, &public, Some(&assignment)).unwrap();
+			cs.is_satisfied()
+		};
+		assert!(satisfied(&statement, &f.output));
+
+		assert!(!satisfied(
+			&MixerStatement {
+				amount: 391,
+				..statement
+			},
+			&f.output
+		));
+		assert!(!satisfied(
+			&MixerStatement {
+				nullifier: Scalar::zero(),
+				..statement
+			},
+			&f.output
+		));
+	}
+
+	/// Pins the statement of the fixture transfer, so that a change to the
+	/// encoding of notes or nullifiers does not go unnoticed.
+	#[test]
+	fn fixture_values() {
+		let f = fixture(MimcParams::from_seed(b"test", 10).unwrap());
+		let (_, statement) = prove(&f, 390, 10).unwrap();
+		assert_eq!(
+			hex::encode(statement.root.as_bytes()),
+			"32e458b62c4c901658e6f80daa6e45ac9c197a5f87c72cd6a79d777aa0f6830e"
+		);
+		assert_eq!(
+			hex::encode(statement.nullifier.as_bytes()),
+			"5458be5521ac469e4beb195a2d7595b817eee644b94447d016bb40c69407600c"
+		);
+		assert_eq!(
+			hex::encode(statement.output_commitment.as_bytes()),
+			"3ff8e4ee681b3611e93dcaa7bd96cd111071d3378fd0ee3b5b3f31d8c34a1a05"
+		);
+	}
+}
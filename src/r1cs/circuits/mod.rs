@@ -0,0 +1,9 @@
+//! Complete circuits built from the [`gadgets`](super::gadgets), with the
+//! functions to prove and verify them.
+//!
+//! Unlike a gadget, which constrains part of a statement, a circuit fixes
+//! the whole statement: its public inputs, its transcript and the layout
+//! of its witness, so that applications depend on a tested circuit rather
+//! than on their own assembly of gadgets.
+
+pub mod mixer;
@@ -1,5 +1,7 @@
 //! Definition of the constraint system trait.
 
+use alloc::vec::Vec;
+
 use super::{LinearCombination, R1CSError, Variable};
 use curve25519_dalek::scalar::Scalar;
 use merlin::Transcript;
@@ -38,6 +40,26 @@ pub trait ConstraintSystem {
 	/// Returns `(left, right, out)` for use in further constraints.
 	fn multiply(&mut self, left: LinearCombination, right: LinearCombination) -> (Variable, Variable, Variable);
 
+	/// Allocate and constrain a multiplier for each pair of linear
+	/// combinations `(left, right)` in `pairs`, like
+	/// [`multiply`](ConstraintSystem::multiply) does for one pair.
+	///
+	/// Returns `(left, right, out)` for each multiplier, in order.  The
+	/// constraint system is the same as if the pairs were multiplied one
+	/// by one, but the prover evaluates all of them at once, and reserves
+	/// the space for the multipliers and their constraints ahead of time.
+	fn multiply_many(
+		&mut self,
+		pairs: &[(LinearCombination, LinearCombination)],
+	) -> Vec<(Variable, Variable, Variable)> {
+		self.reserve_multipliers(pairs.len());
+		self.reserve_constraints(2 * pairs.len());
+		pairs
+			.iter()
+			.map(|(left, right)| self.multiply(left.clone(), right.clone()))
+			.collect()
+	}
+
 	/// Allocate a single variable.
 	///
 	/// This either allocates a new multiplier and returns its `left` variable,
@@ -67,12 +89,63 @@ pub trait ConstraintSystem {
 	/// Counts the amount of allocated multipliers.
 	fn multipliers_len(&self) -> usize;
 
+	/// Returns the size of the constraint system built so far.
+	fn metrics(&self) -> Metrics;
+
+	/// Hints that at least `additional` more multipliers are going to be
+	/// allocated, so that the constraint system can reserve memory for
+	/// them at once rather than grow it as they are allocated.
+	///
+	/// This is only a hint: it does not change the circuit, and more or
+	/// fewer multipliers can be allocated.
+	fn reserve_multipliers(&mut self, additional: usize) {
+		let _ = additional;
+	}
+
+	/// Hints that at least `additional` more constraints are going to be
+	/// added, including the two that
+	/// [`multiply`](ConstraintSystem::multiply) adds for each multiplier,
+	/// so that the constraint system can reserve memory for them at once
+	/// rather than grow it as they are added.
+	///
+	/// This is only a hint: it does not change the circuit, and more or
+	/// fewer constraints can be added.
+	fn reserve_constraints(&mut self, additional: usize) {
+		let _ = additional;
+	}
+
+	/// Returns the [`Circuit::digest`](::r1cs::Circuit::digest) of the
+	/// constraint system built so far, without any randomized constraints.
+	///
+	/// The digest of the non-randomized constraints is committed to the
+	/// transcript when proving and verifying, so a proof only verifies
+	/// against the circuit it was created for.  Comparing the digests of
+	/// the prover and verifier tells a circuit mismatch apart from an
+	/// invalid proof.
+	fn circuit_digest(&self) -> [u8; 32];
+
 	/// Enforce the explicit constraint that
 	/// ```text
 	/// lc = 0
 	/// ```
 	fn constrain(&mut self, lc: LinearCombination);
 
+	/// Enforce the explicit constraint that
+	/// ```text
+	/// lc = 0
+	/// ```
+	/// like [`constrain`](ConstraintSystem::constrain), and name it
+	/// `label`.
+	///
+	/// The label is only used to report which constraint is not
+	/// satisfied, by the [`Prover`](::r1cs::Prover) when it panics and by
+	/// the [`TestConstraintSystem`](::r1cs::TestConstraintSystem).  It is
+	/// not part of the circuit, and is not committed to the transcript.
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		let _ = label;
+		self.constrain(lc)
+	}
+
 	/// Evaluate a linear combination. Only prover can evaluate and return the
 	/// scalar value, verifier returns None.
 	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar>;
@@ -84,6 +157,109 @@ pub trait ConstraintSystem {
 	fn allocate_single(&mut self, assignment: Option<Scalar>) -> Result<(Variable, Option<Variable>), R1CSError>;
 }
 
+/// A mutable reference to a constraint system is a constraint system, so
+/// that code generic over a sized `CS`, such as a
+/// [`Gadget`](::r1cs::gadgets::Gadget), can be used with a
+/// `&mut dyn ConstraintSystem`.
+impl<CS: ConstraintSystem + ?Sized> ConstraintSystem for &mut CS {
+	fn transcript(&mut self) -> &mut Transcript {
+		(**self).transcript()
+	}
+
+	fn multiply(&mut self, left: LinearCombination, right: LinearCombination) -> (Variable, Variable, Variable) {
+		(**self).multiply(left, right)
+	}
+
+	fn multiply_many(
+		&mut self,
+		pairs: &[(LinearCombination, LinearCombination)],
+	) -> Vec<(Variable, Variable, Variable)> {
+		(**self).multiply_many(pairs)
+	}
+
+	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError> {
+		(**self).allocate(assignment)
+	}
+
+	fn allocate_multiplier(
+		&mut self,
+		input_assignments: Option<(Scalar, Scalar)>,
+	) -> Result<(Variable, Variable, Variable), R1CSError> {
+		(**self).allocate_multiplier(input_assignments)
+	}
+
+	fn multipliers_len(&self) -> usize {
+		(**self).multipliers_len()
+	}
+
+	fn metrics(&self) -> Metrics {
+		(**self).metrics()
+	}
+
+	fn reserve_multipliers(&mut self, additional: usize) {
+		(**self).reserve_multipliers(additional)
+	}
+
+	fn reserve_constraints(&mut self, additional: usize) {
+		(**self).reserve_constraints(additional)
+	}
+
+	fn circuit_digest(&self) -> [u8; 32] {
+		(**self).circuit_digest()
+	}
+
+	fn constrain(&mut self, lc: LinearCombination) {
+		(**self).constrain(lc)
+	}
+
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		(**self).constrain_labeled(lc, label)
+	}
+
+	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar> {
+		(**self).evaluate_lc(lc)
+	}
+
+	fn allocate_single(&mut self, assignment: Option<Scalar>) -> Result<(Variable, Option<Variable>), R1CSError> {
+		(**self).allocate_single(assignment)
+	}
+}
+
+/// The size of a constraint system, as returned by
+/// [`ConstraintSystem::metrics`].
+///
+/// The proof size grows with the logarithm of the number of multipliers
+/// (padded to a power of two), and the prover and verifier time grows
+/// linearly with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metrics {
+	/// The number of allocated multipliers.
+	pub multipliers: usize,
+	/// The number of linear constraints, including those implicitly
+	/// added by [`ConstraintSystem::multiply`].
+	pub constraints: usize,
+	/// The number of committed high-level variables.
+	pub committed_variables: usize,
+	/// The total number of entries of the committed vectors.
+	pub vector_committed_variables: usize,
+	/// The number of vector commitments.
+	pub vector_commitments: usize,
+	/// The phase the constraint system is in.
+	pub phase: Phase,
+}
+
+/// The phase of a constraint system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+	/// Before the low-level variables are committed, when constraints
+	/// cannot use challenges yet.
+	NonRandomized,
+	/// Within a callback passed to
+	/// [`RandomizableConstraintSystem::specify_randomized_constraints`],
+	/// when challenges can be sampled.
+	Randomized,
+}
+
 /// An extension to the constraint system trait that permits randomized
 /// constraints. Gadgets that do not use randomization should use trait bound
 /// `CS: ConstraintSystem`, while gadgets that need randomization should use
@@ -145,4 +321,19 @@ pub trait RandomizedConstraintSystem: ConstraintSystem {
 	/// })
 	/// ```
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar;
+
+	/// Returns the value assigned to `var`.
+	///
+	/// The prover returns the assignment of any variable allocated so far,
+	/// including those allocated in the first phase, and `None` for
+	/// variables out of range.  The verifier always returns `None`.
+	///
+	/// ```text
+	/// cs.specify_randomized_constraints(move |cs| {
+	///     let z = cs.challenge_scalar(b"some challenge");
+	///     let shifted = cs.value_of(var).map(|value| value + z);
+	///     // ...
+	/// })
+	/// ```
+	fn value_of(&self, var: Variable) -> Option<Scalar>;
 }
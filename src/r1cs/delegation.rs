@@ -0,0 +1,148 @@
+//! The `delegation` module contains the API for delegating the creation of
+//! an [`R1CSProof`](super::R1CSProof) to an untrusted proving service,
+//! without giving it the blinding factors of the commitments.
+//!
+//! The client builds the constraint system with a [`Prover`](super::Prover)
+//! as usual, and sends the [`Circuit`](super::Circuit), its
+//! [`Witness`](super::Witness) with the blinding factors removed by
+//! [`Witness::without_blindings`](super::Witness::without_blindings), and
+//! the commitments to the service.  The blinding factors stay with the
+//! client, in a [`DelegationSecret`].
+//!
+//! The protocol then has one round:
+//!
+//! 1. the client sends the [`BlindingCommitment`] of its secret along with the
+//!    witness;
+//! 2. the service, with
+//!    [`Prover::prove_delegated_with_rng`](super::Prover::prove_delegated_with_rng),
+//!    creates the proof up to the challenge \\(x\\) and sends a
+//!    [`BlindingChallenge`];
+//! 3. the client answers with a [`BlindingResponse`], and the service completes
+//!    the proof.
+//!
+//! The blinding factors only enter the proof through the blinding of
+//! \\(t(x)\\), where they contribute \\(x^2 \langle w_V, \tilde{v}
+//! \rangle\\).  The client contributes this sum masked by \\(\rho x\\) for
+//! a random \\(\rho\\), and the service adds \\(\rho \tilde{B}\\) to
+//! \\(T_1\\) so that the masked sum verifies.  Since \\(\rho\\) is used
+//! once, the response reveals nothing about the blinding factors, even if
+//! the service chooses the challenge.
+//!
+//! The service learns the values of the committed variables and the
+//! assignments of the multipliers, which it needs to create the proof: the
+//! delegation keeps the commitments hiding towards it, but not the
+//! statement.  The client should verify the proof it receives.
+
+#![allow(non_snake_case)]
+
+use alloc::vec::Vec;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+use rand_core::{CryptoRng, RngCore};
+
+use super::R1CSError;
+use crate::{generators::PedersenGens, util};
+
+/// The secret of a client delegating a proof: the blinding factors of its
+/// delegated commitments, and the one-time mask \\(\rho\\) of its
+/// response.
+///
+/// Its scalars are overwritten with zeros when it is dropped.
+pub struct DelegationSecret {
+	blindings: Vec<Scalar>,
+	rho: Scalar,
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for DelegationSecret {
+	fn drop(&mut self) {
+		util::clear_secrets(&mut self.blindings);
+		util::clear_secret(&mut self.rho);
+	}
+}
+
+/// The commitment \\(\rho \tilde{B}\\) to the mask of a client's response,
+/// sent to the proving service along with the witness.
+#[derive(Copy, Clone, Debug)]
+pub struct BlindingCommitment {
+	pub(super) P: CompressedRistretto,
+}
+
+/// The challenge \\(x\\) of a delegated proof, with the weights
+/// \\(w_V\\) of the delegated commitments in the constraints.
+#[derive(Clone, Debug)]
+pub struct BlindingChallenge {
+	pub(super) x: Scalar,
+	pub(super) weights: Vec<Scalar>,
+}
+
+/// A client's contribution \\(\rho x + x^2 \langle w_V, \tilde{v}
+/// \rangle\\) to the blinding of \\(t(x)\\).
+#[derive(Copy, Clone, Debug)]
+pub struct BlindingResponse {
+	pub(super) t_x_blinding: Scalar,
+}
+
+impl DelegationSecret {
+	/// Creates the secret for delegating a proof over commitments with the
+	/// blinding factors `blindings`, in the order of the commitments, and
+	/// returns it with the commitment to its mask.
+	pub fn new<T: RngCore + CryptoRng>(
+		blindings: Vec<Scalar>,
+		pc_gens: &PedersenGens,
+		rng: &mut T,
+	) -> (Self, BlindingCommitment) {
+		let rho = Scalar::random(rng);
+		let P = (rho * pc_gens.B_blinding).compress();
+		(DelegationSecret { blindings, rho }, BlindingCommitment { P })
+	}
+
+	/// Answers the `challenge` of the proving service, consuming the secret
+	/// so that its mask is only used once.
+	///
+	/// Returns [`R1CSError::FormatError`] if the challenge has a zero
+	/// \\(x\\), which would not mask the response, or a weight for each of
+	/// a different number of commitments.
+	pub fn respond(self, challenge: &BlindingChallenge) -> Result<BlindingResponse, R1CSError> {
+		if challenge.x == Scalar::zero() || challenge.weights.len() != self.blindings.len() {
+			return Err(R1CSError::FormatError);
+		}
+		let x = challenge.x;
+		let weighted: Scalar = challenge
+			.weights
+			.iter()
+			.zip(self.blindings.iter())
+			.map(|(w, v_blinding)| w * v_blinding)
+			.sum();
+		Ok(BlindingResponse {
+			t_x_blinding: self.rho * x + x * x * weighted,
+		})
+	}
+}
+
+impl BlindingCommitment {
+	/// Returns the point \\(\rho \tilde{B}\\).
+	pub fn as_point(&self) -> &CompressedRistretto {
+		&self.P
+	}
+
+	/// Decompresses the commitment, or returns [`R1CSError::FormatError`]
+	/// if it is not a valid point.
+	pub(super) fn decompress(&self) -> Result<RistrettoPoint, R1CSError> {
+		self.P.decompress().ok_or(R1CSError::FormatError)
+	}
+}
+
+impl BlindingChallenge {
+	/// Returns the challenge \\(x\\).
+	pub fn x(&self) -> Scalar {
+		self.x
+	}
+
+	/// Returns the weights \\(w_V\\) of the delegated commitments.
+	pub fn weights(&self) -> &[Scalar] {
+		&self.weights
+	}
+}
@@ -0,0 +1,119 @@
+//! Decomposition of values into bits, in either bit order.
+//!
+//! [`decompose`] allocates the bits of a value, constrains each of them to
+//! be boolean, and constrains them to recompose to the value, so circuits
+//! do not need to hand-roll the \\(b (1 - b) = 0\\) constraints.
+//! [`recompose`] builds the linear combination of bits in the same order.
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+
+use super::bit_decomposition;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+/// The order of the bits returned by [`decompose`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum BitOrder {
+	/// The least significant bit first.
+	#[default]
+	LittleEndian,
+	/// The most significant bit first.
+	BigEndian,
+}
+
+/// Decomposes `value` into `n_bits` boolean variables in the given bit
+/// `order`, and enforces that they recompose to `value`.
+///
+/// The prover returns a [`R1CSError::GadgetError`] if its assignment of
+/// `value` does not fit in `n_bits` bits, and both parties return one if
+/// `n_bits` is not in `1..=MAX_BITSIZE`.  Uses `n_bits` multipliers.
+pub fn decompose<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	value: LinearCombination,
+	n_bits: usize,
+	order: BitOrder,
+) -> Result<Vec<Variable>, R1CSError> {
+	let mut bits = bit_decomposition(cs, value, n_bits)?;
+	if order == BitOrder::BigEndian {
+		bits.reverse();
+	}
+	Ok(bits)
+}
+
+/// Returns the value of `bits` in the given bit `order`, as
+/// `Sum(b_i * 2^i)` over the bits from the least significant one.
+///
+/// The bits are not constrained to be boolean.  Uses no multipliers.
+pub fn recompose(bits: &[Variable], order: BitOrder) -> LinearCombination {
+	let weight = |i: usize| match order {
+		BitOrder::LittleEndian => i,
+		BitOrder::BigEndian => bits.len() - 1 - i,
+	};
+	let mut exp_2 = Vec::with_capacity(bits.len());
+	let mut power = Scalar::one();
+	for _ in 0..bits.len() {
+		exp_2.push(power);
+		power = power + power;
+	}
+	bits.iter().enumerate().map(|(i, b)| (*b, exp_2[weight(i)])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::{
+		constrain_equal,
+		tests::{prove, roundtrip, verify},
+		MAX_BITSIZE,
+	};
+
+	#[test]
+	fn decompose_in_both_orders() {
+		for (order, expected) in [
+			(BitOrder::LittleEndian, [0u64, 1, 1, 0, 1]),
+			(BitOrder::BigEndian, [1, 0, 1, 1, 0]),
+		]
+		.iter()
+		{
+			assert!(roundtrip(&[0b10110], &|cs, vars| {
+				let bits = decompose(cs, vars[0].into(), 5, *order)?;
+				for (bit, expected) in bits.iter().zip(expected.iter()) {
+					constrain_equal(cs, (*bit).into(), Scalar::from(*expected).into());
+				}
+				constrain_equal(cs, recompose(&bits, *order), vars[0].into());
+				Ok(())
+			})
+			.is_ok());
+		}
+
+		// The prover refuses values that do not fit, and both parties
+		// refuse unsupported bitsizes.
+		match roundtrip(&[32], &|cs, vars| {
+			decompose(cs, vars[0].into(), 5, BitOrder::BigEndian).map(|_| ())
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+		match roundtrip(&[0], &|cs, vars| {
+			decompose(cs, vars[0].into(), MAX_BITSIZE + 1, BitOrder::default()).map(|_| ())
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+
+	#[test]
+	fn bit_order_is_part_of_the_statement() {
+		let gadget = |order: BitOrder| {
+			move |cs: &mut dyn ConstraintSystem, vars: &[Variable]| {
+				let bits = decompose(cs, vars[0].into(), 4, order)?;
+				constrain_equal(cs, bits[0].into(), Scalar::one().into());
+				Ok(())
+			}
+		};
+		let (proof, commitments) = prove(&[0b0001], &gadget(BitOrder::LittleEndian)).unwrap();
+		assert!(verify(&proof, &commitments, &gadget(BitOrder::LittleEndian)).is_ok());
+		assert!(verify(&proof, &commitments, &gadget(BitOrder::BigEndian)).is_err());
+	}
+}
@@ -0,0 +1,173 @@
+//! Boolean variables, whose type records that they are constrained to be
+//! `0` or `1`.
+//!
+//! A [`Bit`] can only be obtained from a gadget that enforces its
+//! booleanity, so a gadget taking `Bit`s cannot be passed an arbitrary
+//! [`Variable`] by mistake.  The logical operations on bits add the
+//! constraints that make their result a bit again, using one multiplier
+//! each.
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{
+	allocate_boolean,
+	bits::{decompose, BitOrder},
+	constrain_boolean,
+};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+/// A variable constrained to be either `0` or `1`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Bit(Variable);
+
+impl Bit {
+	/// Allocates a bit, assigned `assignment` by the prover.
+	///
+	/// Uses one multiplier.
+	pub fn allocate<CS: ConstraintSystem + ?Sized>(cs: &mut CS, assignment: Option<bool>) -> Result<Bit, R1CSError> {
+		allocate_boolean(cs, assignment).map(Bit)
+	}
+
+	/// Enforces that `var` is either `0` or `1`, and returns it as a bit.
+	///
+	/// Uses one multiplier.
+	pub fn constrain<CS: ConstraintSystem + ?Sized>(cs: &mut CS, var: Variable) -> Bit {
+		constrain_boolean(cs, var.into());
+		Bit(var)
+	}
+
+	/// Decomposes `value` into `n_bits` bits in the given bit `order`, like
+	/// [`decompose`].
+	///
+	/// Uses `n_bits` multipliers.
+	pub fn decompose<CS: ConstraintSystem + ?Sized>(
+		cs: &mut CS,
+		value: LinearCombination,
+		n_bits: usize,
+		order: BitOrder,
+	) -> Result<Vec<Bit>, R1CSError> {
+		decompose(cs, value, n_bits, order).map(|bits| bits.into_iter().map(Bit).collect())
+	}
+
+	/// Returns the variable of the bit.
+	pub fn variable(self) -> Variable {
+		self.0
+	}
+
+	/// Returns `self AND other`, as \\(a b\\).
+	///
+	/// Uses one multiplier.
+	pub fn and<CS: ConstraintSystem + ?Sized>(self, cs: &mut CS, other: Bit) -> Bit {
+		let (_, _, o) = cs.multiply(self.0.into(), other.0.into());
+		Bit(o)
+	}
+
+	/// Returns `self OR other`, as \\((a + b)(3 - a - b) / 2\\), which is
+	/// `0`, `1` and `1` when \\(a + b\\) is `0`, `1` and `2`.
+	///
+	/// Uses one multiplier.
+	pub fn or<CS: ConstraintSystem + ?Sized>(self, cs: &mut CS, other: Bit) -> Bit {
+		let half = Scalar::from(2u64).invert();
+		let (_, _, o) = cs.multiply(self.0 + other.0, (Scalar::from(3u64) - self.0 - other.0) * half);
+		Bit(o)
+	}
+
+	/// Returns `self XOR other`, as \\((a - b)^2\\).
+	///
+	/// Uses one multiplier.
+	pub fn xor<CS: ConstraintSystem + ?Sized>(self, cs: &mut CS, other: Bit) -> Bit {
+		let (_, _, o) = cs.multiply(self.0 - other.0, self.0 - other.0);
+		Bit(o)
+	}
+
+	/// Returns `NOT self`, as \\(1 - a\\).
+	///
+	/// The negation is a linear combination of the bit, which takes a
+	/// multiplier to be held in a variable.  Gadgets which only need
+	/// \\(1 - a\\) in a linear combination should use it directly.
+	pub fn not<CS: ConstraintSystem + ?Sized>(self, cs: &mut CS) -> Bit {
+		let (_, _, o) = cs.multiply(Scalar::one() - self.0, Scalar::one().into());
+		Bit(o)
+	}
+}
+
+impl From<Bit> for Variable {
+	fn from(bit: Bit) -> Variable {
+		bit.0
+	}
+}
+
+impl From<Bit> for LinearCombination {
+	fn from(bit: Bit) -> LinearCombination {
+		bit.0.into()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::{constrain_equal, tests::roundtrip},
+		TestConstraintSystem,
+	};
+
+	#[test]
+	fn truth_tables() {
+		let bit = |x: bool| Scalar::from(x as u64);
+		for (a, b) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+			let mut cs = TestConstraintSystem::new();
+			let a_var = cs.commit(bit(*a));
+			let b_var = cs.commit(bit(*b));
+			let (a_bit, b_bit) = (Bit::constrain(&mut cs, a_var), Bit::constrain(&mut cs, b_var));
+
+			let and = a_bit.and(&mut cs, b_bit);
+			let or = a_bit.or(&mut cs, b_bit);
+			let xor = a_bit.xor(&mut cs, b_bit);
+			let not = a_bit.not(&mut cs);
+			assert_eq!(cs.evaluate_lc(&and.into()), Some(bit(*a && *b)));
+			assert_eq!(cs.evaluate_lc(&or.into()), Some(bit(*a || *b)));
+			assert_eq!(cs.evaluate_lc(&xor.into()), Some(bit(*a ^ *b)));
+			assert_eq!(cs.evaluate_lc(&not.into()), Some(bit(!*a)));
+			assert!(cs.is_satisfied());
+		}
+
+		// The operations specify the same statement for the verifier.
+		assert!(roundtrip(&[1, 0], &|cs, vars| {
+			let a = Bit::constrain(cs, vars[0]);
+			let b = Bit::constrain(cs, vars[1]);
+			let (and, or, xor, not) = (a.and(cs, b), a.or(cs, b), a.xor(cs, b), a.not(cs));
+			constrain_equal(cs, and.into(), Scalar::zero().into());
+			constrain_equal(cs, or.into(), Scalar::one().into());
+			constrain_equal(cs, xor.into(), Scalar::one().into());
+			constrain_equal(cs, not.into(), Scalar::zero().into());
+			Ok(())
+		})
+		.is_ok());
+	}
+
+	#[test]
+	fn bits_are_constrained() {
+		let mut cs = TestConstraintSystem::new();
+		let var = cs.commit(Scalar::from(2u64));
+		Bit::constrain(&mut cs, var);
+		assert!(!cs.is_satisfied());
+
+		let mut cs = TestConstraintSystem::new();
+		let bits = Bit::decompose(&mut cs, Scalar::from(0b110u64).into(), 3, BitOrder::LittleEndian).unwrap();
+		let allocated = Bit::allocate(&mut cs, Some(true)).unwrap();
+		let values: Vec<_> = bits
+			.into_iter()
+			.chain(Some(allocated))
+			.map(|bit| cs.evaluate_lc(&bit.into()))
+			.collect();
+		assert_eq!(values, vec![
+			Some(Scalar::zero()),
+			Some(Scalar::one()),
+			Some(Scalar::one()),
+			Some(Scalar::one())
+		]);
+		assert!(cs.is_satisfied());
+	}
+}
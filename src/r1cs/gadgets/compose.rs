@@ -0,0 +1,229 @@
+//! Composition of gadgets into larger circuits.
+//!
+//! A [`Gadget`] is a reusable piece of a circuit that maps a list of input
+//! linear combinations to a list of outputs.  Gadgets are combined with
+//! [`Gadget::then`], which feeds the outputs of one gadget to the next,
+//! and [`Gadget::repeat`], which chains a gadget with itself.  Since a
+//! composed gadget is itself a [`Gadget`], each part of a circuit can be
+//! tested in isolation, e.g. against a
+//! [`TestConstraintSystem`](super::super::TestConstraintSystem), and
+//! then combined.
+//!
+//! Closures over `&mut dyn ConstraintSystem` become gadgets with
+//! [`from_fn`], and the hash functions of [`mimc`](super::mimc) and
+//! [`poseidon`](super::poseidon) are gadgets hashing their inputs to a
+//! single output.
+
+use alloc::{vec, vec::Vec};
+
+use super::{mimc, poseidon, ConstraintSystem, LinearCombination, R1CSError};
+
+/// A reusable part of a circuit, with a list of linear combinations as
+/// inputs and outputs.
+pub trait Gadget {
+	/// Adds the variables and constraints of the gadget to `cs`, and
+	/// returns its outputs.
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError>;
+
+	/// Returns a gadget which builds `self`, then `next` on the outputs
+	/// of `self`.
+	fn then<G: Gadget>(self, next: G) -> Then<Self, G>
+	where
+		Self: Sized,
+	{
+		Then { first: self, next }
+	}
+
+	/// Returns a gadget which builds `self` `times` times, each on the
+	/// outputs of the previous one.  Repeating a gadget zero times
+	/// returns the inputs unchanged.
+	fn repeat(self, times: usize) -> Repeat<Self>
+	where
+		Self: Sized,
+	{
+		Repeat { gadget: self, times }
+	}
+}
+
+impl<G: Gadget + ?Sized> Gadget for &G {
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError> {
+		(**self).build(cs, inputs)
+	}
+}
+
+/// A gadget built by [`Gadget::then`].
+#[derive(Clone, Debug)]
+pub struct Then<A, B> {
+	first: A,
+	next: B,
+}
+
+impl<A: Gadget, B: Gadget> Gadget for Then<A, B> {
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError> {
+		let outputs = self.first.build(cs, inputs)?;
+		self.next.build(cs, &outputs)
+	}
+}
+
+/// A gadget built by [`Gadget::repeat`].
+#[derive(Clone, Debug)]
+pub struct Repeat<G> {
+	gadget: G,
+	times: usize,
+}
+
+impl<G: Gadget> Gadget for Repeat<G> {
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError> {
+		let mut outputs = inputs.to_vec();
+		for _ in 0..self.times {
+			outputs = self.gadget.build(cs, &outputs)?;
+		}
+		Ok(outputs)
+	}
+}
+
+/// A gadget built by [`from_fn`].
+#[derive(Clone, Debug)]
+pub struct FromFn<F> {
+	f: F,
+}
+
+/// Returns a gadget which calls `f` to build it.
+pub fn from_fn<F>(f: F) -> FromFn<F>
+where
+	F: Fn(&mut dyn ConstraintSystem, &[LinearCombination]) -> Result<Vec<LinearCombination>, R1CSError>,
+{
+	FromFn { f }
+}
+
+impl<F> Gadget for FromFn<F>
+where
+	F: Fn(&mut dyn ConstraintSystem, &[LinearCombination]) -> Result<Vec<LinearCombination>, R1CSError>,
+{
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError> {
+		(self.f)(cs, inputs)
+	}
+}
+
+/// Hashes the inputs with [`mimc::hash_gadget`].
+impl Gadget for mimc::MimcParams {
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError> {
+		Ok(vec![mimc::hash_gadget(cs, self, inputs)])
+	}
+}
+
+/// Hashes the inputs with [`poseidon::hash_gadget`].
+impl Gadget for poseidon::PoseidonParams {
+	fn build<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[LinearCombination],
+	) -> Result<Vec<LinearCombination>, R1CSError> {
+		Ok(vec![poseidon::hash_gadget(cs, self, inputs)?])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use curve25519_dalek::scalar::Scalar;
+
+	use crate::r1cs::{
+		gadgets::{range_check, tests::roundtrip},
+		TestConstraintSystem,
+	};
+
+	/// The outputs of the gadgets built with [`from_fn`].
+	type Outputs = Result<Vec<LinearCombination>, R1CSError>;
+
+	/// Squares each input.
+	fn square() -> FromFn<impl Fn(&mut dyn ConstraintSystem, &[LinearCombination]) -> Outputs> {
+		from_fn(|cs, inputs| {
+			Ok(inputs
+				.iter()
+				.map(|x| cs.multiply(x.clone(), x.clone()).2.into())
+				.collect())
+		})
+	}
+
+	/// Checks that the single input fits in 8 bits, and returns it.
+	fn byte() -> FromFn<impl Fn(&mut dyn ConstraintSystem, &[LinearCombination]) -> Outputs> {
+		from_fn(|cs, inputs| {
+			range_check(cs, inputs[0].clone(), 8)?;
+			Ok(inputs.to_vec())
+		})
+	}
+
+	#[test]
+	fn sequenced_gadgets() {
+		// x^4 for x = 3 fits in a byte, but not x^8.
+		let mut cs = TestConstraintSystem::new();
+		let x = cs.commit(Scalar::from(3u64));
+		let outputs = square().repeat(2).then(byte()).build(&mut cs, &[x.into()]).unwrap();
+		assert_eq!(cs.evaluate_lc(&outputs[0]), Some(Scalar::from(81u64)));
+		assert!(cs.is_satisfied());
+		assert_eq!(cs.multipliers_len(), 10);
+
+		let mut cs = TestConstraintSystem::new();
+		let x = cs.commit(Scalar::from(3u64));
+		assert!(square().repeat(3).then(byte()).build(&mut cs, &[x.into()]).is_err());
+
+		// Repeating a gadget zero times adds nothing.
+		let mut cs = TestConstraintSystem::new();
+		let x = cs.commit(Scalar::from(3u64));
+		let outputs = square().repeat(0).build(&mut cs, &[x.into()]).unwrap();
+		assert_eq!(cs.evaluate_lc(&outputs[0]), Some(Scalar::from(3u64)));
+		assert_eq!(cs.multipliers_len(), 0);
+	}
+
+	#[test]
+	fn composed_gadget_roundtrip() {
+		let params = mimc::MimcParams::from_seed(b"compose", 4).unwrap();
+		let expected = params.hash(&[params.hash(&[Scalar::from(9u64), Scalar::from(16u64)])]);
+
+		// The hash of the hash of the squares of the inputs.
+		let gadget = square().then(&params).then(&params);
+
+		let mut cs = TestConstraintSystem::new();
+		let x = cs.commit(Scalar::from(3u64));
+		let y = cs.commit(Scalar::from(4u64));
+		let outputs = gadget.build(&mut cs, &[x.into(), y.into()]).unwrap();
+		assert_eq!(cs.evaluate_lc(&outputs[0]), Some(expected));
+		assert!(cs.is_satisfied());
+
+		assert!(roundtrip(&[3, 4], &|mut cs, vars| {
+			let outputs = gadget.build(&mut cs, &[vars[0].into(), vars[1].into()])?;
+			byte().build(&mut cs, &[vars[0].into()])?;
+			if let Some(digest) = cs.evaluate_lc(&outputs[0]) {
+				assert_eq!(digest, expected);
+			}
+			Ok(())
+		})
+		.is_ok());
+	}
+}
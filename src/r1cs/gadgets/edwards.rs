@@ -0,0 +1,476 @@
+//! Arithmetic on a twisted Edwards curve defined over the Ristretto scalar
+//! field, together with the matching constraint system gadgets.
+//!
+//! Such an embedded curve, like Jubjub over the BLS12-381 scalar field,
+//! has points whose coordinates are scalars, so group operations on it can
+//! be proven in a constraint system: e.g. that a committed secret key is
+//! the discrete logarithm of a public key, or that a signature verifies.
+//!
+//! The curve \\(a x^2 + y^2 = 1 + d x^2 y^2\\) is given by its
+//! [`EdwardsParams`], which only accept a square \\(a\\) and a non-square
+//! \\(d\\), so that the addition law is complete: the same formulas, with
+//! no exceptional cases, add any two points of the curve, including the
+//! identity \\((0, 1)\\) and a point with itself.  This crate does not
+//! choose a curve: the parameters, and a base point in a subgroup of
+//! large prime order, must come from the specification of the protocol.
+//!
+//! Points in a constraint system are [`PointVar`]s.  The gadgets do not
+//! check that their inputs are on the curve: use [`constrain_on_curve`]
+//! on points that are not already known to be on it.
+
+use alloc::format;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{bit_at, bit_decomposition, conditional_select};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+/// Parameters \\(a\\) and \\(d\\) of the twisted Edwards curve
+/// \\(a x^2 + y^2 = 1 + d x^2 y^2\\).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EdwardsParams {
+	a: Scalar,
+	d: Scalar,
+}
+
+/// A point of the curve, in affine coordinates.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Point {
+	/// The \\(x\\) coordinate.
+	pub x: Scalar,
+	/// The \\(y\\) coordinate.
+	pub y: Scalar,
+}
+
+/// A point of the curve in a constraint system, in affine coordinates.
+#[derive(Clone, Debug)]
+pub struct PointVar {
+	/// The \\(x\\) coordinate.
+	pub x: LinearCombination,
+	/// The \\(y\\) coordinate.
+	pub y: LinearCombination,
+}
+
+impl EdwardsParams {
+	/// Creates the parameters of the curve \\(a x^2 + y^2 = 1 + d x^2
+	/// y^2\\).
+	///
+	/// Returns a [`R1CSError::GadgetError`] if \\(a\\) is not a nonzero
+	/// square or \\(d\\) is not a non-square, as the addition law would not
+	/// be complete.
+	pub fn new(a: Scalar, d: Scalar) -> Result<Self, R1CSError> {
+		if a == Scalar::zero() || !is_square(&a) {
+			return Err(gadget_error("a must be a nonzero square"));
+		}
+		if is_square(&d) {
+			return Err(gadget_error("d must not be a square"));
+		}
+		Ok(EdwardsParams { a, d })
+	}
+
+	/// Returns the parameter \\(a\\).
+	pub fn a(&self) -> Scalar {
+		self.a
+	}
+
+	/// Returns the parameter \\(d\\).
+	pub fn d(&self) -> Scalar {
+		self.d
+	}
+
+	/// Returns whether `p` is on the curve.
+	pub fn is_on_curve(&self, p: &Point) -> bool {
+		let (x2, y2) = (p.x * p.x, p.y * p.y);
+		self.a * x2 + y2 == Scalar::one() + self.d * x2 * y2
+	}
+
+	/// Returns `p + q`.
+	///
+	/// The result is only meaningful if both points are on the curve.
+	pub fn add(&self, p: &Point, q: &Point) -> Point {
+		let t = self.d * p.x * q.x * p.y * q.y;
+		Point {
+			x: (p.x * q.y + p.y * q.x) * (Scalar::one() + t).invert(),
+			y: (p.y * q.y - self.a * p.x * q.x) * (Scalar::one() - t).invert(),
+		}
+	}
+
+	/// Returns the point of the curve with the given `y` coordinate and an
+	/// even `x` coordinate, or `None` if there is none.
+	pub fn point_from_y(&self, y: Scalar) -> Option<Point> {
+		// x^2 = (1 - y^2) / (a - d y^2), where the denominator is nonzero
+		// since a / d is not a square.
+		let y2 = y * y;
+		let x = sqrt(&((Scalar::one() - y2) * (self.a - self.d * y2).invert()))?;
+		let x = if x.to_bytes()[0] & 1 == 1 { -x } else { x };
+		Some(Point { x, y })
+	}
+
+	/// Returns `k * p`, for `k` taken as an integer in `[0, 2^256)`.
+	///
+	/// The result is only meaningful if `p` is on the curve.  This is not
+	/// constant time, so it must not be used with secret scalars outside of
+	/// proving.
+	pub fn mul(&self, p: &Point, k: &Scalar) -> Point {
+		let bytes = k.to_bytes();
+		(0..256).rev().fold(Point::identity(), |acc, i| {
+			let acc = self.add(&acc, &acc);
+			if bit_at(&bytes, i) {
+				self.add(&acc, p)
+			} else {
+				acc
+			}
+		})
+	}
+}
+
+impl Point {
+	/// Returns the identity \\((0, 1)\\).
+	pub fn identity() -> Self {
+		Point {
+			x: Scalar::zero(),
+			y: Scalar::one(),
+		}
+	}
+}
+
+impl From<Point> for PointVar {
+	fn from(p: Point) -> Self {
+		PointVar {
+			x: p.x.into(),
+			y: p.y.into(),
+		}
+	}
+}
+
+/// Enforces that `p` is on the curve.  Uses three multipliers.
+pub fn constrain_on_curve<CS: ConstraintSystem + ?Sized>(cs: &mut CS, params: &EdwardsParams, p: &PointVar) {
+	let (_, _, x2) = cs.multiply(p.x.clone(), p.x.clone());
+	let (_, _, y2) = cs.multiply(p.y.clone(), p.y.clone());
+	let (_, _, x2y2) = cs.multiply(x2.into(), y2.into());
+	cs.constrain_labeled(x2 * params.a + y2 - Scalar::one() - x2y2 * params.d, "on curve");
+}
+
+/// Constrains `p + q` and returns it.
+///
+/// The prover returns a [`R1CSError::GadgetError`] if the addition is
+/// undefined on its assignments, which can only happen for points off the
+/// curve.  Uses seven multipliers.
+pub fn add_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &EdwardsParams,
+	p: &PointVar,
+	q: &PointVar,
+) -> Result<PointVar, R1CSError> {
+	let (_, _, x1y2) = cs.multiply(p.x.clone(), q.y.clone());
+	let (_, _, y1x2) = cs.multiply(p.y.clone(), q.x.clone());
+	let (_, _, x1x2) = cs.multiply(p.x.clone(), q.x.clone());
+	let (_, _, y1y2) = cs.multiply(p.y.clone(), q.y.clone());
+	let (_, _, x1x2y1y2) = cs.multiply(x1y2.into(), y1x2.into());
+
+	// x3 = (x1 y2 + y1 x2) / (1 + d x1 x2 y1 y2)
+	let x3 = divide(
+		cs,
+		x1y2 + y1x2,
+		LinearCombination::from(Scalar::one()) + x1x2y1y2 * params.d,
+	)?;
+	// y3 = (y1 y2 - a x1 x2) / (1 - d x1 x2 y1 y2)
+	let y3 = divide(
+		cs,
+		y1y2 - x1x2 * params.a,
+		LinearCombination::from(Scalar::one()) - x1x2y1y2 * params.d,
+	)?;
+	Ok(PointVar { x: x3, y: y3 })
+}
+
+/// Constrains `2 * p` and returns it.
+///
+/// See [`add_gadget`] for the errors returned.  Uses seven multipliers.
+pub fn double_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &EdwardsParams,
+	p: &PointVar,
+) -> Result<PointVar, R1CSError> {
+	add_gadget(cs, params, p, p)
+}
+
+/// Returns `p` if `condition` is `1` and `q` if `condition` is `0`.
+///
+/// The `condition` must already be constrained to be boolean.  Uses two
+/// multipliers.
+pub fn select_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	condition: LinearCombination,
+	p: &PointVar,
+	q: &PointVar,
+) -> PointVar {
+	PointVar {
+		x: conditional_select(cs, condition.clone(), p.x.clone(), q.x.clone()),
+		y: conditional_select(cs, condition, p.y.clone(), q.y.clone()),
+	}
+}
+
+/// Constrains `k * p` for a variable point `p` and returns it, where `k`
+/// is in `[0, 2^n_bits)`.
+///
+/// Doubles and conditionally adds `p` for each bit of `k`, from the most
+/// significant one.  The prover returns a [`R1CSError::GadgetError`] if
+/// its assignment of `k` does not fit in `n_bits` bits, and both parties
+/// return one if `n_bits` is not in `1..=MAX_BITSIZE`.  Uses `17 * n_bits`
+/// multipliers.
+pub fn scalar_mul_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &EdwardsParams,
+	k: LinearCombination,
+	n_bits: usize,
+	p: &PointVar,
+) -> Result<PointVar, R1CSError> {
+	let bits = bit_decomposition(cs, k, n_bits)?;
+	scalar_mul_bits_gadget(cs, params, &bits, p)
+}
+
+/// Constrains `k * p` for a variable point `p` and returns it, where `k`
+/// is given by its `bits`, least significant bit first.
+///
+/// The `bits` must already be constrained to be boolean, e.g. by
+/// [`bit_decomposition`](super::bit_decomposition).  See [`add_gadget`]
+/// for the errors returned.  Uses `16 * bits.len()` multipliers.
+pub fn scalar_mul_bits_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &EdwardsParams,
+	bits: &[Variable],
+	p: &PointVar,
+) -> Result<PointVar, R1CSError> {
+	let mut acc = PointVar::from(Point::identity());
+	for bit in bits.iter().rev() {
+		acc = double_gadget(cs, params, &acc)?;
+		let sum = add_gadget(cs, params, &acc, p)?;
+		acc = select_gadget(cs, (*bit).into(), &sum, &acc);
+	}
+	Ok(acc)
+}
+
+/// Constrains `k * base` for a fixed `base` and returns it, where `k` is
+/// in `[0, 2^n_bits)`.
+///
+/// The multiples of `base` are computed outside of the constraint system,
+/// so each window of two bits of `k` selects one of four constant points
+/// with a single multiplier, and the windows are summed without doublings.
+/// See [`scalar_mul_gadget`] for the errors returned.  Uses `n_bits`
+/// multipliers, plus eight per window of two bits after the first one.
+pub fn fixed_base_mul_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &EdwardsParams,
+	k: LinearCombination,
+	n_bits: usize,
+	base: &Point,
+) -> Result<PointVar, R1CSError> {
+	let bits = bit_decomposition(cs, k, n_bits)?;
+
+	let mut acc: Option<PointVar> = None;
+	// 4^i * base for the window i.
+	let mut window_base = *base;
+	for window in bits.chunks(2) {
+		let t1 = window_base;
+		let t2 = params.add(&t1, &t1);
+		let t3 = params.add(&t2, &t1);
+		let table = [Point::identity(), t1, t2, t3];
+		window_base = params.add(&t2, &t2);
+
+		let selected = select_from_table(cs, window, &table);
+		acc = Some(match acc {
+			Some(acc) => add_gadget(cs, params, &acc, &selected)?,
+			None => selected,
+		});
+	}
+
+	Ok(acc.unwrap_or_else(|| Point::identity().into()))
+}
+
+/// Returns `table[b0 + 2 * b1]` for the bits `window = [b0, b1]`, or
+/// `table[b0]` for `window = [b0]`.  Uses one multiplier for two bits, and
+/// none for one.
+fn select_from_table<CS: ConstraintSystem + ?Sized>(cs: &mut CS, window: &[Variable], table: &[Point; 4]) -> PointVar {
+	let coordinate = |cs: &mut CS, t: [Scalar; 4]| -> LinearCombination {
+		// t0 + b0 (t1 - t0) + b1 (t2 - t0) + b0 b1 (t3 - t2 - t1 + t0)
+		let mut lc = LinearCombination::from(t[0]) + window[0] * (t[1] - t[0]);
+		if let Some(b1) = window.get(1) {
+			let (_, _, b0b1) = cs.multiply(window[0].into(), (*b1).into());
+			lc += *b1 * (t[2] - t[0]);
+			lc += b0b1 * (t[3] - t[2] - t[1] + t[0]);
+		}
+		lc
+	};
+	let xs = [table[0].x, table[1].x, table[2].x, table[3].x];
+	let ys = [table[0].y, table[1].y, table[2].y, table[3].y];
+	PointVar {
+		x: coordinate(cs, xs),
+		y: coordinate(cs, ys),
+	}
+}
+
+/// Allocates and returns `num / den`, enforcing `quotient * den = num`.
+/// Uses one multiplier.
+fn divide<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	num: LinearCombination,
+	den: LinearCombination,
+) -> Result<LinearCombination, R1CSError> {
+	let assignment = match (cs.evaluate_lc(&num), cs.evaluate_lc(&den)) {
+		(Some(_), Some(den)) if den == Scalar::zero() => {
+			return Err(gadget_error("point addition is undefined"));
+		}
+		(Some(num), Some(den)) => Some((num * den.invert(), den)),
+		_ => None,
+	};
+	let (quotient, den_var, product) = cs.allocate_multiplier(assignment)?;
+	cs.constrain_labeled(den_var - den, "division");
+	cs.constrain_labeled(product - num, "division");
+	Ok(quotient.into())
+}
+
+/// Returns whether `x` is a square, by Euler's criterion.
+fn is_square(x: &Scalar) -> bool {
+	// (l - 1) / 2, as the scalar with the same integer representation.
+	let exponent = -Scalar::one() * Scalar::from(2u64).invert();
+	pow(x, &exponent) != -Scalar::one()
+}
+
+/// Returns a square root of `x`, or `None` if `x` is not a square.
+fn sqrt(x: &Scalar) -> Option<Scalar> {
+	// Since l = 5 (mod 8), x^((l + 3) / 8) is a square root of x or of -x,
+	// and sqrt(-1) = 2^((l - 1) / 4).
+	let candidate = pow(x, &(Scalar::from(3u64) * Scalar::from(8u64).invert()));
+	let square = candidate * candidate;
+	if square == *x {
+		Some(candidate)
+	} else if square == -x {
+		let sqrt_minus_one = pow(&Scalar::from(2u64), &(-Scalar::from(4u64).invert()));
+		Some(candidate * sqrt_minus_one)
+	} else {
+		None
+	}
+}
+
+/// Returns `x^exponent`, for `exponent` taken as an integer in `[0, 2^256)`.
+fn pow(x: &Scalar, exponent: &Scalar) -> Scalar {
+	let bytes = exponent.to_bytes();
+	(0..256).rev().fold(Scalar::one(), |acc, i| {
+		let acc = acc * acc;
+		if bit_at(&bytes, i) {
+			acc * x
+		} else {
+			acc
+		}
+	})
+}
+
+fn gadget_error(description: &str) -> R1CSError {
+	R1CSError::GadgetError {
+		description: format!("Edwards: {}", description),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::{
+		constrain_equal,
+		tests::{prove, roundtrip, verify},
+	};
+
+	/// The curve `-x^2 + y^2 = 1 + 2 x^2 y^2`, for testing only.
+	fn params() -> EdwardsParams {
+		EdwardsParams::new(-Scalar::one(), Scalar::from(2u64)).unwrap()
+	}
+
+	/// A point of the test curve with `y = 2`.
+	fn base() -> Point {
+		let x = Scalar::from_canonical_bytes([
+			0x56, 0xd2, 0x01, 0xf8, 0x58, 0xb4, 0xdb, 0x73, 0x1a, 0x58, 0x74, 0x16, 0x1c, 0x2d, 0xa7, 0xe1, 0x29, 0x2f,
+			0x7b, 0x95, 0x8f, 0x75, 0x2a, 0xbe, 0x4e, 0x57, 0xff, 0x7e, 0x4f, 0xd0, 0xe9, 0x00,
+		])
+		.unwrap();
+		Point {
+			x,
+			y: Scalar::from(2u64),
+		}
+	}
+
+	fn constrain_point<CS: ConstraintSystem + ?Sized>(cs: &mut CS, p: PointVar, expected: Point) {
+		constrain_equal(cs, p.x, expected.x.into());
+		constrain_equal(cs, p.y, expected.y.into());
+	}
+
+	#[test]
+	fn params_must_give_a_complete_addition() {
+		assert!(EdwardsParams::new(Scalar::from(2u64), Scalar::from(2u64)).is_err());
+		assert!(EdwardsParams::new(-Scalar::one(), Scalar::from(4u64)).is_err());
+		assert!(EdwardsParams::new(Scalar::zero(), Scalar::from(2u64)).is_err());
+	}
+
+	#[test]
+	fn native_arithmetic() {
+		let (params, base) = (params(), base());
+		assert!(params.is_on_curve(&base));
+		assert!(params.is_on_curve(&Point::identity()));
+		assert_eq!(params.add(&base, &Point::identity()), base);
+
+		let mut expected = Point::identity();
+		for k in 0u64..10 {
+			assert_eq!(params.mul(&base, &Scalar::from(k)), expected);
+			expected = params.add(&expected, &base);
+			assert!(params.is_on_curve(&expected));
+		}
+		let minus_base = Point { x: -base.x, y: base.y };
+		assert_eq!(params.add(&base, &minus_base), Point::identity());
+
+		let from_y = params.point_from_y(base.y).unwrap();
+		assert!(from_y == base || from_y == minus_base);
+		assert_eq!(from_y.x.to_bytes()[0] & 1, 0);
+		let mut found = 0;
+		for y in 3u64..20 {
+			if let Some(p) = params.point_from_y(Scalar::from(y)) {
+				assert!(params.is_on_curve(&p));
+				found += 1;
+			}
+		}
+		assert!(found > 0);
+	}
+
+	#[test]
+	fn scalar_multiplication() {
+		let (params, base) = (params(), base());
+		for (k, n_bits) in [(0u64, 1), (1, 1), (6, 3), (117, 7), (200, 8)].iter() {
+			let expected = params.mul(&base, &Scalar::from(*k));
+			assert!(roundtrip(&[*k], &|cs, vars| {
+				let p = PointVar::from(base);
+				constrain_on_curve(cs, &params, &p);
+				let variable = scalar_mul_gadget(cs, &params, vars[0].into(), *n_bits, &p)?;
+				constrain_point(cs, variable, expected);
+				let fixed = fixed_base_mul_gadget(cs, &params, vars[0].into(), *n_bits, &base)?;
+				constrain_point(cs, fixed, expected);
+				Ok(())
+			})
+			.is_ok());
+		}
+	}
+
+	#[test]
+	fn key_derivation_statement() {
+		let (params, base) = (params(), base());
+		let public_key = params.mul(&base, &Scalar::from(4321u64));
+		let gadget = |public_key: Point| {
+			move |cs: &mut dyn ConstraintSystem, vars: &[Variable]| {
+				let derived = fixed_base_mul_gadget(cs, &params, vars[0].into(), 16, &base)?;
+				constrain_point(cs, derived, public_key);
+				Ok(())
+			}
+		};
+
+		let (proof, commitments) = prove(&[4321], &gadget(public_key)).unwrap();
+		assert!(verify(&proof, &commitments, &gadget(public_key)).is_ok());
+		let other_key = params.add(&public_key, &base);
+		assert!(verify(&proof, &commitments, &gadget(other_key)).is_err());
+	}
+}
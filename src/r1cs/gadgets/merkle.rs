@@ -0,0 +1,374 @@
+//! Merkle trees over the Ristretto scalar field and a gadget proving
+//! membership of a leaf under a root.
+//!
+//! A [`MerkleTree`] has a fixed depth and only stores the nodes that
+//! differ from an empty subtree, so it can be used both as a dense tree
+//! built from a list of leaves and as a sparse tree with `2^64` leaves.
+//! Empty leaves are zero.
+//!
+//! The two-to-one hash is abstracted by the [`TreeHasher`] trait, which is
+//! implemented for the in-crate [`PoseidonParams`] and [`MimcParams`].
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+use sp_std::collections::btree_map::BTreeMap;
+
+use super::{
+	conditional_select, constrain_boolean,
+	mimc::{self, MimcParams},
+	poseidon::{self, PoseidonParams},
+};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The largest supported tree depth.
+pub const MAX_DEPTH: usize = 64;
+
+/// A two-to-one hash with a matching constraint system gadget.
+pub trait TreeHasher {
+	/// Hashes a pair of child nodes into their parent.
+	fn hash_pair(&self, left: Scalar, right: Scalar) -> Scalar;
+
+	/// Constrains the hash of a pair of child nodes and returns it.
+	fn hash_pair_gadget<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		left: LinearCombination,
+		right: LinearCombination,
+	) -> Result<LinearCombination, R1CSError>;
+}
+
+impl TreeHasher for PoseidonParams {
+	fn hash_pair(&self, left: Scalar, right: Scalar) -> Scalar {
+		self.hash(&[left, right])
+	}
+
+	fn hash_pair_gadget<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		left: LinearCombination,
+		right: LinearCombination,
+	) -> Result<LinearCombination, R1CSError> {
+		poseidon::hash_gadget(cs, self, &[left, right])
+	}
+}
+
+impl TreeHasher for MimcParams {
+	fn hash_pair(&self, left: Scalar, right: Scalar) -> Scalar {
+		self.hash(&[left, right])
+	}
+
+	fn hash_pair_gadget<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		left: LinearCombination,
+		right: LinearCombination,
+	) -> Result<LinearCombination, R1CSError> {
+		Ok(mimc::hash_gadget(cs, self, &[left, right]))
+	}
+}
+
+/// A Merkle tree of fixed depth.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<H: TreeHasher> {
+	hasher: H,
+	depth: usize,
+	/// The roots of empty subtrees, indexed by level.
+	empty: Vec<Scalar>,
+	/// The non-empty nodes, keyed by level (leaves are level 0) and index.
+	nodes: BTreeMap<(usize, u64), Scalar>,
+}
+
+impl<H: TreeHasher> MerkleTree<H> {
+	/// Creates an empty tree with `2^depth` leaves.
+	pub fn new(hasher: H, depth: usize) -> Result<Self, R1CSError> {
+		if !(1..=MAX_DEPTH).contains(&depth) {
+			return Err(R1CSError::GadgetError {
+				description: "Merkle: depth must satisfy 1 <= depth <= 64".into(),
+			});
+		}
+
+		let mut empty = Vec::with_capacity(depth + 1);
+		empty.push(Scalar::zero());
+		for level in 0..depth {
+			empty.push(hasher.hash_pair(empty[level], empty[level]));
+		}
+
+		Ok(MerkleTree {
+			hasher,
+			depth,
+			empty,
+			nodes: BTreeMap::new(),
+		})
+	}
+
+	/// Creates a tree with `2^depth` leaves, the first of which are
+	/// `leaves`.
+	pub fn from_leaves(hasher: H, depth: usize, leaves: &[Scalar]) -> Result<Self, R1CSError> {
+		let mut tree = MerkleTree::new(hasher, depth)?;
+		for (index, leaf) in leaves.iter().enumerate() {
+			tree.insert(index as u64, *leaf)?;
+		}
+		Ok(tree)
+	}
+
+	/// Returns the depth of the tree.
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	/// Returns the root of the tree.
+	pub fn root(&self) -> Scalar {
+		self.node(self.depth, 0)
+	}
+
+	/// Returns the leaf at `index`.
+	pub fn leaf(&self, index: u64) -> Scalar {
+		self.node(0, index)
+	}
+
+	/// Sets the leaf at `index` and updates its ancestors.
+	pub fn insert(&mut self, index: u64, leaf: Scalar) -> Result<(), R1CSError> {
+		self.check_index(index)?;
+
+		let mut node = leaf;
+		let mut index = index;
+		for level in 0..self.depth {
+			self.set_node(level, index, node);
+			let sibling = self.node(level, index ^ 1);
+			node = if index & 1 == 0 {
+				self.hasher.hash_pair(node, sibling)
+			} else {
+				self.hasher.hash_pair(sibling, node)
+			};
+			index >>= 1;
+		}
+		self.set_node(self.depth, 0, node);
+
+		Ok(())
+	}
+
+	/// Returns the authentication path of the leaf at `index`.
+	pub fn path(&self, index: u64) -> Result<MerklePath, R1CSError> {
+		self.check_index(index)?;
+
+		let siblings = (0..self.depth)
+			.map(|level| self.node(level, (index >> level) ^ 1))
+			.collect();
+		Ok(MerklePath { index, siblings })
+	}
+
+	fn node(&self, level: usize, index: u64) -> Scalar {
+		*self.nodes.get(&(level, index)).unwrap_or(&self.empty[level])
+	}
+
+	fn set_node(&mut self, level: usize, index: u64, node: Scalar) {
+		if node == self.empty[level] {
+			self.nodes.remove(&(level, index));
+		} else {
+			self.nodes.insert((level, index), node);
+		}
+	}
+
+	fn check_index(&self, index: u64) -> Result<(), R1CSError> {
+		if self.depth < 64 && index >> self.depth != 0 {
+			return Err(R1CSError::GadgetError {
+				description: "Merkle: leaf index out of range".into(),
+			});
+		}
+		Ok(())
+	}
+}
+
+/// The authentication path of a leaf: its index and the siblings of the
+/// nodes on the way from the leaf to the root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerklePath {
+	/// The index of the leaf.
+	pub index: u64,
+	/// The siblings, starting at the leaf level.
+	pub siblings: Vec<Scalar>,
+}
+
+impl MerklePath {
+	/// Computes the root of the tree containing `leaf` at this path.
+	pub fn compute_root<H: TreeHasher>(&self, hasher: &H, leaf: Scalar) -> Scalar {
+		self.siblings.iter().enumerate().fold(leaf, |node, (level, sibling)| {
+			if (self.index >> level) & 1 == 0 {
+				hasher.hash_pair(node, *sibling)
+			} else {
+				hasher.hash_pair(*sibling, node)
+			}
+		})
+	}
+
+	/// Allocates the variables of a path of length `depth` in the
+	/// constraint system, for use with [`membership_gadget`].
+	///
+	/// The prover passes its path, and the verifier passes `None`.
+	pub fn allocate<CS: ConstraintSystem + ?Sized>(
+		cs: &mut CS,
+		depth: usize,
+		path: Option<&MerklePath>,
+	) -> Result<Vec<PathNode>, R1CSError> {
+		if let Some(path) = path {
+			if path.siblings.len() != depth {
+				return Err(R1CSError::GadgetError {
+					description: "Merkle: path length does not match the depth".into(),
+				});
+			}
+		}
+
+		(0..depth)
+			.map(|level| {
+				let sibling = cs.allocate(path.map(|p| p.siblings[level]))?;
+				let is_right = cs.allocate(path.map(|p| Scalar::from((p.index >> level) & 1)))?;
+				Ok(PathNode {
+					sibling: sibling.into(),
+					is_right: is_right.into(),
+				})
+			})
+			.collect()
+	}
+}
+
+/// A level of an authentication path in a constraint system.
+#[derive(Clone, Debug)]
+pub struct PathNode {
+	/// The sibling of the node on the path.
+	pub sibling: LinearCombination,
+	/// Whether the node on the path is a right child, as `0` or `1`.
+	pub is_right: LinearCombination,
+}
+
+/// Enforces that `leaf` is in the tree with the given `root`, using the
+/// authentication `path` starting at the leaf level.
+///
+/// The `is_right` flags of the path are constrained to be boolean.  Uses
+/// two multipliers per level in addition to the hash gadget.
+pub fn membership_gadget<CS: ConstraintSystem + ?Sized, H: TreeHasher>(
+	cs: &mut CS,
+	hasher: &H,
+	root: LinearCombination,
+	leaf: LinearCombination,
+	path: &[PathNode],
+) -> Result<(), R1CSError> {
+	let mut node = leaf;
+	for PathNode { sibling, is_right } in path.iter().cloned() {
+		constrain_boolean(cs, is_right.clone());
+
+		// left = is_right ? sibling : node, and right is the other one.
+		let left = conditional_select(cs, is_right, sibling.clone(), node.clone());
+		let right = (node + sibling - left.clone()).simplify();
+		node = hasher.hash_pair_gadget(cs, left, right)?.simplify();
+	}
+	cs.constrain_labeled(node - root, "merkle root");
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::tests::{prove, roundtrip, verify};
+
+	fn test_hasher() -> MimcParams {
+		MimcParams::from_seed(b"test", 10).unwrap()
+	}
+
+	#[test]
+	fn sparse_and_dense_trees_agree() {
+		let leaves: Vec<Scalar> = (1..=5u64).map(Scalar::from).collect();
+		let dense = MerkleTree::from_leaves(test_hasher(), 3, &leaves).unwrap();
+
+		let mut sparse = MerkleTree::new(test_hasher(), 3).unwrap();
+		for (index, leaf) in leaves.iter().enumerate().rev() {
+			sparse.insert(index as u64, *leaf).unwrap();
+		}
+		assert_eq!(dense.root(), sparse.root());
+
+		// Clearing a leaf restores the root of the remaining leaves.
+		sparse.insert(4, Scalar::zero()).unwrap();
+		assert_eq!(
+			sparse.root(),
+			MerkleTree::from_leaves(test_hasher(), 3, &leaves[..4]).unwrap().root()
+		);
+		assert_eq!(MerkleTree::new(test_hasher(), 3).unwrap().root(), dense.empty[3]);
+	}
+
+	#[test]
+	fn paths_compute_root() {
+		let hasher = test_hasher();
+		let mut tree = MerkleTree::new(hasher.clone(), MAX_DEPTH).unwrap();
+		for index in [0u64, 1, 42, u64::max_value()].iter() {
+			tree.insert(*index, Scalar::from(*index) + Scalar::one()).unwrap();
+		}
+
+		for index in [0u64, 1, 42, 43, u64::max_value()].iter() {
+			let path = tree.path(*index).unwrap();
+			assert_eq!(path.compute_root(&hasher, tree.leaf(*index)), tree.root());
+			assert_ne!(path.compute_root(&hasher, Scalar::from(7u64)), tree.root());
+		}
+	}
+
+	#[test]
+	fn invalid_depth_and_index() {
+		assert!(MerkleTree::new(test_hasher(), 0).is_err());
+		assert!(MerkleTree::new(test_hasher(), MAX_DEPTH + 1).is_err());
+
+		let mut tree = MerkleTree::new(test_hasher(), 4).unwrap();
+		assert!(tree.insert(16, Scalar::one()).is_err());
+		assert!(tree.path(16).is_err());
+	}
+
+	#[test]
+	fn membership_gadget_roundtrip() {
+		let leaves: Vec<Scalar> = (10..18u64).map(Scalar::from).collect();
+		let mimc_tree = MerkleTree::from_leaves(test_hasher(), 8, &leaves).unwrap();
+		let poseidon_tree = MerkleTree::from_leaves(PoseidonParams::default(), 2, &leaves[..4]).unwrap();
+
+		for index in [0u64, 3].iter() {
+			let mimc_path = mimc_tree.path(*index).unwrap();
+			let poseidon_path = poseidon_tree.path(*index).unwrap();
+
+			assert!(roundtrip(&[10 + *index], &|cs, vars| {
+				let path = MerklePath::allocate(cs, 8, cs.evaluate_lc(&vars[0].into()).map(|_| &mimc_path))?;
+				membership_gadget(cs, &mimc_tree.hasher, mimc_tree.root().into(), vars[0].into(), &path)
+			})
+			.is_ok());
+
+			assert!(roundtrip(&[10 + *index], &|cs, vars| {
+				let path = MerklePath::allocate(cs, 2, cs.evaluate_lc(&vars[0].into()).map(|_| &poseidon_path))?;
+				membership_gadget(
+					cs,
+					&poseidon_tree.hasher,
+					poseidon_tree.root().into(),
+					vars[0].into(),
+					&path,
+				)
+			})
+			.is_ok());
+		}
+	}
+
+	#[test]
+	fn membership_gadget_rejects_wrong_root() {
+		let leaves: Vec<Scalar> = (10..18u64).map(Scalar::from).collect();
+		let tree = MerkleTree::from_leaves(test_hasher(), 8, &leaves).unwrap();
+		let other = MerkleTree::from_leaves(test_hasher(), 8, &leaves[1..]).unwrap();
+		let path = tree.path(2).unwrap();
+
+		let gadget = |root: Scalar| {
+			let (tree, path) = (&tree, &path);
+			move |cs: &mut dyn ConstraintSystem, vars: &[crate::r1cs::Variable]| {
+				let prover_path = cs.evaluate_lc(&vars[0].into()).map(|_| path);
+				let path = MerklePath::allocate(cs, 8, prover_path)?;
+				membership_gadget(cs, &tree.hasher, root.into(), vars[0].into(), &path)
+			}
+		};
+
+		let (proof, commitments) = prove(&[12], &gadget(tree.root())).unwrap();
+		assert!(verify(&proof, &commitments, &gadget(tree.root())).is_ok());
+		assert!(verify(&proof, &commitments, &gadget(other.root())).is_err());
+	}
+}
@@ -0,0 +1,749 @@
+//! Reusable gadgets for building constraint systems.
+//!
+//! Every gadget is written against the [`ConstraintSystem`] trait, so the
+//! same code specifies the statement for both the [`Prover`](super::Prover)
+//! and the [`Verifier`](super::Verifier).  Witness values are derived with
+//! [`ConstraintSystem::evaluate_lc`], so gadgets only take the linear
+//! combinations they operate on.
+//!
+//! Gadgets returning a boolean result return it as a
+//! [`LinearCombination`] that is constrained to be `0` or `1`.  Gadgets
+//! taking a boolean `condition` assume it has already been constrained,
+//! e.g. by [`constrain_boolean`] or [`allocate_boolean`].  The [`Bit`]
+//! type records that a variable is constrained in its type instead.
+//!
+//! The [`nonnative`] gadgets emulate arithmetic modulo a foreign modulus,
+//! such as the fields of secp256k1, on elements split into range-checked
+//! limbs.
+
+use alloc::{format, vec::Vec};
+use curve25519_dalek::scalar::Scalar;
+use digest::{ExtendableOutputDirty, Update, XofReader};
+use sha3::Shake256;
+
+use super::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+pub mod bits;
+pub mod boolean;
+pub mod compose;
+pub mod edwards;
+pub mod merkle;
+pub mod mimc;
+pub mod multiset;
+pub mod nonnative;
+pub mod nullifier;
+pub mod pedersen_hash;
+pub mod poseidon;
+pub mod schnorr;
+pub mod sha256;
+pub mod shuffle;
+pub mod sort;
+pub mod uint;
+
+pub use self::{boolean::Bit, compose::Gadget};
+
+/// The largest supported bitsize for [`bit_decomposition`].
+///
+/// A sum of at most 252 bits is smaller than the group order, so the
+/// decomposition cannot wrap around.
+pub const MAX_BITSIZE: usize = 252;
+
+/// Enforces that `x` is either `0` or `1`.
+///
+/// Uses one multiplier.
+pub fn constrain_boolean<CS: ConstraintSystem + ?Sized>(cs: &mut CS, x: LinearCombination) {
+	// x * (1 - x) = 0
+	let (_, _, o) = cs.multiply(x.clone(), LinearCombination::from(Scalar::one()) - x);
+	cs.constrain_labeled(o.into(), "boolean");
+}
+
+/// Allocates a variable constrained to be either `0` or `1`.
+///
+/// Uses one multiplier.
+pub fn allocate_boolean<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	assignment: Option<bool>,
+) -> Result<Variable, R1CSError> {
+	let (a, b, o) = cs.allocate_multiplier(assignment.map(|bit| {
+		let bit = Scalar::from(bit as u64);
+		(Scalar::one() - bit, bit)
+	}))?;
+
+	// Enforce a * b = 0, so one of (a,b) is zero
+	cs.constrain_labeled(o.into(), "boolean");
+
+	// Enforce that a = 1 - b, so they both are 1 or 0.
+	cs.constrain_labeled(a + (b - 1u64), "boolean");
+
+	Ok(b)
+}
+
+/// Decomposes `v` into `n` boolean variables, least significant bit
+/// first, and enforces that `v = Sum(b_i * 2^i, i = 0..n-1)`.
+///
+/// The prover returns a [`R1CSError::GadgetError`] if its assignment of
+/// `v` does not fit in `n` bits, and both parties return one if `n` is not
+/// in `1..=MAX_BITSIZE`.  Uses `n` multipliers.
+pub fn bit_decomposition<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	mut v: LinearCombination,
+	n: usize,
+) -> Result<Vec<Variable>, R1CSError> {
+	if !(1..=MAX_BITSIZE).contains(&n) {
+		return Err(R1CSError::GadgetError {
+			description: format!("bitsize must satisfy 1 <= n <= {}", MAX_BITSIZE),
+		});
+	}
+
+	let v_bytes = cs.evaluate_lc(&v).map(|v| v.to_bytes());
+	if let Some(bytes) = v_bytes {
+		if (n..256).any(|i| bit_at(&bytes, i)) {
+			return Err(R1CSError::GadgetError {
+				description: format!("value does not fit in {} bits", n),
+			});
+		}
+	}
+
+	let mut bits = Vec::with_capacity(n);
+	let mut exp_2 = Scalar::one();
+	for i in 0..n {
+		let b = allocate_boolean(cs, v_bytes.map(|bytes| bit_at(&bytes, i)))?;
+
+		// Add `-b_i*2^i` to the linear combination
+		// in order to form the following constraint by the end of the loop:
+		// v = Sum(b_i * 2^i, i = 0..n-1)
+		v -= b * exp_2;
+		exp_2 = exp_2 + exp_2;
+
+		bits.push(b);
+	}
+
+	// Enforce that v = Sum(b_i * 2^i, i = 0..n-1)
+	cs.constrain_labeled(v, "bit decomposition");
+
+	Ok(bits)
+}
+
+/// Enforces that `v` is in the range `[0, 2^n)`.
+///
+/// See [`bit_decomposition`] for the errors returned.  Uses `n`
+/// multipliers.
+pub fn range_check<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	v: LinearCombination,
+	n: usize,
+) -> Result<(), R1CSError> {
+	bit_decomposition(cs, v, n).map(|_| ())
+}
+
+/// Returns a boolean that is `1` if `x` is zero and `0` otherwise.
+///
+/// Uses two multipliers.
+pub fn is_zero<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	x: LinearCombination,
+) -> Result<LinearCombination, R1CSError> {
+	// The prover supplies the inverse of x, or zero if x is zero.
+	let inv = cs.allocate(cs.evaluate_lc(&x).map(|x| x.invert()))?;
+
+	// If x != 0 then x * (1 - x * inv) = 0 forces x * inv = 1, so the result
+	// is 0.  If x = 0 then x * inv = 0 regardless of inv, so the result is 1.
+	let (_, _, x_inv) = cs.multiply(x.clone(), inv.into());
+	let result = Scalar::one() - x_inv;
+	let (_, _, o) = cs.multiply(x, result.clone());
+	cs.constrain(o.into());
+
+	Ok(result)
+}
+
+/// Returns a boolean that is `1` if `x` is nonzero and `0` otherwise.
+///
+/// Uses two multipliers.
+pub fn is_nonzero<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	x: LinearCombination,
+) -> Result<LinearCombination, R1CSError> {
+	is_zero(cs, x).map(|z| LinearCombination::from(Scalar::one()) - z)
+}
+
+/// Returns a boolean that is `1` if `a = b` and `0` otherwise.
+///
+/// Uses two multipliers.
+pub fn is_equal<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+) -> Result<LinearCombination, R1CSError> {
+	is_zero(cs, a - b)
+}
+
+/// Enforces that `a = b`.
+pub fn constrain_equal<CS: ConstraintSystem + ?Sized>(cs: &mut CS, a: LinearCombination, b: LinearCombination) {
+	cs.constrain_labeled(a - b, "equal");
+}
+
+/// Enforces that `a < b`, where both `a` and `b` are in `[0, 2^n)`.
+///
+/// The bounds on `a` and `b` are not checked by this gadget: without them
+/// the comparison is performed modulo the group order.  Use
+/// [`range_check`] on the inputs if they are not already known to be in
+/// range.  Both parties return a [`R1CSError::GadgetError`] if `n` is not
+/// in `1..MAX_BITSIZE`, since for larger `n` the difference of the inputs
+/// can wrap around the group order and still fit in `n` bits.  Uses `n`
+/// multipliers.
+pub fn constrain_less_than<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	n: usize,
+) -> Result<(), R1CSError> {
+	check_comparison_bitsize(n)?;
+	// a < b  <=>  b - a - 1 in [0, 2^n)
+	range_check(cs, b - a - Scalar::one(), n)
+}
+
+/// Enforces that `a <= b`, where both `a` and `b` are in `[0, 2^n)`.
+///
+/// See [`constrain_less_than`] for the preconditions and the errors
+/// returned.  Uses `n` multipliers.
+pub fn constrain_less_or_equal<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	n: usize,
+) -> Result<(), R1CSError> {
+	check_comparison_bitsize(n)?;
+	// a <= b  <=>  b - a in [0, 2^n)
+	range_check(cs, b - a, n)
+}
+
+/// Returns a boolean variable that is `1` if `a < b` and `0` otherwise,
+/// where both `a` and `b` are in `[0, 2^n)`.
+///
+/// Decomposes `b - a - 1 + 2^n` into `n + 1` bits, whose most significant
+/// bit is the result.  See [`constrain_less_than`] for the preconditions;
+/// the prover returns a [`R1CSError::GadgetError`] if they do not hold, and
+/// both parties return one if `n + 1` is not in `1..=MAX_BITSIZE`.  Uses
+/// `n + 1` multipliers.
+pub fn less_than<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	n: usize,
+) -> Result<Variable, R1CSError> {
+	// a < b  <=>  b - a - 1 + 2^n in [2^n, 2^(n+1))
+	comparison_bit(cs, b - a - Scalar::one(), n)
+}
+
+/// Returns a boolean variable that is `1` if `a <= b` and `0` otherwise,
+/// where both `a` and `b` are in `[0, 2^n)`.
+///
+/// See [`less_than`] for the errors returned.  Uses `n + 1` multipliers.
+pub fn less_equal<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	n: usize,
+) -> Result<Variable, R1CSError> {
+	// a <= b  <=>  b - a + 2^n in [2^n, 2^(n+1))
+	comparison_bit(cs, b - a, n)
+}
+
+/// Returns the minimum of `a` and `b`, where both are in `[0, 2^n)`.
+///
+/// See [`less_than`] for the errors returned.  Uses `n + 2` multipliers.
+pub fn min<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	n: usize,
+) -> Result<LinearCombination, R1CSError> {
+	let lt = less_than(cs, a.clone(), b.clone(), n)?;
+	Ok(conditional_select(cs, lt.into(), a, b))
+}
+
+/// Returns the maximum of `a` and `b`, where both are in `[0, 2^n)`.
+///
+/// See [`less_than`] for the errors returned.  Uses `n + 2` multipliers.
+pub fn max<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	n: usize,
+) -> Result<LinearCombination, R1CSError> {
+	let lt = less_than(cs, a.clone(), b.clone(), n)?;
+	Ok(conditional_select(cs, lt.into(), b, a))
+}
+
+/// Decomposes `d + 2^n`, for `d` in `[-2^n, 2^n)`, into `n + 1` bits and
+/// returns the most significant one, which is `1` if `d >= 0`.
+fn comparison_bit<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	d: LinearCombination,
+	n: usize,
+) -> Result<Variable, R1CSError> {
+	check_comparison_bitsize(n)?;
+	let offset = (0..n).fold(Scalar::one(), |acc, _| acc + acc);
+	let bits = bit_decomposition(cs, d + offset, n + 1)?;
+	Ok(bits[n])
+}
+
+/// Checks that inputs of `n` bits can be compared: their difference must
+/// be less than half the group order, so that it does not wrap around.
+fn check_comparison_bitsize(n: usize) -> Result<(), R1CSError> {
+	if n >= MAX_BITSIZE {
+		return Err(R1CSError::GadgetError {
+			description: format!("bitsize must satisfy n < {}", MAX_BITSIZE),
+		});
+	}
+	Ok(())
+}
+
+/// Returns `a` if `condition` is `1` and `b` if `condition` is `0`.
+///
+/// The `condition` must already be constrained to be boolean.  Uses one
+/// multiplier.
+pub fn conditional_select<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	condition: LinearCombination,
+	a: LinearCombination,
+	b: LinearCombination,
+) -> LinearCombination {
+	// b + condition * (a - b)
+	let (_, _, o) = cs.multiply(condition, a - b.clone());
+	b + o
+}
+
+/// Enforces that `member` is one of the scalars in `set`.
+///
+/// Constrains the product of the differences `member - s` to be zero.  The
+/// prover returns a [`R1CSError::GadgetError`] if its assignment of
+/// `member` is not in the set, and both parties return one if the set is
+/// empty.  Uses `set.len() - 1` multipliers.
+pub fn set_membership<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	member: LinearCombination,
+	set: &[Scalar],
+) -> Result<(), R1CSError> {
+	if set.is_empty() {
+		return Err(R1CSError::GadgetError {
+			description: "set must not be empty".into(),
+		});
+	}
+	if let Some(value) = cs.evaluate_lc(&member) {
+		if !set.contains(&value) {
+			return Err(R1CSError::GadgetError {
+				description: "value is not a member of the set".into(),
+			});
+		}
+	}
+
+	let product = product_of_differences(cs, member, set);
+	cs.constrain_labeled(product, "set membership");
+
+	Ok(())
+}
+
+/// Enforces that `member` is none of the scalars in `set`.
+///
+/// Constrains the product of the differences `member - s` to have an
+/// inverse.  The prover returns a [`R1CSError::GadgetError`] if its
+/// assignment of `member` is in the set.  Uses `set.len() + 1` multipliers
+/// for a nonempty set.
+pub fn set_non_membership<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	member: LinearCombination,
+	set: &[Scalar],
+) -> Result<(), R1CSError> {
+	if set.is_empty() {
+		return Ok(());
+	}
+	if let Some(value) = cs.evaluate_lc(&member) {
+		if set.contains(&value) {
+			return Err(R1CSError::GadgetError {
+				description: "value is a member of the set".into(),
+			});
+		}
+	}
+
+	let product = product_of_differences(cs, member, set);
+	let inv = cs.allocate(cs.evaluate_lc(&product).map(|p| p.invert()))?;
+	let (_, _, o) = cs.multiply(product, inv.into());
+	cs.constrain_labeled(o - Scalar::one(), "set non-membership");
+
+	Ok(())
+}
+
+/// Returns `Prod(member - s_i)` over a nonempty `set`.  Uses
+/// `set.len() - 1` multipliers.
+fn product_of_differences<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	member: LinearCombination,
+	set: &[Scalar],
+) -> LinearCombination {
+	set[1..].iter().fold(member.clone() - set[0], |product, s| {
+		let (_, _, o) = cs.multiply(product, member.clone() - *s);
+		o.into()
+	})
+}
+
+fn bit_at(bytes: &[u8; 32], i: usize) -> bool {
+	(bytes[i >> 3] >> (i & 7)) & 1 == 1
+}
+
+/// Constrains `x^5` and returns it.  Uses three multipliers.
+fn pow5_gadget<CS: ConstraintSystem + ?Sized>(cs: &mut CS, x: LinearCombination) -> LinearCombination {
+	let (x, _, x2) = cs.multiply(x.clone(), x);
+	let (_, _, x4) = cs.multiply(x2.into(), x2.into());
+	let (_, _, x5) = cs.multiply(x4.into(), x.into());
+	x5.into()
+}
+
+fn pow5(x: Scalar) -> Scalar {
+	let x2 = x * x;
+	x2 * x2 * x
+}
+
+/// Squeezes `count` uniformly random scalars from SHAKE256 seeded with
+/// `label` and `seed`.
+fn squeeze_scalars(label: &[u8], seed: &[u8], count: usize) -> Vec<Scalar> {
+	let mut shake = Shake256::default();
+	shake.update(label);
+	shake.update(seed);
+	let mut reader = shake.finalize_xof_dirty();
+
+	(0..count)
+		.map(|_| {
+			let mut uniform_bytes = [0u8; 64];
+			reader.read(&mut uniform_bytes);
+			Scalar::from_bytes_mod_order_wide(&uniform_bytes)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use curve25519_dalek::ristretto::CompressedRistretto;
+	use merlin::Transcript;
+
+	use crate::{
+		generators::{BulletproofGens, PedersenGens},
+		r1cs::{Prover, R1CSProof, Verifier},
+	};
+
+	pub(super) type Gadget<'a> = &'a dyn Fn(&mut dyn ConstraintSystem, &[Variable]) -> Result<(), R1CSError>;
+
+	/// Commits to `values` and proves the statement `gadget` constrains
+	/// them with, with `gens_capacity` generators.
+	///
+	/// Unlike [`prove`], the gadget is given the prover itself, so it can be
+	/// a randomizable one, and it can close over the public inputs of the
+	/// statement.
+	pub(super) fn prove_statement<F>(
+		gens_capacity: usize,
+		values: &[Scalar],
+		gadget: F,
+	) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError>
+	where
+		F: FnOnce(&mut Prover, &[Variable]) -> Result<(), R1CSError>,
+	{
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(gens_capacity, 1);
+		let mut transcript = Transcript::new(b"GadgetTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+		let mut rng = rand::thread_rng();
+		let (commitments, vars): (Vec<_>, Vec<_>) = values
+			.iter()
+			.map(|v| prover.commit(*v, Scalar::random(&mut rng)))
+			.unzip();
+
+		gadget(&mut prover, &vars)?;
+		let proof = prover.prove(&bp_gens)?;
+
+		Ok((proof, commitments))
+	}
+
+	/// Verifies a proof of [`prove_statement`] for the statement `gadget`
+	/// constrains the `commitments` with.
+	pub(super) fn verify_statement<F>(
+		gens_capacity: usize,
+		proof: &R1CSProof,
+		commitments: &[CompressedRistretto],
+		gadget: F,
+	) -> Result<(), R1CSError>
+	where
+		F: FnOnce(&mut Verifier, &[Variable]) -> Result<(), R1CSError>,
+	{
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(gens_capacity, 1);
+		let mut transcript = Transcript::new(b"GadgetTest");
+		let mut verifier = Verifier::new(&mut transcript);
+
+		let vars: Vec<_> = commitments.iter().map(|c| verifier.commit(*c)).collect();
+
+		gadget(&mut verifier, &vars)?;
+		verifier.verify(proof, &pc_gens, &bp_gens)
+	}
+
+	pub(super) fn prove(values: &[u64], gadget: Gadget) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError> {
+		let values: Vec<_> = values.iter().map(|v| Scalar::from(*v)).collect();
+		prove_statement(512, &values, |cs, vars| gadget(cs, vars))
+	}
+
+	pub(super) fn verify(
+		proof: &R1CSProof,
+		commitments: &[CompressedRistretto],
+		gadget: Gadget,
+	) -> Result<(), R1CSError> {
+		verify_statement(512, proof, commitments, |cs, vars| gadget(cs, vars))
+	}
+
+	pub(super) fn roundtrip(values: &[u64], gadget: Gadget) -> Result<(), R1CSError> {
+		let (proof, commitments) = prove(values, gadget)?;
+		verify(&proof, &commitments, gadget)
+	}
+
+	#[test]
+	fn boolean_gadgets() {
+		for bit in [0u64, 1].iter() {
+			assert!(roundtrip(&[*bit], &|cs, vars| {
+				constrain_boolean(cs, vars[0].into());
+				let b = allocate_boolean(cs, cs.evaluate_lc(&vars[0].into()).map(|v| v == Scalar::one()))?;
+				constrain_equal(cs, b.into(), vars[0].into());
+				Ok(())
+			})
+			.is_ok());
+		}
+	}
+
+	#[test]
+	fn bit_decomposition_and_range_check() {
+		assert!(roundtrip(&[0b1011], &|cs, vars| {
+			let bits = bit_decomposition(cs, vars[0].into(), 4)?;
+			for (bit, expected) in bits.iter().zip([1u64, 1, 0, 1].iter()) {
+				constrain_equal(cs, (*bit).into(), Scalar::from(*expected).into());
+			}
+			Ok(())
+		})
+		.is_ok());
+
+		for n in [1, 8, 32, 64].iter() {
+			let max = ((1u128 << n) - 1) as u64;
+			assert!(roundtrip(&[0, max], &|cs, vars| {
+				range_check(cs, vars[0].into(), *n)?;
+				range_check(cs, vars[1].into(), *n)
+			})
+			.is_ok());
+		}
+
+		// The prover refuses values that do not fit.
+		match roundtrip(&[256], &|cs, vars| range_check(cs, vars[0].into(), 8)) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+		for n in [0, MAX_BITSIZE + 1].iter() {
+			match roundtrip(&[0], &|cs, vars| range_check(cs, vars[0].into(), *n)) {
+				Err(R1CSError::GadgetError { .. }) => {}
+				_ => panic!("expected a gadget error"),
+			}
+		}
+	}
+
+	#[test]
+	fn zero_and_equality_tests() {
+		// (x, y, x == 0, x != 0, x == y)
+		let cases = [
+			(0u64, 0u64, 1u64, 0u64, 1u64),
+			(0, 5, 1, 0, 0),
+			(7, 7, 0, 1, 1),
+			(7, 8, 0, 1, 0),
+		];
+		for (x, y, zero, nonzero, equal) in cases.iter() {
+			assert!(roundtrip(&[*x, *y], &|cs, vars| {
+				let z = is_zero(cs, vars[0].into())?;
+				let nz = is_nonzero(cs, vars[0].into())?;
+				let eq = is_equal(cs, vars[0].into(), vars[1].into())?;
+				constrain_equal(cs, z, Scalar::from(*zero).into());
+				constrain_equal(cs, nz, Scalar::from(*nonzero).into());
+				constrain_equal(cs, eq, Scalar::from(*equal).into());
+				Ok(())
+			})
+			.is_ok());
+		}
+
+		// A proof that x == 0 does not verify as a proof that x != 0.
+		let (proof, commitments) = prove(&[0], &|cs, vars| {
+			let z = is_zero(cs, vars[0].into())?;
+			constrain_equal(cs, z, Scalar::one().into());
+			Ok(())
+		})
+		.unwrap();
+		assert!(verify(&proof, &commitments, &|cs, vars| {
+			let z = is_zero(cs, vars[0].into())?;
+			constrain_equal(cs, z, Scalar::zero().into());
+			Ok(())
+		})
+		.is_err());
+	}
+
+	#[test]
+	fn comparisons() {
+		let n = 16;
+		assert!(roundtrip(&[3, 4], &|cs, vars| constrain_less_than(
+			cs,
+			vars[0].into(),
+			vars[1].into(),
+			n
+		))
+		.is_ok());
+		assert!(roundtrip(&[4, 4], &|cs, vars| constrain_less_or_equal(
+			cs,
+			vars[0].into(),
+			vars[1].into(),
+			n
+		))
+		.is_ok());
+		assert!(roundtrip(&[0, 65535], &|cs, vars| constrain_less_than(
+			cs,
+			vars[0].into(),
+			vars[1].into(),
+			n
+		))
+		.is_ok());
+
+		// The difference wraps around, so the prover cannot decompose it.
+		assert!(roundtrip(&[4, 4], &|cs, vars| constrain_less_than(
+			cs,
+			vars[0].into(),
+			vars[1].into(),
+			n
+		))
+		.is_err());
+		assert!(roundtrip(&[5, 4], &|cs, vars| constrain_less_or_equal(
+			cs,
+			vars[0].into(),
+			vars[1].into(),
+			n
+		))
+		.is_err());
+
+		// At n = MAX_BITSIZE, b - a - 1 for a = 2^200 and b = 0 wraps around
+		// the group order to a value that fits in n bits, so both parties
+		// refuse the bitsize.
+		let a = (0..200).fold(Scalar::one(), |acc, _| acc + acc);
+		let false_comparison = |cs: &mut Prover, vars: &[Variable]| {
+			range_check(cs, vars[0].into(), MAX_BITSIZE)?;
+			range_check(cs, vars[1].into(), MAX_BITSIZE)?;
+			constrain_less_than(cs, vars[0].into(), vars[1].into(), MAX_BITSIZE)
+		};
+		match prove_statement(1024, &[a, Scalar::zero()], false_comparison) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+		for n in [0, MAX_BITSIZE].iter() {
+			match roundtrip(&[0, 0], &|cs, vars| {
+				constrain_less_or_equal(cs, vars[0].into(), vars[1].into(), *n)
+			}) {
+				Err(R1CSError::GadgetError { .. }) => {}
+				_ => panic!("expected a gadget error"),
+			}
+		}
+	}
+
+	#[test]
+	fn comparison_results() {
+		let n = 8;
+		// (a, b, a < b, a <= b)
+		let cases = [
+			(3u64, 4u64, 1u64, 1u64),
+			(4, 4, 0, 1),
+			(5, 4, 0, 0),
+			(0, 255, 1, 1),
+			(255, 0, 0, 0),
+		];
+		for (a, b, lt, le) in cases.iter() {
+			assert!(roundtrip(&[*a, *b], &|cs, vars| {
+				let (a_lc, b_lc) = (LinearCombination::from(vars[0]), LinearCombination::from(vars[1]));
+				let lt_bit = less_than(cs, a_lc.clone(), b_lc.clone(), n)?;
+				let le_bit = less_equal(cs, a_lc.clone(), b_lc.clone(), n)?;
+				let min_value = min(cs, a_lc.clone(), b_lc.clone(), n)?;
+				let max_value = max(cs, a_lc, b_lc, n)?;
+				constrain_equal(cs, lt_bit.into(), Scalar::from(*lt).into());
+				constrain_equal(cs, le_bit.into(), Scalar::from(*le).into());
+				constrain_equal(cs, min_value, Scalar::from(*a.min(b)).into());
+				constrain_equal(cs, max_value, Scalar::from(*a.max(b)).into());
+				Ok(())
+			})
+			.is_ok());
+		}
+
+		// The prover refuses inputs out of range, and both parties refuse
+		// unsupported bitsizes.
+		match roundtrip(&[256, 0], &|cs, vars| {
+			less_than(cs, vars[0].into(), vars[1].into(), n).map(|_| ())
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+		match roundtrip(&[0, 0], &|cs, vars| {
+			less_equal(cs, vars[0].into(), vars[1].into(), MAX_BITSIZE).map(|_| ())
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+
+	#[test]
+	fn conditional_select_gadget() {
+		for (condition, expected) in [(0u64, 20u64), (1, 10)].iter() {
+			assert!(roundtrip(&[*condition, 10, 20], &|cs, vars| {
+				constrain_boolean(cs, vars[0].into());
+				let selected = conditional_select(cs, vars[0].into(), vars[1].into(), vars[2].into());
+				constrain_equal(cs, selected, Scalar::from(*expected).into());
+				Ok(())
+			})
+			.is_ok());
+		}
+	}
+
+	#[test]
+	fn set_membership_gadgets() {
+		let set: Vec<Scalar> = [2u64, 3, 5, 7, 11].iter().map(|s| Scalar::from(*s)).collect();
+
+		for member in [2u64, 7, 11].iter() {
+			assert!(roundtrip(&[*member], &|cs, vars| set_membership(cs, vars[0].into(), &set)).is_ok());
+			assert!(roundtrip(&[*member], &|cs, vars| set_non_membership(cs, vars[0].into(), &set)).is_err());
+		}
+		for non_member in [0u64, 4, 12].iter() {
+			assert!(roundtrip(&[*non_member], &|cs, vars| set_non_membership(cs, vars[0].into(), &set)).is_ok());
+			assert!(roundtrip(&[*non_member], &|cs, vars| set_membership(cs, vars[0].into(), &set)).is_err());
+		}
+
+		assert!(roundtrip(&[3], &|cs, vars| set_membership(cs, vars[0].into(), &set[1..2])).is_ok());
+		assert!(roundtrip(&[3], &|cs, vars| set_membership(cs, vars[0].into(), &[])).is_err());
+		assert!(roundtrip(&[3], &|cs, vars| set_non_membership(cs, vars[0].into(), &[])).is_ok());
+	}
+
+	#[test]
+	fn set_membership_rejects_other_set() {
+		let allowed: Vec<Scalar> = [2u64, 3].iter().map(|s| Scalar::from(*s)).collect();
+		let denied: Vec<Scalar> = [4u64, 5].iter().map(|s| Scalar::from(*s)).collect();
+
+		let (proof, commitments) = prove(&[3], &|cs, vars| set_membership(cs, vars[0].into(), &allowed)).unwrap();
+		assert!(verify(&proof, &commitments, &|cs, vars| set_membership(
+			cs,
+			vars[0].into(),
+			&allowed
+		))
+		.is_ok());
+		assert!(verify(&proof, &commitments, &|cs, vars| set_membership(
+			cs,
+			vars[0].into(),
+			&denied
+		))
+		.is_err());
+	}
+}
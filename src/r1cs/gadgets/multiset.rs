@@ -0,0 +1,298 @@
+//! Multiset equality and table lookups, using the challenges of the
+//! randomized phase.
+//!
+//! Both gadgets compress each tuple \\((t_0, \ldots, t_{k-1})\\) into the
+//! single value \\(\sum_j w^j t_j\\) for a challenge \\(w\\), and then
+//! compare the compressed values as multisets at a challenge \\(z\\):
+//!
+//! * [`multiset_equal`] checks the grand products \\(\prod_i (z - x_i) =
+//!   \prod_i (z - y_i)\\), which proves that the tuples of `y` are a
+//!   permutation of those of `x`;
+//! * [`lookup`] checks the sums \\(\sum_i 1 / (z - v_i) = \sum_j m_j / (z -
+//!   t_j)\\) for multiplicities \\(m_j\\) committed to before the challenges,
+//!   which proves that every tuple of `values` is a row of the public `table`.
+//!
+//! The challenges are bound to all the variables of the first phase, so
+//! the inputs must be assigned before the gadgets are called.  The
+//! constraints are only added in the randomized phase, so a constraint
+//! system using them cannot be exported as a [`Circuit`](crate::r1cs::Circuit).
+
+use alloc::{format, vec::Vec};
+use curve25519_dalek::scalar::Scalar;
+
+use crate::r1cs::{
+	ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem, RandomizedConstraintSystem, Variable,
+};
+
+/// Enforces that the tuples of `y` are a permutation of the tuples of `x`.
+///
+/// Returns a [`R1CSError::GadgetError`] if `x` and `y` have different
+/// lengths, or if the tuples do not all have the same number of entries.
+/// Uses `2 * (x.len() - 1)` multipliers in the randomized phase for
+/// nonempty inputs.
+pub fn multiset_equal<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	x: Vec<Vec<LinearCombination>>,
+	y: Vec<Vec<LinearCombination>>,
+) -> Result<(), R1CSError> {
+	if x.len() != y.len() {
+		return Err(R1CSError::GadgetError {
+			description: format!("multisets have different sizes {} and {}", x.len(), y.len()),
+		});
+	}
+	check_arity(x.iter().chain(y.iter()))?;
+	if x.is_empty() {
+		return Ok(());
+	}
+
+	cs.specify_randomized_constraints(move |cs| {
+		let w = cs.challenge_scalar(b"multiset tuple challenge");
+		let z = cs.challenge_scalar(b"multiset challenge");
+
+		let product_x = grand_product(cs, x.iter().map(|t| shift(z, t, w)).collect());
+		let product_y = grand_product(cs, y.iter().map(|t| shift(z, t, w)).collect());
+		cs.constrain_labeled(product_x - product_y, "multiset equality");
+		Ok(())
+	})
+}
+
+/// Enforces that `y` is a permutation of `x`.
+///
+/// This is [`multiset_equal`] for tuples of one entry.
+pub fn permutation<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	x: Vec<LinearCombination>,
+	y: Vec<LinearCombination>,
+) -> Result<(), R1CSError> {
+	let singletons = |v: Vec<LinearCombination>| v.into_iter().map(|lc| vec![lc]).collect();
+	multiset_equal(cs, singletons(x), singletons(y))
+}
+
+/// Enforces that every tuple of `values` is a row of `table`.
+///
+/// The prover returns a [`R1CSError::GadgetError`] if one of its values is
+/// not in the table, and both parties return one if the table is empty or
+/// if the tuples do not all have the same number of entries.  Uses
+/// `table.len() / 2` multipliers rounded up, and `values.len()`
+/// multipliers in the randomized phase.
+pub fn lookup<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	values: Vec<Vec<LinearCombination>>,
+	table: Vec<Vec<Scalar>>,
+) -> Result<(), R1CSError> {
+	if table.is_empty() {
+		return Err(R1CSError::GadgetError {
+			description: "table must not be empty".into(),
+		});
+	}
+	let rows: Vec<Vec<LinearCombination>> = table
+		.iter()
+		.map(|row| row.iter().map(|t| LinearCombination::from(*t)).collect())
+		.collect();
+	check_arity(values.iter().chain(rows.iter()))?;
+
+	// The prover counts the occurrences of each row among the values.
+	let mut multiplicities: Option<Vec<u64>> = Some(vec![0; table.len()]);
+	for value in values.iter() {
+		let assignment: Option<Vec<Scalar>> = value.iter().map(|lc| cs.evaluate_lc(lc)).collect();
+		if let (Some(assignment), Some(counts)) = (assignment, multiplicities.as_mut()) {
+			let row = table
+				.iter()
+				.position(|row| *row == assignment)
+				.ok_or(R1CSError::GadgetError {
+					description: "value is not in the table".into(),
+				})?;
+			counts[row] += 1;
+		} else {
+			multiplicities = None;
+		}
+	}
+	let m: Vec<Variable> = (0..table.len())
+		.map(|j| cs.allocate(multiplicities.as_ref().map(|counts| Scalar::from(counts[j]))))
+		.collect::<Result<_, _>>()?;
+
+	cs.specify_randomized_constraints(move |cs| {
+		let w = cs.challenge_scalar(b"lookup tuple challenge");
+		let z = cs.challenge_scalar(b"lookup challenge");
+
+		// sum_j m_j / (z - t_j), which is linear in the m_j since the table
+		// is public.
+		let mut sum = LinearCombination::default();
+		for (row, m_j) in table.iter().zip(m.iter()) {
+			let d_j = z - row.iter().rev().fold(Scalar::zero(), |acc, t| acc * w + t);
+			if d_j == Scalar::zero() {
+				return Err(R1CSError::GadgetError {
+					description: "lookup challenge hits the table".into(),
+				});
+			}
+			sum += *m_j * d_j.invert();
+		}
+
+		// sum_i 1 / (z - v_i), with each inverse allocated and constrained.
+		for value in values.iter() {
+			let d = shift(z, value, w);
+			let assignment = match cs.evaluate_lc(&d) {
+				Some(d) if d == Scalar::zero() => {
+					return Err(R1CSError::GadgetError {
+						description: "lookup challenge hits a value".into(),
+					})
+				}
+				Some(d) => Some((d, d.invert())),
+				None => None,
+			};
+			let (left, inv, out) = cs.allocate_multiplier(assignment)?;
+			cs.constrain_labeled(d - left, "lookup");
+			cs.constrain_labeled(out - Scalar::one(), "lookup");
+			sum -= inv;
+		}
+
+		cs.constrain_labeled(sum, "lookup");
+		Ok(())
+	})
+}
+
+/// Returns `Sum(w^j * t_j)`.
+fn compress(tuple: &[LinearCombination], w: Scalar) -> LinearCombination {
+	let mut exp_w = Scalar::one();
+	let mut result = LinearCombination::default();
+	for t in tuple {
+		result += t.clone() * exp_w;
+		exp_w *= w;
+	}
+	result
+}
+
+/// Returns `z - Sum(w^j * t_j)`.
+fn shift(z: Scalar, tuple: &[LinearCombination], w: Scalar) -> LinearCombination {
+	LinearCombination::from(z) - compress(tuple, w)
+}
+
+/// Returns the product of `terms`, using `terms.len() - 1` multipliers for
+/// a nonempty list.
+fn grand_product<CS: ConstraintSystem + ?Sized>(cs: &mut CS, terms: Vec<LinearCombination>) -> LinearCombination {
+	let mut terms = terms.into_iter();
+	let first = terms.next().unwrap_or_else(|| Scalar::one().into());
+	terms.fold(first, |product, term| {
+		let (_, _, o) = cs.multiply(product, term);
+		o.into()
+	})
+}
+
+/// Checks that all tuples have the same, nonzero, number of entries.
+fn check_arity<'a, I: Iterator<Item = &'a Vec<LinearCombination>>>(mut tuples: I) -> Result<(), R1CSError> {
+	let arity = match tuples.next() {
+		Some(first) => first.len(),
+		None => return Ok(()),
+	};
+	if arity == 0 || tuples.any(|t| t.len() != arity) {
+		return Err(R1CSError::GadgetError {
+			description: "tuples must have the same nonzero number of entries".into(),
+		});
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::tests::{prove_statement, verify_statement};
+
+	type Statement = fn(&mut dyn Randomizable, &[Variable]) -> Result<(), R1CSError>;
+
+	/// The constraint systems of both parties, as a trait object.
+	trait Randomizable {
+		fn permutation(&mut self, x: Vec<LinearCombination>, y: Vec<LinearCombination>) -> Result<(), R1CSError>;
+		fn multiset_equal(
+			&mut self,
+			x: Vec<Vec<LinearCombination>>,
+			y: Vec<Vec<LinearCombination>>,
+		) -> Result<(), R1CSError>;
+		fn lookup(&mut self, values: Vec<Vec<LinearCombination>>, table: Vec<Vec<Scalar>>) -> Result<(), R1CSError>;
+	}
+
+	impl<CS: RandomizableConstraintSystem> Randomizable for CS {
+		fn permutation(&mut self, x: Vec<LinearCombination>, y: Vec<LinearCombination>) -> Result<(), R1CSError> {
+			permutation(self, x, y)
+		}
+
+		fn multiset_equal(
+			&mut self,
+			x: Vec<Vec<LinearCombination>>,
+			y: Vec<Vec<LinearCombination>>,
+		) -> Result<(), R1CSError> {
+			multiset_equal(self, x, y)
+		}
+
+		fn lookup(&mut self, values: Vec<Vec<LinearCombination>>, table: Vec<Vec<Scalar>>) -> Result<(), R1CSError> {
+			lookup(self, values, table)
+		}
+	}
+
+	/// Proves `prover_statement` on `values`, and verifies the proof
+	/// against `verifier_statement`.
+	fn roundtrip(values: &[u64], prover_statement: Statement, verifier_statement: Statement) -> Result<(), R1CSError> {
+		let values: Vec<_> = values.iter().map(|v| Scalar::from(*v)).collect();
+		let (proof, commitments) = prove_statement(64, &values, |cs, vars| prover_statement(cs, vars))?;
+		verify_statement(64, &proof, &commitments, |cs, vars| verifier_statement(cs, vars))
+	}
+
+	fn lcs(vars: &[Variable]) -> Vec<LinearCombination> {
+		vars.iter().map(|v| (*v).into()).collect()
+	}
+
+	fn pairs(vars: &[Variable]) -> Vec<Vec<LinearCombination>> {
+		vars.chunks(2).map(lcs).collect()
+	}
+
+	fn table() -> Vec<Vec<Scalar>> {
+		(0u64..4).map(|i| vec![Scalar::from(i), Scalar::from(i * i)]).collect()
+	}
+
+	#[test]
+	fn permutations() {
+		let shuffle: Statement = |cs, vars| cs.permutation(lcs(&vars[..3]), lcs(&vars[3..]));
+		assert!(roundtrip(&[3, 5, 5, 5, 3, 5], shuffle, shuffle).is_ok());
+
+		// The verifier checks the statement it was given.
+		let identity: Statement = |cs, vars| cs.permutation(lcs(&vars[..3]), lcs(&vars[..3]));
+		assert!(roundtrip(&[3, 5, 5, 5, 3, 5], identity, shuffle).is_err());
+
+		let sizes: Statement = |cs, vars| cs.permutation(lcs(&vars[..1]), lcs(&vars[1..]));
+		match roundtrip(&[1, 1, 1], sizes, sizes) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+
+	#[test]
+	fn tuple_permutations() {
+		// The pairs (1, 2), (3, 4) against (3, 4), (1, 2).
+		let shuffle: Statement = |cs, vars| cs.multiset_equal(pairs(&vars[..4]), pairs(&vars[4..]));
+		assert!(roundtrip(&[1, 2, 3, 4, 3, 4, 1, 2], shuffle, shuffle).is_ok());
+
+		let arity: Statement = |cs, vars| cs.multiset_equal(vec![lcs(&vars[..2])], vec![lcs(&vars[2..])]);
+		match roundtrip(&[1, 2, 1], arity, arity) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+
+	#[test]
+	fn table_lookups() {
+		let squares: Statement = |cs, vars| cs.lookup(pairs(vars), table());
+		assert!(roundtrip(&[2, 4, 0, 0, 2, 4, 3, 9], squares, squares).is_ok());
+
+		// The prover refuses values that are not in the table.
+		match roundtrip(&[2, 4, 3, 8], squares, squares) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+
+		let empty: Statement = |cs, vars| cs.lookup(pairs(vars), Vec::new());
+		match roundtrip(&[2, 4], empty, empty) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+}
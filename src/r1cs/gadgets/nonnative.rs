@@ -0,0 +1,538 @@
+//! Arithmetic modulo a foreign modulus, such as the base field of
+//! secp256k1, inside the Ristretto scalar field.
+//!
+//! A [`ForeignElement`] holds an integer in `[0, p)` as [`LIMBS`] limbs of
+//! [`LIMB_BITS`] bits, least significant first.  The limbs are
+//! range-checked and the element is checked to be less than the modulus
+//! when it is created, so every element has a unique representation and
+//! two elements can be compared limb by limb.
+//!
+//! The prover supplies the result of each operation together with its
+//! quotient by the modulus, and the gadgets enforce an identity such as
+//! \\(a b = q p + r\\) limb by limb, carrying between limbs through
+//! range-checked linear combinations.  The limb sums and the carries stay
+//! far below the order of the Ristretto scalar field, so the identity holds
+//! over the integers and not merely modulo the group order.
+
+use alloc::{format, vec, vec::Vec};
+use core::cmp::Ordering;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{allocate_boolean, constrain_equal, range_check};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The number of limbs of a foreign element.
+pub const LIMBS: usize = 4;
+
+/// The width of a limb, in bits.
+pub const LIMB_BITS: usize = 64;
+
+// The widths of the signed carries between limbs.  A limb of a product
+// sums at most `LIMBS` products of two limbs on each side, so it is less
+// than 2^130 in absolute value and its carry is less than 2^67.  The limbs
+// of a sum or a difference are less than 2^66, with carries of at most 3,
+// and those of the canonicity check have carries of 0 or 1.
+const MUL_CARRY_BITS: usize = 68;
+const ADD_CARRY_BITS: usize = 3;
+const CANONICAL_CARRY_BITS: usize = 2;
+
+/// The base field of secp256k1, of modulus \\(2^{256} - 2^{32} - 977\\).
+pub const SECP256K1_BASE: ForeignField = ForeignField {
+	modulus: [
+		0xFFFF_FFFE_FFFF_FC2F,
+		0xFFFF_FFFF_FFFF_FFFF,
+		0xFFFF_FFFF_FFFF_FFFF,
+		0xFFFF_FFFF_FFFF_FFFF,
+	],
+};
+
+/// The scalar field of secp256k1, whose modulus is the order of its group.
+pub const SECP256K1_SCALAR: ForeignField = ForeignField {
+	modulus: [
+		0xBFD2_5E8C_D036_4141,
+		0xBAAE_DCE6_AF48_A03B,
+		0xFFFF_FFFF_FFFF_FFFE,
+		0xFFFF_FFFF_FFFF_FFFF,
+	],
+};
+
+/// The integers modulo a modulus of at most 256 bits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ForeignField {
+	modulus: [u64; LIMBS],
+}
+
+/// An element of a [`ForeignField`], as range-checked limbs.
+#[derive(Clone, Debug)]
+pub struct ForeignElement {
+	limbs: Vec<LinearCombination>,
+}
+
+impl ForeignField {
+	/// Returns the integers modulo `modulus`, given as little-endian limbs.
+	///
+	/// The modulus is not required to be prime, as none of the gadgets
+	/// computes inverses.  Returns a [`R1CSError::GadgetError`] if it is
+	/// less than 2.
+	pub fn new(modulus: [u64; LIMBS]) -> Result<ForeignField, R1CSError> {
+		if less_than(&modulus, &[2, 0, 0, 0]) {
+			return Err(gadget_error("the modulus must be at least 2"));
+		}
+		Ok(ForeignField { modulus })
+	}
+
+	/// Returns the modulus, as little-endian limbs.
+	pub fn modulus(&self) -> [u64; LIMBS] {
+		self.modulus
+	}
+
+	/// Returns the constant element `value`, given as little-endian limbs.
+	///
+	/// Returns a [`R1CSError::GadgetError`] if `value` is not less than the
+	/// modulus.  Uses no multipliers.
+	pub fn constant(&self, value: [u64; LIMBS]) -> Result<ForeignElement, R1CSError> {
+		if !less_than(&value, &self.modulus) {
+			return Err(gadget_error("the element is not less than the modulus"));
+		}
+		Ok(ForeignElement {
+			limbs: value.iter().map(|limb| Scalar::from(*limb).into()).collect(),
+		})
+	}
+
+	/// Allocates an element, assigned `value` by the prover as
+	/// little-endian limbs.
+	///
+	/// The prover returns a [`R1CSError::GadgetError`] if `value` is not
+	/// less than the modulus.  Uses 522 multipliers.
+	pub fn allocate<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		value: Option<[u64; LIMBS]>,
+	) -> Result<ForeignElement, R1CSError> {
+		let limbs = allocate_limbs(cs, value)?;
+		self.constrain_canonical(cs, &limbs)?;
+		Ok(ForeignElement { limbs })
+	}
+
+	/// Returns the element whose limbs are `limbs`, least significant
+	/// first, e.g. committed variables, and enforces that each limb is in
+	/// `[0, 2^LIMB_BITS)` and that the element is less than the modulus.
+	///
+	/// The prover returns a [`R1CSError::GadgetError`] if its assignment
+	/// does not satisfy these bounds, and both parties return one if there
+	/// are not [`LIMBS`] limbs.  Uses 520 multipliers.
+	pub fn from_limbs<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		limbs: Vec<LinearCombination>,
+	) -> Result<ForeignElement, R1CSError> {
+		if limbs.len() != LIMBS {
+			return Err(gadget_error(&format!("expected {} limbs, got {}", LIMBS, limbs.len())));
+		}
+		for limb in limbs.iter() {
+			range_check(cs, limb.clone(), LIMB_BITS)?;
+		}
+		self.constrain_canonical(cs, &limbs)?;
+		Ok(ForeignElement { limbs })
+	}
+
+	/// Returns \\(a + b \bmod p\\).
+	///
+	/// Enforces \\(a + b = q p + r\\) for a boolean \\(q\\).  Uses 532
+	/// multipliers.
+	pub fn add<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		a: &ForeignElement,
+		b: &ForeignElement,
+	) -> Result<ForeignElement, R1CSError> {
+		let witness = a.value(cs).zip(b.value(cs)).map(|(a, b)| {
+			let (sum, carry) = add_limbs(&a, &b);
+			if carry || !less_than(&sum, &self.modulus) {
+				(sub_limbs(&sum, &self.modulus).0, true)
+			} else {
+				(sum, false)
+			}
+		});
+		let r = self.allocate(cs, witness.map(|(r, _)| r))?;
+		let quotient = allocate_boolean(cs, witness.map(|(_, q)| q))?;
+
+		let terms = (0..LIMBS)
+			.map(|k| a.limbs[k].clone() + &b.limbs[k] - &r.limbs[k] - quotient * Scalar::from(self.modulus[k]))
+			.collect();
+		constrain_zero(cs, terms, ADD_CARRY_BITS)?;
+		Ok(r)
+	}
+
+	/// Returns \\(a - b \bmod p\\).
+	///
+	/// Enforces \\(a - b + p = q p + r\\) for a boolean \\(q\\).  Uses 532
+	/// multipliers.
+	pub fn sub<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		a: &ForeignElement,
+		b: &ForeignElement,
+	) -> Result<ForeignElement, R1CSError> {
+		let witness = a.value(cs).zip(b.value(cs)).map(|(a, b)| {
+			let (difference, borrow) = sub_limbs(&a, &b);
+			if borrow {
+				(add_limbs(&difference, &self.modulus).0, false)
+			} else {
+				(difference, true)
+			}
+		});
+		let r = self.allocate(cs, witness.map(|(r, _)| r))?;
+		let quotient = allocate_boolean(cs, witness.map(|(_, q)| q))?;
+
+		let terms = (0..LIMBS)
+			.map(|k| {
+				a.limbs[k].clone() - &b.limbs[k] - &r.limbs[k]
+					+ (Scalar::one() - quotient) * Scalar::from(self.modulus[k])
+			})
+			.collect();
+		constrain_zero(cs, terms, ADD_CARRY_BITS)?;
+		Ok(r)
+	}
+
+	/// Returns \\(a b \bmod p\\).
+	///
+	/// Enforces \\(a b = q p + r\\), where the quotient \\(q\\) is less than
+	/// \\(2^{256}\\) as both operands are less than the modulus.  Uses 1204
+	/// multipliers.
+	pub fn mul<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		a: &ForeignElement,
+		b: &ForeignElement,
+	) -> Result<ForeignElement, R1CSError> {
+		let witness = a
+			.value(cs)
+			.zip(b.value(cs))
+			.map(|(a, b)| divrem(&mul_wide(&a, &b), &self.modulus));
+		let pairs: Vec<_> = a
+			.limbs
+			.iter()
+			.flat_map(|a_i| b.limbs.iter().map(move |b_j| (a_i.clone(), b_j.clone())))
+			.collect();
+		let products = cs.multiply_many(&pairs);
+		let quotient = allocate_limbs(cs, witness.map(|(q, _)| q))?;
+		let r = self.allocate(cs, witness.map(|(_, r)| r))?;
+
+		let mut terms = vec![LinearCombination::default(); 2 * LIMBS - 1];
+		for (n, (_, _, o)) in products.into_iter().enumerate() {
+			terms[n / LIMBS + n % LIMBS] += o;
+		}
+		for (i, q_i) in quotient.iter().enumerate() {
+			for (j, p_j) in self.modulus.iter().enumerate() {
+				terms[i + j] -= q_i.clone() * Scalar::from(*p_j);
+			}
+		}
+		for (term, r_k) in terms.iter_mut().zip(r.limbs.iter()) {
+			*term -= r_k;
+		}
+		constrain_zero(cs, terms, MUL_CARRY_BITS)?;
+		Ok(r)
+	}
+
+	/// Enforces that the integer represented by `limbs` is less than the
+	/// modulus, by showing that \\(p - 1 - x\\) has limbs in range.
+	///
+	/// Uses 264 multipliers.
+	fn constrain_canonical<CS: ConstraintSystem + ?Sized>(
+		&self,
+		cs: &mut CS,
+		limbs: &[LinearCombination],
+	) -> Result<(), R1CSError> {
+		let max = sub_limbs(&self.modulus, &[1, 0, 0, 0]).0;
+		let witness = match limbs_value(cs, limbs) {
+			Some(x) => match sub_limbs(&max, &x) {
+				(_, true) => return Err(gadget_error("the element is not less than the modulus")),
+				(gap, false) => Some(gap),
+			},
+			None => None,
+		};
+		let gap = allocate_limbs(cs, witness)?;
+
+		let d = (0..LIMBS)
+			.map(|k| limbs[k].clone() + &gap[k] - Scalar::from(max[k]))
+			.collect();
+		constrain_zero(cs, d, CANONICAL_CARRY_BITS)
+	}
+}
+
+impl ForeignElement {
+	/// Returns the limbs of the element, least significant first.
+	pub fn limbs(&self) -> &[LinearCombination] {
+		&self.limbs
+	}
+
+	/// Returns the prover's assignment of the element as little-endian
+	/// limbs, or `None` for the verifier.
+	pub fn value<CS: ConstraintSystem + ?Sized>(&self, cs: &CS) -> Option<[u64; LIMBS]> {
+		limbs_value(cs, &self.limbs)
+	}
+
+	/// Enforces that `self` and `other` are equal.
+	///
+	/// Elements are always less than the modulus, so this compares their
+	/// limbs.  Uses no multipliers.
+	pub fn constrain_equal<CS: ConstraintSystem + ?Sized>(&self, cs: &mut CS, other: &ForeignElement) {
+		for (a, b) in self.limbs.iter().zip(other.limbs.iter()) {
+			constrain_equal(cs, a.clone(), b.clone());
+		}
+	}
+}
+
+/// Returns the little-endian limbs of a big-endian encoding of 32 bytes,
+/// such as a coordinate of a secp256k1 public key.
+pub fn limbs_from_be_bytes(bytes: &[u8; 32]) -> [u64; LIMBS] {
+	let mut limbs = [0u64; LIMBS];
+	for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8).rev()) {
+		let mut buf = [0u8; 8];
+		buf.copy_from_slice(chunk);
+		*limb = u64::from_be_bytes(buf);
+	}
+	limbs
+}
+
+/// Allocates [`LIMBS`] limbs in `[0, 2^LIMB_BITS)`, assigned `value` by the
+/// prover.
+///
+/// Uses 258 multipliers.
+fn allocate_limbs<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	value: Option<[u64; LIMBS]>,
+) -> Result<Vec<LinearCombination>, R1CSError> {
+	(0..LIMBS)
+		.map(|k| {
+			let limb: LinearCombination = cs.allocate(value.map(|v| Scalar::from(v[k])))?.into();
+			range_check(cs, limb.clone(), LIMB_BITS)?;
+			Ok(limb)
+		})
+		.collect()
+}
+
+/// Enforces that the integer \\(\sum_k d_k 2^{64 k}\\) is zero, given its
+/// limbs \\(d_k\\), where the signed carry out of each limb fits in
+/// `carry_bits` bits.
+///
+/// Each carry is the linear combination \\((d_k + c_{k-1}) / 2^{64}\\),
+/// which only fits in `carry_bits` bits if the division is exact.  Uses
+/// `(d.len() - 1) * carry_bits` multipliers.
+fn constrain_zero<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	d: Vec<LinearCombination>,
+	carry_bits: usize,
+) -> Result<(), R1CSError> {
+	let shift = pow2(LIMB_BITS).invert();
+	let offset = pow2(carry_bits - 1);
+	let mut d = d.into_iter();
+	let mut carry = d.next().unwrap_or_default();
+	for d_k in d {
+		carry *= shift;
+		range_check(cs, carry.clone() + offset, carry_bits)?;
+		carry += d_k;
+	}
+	constrain_equal(cs, carry, Scalar::zero().into());
+	Ok(())
+}
+
+fn limbs_value<CS: ConstraintSystem + ?Sized>(cs: &CS, limbs: &[LinearCombination]) -> Option<[u64; LIMBS]> {
+	let mut value = [0u64; LIMBS];
+	for (v, limb) in value.iter_mut().zip(limbs.iter()) {
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(&cs.evaluate_lc(limb)?.as_bytes()[..8]);
+		*v = u64::from_le_bytes(bytes);
+	}
+	Some(value)
+}
+
+fn less_than(a: &[u64; LIMBS], b: &[u64; LIMBS]) -> bool {
+	a.iter().rev().cmp(b.iter().rev()) == Ordering::Less
+}
+
+fn add_limbs(a: &[u64; LIMBS], b: &[u64; LIMBS]) -> ([u64; LIMBS], bool) {
+	let mut sum = [0u64; LIMBS];
+	let mut carry = false;
+	for ((s, a), b) in sum.iter_mut().zip(a.iter()).zip(b.iter()) {
+		let (t, c1) = a.overflowing_add(*b);
+		let (t, c2) = t.overflowing_add(carry as u64);
+		*s = t;
+		carry = c1 || c2;
+	}
+	(sum, carry)
+}
+
+fn sub_limbs(a: &[u64; LIMBS], b: &[u64; LIMBS]) -> ([u64; LIMBS], bool) {
+	let mut difference = [0u64; LIMBS];
+	let mut borrow = false;
+	for ((s, a), b) in difference.iter_mut().zip(a.iter()).zip(b.iter()) {
+		let (t, b1) = a.overflowing_sub(*b);
+		let (t, b2) = t.overflowing_sub(borrow as u64);
+		*s = t;
+		borrow = b1 || b2;
+	}
+	(difference, borrow)
+}
+
+fn mul_wide(a: &[u64; LIMBS], b: &[u64; LIMBS]) -> [u64; 2 * LIMBS] {
+	let mut product = [0u64; 2 * LIMBS];
+	for (i, a_i) in a.iter().enumerate() {
+		let mut carry = 0u128;
+		for (j, b_j) in b.iter().enumerate() {
+			let t = u128::from(*a_i) * u128::from(*b_j) + u128::from(product[i + j]) + carry;
+			product[i + j] = t as u64;
+			carry = t >> 64;
+		}
+		product[i + LIMBS] = carry as u64;
+	}
+	product
+}
+
+/// Returns the quotient and the remainder of `x` by `m`, by binary long
+/// division.  The quotient is truncated to [`LIMBS`] limbs, which holds it
+/// whole when `x` is a product of two integers less than `m`.
+fn divrem(x: &[u64; 2 * LIMBS], m: &[u64; LIMBS]) -> ([u64; LIMBS], [u64; LIMBS]) {
+	let mut q = [0u64; LIMBS];
+	let mut r = [0u64; LIMBS];
+	for bit in (0..2 * LIMBS * LIMB_BITS).rev() {
+		// r < m, so 2 r + 1 < 2 m, but it may not fit in LIMBS limbs.
+		let overflow = r[LIMBS - 1] >> 63 == 1;
+		for k in (1..LIMBS).rev() {
+			r[k] = (r[k] << 1) | (r[k - 1] >> 63);
+		}
+		r[0] = (r[0] << 1) | ((x[bit / 64] >> (bit % 64)) & 1);
+		if overflow || !less_than(&r, m) {
+			r = sub_limbs(&r, m).0;
+			if bit < LIMBS * LIMB_BITS {
+				q[bit / 64] |= 1 << (bit % 64);
+			}
+		}
+	}
+	(q, r)
+}
+
+fn pow2(n: usize) -> Scalar {
+	(0..n).fold(Scalar::one(), |acc, _| acc + acc)
+}
+
+fn gadget_error(description: &str) -> R1CSError {
+	R1CSError::GadgetError {
+		description: format!("foreign field: {}", description),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::tests::{prove_statement, verify_statement},
+		TestConstraintSystem, Variable,
+	};
+
+	const P_MINUS_ONE: [u64; LIMBS] = [
+		0xFFFF_FFFE_FFFF_FC2E,
+		0xFFFF_FFFF_FFFF_FFFF,
+		0xFFFF_FFFF_FFFF_FFFF,
+		0xFFFF_FFFF_FFFF_FFFF,
+	];
+
+	/// An operation, its operands and its result.
+	type Case = (usize, [u64; LIMBS], [u64; LIMBS], [u64; LIMBS]);
+
+	#[test]
+	fn secp256k1_arithmetic() {
+		let field = SECP256K1_BASE;
+		let two_255 = [0, 0, 0, 1 << 63];
+		let mut p_minus_two = P_MINUS_ONE;
+		p_minus_two[0] -= 1;
+		// (op, a, b, a op b mod p), where 2^256 = 2^32 + 977 mod p.
+		let cases: [Case; 6] = [
+			(0, P_MINUS_ONE, P_MINUS_ONE, p_minus_two),
+			(0, [1, 0, 0, 0], [2, 0, 0, 0], [3, 0, 0, 0]),
+			(1, [0, 0, 0, 0], [1, 0, 0, 0], P_MINUS_ONE),
+			(1, two_255, two_255, [0, 0, 0, 0]),
+			(2, P_MINUS_ONE, P_MINUS_ONE, [1, 0, 0, 0]),
+			(2, two_255, [2, 0, 0, 0], [0x1_0000_03D1, 0, 0, 0]),
+		];
+		for (op, a, b, expected) in cases.iter() {
+			let mut cs = TestConstraintSystem::new();
+			let a = field.allocate(&mut cs, Some(*a)).unwrap();
+			let b = field.allocate(&mut cs, Some(*b)).unwrap();
+			let result = match op {
+				0 => field.add(&mut cs, &a, &b),
+				1 => field.sub(&mut cs, &a, &b),
+				_ => field.mul(&mut cs, &a, &b),
+			}
+			.unwrap();
+			assert_eq!(result.value(&cs), Some(*expected));
+			result.constrain_equal(&mut cs, &field.constant(*expected).unwrap());
+			assert!(cs.is_satisfied());
+
+			// A wrong result does not satisfy the constraints.
+			let mut wrong = *expected;
+			wrong[1] ^= 1;
+			result.constrain_equal(&mut cs, &field.constant(wrong).unwrap());
+			assert!(!cs.is_satisfied());
+		}
+	}
+
+	#[test]
+	fn elements_are_reduced() {
+		let field = SECP256K1_SCALAR;
+		let n = field.modulus();
+		assert!(ForeignField::new([1, 0, 0, 0]).is_err());
+		assert!(field.constant(n).is_err());
+
+		let mut cs = TestConstraintSystem::new();
+		match field.allocate(&mut cs, Some(n)) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("the modulus was allocated as an element"),
+		}
+
+		let mut cs = TestConstraintSystem::new();
+		let limbs = n.iter().map(|limb| cs.commit(Scalar::from(*limb)).into()).collect();
+		assert!(field.from_limbs(&mut cs, limbs).is_err());
+
+		let mut cs = TestConstraintSystem::new();
+		let limbs = vec![cs.commit(Scalar::one()).into()];
+		assert!(field.from_limbs(&mut cs, limbs).is_err());
+	}
+
+	#[test]
+	fn big_endian_bytes() {
+		let mut bytes = [0u8; 32];
+		bytes[0] = 0x80;
+		bytes[31] = 0x01;
+		assert_eq!(limbs_from_be_bytes(&bytes), [1, 0, 0, 1 << 63]);
+	}
+
+	/// Proves that the product of two committed elements of the secp256k1
+	/// base field is `product`.
+	fn mul_gadget(cs: &mut dyn ConstraintSystem, vars: &[Variable], product: [u64; LIMBS]) -> Result<(), R1CSError> {
+		let field = SECP256K1_BASE;
+		let a = field.from_limbs(cs, vars[..LIMBS].iter().map(|v| (*v).into()).collect())?;
+		let b = field.from_limbs(cs, vars[LIMBS..].iter().map(|v| (*v).into()).collect())?;
+		let result = field.mul(cs, &a, &b)?;
+		result.constrain_equal(cs, &field.constant(product)?);
+		Ok(())
+	}
+
+	#[test]
+	fn committed_multiplication() {
+		// (p - 1)^2 = 1 mod p.
+		let values: Vec<_> = P_MINUS_ONE
+			.iter()
+			.chain(P_MINUS_ONE.iter())
+			.map(|limb| Scalar::from(*limb))
+			.collect();
+		let (proof, commitments) =
+			prove_statement(4096, &values, |cs, vars| mul_gadget(cs, vars, [1, 0, 0, 0])).unwrap();
+		for &(product, valid) in &[([1, 0, 0, 0], true), ([2, 0, 0, 0], false)] {
+			let result = verify_statement(4096, &proof, &commitments, |cs, vars| mul_gadget(cs, vars, product));
+			assert_eq!(result.is_ok(), valid);
+		}
+	}
+}
@@ -0,0 +1,186 @@
+//! Key derivation and nullifiers for anonymous transfers, with the
+//! matching constraint system gadgets.
+//!
+//! A note of an anonymous transfer is owned by a [`SpendingKey`], from
+//! which the keys below are derived with any [`TreeHasher`], such as
+//! [`PoseidonParams`](super::poseidon::PoseidonParams) or
+//! [`MimcParams`](super::mimc::MimcParams):
+//!
+//! * the nullifier key \\(nk = PRF_{sk}(t_{nk})\\), for a fixed tag
+//!   \\(t_{nk}\\);
+//! * the public key \\(pk = H(t_{pk}, nk)\\), for a fixed tag \\(t_{pk}\\),
+//!   which notes are sent to;
+//! * the nullifier \\(PRF_{nk}(\rho)\\) of a note with the unique value
+//!   \\(\rho\\), e.g. its commitment or its position in the tree of notes,
+//!   which is revealed when spending it to prevent double spends.
+//!
+//! The pseudorandom function is \\(PRF_k(x) = H(k, x)\\) for the two-to-one
+//! hash \\(H\\).  The public key puts a public tag first, while the
+//! pseudorandom function puts a secret key first, so their outputs do not
+//! collide.  Nullifiers cannot be linked to each other or to the public key
+//! without the nullifier key, and a note has a single nullifier, since it
+//! is a deterministic function of the note.
+
+use curve25519_dalek::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+
+use super::{merkle::TreeHasher, squeeze_scalars};
+use crate::{
+	r1cs::{ConstraintSystem, LinearCombination, R1CSError},
+	util,
+};
+
+/// A spending key, the secret from which the other keys are derived.
+///
+/// Its scalar is overwritten with zeros when it is dropped.
+pub struct SpendingKey(Scalar);
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for SpendingKey {
+	fn drop(&mut self) {
+		util::clear_secret(&mut self.0);
+	}
+}
+
+impl SpendingKey {
+	/// Creates a spending key from a secret scalar.
+	pub fn from_scalar(secret: Scalar) -> Self {
+		SpendingKey(secret)
+	}
+
+	/// Generates a random spending key.
+	pub fn random<T: RngCore + CryptoRng>(rng: &mut T) -> Self {
+		SpendingKey(Scalar::random(rng))
+	}
+
+	/// Returns the secret scalar, e.g. to assign it in a constraint system.
+	pub fn as_scalar(&self) -> &Scalar {
+		&self.0
+	}
+
+	/// Returns the nullifier key \\(nk = PRF_{sk}(t_{nk})\\).
+	pub fn nullifier_key<H: TreeHasher>(&self, hasher: &H) -> Scalar {
+		prf(hasher, self.0, nullifier_key_tag())
+	}
+
+	/// Returns the public key \\(pk = H(t_{pk}, nk)\\).
+	pub fn public_key<H: TreeHasher>(&self, hasher: &H) -> Scalar {
+		public_key(hasher, self.nullifier_key(hasher))
+	}
+
+	/// Returns the nullifier of the note with the unique value `rho`.
+	pub fn nullifier<H: TreeHasher>(&self, hasher: &H, rho: Scalar) -> Scalar {
+		nullifier(hasher, self.nullifier_key(hasher), rho)
+	}
+}
+
+/// Returns \\(PRF_{key}(input) = H(key, input)\\).
+pub fn prf<H: TreeHasher>(hasher: &H, key: Scalar, input: Scalar) -> Scalar {
+	hasher.hash_pair(key, input)
+}
+
+/// Returns the public key \\(H(t_{pk}, nk)\\) of the nullifier key `nk`.
+pub fn public_key<H: TreeHasher>(hasher: &H, nullifier_key: Scalar) -> Scalar {
+	hasher.hash_pair(public_key_tag(), nullifier_key)
+}
+
+/// Returns the nullifier \\(PRF_{nk}(\rho)\\) of the note with the unique
+/// value `rho`, for the nullifier key `nk`.
+pub fn nullifier<H: TreeHasher>(hasher: &H, nullifier_key: Scalar, rho: Scalar) -> Scalar {
+	prf(hasher, nullifier_key, rho)
+}
+
+/// Constrains \\(PRF_{key}(input)\\) and returns it.
+///
+/// Uses one hash gadget.
+pub fn prf_gadget<CS: ConstraintSystem + ?Sized, H: TreeHasher>(
+	cs: &mut CS,
+	hasher: &H,
+	key: LinearCombination,
+	input: LinearCombination,
+) -> Result<LinearCombination, R1CSError> {
+	hasher.hash_pair_gadget(cs, key, input)
+}
+
+/// Constrains the public key and the nullifier of the note with the unique
+/// value `rho`, for the `spending_key`, and returns them.
+///
+/// A spend circuit constrains the public key to be the owner of a note in
+/// the tree, e.g. with [`membership_gadget`](super::merkle::membership_gadget)
+/// on its commitment, and reveals the nullifier.  Uses three hash gadgets.
+pub fn nullifier_gadget<CS: ConstraintSystem + ?Sized, H: TreeHasher>(
+	cs: &mut CS,
+	hasher: &H,
+	spending_key: LinearCombination,
+	rho: LinearCombination,
+) -> Result<(LinearCombination, LinearCombination), R1CSError> {
+	let nullifier_key = prf_gadget(cs, hasher, spending_key, nullifier_key_tag().into())?.simplify();
+	let public_key = hasher.hash_pair_gadget(cs, public_key_tag().into(), nullifier_key.clone())?;
+	let nullifier = prf_gadget(cs, hasher, nullifier_key, rho)?;
+	Ok((public_key, nullifier))
+}
+
+fn nullifier_key_tag() -> Scalar {
+	squeeze_scalars(b"NullifierTag", b"nullifier key", 1)[0]
+}
+
+fn public_key_tag() -> Scalar {
+	squeeze_scalars(b"NullifierTag", b"public key", 1)[0]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::{
+			constrain_equal,
+			mimc::MimcParams,
+			poseidon::PoseidonParams,
+			tests::{prove, verify},
+		},
+		Variable,
+	};
+
+	#[test]
+	fn derived_keys() {
+		let hasher = PoseidonParams::default();
+		let key = SpendingKey::random(&mut rand::thread_rng());
+		let other = SpendingKey::random(&mut rand::thread_rng());
+		let rho = Scalar::from(7u64);
+
+		let nk = key.nullifier_key(&hasher);
+		assert_eq!(key.public_key(&hasher), public_key(&hasher, nk));
+		assert_eq!(key.nullifier(&hasher, rho), nullifier(&hasher, nk, rho));
+		assert_ne!(key.nullifier(&hasher, rho), key.nullifier(&hasher, rho + Scalar::one()));
+		assert_ne!(key.nullifier(&hasher, rho), other.nullifier(&hasher, rho));
+		assert_ne!(key.public_key(&hasher), other.public_key(&hasher));
+	}
+
+	fn check_gadget<H: TreeHasher + Clone>(hasher: H) {
+		let key = SpendingKey::from_scalar(Scalar::from(123_456u64));
+		let rho = 42u64;
+		let public_key = key.public_key(&hasher);
+		let nullifier = key.nullifier(&hasher, Scalar::from(rho));
+
+		let gadget = |expected_nullifier: Scalar| {
+			let hasher = hasher.clone();
+			move |cs: &mut dyn ConstraintSystem, vars: &[Variable]| {
+				let (pk, nf) = nullifier_gadget(cs, &hasher, vars[0].into(), vars[1].into())?;
+				constrain_equal(cs, pk, public_key.into());
+				constrain_equal(cs, nf, expected_nullifier.into());
+				Ok(())
+			}
+		};
+		let (proof, commitments) = prove(&[123_456, rho], &gadget(nullifier)).unwrap();
+		assert!(verify(&proof, &commitments, &gadget(nullifier)).is_ok());
+		let other = key.nullifier(&hasher, Scalar::from(rho + 1));
+		assert!(verify(&proof, &commitments, &gadget(other)).is_err());
+	}
+
+	#[test]
+	fn gadget_matches_native_derivation() {
+		check_gadget(PoseidonParams::generate(3, 8, 20).unwrap());
+		check_gadget(MimcParams::from_seed(b"test", 10).unwrap());
+	}
+}
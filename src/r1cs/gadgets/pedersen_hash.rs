@@ -0,0 +1,236 @@
+//! The Pedersen hash over an embedded curve, together with the matching
+//! constraint system gadget.
+//!
+//! The hash of the inputs \\(m_0, \ldots, m_{n-1}\\), each of
+//! `input_bits` bits, is the point \\(\sum_i m_i G_i\\) of the embedded
+//! curve, for generators \\(G_i\\) derived from a label like the
+//! generators of [`PedersenGens`](crate::PedersenGens) and
+//! [`BulletproofGens`](crate::BulletproofGens): nobody knows the discrete
+//! logarithms between them.  A collision gives a nontrivial relation
+//! \\(\sum_i (m_i - m'_i) G_i = 0\\), so collision resistance reduces to
+//! the discrete logarithm problem on the embedded curve, the same kind of
+//! assumption as the binding of Pedersen commitments.
+//!
+//! The hash is not a random oracle: it is linear in its inputs, and must
+//! not be used where a pseudorandom output is needed.  Each input must
+//! have fewer bits than the order of the generators, and the number of
+//! inputs is fixed by the parameters.
+
+use alloc::{format, vec::Vec};
+use curve25519_dalek::scalar::Scalar;
+
+use super::{
+	bit_at,
+	edwards::{self, EdwardsParams, Point, PointVar},
+	squeeze_scalars, MAX_BITSIZE,
+};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Parameters of the Pedersen hash: the embedded curve, a generator per
+/// input, and the number of bits of each input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PedersenHashParams {
+	curve: EdwardsParams,
+	generators: Vec<Point>,
+	input_bits: usize,
+}
+
+impl PedersenHashParams {
+	/// Creates parameters from explicit generators, one per input.
+	///
+	/// Returns a [`R1CSError::GadgetError`] if there are no generators, if
+	/// one of them is the identity or is not on the curve, or if
+	/// `input_bits` is not in `1..=MAX_BITSIZE`.
+	pub fn new(curve: EdwardsParams, generators: Vec<Point>, input_bits: usize) -> Result<Self, R1CSError> {
+		if generators.is_empty() {
+			return Err(gadget_error("there must be at least one generator"));
+		}
+		if generators
+			.iter()
+			.any(|g| *g == Point::identity() || !curve.is_on_curve(g))
+		{
+			return Err(gadget_error(
+				"generators must be points of the curve other than the identity",
+			));
+		}
+		if !(1..=MAX_BITSIZE).contains(&input_bits) {
+			return Err(gadget_error(&format!(
+				"input bits must satisfy 1 <= n <= {}",
+				MAX_BITSIZE
+			)));
+		}
+		Ok(PedersenHashParams {
+			curve,
+			generators,
+			input_bits,
+		})
+	}
+
+	/// Derives parameters for `n_inputs` inputs from `label`.
+	///
+	/// Each generator is the point with the first \\(y\\) coordinate squeezed
+	/// from SHAKE256, seeded with the label, the index of the generator and
+	/// a counter, that is on the curve, multiplied by the `cofactor` of the
+	/// curve so that it is in the subgroup of large prime order.
+	pub fn generate(
+		curve: EdwardsParams,
+		label: &[u8],
+		n_inputs: usize,
+		input_bits: usize,
+		cofactor: u64,
+	) -> Result<Self, R1CSError> {
+		let generators = (0..n_inputs as u64)
+			.map(|i| {
+				(0u64..)
+					.filter_map(|counter| {
+						let seed = [label, &i.to_le_bytes(), &counter.to_le_bytes()].concat();
+						let y = squeeze_scalars(b"PedersenHashGenerator", &seed, 1)[0];
+						let point = curve.mul(&curve.point_from_y(y)?, &Scalar::from(cofactor));
+						Some(point).filter(|p| *p != Point::identity())
+					})
+					.next()
+					.unwrap()
+			})
+			.collect();
+		PedersenHashParams::new(curve, generators, input_bits)
+	}
+
+	/// Returns the number of inputs.
+	pub fn n_inputs(&self) -> usize {
+		self.generators.len()
+	}
+
+	/// Returns the number of bits of each input.
+	pub fn input_bits(&self) -> usize {
+		self.input_bits
+	}
+
+	/// Returns the generators, one per input.
+	pub fn generators(&self) -> &[Point] {
+		&self.generators
+	}
+
+	/// Returns the hash of `inputs`.
+	///
+	/// # Panics
+	///
+	/// Panics if `inputs.len()` is not the number of inputs, or if an input
+	/// does not fit in `input_bits` bits.
+	pub fn hash(&self, inputs: &[Scalar]) -> Point {
+		assert_eq!(inputs.len(), self.generators.len());
+		inputs
+			.iter()
+			.zip(self.generators.iter())
+			.fold(Point::identity(), |acc, (m, g)| {
+				let bytes = m.to_bytes();
+				assert!(!(self.input_bits..256).any(|i| bit_at(&bytes, i)));
+				self.curve.add(&acc, &self.curve.mul(g, m))
+			})
+	}
+}
+
+/// Constrains the Pedersen hash of `inputs` and returns it.
+///
+/// Returns a [`R1CSError::GadgetError`] if `inputs.len()` is not the number
+/// of inputs, and the prover returns one if an input does not fit in
+/// `input_bits` bits.  Uses `input_bits` multipliers, plus about eight per
+/// two bits, for each input.
+pub fn hash_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &PedersenHashParams,
+	inputs: &[LinearCombination],
+) -> Result<PointVar, R1CSError> {
+	if inputs.len() != params.generators.len() {
+		return Err(gadget_error("number of inputs must equal the number of generators"));
+	}
+
+	let mut acc: Option<PointVar> = None;
+	for (m, g) in inputs.iter().zip(params.generators.iter()) {
+		let term = edwards::fixed_base_mul_gadget(cs, &params.curve, m.clone(), params.input_bits, g)?;
+		acc = Some(match acc {
+			Some(acc) => edwards::add_gadget(cs, &params.curve, &acc, &term)?,
+			None => term,
+		});
+	}
+	Ok(acc.unwrap_or_else(|| Point::identity().into()))
+}
+
+fn gadget_error(description: &str) -> R1CSError {
+	R1CSError::GadgetError {
+		description: format!("Pedersen hash: {}", description),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::{
+			constrain_equal,
+			tests::{prove, roundtrip, verify},
+		},
+		Variable,
+	};
+
+	/// The test curve of the `edwards` tests, whose cofactor is not known.
+	fn curve() -> EdwardsParams {
+		EdwardsParams::new(-Scalar::one(), Scalar::from(2u64)).unwrap()
+	}
+
+	fn params() -> PedersenHashParams {
+		PedersenHashParams::generate(curve(), b"PedersenHashTest", 2, 32, 1).unwrap()
+	}
+
+	#[test]
+	fn generators_are_derived_from_the_label() {
+		let params = params();
+		assert_eq!(params.n_inputs(), 2);
+		assert_eq!(
+			params,
+			PedersenHashParams::generate(curve(), b"PedersenHashTest", 2, 32, 1).unwrap()
+		);
+		assert_ne!(params.generators()[0], params.generators()[1]);
+
+		let other = PedersenHashParams::generate(curve(), b"OtherLabel", 2, 32, 1).unwrap();
+		assert_ne!(params.generators(), other.generators());
+
+		assert!(PedersenHashParams::new(curve(), Vec::new(), 32).is_err());
+		assert!(PedersenHashParams::new(curve(), vec![Point::identity()], 32).is_err());
+		assert!(PedersenHashParams::new(curve(), params.generators().to_vec(), 0).is_err());
+	}
+
+	#[test]
+	fn gadget_matches_native_hash() {
+		let params = params();
+		let inputs = [123_456u64, 4_000_000_000];
+		let expected = params.hash(&[Scalar::from(inputs[0]), Scalar::from(inputs[1])]);
+		assert!(params.curve.is_on_curve(&expected));
+		assert_ne!(
+			expected,
+			params.hash(&[Scalar::from(inputs[1]), Scalar::from(inputs[0])])
+		);
+
+		let gadget = |expected: Point| {
+			let params = params.clone();
+			move |cs: &mut dyn ConstraintSystem, vars: &[Variable]| {
+				let inputs: Vec<LinearCombination> = vars.iter().map(|v| (*v).into()).collect();
+				let hash = hash_gadget(cs, &params, &inputs)?;
+				constrain_equal(cs, hash.x, expected.x.into());
+				constrain_equal(cs, hash.y, expected.y.into());
+				Ok(())
+			}
+		};
+		let (proof, commitments) = prove(&inputs, &gadget(expected)).unwrap();
+		assert!(verify(&proof, &commitments, &gadget(expected)).is_ok());
+		let other = params.hash(&[Scalar::from(inputs[0] + 1), Scalar::from(inputs[1])]);
+		assert!(verify(&proof, &commitments, &gadget(other)).is_err());
+
+		match roundtrip(&[1], &|cs, vars| {
+			hash_gadget(cs, &params, &[vars[0].into()]).map(|_| ())
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+}
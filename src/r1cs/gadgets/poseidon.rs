@@ -0,0 +1,314 @@
+//! The Poseidon permutation and sponge hash over the Ristretto scalar
+//! field, together with the matching constraint system gadgets.
+//!
+//! The permutation follows the Hades design: `full_rounds / 2` rounds
+//! applying the `x^5` S-box to every state element, `partial_rounds` rounds
+//! applying it to the first element only, and another `full_rounds / 2`
+//! full rounds.  Each round adds round constants before the S-box and
+//! multiplies the state by an MDS matrix after it.
+//!
+//! Hashing uses a sponge with a capacity of one element and a rate of
+//! `width - 1` elements.  The capacity element is initialised to the
+//! number of inputs, so inputs of different lengths are domain-separated.
+
+use alloc::{format, vec::Vec};
+use curve25519_dalek::scalar::Scalar;
+
+use super::{pow5, pow5_gadget, squeeze_scalars};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Parameters of the Poseidon permutation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoseidonParams {
+	width: usize,
+	full_rounds: usize,
+	partial_rounds: usize,
+	round_constants: Vec<Scalar>,
+	mds: Vec<Vec<Scalar>>,
+}
+
+impl PoseidonParams {
+	/// Creates parameters from explicit round constants and MDS matrix.
+	///
+	/// The `round_constants` are consumed `width` at a time, one group per
+	/// round, so there must be `width * (full_rounds + partial_rounds)` of
+	/// them.  The `mds` matrix must be `width x width` and is not checked
+	/// to be MDS.
+	pub fn new(
+		width: usize,
+		full_rounds: usize,
+		partial_rounds: usize,
+		round_constants: Vec<Scalar>,
+		mds: Vec<Vec<Scalar>>,
+	) -> Result<Self, R1CSError> {
+		if width < 2 {
+			return Err(gadget_error("width must be at least 2"));
+		}
+		if full_rounds == 0 || full_rounds % 2 != 0 {
+			return Err(gadget_error("number of full rounds must be even and nonzero"));
+		}
+		if round_constants.len() != width * (full_rounds + partial_rounds) {
+			return Err(gadget_error("wrong number of round constants"));
+		}
+		if mds.len() != width || mds.iter().any(|row| row.len() != width) {
+			return Err(gadget_error("MDS matrix must be width x width"));
+		}
+
+		Ok(PoseidonParams {
+			width,
+			full_rounds,
+			partial_rounds,
+			round_constants,
+			mds,
+		})
+	}
+
+	/// Generates parameters for the given width and numbers of rounds.
+	///
+	/// The round constants are squeezed from SHAKE256 seeded with the width
+	/// and numbers of rounds, and the MDS matrix is the Cauchy matrix
+	/// `M[i][j] = 1 / (i + width + j)`.
+	pub fn generate(width: usize, full_rounds: usize, partial_rounds: usize) -> Result<Self, R1CSError> {
+		let seed: Vec<u8> = [width, full_rounds, partial_rounds]
+			.iter()
+			.flat_map(|param| (*param as u64).to_le_bytes().to_vec())
+			.collect();
+		let round_constants = squeeze_scalars(b"PoseidonRoundConstants", &seed, width * (full_rounds + partial_rounds));
+
+		let mds = (0..width)
+			.map(|i| {
+				(0..width)
+					.map(|j| Scalar::from((i + width + j) as u64).invert())
+					.collect()
+			})
+			.collect();
+
+		PoseidonParams::new(width, full_rounds, partial_rounds, round_constants, mds)
+	}
+
+	/// Returns the number of state elements.
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	/// Returns the total number of full rounds.
+	pub fn full_rounds(&self) -> usize {
+		self.full_rounds
+	}
+
+	/// Returns the number of partial rounds.
+	pub fn partial_rounds(&self) -> usize {
+		self.partial_rounds
+	}
+
+	/// Applies the permutation to `state` in place.
+	///
+	/// # Panics
+	///
+	/// Panics if `state.len()` is not the width of the permutation.
+	pub fn permute(&self, state: &mut [Scalar]) {
+		assert_eq!(state.len(), self.width);
+
+		for (round, constants) in self.round_constants.chunks(self.width).enumerate() {
+			for (s, c) in state.iter_mut().zip(constants) {
+				*s += c;
+			}
+
+			let sbox_width = if self.is_full_round(round) { self.width } else { 1 };
+			for s in state[..sbox_width].iter_mut() {
+				*s = pow5(*s);
+			}
+
+			let mixed: Vec<Scalar> = self
+				.mds
+				.iter()
+				.map(|row| row.iter().zip(state.iter()).map(|(m, s)| m * s).sum())
+				.collect();
+			state.copy_from_slice(&mixed);
+		}
+	}
+
+	/// Hashes `inputs` to a single scalar.
+	pub fn hash(&self, inputs: &[Scalar]) -> Scalar {
+		let mut state = vec![Scalar::zero(); self.width];
+		state[0] = Scalar::from(inputs.len() as u64);
+
+		if inputs.is_empty() {
+			self.permute(&mut state);
+		}
+		for chunk in inputs.chunks(self.width - 1) {
+			for (s, x) in state[1..].iter_mut().zip(chunk) {
+				*s += x;
+			}
+			self.permute(&mut state);
+		}
+
+		state[1]
+	}
+
+	fn is_full_round(&self, round: usize) -> bool {
+		let half = self.full_rounds / 2;
+		round < half || round >= half + self.partial_rounds
+	}
+}
+
+impl Default for PoseidonParams {
+	/// Parameters of width 3 with 8 full and 57 partial rounds, targeting
+	/// 128-bit security for hashing two elements at a time.
+	fn default() -> Self {
+		PoseidonParams::generate(3, 8, 57).unwrap()
+	}
+}
+
+/// Constrains the Poseidon permutation of `state` and returns the
+/// permuted state.
+///
+/// Uses `3 * (width * full_rounds + partial_rounds)` multipliers.
+pub fn permutation_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &PoseidonParams,
+	mut state: Vec<LinearCombination>,
+) -> Result<Vec<LinearCombination>, R1CSError> {
+	if state.len() != params.width {
+		return Err(gadget_error("state length must equal the permutation width"));
+	}
+
+	for (round, constants) in params.round_constants.chunks(params.width).enumerate() {
+		for (s, c) in state.iter_mut().zip(constants) {
+			*s += *c;
+		}
+
+		let sbox_width = if params.is_full_round(round) { params.width } else { 1 };
+		for s in state[..sbox_width].iter_mut() {
+			*s = pow5_gadget(cs, s.clone());
+		}
+
+		// Simplifying keeps the linear combinations from growing
+		// exponentially over the partial rounds.
+		state = params
+			.mds
+			.iter()
+			.map(|row| {
+				row.iter()
+					.zip(state.iter())
+					.fold(LinearCombination::default(), |acc, (m, s)| acc + *m * s.clone())
+					.simplify()
+			})
+			.collect();
+	}
+
+	Ok(state)
+}
+
+/// Constrains the Poseidon hash of `inputs` and returns it.
+///
+/// Uses one [`permutation_gadget`] per `width - 1` inputs, and one for an
+/// empty input.
+pub fn hash_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &PoseidonParams,
+	inputs: &[LinearCombination],
+) -> Result<LinearCombination, R1CSError> {
+	let mut state = vec![LinearCombination::default(); params.width];
+	state[0] = Scalar::from(inputs.len() as u64).into();
+
+	if inputs.is_empty() {
+		state = permutation_gadget(cs, params, state)?;
+	}
+	for chunk in inputs.chunks(params.width - 1) {
+		for (s, x) in state[1..].iter_mut().zip(chunk) {
+			*s += x;
+		}
+		state = permutation_gadget(cs, params, state)?;
+	}
+
+	Ok(state.swap_remove(1))
+}
+
+fn gadget_error(description: &str) -> R1CSError {
+	R1CSError::GadgetError {
+		description: format!("Poseidon: {}", description),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::tests::{prove, roundtrip, verify};
+
+	#[test]
+	fn invalid_params() {
+		assert!(PoseidonParams::generate(1, 8, 57).is_err());
+		assert!(PoseidonParams::generate(3, 7, 57).is_err());
+		assert!(PoseidonParams::generate(3, 0, 57).is_err());
+		assert!(PoseidonParams::new(3, 2, 0, vec![Scalar::zero(); 5], vec![vec![Scalar::one(); 3]; 3]).is_err());
+		assert!(PoseidonParams::new(3, 2, 0, vec![Scalar::zero(); 6], vec![vec![Scalar::one(); 2]; 3]).is_err());
+		assert!(PoseidonParams::new(3, 2, 0, vec![Scalar::zero(); 6], vec![vec![Scalar::one(); 3]; 3]).is_ok());
+	}
+
+	#[test]
+	fn native_hash() {
+		let params = PoseidonParams::default();
+		let inputs: Vec<Scalar> = (1..=5u64).map(Scalar::from).collect();
+
+		assert_eq!(params.hash(&inputs), params.hash(&inputs));
+		assert_ne!(params.hash(&inputs[..2]), params.hash(&inputs[..3]));
+		assert_ne!(params.hash(&[]), params.hash(&[Scalar::zero()]));
+		assert_ne!(
+			params.hash(&inputs),
+			PoseidonParams::generate(3, 8, 56).unwrap().hash(&inputs)
+		);
+	}
+
+	#[test]
+	fn gadget_matches_native_hash() {
+		let default_params = PoseidonParams::default();
+		let wide_params = PoseidonParams::generate(5, 8, 60).unwrap();
+		let cases = [
+			(&default_params, 0u64),
+			(&default_params, 1),
+			(&default_params, 3),
+			(&wide_params, 4),
+		];
+
+		for (params, len) in cases.iter() {
+			let values: Vec<u64> = (0..*len).map(|i| 3 * i + 1).collect();
+			let expected = params.hash(&values.iter().map(|v| Scalar::from(*v)).collect::<Vec<_>>());
+			assert!(roundtrip(&values, &|cs, vars| {
+				let inputs: Vec<LinearCombination> = vars.iter().map(|v| (*v).into()).collect();
+				let hash = hash_gadget(cs, params, &inputs)?;
+				cs.constrain(hash - expected);
+				Ok(())
+			})
+			.is_ok());
+		}
+	}
+
+	#[test]
+	fn gadget_rejects_wrong_hash() {
+		let params = PoseidonParams::default();
+		let expected = params.hash(&[Scalar::from(1u64), Scalar::from(2u64)]);
+		let params = &params;
+		let gadget = |expected: Scalar| {
+			move |cs: &mut dyn ConstraintSystem, vars: &[crate::r1cs::Variable]| {
+				let hash = hash_gadget(cs, params, &[vars[0].into(), vars[1].into()])?;
+				cs.constrain(hash - expected);
+				Ok(())
+			}
+		};
+
+		let (proof, commitments) = prove(&[1, 2], &gadget(expected)).unwrap();
+		assert!(verify(&proof, &commitments, &gadget(expected)).is_ok());
+		assert!(verify(&proof, &commitments, &gadget(expected + Scalar::one())).is_err());
+	}
+
+	#[test]
+	fn permutation_gadget_checks_width() {
+		let params = PoseidonParams::default();
+		assert!(roundtrip(&[1, 2], &|cs, vars| {
+			permutation_gadget(cs, &params, vec![vars[0].into(), vars[1].into()]).map(|_| ())
+		})
+		.is_err());
+	}
+}
@@ -0,0 +1,283 @@
+//! Verification of Schnorr signatures over an embedded curve.
+//!
+//! A signature \\((R, s)\\) on a message hash \\(m\\) under the public key
+//! \\(P\\) is valid if \\(s G = R + e P\\), where \\(G\\) is the generator
+//! and the challenge \\(e\\) is the low [`CHALLENGE_BITS`] bits of the
+//! Poseidon hash of \\((R_x, R_y, P_x, P_y, m)\\).  Poseidon is used, rather
+//! than a bit-oriented hash, so that the challenge is cheap to compute in
+//! the constraint system, and the challenge is truncated to halve the
+//! cost of \\(e P\\) while keeping 128-bit security.
+//!
+//! With the [`verify`] gadget, a circuit proves that it knows a valid
+//! signature on committed data without revealing the signature, or the
+//! data and the signer if they are committed too.  Signing requires
+//! reducing \\(s = k + e x\\) modulo the order of the generator, which
+//! comes with the specification of the curve, so this module leaves it to
+//! the signing implementation of the protocol.
+
+use alloc::format;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{
+	bit_at, bit_decomposition,
+	edwards::{self, EdwardsParams, Point, PointVar},
+	poseidon::{self, PoseidonParams},
+	MAX_BITSIZE,
+};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The number of bits of the challenge.
+pub const CHALLENGE_BITS: usize = 128;
+
+/// Parameters of the signature scheme: the embedded curve, its generator,
+/// the Poseidon parameters of the challenge hash, and the number of bits
+/// of the scalars \\(s\\).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchnorrParams {
+	curve: EdwardsParams,
+	generator: Point,
+	hash: PoseidonParams,
+	scalar_bits: usize,
+}
+
+/// A Schnorr signature \\((R, s)\\), with \\(s\\) as an integer less than
+/// the order of the generator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	/// The commitment \\(R\\) to the nonce.
+	pub r: Point,
+	/// The response \\(s\\).
+	pub s: Scalar,
+}
+
+/// A Schnorr signature in a constraint system.
+#[derive(Clone, Debug)]
+pub struct SignatureVar {
+	/// The commitment \\(R\\) to the nonce.
+	pub r: PointVar,
+	/// The response \\(s\\).
+	pub s: LinearCombination,
+}
+
+impl SchnorrParams {
+	/// Creates the parameters of the signature scheme, where `scalar_bits`
+	/// is the bit length of the order of `generator`.
+	///
+	/// Returns a [`R1CSError::GadgetError`] if `generator` is not on the
+	/// curve or `scalar_bits` is not in `1..=MAX_BITSIZE`.  The order of the
+	/// generator is not checked.
+	pub fn new(
+		curve: EdwardsParams,
+		generator: Point,
+		hash: PoseidonParams,
+		scalar_bits: usize,
+	) -> Result<Self, R1CSError> {
+		if !curve.is_on_curve(&generator) {
+			return Err(gadget_error("generator is not on the curve"));
+		}
+		if !(1..=MAX_BITSIZE).contains(&scalar_bits) {
+			return Err(gadget_error(&format!(
+				"scalar bits must satisfy 1 <= n <= {}",
+				MAX_BITSIZE
+			)));
+		}
+		Ok(SchnorrParams {
+			curve,
+			generator,
+			hash,
+			scalar_bits,
+		})
+	}
+
+	/// Returns the parameters of the embedded curve.
+	pub fn curve(&self) -> &EdwardsParams {
+		&self.curve
+	}
+
+	/// Returns the generator.
+	pub fn generator(&self) -> &Point {
+		&self.generator
+	}
+
+	/// Returns the challenge of a signature with the nonce commitment `r`
+	/// by `public_key` on `msg_hash`.
+	///
+	/// Returns `None` in the negligible case that the hash is not less than
+	/// \\(2^{252}\\), which the [`verify`] gadget rejects: the signer must
+	/// then sign with a fresh nonce.
+	pub fn challenge(&self, r: &Point, public_key: &Point, msg_hash: &Scalar) -> Option<Scalar> {
+		let hash = self.hash.hash(&[r.x, r.y, public_key.x, public_key.y, *msg_hash]);
+		let mut bytes = hash.to_bytes();
+		if (MAX_BITSIZE..256).any(|i| bit_at(&bytes, i)) {
+			return None;
+		}
+		for byte in bytes[CHALLENGE_BITS / 8..].iter_mut() {
+			*byte = 0;
+		}
+		Some(Scalar::from_bits(bytes))
+	}
+
+	/// Returns whether `signature` is a valid signature by `public_key` on
+	/// `msg_hash`.
+	///
+	/// This is not constant time, which is fine for public signatures.
+	pub fn verify(&self, public_key: &Point, msg_hash: &Scalar, signature: &Signature) -> bool {
+		let e = match self.challenge(&signature.r, public_key, msg_hash) {
+			Some(e) => e,
+			None => return false,
+		};
+		let s_bytes = signature.s.to_bytes();
+		if (self.scalar_bits..256).any(|i| bit_at(&s_bytes, i)) {
+			return false;
+		}
+		self.curve.is_on_curve(&signature.r)
+			&& self.curve.is_on_curve(public_key)
+			&& self.curve.mul(&self.generator, &signature.s)
+				== self.curve.add(&signature.r, &self.curve.mul(public_key, &e))
+	}
+}
+
+/// Enforces that `signature` is a valid signature by `public_key` on
+/// `msg_hash`.
+///
+/// The points of the signature and the public key are constrained to be on
+/// the curve, but not to be in the subgroup of the generator: a protocol
+/// whose curve has a cofactor must check public keys when registering
+/// them.  The prover returns a [`R1CSError::GadgetError`] if its
+/// assignments do not fit in the bits decomposed by the gadget.  Uses
+/// about `5 * scalar_bits + 16 * CHALLENGE_BITS + 2000` multipliers with
+/// the default Poseidon parameters.
+pub fn verify<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	params: &SchnorrParams,
+	public_key: &PointVar,
+	msg_hash: LinearCombination,
+	signature: &SignatureVar,
+) -> Result<(), R1CSError> {
+	let curve = &params.curve;
+	edwards::constrain_on_curve(cs, curve, &signature.r);
+	edwards::constrain_on_curve(cs, curve, public_key);
+
+	let hash = poseidon::hash_gadget(cs, &params.hash, &[
+		signature.r.x.clone(),
+		signature.r.y.clone(),
+		public_key.x.clone(),
+		public_key.y.clone(),
+		msg_hash,
+	])?;
+	// The decomposition is unique since 2^252 is less than the field
+	// order, so the challenge is its low bits.
+	let hash_bits = bit_decomposition(cs, hash, MAX_BITSIZE)?;
+	let e_public_key = edwards::scalar_mul_bits_gadget(cs, curve, &hash_bits[..CHALLENGE_BITS], public_key)?;
+
+	let s_generator =
+		edwards::fixed_base_mul_gadget(cs, curve, signature.s.clone(), params.scalar_bits, &params.generator)?;
+	let expected = edwards::add_gadget(cs, curve, &signature.r, &e_public_key)?;
+
+	cs.constrain_labeled(s_generator.x - expected.x, "schnorr");
+	cs.constrain_labeled(s_generator.y - expected.y, "schnorr");
+	Ok(())
+}
+
+fn gadget_error(description: &str) -> R1CSError {
+	R1CSError::GadgetError {
+		description: format!("Schnorr: {}", description),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::tests::{prove_statement, verify_statement},
+		Variable,
+	};
+
+	/// The test curve of the `edwards` tests, whose group order is not
+	/// known, with signatures computed over the integers: for secret keys
+	/// below `2^64` and nonces below `2^200`, `s = k + e x` needs no
+	/// reduction.
+	fn params() -> SchnorrParams {
+		let curve = EdwardsParams::new(-Scalar::one(), Scalar::from(2u64)).unwrap();
+		let x = Scalar::from_canonical_bytes([
+			0x56, 0xd2, 0x01, 0xf8, 0x58, 0xb4, 0xdb, 0x73, 0x1a, 0x58, 0x74, 0x16, 0x1c, 0x2d, 0xa7, 0xe1, 0x29, 0x2f,
+			0x7b, 0x95, 0x8f, 0x75, 0x2a, 0xbe, 0x4e, 0x57, 0xff, 0x7e, 0x4f, 0xd0, 0xe9, 0x00,
+		])
+		.unwrap();
+		let generator = Point {
+			x,
+			y: Scalar::from(2u64),
+		};
+		SchnorrParams::new(curve, generator, PoseidonParams::default(), 200).unwrap()
+	}
+
+	fn sign(params: &SchnorrParams, secret_key: u64, msg_hash: &Scalar) -> (Point, Signature) {
+		let public_key = params.curve.mul(&params.generator, &Scalar::from(secret_key));
+		let mut rng = rand::thread_rng();
+		loop {
+			let mut k_bytes = Scalar::random(&mut rng).to_bytes();
+			for byte in k_bytes[24..].iter_mut() {
+				*byte = 0;
+			}
+			let k = Scalar::from_bits(k_bytes);
+			let r = params.curve.mul(&params.generator, &k);
+			if let Some(e) = params.challenge(&r, &public_key, msg_hash) {
+				let s = k + e * Scalar::from(secret_key);
+				return (public_key, Signature { r, s });
+			}
+		}
+	}
+
+	/// Constrains the committed message hash and signature, in `vars`, to
+	/// be signed with `public_key`.
+	fn statement<CS: ConstraintSystem + ?Sized>(
+		cs: &mut CS,
+		params: &SchnorrParams,
+		public_key: &Point,
+		vars: &[Variable],
+	) -> Result<(), R1CSError> {
+		let signature = SignatureVar {
+			r: PointVar {
+				x: vars[1].into(),
+				y: vars[2].into(),
+			},
+			s: vars[3].into(),
+		};
+		verify(cs, params, &(*public_key).into(), vars[0].into(), &signature)
+	}
+
+	#[test]
+	fn native_signatures() {
+		let params = params();
+		let msg_hash = Scalar::from(42u64);
+		let (public_key, signature) = sign(&params, 123_456_789, &msg_hash);
+		assert!(params.verify(&public_key, &msg_hash, &signature));
+		assert!(!params.verify(&public_key, &Scalar::from(43u64), &signature));
+
+		let (other_key, _) = sign(&params, 987_654_321, &msg_hash);
+		assert!(!params.verify(&other_key, &msg_hash, &signature));
+	}
+
+	#[test]
+	fn proves_knowledge_of_a_signature() {
+		let params = params();
+		let msg_hash = Scalar::from(42u64);
+		let (public_key, signature) = sign(&params, 123_456_789, &msg_hash);
+
+		// The public key and the signature are known to the prover only.
+		let values = [msg_hash, signature.r.x, signature.r.y, signature.s];
+		let (proof, commitments) =
+			prove_statement(8192, &values, |cs, vars| statement(cs, &params, &public_key, vars)).unwrap();
+		let verify_proof = |public_key: &Point| {
+			verify_statement(8192, &proof, &commitments, |cs, vars| {
+				statement(cs, &params, public_key, vars)
+			})
+		};
+		assert!(verify_proof(&public_key).is_ok());
+
+		// The proof is bound to the public key.
+		let other_key = params.curve.add(&public_key, &params.generator);
+		assert!(verify_proof(&other_key).is_err());
+	}
+}
@@ -0,0 +1,418 @@
+//! The SHA-256 compression function and hash as constraint system gadgets.
+//!
+//! SHA-256 is far more expensive in a constraint system than
+//! [`poseidon`](super::poseidon), since it works on bits: a compression
+//! costs [`COMPRESSION_MULTIPLIERS`] multipliers, plus 32 per variable
+//! input word.  It is meant for circuits that must bind to data hashed
+//! outside of them, such as Bitcoin block headers or Ethereum storage
+//! proofs.
+//!
+//! The gadgets represent each bit as a linear combination constrained to
+//! be boolean, and fold operations on constant bits: the padding of the
+//! message, the initial state, and the message schedule of a block of
+//! padding only cost no multipliers.
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+
+use super::bit_decomposition;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+/// The initial hash value of SHA-256.
+pub const IV: [u32; 8] = [
+	0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The number of multipliers of a [`compress`] of variable words, besides
+/// the decomposition of its inputs.
+pub const COMPRESSION_MULTIPLIERS: usize = 26_288;
+
+const K: [u32; 64] = [
+	0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+	0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+	0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+	0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+	0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+	0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+	0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+	0xc67178f2,
+];
+
+/// A 32-bit word, as its bits from the least significant one.
+type Word = Vec<LinearCombination>;
+
+/// Applies the SHA-256 compression function to the `state` of 8 words and
+/// the `block` of 16 words, and returns the new state.
+///
+/// The words are linear combinations whose values are in `[0, 2^32)`,
+/// which is enforced by decomposing them.  Returns a
+/// [`R1CSError::GadgetError`] if there are not 8 state and 16 block words,
+/// and the prover returns one if a word does not fit in 32 bits.  Uses
+/// [`COMPRESSION_MULTIPLIERS`] multipliers, plus 32 per word that is not
+/// constant.
+pub fn compress<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	state: &[LinearCombination],
+	block: &[LinearCombination],
+) -> Result<Vec<LinearCombination>, R1CSError> {
+	if state.len() != 8 || block.len() != 16 {
+		return Err(R1CSError::GadgetError {
+			description: "SHA-256 compresses 8 state words and 16 block words".into(),
+		});
+	}
+	let state = state
+		.iter()
+		.map(|w| decompose_word(cs, w.clone()))
+		.collect::<Result<Vec<_>, _>>()?;
+	let block = block
+		.iter()
+		.map(|w| decompose_word(cs, w.clone()))
+		.collect::<Result<Vec<_>, _>>()?;
+	let state = compress_words(cs, &state, &block)?;
+	Ok(state.iter().map(|w| recompose(w)).collect())
+}
+
+/// Constrains the SHA-256 hash of `message` and returns it.
+///
+/// The message is a list of linear combinations whose values are bytes,
+/// which is enforced by decomposing them, and its length is part of the
+/// statement.  Returns the 32 bytes of the hash as linear combinations.
+/// The prover returns a [`R1CSError::GadgetError`] if a byte does not fit
+/// in 8 bits.  Uses 8 multipliers per byte and [`COMPRESSION_MULTIPLIERS`]
+/// per block, or fewer for blocks of padding only.
+pub fn hash_gadget<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	message: &[LinearCombination],
+) -> Result<Vec<LinearCombination>, R1CSError> {
+	let mut bytes: Vec<Vec<LinearCombination>> = message
+		.iter()
+		.map(|b| {
+			Ok(bit_decomposition(cs, b.clone(), 8)?
+				.into_iter()
+				.map(LinearCombination::from)
+				.collect())
+		})
+		.collect::<Result<_, R1CSError>>()?;
+
+	// Padding: a one bit, zeros up to 56 bytes modulo 64, and the length
+	// of the message in bits as a 64-bit big-endian integer.
+	let bit_len = (message.len() as u64) * 8;
+	bytes.push(constant_bits(0x80, 8));
+	while bytes.len() % 64 != 56 {
+		bytes.push(constant_bits(0, 8));
+	}
+	for byte in bit_len.to_be_bytes().iter() {
+		bytes.push(constant_bits(*byte as u64, 8));
+	}
+
+	let words: Vec<Word> = bytes
+		.chunks(4)
+		.map(|be_bytes| be_bytes.iter().rev().flatten().cloned().collect())
+		.collect();
+	let mut state: Vec<Word> = IV.iter().map(|w| constant_bits(*w as u64, 32)).collect();
+	for block in words.chunks(16) {
+		state = compress_words(cs, &state, block)?;
+	}
+
+	Ok(state
+		.iter()
+		.flat_map(|word| word.chunks(8).rev().map(recompose).collect::<Vec<_>>())
+		.collect())
+}
+
+fn compress_words<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	state: &[Word],
+	block: &[Word],
+) -> Result<Vec<Word>, R1CSError> {
+	// Message schedule.
+	let mut w: Vec<Word> = block.to_vec();
+	for t in 16..64 {
+		let s0 = xor3(cs, &rotr(&w[t - 15], 7), &rotr(&w[t - 15], 18), &shr(&w[t - 15], 3));
+		let s1 = xor3(cs, &rotr(&w[t - 2], 17), &rotr(&w[t - 2], 19), &shr(&w[t - 2], 10));
+		let next = add(cs, &[&w[t - 16], &s0, &w[t - 7], &s1], 0)?;
+		w.push(next);
+	}
+
+	let mut v: Vec<Word> = state.to_vec();
+	for t in 0..64 {
+		let s1 = xor3(cs, &rotr(&v[4], 6), &rotr(&v[4], 11), &rotr(&v[4], 25));
+		let ch = ch(cs, &v[4], &v[5], &v[6]);
+		let s0 = xor3(cs, &rotr(&v[0], 2), &rotr(&v[0], 13), &rotr(&v[0], 22));
+		let maj = maj(cs, &v[0], &v[1], &v[2]);
+
+		// a = T1 + T2 and e = d + T1, with T1 = h + S1 + ch + K + W and
+		// T2 = S0 + maj, each with a single decomposition.
+		let a = add(cs, &[&v[7], &s1, &ch, &w[t], &s0, &maj], K[t])?;
+		let e = add(cs, &[&v[3], &v[7], &s1, &ch, &w[t]], K[t])?;
+		v.pop();
+		v.insert(0, a);
+		v[4] = e;
+	}
+
+	state.iter().zip(v.iter()).map(|(h, v)| add(cs, &[h, v], 0)).collect()
+}
+
+/// Returns the sum of `words` and `constant` modulo `2^32`, with one
+/// multiplier per bit of the sum.
+fn add<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	words: &[&[LinearCombination]],
+	constant: u32,
+) -> Result<Word, R1CSError> {
+	let sum = words
+		.iter()
+		.fold(LinearCombination::from(constant as u64), |acc, word| {
+			acc + recompose(word)
+		});
+	// The sum is less than (words.len() + 1) * 2^32.
+	let n_bits = 32 + (64 - (words.len() as u64).leading_zeros()) as usize;
+	if let Some(value) = constant_value(&sum) {
+		let bytes = value.to_bytes();
+		return Ok(constant_bits(
+			u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
+			32,
+		));
+	}
+	let bits = bit_decomposition(cs, sum, n_bits)?;
+	Ok(bits[..32].iter().map(|b| (*b).into()).collect())
+}
+
+fn decompose_word<CS: ConstraintSystem + ?Sized>(cs: &mut CS, word: LinearCombination) -> Result<Word, R1CSError> {
+	if let Some(value) = constant_value(&word) {
+		let bytes = value.to_bytes();
+		if bytes[4..].iter().all(|b| *b == 0) {
+			return Ok(constant_bits(
+				u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
+				32,
+			));
+		}
+	}
+	Ok(bit_decomposition(cs, word, 32)?
+		.into_iter()
+		.map(Variable::into)
+		.collect())
+}
+
+fn xor3<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: &[LinearCombination],
+	b: &[LinearCombination],
+	c: &[LinearCombination],
+) -> Word {
+	(0..32)
+		.map(|i| {
+			let ab = xor(cs, a[i].clone(), b[i].clone());
+			xor(cs, ab, c[i].clone())
+		})
+		.collect()
+}
+
+/// `(e & f) ^ (!e & g)`, as `g + e (f - g)`.
+fn ch<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	e: &[LinearCombination],
+	f: &[LinearCombination],
+	g: &[LinearCombination],
+) -> Word {
+	(0..32)
+		.map(|i| g[i].clone() + product(cs, e[i].clone(), f[i].clone() - g[i].clone()))
+		.collect()
+}
+
+/// `(a & b) ^ (a & c) ^ (b & c)`, as `b c + a (b + c - 2 b c)`.
+fn maj<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: &[LinearCombination],
+	b: &[LinearCombination],
+	c: &[LinearCombination],
+) -> Word {
+	(0..32)
+		.map(|i| {
+			let bc = product(cs, b[i].clone(), c[i].clone());
+			let b_xor_c = b[i].clone() + c[i].clone() - bc.clone() * Scalar::from(2u64);
+			bc + product(cs, a[i].clone(), b_xor_c)
+		})
+		.collect()
+}
+
+/// `a ^ b`, as `a + b - 2 a b`.
+fn xor<CS: ConstraintSystem + ?Sized>(cs: &mut CS, a: LinearCombination, b: LinearCombination) -> LinearCombination {
+	let ab = product(cs, a.clone(), b.clone());
+	a + b - ab * Scalar::from(2u64)
+}
+
+/// Returns `a * b`, with a multiplier unless one of them is constant.
+fn product<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+) -> LinearCombination {
+	match (constant_value(&a), constant_value(&b)) {
+		(Some(a), _) => b * a,
+		(_, Some(b)) => a * b,
+		_ => cs.multiply(a, b).2.into(),
+	}
+}
+
+fn rotr(word: &[LinearCombination], n: usize) -> Word {
+	(0..32).map(|i| word[(i + n) % 32].clone()).collect()
+}
+
+fn shr(word: &[LinearCombination], n: usize) -> Word {
+	(0..32).map(|i| word.get(i + n).cloned().unwrap_or_default()).collect()
+}
+
+fn recompose(bits: &[LinearCombination]) -> LinearCombination {
+	let mut exp_2 = Scalar::one();
+	let mut result = LinearCombination::default();
+	for bit in bits {
+		result += bit.clone() * exp_2;
+		exp_2 = exp_2 + exp_2;
+	}
+	result
+}
+
+fn constant_bits(value: u64, n: usize) -> Word {
+	(0..n).map(|i| LinearCombination::from((value >> i) & 1)).collect()
+}
+
+/// Returns the value of `lc` if it has no variables.
+fn constant_value(lc: &LinearCombination) -> Option<Scalar> {
+	lc.terms
+		.iter()
+		.map(|(var, coeff)| match var {
+			Variable::One() => Some(*coeff),
+			_ if *coeff == Scalar::zero() => Some(Scalar::zero()),
+			_ => None,
+		})
+		.sum()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::{
+		gadgets::{constrain_equal, tests::roundtrip},
+		TestConstraintSystem,
+	};
+
+	/// Hashes `message` in a test constraint system, and returns whether
+	/// the constraints are satisfied with `digest` as the hash.
+	fn check_hash(message: &[u8], digest: &str) -> bool {
+		let digest = hex::decode(digest).unwrap();
+		let mut cs = TestConstraintSystem::new();
+		let bytes: Vec<LinearCombination> = message
+			.iter()
+			.map(|b| cs.commit(Scalar::from(*b as u64)).into())
+			.collect();
+		let hash = hash_gadget(&mut cs, &bytes).unwrap();
+		for (h, d) in hash.into_iter().zip(digest.iter()) {
+			constrain_equal(&mut cs, h, Scalar::from(*d as u64).into());
+		}
+		cs.is_satisfied()
+	}
+
+	#[test]
+	fn test_vectors() {
+		let two_blocks = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+		let counting: Vec<u8> = (0..119).collect();
+		for (message, digest) in [
+			(
+				&b""[..],
+				"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+			),
+			(
+				&b"abc"[..],
+				"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+			),
+			(
+				&[b'a'; 55][..],
+				"9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+			),
+			(
+				&[b'a'; 56][..],
+				"b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+			),
+			(
+				&[b'a'; 64][..],
+				"ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+			),
+			(
+				&two_blocks[..],
+				"248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+			),
+			(
+				&counting[..],
+				"da18797ed7c3a777f0847f429724a2d8cd5138e6ed2895c3fa1a6d39d18f7ec6",
+			),
+		]
+		.iter()
+		{
+			assert!(check_hash(message, digest));
+		}
+
+		// A wrong digest, and the digest of a different message.
+		assert!(!check_hash(
+			b"abc",
+			"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ae"
+		));
+		assert!(!check_hash(
+			b"abd",
+			"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+		));
+	}
+
+	#[test]
+	fn double_sha256() {
+		let digest = hex::decode("4f8b42c22dd3729b519ba6f68d2da7cc5b2d606d05daed5ad5128cc03e6c6358").unwrap();
+		let mut cs = TestConstraintSystem::new();
+		let bytes: Vec<LinearCombination> = b"abc"
+			.iter()
+			.map(|b| cs.commit(Scalar::from(*b as u64)).into())
+			.collect();
+		let first = hash_gadget(&mut cs, &bytes).unwrap();
+		let second = hash_gadget(&mut cs, &first).unwrap();
+		for (h, d) in second.into_iter().zip(digest.iter()) {
+			constrain_equal(&mut cs, h, Scalar::from(*d as u64).into());
+		}
+		assert!(cs.is_satisfied());
+	}
+
+	#[test]
+	fn compression_metrics() {
+		// The first block of "abc", whose compression is its hash.
+		let mut block = [0u32; 16];
+		block[0] = 0x61626380;
+		block[15] = 24;
+		let expected = [
+			0xba7816bfu32,
+			0x8f01cfea,
+			0x414140de,
+			0x5dae2223,
+			0xb00361a3,
+			0x96177a9c,
+			0xb410ff61,
+			0xf20015ad,
+		];
+
+		let mut cs = TestConstraintSystem::new();
+		let state: Vec<LinearCombination> = IV.iter().map(|w| cs.commit(Scalar::from(*w as u64)).into()).collect();
+		let block: Vec<LinearCombination> = block
+			.iter()
+			.map(|w| cs.commit(Scalar::from(*w as u64)).into())
+			.collect();
+		let new_state = compress(&mut cs, &state, &block).unwrap();
+		for (h, e) in new_state.into_iter().zip(expected.iter()) {
+			constrain_equal(&mut cs, h, Scalar::from(*e as u64).into());
+		}
+		assert!(cs.is_satisfied());
+		assert_eq!(cs.metrics().multipliers, COMPRESSION_MULTIPLIERS + 24 * 32);
+
+		match roundtrip(&[0], &|cs, vars| compress(cs, &[vars[0].into()], &[]).map(|_| ())) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+}
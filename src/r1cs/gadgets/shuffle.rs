@@ -0,0 +1,129 @@
+//! Proof that one committed vector is a permutation of another.
+//!
+//! The [`shuffle`] gadget is the k-shuffle of the R1CS documentation
+//! example, moved out of the example code: it enforces \\(\prod_i (x_i - z) =
+//! \prod_i (y_i - z)\\) for a challenge \\(z\\) of the randomized phase, which
+//! holds with negligible probability unless the \\(y_i\\) are a permutation of
+//! the \\(x_i\\).
+//!
+//! To shuffle tuples, such as the bids of an auction with their bidders,
+//! use [`multiset_equal`](super::multiset::multiset_equal).
+
+use alloc::{format, vec::Vec};
+
+use crate::r1cs::{ConstraintSystem, R1CSError, RandomizableConstraintSystem, RandomizedConstraintSystem, Variable};
+
+/// Enforces that `y` is a permutation of `x`.
+///
+/// Returns a [`R1CSError::GadgetError`] if `x` and `y` have different
+/// lengths.  Uses `2 * (k - 1)` multipliers in the randomized phase for
+/// vectors of length `k > 1`, and none otherwise.
+pub fn shuffle<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	x: Vec<Variable>,
+	y: Vec<Variable>,
+) -> Result<(), R1CSError> {
+	if x.len() != y.len() {
+		return Err(R1CSError::GadgetError {
+			description: format!("cannot shuffle {} values into {}", x.len(), y.len()),
+		});
+	}
+	let k = x.len();
+
+	match k {
+		0 => Ok(()),
+		1 => {
+			cs.constrain(y[0] - x[0]);
+			Ok(())
+		}
+		_ => cs.specify_randomized_constraints(move |cs| {
+			let z = cs.challenge_scalar(b"shuffle challenge");
+
+			// Make last x multiplier for i = k-1 and k-2
+			let (_, _, last_mulx_out) = cs.multiply(x[k - 1] - z, x[k - 2] - z);
+
+			// Make multipliers for x from i == [0, k-3]
+			let first_mulx_out = (0..k - 2).rev().fold(last_mulx_out, |prev_out, i| {
+				let (_, _, out) = cs.multiply(prev_out.into(), x[i] - z);
+				out
+			});
+
+			// Make last y multiplier for i = k-1 and k-2
+			let (_, _, last_muly_out) = cs.multiply(y[k - 1] - z, y[k - 2] - z);
+
+			// Make multipliers for y from i == [0, k-3]
+			let first_muly_out = (0..k - 2).rev().fold(last_muly_out, |prev_out, i| {
+				let (_, _, out) = cs.multiply(prev_out.into(), y[i] - z);
+				out
+			});
+
+			// Constrain last x mul output and last y mul output to be equal
+			cs.constrain(first_mulx_out - first_muly_out);
+
+			Ok(())
+		}),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+	use rand::seq::SliceRandom;
+
+	use crate::r1cs::{
+		gadgets::tests::{prove_statement, verify_statement},
+		R1CSProof,
+	};
+
+	/// Proves that the last half of the committed values is a shuffle of
+	/// the first half.
+	fn prove(input: &[Scalar], output: &[Scalar]) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError> {
+		let k = input.len();
+		let values: Vec<_> = input.iter().chain(output).cloned().collect();
+		prove_statement(128, &values, |cs, vars| {
+			shuffle(cs, vars[..k].to_vec(), vars[k..].to_vec())
+		})
+	}
+
+	fn verify(proof: &R1CSProof, commitments: &[CompressedRistretto]) -> Result<(), R1CSError> {
+		let k = commitments.len() / 2;
+		verify_statement(128, proof, commitments, |cs, vars| {
+			shuffle(cs, vars[..k].to_vec(), vars[k..].to_vec())
+		})
+	}
+
+	fn random_shuffle(k: usize) -> (Vec<Scalar>, Vec<Scalar>) {
+		let mut rng = rand::thread_rng();
+		let input: Vec<Scalar> = (0..k).map(|_| Scalar::from(rand::random::<u8>() as u64)).collect();
+		let mut output = input.clone();
+		output.shuffle(&mut rng);
+		(input, output)
+	}
+
+	#[test]
+	fn shuffles_up_to_64() {
+		for k in [1, 2, 3, 7, 16, 64].iter() {
+			let (input, output) = random_shuffle(*k);
+			let (proof, commitments) = prove(&input, &output).unwrap();
+			assert!(verify(&proof, &commitments).is_ok());
+		}
+	}
+
+	#[test]
+	fn rejects_other_commitments() {
+		let (input, output) = random_shuffle(8);
+		let (proof, mut commitments) = prove(&input, &output).unwrap();
+
+		// A commitment to a value that is not part of the shuffle.
+		let (_, other_commitments) = prove(&[Scalar::from(1000u64)], &[Scalar::from(1000u64)]).unwrap();
+		commitments[8 + 3] = other_commitments[1];
+		assert!(verify(&proof, &commitments).is_err());
+
+		match prove(&input, &output[1..]) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+}
@@ -0,0 +1,118 @@
+//! Sortedness checks, and sorting of committed values.
+//!
+//! [`constrain_sorted`] compares adjacent values, and [`sort`] allocates a
+//! sorted copy of a vector and ties it to the original with a
+//! [`shuffle`](super::shuffle::shuffle).  The sorted copy gives order
+//! statistics such as the minimum, the median or a percentile of the
+//! values by position, e.g. `sorted[sorted.len() / 2]`.
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{constrain_less_or_equal, shuffle::shuffle};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem, Variable};
+
+/// Enforces that `values` are sorted in nondecreasing order, where all of
+/// them are in `[0, 2^n)`.
+///
+/// See [`constrain_less_than`](super::constrain_less_than) for the
+/// preconditions; the prover returns a [`R1CSError::GadgetError`] if two
+/// adjacent values are out of order.  Uses `n * (values.len() - 1)`
+/// multipliers for nonempty inputs.
+pub fn constrain_sorted<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	values: &[LinearCombination],
+	n: usize,
+) -> Result<(), R1CSError> {
+	for pair in values.windows(2) {
+		constrain_less_or_equal(cs, pair[0].clone(), pair[1].clone(), n)?;
+	}
+	Ok(())
+}
+
+/// Returns the `values` sorted in nondecreasing order, where all of them
+/// are in `[0, 2^n)`, and enforces that the result is sorted and a
+/// permutation of `values`.
+///
+/// See [`constrain_sorted`] for the errors returned.  For `k` values, uses
+/// `k / 2` multipliers rounded up, `n * (k - 1)` multipliers, and
+/// `2 * (k - 1)` multipliers in the randomized phase.
+pub fn sort<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	values: Vec<Variable>,
+	n: usize,
+) -> Result<Vec<Variable>, R1CSError> {
+	let assignments: Option<Vec<Scalar>> = values.iter().map(|v| cs.evaluate_lc(&(*v).into())).collect();
+	let sorted_assignments = assignments.map(|mut assignments| {
+		assignments.sort_by(compare);
+		assignments
+	});
+
+	let sorted: Vec<Variable> = (0..values.len())
+		.map(|i| cs.allocate(sorted_assignments.as_ref().map(|a| a[i])))
+		.collect::<Result<_, _>>()?;
+
+	let sorted_lcs: Vec<LinearCombination> = sorted.iter().map(|v| (*v).into()).collect();
+	constrain_sorted(cs, &sorted_lcs, n)?;
+	shuffle(cs, values, sorted.clone())?;
+	Ok(sorted)
+}
+
+/// Compares two scalars as integers in `[0, l)`.
+fn compare(a: &Scalar, b: &Scalar) -> Ordering {
+	a.as_bytes().iter().rev().cmp(b.as_bytes().iter().rev())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::{
+		constrain_equal,
+		tests::{prove_statement, roundtrip, verify_statement},
+	};
+
+	/// Sorts the committed `values` and constrains the median to `median`.
+	fn median_statement<CS: RandomizableConstraintSystem>(
+		cs: &mut CS,
+		vars: Vec<Variable>,
+		median: u64,
+	) -> Result<(), R1CSError> {
+		let sorted = sort(cs, vars, 16)?;
+		let middle = sorted[sorted.len() / 2];
+		constrain_equal(cs, middle.into(), Scalar::from(median).into());
+		Ok(())
+	}
+
+	#[test]
+	fn sorted_values() {
+		assert!(roundtrip(&[1, 1, 4, 300], &|cs, vars| {
+			let lcs: Vec<LinearCombination> = vars.iter().map(|v| (*v).into()).collect();
+			constrain_sorted(cs, &lcs, 16)
+		})
+		.is_ok());
+
+		// The prover refuses values out of order.
+		match roundtrip(&[1, 4, 3], &|cs, vars| {
+			let lcs: Vec<LinearCombination> = vars.iter().map(|v| (*v).into()).collect();
+			constrain_sorted(cs, &lcs, 16)
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+	}
+
+	#[test]
+	fn median_of_committed_values() {
+		let values: Vec<_> = [500u64, 3, 65535, 42, 7].iter().map(|v| Scalar::from(*v)).collect();
+		let (proof, commitments) =
+			prove_statement(256, &values, |cs, vars| median_statement(cs, vars.to_vec(), 42)).unwrap();
+		for &(median, valid) in &[(42, true), (7, false)] {
+			let result = verify_statement(256, &proof, &commitments, |cs, vars| {
+				median_statement(cs, vars.to_vec(), median)
+			});
+			assert_eq!(result.is_ok(), valid);
+		}
+	}
+}
@@ -0,0 +1,174 @@
+//! Fixed-width unsigned integer arithmetic, with the semantics of Rust's
+//! `overflowing_*` operations on `u32` and `u64`.
+//!
+//! The operands are linear combinations whose values are in `[0, 2^width)`.
+//! These gadgets do not check this: use
+//! [`range_check`](super::range_check) on the inputs of the circuit, while
+//! the results of the gadgets are always in range.  Each gadget returns the
+//! wrapped result and a boolean flag telling whether the operation
+//! overflowed, so circuits can mirror runtime arithmetic exactly.
+
+use alloc::format;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{
+	bit_decomposition,
+	bits::{recompose, BitOrder},
+	is_nonzero,
+};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The largest supported integer width, in bits.
+pub const MAX_WIDTH: usize = 64;
+
+/// Returns `a + b` wrapped to `width` bits, and a boolean that is `1` if
+/// the addition overflowed, as `u64::overflowing_add` for a width of 64.
+///
+/// The prover returns a [`R1CSError::GadgetError`] if the exact result of
+/// the operation on its assignments does not fit in the bits decomposed by
+/// the gadget, which catches some but not all operands out of range, and
+/// both parties return one if `width` is not in `1..=MAX_WIDTH`.  Uses
+/// `width + 1` multipliers.
+pub fn add_with_carry<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	width: usize,
+) -> Result<(LinearCombination, LinearCombination), R1CSError> {
+	check_width(width)?;
+	// a + b in [0, 2^(width+1)), and the carry is its top bit.
+	let bits = bit_decomposition(cs, a + b, width + 1)?;
+	Ok((recompose(&bits[..width], BitOrder::LittleEndian), bits[width].into()))
+}
+
+/// Returns `a - b` wrapped to `width` bits, and a boolean that is `1` if
+/// the subtraction borrowed, as `u64::overflowing_sub` for a width of 64.
+///
+/// See [`add_with_carry`] for the errors returned.  Uses `width + 1`
+/// multipliers.
+pub fn sub_with_borrow<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	width: usize,
+) -> Result<(LinearCombination, LinearCombination), R1CSError> {
+	check_width(width)?;
+	// a - b + 2^width in [1, 2^(width+1)), whose top bit is set unless the
+	// subtraction borrowed.
+	let bits = bit_decomposition(cs, a - b + pow2(width), width + 1)?;
+	let borrow = LinearCombination::from(Scalar::one()) - bits[width];
+	Ok((recompose(&bits[..width], BitOrder::LittleEndian), borrow))
+}
+
+/// Returns `a * b` wrapped to `width` bits, and a boolean that is `1` if
+/// the multiplication overflowed, as `u64::overflowing_mul` for a width of
+/// 64.
+///
+/// See [`add_with_carry`] for the errors returned.  Uses `2 * width + 3`
+/// multipliers.
+pub fn mul_with_overflow<CS: ConstraintSystem + ?Sized>(
+	cs: &mut CS,
+	a: LinearCombination,
+	b: LinearCombination,
+	width: usize,
+) -> Result<(LinearCombination, LinearCombination), R1CSError> {
+	check_width(width)?;
+	// a * b in [0, 2^(2*width)), which overflows if any of its high bits
+	// is set.
+	let (_, _, product) = cs.multiply(a, b);
+	let bits = bit_decomposition(cs, product.into(), 2 * width)?;
+	let high = recompose(&bits[width..], BitOrder::LittleEndian);
+	let overflow = is_nonzero(cs, high)?;
+	Ok((recompose(&bits[..width], BitOrder::LittleEndian), overflow))
+}
+
+fn check_width(width: usize) -> Result<(), R1CSError> {
+	if !(1..=MAX_WIDTH).contains(&width) {
+		return Err(R1CSError::GadgetError {
+			description: format!("width must satisfy 1 <= width <= {}", MAX_WIDTH),
+		});
+	}
+	Ok(())
+}
+
+fn pow2(n: usize) -> Scalar {
+	(0..n).fold(Scalar::one(), |acc, _| acc + acc)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::r1cs::gadgets::{constrain_equal, tests::roundtrip};
+
+	/// Checks the gadgets against the native operations on `u64`, or on
+	/// `u32` for a width of 32.
+	fn check(a: u64, b: u64, width: usize) {
+		let native = |f32: fn(u32, u32) -> (u32, bool), f64: fn(u64, u64) -> (u64, bool)| match width {
+			32 => {
+				let (r, o) = f32(a as u32, b as u32);
+				(r as u64, o)
+			}
+			_ => f64(a, b),
+		};
+		let expected = [
+			native(u32::overflowing_add, u64::overflowing_add),
+			native(u32::overflowing_sub, u64::overflowing_sub),
+			native(u32::overflowing_mul, u64::overflowing_mul),
+		];
+
+		assert!(roundtrip(&[a, b], &|cs, vars| {
+			let results = [
+				add_with_carry(cs, vars[0].into(), vars[1].into(), width)?,
+				sub_with_borrow(cs, vars[0].into(), vars[1].into(), width)?,
+				mul_with_overflow(cs, vars[0].into(), vars[1].into(), width)?,
+			];
+			for ((value, flag), (expected_value, expected_flag)) in results.iter().zip(expected.iter()) {
+				constrain_equal(cs, value.clone(), Scalar::from(*expected_value).into());
+				constrain_equal(cs, flag.clone(), Scalar::from(*expected_flag as u64).into());
+			}
+			Ok(())
+		})
+		.is_ok());
+	}
+
+	#[test]
+	fn matches_native_arithmetic() {
+		let max32 = u32::max_value() as u64;
+		for (a, b) in [(0, 0), (1, 2), (2, 1), (max32, 1), (max32, max32), (65536, 65536)].iter() {
+			check(*a, *b, 32);
+		}
+		let max64 = u64::max_value();
+		for (a, b) in [
+			(0, 0),
+			(7, 9),
+			(max64, 1),
+			(1, max64),
+			(max64, max64),
+			(1 << 32, 1 << 32),
+		]
+		.iter()
+		{
+			check(*a, *b, 64);
+		}
+	}
+
+	#[test]
+	fn rejects_bad_inputs() {
+		// A sum that does not fit in 33 bits, and an unsupported width.
+		match roundtrip(&[1 << 33, 0], &|cs, vars| {
+			add_with_carry(cs, vars[0].into(), vars[1].into(), 32).map(|_| ())
+		}) {
+			Err(R1CSError::GadgetError { .. }) => {}
+			_ => panic!("expected a gadget error"),
+		}
+		for width in [0, MAX_WIDTH + 1].iter() {
+			match roundtrip(&[0, 0], &|cs, vars| {
+				mul_with_overflow(cs, vars[0].into(), vars[1].into(), *width).map(|_| ())
+			}) {
+				Err(R1CSError::GadgetError { .. }) => {}
+				_ => panic!("expected a gadget error"),
+			}
+		}
+	}
+}
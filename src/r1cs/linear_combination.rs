@@ -4,16 +4,22 @@ use alloc::vec::Vec;
 use core::{
 	cmp::Ord,
 	iter::FromIterator,
-	ops::{Add, Mul, Neg, Sub},
+	ops::{Add, AddAssign, Deref, DerefMut, Mul, MulAssign, Neg, Sub, SubAssign},
+	slice,
 };
 use curve25519_dalek::scalar::Scalar;
-use sp_std::collections::btree_map::BTreeMap;
 
 /// Represents a variable in a constraint system.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub enum Variable {
 	/// Represents an external input specified by a commitment.
 	Committed(usize),
+	/// Represents an entry of a vector of external inputs specified by a
+	/// single vector commitment.
+	VectorCommitted(usize),
+	/// Represents a public input, whose value is known to both the prover
+	/// and the verifier.
+	Public(usize),
 	/// Represents the left input of a multiplication gate.
 	MultiplierLeft(usize),
 	/// Represents the right input of a multiplication gate.
@@ -26,17 +32,13 @@ pub enum Variable {
 
 impl From<Variable> for LinearCombination {
 	fn from(v: Variable) -> LinearCombination {
-		LinearCombination {
-			terms: vec![(v, Scalar::one())],
-		}
+		LinearCombination::from_slice(&[(v, Scalar::one())])
 	}
 }
 
 impl<S: Into<Scalar>> From<S> for LinearCombination {
 	fn from(s: S) -> LinearCombination {
-		LinearCombination {
-			terms: vec![(Variable::One(), s.into())],
-		}
+		LinearCombination::from_slice(&[(Variable::One(), s.into())])
 	}
 }
 
@@ -70,9 +72,7 @@ impl<S: Into<Scalar>> Mul<S> for Variable {
 	type Output = LinearCombination;
 
 	fn mul(self, other: S) -> Self::Output {
-		LinearCombination {
-			terms: vec![(self, other.into())],
-		}
+		LinearCombination::from_slice(&[(self, other.into())])
 	}
 }
 
@@ -82,9 +82,7 @@ impl Add<Variable> for Scalar {
 	type Output = LinearCombination;
 
 	fn add(self, other: Variable) -> Self::Output {
-		LinearCombination {
-			terms: vec![(Variable::One(), self), (other, Scalar::one())],
-		}
+		LinearCombination::from_slice(&[(Variable::One(), self), (other, Scalar::one())])
 	}
 }
 
@@ -92,9 +90,7 @@ impl Sub<Variable> for Scalar {
 	type Output = LinearCombination;
 
 	fn sub(self, other: Variable) -> Self::Output {
-		LinearCombination {
-			terms: vec![(Variable::One(), self), (other, -Scalar::one())],
-		}
+		LinearCombination::from_slice(&[(Variable::One(), self), (other, -Scalar::one())])
 	}
 }
 
@@ -102,50 +98,259 @@ impl Mul<Variable> for Scalar {
 	type Output = LinearCombination;
 
 	fn mul(self, other: Variable) -> Self::Output {
-		LinearCombination {
-			terms: vec![(other, self)],
+		LinearCombination::from_slice(&[(other, self)])
+	}
+}
+
+/// The number of terms above which a linear combination built by
+/// additions and subtractions is simplified automatically.
+const SIMPLIFY_THRESHOLD: usize = 64;
+
+/// The number of terms a [`LinearCombination`] holds without allocating.
+const INLINE_TERMS: usize = 4;
+
+/// The terms of a [`LinearCombination`].
+///
+/// Each operand of `+` and `-` on variables is a linear combination of its
+/// own, so `a + b - c` creates three of them.  Up to four terms, which
+/// covers most linear combinations of gadgets, are held inline, and the
+/// terms only move to a vector once there are more.
+#[derive(Clone, Debug)]
+pub(super) enum Terms {
+	/// The first `len` of the `terms` are used.
+	Inline {
+		len: usize,
+		terms: [(Variable, Scalar); INLINE_TERMS],
+	},
+	/// Terms that did not fit inline.
+	Heap(Vec<(Variable, Scalar)>),
+}
+
+impl Terms {
+	fn new() -> Self {
+		Terms::Inline {
+			len: 0,
+			terms: [(Variable::One(), Scalar::zero()); INLINE_TERMS],
+		}
+	}
+
+	/// Appends a term, moving the terms to a vector if they no longer fit
+	/// inline.
+	pub(super) fn push(&mut self, term: (Variable, Scalar)) {
+		match self {
+			Terms::Inline { len, terms } if *len < INLINE_TERMS => {
+				terms[*len] = term;
+				*len += 1;
+			}
+			Terms::Inline { len, terms } => {
+				let mut heap = Vec::with_capacity(2 * INLINE_TERMS);
+				heap.extend_from_slice(&terms[..*len]);
+				heap.push(term);
+				*self = Terms::Heap(heap);
+			}
+			Terms::Heap(heap) => heap.push(term),
+		}
+	}
+
+	fn truncate(&mut self, new_len: usize) {
+		match self {
+			Terms::Inline { len, .. } => *len = new_len.min(*len),
+			Terms::Heap(heap) => heap.truncate(new_len),
+		}
+	}
+
+	fn into_vec(self) -> Vec<(Variable, Scalar)> {
+		match self {
+			Terms::Inline { len, terms } => terms[..len].to_vec(),
+			Terms::Heap(heap) => heap,
 		}
 	}
 }
 
+impl Deref for Terms {
+	type Target = [(Variable, Scalar)];
+
+	fn deref(&self) -> &Self::Target {
+		match self {
+			Terms::Inline { len, terms } => &terms[..*len],
+			Terms::Heap(heap) => heap,
+		}
+	}
+}
+
+impl DerefMut for Terms {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		match self {
+			Terms::Inline { len, terms } => &mut terms[..*len],
+			Terms::Heap(heap) => heap,
+		}
+	}
+}
+
+impl Extend<(Variable, Scalar)> for Terms {
+	fn extend<T>(&mut self, iter: T)
+	where
+		T: IntoIterator<Item = (Variable, Scalar)>,
+	{
+		let iter = iter.into_iter();
+		if let Terms::Heap(heap) = self {
+			heap.reserve(iter.size_hint().0);
+		}
+		for term in iter {
+			self.push(term);
+		}
+	}
+}
+
+impl FromIterator<(Variable, Scalar)> for Terms {
+	fn from_iter<T>(iter: T) -> Self
+	where
+		T: IntoIterator<Item = (Variable, Scalar)>,
+	{
+		let mut terms = Terms::new();
+		terms.extend(iter);
+		terms
+	}
+}
+
+impl<'a> IntoIterator for &'a Terms {
+	type IntoIter = slice::Iter<'a, (Variable, Scalar)>;
+	type Item = &'a (Variable, Scalar);
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl IntoIterator for Terms {
+	type IntoIter = alloc::vec::IntoIter<(Variable, Scalar)>;
+	type Item = (Variable, Scalar);
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.into_vec().into_iter()
+	}
+}
+
 /// Represents a linear combination of
 /// [`Variables`](::r1cs::Variable).  Each term is represented by a
 /// `(Variable, Scalar)` pair.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Adding or subtracting linear combinations concatenates their terms.
+/// The assigning operators `+=`, `-=` and `*=`, which take variables,
+/// scalars and linear combinations, update the terms in place, so that a
+/// loop accumulating a linear combination does not move it on every
+/// step.
+/// Once a linear combination has more than 64 terms, and twice as many as
+/// after it was last simplified, it is [simplified](Self::simplify)
+/// automatically, so that long chains of additions of the same variables
+/// stay small without calling [`simplify`](Self::simplify) by hand.
+/// Linear combinations of up to four terms do not allocate.
+#[derive(Clone, Debug)]
 pub struct LinearCombination {
-	pub(super) terms: Vec<(Variable, Scalar)>,
+	pub(super) terms: Terms,
+	/// The number of terms after the last simplification.
+	simplified_len: usize,
 }
 
 impl LinearCombination {
+	pub(super) fn from_terms(terms: Vec<(Variable, Scalar)>) -> Self {
+		LinearCombination {
+			terms: Terms::Heap(terms),
+			simplified_len: 0,
+		}
+	}
+
+	fn from_slice(terms: &[(Variable, Scalar)]) -> Self {
+		terms.iter().collect()
+	}
+
 	pub fn get_terms(self) -> Vec<(Variable, Scalar)> {
+		self.terms.into_vec()
+	}
+
+	/// Evaluates the linear combination, given the value of each of its
+	/// variables by `lookup`.
+	///
+	/// `lookup` is called once for each term, except for the terms of
+	/// [`Variable::One`], whose value is one.
+	pub fn evaluate(&self, lookup: impl Fn(Variable) -> Scalar) -> Scalar {
 		self.terms
+			.iter()
+			.map(|&(var, coeff)| match var {
+				Variable::One() => coeff,
+				var => coeff * lookup(var),
+			})
+			.sum()
 	}
 
 	/// Simplify linear combination by taking Variables common across terms and
-	/// adding their corresponding scalars. Useful when linear combinations
-	/// become large. Takes ownership of linear combination as this function is
-	/// useful when memory is limited and the obvious action after this function
-	/// call will be to free the memory held by the old linear combination
-	pub fn simplify(self) -> Self {
-		// Build hashmap to hold unique variables with their values.
-		let mut vars: BTreeMap<Variable, Scalar> = BTreeMap::new();
+	/// adding their corresponding scalars, and sorting the terms by
+	/// variable. Useful when linear combinations become large. Takes
+	/// ownership of linear combination as this function is useful when memory
+	/// is limited and the obvious action after this function call will be to
+	/// free the memory held by the old linear combination
+	pub fn simplify(mut self) -> Self {
+		self.simplify_in_place();
+		self
+	}
 
-		let terms = self.get_terms();
-		for (var, val) in terms {
-			*vars.entry(var).or_insert_with(Scalar::zero) += val;
+	fn simplify_in_place(&mut self) {
+		self.terms.sort_by(|(a, _), (b, _)| a.cmp(b));
+		let mut len = 0;
+		for i in 0..self.terms.len() {
+			if len > 0 && self.terms[len - 1].0 == self.terms[i].0 {
+				let coeff = self.terms[i].1;
+				self.terms[len - 1].1 += coeff;
+			} else {
+				self.terms.swap(len, i);
+				len += 1;
+			}
 		}
+		self.terms.truncate(len);
+		self.simplified_len = len;
+	}
 
-		let mut new_lc_terms = vec![];
-		for (var, val) in vars {
-			new_lc_terms.push((var, val));
+	/// Simplifies the linear combination if it has grown enough since it
+	/// was last simplified, which keeps the cost of simplification linear
+	/// in the number of terms added.
+	fn simplify_if_large(&mut self) {
+		if self.terms.len() > SIMPLIFY_THRESHOLD.max(2 * self.simplified_len) {
+			self.simplify_in_place();
 		}
-		new_lc_terms.iter().collect()
+	}
+
+	/// Renumbers the variables of the linear combination as if their
+	/// constraint system was preceded by one with `committed` committed
+	/// variables, `public` public inputs and `multipliers` multipliers.
+	pub(super) fn shift(mut self, committed: usize, public: usize, multipliers: usize) -> Self {
+		for (var, _) in self.terms.iter_mut() {
+			*var = match *var {
+				Variable::Committed(i) => Variable::Committed(committed + i),
+				Variable::Public(i) => Variable::Public(public + i),
+				Variable::MultiplierLeft(i) => Variable::MultiplierLeft(multipliers + i),
+				Variable::MultiplierRight(i) => Variable::MultiplierRight(multipliers + i),
+				Variable::MultiplierOutput(i) => Variable::MultiplierOutput(multipliers + i),
+				var => var,
+			};
+		}
+		self
+	}
+}
+
+/// Linear combinations are equal if they have the same terms in the same
+/// order, regardless of when they were last simplified.
+impl PartialEq for LinearCombination {
+	fn eq(&self, other: &Self) -> bool {
+		self.terms[..] == other.terms[..]
 	}
 }
 
 impl Default for LinearCombination {
 	fn default() -> Self {
-		LinearCombination { terms: Vec::new() }
+		LinearCombination {
+			terms: Terms::new(),
+			simplified_len: 0,
+		}
 	}
 }
 
@@ -156,6 +361,7 @@ impl FromIterator<(Variable, Scalar)> for LinearCombination {
 	{
 		LinearCombination {
 			terms: iter.into_iter().collect(),
+			simplified_len: 0,
 		}
 	}
 }
@@ -165,9 +371,7 @@ impl<'a> FromIterator<&'a (Variable, Scalar)> for LinearCombination {
 	where
 		T: IntoIterator<Item = &'a (Variable, Scalar)>,
 	{
-		LinearCombination {
-			terms: iter.into_iter().cloned().collect(),
-		}
+		iter.into_iter().cloned().collect()
 	}
 }
 
@@ -177,8 +381,8 @@ impl<L: Into<LinearCombination>> Add<L> for LinearCombination {
 	type Output = Self;
 
 	fn add(mut self, rhs: L) -> Self::Output {
-		self.terms.extend(rhs.into().terms.iter().cloned());
-		LinearCombination { terms: self.terms }
+		self += rhs;
+		self
 	}
 }
 
@@ -186,9 +390,58 @@ impl<L: Into<LinearCombination>> Sub<L> for LinearCombination {
 	type Output = Self;
 
 	fn sub(mut self, rhs: L) -> Self::Output {
+		self -= rhs;
+		self
+	}
+}
+
+impl<L: Into<LinearCombination>> AddAssign<L> for LinearCombination {
+	fn add_assign(&mut self, rhs: L) {
+		self.terms.extend(rhs.into().terms.iter().cloned());
+		self.simplify_if_large();
+	}
+}
+
+impl<L: Into<LinearCombination>> SubAssign<L> for LinearCombination {
+	fn sub_assign(&mut self, rhs: L) {
 		self.terms
-			.extend(rhs.into().terms.iter().map(|(var, coeff)| (*var, -coeff)));
-		LinearCombination { terms: self.terms }
+			.extend(rhs.into().terms.iter().map(|&(var, coeff)| (var, -coeff)));
+		self.simplify_if_large();
+	}
+}
+
+// Arithmetic with borrowed linear combinations copies their terms, rather
+// than cloning them into a new linear combination first.
+
+impl<'a> Add<&'a LinearCombination> for LinearCombination {
+	type Output = Self;
+
+	fn add(mut self, rhs: &'a LinearCombination) -> Self::Output {
+		self += rhs;
+		self
+	}
+}
+
+impl<'a> Sub<&'a LinearCombination> for LinearCombination {
+	type Output = Self;
+
+	fn sub(mut self, rhs: &'a LinearCombination) -> Self::Output {
+		self -= rhs;
+		self
+	}
+}
+
+impl<'a> AddAssign<&'a LinearCombination> for LinearCombination {
+	fn add_assign(&mut self, rhs: &'a LinearCombination) {
+		self.terms.extend(rhs.terms.iter().cloned());
+		self.simplify_if_large();
+	}
+}
+
+impl<'a> SubAssign<&'a LinearCombination> for LinearCombination {
+	fn sub_assign(&mut self, rhs: &'a LinearCombination) {
+		self.terms.extend(rhs.terms.iter().map(|&(var, coeff)| (var, -coeff)));
+		self.simplify_if_large();
 	}
 }
 
@@ -196,12 +449,7 @@ impl Mul<LinearCombination> for Scalar {
 	type Output = LinearCombination;
 
 	fn mul(self, other: LinearCombination) -> Self::Output {
-		let out_terms = other
-			.terms
-			.into_iter()
-			.map(|(var, scalar)| (var, scalar * self))
-			.collect();
-		LinearCombination { terms: out_terms }
+		other * self
 	}
 }
 
@@ -220,10 +468,117 @@ impl<S: Into<Scalar>> Mul<S> for LinearCombination {
 	type Output = Self;
 
 	fn mul(mut self, other: S) -> Self::Output {
+		self *= other;
+		self
+	}
+}
+
+impl<S: Into<Scalar>> MulAssign<S> for LinearCombination {
+	fn mul_assign(&mut self, other: S) {
 		let other = other.into();
 		for (_, s) in self.terms.iter_mut() {
 			*s *= other
 		}
-		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn simplify_merges_and_sorts_terms() {
+		let a = Variable::MultiplierLeft(0);
+		let b = Variable::Committed(1);
+		let lc = (a + b * 2u64 - a * 3u64 + 5u64).simplify();
+		assert_eq!(lc.get_terms(), vec![
+			(b, Scalar::from(2u64)),
+			(a, -Scalar::from(2u64)),
+			(Variable::One(), Scalar::from(5u64)),
+		]);
+	}
+
+	#[test]
+	fn long_sums_stay_small() {
+		let vars: Vec<Variable> = (0..10).map(Variable::Committed).collect();
+		let mut lc = LinearCombination::default();
+		let mut expected = LinearCombination::default();
+		for i in 0..10_000u64 {
+			lc += vars[i as usize % 10] * i;
+			expected.terms.push((vars[i as usize % 10], Scalar::from(i)));
+		}
+		assert!(lc.terms.len() <= 2 * SIMPLIFY_THRESHOLD);
+		assert_eq!(lc.simplify(), expected.simplify());
+
+		// Distinct variables are kept.
+		let mut lc = LinearCombination::default();
+		for i in 0..1000 {
+			lc -= Variable::MultiplierOutput(i);
+		}
+		assert_eq!(lc.simplify().terms.len(), 1000);
+	}
+
+	#[test]
+	fn borrowed_arithmetic_matches_owned() {
+		let (a, b) = (Variable::Committed(0), Variable::MultiplierLeft(1));
+		let lc = a * 2u64 - b;
+		let mut sum = LinearCombination::from(b);
+		sum += &lc;
+		sum -= &lc;
+		assert_eq!(sum, b + lc.clone() - lc.clone());
+		assert_eq!(LinearCombination::from(a) + &lc - &lc, a + lc.clone() - lc);
+	}
+
+	#[test]
+	fn short_linear_combinations_are_inline() {
+		let vars: Vec<Variable> = (0..2 * INLINE_TERMS).map(Variable::Committed).collect();
+		for len in 0..vars.len() {
+			let mut lc = LinearCombination::default();
+			let mut expected = Vec::new();
+			for (i, var) in vars[..len].iter().enumerate() {
+				if i % 2 == 0 {
+					lc += *var;
+					expected.push((*var, Scalar::one()));
+				} else {
+					lc -= *var;
+					expected.push((*var, -Scalar::one()));
+				}
+			}
+			assert_eq!(matches!(lc.terms, Terms::Inline { .. }), len <= INLINE_TERMS);
+			assert_eq!(lc.clone().simplify().terms.len(), len);
+			assert_eq!(lc.get_terms(), expected);
+		}
+	}
+
+	#[test]
+	fn assign_operators_match_binary_operators() {
+		let (a, b) = (Variable::Committed(0), Variable::MultiplierOutput(2));
+		let mut lc = LinearCombination::from(a);
+		lc += b;
+		lc -= Scalar::from(3u64);
+		lc *= 5u64;
+		lc += 7u64;
+		lc -= a * 2u64;
+		assert_eq!(lc, (a + b - Scalar::from(3u64)) * 5u64 + 7u64 - a * 2u64);
+		assert_eq!(lc.simplify().get_terms(), vec![
+			(a, Scalar::from(3u64)),
+			(b, Scalar::from(5u64)),
+			(Variable::One(), -Scalar::from(8u64)),
+		]);
+	}
+
+	#[test]
+	fn evaluate_looks_up_variables() {
+		let (a, b) = (Variable::Committed(0), Variable::MultiplierOutput(1));
+		let lookup = |var| match var {
+			Variable::Committed(0) => Scalar::from(3u64),
+			Variable::MultiplierOutput(1) => Scalar::from(5u64),
+			var => panic!("unexpected variable {:?}", var),
+		};
+
+		let lc = a * 2u64 - b + 7u64;
+		assert_eq!(lc.evaluate(lookup), Scalar::from(8u64));
+		assert_eq!((lc.clone() - lc).evaluate(lookup), Scalar::zero());
+		assert_eq!(LinearCombination::default().evaluate(lookup), Scalar::zero());
 	}
 }
@@ -0,0 +1,409 @@
+#![allow(non_snake_case)]
+
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+
+use super::{Circuit, ConstraintSystem, LinearCombination, Metrics, Phase, R1CSError, Variable};
+
+/// A [`ConstraintSystem`] implementation for testing gadgets, which checks
+/// the constraints against the assignments without creating a proof.
+///
+/// Unlike a [`Prover`](super::Prover), which panics as soon as a
+/// constraint is not satisfied, the test constraint system records every
+/// constraint, so that a test can find out which one fails with
+/// [`which_is_unsatisfied`](TestConstraintSystem::which_is_unsatisfied).
+/// Constraints are labelled with
+/// [`constrain_labeled`](ConstraintSystem::constrain_labeled) and
+/// grouped with [`namespace`](TestConstraintSystem::namespace), e.g.
+///
+/// ```text
+/// cs.namespace("range check", |cs| range_check(cs, a.into(), 8))?;
+/// cs.constrain_labeled(a + b - c, "sum");
+/// assert_eq!(cs.which_is_unsatisfied(), Some("sum"));
+/// ```
+///
+/// The assignments of a witness built by a gadget can be modified with
+/// [`set_value`](TestConstraintSystem::set_value), to check that the
+/// constraints reject it.
+///
+/// It needs no generators or blinding factors, and its variables are
+/// numbered as in a prover, so a gadget builds the same
+/// [`circuit_digest`](ConstraintSystem::circuit_digest) in both.
+/// Randomized constraints and vector-committed variables are not
+/// supported.
+pub struct TestConstraintSystem {
+	transcript: Transcript,
+	/// The constraints accumulated so far.
+	constraints: Vec<LinearCombination>,
+	/// The labels of the constraints.
+	constraint_labels: Vec<String>,
+	/// The labels of the multipliers.
+	multiplier_labels: Vec<String>,
+	/// The namespaces entered so far, outermost first.
+	namespaces: Vec<String>,
+	/// Stores assignments to the "left" of multiplication gates
+	a_L: Vec<Scalar>,
+	/// Stores assignments to the "right" of multiplication gates
+	a_R: Vec<Scalar>,
+	/// Stores assignments to the "output" of multiplication gates
+	a_O: Vec<Scalar>,
+	/// Values of the committed variables
+	v: Vec<Scalar>,
+	/// Values of the public inputs
+	public: Vec<Scalar>,
+	/// Index of a pending multiplier that's not fully assigned yet.
+	pending_multiplier: Option<usize>,
+}
+
+impl Default for TestConstraintSystem {
+	fn default() -> Self {
+		TestConstraintSystem::new()
+	}
+}
+
+impl TestConstraintSystem {
+	/// Construct an empty test constraint system.
+	pub fn new() -> Self {
+		TestConstraintSystem {
+			transcript: Transcript::new(b"TestConstraintSystem"),
+			constraints: Vec::new(),
+			constraint_labels: Vec::new(),
+			multiplier_labels: Vec::new(),
+			namespaces: Vec::new(),
+			a_L: Vec::new(),
+			a_R: Vec::new(),
+			a_O: Vec::new(),
+			v: Vec::new(),
+			public: Vec::new(),
+			pending_multiplier: None,
+		}
+	}
+
+	/// Allocates a committed variable with the value `v`, and returns it.
+	pub fn commit(&mut self, v: Scalar) -> Variable {
+		let i = self.v.len();
+		self.v.push(v);
+		Variable::Committed(i)
+	}
+
+	/// Allocates a public input with the value `x`, and returns it.
+	pub fn allocate_public(&mut self, x: Scalar) -> Variable {
+		let i = self.public.len();
+		self.public.push(x);
+		Variable::Public(i)
+	}
+
+	/// Replaces the value assigned to `var`, e.g. to check that the
+	/// constraints reject a modified witness.
+	///
+	/// The assignments of the other variables are left as they are, so
+	/// changing an input of a multiplier breaks it unless its output is
+	/// changed accordingly.
+	///
+	/// # Panics
+	///
+	/// Panics if `var` has not been allocated, or is a constant.
+	pub fn set_value(&mut self, var: Variable, value: Scalar) {
+		match var {
+			Variable::MultiplierLeft(i) => self.a_L[i] = value,
+			Variable::MultiplierRight(i) => self.a_R[i] = value,
+			Variable::MultiplierOutput(i) => self.a_O[i] = value,
+			Variable::Committed(i) => self.v[i] = value,
+			Variable::Public(i) => self.public[i] = value,
+			Variable::VectorCommitted(_) | Variable::One() => panic!("cannot assign {:?}", var),
+		}
+	}
+
+	/// Runs `f` in a namespace called `label`, which prefixes the labels
+	/// of the constraints and multipliers `f` adds.
+	///
+	/// Namespaces nest, and their labels are separated by `/`.
+	pub fn namespace<T, F>(&mut self, label: &str, f: F) -> T
+	where
+		F: FnOnce(&mut Self) -> T,
+	{
+		self.namespaces.push(label.to_string());
+		let result = f(self);
+		self.namespaces.pop();
+		result
+	}
+
+	/// Returns the label of the first multiplier or constraint which is
+	/// not satisfied by the assignments, or `None` if they are all
+	/// satisfied.
+	///
+	/// The multipliers are checked first.  A multiplier is labelled
+	/// `multiplier <i>`, and a constraint added without a label is
+	/// labelled `constraint <i>`, with `i` its index in the whole
+	/// constraint system and prefixed with the enclosing namespaces.  The
+	/// constraints implicitly added by [`ConstraintSystem::multiply`] are
+	/// labelled `multiplier <i> left` and `multiplier <i> right`.
+	pub fn which_is_unsatisfied(&self) -> Option<&str> {
+		let gate = (0..self.a_L.len()).find(|&i| self.a_L[i] * self.a_R[i] != self.a_O[i]);
+		if let Some(i) = gate {
+			return Some(&self.multiplier_labels[i]);
+		}
+		self.unsatisfied_constraints()
+			.first()
+			.map(|&i| self.constraint_labels[i].as_str())
+	}
+
+	/// Returns the indices of the constraints which are not satisfied by
+	/// the assignments, in the order they were added.
+	///
+	/// The constraints implicitly added by
+	/// [`ConstraintSystem::multiply`] are counted, two per call.
+	pub fn unsatisfied_constraints(&self) -> Vec<usize> {
+		self.constraints
+			.iter()
+			.enumerate()
+			.filter(|(_, lc)| self.eval(lc) != Scalar::zero())
+			.map(|(i, _)| i)
+			.collect()
+	}
+
+	/// Returns `true` if the assignments satisfy all the multipliers and
+	/// constraints.
+	pub fn is_satisfied(&self) -> bool {
+		self.which_is_unsatisfied().is_none()
+	}
+
+	/// Returns the constraint system built so far as a [`Circuit`].
+	pub fn circuit(&self) -> Circuit {
+		Circuit::new(self.v.len(), 0, self.public.len(), self.a_L.len(), &self.constraints)
+	}
+
+	/// Returns `label` prefixed with the current namespaces.
+	fn path(&self, label: &str) -> String {
+		let mut path = String::new();
+		for namespace in &self.namespaces {
+			path.push_str(namespace);
+			path.push('/');
+		}
+		path.push_str(label);
+		path
+	}
+
+	fn push_constraint(&mut self, lc: LinearCombination, label: String) {
+		self.constraints.push(lc);
+		self.constraint_labels.push(self.path(&label));
+	}
+
+	fn push_multiplier(&mut self, l: Scalar, r: Scalar, o: Scalar) -> (Variable, Variable, Variable) {
+		let i = self.a_L.len();
+		self.a_L.push(l);
+		self.a_R.push(r);
+		self.a_O.push(o);
+		self.multiplier_labels.push(self.path(&format!("multiplier {}", i)));
+		(
+			Variable::MultiplierLeft(i),
+			Variable::MultiplierRight(i),
+			Variable::MultiplierOutput(i),
+		)
+	}
+
+	fn eval(&self, lc: &LinearCombination) -> Scalar {
+		lc.evaluate(|var| match var {
+			Variable::MultiplierLeft(i) => self.a_L[i],
+			Variable::MultiplierRight(i) => self.a_R[i],
+			Variable::MultiplierOutput(i) => self.a_O[i],
+			Variable::Committed(i) => self.v[i],
+			Variable::Public(i) => self.public[i],
+			Variable::VectorCommitted(_) => panic!("vector commitments are not supported"),
+			Variable::One() => Scalar::one(),
+		})
+	}
+}
+
+impl ConstraintSystem for TestConstraintSystem {
+	fn transcript(&mut self) -> &mut Transcript {
+		&mut self.transcript
+	}
+
+	fn multiply(
+		&mut self,
+		mut left: LinearCombination,
+		mut right: LinearCombination,
+	) -> (Variable, Variable, Variable) {
+		let l = self.eval(&left);
+		let r = self.eval(&right);
+		let (l_var, r_var, o_var) = self.push_multiplier(l, r, l * r);
+
+		let i = self.a_L.len() - 1;
+		left.terms.push((l_var, -Scalar::one()));
+		right.terms.push((r_var, -Scalar::one()));
+		self.push_constraint(left, format!("multiplier {} left", i));
+		self.push_constraint(right, format!("multiplier {} right", i));
+
+		(l_var, r_var, o_var)
+	}
+
+	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError> {
+		let gate = self.pending_multiplier.unwrap_or_else(|| self.a_L.len());
+		let scalar = assignment.ok_or(R1CSError::MissingAssignment { gate })?;
+
+		match self.pending_multiplier {
+			None => {
+				let (l_var, ..) = self.push_multiplier(scalar, Scalar::zero(), Scalar::zero());
+				self.pending_multiplier = Some(self.a_L.len() - 1);
+				Ok(l_var)
+			}
+			Some(i) => {
+				self.pending_multiplier = None;
+				self.a_R[i] = scalar;
+				self.a_O[i] = self.a_L[i] * self.a_R[i];
+				Ok(Variable::MultiplierRight(i))
+			}
+		}
+	}
+
+	fn allocate_multiplier(
+		&mut self,
+		input_assignments: Option<(Scalar, Scalar)>,
+	) -> Result<(Variable, Variable, Variable), R1CSError> {
+		let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment { gate: self.a_L.len() })?;
+		Ok(self.push_multiplier(l, r, l * r))
+	}
+
+	fn multipliers_len(&self) -> usize {
+		self.a_L.len()
+	}
+
+	fn metrics(&self) -> Metrics {
+		Metrics {
+			multipliers: self.a_L.len(),
+			constraints: self.constraints.len(),
+			committed_variables: self.v.len(),
+			vector_committed_variables: 0,
+			vector_commitments: 0,
+			phase: Phase::NonRandomized,
+		}
+	}
+
+	fn reserve_multipliers(&mut self, additional: usize) {
+		self.a_L.reserve(additional);
+		self.a_R.reserve(additional);
+		self.a_O.reserve(additional);
+		self.multiplier_labels.reserve(additional);
+	}
+
+	fn reserve_constraints(&mut self, additional: usize) {
+		self.constraints.reserve(additional);
+		self.constraint_labels.reserve(additional);
+	}
+
+	fn circuit_digest(&self) -> [u8; 32] {
+		self.circuit().digest()
+	}
+
+	fn constrain(&mut self, lc: LinearCombination) {
+		let label = format!("constraint {}", self.constraints.len());
+		self.push_constraint(lc, label);
+	}
+
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		self.push_constraint(lc, label.to_string());
+	}
+
+	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar> {
+		Some(self.eval(lc))
+	}
+
+	fn allocate_single(&mut self, assignment: Option<Scalar>) -> Result<(Variable, Option<Variable>), R1CSError> {
+		let var = self.allocate(assignment)?;
+		match var {
+			Variable::MultiplierLeft(i) => Ok((Variable::MultiplierLeft(i), None)),
+			Variable::MultiplierRight(i) => Ok((Variable::MultiplierRight(i), Some(Variable::MultiplierOutput(i)))),
+			_ => Err(R1CSError::FormatError),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::{
+		generators::PedersenGens,
+		r1cs::{gadgets, Prover},
+	};
+
+	#[test]
+	fn report_unsatisfied_constraints() {
+		let mut cs = TestConstraintSystem::new();
+		let a = cs.commit(Scalar::from(3u64));
+		let b = cs.commit(Scalar::from(4u64));
+		let (_, _, ab) = cs.multiply(a.into(), b.into());
+		cs.constrain(ab - Scalar::from(12u64));
+		assert!(cs.is_satisfied());
+
+		cs.constrain(a + b - Scalar::from(8u64));
+		cs.constrain(a - Scalar::from(3u64));
+		cs.constrain(b - Scalar::from(5u64));
+		assert!(!cs.is_satisfied());
+		assert_eq!(cs.unsatisfied_constraints(), vec![3, 5]);
+		assert_eq!(cs.metrics().constraints, 6);
+
+		// Assignments are still required, as in the prover.
+		assert_eq!(cs.allocate(None), Err(R1CSError::MissingAssignment { gate: 1 }));
+	}
+
+	#[test]
+	fn label_unsatisfied_constraints() {
+		let mut cs = TestConstraintSystem::new();
+		let a = cs.commit(Scalar::from(5u64));
+		let b = cs.commit(Scalar::from(7u64));
+		let bits = cs
+			.namespace("inputs", |cs| {
+				cs.namespace("a", |cs| gadgets::bit_decomposition(cs, a.into(), 4))
+			})
+			.unwrap();
+		let (_, _, ab) = cs.namespace("product", |cs| cs.multiply(a.into(), b.into()));
+		cs.constrain_labeled(ab - Scalar::from(35u64), "product is 35");
+		assert_eq!(cs.which_is_unsatisfied(), None);
+
+		cs.set_value(b, Scalar::from(6u64));
+		assert_eq!(cs.which_is_unsatisfied(), Some("product/multiplier 4 right"));
+		cs.set_value(Variable::MultiplierRight(4), Scalar::from(6u64));
+		assert_eq!(cs.which_is_unsatisfied(), Some("product/multiplier 4"));
+		cs.set_value(Variable::MultiplierOutput(4), Scalar::from(30u64));
+		assert_eq!(cs.which_is_unsatisfied(), Some("product is 35"));
+
+		// A bit that is not boolean fails its multiplier, and the bit
+		// decomposition then fails.
+		cs.set_value(bits[1], Scalar::from(2u64));
+		assert_eq!(cs.which_is_unsatisfied(), Some("inputs/a/multiplier 1"));
+		cs.set_value(Variable::MultiplierOutput(1), Scalar::from(2u64));
+		assert_eq!(cs.which_is_unsatisfied(), Some("inputs/a/boolean"));
+	}
+
+	#[test]
+	fn same_circuit_as_prover() {
+		let gadget = |cs: &mut dyn ConstraintSystem, vars: &[Variable]| {
+			gadgets::range_check(cs, vars[0].into(), 8).unwrap();
+			let (_, _, square) = cs.multiply(vars[0].into(), vars[0].into());
+			cs.constrain(square - vars[1]);
+		};
+
+		let mut cs = TestConstraintSystem::new();
+		let vars = [cs.commit(Scalar::from(12u64)), cs.commit(Scalar::from(144u64))];
+		gadget(&mut cs, &vars);
+		assert!(cs.is_satisfied());
+
+		let pc_gens = PedersenGens::default();
+		let mut transcript = Transcript::new(b"MockTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let vars = [
+			prover.commit(Scalar::from(12u64), Scalar::one()).1,
+			prover.commit(Scalar::from(144u64), Scalar::one()).1,
+		];
+		gadget(&mut prover, &vars);
+		assert_eq!(prover.metrics(), cs.metrics());
+		assert_eq!(prover.circuit_digest(), cs.circuit_digest());
+	}
+}
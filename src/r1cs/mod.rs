@@ -3,18 +3,45 @@
 #[doc(include = "../../docs/cs-proof.md")]
 mod notes {}
 
+mod chunked;
+mod circuit;
+pub mod circuits;
 mod constraint_system;
+pub mod delegation;
+pub mod gadgets;
 mod linear_combination;
+mod mock;
+pub mod mpc;
 mod proof;
 mod prover;
 mod verifier;
+mod verifying_key;
+mod witness;
 
 pub use self::{
-	constraint_system::{ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem},
+	chunked::ChunkedProof,
+	circuit::Circuit,
+	constraint_system::{ConstraintSystem, Metrics, Phase, RandomizableConstraintSystem, RandomizedConstraintSystem},
 	linear_combination::{LinearCombination, Variable},
+	mock::TestConstraintSystem,
 	proof::R1CSProof,
 	prover::Prover,
 	verifier::Verifier,
+	verifying_key::CircuitVerifyingKey,
+	witness::Witness,
 };
 
 pub use crate::errors::R1CSError;
+
+use merlin::Transcript;
+
+use crate::transcript;
+
+/// Returns a digest of the `transcript` of a constraint system proven in
+/// a batch, which binds the batch proof to the commitments of the
+/// constraint system and to any data the application appended to it.
+fn batch_member_digest(transcript: &mut Transcript) -> [u8; 32] {
+	let mut digest = [0u8; 32];
+	transcript::challenge_bytes(transcript, b"r1cs-batch-member", &mut digest);
+	digest
+}
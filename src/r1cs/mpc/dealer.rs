@@ -0,0 +1,435 @@
+//! The `dealer` module contains the API for the dealer state while the dealer
+//! is engaging in an aggregated R1CS proof.
+//!
+//! For more explanation of how the `dealer`, `party`, and `messages` modules
+//! orchestrate the protocol execution, see the documentation in the
+//! [`mpc`](super) module.
+
+use alloc::vec::Vec;
+use core::{iter, ops::Range};
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::Identity,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+use super::{check_layout, messages::*, party_ranges, Circuit, FlattenedConstraints, PartyLayout};
+use crate::{
+	errors::MPCError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::InnerProductProof,
+	r1cs::{R1CSProof, Verifier},
+	transcript::{self, TranscriptProtocol},
+	util,
+};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+/// Used to construct a dealer for the aggregated R1CS proof protocol.
+pub struct Dealer {}
+
+#[allow(clippy::new_ret_no_self)]
+impl Dealer {
+	/// Creates a new dealer coordinating `layout.len()` parties, which
+	/// hold the variables of `circuit` as described by `layout`.
+	///
+	/// Returns [`MPCError::InvalidLayout`] if the layout does not cover
+	/// exactly the committed variables and multipliers of the circuit,
+	/// or if the circuit has vector-committed variables.
+	pub fn new<'a, 'b>(
+		bp_gens: &'b BulletproofGens,
+		pc_gens: &'b PedersenGens,
+		transcript: &'a mut Transcript,
+		circuit: &'b Circuit,
+		layout: &[PartyLayout],
+	) -> Result<DealerAwaitingInputCommitments<'a, 'b>, MPCError> {
+		check_layout(circuit, layout)?;
+		if bp_gens.gens_capacity < circuit.multipliers().next_power_of_two() || bp_gens.party_capacity < 1 {
+			return Err(MPCError::InvalidGeneratorsLength);
+		}
+		let ranges = (0..layout.len())
+			.map(|j| party_ranges(circuit, layout, j))
+			.collect::<Result<_, _>>()?;
+
+		// At the end of the protocol, the dealer will attempt to verify
+		// the proof, and if it fails, determine which party's shares were
+		// invalid.  As for range proofs, we keep a copy of the initial
+		// transcript state to verify the proof with.
+		let initial_transcript = transcript.clone();
+
+		transcript.r1cs_domain_sep();
+
+		Ok(DealerAwaitingInputCommitments {
+			bp_gens,
+			pc_gens,
+			transcript,
+			initial_transcript,
+			circuit,
+			ranges,
+		})
+	}
+}
+
+/// A dealer waiting for the parties to send their [`InputCommitment`]s.
+pub struct DealerAwaitingInputCommitments<'a, 'b> {
+	bp_gens: &'b BulletproofGens,
+	pc_gens: &'b PedersenGens,
+	transcript: &'a mut Transcript,
+	/// The dealer keeps a copy of the initial transcript state, so
+	/// that it can attempt to verify the aggregated proof at the end.
+	initial_transcript: Transcript,
+	circuit: &'b Circuit,
+	/// The committed variables and multipliers of each party.
+	ranges: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl<'a, 'b> DealerAwaitingInputCommitments<'a, 'b> {
+	/// Receive each party's [`InputCommitment`]s and compute the
+	/// [`GateChallenge`].
+	///
+	/// Returns [`MPCError::InvalidLayout`] if a party did not commit to
+	/// the number of variables given by the layout.
+	pub fn receive_input_commitments(
+		self,
+		input_commitments: Vec<InputCommitment>,
+	) -> Result<(DealerAwaitingPolyCommitments<'a, 'b>, GateChallenge), MPCError> {
+		let m = self.ranges.len();
+		if m != input_commitments.len() {
+			return Err(MPCError::WrongNumBitCommitments {
+				expected: m,
+				actual: input_commitments.len(),
+			});
+		}
+		let wrong_size = input_commitments
+			.iter()
+			.zip(self.ranges.iter())
+			.any(|(ic, (committed, _))| ic.V.len() != committed.len());
+		if wrong_size {
+			return Err(MPCError::InvalidLayout);
+		}
+
+		// Commit each V individually, followed by their number, as the
+		// single-party prover does.
+		let V: Vec<CompressedRistretto> = input_commitments.iter().flat_map(|ic| ic.V.iter().cloned()).collect();
+		for V_i in V.iter() {
+			self.transcript.append_point(b"V", V_i);
+		}
+		transcript::append_u64(self.transcript, b"m", V.len() as u64);
+
+		// Bind the proof to the circuit.
+		transcript::append_message(self.transcript, b"circuit", &self.circuit.digest());
+
+		// Commit aggregated A_I, A_O, S
+		let A_I1: RistrettoPoint = input_commitments.iter().map(|ic| ic.A_I).sum();
+		let A_O1: RistrettoPoint = input_commitments.iter().map(|ic| ic.A_O).sum();
+		let S1: RistrettoPoint = input_commitments.iter().map(|ic| ic.S).sum();
+		let (A_I1, A_O1, S1) = (A_I1.compress(), A_O1.compress(), S1.compress());
+		self.transcript.append_point(b"A_I1", &A_I1);
+		self.transcript.append_point(b"A_O1", &A_O1);
+		self.transcript.append_point(b"S1", &S1);
+
+		// A circuit has no randomized constraints, so there are no
+		// second-phase commitments.
+		self.transcript.r1cs_1phase_domain_sep();
+		self.transcript.append_point(b"A_I2", &CompressedRistretto::identity());
+		self.transcript.append_point(b"A_O2", &CompressedRistretto::identity());
+		self.transcript.append_point(b"S2", &CompressedRistretto::identity());
+
+		let y = self.transcript.challenge_scalar(b"y");
+		let z = self.transcript.challenge_scalar(b"z");
+		let gate_challenge = GateChallenge { y, z };
+
+		Ok((
+			DealerAwaitingPolyCommitments {
+				bp_gens: self.bp_gens,
+				pc_gens: self.pc_gens,
+				transcript: self.transcript,
+				initial_transcript: self.initial_transcript,
+				circuit: self.circuit,
+				ranges: self.ranges,
+				V,
+				input_commitments,
+				gate_challenge,
+				A_I1,
+				A_O1,
+				S1,
+			},
+			gate_challenge,
+		))
+	}
+}
+
+/// A dealer which has sent the [`GateChallenge`] to the parties and
+/// is waiting for their [`PolyCommitment`]s.
+pub struct DealerAwaitingPolyCommitments<'a, 'b> {
+	bp_gens: &'b BulletproofGens,
+	pc_gens: &'b PedersenGens,
+	transcript: &'a mut Transcript,
+	initial_transcript: Transcript,
+	circuit: &'b Circuit,
+	ranges: Vec<(Range<usize>, Range<usize>)>,
+	V: Vec<CompressedRistretto>,
+	input_commitments: Vec<InputCommitment>,
+	gate_challenge: GateChallenge,
+	A_I1: CompressedRistretto,
+	A_O1: CompressedRistretto,
+	S1: CompressedRistretto,
+}
+
+impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
+	/// Receive [`PolyCommitment`]s from the parties and compute the
+	/// [`PolyChallenge`].
+	pub fn receive_poly_commitments(
+		self,
+		poly_commitments: Vec<PolyCommitment>,
+	) -> Result<(DealerAwaitingProofShares<'a, 'b>, PolyChallenge), MPCError> {
+		let m = self.ranges.len();
+		if m != poly_commitments.len() {
+			return Err(MPCError::WrongNumPolyCommitments {
+				expected: m,
+				actual: poly_commitments.len(),
+			});
+		}
+
+		// Commit sums of the parties' T_i's
+		let T_1: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_1).sum();
+		let T_3: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_3).sum();
+		let T_4: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_4).sum();
+		let T_5: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_5).sum();
+		let T_6: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_6).sum();
+		let T = [
+			T_1.compress(),
+			T_3.compress(),
+			T_4.compress(),
+			T_5.compress(),
+			T_6.compress(),
+		];
+		self.transcript.append_point(b"T_1", &T[0]);
+		self.transcript.append_point(b"T_3", &T[1]);
+		self.transcript.append_point(b"T_4", &T[2]);
+		self.transcript.append_point(b"T_5", &T[3]);
+		self.transcript.append_point(b"T_6", &T[4]);
+
+		let u = self.transcript.challenge_scalar(b"u");
+		let x = self.transcript.challenge_scalar(b"x");
+		let poly_challenge = PolyChallenge { x };
+
+		Ok((
+			DealerAwaitingProofShares {
+				bp_gens: self.bp_gens,
+				pc_gens: self.pc_gens,
+				transcript: self.transcript,
+				initial_transcript: self.initial_transcript,
+				circuit: self.circuit,
+				ranges: self.ranges,
+				V: self.V,
+				input_commitments: self.input_commitments,
+				gate_challenge: self.gate_challenge,
+				poly_challenge,
+				u,
+				A_I1: self.A_I1,
+				A_O1: self.A_O1,
+				S1: self.S1,
+				T,
+			},
+			poly_challenge,
+		))
+	}
+}
+
+/// A dealer which has sent the [`PolyChallenge`] to the parties and
+/// is waiting to aggregate their [`ProofShare`]s into a
+/// [`R1CSProof`].
+pub struct DealerAwaitingProofShares<'a, 'b> {
+	bp_gens: &'b BulletproofGens,
+	pc_gens: &'b PedersenGens,
+	transcript: &'a mut Transcript,
+	initial_transcript: Transcript,
+	circuit: &'b Circuit,
+	ranges: Vec<(Range<usize>, Range<usize>)>,
+	V: Vec<CompressedRistretto>,
+	input_commitments: Vec<InputCommitment>,
+	gate_challenge: GateChallenge,
+	poly_challenge: PolyChallenge,
+	u: Scalar,
+	A_I1: CompressedRistretto,
+	A_O1: CompressedRistretto,
+	S1: CompressedRistretto,
+	/// The commitments \\(T_1, T_3, T_4, T_5, T_6\\).
+	T: [CompressedRistretto; 5],
+}
+
+impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
+	/// Assembles proof shares into an `R1CSProof`.
+	///
+	/// Used as a helper function by `receive_trusted_shares` (which
+	/// just hands back the result) and `receive_shares` (which
+	/// validates the proof shares).
+	fn assemble_shares(&mut self, proof_shares: &[ProofShare]) -> Result<R1CSProof, MPCError> {
+		if self.ranges.len() != proof_shares.len() {
+			return Err(MPCError::WrongNumProofShares {
+				expected: self.ranges.len(),
+				actual: proof_shares.len(),
+			});
+		}
+
+		// Validate lengths for each share
+		let mut bad_shares = Vec::<_>::new(); // no allocations until we append
+		for (j, (share, (_, gates))) in proof_shares.iter().zip(self.ranges.iter()).enumerate() {
+			if let Err(e) = share.check_size(gates, self.bp_gens) {
+				bad_shares.push((j, e));
+			}
+		}
+		if !bad_shares.is_empty() {
+			return Err(MPCError::MalformedProofShares { bad_shares });
+		}
+
+		let t_x: Scalar = proof_shares.iter().map(|ps| ps.t_x).sum();
+		let t_x_blinding: Scalar = proof_shares.iter().map(|ps| ps.t_x_blinding).sum();
+		let e_blinding: Scalar = proof_shares.iter().map(|ps| ps.e_blinding).sum();
+
+		self.transcript.append_scalar(b"t_x", &t_x);
+		self.transcript.append_scalar(b"t_x_blinding", &t_x_blinding);
+		self.transcript.append_scalar(b"e_blinding", &e_blinding);
+
+		// Get a challenge value to combine statements for the IPP
+		let w = self.transcript.challenge_scalar(b"w");
+		let Q = w * self.pc_gens.B;
+
+		// If the number of multipliers is not a power of 2, the dealer
+		// pads the circuit as the single-party prover does.
+		let y = self.gate_challenge.y;
+		let n = self.circuit.multipliers();
+		let padded_n = n.next_power_of_two();
+		let pad = padded_n - n;
+
+		let l_vec: Vec<Scalar> = proof_shares
+			.iter()
+			.flat_map(|ps| ps.l_vec.iter().cloned())
+			.chain(iter::repeat(Scalar::zero()).take(pad))
+			.collect();
+		let r_vec: Vec<Scalar> = proof_shares
+			.iter()
+			.flat_map(|ps| ps.r_vec.iter().cloned())
+			.chain(util::exp_iter(y).skip(n).take(pad).map(|exp_y| -exp_y))
+			.collect();
+
+		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one())
+			.take(n)
+			.chain(iter::repeat(self.u).take(pad))
+			.collect();
+		let H_factors: Vec<Scalar> = util::exp_iter(y.invert())
+			.zip(G_factors.iter())
+			.map(|(y_inv, u_or_1)| y_inv * u_or_1)
+			.collect();
+
+		let gens = self.bp_gens.share(0);
+		let ipp_proof = InnerProductProof::create(
+			self.transcript,
+			&Q,
+			&G_factors,
+			&H_factors,
+			gens.G(padded_n).cloned().collect(),
+			gens.H(padded_n).cloned().collect(),
+			l_vec,
+			r_vec,
+		);
+
+		Ok(R1CSProof {
+			A_I1: self.A_I1,
+			A_O1: self.A_O1,
+			S1: self.S1,
+			A_I2: CompressedRistretto::identity(),
+			A_O2: CompressedRistretto::identity(),
+			S2: CompressedRistretto::identity(),
+			T_1: self.T[0],
+			T_3: self.T[1],
+			T_4: self.T[2],
+			T_5: self.T[3],
+			T_6: self.T[4],
+			t_x,
+			t_x_blinding,
+			e_blinding,
+			ipp_proof,
+		})
+	}
+
+	/// Assemble the final aggregated [`R1CSProof`] from the given
+	/// `proof_shares`, then validate the proof to ensure that all
+	/// `ProofShare`s were well-formed.
+	///
+	/// This is a convenience wrapper around receive_shares_with_rng
+	#[cfg(feature = "std")]
+	pub fn receive_shares(self, proof_shares: &[ProofShare]) -> Result<R1CSProof, MPCError> {
+		self.receive_shares_with_rng(proof_shares, &mut thread_rng())
+	}
+
+	/// Assemble the final aggregated [`R1CSProof`] from the given
+	/// `proof_shares`, then validate the proof to ensure that all
+	/// `ProofShare`s were well-formed.
+	///
+	/// If the aggregated proof fails to validate, this function audits
+	/// the submitted shares to determine which shares were invalid,
+	/// and returns [`MPCError::MalformedProofShares`] with the indexes
+	/// of the parties whose shares were malformed.  If every share
+	/// passes the audit, it returns [`MPCError::VerificationError`].
+	pub fn receive_shares_with_rng<T: RngCore + CryptoRng>(
+		mut self,
+		proof_shares: &[ProofShare],
+		rng: &mut T,
+	) -> Result<R1CSProof, MPCError> {
+		let proof = self.assemble_shares(proof_shares)?;
+
+		// See comment in `Dealer::new` for why we use `initial_transcript`
+		let verifier = Verifier::from_circuit(&mut self.initial_transcript, self.circuit, &self.V)
+			.map_err(|_| MPCError::InvalidLayout)?;
+		if verifier
+			.verify_with_rng(&proof, self.pc_gens, self.bp_gens, rng)
+			.is_ok()
+		{
+			return Ok(proof);
+		}
+
+		// Proof verification failed. Now audit the parties:
+		let mut bad_shares = Vec::new();
+		for (j, (committed, gates)) in self.ranges.iter().enumerate() {
+			let weights = FlattenedConstraints::new(self.circuit, &self.gate_challenge.z, committed, gates);
+			if let Err(e) = proof_shares[j].audit_share(
+				self.bp_gens,
+				self.pc_gens,
+				gates,
+				&weights,
+				&self.input_commitments[j],
+				&self.gate_challenge,
+				&self.poly_challenge,
+			) {
+				bad_shares.push((j, e));
+			}
+		}
+		if bad_shares.is_empty() {
+			Err(MPCError::VerificationError)
+		} else {
+			Err(MPCError::MalformedProofShares { bad_shares })
+		}
+	}
+
+	/// Assemble the final aggregated [`R1CSProof`] from the given
+	/// `proof_shares`, but skip validation of the proof.
+	///
+	/// ## WARNING
+	///
+	/// This function does **NOT** validate the proof shares.  It is
+	/// suitable for creating aggregated proofs when all parties are
+	/// known by the dealer to be honest.
+	///
+	/// Otherwise, use
+	/// [`receive_shares`](DealerAwaitingProofShares::receive_shares),
+	/// which validates that all shares are well-formed, or else
+	/// detects which party(ies) submitted malformed shares.
+	pub fn receive_trusted_shares(mut self, proof_shares: &[ProofShare]) -> Result<R1CSProof, MPCError> {
+		self.assemble_shares(proof_shares)
+	}
+}
@@ -0,0 +1,152 @@
+//! The `messages` module contains the API for the messages passed between the
+//! parties and the dealer in an aggregated R1CS proof.
+//!
+//! For more explanation of how the `dealer`, `party`, and `messages` modules
+//! orchestrate the protocol execution, see the documentation in the
+//! [`mpc`](super) module.
+
+use alloc::vec::Vec;
+use core::{iter, ops::Range};
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{IsIdentity, VartimeMultiscalarMul},
+};
+
+use super::FlattenedConstraints;
+use crate::{
+	errors::ShareError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::inner_product,
+	util,
+};
+
+/// A commitment to a party's committed variables and to the assignments
+/// of its multipliers.
+#[derive(Clone, Debug)]
+pub struct InputCommitment {
+	pub(super) V: Vec<CompressedRistretto>,
+	pub(super) A_I: RistrettoPoint,
+	pub(super) A_O: RistrettoPoint,
+	pub(super) S: RistrettoPoint,
+}
+
+/// Challenge values derived from all parties' [`InputCommitment`]s.
+#[derive(Copy, Clone, Debug)]
+pub struct GateChallenge {
+	pub(super) y: Scalar,
+	pub(super) z: Scalar,
+}
+
+/// A commitment to a party's share of the coefficients of the polynomial
+/// \\(t(x)\\), except the one the verifier computes.
+#[derive(Copy, Clone, Debug)]
+pub struct PolyCommitment {
+	pub(super) T_1: RistrettoPoint,
+	pub(super) T_3: RistrettoPoint,
+	pub(super) T_4: RistrettoPoint,
+	pub(super) T_5: RistrettoPoint,
+	pub(super) T_6: RistrettoPoint,
+}
+
+/// Challenge values derived from all parties' [`PolyCommitment`]s.
+#[derive(Copy, Clone, Debug)]
+pub struct PolyChallenge {
+	pub(super) x: Scalar,
+}
+
+/// A party's proof share, ready for aggregation into the final
+/// [`R1CSProof`](crate::r1cs::R1CSProof).
+#[derive(Clone, Debug)]
+pub struct ProofShare {
+	pub(super) t_x: Scalar,
+	pub(super) t_x_blinding: Scalar,
+	pub(super) e_blinding: Scalar,
+	pub(super) l_vec: Vec<Scalar>,
+	pub(super) r_vec: Vec<Scalar>,
+}
+
+impl ProofShare {
+	/// Checks that the vectors of the share have one entry for each of the
+	/// party's multipliers, and that the generators cover them.
+	pub(super) fn check_size(&self, gates: &Range<usize>, bp_gens: &BulletproofGens) -> Result<(), ShareError> {
+		if self.l_vec.len() != gates.len() || self.r_vec.len() != gates.len() {
+			return Err(ShareError::WrongSize);
+		}
+
+		if gates.end > bp_gens.gens_capacity {
+			return Err(ShareError::WrongSize);
+		}
+
+		Ok(())
+	}
+
+	/// Audit the proof share of the party holding the multipliers in
+	/// `gates`, whose constraint weights are `weights`, to determine
+	/// whether it is malformed, and if so, which check it fails first.
+	///
+	/// The polynomial commitments cannot be audited, since the parties do
+	/// not commit to their shares of the coefficient \\(t_2\\).
+	#[allow(clippy::too_many_arguments)]
+	pub(super) fn audit_share(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		gates: &Range<usize>,
+		weights: &FlattenedConstraints,
+		input_commitment: &InputCommitment,
+		gate_challenge: &GateChallenge,
+		poly_challenge: &PolyChallenge,
+	) -> Result<(), ShareError> {
+		self.check_size(gates, bp_gens)?;
+
+		if input_commitment.V.iter().any(|V| V.decompress().is_none()) {
+			return Err(ShareError::InvalidValueCommitment);
+		}
+
+		if self.t_x != inner_product(&self.l_vec, &self.r_vec) {
+			return Err(ShareError::InconsistentInnerProduct);
+		}
+
+		let x = poly_challenge.x;
+		let y_inv = gate_challenge.y.invert();
+		let exp_y_inv: Vec<Scalar> = util::exp_iter(y_inv).skip(gates.start).take(gates.len()).collect();
+
+		// l = x * a_L + x * y^-n * W_R + x^2 * a_O + x^3 * s_L
+		let g = self
+			.l_vec
+			.iter()
+			.zip(weights.wR.iter())
+			.zip(exp_y_inv.iter())
+			.map(|((l_i, wR_i), exp_y_inv)| l_i - x * exp_y_inv * wR_i);
+		// y^-n * r = x * a_R + x^3 * s_R + y^-n * (W_O + x * W_L) - 1
+		let h = self
+			.r_vec
+			.iter()
+			.zip(weights.wL.iter().zip(weights.wO.iter()))
+			.zip(exp_y_inv.iter())
+			.map(|((r_i, (wL_i, wO_i)), exp_y_inv)| exp_y_inv * (r_i - wO_i - x * wL_i) + Scalar::one());
+
+		let gens = bp_gens.share(0);
+		let P_check = RistrettoPoint::vartime_multiscalar_mul(
+			iter::once(-x)
+				.chain(iter::once(-x * x))
+				.chain(iter::once(-x * x * x))
+				.chain(iter::once(self.e_blinding))
+				.chain(g)
+				.chain(h),
+			iter::once(&input_commitment.A_I)
+				.chain(iter::once(&input_commitment.A_O))
+				.chain(iter::once(&input_commitment.S))
+				.chain(iter::once(&pc_gens.B_blinding))
+				.chain(gens.G(gates.end).skip(gates.start))
+				.chain(gens.H(gates.end).skip(gates.start)),
+		);
+
+		if P_check.is_identity() {
+			Ok(())
+		} else {
+			Err(ShareError::InconsistentGateCommitment)
+		}
+	}
+}
@@ -0,0 +1,308 @@
+//! The `mpc` module contains an API for producing a single
+//! [`R1CSProof`](super::R1CSProof) for a [`Circuit`] whose witness is held
+//! by several parties, using an aggregated multiparty computation protocol
+//! analogous to the one for range proofs in
+//! [`range_proof_mpc`](::range_proof_mpc).
+//!
+//! The parties agree on a public [`Circuit`] and on a layout of it: each
+//! party holds a contiguous run of the committed variables and of the
+//! multipliers, in party order, described by its [`PartyLayout`].  A party
+//! knows the openings of its commitments and the assignments of its
+//! multipliers, and nothing about those of the other parties.  The linear
+//! constraints of the circuit may freely combine variables held by
+//! different parties, but each multiplier is assigned by a single party,
+//! so both of its inputs must be known to that party.
+//!
+//! The protocol has three rounds, driven by a dealer who never learns the
+//! witness:
+//!
+//! 1. each party sends an [`InputCommitment`] to its commitments and
+//!    multipliers, and the dealer answers with a [`GateChallenge`];
+//! 2. each party sends a [`PolyCommitment`], and the dealer answers with a
+//!    [`PolyChallenge`];
+//! 3. each party sends a [`ProofShare`], and the dealer assembles the proof.
+//!
+//! The resulting proof is verified like any other, with
+//! [`Verifier::from_circuit`](super::Verifier::from_circuit) given the
+//! commitments of all parties in order.
+//!
+//! Circuits with randomized constraints cannot be described by a
+//! [`Circuit`], and circuits with vector-committed variables or public
+//! inputs are not supported.
+//!
+//! [`InputCommitment`]: messages::InputCommitment
+//! [`GateChallenge`]: messages::GateChallenge
+//! [`PolyCommitment`]: messages::PolyCommitment
+//! [`PolyChallenge`]: messages::PolyChallenge
+//! [`ProofShare`]: messages::ProofShare
+
+#![allow(non_snake_case)]
+
+use alloc::vec::Vec;
+use core::ops::Range;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{Circuit, Variable};
+use crate::errors::MPCError;
+
+pub mod dealer;
+pub mod messages;
+pub mod party;
+
+/// The part of a [`Circuit`] held by one party of an aggregation.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PartyLayout {
+	/// The number of committed variables the party holds.
+	pub committed_variables: usize,
+	/// The number of multipliers the party holds.
+	pub multipliers: usize,
+}
+
+/// Returns the committed variables and multipliers held by party `j` in
+/// the `layout` of `circuit`.
+///
+/// Returns [`MPCError::InvalidLayout`] if there is no party `j`, if the
+/// layout does not cover exactly the variables of the circuit, or if the
+/// circuit has vector-committed variables or public inputs.
+fn party_ranges(circuit: &Circuit, layout: &[PartyLayout], j: usize) -> Result<(Range<usize>, Range<usize>), MPCError> {
+	check_layout(circuit, layout)?;
+	if j >= layout.len() {
+		return Err(MPCError::InvalidLayout);
+	}
+	let before = &layout[..j];
+	let v_start: usize = before.iter().map(|p| p.committed_variables).sum();
+	let g_start: usize = before.iter().map(|p| p.multipliers).sum();
+	Ok((
+		v_start..v_start + layout[j].committed_variables,
+		g_start..g_start + layout[j].multipliers,
+	))
+}
+
+/// Checks that `layout` covers exactly the variables of `circuit`.
+fn check_layout(circuit: &Circuit, layout: &[PartyLayout]) -> Result<(), MPCError> {
+	let committed_variables: usize = layout.iter().map(|p| p.committed_variables).sum();
+	let multipliers: usize = layout.iter().map(|p| p.multipliers).sum();
+	if layout.is_empty()
+		|| committed_variables != circuit.committed_variables()
+		|| multipliers != circuit.multipliers()
+		|| circuit.vector_committed_variables() != 0
+		|| circuit.public_inputs() != 0
+	{
+		return Err(MPCError::InvalidLayout);
+	}
+	Ok(())
+}
+
+/// The constraints of a circuit flattened with the challenge `z`, as in
+/// the single-party prover: the weights \\( z \cdot z^Q \cdot W_{L,R,O,V}
+/// \\) of the multipliers in `gates` and of the committed variables in
+/// `committed`.
+struct FlattenedConstraints {
+	wL: Vec<Scalar>,
+	wR: Vec<Scalar>,
+	wO: Vec<Scalar>,
+	wV: Vec<Scalar>,
+}
+
+impl FlattenedConstraints {
+	fn new(circuit: &Circuit, z: &Scalar, committed: &Range<usize>, gates: &Range<usize>) -> Self {
+		let n = gates.len();
+		let mut wL = vec![Scalar::zero(); n];
+		let mut wR = vec![Scalar::zero(); n];
+		let mut wO = vec![Scalar::zero(); n];
+		let mut wV = vec![Scalar::zero(); committed.len()];
+
+		let mut exp_z = *z;
+		for lc in circuit.constraints() {
+			for (var, coeff) in &lc.terms {
+				match var {
+					Variable::MultiplierLeft(i) if gates.contains(i) => {
+						wL[i - gates.start] += exp_z * coeff;
+					}
+					Variable::MultiplierRight(i) if gates.contains(i) => {
+						wR[i - gates.start] += exp_z * coeff;
+					}
+					Variable::MultiplierOutput(i) if gates.contains(i) => {
+						wO[i - gates.start] += exp_z * coeff;
+					}
+					Variable::Committed(i) if committed.contains(i) => {
+						wV[i - committed.start] -= exp_z * coeff;
+					}
+					// Variables of other parties, and constant terms
+					_ => {}
+				}
+			}
+			exp_z *= z;
+		}
+
+		FlattenedConstraints { wL, wR, wO, wV }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{dealer::*, messages::*, party::*, *};
+
+	use curve25519_dalek::ristretto::CompressedRistretto;
+	use merlin::Transcript;
+
+	use crate::{
+		errors::ShareError,
+		generators::{BulletproofGens, PedersenGens},
+		r1cs::{ConstraintSystem, R1CSProof, Verifier},
+	};
+
+	/// A circuit for `a^2 + b^2 = c^2` and `d = a + b`, where the first
+	/// three parties each hold one of `a, b, c` and its square, and the
+	/// fourth holds `d` but no multiplier.
+	fn pythagorean_circuit() -> (Circuit, Vec<PartyLayout>) {
+		let mut transcript = Transcript::new(b"R1CSAggregationTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let vars: Vec<Variable> = (0..4)
+			.map(|_| verifier.commit(CompressedRistretto::default()))
+			.collect();
+		let (_, _, a2) = verifier.multiply(vars[0].into(), vars[0].into());
+		let (_, _, b2) = verifier.multiply(vars[1].into(), vars[1].into());
+		let (_, _, c2) = verifier.multiply(vars[2].into(), vars[2].into());
+		verifier.constrain(a2 + b2 - c2);
+		verifier.constrain(vars[0] + vars[1] - vars[3]);
+
+		let holds = |multipliers| PartyLayout {
+			committed_variables: 1,
+			multipliers,
+		};
+		(verifier.circuit().unwrap(), vec![
+			holds(1),
+			holds(1),
+			holds(1),
+			holds(0),
+		])
+	}
+
+	/// Runs the protocol for parties holding `values` in the Pythagorean
+	/// circuit, letting `tamper` modify the proof shares, and returns the
+	/// commitments and the dealer's result.
+	fn aggregate(
+		values: [u64; 4],
+		tamper: impl Fn(&mut [ProofShare]),
+	) -> (Vec<CompressedRistretto>, Result<R1CSProof, MPCError>) {
+		let (circuit, layout) = pythagorean_circuit();
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(4, 1);
+		let mut rng = rand::thread_rng();
+
+		let mut transcript = Transcript::new(b"R1CSAggregationTest");
+		let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, &circuit, &layout).unwrap();
+
+		let (parties, input_commitments): (Vec<_>, Vec<_>) = values
+			.iter()
+			.enumerate()
+			.map(|(j, &value)| {
+				let v = Scalar::from(value);
+				let inputs = if j < 3 { vec![v] } else { vec![] };
+				let party = Party::new(
+					&bp_gens,
+					&pc_gens,
+					vec![v],
+					vec![Scalar::random(&mut rng)],
+					inputs.clone(),
+					inputs,
+				)
+				.unwrap();
+				party.assign_position(&circuit, &layout, j).unwrap()
+			})
+			.unzip();
+		let commitments = input_commitments.iter().map(|ic| ic.V[0]).collect();
+
+		let (dealer, gate_challenge) = dealer.receive_input_commitments(input_commitments).unwrap();
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) =
+			parties.into_iter().map(|p| p.apply_challenge(&gate_challenge)).unzip();
+
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+		let mut proof_shares: Vec<_> = parties
+			.into_iter()
+			.map(|p| p.apply_challenge(&poly_challenge).unwrap())
+			.collect();
+		tamper(&mut proof_shares);
+
+		(commitments, dealer.receive_shares(&proof_shares))
+	}
+
+	#[test]
+	fn aggregate_pythagorean_triple() {
+		let (commitments, proof) = aggregate([3, 4, 5, 7], |_| {});
+		let proof = proof.unwrap();
+
+		// The proof verifies like a single-party proof for the circuit.
+		let (circuit, _) = pythagorean_circuit();
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(4, 1);
+		let mut transcript = Transcript::new(b"R1CSAggregationTest");
+		let verifier = Verifier::from_circuit(&mut transcript, &circuit, &commitments).unwrap();
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+		// A witness that does not satisfy the circuit cannot be blamed
+		// on a party.
+		let (_, proof) = aggregate([3, 4, 6, 7], |_| {});
+		assert_eq!(proof.unwrap_err(), MPCError::VerificationError);
+	}
+
+	#[test]
+	fn detect_malformed_proof_shares() {
+		let (_, proof) = aggregate([3, 4, 5, 7], |shares| {
+			shares[1].e_blinding += Scalar::one();
+			shares[2].t_x += Scalar::one();
+			shares[3].l_vec.push(Scalar::one());
+		});
+		assert_eq!(proof.unwrap_err(), MPCError::MalformedProofShares {
+			bad_shares: vec![(3, ShareError::WrongSize)],
+		});
+
+		let (_, proof) = aggregate([3, 4, 5, 7], |shares| {
+			shares[1].e_blinding += Scalar::one();
+			shares[2].t_x += Scalar::one();
+		});
+		assert_eq!(proof.unwrap_err(), MPCError::MalformedProofShares {
+			bad_shares: vec![
+				(1, ShareError::InconsistentGateCommitment),
+				(2, ShareError::InconsistentInnerProduct)
+			],
+		});
+	}
+
+	#[test]
+	fn reject_invalid_layouts() {
+		let (circuit, layout) = pythagorean_circuit();
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(4, 1);
+		let mut transcript = Transcript::new(b"R1CSAggregationTest");
+
+		// The layout must cover the circuit exactly.
+		assert!(matches!(
+			Dealer::new(&bp_gens, &pc_gens, &mut transcript, &circuit, &layout[..3]),
+			Err(MPCError::InvalidLayout)
+		));
+		assert!(matches!(
+			Dealer::new(&bp_gens, &pc_gens, &mut transcript, &circuit, &[]),
+			Err(MPCError::InvalidLayout)
+		));
+		assert!(matches!(
+			Dealer::new(
+				&BulletproofGens::new(2, 1),
+				&pc_gens,
+				&mut transcript,
+				&circuit,
+				&layout
+			),
+			Err(MPCError::InvalidGeneratorsLength)
+		));
+
+		// A party must hold what the layout gives it.
+		let one = vec![Scalar::one()];
+		let party = Party::new(&bp_gens, &pc_gens, one.clone(), one.clone(), vec![], vec![]).unwrap();
+		assert!(party.assign_position(&circuit, &layout, 0).is_err());
+		let party = Party::new(&bp_gens, &pc_gens, one.clone(), one.clone(), vec![], vec![]).unwrap();
+		assert!(party.assign_position(&circuit, &layout, 4).is_err());
+		assert!(Party::new(&bp_gens, &pc_gens, one, vec![], vec![], vec![]).is_err());
+	}
+}
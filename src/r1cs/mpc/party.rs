@@ -0,0 +1,356 @@
+//! The `party` module contains the API for the party state while the party is
+//! engaging in an aggregated R1CS proof.
+//!
+//! Each state of the MPC protocol is represented by a different Rust
+//! type.  The state transitions consume the previous state, making it
+//! a compile error to perform the steps out of order or to repeat a
+//! step.
+//!
+//! For more explanation of how the `dealer`, `party`, and `messages`
+//! modules orchestrate the protocol execution, see the documentation
+//! in the [`mpc`](super) module.
+
+use alloc::vec::Vec;
+use core::{iter, mem, ops::Range};
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use rand_core::{CryptoRng, RngCore};
+
+use super::{messages::*, party_ranges, Circuit, FlattenedConstraints, PartyLayout};
+use crate::{
+	errors::MPCError,
+	generators::{BulletproofGens, PedersenGens},
+	util,
+};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+/// Used to construct a party for the aggregated R1CS proof protocol.
+pub struct Party {}
+
+#[allow(clippy::new_ret_no_self)]
+impl Party {
+	/// Constructs a `PartyAwaitingPosition` holding the openings
+	/// `(v, v_blinding)` of the party's committed variables and the left
+	/// and right inputs `a_L`, `a_R` of its multipliers, in the order of
+	/// the circuit.
+	///
+	/// Returns [`MPCError::InvalidLayout`] if the openings or the inputs
+	/// have different lengths.
+	pub fn new<'a>(
+		bp_gens: &'a BulletproofGens,
+		pc_gens: &'a PedersenGens,
+		v: Vec<Scalar>,
+		v_blinding: Vec<Scalar>,
+		a_L: Vec<Scalar>,
+		a_R: Vec<Scalar>,
+	) -> Result<PartyAwaitingPosition<'a>, MPCError> {
+		if v.len() != v_blinding.len() || a_L.len() != a_R.len() {
+			return Err(MPCError::InvalidLayout);
+		}
+
+		let V = v
+			.iter()
+			.zip(v_blinding.iter())
+			.map(|(v, v_blinding)| pc_gens.commit(*v, *v_blinding).compress())
+			.collect();
+		let a_O = a_L.iter().zip(a_R.iter()).map(|(l, r)| l * r).collect();
+
+		Ok(PartyAwaitingPosition {
+			bp_gens,
+			pc_gens,
+			v,
+			v_blinding,
+			a_L,
+			a_R,
+			a_O,
+			V,
+		})
+	}
+}
+
+/// A party waiting for the dealer to assign their position in the aggregation.
+pub struct PartyAwaitingPosition<'a> {
+	bp_gens: &'a BulletproofGens,
+	pc_gens: &'a PedersenGens,
+	v: Vec<Scalar>,
+	v_blinding: Vec<Scalar>,
+	a_L: Vec<Scalar>,
+	a_R: Vec<Scalar>,
+	a_O: Vec<Scalar>,
+	V: Vec<CompressedRistretto>,
+}
+
+impl<'a> PartyAwaitingPosition<'a> {
+	/// Assigns position `j` in the `layout` of `circuit` to this party,
+	/// and commits to the assignments of its multipliers.
+	/// This is a convenience wrapper around
+	/// [`PartyAwaitingPosition::assign_position_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn assign_position(
+		self,
+		circuit: &'a Circuit,
+		layout: &[PartyLayout],
+		j: usize,
+	) -> Result<(PartyAwaitingGateChallenge<'a>, InputCommitment), MPCError> {
+		self.assign_position_with_rng(circuit, layout, j, &mut thread_rng())
+	}
+
+	/// Assigns position `j` in the `layout` of `circuit` to this party,
+	/// and commits to the assignments of its multipliers.
+	///
+	/// Returns [`MPCError::InvalidLayout`] if the layout does not cover
+	/// the circuit, or if the party's openings and inputs do not match
+	/// its part of the layout.
+	pub fn assign_position_with_rng<T: RngCore + CryptoRng>(
+		mut self,
+		circuit: &'a Circuit,
+		layout: &[PartyLayout],
+		j: usize,
+		rng: &mut T,
+	) -> Result<(PartyAwaitingGateChallenge<'a>, InputCommitment), MPCError> {
+		let (committed, gates) = party_ranges(circuit, layout, j)?;
+		if committed.len() != self.v.len() || gates.len() != self.a_L.len() {
+			return Err(MPCError::InvalidLayout);
+		}
+		if self.bp_gens.gens_capacity < gates.end {
+			return Err(MPCError::InvalidGeneratorsLength);
+		}
+
+		let n = gates.len();
+		let gens = self.bp_gens.share(0);
+
+		let i_blinding = Scalar::random(rng);
+		let o_blinding = Scalar::random(rng);
+		let s_blinding = Scalar::random(rng);
+
+		let s_L: Vec<Scalar> = (0..n).map(|_| Scalar::random(rng)).collect();
+		let s_R: Vec<Scalar> = (0..n).map(|_| Scalar::random(rng)).collect();
+
+		// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+		let A_I = util::multiscalar_mul(
+			iter::once(&i_blinding).chain(self.a_L.iter()).chain(self.a_R.iter()),
+			iter::once(&self.pc_gens.B_blinding)
+				.chain(gens.G(gates.end).skip(gates.start))
+				.chain(gens.H(gates.end).skip(gates.start)),
+		);
+
+		// A_O = <a_O, G> + o_blinding * B_blinding
+		let A_O = util::multiscalar_mul(
+			iter::once(&o_blinding).chain(self.a_O.iter()),
+			iter::once(&self.pc_gens.B_blinding).chain(gens.G(gates.end).skip(gates.start)),
+		);
+
+		// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+		let S = util::multiscalar_mul(
+			iter::once(&s_blinding).chain(s_L.iter()).chain(s_R.iter()),
+			iter::once(&self.pc_gens.B_blinding)
+				.chain(gens.G(gates.end).skip(gates.start))
+				.chain(gens.H(gates.end).skip(gates.start)),
+		);
+
+		let input_commitment = InputCommitment {
+			V: self.V.clone(),
+			A_I,
+			A_O,
+			S,
+		};
+
+		let next_state = PartyAwaitingGateChallenge {
+			pc_gens: self.pc_gens,
+			circuit,
+			committed,
+			gates,
+			v_blinding: mem::take(&mut self.v_blinding),
+			a_L: mem::take(&mut self.a_L),
+			a_R: mem::take(&mut self.a_R),
+			a_O: mem::take(&mut self.a_O),
+			i_blinding,
+			o_blinding,
+			s_blinding,
+			s_L,
+			s_R,
+		};
+
+		Ok((next_state, input_commitment))
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl<'a> Drop for PartyAwaitingPosition<'a> {
+	fn drop(&mut self) {
+		util::clear_secrets(&mut self.v);
+		util::clear_secrets(&mut self.v_blinding);
+		util::clear_secrets(&mut self.a_L);
+		util::clear_secrets(&mut self.a_R);
+		util::clear_secrets(&mut self.a_O);
+	}
+}
+
+/// A party which has committed to its multipliers and is waiting for the
+/// [`GateChallenge`] from the dealer.
+pub struct PartyAwaitingGateChallenge<'a> {
+	pc_gens: &'a PedersenGens,
+	circuit: &'a Circuit,
+	committed: Range<usize>,
+	gates: Range<usize>,
+	v_blinding: Vec<Scalar>,
+	a_L: Vec<Scalar>,
+	a_R: Vec<Scalar>,
+	a_O: Vec<Scalar>,
+	i_blinding: Scalar,
+	o_blinding: Scalar,
+	s_blinding: Scalar,
+	s_L: Vec<Scalar>,
+	s_R: Vec<Scalar>,
+}
+
+impl<'a> PartyAwaitingGateChallenge<'a> {
+	/// Receive a [`GateChallenge`] from the dealer and use it to
+	/// compute commitments to the party's polynomial coefficients.
+	/// This is a convenience wrapper around
+	/// [`PartyAwaitingGateChallenge::apply_challenge_with_rng`], passing
+	/// in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn apply_challenge(self, gc: &GateChallenge) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+		self.apply_challenge_with_rng(gc, &mut thread_rng())
+	}
+
+	/// Receive a [`GateChallenge`] from the dealer and use it to
+	/// compute commitments to the party's polynomial coefficients.
+	pub fn apply_challenge_with_rng<T: RngCore + CryptoRng>(
+		self,
+		gc: &GateChallenge,
+		rng: &mut T,
+	) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+		let n = self.gates.len();
+		let weights = FlattenedConstraints::new(self.circuit, &gc.z, &self.committed, &self.gates);
+
+		let mut l_poly = util::VecPoly3::zero(n);
+		let mut r_poly = util::VecPoly3::zero(n);
+
+		// The party's multipliers start at position `gates.start` of the
+		// circuit, and so are weighted from y^gates.start on.
+		let exp_y = util::exp_iter(gc.y).skip(self.gates.start);
+		let exp_y_inv = util::exp_iter(gc.y.invert()).skip(self.gates.start);
+		for (i, (exp_y, exp_y_inv)) in exp_y.zip(exp_y_inv).take(n).enumerate() {
+			// l_poly.0 = 0
+			// l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
+			l_poly.1[i] = self.a_L[i] + exp_y_inv * weights.wR[i];
+			// l_poly.2 = a_O
+			l_poly.2[i] = self.a_O[i];
+			// l_poly.3 = s_L
+			l_poly.3[i] = self.s_L[i];
+			// r_poly.0 = (z * z^Q * W_O) - y^n
+			r_poly.0[i] = weights.wO[i] - exp_y;
+			// r_poly.1 = y^n * a_R + (z * z^Q * W_L)
+			r_poly.1[i] = exp_y * self.a_R[i] + weights.wL[i];
+			// r_poly.2 = 0
+			// r_poly.3 = y^n * s_R
+			r_poly.3[i] = exp_y * self.s_R[i];
+		}
+
+		let t_poly = util::VecPoly3::special_inner_product(&l_poly, &r_poly);
+
+		let t_1_blinding = Scalar::random(rng);
+		let t_3_blinding = Scalar::random(rng);
+		let t_4_blinding = Scalar::random(rng);
+		let t_5_blinding = Scalar::random(rng);
+		let t_6_blinding = Scalar::random(rng);
+
+		let poly_commitment = PolyCommitment {
+			T_1: self.pc_gens.commit(t_poly.t1, t_1_blinding),
+			T_3: self.pc_gens.commit(t_poly.t3, t_3_blinding),
+			T_4: self.pc_gens.commit(t_poly.t4, t_4_blinding),
+			T_5: self.pc_gens.commit(t_poly.t5, t_5_blinding),
+			T_6: self.pc_gens.commit(t_poly.t6, t_6_blinding),
+		};
+
+		// t_2_blinding = <z*z^Q, W_V * v_blinding>
+		let t_2_blinding = weights
+			.wV
+			.iter()
+			.zip(self.v_blinding.iter())
+			.map(|(c, v_blinding)| c * v_blinding)
+			.sum();
+
+		let papc = PartyAwaitingPolyChallenge {
+			l_poly,
+			r_poly,
+			t_poly,
+			t_blinding_poly: util::Poly6 {
+				t1: t_1_blinding,
+				t2: t_2_blinding,
+				t3: t_3_blinding,
+				t4: t_4_blinding,
+				t5: t_5_blinding,
+				t6: t_6_blinding,
+			},
+			i_blinding: self.i_blinding,
+			o_blinding: self.o_blinding,
+			s_blinding: self.s_blinding,
+		};
+
+		(papc, poly_commitment)
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl<'a> Drop for PartyAwaitingGateChallenge<'a> {
+	fn drop(&mut self) {
+		util::clear_secrets(&mut self.v_blinding);
+		util::clear_secrets(&mut self.a_L);
+		util::clear_secrets(&mut self.a_R);
+		util::clear_secrets(&mut self.a_O);
+		util::clear_secret(&mut self.i_blinding);
+		util::clear_secret(&mut self.o_blinding);
+		util::clear_secret(&mut self.s_blinding);
+		util::clear_secrets(&mut self.s_L);
+		util::clear_secrets(&mut self.s_R);
+	}
+}
+
+/// A party which has committed to its polynomial coefficients and is
+/// waiting for the [`PolyChallenge`] from the dealer.
+pub struct PartyAwaitingPolyChallenge {
+	l_poly: util::VecPoly3,
+	r_poly: util::VecPoly3,
+	t_poly: util::Poly6,
+	t_blinding_poly: util::Poly6,
+	i_blinding: Scalar,
+	o_blinding: Scalar,
+	s_blinding: Scalar,
+}
+
+impl PartyAwaitingPolyChallenge {
+	/// Receive a [`PolyChallenge`] from the dealer and compute the
+	/// party's proof share.
+	pub fn apply_challenge(self, pc: &PolyChallenge) -> Result<ProofShare, MPCError> {
+		// Prevent a malicious dealer from annihilating the blinding
+		// factors by supplying a zero challenge.
+		if pc.x == Scalar::zero() {
+			return Err(MPCError::MaliciousDealer);
+		}
+
+		let x = pc.x;
+		Ok(ProofShare {
+			t_x: self.t_poly.eval(x),
+			t_x_blinding: self.t_blinding_poly.eval(x),
+			e_blinding: x * (self.i_blinding + x * (self.o_blinding + x * self.s_blinding)),
+			l_vec: self.l_poly.eval(x),
+			r_vec: self.r_poly.eval(x),
+		})
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for PartyAwaitingPolyChallenge {
+	fn drop(&mut self) {
+		util::clear_secret(&mut self.i_blinding);
+		util::clear_secret(&mut self.o_blinding);
+		util::clear_secret(&mut self.s_blinding);
+
+		// Note: the polynomials clear their own secrets on drop.
+	}
+}
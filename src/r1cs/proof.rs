@@ -8,11 +8,15 @@ use curve25519_dalek::{
 	traits::{Identity, IsIdentity},
 };
 
+use super::{Metrics, Phase};
 use crate::{errors::R1CSError, inner_product_proof::InnerProductProof, util};
 
-#[cfg(feature = "std")]
-use serde::de::Visitor;
-#[cfg(feature = "std")]
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
 const ONE_PHASE_COMMITMENTS: u8 = 0;
@@ -34,6 +38,11 @@ const TWO_PHASE_COMMITMENTS: u8 = 1;
 /// the constraint system using
 /// [`VerifierCS::verify`](::r1cs::VerifierCS::verify) to verify the
 /// proof.
+///
+/// A proof received from an untrusted source should be decoded with
+/// [`R1CSProof::from_bytes_strict`], which accepts a single encoding of
+/// each proof.  Verification rejects proofs with points or scalars that
+/// are not canonically encoded with [`R1CSError::FormatError`].
 #[derive(Clone, Debug)]
 #[allow(non_snake_case)]
 pub struct R1CSProof {
@@ -123,7 +132,34 @@ impl R1CSProof {
 		1 + elements * 32 + self.ipp_proof.serialized_size()
 	}
 
-	fn missing_phase2_commitments(&self) -> bool {
+	/// Returns the size in bytes of a proof of a constraint system with the
+	/// given `metrics`, as returned by [`R1CSProof::to_bytes`].
+	///
+	/// The `metrics` should be taken once the constraint system is fully
+	/// built.  If they were taken in the randomized phase the proof is
+	/// assumed to contain second-phase commitments, so the result is an
+	/// upper bound if no multipliers were allocated in that phase.
+	pub fn size(metrics: &Metrics) -> usize {
+		let elements = if metrics.phase == Phase::Randomized { 14 } else { 11 };
+		let padded_n = (metrics.multipliers + metrics.vector_committed_variables).next_power_of_two();
+		let lg_n = padded_n.trailing_zeros() as usize;
+		1 + (elements + 2 * lg_n + 2) * 32
+	}
+
+	/// Returns the number of scalar multiplications performed by the
+	/// verifier of a proof of a constraint system with the given `metrics`.
+	///
+	/// Verification is a single multiscalar multiplication of this size,
+	/// so this is the dominant term of its cost.
+	pub fn verification_cost(metrics: &Metrics) -> usize {
+		let padded_n = (metrics.multipliers + metrics.vector_committed_variables).next_power_of_two();
+		let lg_n = padded_n.trailing_zeros() as usize;
+		// B, B_blinding, G, H, the six phase commitments, V, the vector
+		// commitments, T_1, T_3..T_6, and the L, R points of the ipp.
+		2 + 2 * padded_n + 6 + metrics.committed_variables + metrics.vector_commitments + 5 + 2 * lg_n
+	}
+
+	pub(super) fn missing_phase2_commitments(&self) -> bool {
 		self.A_I2.is_identity() && self.A_O2.is_identity() && self.S2.is_identity()
 	}
 
@@ -206,47 +242,106 @@ impl R1CSProof {
 			ipp_proof,
 		})
 	}
+
+	/// Deserializes the proof from a byte slice, accepting only the
+	/// canonical encoding of a proof.
+	///
+	/// In addition to the checks of [`R1CSProof::from_bytes`], returns
+	/// [`R1CSError::FormatError`] if any of the points is not the
+	/// canonical encoding of a Ristretto point, or if the encoding
+	/// includes second-phase commitments that are all the identity,
+	/// which [`R1CSProof::to_bytes`] would have omitted.  Every proof
+	/// therefore has at most one encoding accepted by this function.
+	pub fn from_bytes_strict(slice: &[u8]) -> Result<R1CSProof, R1CSError> {
+		let proof = R1CSProof::from_bytes(slice)?;
+		if slice[0] == TWO_PHASE_COMMITMENTS && proof.missing_phase2_commitments() {
+			return Err(R1CSError::FormatError);
+		}
+		if !proof.is_canonical() {
+			return Err(R1CSError::FormatError);
+		}
+		Ok(proof)
+	}
+
+	/// Returns whether all points of the proof decompress and all of its
+	/// scalars are canonical.
+	pub(super) fn is_canonical(&self) -> bool {
+		[
+			self.A_I1, self.A_O1, self.S1, self.A_I2, self.A_O2, self.S2, self.T_1, self.T_3, self.T_4, self.T_5,
+			self.T_6,
+		]
+		.iter()
+		.all(|P| P.decompress().is_some())
+			&& [self.t_x, self.t_x_blinding, self.e_blinding]
+				.iter()
+				.all(|s| s.is_canonical())
+			&& self.ipp_proof.is_canonical()
+	}
+
+	/// Serializes the proof into the versioned encoding, which prefixes
+	/// the encoding of [`R1CSProof::to_bytes`] with a header.
+	///
+	/// # Layout
+	///
+	/// * a version tag byte, currently `1`,
+	/// * the length of the rest of the encoding as a little-endian `u32`,
+	/// * the encoding of [`R1CSProof::to_bytes`].
+	pub fn to_bytes_versioned(&self) -> Vec<u8> {
+		util::encode_versioned(&self.to_bytes())
+	}
+
+	/// Deserializes a proof from the encoding of
+	/// [`R1CSProof::to_bytes_versioned`].
+	///
+	/// Returns [`R1CSError::FormatError`] if the version is unknown, if
+	/// the length does not match the slice, or if the rest of the slice
+	/// cannot be parsed into a `R1CSProof`.
+	pub fn from_bytes_versioned(slice: &[u8]) -> Result<R1CSProof, R1CSError> {
+		R1CSProof::from_bytes(util::decode_versioned(slice).ok_or(R1CSError::FormatError)?)
+	}
+}
+
+// The SCALE encoding is the length-prefixed byte encoding from `to_bytes`.
+#[cfg(feature = "scale")]
+impl Encode for R1CSProof {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "scale")]
+impl EncodeLike for R1CSProof {}
+
+#[cfg(feature = "scale")]
+impl Decode for R1CSProof {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		R1CSProof::from_bytes(&bytes).map_err(|_| "Invalid R1CSProof encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for R1CSProof {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: Serializer,
 	{
-		serializer.serialize_bytes(&self.to_bytes()[..])
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for R1CSProof {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where
 		D: Deserializer<'de>,
 	{
-		struct R1CSProofVisitor;
-
-		impl<'de> Visitor<'de> for R1CSProofVisitor {
-			type Value = R1CSProof;
-
-			fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
-				formatter.write_str("a valid R1CSProof")
-			}
-
-			fn visit_bytes<E>(self, v: &[u8]) -> Result<R1CSProof, E>
-			where
-				E: serde::de::Error,
-			{
-				// Using Error::custom requires T: Display, which our error
-				// type only implements when it implements std::error::Error.
-				#[cfg(feature = "std")]
-				return R1CSProof::from_bytes(v).map_err(serde::de::Error::custom);
-				// In no-std contexts, drop the error message.
-				#[cfg(not(feature = "std"))]
-				return R1CSProof::from_bytes(v).map_err(|_| serde::de::Error::custom("deserialization error"));
-			}
-		}
-
-		deserializer.deserialize_bytes(R1CSProofVisitor)
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid R1CSProof")?;
+		R1CSProof::from_bytes(&bytes).map_err(serde::de::Error::custom)
 	}
 }
@@ -1,30 +1,39 @@
 #![allow(non_snake_case)]
 
 use alloc::{boxed::Box, vec::Vec};
-use clear_on_drop::clear::Clear;
-use core::mem;
+use core::{iter, mem};
 use curve25519_dalek::{
 	ristretto::{CompressedRistretto, RistrettoPoint},
 	scalar::Scalar,
-	traits::{Identity, MultiscalarMul},
+	traits::Identity,
 };
 use merlin::Transcript;
 use rand_core::{CryptoRng, RngCore};
 
 use super::{
-	ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem, RandomizedConstraintSystem, Variable,
+	batch_member_digest,
+	chunked::{self, ChunkPlan, ChunkedProof},
+	circuit::CircuitHasher,
+	delegation::{BlindingChallenge, BlindingCommitment, BlindingResponse},
+	Circuit, ConstraintSystem, LinearCombination, Metrics, Phase, R1CSProof, RandomizableConstraintSystem,
+	RandomizedConstraintSystem, Variable, Witness,
 };
 
 use crate::{
 	errors::R1CSError,
 	generators::{BulletproofGens, PedersenGens},
 	inner_product_proof::InnerProductProof,
-	transcript::TranscriptProtocol,
+	transcript::{self, TranscriptProtocol},
+	util,
 };
 
 #[cfg(feature = "std")]
 use rand::thread_rng;
 
+/// A closure adding randomized constraints, called in the second phase of
+/// the protocol.
+type DeferredConstraint<'t, 'g> = Box<dyn Fn(&mut RandomizingProver<'t, 'g>) -> Result<(), R1CSError>>;
+
 /// A [`ConstraintSystem`] implementation for use by the prover.
 ///
 /// The prover commits high-level variables and their blinding factors `(v,
@@ -39,6 +48,8 @@ pub struct Prover<'t, 'g> {
 	pc_gens: &'g PedersenGens,
 	/// The constraints accumulated so far.
 	constraints: Vec<LinearCombination>,
+	/// Running digest of the non-randomized constraints
+	circuit_hasher: CircuitHasher,
 	/// Stores assignments to the "left" of multiplication gates
 	a_L: Vec<Scalar>,
 	/// Stores assignments to the "right" of multiplication gates
@@ -49,10 +60,20 @@ pub struct Prover<'t, 'g> {
 	v: Vec<Scalar>,
 	/// High-level witness data (blinding openings to V commitments)
 	v_blinding: Vec<Scalar>,
+	/// High-level witness data (value openings to the entries of vector
+	/// commitments, concatenated)
+	vector_values: Vec<Scalar>,
+	/// High-level witness data (blinding openings to vector commitments)
+	vector_blindings: Vec<Scalar>,
+	/// Values of the public inputs
+	public: Vec<Scalar>,
+	/// Indices of the committed variables whose blinding factors are held
+	/// by a delegating client
+	blinded: Vec<usize>,
 
 	/// This list holds closures that will be called in the second phase of the
 	/// protocol, when non-randomized variables are committed.
-	deferred_constraints: Vec<Box<dyn Fn(&mut RandomizingProver<'t, 'g>) -> Result<(), R1CSError>>>,
+	deferred_constraints: Vec<DeferredConstraint<'t, 'g>>,
 
 	/// Index of a pending multiplier that's not fully assigned yet.
 	pending_multiplier: Option<usize>,
@@ -73,25 +94,13 @@ pub struct RandomizingProver<'t, 'g> {
 /// Overwrite secrets with null bytes when they go out of scope.
 impl<'t, 'g> Drop for Prover<'t, 'g> {
 	fn drop(&mut self) {
-		self.v.clear();
-		self.v_blinding.clear();
-
-		// Important: due to how ClearOnDrop auto-implements InitializableFromZeroed
-		// for T: Default, calling .clear() on Vec compiles, but does not
-		// clear the content. Instead, it only clears the Vec's header.
-		// Clearing the underlying buffer item-by-item will do the job, but will
-		// keep the header as-is, which is fine since the header does not contain
-		// secrets.
-		for e in self.a_L.iter_mut() {
-			e.clear();
-		}
-		for e in self.a_R.iter_mut() {
-			e.clear();
-		}
-		for e in self.a_O.iter_mut() {
-			e.clear();
-		}
-		// XXX use ClearOnDrop instead of doing the above
+		util::clear_secrets(&mut self.v);
+		util::clear_secrets(&mut self.v_blinding);
+		util::clear_secrets(&mut self.vector_values);
+		util::clear_secrets(&mut self.vector_blindings);
+		util::clear_secrets(&mut self.a_L);
+		util::clear_secrets(&mut self.a_R);
+		util::clear_secrets(&mut self.a_O);
 	}
 }
 
@@ -128,8 +137,46 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 		(l_var, r_var, o_var)
 	}
 
+	fn multiply_many(
+		&mut self,
+		pairs: &[(LinearCombination, LinearCombination)],
+	) -> Vec<(Variable, Variable, Variable)> {
+		// Evaluate all the inputs before allocating any multiplier, which
+		// none of them can refer to.
+		let inputs: Vec<(Scalar, Scalar)> = pairs
+			.iter()
+			.map(|(left, right)| (self.eval(left), self.eval(right)))
+			.collect();
+
+		let first = self.a_L.len();
+		self.reserve_multipliers(pairs.len());
+		for (l, r) in inputs {
+			self.a_L.push(l);
+			self.a_R.push(r);
+			self.a_O.push(l * r);
+		}
+
+		// The constraints hold by construction, so unlike `multiply` this
+		// does not evaluate them again.
+		self.reserve_constraints(2 * pairs.len());
+		(first..)
+			.zip(pairs.iter())
+			.map(|(i, (left, right))| {
+				let (l_var, r_var, o_var) = (
+					Variable::MultiplierLeft(i),
+					Variable::MultiplierRight(i),
+					Variable::MultiplierOutput(i),
+				);
+				self.push_constraint(left.clone() - l_var);
+				self.push_constraint(right.clone() - r_var);
+				(l_var, r_var, o_var)
+			})
+			.collect()
+	}
+
 	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError> {
-		let scalar = assignment.ok_or(R1CSError::MissingAssignment)?;
+		let gate = self.pending_multiplier.unwrap_or_else(|| self.a_L.len());
+		let scalar = assignment.ok_or(R1CSError::MissingAssignment { gate })?;
 
 		match self.pending_multiplier {
 			None => {
@@ -153,7 +200,7 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 		&mut self,
 		input_assignments: Option<(Scalar, Scalar)>,
 	) -> Result<(Variable, Variable, Variable), R1CSError> {
-		let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment)?;
+		let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment { gate: self.a_L.len() })?;
 		let o = l * r;
 
 		// Create variables for l,r,o ...
@@ -172,10 +219,46 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 		self.a_L.len()
 	}
 
+	fn metrics(&self) -> Metrics {
+		Metrics {
+			multipliers: self.a_L.len(),
+			constraints: self.constraints.len(),
+			committed_variables: self.v.len(),
+			vector_committed_variables: self.vector_values.len(),
+			vector_commitments: self.vector_blindings.len(),
+			phase: Phase::NonRandomized,
+		}
+	}
+
+	fn reserve_multipliers(&mut self, additional: usize) {
+		self.a_L.reserve(additional);
+		self.a_R.reserve(additional);
+		self.a_O.reserve(additional);
+	}
+
+	fn reserve_constraints(&mut self, additional: usize) {
+		self.constraints.reserve(additional);
+	}
+
+	fn circuit_digest(&self) -> [u8; 32] {
+		self.circuit_hasher.digest(
+			self.v.len(),
+			self.vector_values.len(),
+			self.public.len(),
+			self.a_L.len(),
+		)
+	}
+
 	fn constrain(&mut self, lc: LinearCombination) {
 		let res = self.evaluate_lc(&lc).unwrap();
 		assert!(res == Scalar::zero());
-		self.constraints.push(lc);
+		self.push_constraint(lc);
+	}
+
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		let res = self.evaluate_lc(&lc).unwrap();
+		assert!(res == Scalar::zero(), "constraint `{}` is not satisfied", label);
+		self.push_constraint(lc);
 	}
 
 	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar> {
@@ -213,6 +296,13 @@ impl<'t, 'g> ConstraintSystem for RandomizingProver<'t, 'g> {
 		self.prover.multiply(left, right)
 	}
 
+	fn multiply_many(
+		&mut self,
+		pairs: &[(LinearCombination, LinearCombination)],
+	) -> Vec<(Variable, Variable, Variable)> {
+		self.prover.multiply_many(pairs)
+	}
+
 	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError> {
 		self.prover.allocate(assignment)
 	}
@@ -228,10 +318,33 @@ impl<'t, 'g> ConstraintSystem for RandomizingProver<'t, 'g> {
 		self.prover.multipliers_len()
 	}
 
+	fn metrics(&self) -> Metrics {
+		Metrics {
+			phase: Phase::Randomized,
+			..self.prover.metrics()
+		}
+	}
+
+	fn reserve_multipliers(&mut self, additional: usize) {
+		self.prover.reserve_multipliers(additional)
+	}
+
+	fn reserve_constraints(&mut self, additional: usize) {
+		self.prover.reserve_constraints(additional)
+	}
+
+	fn circuit_digest(&self) -> [u8; 32] {
+		self.prover.circuit_digest()
+	}
+
 	fn constrain(&mut self, lc: LinearCombination) {
 		self.prover.constrain(lc)
 	}
 
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		self.prover.constrain_labeled(lc, label)
+	}
+
 	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar> {
 		self.prover.evaluate_lc(lc)
 	}
@@ -245,6 +358,10 @@ impl<'t, 'g> RandomizedConstraintSystem for RandomizingProver<'t, 'g> {
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
 		self.prover.transcript.challenge_scalar(label)
 	}
+
+	fn value_of(&self, var: Variable) -> Option<Scalar> {
+		self.prover.value_of(var)
+	}
 }
 
 impl<'t, 'g> Prover<'t, 'g> {
@@ -276,7 +393,12 @@ impl<'t, 'g> Prover<'t, 'g> {
 			transcript,
 			v: Vec::new(),
 			v_blinding: Vec::new(),
+			vector_values: Vec::new(),
+			vector_blindings: Vec::new(),
+			public: Vec::new(),
+			blinded: Vec::new(),
 			constraints: Vec::new(),
+			circuit_hasher: CircuitHasher::new(),
 			a_L: Vec::new(),
 			a_R: Vec::new(),
 			a_O: Vec::new(),
@@ -316,6 +438,123 @@ impl<'t, 'g> Prover<'t, 'g> {
 		(V, Variable::Committed(i))
 	}
 
+	/// Adds the commitment `V` to a high-level variable with the value `v`
+	/// to the transcript, without its blinding factor.
+	///
+	/// This is the proving service's side of a
+	/// [`delegation`](super::delegation): the blinding factor is held by
+	/// the client, and the constraint system can only be proven with
+	/// [`Prover::prove_delegated_with_rng`].
+	///
+	/// # Returns
+	///
+	/// Returns a [`Variable`] corresponding to the commitment, which can be
+	/// used to form constraints.
+	pub fn commit_blinded(&mut self, v: Scalar, V: CompressedRistretto) -> Variable {
+		let i = self.v.len();
+		self.v.push(v);
+		self.v_blinding.push(Scalar::zero());
+		self.blinded.push(i);
+
+		// Add the commitment to the transcript.
+		self.transcript.append_point(b"V", &V);
+
+		Variable::Committed(i)
+	}
+
+	/// Allocates a public input with the value `x`, known to both the
+	/// prover and the verifier.
+	///
+	/// Unlike a committed variable, a public input needs no commitment:
+	/// the verifier passes the same value to
+	/// [`Verifier::allocate_public`](::r1cs::Verifier::allocate_public),
+	/// and the values of all public inputs are appended to the transcript
+	/// when proving.  A proof only verifies for the values it was created
+	/// with, but the [`Circuit`] does not depend on them.
+	///
+	/// # Returns
+	///
+	/// Returns a [`Variable`] for the public input, which can be used to
+	/// form constraints.
+	pub fn allocate_public(&mut self, x: Scalar) -> Variable {
+		let i = self.public.len();
+		self.public.push(x);
+		Variable::Public(i)
+	}
+
+	/// Commits to each entry of a vector of external variables, as by
+	/// [`Prover::commit`].
+	///
+	/// Together with a [`VectorLinkProof`](::VectorLinkProof) for the
+	/// returned commitments, this makes the entries of a
+	/// [`VectorCommitment`](::VectorCommitment) available to the
+	/// constraint system.
+	///
+	/// # Panics
+	///
+	/// Panics if `v` and `v_blinding` have different lengths.
+	pub fn commit_each(&mut self, v: &[Scalar], v_blinding: &[Scalar]) -> (Vec<CompressedRistretto>, Vec<Variable>) {
+		assert_eq!(v.len(), v_blinding.len());
+		v.iter()
+			.zip(v_blinding.iter())
+			.map(|(v, v_blinding)| self.commit(*v, *v_blinding))
+			.unzip()
+	}
+
+	/// Creates a single commitment to a vector of high-level variables
+	/// and adds it to the transcript.
+	///
+	/// # Inputs
+	///
+	/// The `values` and `blinding` parameters are the opening of the
+	/// commitment
+	/// \\( \langle \mathbf{v}, \mathbf{G}' \rangle + \tilde{v} \tilde{B} \\),
+	/// where \\( \mathbf{G}' \\) are the next `values.len()` unused
+	/// generators of the second party of `bp_gens`.  The same `bp_gens`
+	/// must be passed to [`Prover::prove`], and needs a `party_capacity` of
+	/// at least 2 and a `gens_capacity` of at least the total length of
+	/// the committed vectors.
+	///
+	/// Unlike committing each entry with [`Prover::commit`], the proof
+	/// carries the entries in the inner-product argument: the verifier
+	/// pays one scalar multiplication per vector commitment plus two per
+	/// entry on shared generators, and the proof grows only with the
+	/// logarithm of the number of entries.
+	///
+	/// # Returns
+	///
+	/// Returns a pair of the vector commitment (as a compressed Ristretto
+	/// point), and a [`Variable`] for each entry of the vector, which can
+	/// be used to form constraints.  Returns
+	/// [`R1CSError::InvalidGeneratorsLength`] if `bp_gens` does not have
+	/// enough generators.
+	pub fn commit_vec(
+		&mut self,
+		bp_gens: &BulletproofGens,
+		values: &[Scalar],
+		blinding: Scalar,
+	) -> Result<(CompressedRistretto, Vec<Variable>), R1CSError> {
+		let offset = self.vector_values.len();
+		let k = values.len();
+		if bp_gens.party_capacity < 2 || bp_gens.gens_capacity < offset + k {
+			return Err(R1CSError::InvalidGeneratorsLength);
+		}
+		self.vector_values.extend_from_slice(values);
+		self.vector_blindings.push(blinding);
+
+		// Add the commitment to the transcript, prefixed with its length.
+		let gens = bp_gens.share(1);
+		let C = util::multiscalar_mul(
+			iter::once(&blinding).chain(values.iter()),
+			iter::once(&self.pc_gens.B_blinding).chain(gens.G(offset + k).skip(offset)),
+		)
+		.compress();
+		transcript::append_u64(self.transcript, b"k", k as u64);
+		self.transcript.append_point(b"C", &C);
+
+		Ok((C, (offset..offset + k).map(Variable::VectorCommitted).collect()))
+	}
+
 	/// Use a challenge, `z`, to flatten the constraints in the
 	/// constraint system into vectors used for proving and
 	/// verification.
@@ -324,10 +563,15 @@ impl<'t, 'g> Prover<'t, 'g> {
 	///
 	/// Returns a tuple of
 	/// ```text
-	/// (wL, wR, wO, wV)
+	/// (wL, wR, wO, wV, wU)
 	/// ```
-	/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
-	fn flattened_constraints(&mut self, z: &Scalar) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>) {
+	/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\), and `wU`
+	/// holds the weights of the entries of the vector commitments.
+	#[allow(clippy::type_complexity)]
+	fn flattened_constraints(
+		&mut self,
+		z: &Scalar,
+	) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>) {
 		let n = self.a_L.len();
 		let m = self.v.len();
 
@@ -335,6 +579,7 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let mut wR = vec![Scalar::zero(); n];
 		let mut wO = vec![Scalar::zero(); n];
 		let mut wV = vec![Scalar::zero(); m];
+		let mut wU = vec![Scalar::zero(); self.vector_values.len()];
 
 		let mut exp_z = *z;
 		for lc in self.constraints.iter() {
@@ -352,7 +597,10 @@ impl<'t, 'g> Prover<'t, 'g> {
 					Variable::Committed(i) => {
 						wV[*i] -= exp_z * coeff;
 					}
-					Variable::One() => {
+					Variable::VectorCommitted(i) => {
+						wU[*i] -= exp_z * coeff;
+					}
+					Variable::Public(_) | Variable::One() => {
 						// The prover doesn't need to handle constant terms
 					}
 				}
@@ -360,23 +608,40 @@ impl<'t, 'g> Prover<'t, 'g> {
 			exp_z *= z;
 		}
 
-		(wL, wR, wO, wV)
+		(wL, wR, wO, wV, wU)
+	}
+
+	/// Returns the value assigned to `var`, or `None` if it has not been
+	/// allocated.
+	fn value_of(&self, var: Variable) -> Option<Scalar> {
+		match var {
+			Variable::MultiplierLeft(i) => self.a_L.get(i).copied(),
+			Variable::MultiplierRight(i) => self.a_R.get(i).copied(),
+			Variable::MultiplierOutput(i) => self.a_O.get(i).copied(),
+			Variable::Committed(i) => self.v.get(i).copied(),
+			Variable::VectorCommitted(i) => self.vector_values.get(i).copied(),
+			Variable::Public(i) => self.public.get(i).copied(),
+			Variable::One() => Some(Scalar::one()),
+		}
 	}
 
 	fn eval(&self, lc: &LinearCombination) -> Scalar {
-		lc.terms
-			.iter()
-			.map(|(var, coeff)| {
-				coeff
-					* match var {
-						Variable::MultiplierLeft(i) => self.a_L[*i],
-						Variable::MultiplierRight(i) => self.a_R[*i],
-						Variable::MultiplierOutput(i) => self.a_O[*i],
-						Variable::Committed(i) => self.v[*i],
-						Variable::One() => Scalar::one(),
-					}
-			})
-			.sum()
+		lc.evaluate(|var| match var {
+			Variable::MultiplierLeft(i) => self.a_L[i],
+			Variable::MultiplierRight(i) => self.a_R[i],
+			Variable::MultiplierOutput(i) => self.a_O[i],
+			Variable::Committed(i) => self.v[i],
+			Variable::VectorCommitted(i) => self.vector_values[i],
+			Variable::Public(i) => self.public[i],
+			Variable::One() => Scalar::one(),
+		})
+	}
+
+	/// Adds a constraint without checking it, and hashes it into the
+	/// circuit digest.
+	fn push_constraint(&mut self, lc: LinearCombination) {
+		self.circuit_hasher.append(&lc);
+		self.constraints.push(lc);
 	}
 
 	/// Calls all remembered callbacks with an API that
@@ -405,24 +670,104 @@ impl<'t, 'g> Prover<'t, 'g> {
 
 	/// Consume this `ConstraintSystem` to produce a proof.
 	#[cfg(feature = "std")]
-	pub fn prove(mut self, bp_gens: &BulletproofGens) -> Result<R1CSProof, R1CSError> {
+	pub fn prove(self, bp_gens: &BulletproofGens) -> Result<R1CSProof, R1CSError> {
 		self.prove_with_rng(&bp_gens, &mut thread_rng())
 	}
 
 	/// Consume this `ConstraintSystem` to produce a proof.
+	///
+	/// The blinding factors are drawn from a transcript RNG keyed with
+	/// the witness and finalized with `prng`, so a deterministic `prng`
+	/// yields a reproducible proof.
+	///
+	/// Returns [`R1CSError::MissingBlindingFactors`] if the constraint
+	/// system has commitments added with [`Prover::commit_blinded`].
 	pub fn prove_with_rng<T: RngCore + CryptoRng>(
-		mut self,
+		self,
 		bp_gens: &BulletproofGens,
 		prng: &mut T,
 	) -> Result<R1CSProof, R1CSError> {
-		use crate::util;
-		use core::iter;
+		if !self.blinded.is_empty() {
+			return Err(R1CSError::MissingBlindingFactors);
+		}
+		type NoDelegation = fn(&BlindingChallenge) -> Result<BlindingResponse, R1CSError>;
+		self.create_proof(bp_gens, None::<(RistrettoPoint, NoDelegation)>, prng)
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof for a delegating
+	/// client, which holds the blinding factors of the commitments added
+	/// with [`Prover::commit_blinded`].
+	/// This is a convenience wrapper around
+	/// [`Prover::prove_delegated_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_delegated<F>(
+		self,
+		bp_gens: &BulletproofGens,
+		commitment: &BlindingCommitment,
+		respond: F,
+	) -> Result<R1CSProof, R1CSError>
+	where
+		F: FnOnce(&BlindingChallenge) -> Result<BlindingResponse, R1CSError>,
+	{
+		self.prove_delegated_with_rng(bp_gens, commitment, respond, &mut thread_rng())
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof for a delegating
+	/// client, which holds the blinding factors of the commitments added
+	/// with [`Prover::commit_blinded`].
+	///
+	/// The `commitment` is the client's [`BlindingCommitment`], and
+	/// `respond` is called once with the [`BlindingChallenge`] for the
+	/// client, and returns its [`BlindingResponse`], as computed by
+	/// [`DelegationSecret::respond`](super::delegation::DelegationSecret::respond).
+	/// See the [`delegation`](super::delegation) module for the protocol.
+	///
+	/// Returns [`R1CSError::FormatError`] if `commitment` is not a valid
+	/// point, and the errors of `respond`.
+	pub fn prove_delegated_with_rng<T, F>(
+		self,
+		bp_gens: &BulletproofGens,
+		commitment: &BlindingCommitment,
+		respond: F,
+		prng: &mut T,
+	) -> Result<R1CSProof, R1CSError>
+	where
+		T: RngCore + CryptoRng,
+		F: FnOnce(&BlindingChallenge) -> Result<BlindingResponse, R1CSError>,
+	{
+		let P = commitment.decompress()?;
+		self.create_proof(bp_gens, Some((P, respond)), prng)
+	}
 
+	/// Creates the proof, with the contribution of a delegating client to
+	/// the blinding of \\(t(x)\\) if `delegation` holds the commitment to
+	/// its mask and the function computing its response.
+	fn create_proof<T, F>(
+		mut self,
+		bp_gens: &BulletproofGens,
+		delegation: Option<(RistrettoPoint, F)>,
+		prng: &mut T,
+	) -> Result<R1CSProof, R1CSError>
+	where
+		T: RngCore + CryptoRng,
+		F: FnOnce(&BlindingChallenge) -> Result<BlindingResponse, R1CSError>,
+	{
 		// Commit a length _suffix_ for the number of high-level variables.
 		// We cannot do this in advance because user can commit variables one-by-one,
 		// but this suffix provides safe disambiguation because each variable
 		// is prefixed with a separate label.
-		self.transcript.append_u64(b"m", self.v.len() as u64);
+		transcript::append_u64(self.transcript, b"m", self.v.len() as u64);
+
+		// Bind the proof to the non-randomized part of the circuit.
+		let circuit_digest = self.circuit_digest();
+		self.circuit_hasher.freeze(circuit_digest);
+		transcript::append_message(self.transcript, b"circuit", &circuit_digest);
+
+		// Bind the proof to the values of the public inputs, whose number
+		// is part of the circuit.
+		for x in self.public.iter() {
+			self.transcript.append_scalar(b"public", x);
+		}
 
 		// Create a `TranscriptRng` from the high-level witness data
 		//
@@ -444,6 +789,9 @@ impl<'t, 'g> Prover<'t, 'g> {
 			for v_b in &self.v_blinding {
 				builder = builder.rekey_with_witness_bytes(b"v_blinding", v_b.as_bytes());
 			}
+			for c_b in &self.vector_blindings {
+				builder = builder.rekey_with_witness_bytes(b"vector_blinding", c_b.as_bytes());
+			}
 
 			builder.finalize(prng)
 		};
@@ -465,24 +813,39 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let mut s_L1: Vec<Scalar> = (0..n1).map(|_| Scalar::random(&mut rng)).collect();
 		let mut s_R1: Vec<Scalar> = (0..n1).map(|_| Scalar::random(&mut rng)).collect();
 
+		// The entries of the vector commitments are blinded in S1 on the
+		// generators of the second party.
+		let n_vec = self.vector_values.len();
+		if n_vec > 0 && (bp_gens.party_capacity < 2 || bp_gens.gens_capacity < n_vec) {
+			return Err(R1CSError::InvalidGeneratorsLength);
+		}
+		let vector_gens = bp_gens.share(1);
+		let mut s_U: Vec<Scalar> = (0..n_vec).map(|_| Scalar::random(&mut rng)).collect();
+
 		// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-		let A_I1 = RistrettoPoint::multiscalar_mul(
+		let A_I1 = util::multiscalar_mul(
 			iter::once(&i_blinding1).chain(self.a_L.iter()).chain(self.a_R.iter()),
 			iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)).chain(gens.H(n1)),
 		)
 		.compress();
 
 		// A_O = <a_O, G> + o_blinding * B_blinding
-		let A_O1 = RistrettoPoint::multiscalar_mul(
+		let A_O1 = util::multiscalar_mul(
 			iter::once(&o_blinding1).chain(self.a_O.iter()),
 			iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)),
 		)
 		.compress();
 
-		// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-		let S1 = RistrettoPoint::multiscalar_mul(
-			iter::once(&s_blinding1).chain(s_L1.iter()).chain(s_R1.iter()),
-			iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)).chain(gens.H(n1)),
+		// S = <s_L, G> + <s_R, H> + <s_U, G'> + s_blinding * B_blinding
+		let S1 = util::multiscalar_mul(
+			iter::once(&s_blinding1)
+				.chain(s_L1.iter())
+				.chain(s_R1.iter())
+				.chain(s_U.iter()),
+			iter::once(&self.pc_gens.B_blinding)
+				.chain(gens.G(n1))
+				.chain(gens.H(n1))
+				.chain(vector_gens.G(n_vec)),
 		)
 		.compress();
 
@@ -496,14 +859,16 @@ impl<'t, 'g> Prover<'t, 'g> {
 		// Pad zeros to the next power of two (or do that implicitly when creating
 		// vectors)
 
-		// If the number of multiplications is not 0 or a power of 2, then pad the
-		// circuit.
+		// If the number of multiplications and vector entries is not 0 or a
+		// power of 2, then pad the circuit.  The padding goes between the
+		// multipliers and the vector entries, which take the last `n_vec`
+		// positions of the inner-product argument.
 		let n = self.a_L.len();
 		let n2 = n - n1;
-		let padded_n = self.a_L.len().next_power_of_two();
-		let pad = padded_n - n;
+		let padded_n = (n + n_vec).next_power_of_two();
+		let pad = padded_n - n_vec - n;
 
-		if bp_gens.gens_capacity < padded_n {
+		if bp_gens.gens_capacity < padded_n - n_vec {
 			return Err(R1CSError::InvalidGeneratorsLength);
 		}
 
@@ -527,7 +892,7 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let (A_I2, A_O2, S2) = if has_2nd_phase_commitments {
 			(
 				// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-				RistrettoPoint::multiscalar_mul(
+				util::multiscalar_mul(
 					iter::once(&i_blinding2)
 						.chain(self.a_L.iter().skip(n1))
 						.chain(self.a_R.iter().skip(n1)),
@@ -537,13 +902,13 @@ impl<'t, 'g> Prover<'t, 'g> {
 				)
 				.compress(),
 				// A_O = <a_O, G> + o_blinding * B_blinding
-				RistrettoPoint::multiscalar_mul(
+				util::multiscalar_mul(
 					iter::once(&o_blinding2).chain(self.a_O.iter().skip(n1)),
 					iter::once(&self.pc_gens.B_blinding).chain(gens.G(n).skip(n1)),
 				)
 				.compress(),
 				// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-				RistrettoPoint::multiscalar_mul(
+				util::multiscalar_mul(
 					iter::once(&s_blinding2).chain(s_L2.iter()).chain(s_R2.iter()),
 					iter::once(&self.pc_gens.B_blinding)
 						.chain(gens.G(n).skip(n1))
@@ -572,10 +937,10 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let y = self.transcript.challenge_scalar(b"y");
 		let z = self.transcript.challenge_scalar(b"z");
 
-		let (wL, wR, wO, wV) = self.flattened_constraints(&z);
+		let (wL, wR, wO, wV, wU) = self.flattened_constraints(&z);
 
-		let mut l_poly = util::VecPoly3::zero(n);
-		let mut r_poly = util::VecPoly3::zero(n);
+		let mut l_poly = util::VecPoly3::zero(n + n_vec);
+		let mut r_poly = util::VecPoly3::zero(n + n_vec);
 
 		let mut exp_y = Scalar::one(); // y^n starting at n=0
 		let y_inv = y.invert();
@@ -601,6 +966,19 @@ impl<'t, 'g> Prover<'t, 'g> {
 			exp_y = exp_y * y; // y^i -> y^(i+1)
 		}
 
+		// The verifier adds the vector commitments scaled by x * y, so that
+		// components on G' that the prover slips into A_I are pushed to a
+		// power of y no other term has.
+		for (j, (u_j, s_j)) in self.vector_values.iter().zip(s_U.iter()).enumerate() {
+			// l_poly.1 = y * u
+			l_poly.1[n + j] = y * u_j;
+			// l_poly.3 = s_U
+			l_poly.3[n + j] = *s_j;
+			// r_poly.1 = -y^-1 * (z * z^Q * W_U), so that <l, r> has
+			// -<u, W_U> in its x^2 coefficient.
+			r_poly.1[n + j] = -wU[j] * y_inv;
+		}
+
 		let t_poly = util::VecPoly3::special_inner_product(&l_poly, &r_poly);
 
 		let t_1_blinding = Scalar::random(&mut rng);
@@ -609,7 +987,12 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let t_5_blinding = Scalar::random(&mut rng);
 		let t_6_blinding = Scalar::random(&mut rng);
 
-		let T_1 = self.pc_gens.commit(t_poly.t1, t_1_blinding).compress();
+		// The client's mask is committed to in T_1.
+		let mut T_1 = self.pc_gens.commit(t_poly.t1, t_1_blinding);
+		if let Some((P, _)) = &delegation {
+			T_1 += P;
+		}
+		let T_1 = T_1.compress();
 		let T_3 = self.pc_gens.commit(t_poly.t3, t_3_blinding).compress();
 		let T_4 = self.pc_gens.commit(t_poly.t4, t_4_blinding).compress();
 		let T_5 = self.pc_gens.commit(t_poly.t5, t_5_blinding).compress();
@@ -625,7 +1008,9 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let x = self.transcript.challenge_scalar(b"x");
 
 		// t_2_blinding = <z*z^Q, W_V * v_blinding>
-		// in the t_x_blinding calculations, line 76.
+		// in the t_x_blinding calculations, line 76.  The blinding factors
+		// of the delegated commitments are zero here, and the client adds
+		// their share to t_x_blinding.
 		let t_2_blinding = wV
 			.iter()
 			.zip(self.v_blinding.iter())
@@ -642,16 +1027,23 @@ impl<'t, 'g> Prover<'t, 'g> {
 		};
 
 		let t_x = t_poly.eval(x);
-		let t_x_blinding = t_blinding_poly.eval(x);
+		let mut t_x_blinding = t_blinding_poly.eval(x);
+		if let Some((_, respond)) = delegation {
+			let challenge = BlindingChallenge {
+				x,
+				weights: self.blinded.iter().map(|&i| wV[i]).collect(),
+			};
+			t_x_blinding += respond(&challenge)?.t_x_blinding;
+		}
 		let mut l_vec = l_poly.eval(x);
-		l_vec.append(&mut vec![Scalar::zero(); pad]);
+		l_vec.splice(n..n, iter::repeat(Scalar::zero()).take(pad));
 
 		let mut r_vec = r_poly.eval(x);
-		r_vec.append(&mut vec![Scalar::zero(); pad]);
+		r_vec.splice(n..n, iter::repeat(Scalar::zero()).take(pad));
 
 		// XXX this should refer to the notes to explain why this is correct
-		for i in n..padded_n {
-			r_vec[i] = -exp_y;
+		for r_i in r_vec[n..n + pad].iter_mut() {
+			*r_i = -exp_y;
 			exp_y = exp_y * y; // y^i -> y^(i+1)
 		}
 
@@ -659,7 +1051,9 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let o_blinding = o_blinding1 + u * o_blinding2;
 		let s_blinding = s_blinding1 + u * s_blinding2;
 
-		let e_blinding = x * (i_blinding + x * (o_blinding + x * s_blinding));
+		let vector_blinding: Scalar = self.vector_blindings.iter().sum();
+
+		let e_blinding = x * (i_blinding + y * vector_blinding + x * (o_blinding + x * s_blinding));
 
 		self.transcript.append_scalar(b"t_x", &t_x);
 		self.transcript.append_scalar(b"t_x_blinding", &t_x_blinding);
@@ -672,6 +1066,7 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let G_factors = iter::repeat(Scalar::one())
 			.take(n1)
 			.chain(iter::repeat(u).take(n2 + pad))
+			.chain(iter::repeat(Scalar::one()).take(n_vec))
 			.collect::<Vec<_>>();
 		let H_factors = exp_y_inv
 			.into_iter()
@@ -684,23 +1079,17 @@ impl<'t, 'g> Prover<'t, 'g> {
 			&Q,
 			&G_factors,
 			&H_factors,
-			gens.G(padded_n).cloned().collect(),
-			gens.H(padded_n).cloned().collect(),
+			gens.G(padded_n - n_vec).chain(vector_gens.G(n_vec)).cloned().collect(),
+			gens.H(padded_n - n_vec).chain(vector_gens.H(n_vec)).cloned().collect(),
 			l_vec,
 			r_vec,
 		);
 
-		// We do not yet have a ClearOnDrop wrapper for Vec<Scalar>.
-		// When PR 202 [1] is merged, we can simply wrap s_L and s_R at the point of
-		// creation. [1] https://github.com/dalek-cryptography/curve25519-dalek/pull/202
-		for scalar in s_L1
-			.iter_mut()
-			.chain(s_L2.iter_mut())
-			.chain(s_R1.iter_mut())
-			.chain(s_R2.iter_mut())
-		{
-			scalar.clear();
-		}
+		util::clear_secrets(&mut s_L1);
+		util::clear_secrets(&mut s_L2);
+		util::clear_secrets(&mut s_R1);
+		util::clear_secrets(&mut s_R2);
+		util::clear_secrets(&mut s_U);
 
 		Ok(R1CSProof {
 			A_I1,
@@ -721,6 +1110,188 @@ impl<'t, 'g> Prover<'t, 'g> {
 		})
 	}
 
+	/// Consume the constraint systems of `provers` to produce a single
+	/// proof of all of them.
+	///
+	/// This is a convenience wrapper around
+	/// [`Prover::prove_combined_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_combined(
+		pc_gens: &'g PedersenGens,
+		transcript: &'t mut Transcript,
+		provers: Vec<Prover<'_, 'g>>,
+		bp_gens: &BulletproofGens,
+	) -> Result<R1CSProof, R1CSError> {
+		Prover::prove_combined_with_rng(pc_gens, transcript, provers, bp_gens, &mut thread_rng())
+	}
+
+	/// An alias of [`Prover::prove_combined`].
+	///
+	/// The single proof of several constraint systems is called combined
+	/// rather than batched to tell it apart from batch verification, which
+	/// checks several proofs at once.
+	#[cfg(feature = "std")]
+	pub fn prove_batch(
+		pc_gens: &'g PedersenGens,
+		transcript: &'t mut Transcript,
+		provers: Vec<Prover<'_, 'g>>,
+		bp_gens: &BulletproofGens,
+	) -> Result<R1CSProof, R1CSError> {
+		Prover::prove_combined(pc_gens, transcript, provers, bp_gens)
+	}
+
+	/// Consume the constraint systems of `provers` to produce a single
+	/// proof of all of them, verified by [`Verifier::verify_combined`].
+	///
+	/// The constraint systems are laid side by side on the generators of
+	/// `bp_gens`, which needs a `gens_capacity` of at least their total
+	/// number of multipliers, and proven with a single inner-product
+	/// argument.  The proof has the size of a proof of one constraint
+	/// system with all the multipliers, so proving many small constraint
+	/// systems together pays the logarithmic part of the proof only
+	/// once.
+	///
+	/// Each prover keeps its own transcript, which must have been created
+	/// with the same `pc_gens`.  The proof is bound to a digest of each of
+	/// them, in order, and to the shared `transcript`.
+	///
+	/// Returns [`R1CSError::RandomizedConstraints`] or
+	/// [`R1CSError::BatchedVectorCommitments`] if one of the constraint
+	/// systems has randomized constraints or vector commitments, and
+	/// [`R1CSError::MissingBlindingFactors`] if one of them has commitments
+	/// added with [`Prover::commit_blinded`].
+	///
+	/// [`Verifier::verify_combined`]: super::Verifier::verify_combined
+	pub fn prove_combined_with_rng<T: RngCore + CryptoRng>(
+		pc_gens: &'g PedersenGens,
+		transcript: &'t mut Transcript,
+		provers: Vec<Prover<'_, 'g>>,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<R1CSProof, R1CSError> {
+		transcript.r1cs_batch_domain_sep(provers.len() as u64);
+		let mut batch = Prover::new(pc_gens, transcript);
+
+		for prover in provers {
+			if !prover.deferred_constraints.is_empty() {
+				return Err(R1CSError::RandomizedConstraints);
+			}
+			if !prover.vector_blindings.is_empty() {
+				return Err(R1CSError::BatchedVectorCommitments);
+			}
+			if !prover.blinded.is_empty() {
+				return Err(R1CSError::MissingBlindingFactors);
+			}
+			let digest = batch_member_digest(prover.transcript);
+			transcript::append_message(batch.transcript, b"member", &digest);
+
+			// The witness is copied, so that dropping the prover clears it.
+			let (committed, public, multipliers) = (batch.v.len(), batch.public.len(), batch.a_L.len());
+			for lc in prover.constraints.iter() {
+				batch.push_constraint(lc.clone().shift(committed, public, multipliers));
+			}
+			batch.public.extend_from_slice(&prover.public);
+			batch.v.extend_from_slice(&prover.v);
+			batch.v_blinding.extend_from_slice(&prover.v_blinding);
+			batch.a_L.extend_from_slice(&prover.a_L);
+			batch.a_R.extend_from_slice(&prover.a_R);
+			batch.a_O.extend_from_slice(&prover.a_O);
+		}
+
+		batch.prove_with_rng(bp_gens, prng)
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof split into
+	/// segments of `chunk_size` multipliers.
+	///
+	/// This is a convenience wrapper around
+	/// [`Prover::prove_chunked_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_chunked(self, bp_gens: &BulletproofGens, chunk_size: usize) -> Result<ChunkedProof, R1CSError> {
+		self.prove_chunked_with_rng(bp_gens, chunk_size, &mut thread_rng())
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof split into
+	/// segments of `chunk_size` multipliers, verified by
+	/// [`Verifier::verify_chunked`].
+	///
+	/// The segments are proven one after the other, each on the first
+	/// `chunk_size` generators of `bp_gens`, so a constraint system with
+	/// far more multipliers than `bp_gens` has generators can be proven
+	/// without allocating the vectors of a proof of all of them.  A
+	/// multiplier wire shared between segments costs a commitment in the
+	/// proof, so `chunk_size` should follow the structure of the circuit,
+	/// such as the size of a repeated gadget, and be a power of two.  See
+	/// [`ChunkedProof`] for the construction.
+	///
+	/// Returns [`R1CSError::InvalidGeneratorsLength`] if `chunk_size` is
+	/// zero or larger than the `gens_capacity` of `bp_gens`,
+	/// [`R1CSError::RandomizedConstraints`] or
+	/// [`R1CSError::BatchedVectorCommitments`] if the constraint system has
+	/// randomized constraints or vector commitments, and
+	/// [`R1CSError::MissingBlindingFactors`] if it has commitments added
+	/// with [`Prover::commit_blinded`].
+	///
+	/// [`Verifier::verify_chunked`]: super::Verifier::verify_chunked
+	pub fn prove_chunked_with_rng<T: RngCore + CryptoRng>(
+		self,
+		bp_gens: &BulletproofGens,
+		chunk_size: usize,
+		prng: &mut T,
+	) -> Result<ChunkedProof, R1CSError> {
+		if chunk_size > bp_gens.gens_capacity {
+			return Err(R1CSError::InvalidGeneratorsLength);
+		}
+		if !self.vector_blindings.is_empty() {
+			return Err(R1CSError::BatchedVectorCommitments);
+		}
+		if !self.blinded.is_empty() {
+			return Err(R1CSError::MissingBlindingFactors);
+		}
+		let circuit = self.circuit()?;
+		let witness = self.witness()?;
+		let plan = ChunkPlan::new(&circuit, chunk_size)?;
+
+		let mut link_values: Vec<Scalar> = plan
+			.links
+			.iter()
+			.map(|var| chunked::wire_value(&witness, var))
+			.collect();
+		let mut link_blindings: Vec<Scalar> = plan.links.iter().map(|_| Scalar::random(prng)).collect();
+		let links: Vec<CompressedRistretto> = link_values
+			.iter()
+			.zip(link_blindings.iter())
+			.map(|(w, w_blinding)| self.pc_gens.commit(*w, *w_blinding).compress())
+			.collect();
+
+		self.transcript
+			.r1cs_chunked_domain_sep(plan.segments.len() as u64, chunk_size as u64);
+		for W in links.iter() {
+			self.transcript.append_point(b"W", W);
+		}
+
+		let segments = plan
+			.segments
+			.iter()
+			.enumerate()
+			.map(|(i, segment)| {
+				let mut transcript = self.transcript.clone();
+				transcript::append_u64(&mut transcript, b"segment", i as u64);
+				let segment_witness = segment.witness(&witness, &link_values, &link_blindings);
+				let prover = Prover::from_witness(self.pc_gens, &mut transcript, segment.circuit(), &segment_witness)?;
+				prover.prove_with_rng(bp_gens, prng)
+			})
+			.collect::<Result<Vec<_>, _>>();
+
+		util::clear_secrets(&mut link_values);
+		util::clear_secrets(&mut link_blindings);
+
+		Ok(ChunkedProof {
+			links,
+			segments: segments?,
+		})
+	}
+
 	pub fn num_constraints(&self) -> usize {
 		self.constraints.len()
 	}
@@ -728,4 +1299,161 @@ impl<'t, 'g> Prover<'t, 'g> {
 	pub fn num_multipliers(&self) -> usize {
 		self.a_O.len()
 	}
+
+	/// Returns the [`Circuit`] built so far, without any assignments.
+	///
+	/// Returns [`R1CSError::RandomizedConstraints`] if the constraint
+	/// system has randomized constraints.
+	pub fn circuit(&self) -> Result<Circuit, R1CSError> {
+		if !self.deferred_constraints.is_empty() {
+			return Err(R1CSError::RandomizedConstraints);
+		}
+		Ok(Circuit::new(
+			self.v.len(),
+			self.vector_values.len(),
+			self.public.len(),
+			self.a_L.len(),
+			&self.constraints,
+		))
+	}
+
+	/// Returns the [`Witness`] assigned so far: the openings of the
+	/// commitments, the values of the public inputs and the assignments of
+	/// the multipliers.
+	///
+	/// Together with the [`Circuit`], this lets another party create the
+	/// proof with [`Prover::from_witness`].
+	///
+	/// Returns [`R1CSError::RandomizedConstraints`] if the constraint
+	/// system has randomized constraints.
+	pub fn witness(&self) -> Result<Witness, R1CSError> {
+		if !self.deferred_constraints.is_empty() {
+			return Err(R1CSError::RandomizedConstraints);
+		}
+		Ok(Witness {
+			v: self.v.clone(),
+			v_blinding: self.v_blinding.clone(),
+			public: self.public.clone(),
+			a_L: self.a_L.clone(),
+			a_R: self.a_R.clone(),
+			a_O: self.a_O.clone(),
+		})
+	}
+
+	/// Constructs a prover for a stored [`Circuit`] with the assignment
+	/// `witness`.
+	///
+	/// The result is equivalent to calling [`Prover::new`], then
+	/// [`Prover::commit`] for each committed variable and
+	/// [`Prover::allocate_public`] for each public input, and building the
+	/// constraint system with the gadget code the circuit and witness were
+	/// extracted from.
+	///
+	/// Returns [`R1CSError::WrongNumCommitments`],
+	/// [`R1CSError::WrongNumPublicInputs`] or
+	/// [`R1CSError::WrongNumMultipliers`] if the witness does not have the
+	/// shape of the circuit, and [`R1CSError::UnsatisfiedConstraint`] if it
+	/// does not satisfy a constraint.  As with
+	/// [`Verifier::from_circuit`](::r1cs::Verifier::from_circuit), the
+	/// circuit must not have vector-committed variables.
+	pub fn from_witness(
+		pc_gens: &'g PedersenGens,
+		transcript: &'t mut Transcript,
+		circuit: &Circuit,
+		witness: &Witness,
+	) -> Result<Self, R1CSError> {
+		check_witness_shape(circuit, witness)?;
+
+		let mut prover = Prover::new(pc_gens, transcript);
+		for (v, v_blinding) in witness.v.iter().zip(witness.v_blinding.iter()) {
+			prover.commit(*v, *v_blinding);
+		}
+		prover.assign_witness(circuit, witness)?;
+		Ok(prover)
+	}
+
+	/// Constructs a prover for a stored [`Circuit`] with the assignment
+	/// `witness`, whose blinding factors are held by a delegating client,
+	/// and the client's `commitments` to its high-level variables.
+	///
+	/// This is the proving service's side of a
+	/// [`delegation`](super::delegation), and is equivalent to
+	/// [`Prover::from_witness`] with [`Prover::commit_blinded`] in place of
+	/// [`Prover::commit`].  The blinding factors of `witness` are ignored.
+	///
+	/// Returns the errors of [`Prover::from_witness`], and
+	/// [`R1CSError::WrongNumCommitments`] if the number of commitments does
+	/// not match the circuit.
+	pub fn from_blinded_witness(
+		pc_gens: &'g PedersenGens,
+		transcript: &'t mut Transcript,
+		circuit: &Circuit,
+		witness: &Witness,
+		commitments: &[CompressedRistretto],
+	) -> Result<Self, R1CSError> {
+		check_witness_shape(circuit, witness)?;
+		if commitments.len() != circuit.committed_variables {
+			return Err(R1CSError::WrongNumCommitments {
+				expected: circuit.committed_variables,
+				actual: commitments.len(),
+			});
+		}
+
+		let mut prover = Prover::new(pc_gens, transcript);
+		for (v, V) in witness.v.iter().zip(commitments.iter()) {
+			prover.commit_blinded(*v, *V);
+		}
+		prover.assign_witness(circuit, witness)?;
+		Ok(prover)
+	}
+
+	/// Allocates the public inputs and assigns the multipliers of
+	/// `witness`, and adds the constraints of `circuit` after checking
+	/// that the witness satisfies them.
+	fn assign_witness(&mut self, circuit: &Circuit, witness: &Witness) -> Result<(), R1CSError> {
+		for x in witness.public.iter() {
+			self.allocate_public(*x);
+		}
+		self.a_L = witness.a_L.clone();
+		self.a_R = witness.a_R.clone();
+		self.a_O = witness.a_O.clone();
+
+		if let Some(index) = circuit
+			.constraints
+			.iter()
+			.position(|lc| self.eval(lc) != Scalar::zero())
+		{
+			return Err(R1CSError::UnsatisfiedConstraint { index });
+		}
+		for lc in circuit.constraints.iter() {
+			self.circuit_hasher.append_canonical(lc);
+		}
+		self.constraints = circuit.constraints.clone();
+		Ok(())
+	}
+}
+
+/// Checks that `witness` has the numbers of committed variables, public
+/// inputs and multipliers of `circuit`, and that the circuit has no
+/// vector-committed variables.
+fn check_witness_shape(circuit: &Circuit, witness: &Witness) -> Result<(), R1CSError> {
+	if witness.v.len() != circuit.committed_variables || circuit.vector_committed_variables != 0 {
+		return Err(R1CSError::WrongNumCommitments {
+			expected: circuit.committed_variables + circuit.vector_committed_variables,
+			actual: witness.v.len(),
+		});
+	}
+	if witness.public.len() != circuit.public_inputs {
+		return Err(R1CSError::WrongNumPublicInputs {
+			expected: circuit.public_inputs,
+			actual: witness.public.len(),
+		});
+	}
+	if witness.multipliers() != circuit.multipliers {
+		return Err(R1CSError::WrongNumMultipliers {
+			expected: circuit.multipliers,
+			actual: witness.multipliers(),
+		});
+	}
+	Ok(())
 }
@@ -1,11 +1,11 @@
 #![allow(non_snake_case)]
 
 use alloc::{boxed::Box, vec::Vec};
-use core::mem;
+use core::{iter, mem};
 use curve25519_dalek::{
 	ristretto::{CompressedRistretto, RistrettoPoint},
 	scalar::Scalar,
-	traits::VartimeMultiscalarMul,
+	traits::IsIdentity,
 };
 use merlin::Transcript;
 use rand_core::{CryptoRng, RngCore};
@@ -14,15 +14,24 @@ use rand_core::{CryptoRng, RngCore};
 use rand::thread_rng;
 
 use super::{
-	ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem, RandomizedConstraintSystem, Variable,
+	batch_member_digest,
+	chunked::{ChunkPlan, ChunkedProof},
+	circuit::CircuitHasher,
+	Circuit, CircuitVerifyingKey, ConstraintSystem, LinearCombination, Metrics, Phase, R1CSProof,
+	RandomizableConstraintSystem, RandomizedConstraintSystem, Variable,
 };
 
 use crate::{
 	errors::R1CSError,
-	generators::{BulletproofGens, PedersenGens},
-	transcript::TranscriptProtocol,
+	generators::{BulletproofGens, PedersenGens, VerificationKey},
+	transcript::{self, TranscriptProtocol},
+	util,
 };
 
+/// A closure adding randomized constraints, called in the second phase of
+/// the protocol.
+type DeferredConstraint<'t> = Box<dyn Fn(&mut RandomizingVerifier<'t>) -> Result<(), R1CSError>>;
+
 /// A [`ConstraintSystem`] implementation for use by the verifier.
 ///
 /// The verifier adds high-level variable commitments to the transcript,
@@ -35,6 +44,8 @@ use crate::{
 pub struct Verifier<'t> {
 	transcript: &'t mut Transcript,
 	constraints: Vec<LinearCombination>,
+	/// Running digest of the non-randomized constraints
+	circuit_hasher: CircuitHasher,
 
 	/// Records the number of low-level variables allocated in the
 	/// constraint system.
@@ -45,12 +56,18 @@ pub struct Verifier<'t> {
 	/// variable assignments.
 	num_vars: usize,
 	V: Vec<CompressedRistretto>,
+	/// Commitments to vectors of high-level variables.
+	C: Vec<CompressedRistretto>,
+	/// The total number of entries of the vectors committed in `C`.
+	num_vector_vars: usize,
+	/// Values of the public inputs.
+	public: Vec<Scalar>,
 
 	/// This list holds closures that will be called in the second phase of the
 	/// protocol, when non-randomized variables are committed.
 	/// After that, the option will flip to None and additional calls to
 	/// `randomize_constraints` will invoke closures immediately.
-	deferred_constraints: Vec<Box<dyn Fn(&mut RandomizingVerifier<'t>) -> Result<(), R1CSError>>>,
+	deferred_constraints: Vec<DeferredConstraint<'t>>,
 
 	/// Index of a pending multiplier that's not fully assigned yet.
 	pending_multiplier: Option<usize>,
@@ -129,10 +146,33 @@ impl<'t> ConstraintSystem for Verifier<'t> {
 		self.num_vars
 	}
 
+	fn metrics(&self) -> Metrics {
+		Metrics {
+			multipliers: self.num_vars,
+			constraints: self.constraints.len(),
+			committed_variables: self.V.len(),
+			vector_committed_variables: self.num_vector_vars,
+			vector_commitments: self.C.len(),
+			phase: Phase::NonRandomized,
+		}
+	}
+
+	// The verifier only counts the multipliers, so it reserves nothing
+	// for them.
+	fn reserve_constraints(&mut self, additional: usize) {
+		self.constraints.reserve(additional);
+	}
+
+	fn circuit_digest(&self) -> [u8; 32] {
+		self.circuit_hasher
+			.digest(self.V.len(), self.num_vector_vars, self.public.len(), self.num_vars)
+	}
+
 	fn constrain(&mut self, lc: LinearCombination) {
 		// TODO: check that the linear combinations are valid
 		// (e.g. that variables are valid, that the linear combination
 		// evals to 0 for prover, etc).
+		self.circuit_hasher.append(&lc);
 		self.constraints.push(lc);
 	}
 
@@ -171,6 +211,13 @@ impl<'t> ConstraintSystem for RandomizingVerifier<'t> {
 		self.verifier.multiply(left, right)
 	}
 
+	fn multiply_many(
+		&mut self,
+		pairs: &[(LinearCombination, LinearCombination)],
+	) -> Vec<(Variable, Variable, Variable)> {
+		self.verifier.multiply_many(pairs)
+	}
+
 	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError> {
 		self.verifier.allocate(assignment)
 	}
@@ -186,6 +233,21 @@ impl<'t> ConstraintSystem for RandomizingVerifier<'t> {
 		self.verifier.multipliers_len()
 	}
 
+	fn metrics(&self) -> Metrics {
+		Metrics {
+			phase: Phase::Randomized,
+			..self.verifier.metrics()
+		}
+	}
+
+	fn reserve_constraints(&mut self, additional: usize) {
+		self.verifier.reserve_constraints(additional)
+	}
+
+	fn circuit_digest(&self) -> [u8; 32] {
+		self.verifier.circuit_digest()
+	}
+
 	fn constrain(&mut self, lc: LinearCombination) {
 		self.verifier.constrain(lc)
 	}
@@ -203,6 +265,10 @@ impl<'t> RandomizedConstraintSystem for RandomizingVerifier<'t> {
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
 		self.verifier.transcript.challenge_scalar(label)
 	}
+
+	fn value_of(&self, _: Variable) -> Option<Scalar> {
+		None
+	}
 }
 
 impl<'t> Verifier<'t> {
@@ -238,7 +304,11 @@ impl<'t> Verifier<'t> {
 			transcript,
 			num_vars: 0,
 			V: Vec::new(),
+			C: Vec::new(),
+			num_vector_vars: 0,
+			public: Vec::new(),
 			constraints: Vec::new(),
+			circuit_hasher: CircuitHasher::new(),
 			deferred_constraints: Vec::new(),
 			pending_multiplier: None,
 		}
@@ -270,6 +340,166 @@ impl<'t> Verifier<'t> {
 		Variable::Committed(i)
 	}
 
+	/// Allocates a public input with the value `x`.
+	///
+	/// This is the verifier's side of
+	/// [`Prover::allocate_public`](::r1cs::Prover::allocate_public): the
+	/// public inputs must be allocated in the same order and with the same
+	/// values as by the prover.
+	///
+	/// # Returns
+	///
+	/// Returns a [`Variable`] for the public input, which can be used to
+	/// form constraints.
+	pub fn allocate_public(&mut self, x: Scalar) -> Variable {
+		let i = self.public.len();
+		self.public.push(x);
+		Variable::Public(i)
+	}
+
+	/// Creates high-level variables for each of `commitments`, as by
+	/// [`Verifier::commit`].
+	///
+	/// Together with a [`VectorLinkProof`](::VectorLinkProof) for
+	/// `commitments`, this makes the entries of a
+	/// [`VectorCommitment`](::VectorCommitment) available to the
+	/// constraint system.
+	pub fn commit_each(&mut self, commitments: &[CompressedRistretto]) -> Vec<Variable> {
+		commitments.iter().map(|V| self.commit(*V)).collect()
+	}
+
+	/// Creates high-level variables for the `len` entries of a vector
+	/// committed to by `commitment`, and adds the commitment to the
+	/// transcript.
+	///
+	/// This is the verifier's side of
+	/// [`Prover::commit_vec`](::r1cs::Prover::commit_vec): the vectors must be
+	/// committed in the same order, and the [`BulletproofGens`] passed to
+	/// [`Verifier::verify`] must be the ones the prover committed with.
+	///
+	/// # Returns
+	///
+	/// Returns a [`Variable`] for each entry of the vector, which can be
+	/// used to form constraints.
+	pub fn commit_vec(&mut self, commitment: CompressedRistretto, len: usize) -> Vec<Variable> {
+		let offset = self.num_vector_vars;
+		self.num_vector_vars += len;
+		self.C.push(commitment);
+
+		// Add the commitment to the transcript, prefixed with its length.
+		transcript::append_u64(self.transcript, b"k", len as u64);
+		self.transcript.append_point(b"C", &commitment);
+
+		(offset..offset + len).map(Variable::VectorCommitted).collect()
+	}
+
+	/// Constructs a verifier for a stored [`Circuit`], committing to
+	/// the given `commitments` to its high-level variables.
+	///
+	/// The result is equivalent to calling [`Verifier::new`] and
+	/// [`Verifier::commit`] for each commitment, then building the
+	/// constraint system with the gadget code the circuit was extracted
+	/// from.
+	///
+	/// Returns [`R1CSError::WrongNumCommitments`] if the number of
+	/// commitments does not match the circuit, or if the circuit has
+	/// vector-committed variables, whose commitments cannot be passed here.
+	/// The vector-committed variables count towards the expected number.
+	///
+	/// The values of the public inputs of the circuit are not part of it,
+	/// and are passed to [`Verifier::allocate_public`] afterwards.
+	pub fn from_circuit(
+		transcript: &'t mut Transcript,
+		circuit: &Circuit,
+		commitments: &[CompressedRistretto],
+	) -> Result<Self, R1CSError> {
+		if commitments.len() != circuit.committed_variables || circuit.vector_committed_variables != 0 {
+			return Err(R1CSError::WrongNumCommitments {
+				expected: circuit.committed_variables + circuit.vector_committed_variables,
+				actual: commitments.len(),
+			});
+		}
+
+		let mut verifier = Verifier::new(transcript);
+		for V in commitments {
+			verifier.commit(*V);
+		}
+		verifier.num_vars = circuit.multipliers;
+		for lc in circuit.constraints.iter() {
+			verifier.circuit_hasher.append_canonical(lc);
+		}
+		verifier.constraints = circuit.constraints.clone();
+
+		Ok(verifier)
+	}
+
+	/// Returns the [`Circuit`] built so far.
+	///
+	/// Returns [`R1CSError::RandomizedConstraints`] if the constraint
+	/// system has randomized constraints.
+	pub fn circuit(&self) -> Result<Circuit, R1CSError> {
+		if !self.deferred_constraints.is_empty() {
+			return Err(R1CSError::RandomizedConstraints);
+		}
+		Ok(Circuit::new(
+			self.V.len(),
+			self.num_vector_vars,
+			self.public.len(),
+			self.num_vars,
+			&self.constraints,
+		))
+	}
+
+	/// Consumes the fully built `Verifier` and returns the
+	/// [`CircuitVerifyingKey`] of its circuit, which verifies proofs for it
+	/// without re-running the gadget code.
+	///
+	/// The commitments and public inputs of this verifier are not part of the
+	/// key, and are passed to [`CircuitVerifyingKey::verify`] instead.
+	///
+	/// Returns [`R1CSError::RandomizedConstraints`] if the constraint
+	/// system has randomized constraints.
+	pub fn finalize(self) -> Result<CircuitVerifyingKey, R1CSError> {
+		self.circuit().map(CircuitVerifyingKey::new)
+	}
+
+	/// Checks that the constraints only refer to commitments, public
+	/// inputs and multipliers of this constraint system.
+	fn check_shape(&self) -> Result<(), R1CSError> {
+		for (var, _) in self.constraints.iter().flat_map(|lc| lc.terms.iter()) {
+			match *var {
+				Variable::Committed(i) if i >= self.V.len() => {
+					return Err(R1CSError::WrongNumCommitments {
+						expected: i + 1,
+						actual: self.V.len(),
+					})
+				}
+				Variable::VectorCommitted(i) if i >= self.num_vector_vars => {
+					return Err(R1CSError::WrongNumCommitments {
+						expected: i + 1,
+						actual: self.num_vector_vars,
+					})
+				}
+				Variable::Public(i) if i >= self.public.len() => {
+					return Err(R1CSError::WrongNumPublicInputs {
+						expected: i + 1,
+						actual: self.public.len(),
+					})
+				}
+				Variable::MultiplierLeft(i) | Variable::MultiplierRight(i) | Variable::MultiplierOutput(i)
+					if i >= self.num_vars =>
+				{
+					return Err(R1CSError::WrongNumMultipliers {
+						expected: i + 1,
+						actual: self.num_vars,
+					})
+				}
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+
 	/// Use a challenge, `z`, to flatten the constraints in the
 	/// constraint system into vectors used for proving and
 	/// verification.
@@ -278,32 +508,52 @@ impl<'t> Verifier<'t> {
 	///
 	/// Returns a tuple of
 	/// ```text
-	/// (wL, wR, wO, wV, wc)
+	/// (wL, wR, wO, wV, wU, wc)
 	/// ```
-	/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+	/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\), and `wU`
+	/// holds the weights of the entries of the vector commitments.
 	///
 	/// This has the same logic as `ProverCS::flattened_constraints()`
 	/// but also computes the constant terms (which the prover skips
 	/// because they're not needed to construct the proof).
-	fn flattened_constraints(&mut self, z: &Scalar) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Scalar) {
+	///
+	/// The terms with a zero coefficient are skipped, so flattening costs
+	/// one multiplication per nonzero term.  `wL` and `wR` also record
+	/// which multipliers have a weight, so that combining them into
+	/// \\(\delta(y, z)\\) costs \\(O(k)\\) for \\(k\\) weighted
+	/// multipliers instead of \\(O(n)\\).
+	#[allow(clippy::type_complexity)]
+	fn flattened_constraints(
+		&mut self,
+		z: &Scalar,
+	) -> (
+		MultiplierWeights,
+		MultiplierWeights,
+		Vec<Scalar>,
+		Vec<Scalar>,
+		Vec<Scalar>,
+		Scalar,
+	) {
 		let n = self.num_vars;
 		let m = self.V.len();
 
-		let mut wL = vec![Scalar::zero(); n];
-		let mut wR = vec![Scalar::zero(); n];
+		let mut wL = MultiplierWeights::new(n);
+		let mut wR = MultiplierWeights::new(n);
 		let mut wO = vec![Scalar::zero(); n];
 		let mut wV = vec![Scalar::zero(); m];
+		let mut wU = vec![Scalar::zero(); self.num_vector_vars];
 		let mut wc = Scalar::zero();
 
+		let zero = Scalar::zero();
 		let mut exp_z = *z;
 		for lc in self.constraints.iter() {
-			for (var, coeff) in &lc.terms {
+			for (var, coeff) in lc.terms.iter().filter(|(_, coeff)| *coeff != zero) {
 				match var {
 					Variable::MultiplierLeft(i) => {
-						wL[*i] += exp_z * coeff;
+						wL.add(*i, exp_z * coeff);
 					}
 					Variable::MultiplierRight(i) => {
-						wR[*i] += exp_z * coeff;
+						wR.add(*i, exp_z * coeff);
 					}
 					Variable::MultiplierOutput(i) => {
 						wO[*i] += exp_z * coeff;
@@ -311,6 +561,12 @@ impl<'t> Verifier<'t> {
 					Variable::Committed(i) => {
 						wV[*i] -= exp_z * coeff;
 					}
+					Variable::VectorCommitted(i) => {
+						wU[*i] -= exp_z * coeff;
+					}
+					Variable::Public(i) => {
+						wc -= exp_z * coeff * self.public[*i];
+					}
 					Variable::One() => {
 						wc -= exp_z * coeff;
 					}
@@ -319,7 +575,7 @@ impl<'t> Verifier<'t> {
 			exp_z *= z;
 		}
 
-		(wL, wR, wO, wV, wc)
+		(wL, wR, wO, wV, wU, wc)
 	}
 
 	/// Calls all remembered callbacks with an API that
@@ -347,12 +603,7 @@ impl<'t> Verifier<'t> {
 	}
 
 	#[cfg(feature = "std")]
-	pub fn verify(
-		mut self,
-		proof: &R1CSProof,
-		pc_gens: &PedersenGens,
-		bp_gens: &BulletproofGens,
-	) -> Result<(), R1CSError> {
+	pub fn verify(self, proof: &R1CSProof, pc_gens: &PedersenGens, bp_gens: &BulletproofGens) -> Result<(), R1CSError> {
 		self.verify_with_rng(proof, pc_gens, bp_gens, &mut thread_rng())
 	}
 
@@ -362,18 +613,475 @@ impl<'t> Verifier<'t> {
 	/// [`BulletproofGens`] should have `gens_capacity` greater than
 	/// the number of multiplication constraints that will eventually
 	/// be added into the constraint system.
+	///
+	/// Besides [`R1CSError::VerificationError`] for a proof that does not
+	/// verify, this returns [`R1CSError::WrongNumMultipliers`] if the
+	/// proof was created for a different number of multipliers (in total
+	/// or in the randomized phase), [`R1CSError::WrongNumCommitments`] if
+	/// a constraint refers to a commitment that was not passed to the
+	/// verifier, and [`R1CSError::FormatError`] if the proof contains
+	/// invalid points.
 	pub fn verify_with_rng<T: RngCore + CryptoRng>(
-		mut self,
+		self,
+		proof: &R1CSProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		let terms = self.verification_terms(
+			proof,
+			bp_gens.gens_capacity,
+			bp_gens.party_capacity,
+			Scalar::one(),
+			prng,
+		)?;
+
+		// The multipliers use the generators of party 0, and the entries of
+		// vector commitments those of party 1.
+		let gens = bp_gens.share(0);
+		let vector_gens = bp_gens.share(1);
+		let k = terms.vector_g_scalars.len();
+
+		let mega_check = util::optional_multiscalar_mul(
+			terms
+				.dynamic_scalars
+				.into_iter()
+				.chain(iter::once(terms.B_scalar))
+				.chain(iter::once(terms.B_blinding_scalar))
+				.chain(terms.g_scalars)
+				.chain(terms.h_scalars)
+				.chain(terms.vector_g_scalars)
+				.chain(terms.vector_h_scalars),
+			terms
+				.dynamic_points
+				.into_iter()
+				.chain(iter::once(Some(pc_gens.B)))
+				.chain(iter::once(Some(pc_gens.B_blinding)))
+				.chain(gens.G(terms.padded_n).map(|&G_i| Some(G_i)))
+				.chain(gens.H(terms.padded_n).map(|&H_i| Some(H_i)))
+				.chain(vector_gens.G(k).map(|&G_i| Some(G_i)))
+				.chain(vector_gens.H(k).map(|&H_i| Some(H_i))),
+		)
+		.ok_or_else(|| R1CSError::VerificationError)?;
+
+		if !mega_check.is_identity() {
+			return Err(R1CSError::VerificationError);
+		}
+
+		Ok(())
+	}
+
+	/// Consume this `Verifier` and attempt to verify the supplied `proof`,
+	/// using the precomputed tables of `vk` for the fixed bases.
+	///
+	/// This accepts the same proofs as [`Verifier::verify_with_rng`] with the
+	/// generators `vk` was created from, and is faster when many proofs are
+	/// verified with the same key.  The number of multipliers, padded to a
+	/// power of two, must fit in the key.
+	pub fn verify_precomputed_with_rng<T: RngCore + CryptoRng>(
+		self,
+		proof: &R1CSProof,
+		vk: &VerificationKey,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		if vk.party_capacity() < 1 {
+			return Err(R1CSError::InvalidGeneratorsLength);
+		}
+		let terms = self.verification_terms(proof, vk.gens_capacity(), vk.party_capacity(), Scalar::one(), prng)?;
+
+		// Lay the scalars out as two parties of equal width, the second of
+		// which holds the entries of vector commitments.
+		let (width, g_scalars, h_scalars) = if terms.vector_g_scalars.is_empty() {
+			(terms.padded_n, terms.g_scalars, terms.h_scalars)
+		} else {
+			let width = terms.padded_n.max(terms.vector_g_scalars.len());
+			let two_parties = |mut party_0: Vec<Scalar>, party_1: Vec<Scalar>| {
+				party_0.resize(width, Scalar::zero());
+				party_0.extend(party_1);
+				party_0
+			};
+			(
+				width,
+				two_parties(terms.g_scalars, terms.vector_g_scalars),
+				two_parties(terms.h_scalars, terms.vector_h_scalars),
+			)
+		};
+
+		let mega_check = vk
+			.optional_mixed_multiscalar_mul(
+				[terms.B_scalar, terms.B_blinding_scalar],
+				&g_scalars,
+				&h_scalars,
+				width,
+				terms.dynamic_scalars,
+				terms.dynamic_points,
+			)
+			.ok_or(R1CSError::VerificationError)?;
+
+		if !mega_check.is_identity() {
+			return Err(R1CSError::VerificationError);
+		}
+
+		Ok(())
+	}
+
+	/// Consume this `Verifier` and attempt to verify the supplied `proof`,
+	/// using the precomputed tables of `vk` for the fixed bases.
+	/// This is a convenience wrapper around
+	/// [`Verifier::verify_precomputed_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_precomputed(self, proof: &R1CSProof, vk: &VerificationKey) -> Result<(), R1CSError> {
+		self.verify_precomputed_with_rng(proof, vk, &mut thread_rng())
+	}
+
+	/// Verifies a proof of the constraint systems of `verifiers` created
+	/// by [`Prover::prove_combined`](super::Prover::prove_combined).
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::verify_combined_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_combined(
+		transcript: &'t mut Transcript,
+		verifiers: Vec<Verifier<'_>>,
+		proof: &R1CSProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError> {
+		Verifier::verify_combined_with_rng(transcript, verifiers, proof, pc_gens, bp_gens, &mut thread_rng())
+	}
+
+	/// An alias of [`Verifier::verify_combined`], which verifies the
+	/// proofs of [`Prover::prove_batch`](super::Prover::prove_batch).
+	///
+	/// Not to be confused with [`Verifier::batch_verify`], which checks
+	/// several proofs at once.
+	#[cfg(feature = "std")]
+	pub fn verify_batch(
+		transcript: &'t mut Transcript,
+		verifiers: Vec<Verifier<'_>>,
+		proof: &R1CSProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError> {
+		Verifier::verify_combined(transcript, verifiers, proof, pc_gens, bp_gens)
+	}
+
+	/// Verifies a proof of the constraint systems of `verifiers` created
+	/// by [`Prover::prove_combined_with_rng`](super::Prover::prove_combined_with_rng).
+	///
+	/// The verifiers must be built like the provers, in the same order,
+	/// with the same transcripts, and the shared `transcript` must be in
+	/// the same state as the prover's.  Unlike [`Verifier::batch_verify`],
+	/// which checks several proofs at once, this checks a single proof.
+	///
+	/// Returns the errors of [`Verifier::verify_with_rng`] for the
+	/// constraint systems laid side by side, and
+	/// [`R1CSError::RandomizedConstraints`] or
+	/// [`R1CSError::BatchedVectorCommitments`] if one of them has
+	/// randomized constraints or vector commitments.
+	pub fn verify_combined_with_rng<T: RngCore + CryptoRng>(
+		transcript: &'t mut Transcript,
+		verifiers: Vec<Verifier<'_>>,
 		proof: &R1CSProof,
 		pc_gens: &PedersenGens,
 		bp_gens: &BulletproofGens,
 		prng: &mut T,
 	) -> Result<(), R1CSError> {
+		transcript.r1cs_batch_domain_sep(verifiers.len() as u64);
+		let mut batch = Verifier::new(transcript);
+
+		for verifier in verifiers {
+			if !verifier.deferred_constraints.is_empty() {
+				return Err(R1CSError::RandomizedConstraints);
+			}
+			if !verifier.C.is_empty() {
+				return Err(R1CSError::BatchedVectorCommitments);
+			}
+			// A constraint referring to a variable of the next constraint
+			// system would no longer be detected once they are merged.
+			verifier.check_shape()?;
+			let digest = batch_member_digest(verifier.transcript);
+			transcript::append_message(batch.transcript, b"member", &digest);
+
+			let (committed, public, multipliers) = (batch.V.len(), batch.public.len(), batch.num_vars);
+			for lc in verifier.constraints.into_iter() {
+				batch.constrain(lc.shift(committed, public, multipliers));
+			}
+			batch.V.extend(verifier.V);
+			batch.public.extend(verifier.public);
+			batch.num_vars += verifier.num_vars;
+		}
+
+		batch.verify_with_rng(proof, pc_gens, bp_gens, prng)
+	}
+
+	/// Consume this `Verifier` and attempt to verify the supplied chunked
+	/// `proof`, using `thread_rng`.
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::verify_chunked_with_rng`].
+	#[cfg(feature = "std")]
+	pub fn verify_chunked(
+		self,
+		proof: &ChunkedProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		chunk_size: usize,
+	) -> Result<(), R1CSError> {
+		self.verify_chunked_with_rng(proof, pc_gens, bp_gens, chunk_size, &mut thread_rng())
+	}
+
+	/// Consume this `Verifier` and attempt to verify the supplied chunked
+	/// `proof`, created by
+	/// [`Prover::prove_chunked_with_rng`](super::Prover::prove_chunked_with_rng)
+	/// with the same `chunk_size`.
+	///
+	/// The constraint system is split into segments as by the prover, and
+	/// the proof of each segment is verified with
+	/// [`Verifier::verify_with_rng`].
+	///
+	/// Returns the errors of [`Verifier::verify_with_rng`] for each segment,
+	/// [`R1CSError::VerificationError`] if the proof does not have the
+	/// numbers of segments and links of the split, and
+	/// [`R1CSError::RandomizedConstraints`] or
+	/// [`R1CSError::BatchedVectorCommitments`] if the constraint system has
+	/// randomized constraints or vector commitments.
+	pub fn verify_chunked_with_rng<T: RngCore + CryptoRng>(
+		self,
+		proof: &ChunkedProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		chunk_size: usize,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		if !self.C.is_empty() {
+			return Err(R1CSError::BatchedVectorCommitments);
+		}
+		self.check_shape()?;
+		let circuit = self.circuit()?;
+		let plan = ChunkPlan::new(&circuit, chunk_size)?;
+		if proof.links.len() != plan.links.len() || proof.segments.len() != plan.segments.len() {
+			return Err(R1CSError::VerificationError);
+		}
+
+		self.transcript
+			.r1cs_chunked_domain_sep(plan.segments.len() as u64, chunk_size as u64);
+		for W in proof.links.iter() {
+			self.transcript.append_point(b"W", W);
+		}
+
+		for (i, (segment, segment_proof)) in plan.segments.iter().zip(proof.segments.iter()).enumerate() {
+			let mut transcript = self.transcript.clone();
+			transcript::append_u64(&mut transcript, b"segment", i as u64);
+			let commitments = segment.commitments(&self.V, &proof.links);
+			let mut verifier = Verifier::from_circuit(&mut transcript, segment.circuit(), &commitments)?;
+			for x in self.public.iter() {
+				verifier.allocate_public(*x);
+			}
+			verifier.verify_with_rng(segment_proof, pc_gens, bp_gens, prng)?;
+		}
+
+		Ok(())
+	}
+
+	/// Verifies a batch of proofs with a single multiscalar multiplication,
+	/// using `thread_rng` for the batching weights.
+	///
+	/// See [`Verifier::batch_verify_with_rng`] for details.
+	#[cfg(feature = "std")]
+	pub fn batch_verify<I>(instances: I, pc_gens: &PedersenGens, bp_gens: &BulletproofGens) -> Result<(), R1CSError>
+	where
+		I: IntoIterator<Item = (Verifier<'t>, &'t R1CSProof)>,
+	{
+		Self::batch_verify_with_rng(instances, pc_gens, bp_gens, &mut thread_rng())
+	}
+
+	/// Verifies a batch of proofs of the same constraint system with a
+	/// single multiscalar multiplication, using `thread_rng` for the
+	/// batching weights.
+	///
+	/// See [`Verifier::batch_verify_proofs_with_rng`] for details.
+	#[cfg(feature = "std")]
+	pub fn batch_verify_proofs<F>(
+		proofs: &[(R1CSProof, Vec<CompressedRistretto>)],
+		transcript: &Transcript,
+		gadget: F,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError>
+	where
+		F: Fn(&mut Verifier, &[Variable]) -> Result<(), R1CSError>,
+	{
+		Verifier::batch_verify_proofs_with_rng(proofs, transcript, gadget, pc_gens, bp_gens, &mut thread_rng())
+	}
+
+	/// Verifies a batch of proofs of the same constraint system with a
+	/// single multiscalar multiplication.
+	///
+	/// Each proof is paired with its value commitments.  For each of them,
+	/// a `Verifier` is created on a copy of `transcript`, which must be in
+	/// the same state as the prover's, the commitments are passed to
+	/// [`Verifier::commit`] in order, and `gadget` builds the constraint
+	/// system on the resulting variables.  The proofs are then checked as
+	/// with [`Verifier::batch_verify_with_rng`], which takes the verifiers
+	/// directly for proofs of different constraint systems or transcripts.
+	pub fn batch_verify_proofs_with_rng<F, T: RngCore + CryptoRng>(
+		proofs: &[(R1CSProof, Vec<CompressedRistretto>)],
+		transcript: &Transcript,
+		gadget: F,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError>
+	where
+		F: Fn(&mut Verifier, &[Variable]) -> Result<(), R1CSError>,
+	{
+		let mut transcripts = vec![transcript.clone(); proofs.len()];
+		let mut instances = Vec::with_capacity(proofs.len());
+		for (transcript, (proof, commitments)) in transcripts.iter_mut().zip(proofs.iter()) {
+			let mut verifier = Verifier::new(transcript);
+			let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+			gadget(&mut verifier, &vars)?;
+			instances.push((verifier, proof));
+		}
+		Verifier::batch_verify_with_rng(instances, pc_gens, bp_gens, prng)
+	}
+
+	/// Verifies a batch of proofs with a single multiscalar multiplication.
+	///
+	/// Each instance is a `Verifier` on which the gadget has already been
+	/// built (with the proof's commitments passed to [`Verifier::commit`]),
+	/// paired with the proof to check.  The verification equation of each
+	/// proof is multiplied by an independent random weight and all equations
+	/// are summed, so that the generators shared by the proofs are only
+	/// multiplied once.  The proofs do not need to have the same number of
+	/// multipliers.
+	///
+	/// Returns `Ok(())` only if every proof in the batch is valid; the
+	/// failing proof is not identified.
+	///
+	/// With the `rayon` feature, the multiscalar multiplication is split
+	/// into chunks.  The transcripts are still replayed one proof at a time,
+	/// since a `Verifier` holds the gadget's randomized constraint callbacks
+	/// and cannot be sent to another thread.
+	pub fn batch_verify_with_rng<I, T: RngCore + CryptoRng>(
+		instances: I,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError>
+	where
+		I: IntoIterator<Item = (Verifier<'t>, &'t R1CSProof)>,
+	{
+		let mut batch = Vec::new();
+		for (verifier, proof) in instances {
+			let weight = Scalar::random(prng);
+			batch.push(verifier.verification_terms(
+				proof,
+				bp_gens.gens_capacity,
+				bp_gens.party_capacity,
+				weight,
+				prng,
+			)?);
+		}
+
+		let max_n = batch.iter().map(|terms| terms.padded_n).max().unwrap_or(0);
+		let max_k = batch
+			.iter()
+			.map(|terms| terms.vector_g_scalars.len())
+			.max()
+			.unwrap_or(0);
+
+		let mut B_scalar = Scalar::zero();
+		let mut B_blinding_scalar = Scalar::zero();
+		let mut g_scalars = vec![Scalar::zero(); max_n];
+		let mut h_scalars = vec![Scalar::zero(); max_n];
+		let mut vector_g_scalars = vec![Scalar::zero(); max_k];
+		let mut vector_h_scalars = vec![Scalar::zero(); max_k];
+		let mut dynamic_scalars = Vec::new();
+		let mut dynamic_points = Vec::new();
+
+		for terms in batch {
+			B_scalar += terms.B_scalar;
+			B_blinding_scalar += terms.B_blinding_scalar;
+			for (acc, g_i) in g_scalars.iter_mut().zip(terms.g_scalars) {
+				*acc += g_i;
+			}
+			for (acc, h_i) in h_scalars.iter_mut().zip(terms.h_scalars) {
+				*acc += h_i;
+			}
+			for (acc, g_i) in vector_g_scalars.iter_mut().zip(terms.vector_g_scalars) {
+				*acc += g_i;
+			}
+			for (acc, h_i) in vector_h_scalars.iter_mut().zip(terms.vector_h_scalars) {
+				*acc += h_i;
+			}
+			dynamic_scalars.extend(terms.dynamic_scalars);
+			dynamic_points.extend(terms.dynamic_points);
+		}
+
+		let gens = bp_gens.share(0);
+		let vector_gens = bp_gens.share(1);
+
+		let mega_check = util::optional_multiscalar_mul(
+			dynamic_scalars
+				.into_iter()
+				.chain(iter::once(B_scalar))
+				.chain(iter::once(B_blinding_scalar))
+				.chain(g_scalars)
+				.chain(h_scalars)
+				.chain(vector_g_scalars)
+				.chain(vector_h_scalars),
+			dynamic_points
+				.into_iter()
+				.chain(iter::once(Some(pc_gens.B)))
+				.chain(iter::once(Some(pc_gens.B_blinding)))
+				.chain(gens.G(max_n).map(|&G_i| Some(G_i)))
+				.chain(gens.H(max_n).map(|&H_i| Some(H_i)))
+				.chain(vector_gens.G(max_k).map(|&G_i| Some(G_i)))
+				.chain(vector_gens.H(max_k).map(|&H_i| Some(H_i))),
+		)
+		.ok_or_else(|| R1CSError::VerificationError)?;
+
+		if !mega_check.is_identity() {
+			return Err(R1CSError::VerificationError);
+		}
+
+		Ok(())
+	}
+
+	/// Consumes this `Verifier`, replays the proof transcript and computes
+	/// the terms of the verification equation for `proof`, with every
+	/// scalar multiplied by `weight`.
+	fn verification_terms<T: RngCore + CryptoRng>(
+		mut self,
+		proof: &R1CSProof,
+		gens_capacity: usize,
+		party_capacity: usize,
+		weight: Scalar,
+		prng: &mut T,
+	) -> Result<VerificationTerms, R1CSError> {
+		// Only proofs accepted by `R1CSProof::from_bytes_strict` can verify.
+		if !proof.is_canonical() {
+			return Err(R1CSError::FormatError);
+		}
+
 		// Commit a length _suffix_ for the number of high-level variables.
 		// We cannot do this in advance because user can commit variables one-by-one,
 		// but this suffix provides safe disambiguation because each variable
 		// is prefixed with a separate label.
-		self.transcript.append_u64(b"m", self.V.len() as u64);
+		transcript::append_u64(self.transcript, b"m", self.V.len() as u64);
+
+		// Bind the proof to the non-randomized part of the circuit.
+		let circuit_digest = self.circuit_digest();
+		self.circuit_hasher.freeze(circuit_digest);
+		transcript::append_message(self.transcript, b"circuit", &circuit_digest);
+
+		// Bind the proof to the values of the public inputs, whose number
+		// is part of the circuit.
+		for x in self.public.iter() {
+			self.transcript.append_scalar(b"public", x);
+		}
 
 		let n1 = self.num_vars;
 		self.transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
@@ -383,21 +1091,33 @@ impl<'t> Verifier<'t> {
 		// Process the remaining constraints.
 		self = self.create_randomized_constraints()?;
 
-		// If the number of multiplications is not 0 or a power of 2, then pad the
-		// circuit.
+		// If the number of multiplications and vector entries is not 0 or a
+		// power of 2, then pad the circuit.  The padding goes between the
+		// multipliers and the vector entries, which take the last `n_vec`
+		// positions of the inner-product argument.
 		let n = self.num_vars;
 		let n2 = n - n1;
-		let padded_n = self.num_vars.next_power_of_two();
-		let pad = padded_n - n;
-
-		use crate::{inner_product_proof::inner_product, util};
-		use core::iter;
+		let n_vec = self.num_vector_vars;
+		let padded_n = (n + n_vec).next_power_of_two();
+		let pad = padded_n - n_vec - n;
 
-		if bp_gens.gens_capacity < padded_n {
+		if gens_capacity < padded_n - n_vec {
 			return Err(R1CSError::InvalidGeneratorsLength);
 		}
-		// We are performing a single-party circuit proof, so party index is 0.
-		let gens = bp_gens.share(0);
+		if n_vec > 0 && (party_capacity < 2 || gens_capacity < n_vec) {
+			return Err(R1CSError::InvalidGeneratorsLength);
+		}
+
+		// Report a constraint system that does not match the proof's
+		// before the verification equation fails as a whole.
+		self.check_shape()?;
+		let lg_n = proof.ipp_proof.L_vec.len();
+		if lg_n >= 32 || 1 << lg_n != padded_n || proof.missing_phase2_commitments() != (n2 == 0) {
+			return Err(R1CSError::WrongNumMultipliers {
+				expected: padded_n,
+				actual: if lg_n < 32 { 1 << lg_n } else { usize::MAX },
+			});
+		}
 
 		// These points are the identity in the 1-phase unrandomized case.
 		self.transcript.append_point(b"A_I2", &proof.A_I2);
@@ -422,7 +1142,7 @@ impl<'t> Verifier<'t> {
 
 		let w = self.transcript.challenge_scalar(b"w");
 
-		let (wL, wR, wO, wV, wc) = self.flattened_constraints(&z);
+		let (wL, wR, wO, wV, wU, wc) = self.flattened_constraints(&z);
 
 		// Get IPP variables
 		let (u_sq, u_inv_sq, s) = proof
@@ -434,15 +1154,20 @@ impl<'t> Verifier<'t> {
 		let b = proof.ipp_proof.b;
 
 		let y_inv = y.invert();
-		let y_inv_vec = util::exp_iter(y_inv).take(padded_n).collect::<Vec<Scalar>>();
-		let yneg_wR = wR
-			.into_iter()
-			.zip(y_inv_vec.iter())
-			.map(|(wRi, exp_y_inv)| wRi * exp_y_inv)
-			.chain(iter::repeat(Scalar::zero()).take(pad))
-			.collect::<Vec<Scalar>>();
+		let y_inv_vec = util::exp_iter(y_inv).take(padded_n + 1).collect::<Vec<Scalar>>();
+		let mut yneg_wR = vec![Scalar::zero(); n + pad];
+		for &i in wR.support.iter() {
+			yneg_wR[i] = wR.weights[i] * y_inv_vec[i];
+		}
 
-		let delta = inner_product(&yneg_wR[0..n], &wL);
+		// Only the multipliers weighted both on the left and on the right
+		// contribute to delta, so the sum runs over the smaller support.
+		let support = if wL.support.len() < wR.support.len() {
+			&wL.support
+		} else {
+			&wR.support
+		};
+		let delta: Scalar = support.iter().map(|&i| yneg_wR[i] * wL.weights[i]).sum();
 
 		let u_for_g = iter::repeat(Scalar::one())
 			.take(n1)
@@ -454,17 +1179,34 @@ impl<'t> Verifier<'t> {
 			.iter()
 			.zip(u_for_g)
 			.zip(s.iter().take(padded_n))
-			.map(|((yneg_wRi, u_or_1), s_i)| u_or_1 * (x * yneg_wRi - a * s_i));
+			.map(|((yneg_wRi, u_or_1), s_i)| weight * u_or_1 * (x * yneg_wRi - a * s_i))
+			.collect();
 
 		let h_scalars = y_inv_vec
 			.iter()
 			.zip(u_for_h)
 			.zip(s.iter().rev().take(padded_n))
-			.zip(wL.into_iter().chain(iter::repeat(Scalar::zero()).take(pad)))
+			.zip(wL.weights.into_iter().chain(iter::repeat(Scalar::zero()).take(pad)))
 			.zip(wO.into_iter().chain(iter::repeat(Scalar::zero()).take(pad)))
 			.map(|((((y_inv_i, u_or_1), s_i_inv), wLi), wOi)| {
-				u_or_1 * (y_inv_i * (x * wLi + wOi - b * s_i_inv) - Scalar::one())
-			});
+				weight * u_or_1 * (y_inv_i * (x * wLi + wOi - b * s_i_inv) - Scalar::one())
+			})
+			.collect();
+
+		// The entries of vector commitments have l(x) = x * y * u + x^3 * s_U,
+		// committed to by the vector commitments and S1, and
+		// r(x) = -x * y^-1 * (z * z^Q * W_U).
+		let vector_g_scalars = s[padded_n - n_vec..].iter().map(|s_i| weight * (-a * s_i)).collect();
+		let vector_h_scalars = wU
+			.iter()
+			.zip(
+				y_inv_vec[padded_n - n_vec..]
+					.iter()
+					.zip(y_inv_vec[padded_n - n_vec + 1..].iter()),
+			)
+			.zip(s[..n_vec].iter().rev())
+			.map(|((wUi, (y_inv_i, y_inv_i1)), s_i_inv)| weight * (-x * wUi * y_inv_i1 - b * s_i_inv * y_inv_i))
+			.collect();
 
 		// Create a `TranscriptRng` from the transcript. The verifier
 		// has no witness data to commit, so this just mixes external
@@ -484,46 +1226,106 @@ impl<'t> Verifier<'t> {
 		let T_scalars = [r * x, r_xxx, r_xxxx, r_xxxxx, r_xxxxxx];
 		let T_points = [proof.T_1, proof.T_3, proof.T_4, proof.T_5, proof.T_6];
 
-		let mega_check = RistrettoPoint::optional_multiscalar_mul(
-			iter::once(x) // A_I1
-				.chain(iter::once(xx)) // A_O1
-				.chain(iter::once(xxx)) // S1
-				.chain(iter::once(u * x)) // A_I2
-				.chain(iter::once(u * xx)) // A_O2
-				.chain(iter::once(u * xxx)) // S2
-				.chain(wV.iter().map(|wVi| wVi * rxx)) // V
-				.chain(T_scalars.iter().cloned()) // T_points
-				.chain(iter::once(
-					w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x),
-				)) // B
-				.chain(iter::once(-proof.e_blinding - r * proof.t_x_blinding)) // B_blinding
-				.chain(g_scalars) // G
-				.chain(h_scalars) // H
-				.chain(u_sq.iter().cloned()) // ipp_proof.L_vec
-				.chain(u_inv_sq.iter().cloned()), // ipp_proof.R_vec
-			iter::once(proof.A_I1.decompress())
-				.chain(iter::once(proof.A_O1.decompress()))
-				.chain(iter::once(proof.S1.decompress()))
-				.chain(iter::once(proof.A_I2.decompress()))
-				.chain(iter::once(proof.A_O2.decompress()))
-				.chain(iter::once(proof.S2.decompress()))
-				.chain(self.V.iter().map(|V_i| V_i.decompress()))
-				.chain(T_points.iter().map(|T_i| T_i.decompress()))
-				.chain(iter::once(Some(pc_gens.B)))
-				.chain(iter::once(Some(pc_gens.B_blinding)))
-				.chain(gens.G(padded_n).map(|&G_i| Some(G_i)))
-				.chain(gens.H(padded_n).map(|&H_i| Some(H_i)))
-				.chain(proof.ipp_proof.L_vec.iter().map(|L_i| L_i.decompress()))
-				.chain(proof.ipp_proof.R_vec.iter().map(|R_i| R_i.decompress())),
-		)
-		.ok_or_else(|| R1CSError::VerificationError)?;
+		let dynamic_scalars = iter::once(x) // A_I1
+			.chain(iter::once(xx)) // A_O1
+			.chain(iter::once(xxx)) // S1
+			.chain(iter::once(u * x)) // A_I2
+			.chain(iter::once(u * xx)) // A_O2
+			.chain(iter::once(u * xxx)) // S2
+			.chain(wV.iter().map(|wVi| wVi * rxx)) // V
+			.chain(iter::repeat(x * y).take(self.C.len())) // C
+			.chain(T_scalars.iter().cloned()) // T_points
+			.chain(u_sq.iter().cloned()) // ipp_proof.L_vec
+			.chain(u_inv_sq.iter().cloned()) // ipp_proof.R_vec
+			.map(|scalar| weight * scalar)
+			.collect();
+		// Points of the proof that are not valid encodings make it
+		// malformed, while invalid commitments just fail verification.
+		let proof_points = |points: &[CompressedRistretto]| {
+			points
+				.iter()
+				.map(|P| P.decompress().map(Some).ok_or(R1CSError::FormatError))
+				.collect::<Result<Vec<_>, _>>()
+		};
+		let mut dynamic_points = proof_points(&[proof.A_I1, proof.A_O1, proof.S1, proof.A_I2, proof.A_O2, proof.S2])?;
+		dynamic_points.extend(self.V.iter().map(|V_i| V_i.decompress()));
+		dynamic_points.extend(self.C.iter().map(|C_i| C_i.decompress()));
+		dynamic_points.extend(proof_points(&T_points)?);
+		dynamic_points.extend(proof_points(&proof.ipp_proof.L_vec)?);
+		dynamic_points.extend(proof_points(&proof.ipp_proof.R_vec)?);
+
+		Ok(VerificationTerms {
+			padded_n: padded_n - n_vec,
+			dynamic_scalars,
+			dynamic_points,
+			B_scalar: weight * (w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x)),
+			B_blinding_scalar: weight * (-proof.e_blinding - r * proof.t_x_blinding),
+			g_scalars,
+			h_scalars,
+			vector_g_scalars,
+			vector_h_scalars,
+		})
+	}
+}
 
-		use curve25519_dalek::traits::IsIdentity;
+/// The contribution of a single R1CS proof to the verification equation,
+/// already scaled by the proof's batching weight.
+struct VerificationTerms {
+	/// Number of multipliers, padded so that together with the entries of
+	/// vector commitments they fill a power of two; the proof uses the
+	/// first `padded_n` generators of party 0.
+	padded_n: usize,
+	/// Scalars for the points specific to this proof: \\(A_{I1}, A_{O1},
+	/// S_1, A_{I2}, A_{O2}, S_2\\), the commitments \\(V\\) and
+	/// \\(C\\), the \\(T_i\\) and the IPP \\(L\\) and \\(R\\) points.
+	dynamic_scalars: Vec<Scalar>,
+	/// The points matching `dynamic_scalars`.
+	dynamic_points: Vec<Option<RistrettoPoint>>,
+	/// Scalar for the Pedersen base \\(B\\).
+	B_scalar: Scalar,
+	/// Scalar for the Pedersen blinding base \\(\tilde{B}\\).
+	B_blinding_scalar: Scalar,
+	/// Scalars for the \\(\mathbf{G}\\) generators.
+	g_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf{H}\\) generators.
+	h_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf{G}\\) generators of party 1, one for
+	/// each entry of the vector commitments.
+	vector_g_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf{H}\\) generators of party 1.
+	vector_h_scalars: Vec<Scalar>,
+}
 
-		if !mega_check.is_identity() {
-			return Err(R1CSError::VerificationError);
+/// The weights of the left or right inputs of the multipliers in the
+/// flattened constraints, with the multipliers which have one.
+///
+/// Circuits often weight each multiplier input in only a few
+/// constraints, so the verifier keeps the `support` of the weights to
+/// visit only the weighted inputs rather than all \\(n\\) of them.
+struct MultiplierWeights {
+	/// The weight of each multiplier, zero if it has none.
+	weights: Vec<Scalar>,
+	/// The multipliers with a weight, in the order they were first
+	/// weighted.
+	support: Vec<usize>,
+	/// Whether each multiplier is in `support`.
+	in_support: Vec<bool>,
+}
+
+impl MultiplierWeights {
+	fn new(n: usize) -> Self {
+		MultiplierWeights {
+			weights: vec![Scalar::zero(); n],
+			support: Vec::new(),
+			in_support: vec![false; n],
 		}
+	}
 
-		Ok(())
+	fn add(&mut self, i: usize, weight: Scalar) {
+		if !self.in_support[i] {
+			self.in_support[i] = true;
+			self.support.push(i);
+		}
+		self.weights[i] += weight;
 	}
 }
@@ -0,0 +1,169 @@
+//! Definition of the verifying key of a circuit, which verifies proofs
+//! without re-running the gadget code.
+
+use alloc::vec::Vec;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{Circuit, R1CSError, R1CSProof, Verifier};
+use crate::generators::{BulletproofGens, PedersenGens};
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A verifying key for the proofs of a fixed circuit.
+///
+/// A `CircuitVerifyingKey` is created once by
+/// [`Verifier::finalize`](::r1cs::Verifier::finalize) after running the
+/// gadget code, and holds the weights of the constraints of the circuit on
+/// each variable, in the canonical form of a [`Circuit`].  It can be stored,
+/// for instance on chain, and each proof is then verified with
+/// [`CircuitVerifyingKey::verify`] given only the commitments and the
+/// values of the public inputs.
+///
+/// The weights are combined with the challenge \\(z\\) of each proof, which
+/// depends on the proof, so the key cannot hold the flattened vectors
+/// themselves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitVerifyingKey {
+	circuit: Circuit,
+}
+
+impl CircuitVerifyingKey {
+	/// Creates the verifying key of `circuit`.
+	pub fn new(circuit: Circuit) -> Self {
+		CircuitVerifyingKey { circuit }
+	}
+
+	/// Returns the circuit of the key.
+	pub fn circuit(&self) -> &Circuit {
+		&self.circuit
+	}
+
+	/// Verifies the supplied `proof` of the circuit with the transcript of
+	/// the prover, the `commitments` to its high-level variables and the
+	/// values of its public inputs.
+	/// This is a convenience wrapper around
+	/// [`CircuitVerifyingKey::verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		&self,
+		transcript: &mut Transcript,
+		proof: &R1CSProof,
+		commitments: &[CompressedRistretto],
+		public_inputs: &[Scalar],
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError> {
+		self.verify_with_rng(
+			transcript,
+			proof,
+			commitments,
+			public_inputs,
+			pc_gens,
+			bp_gens,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies the supplied `proof` of the circuit with the transcript of
+	/// the prover, the `commitments` to its high-level variables and the
+	/// values of its public inputs.
+	///
+	/// This accepts the same proofs as building a [`Verifier`] with the
+	/// gadget code and calling [`Verifier::verify_with_rng`].  Besides its
+	/// errors, this returns [`R1CSError::WrongNumCommitments`] and
+	/// [`R1CSError::WrongNumPublicInputs`] if the numbers of commitments or
+	/// public inputs do not match the circuit.
+	#[allow(clippy::too_many_arguments)]
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		transcript: &mut Transcript,
+		proof: &R1CSProof,
+		commitments: &[CompressedRistretto],
+		public_inputs: &[Scalar],
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		if public_inputs.len() != self.circuit.public_inputs {
+			return Err(R1CSError::WrongNumPublicInputs {
+				expected: self.circuit.public_inputs,
+				actual: public_inputs.len(),
+			});
+		}
+
+		let mut verifier = Verifier::from_circuit(transcript, &self.circuit, commitments)?;
+		for x in public_inputs {
+			verifier.allocate_public(*x);
+		}
+		verifier.verify_with_rng(proof, pc_gens, bp_gens, prng)
+	}
+
+	/// Serializes the key into a byte array, which is the encoding of its
+	/// circuit.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.circuit.to_bytes()
+	}
+
+	/// Deserializes the key from a byte slice.
+	///
+	/// Returns an error if the byte slice is not the encoding of a
+	/// [`Circuit`].
+	pub fn from_bytes(slice: &[u8]) -> Result<CircuitVerifyingKey, R1CSError> {
+		Circuit::from_bytes(slice).map(CircuitVerifyingKey::new)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl Encode for CircuitVerifyingKey {
+	fn size_hint(&self) -> usize {
+		let len = self.circuit.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for CircuitVerifyingKey {}
+
+#[cfg(feature = "scale")]
+impl Decode for CircuitVerifyingKey {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		CircuitVerifyingKey::from_bytes(&bytes).map_err(|_| "Invalid CircuitVerifyingKey encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for CircuitVerifyingKey {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CircuitVerifyingKey {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid CircuitVerifyingKey")?;
+		CircuitVerifyingKey::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
@@ -0,0 +1,276 @@
+#![allow(non_snake_case)]
+
+//! Definition of the witness struct, a serializable assignment of the
+//! variables of a constraint system.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use curve25519_dalek::scalar::Scalar;
+
+use super::R1CSError;
+use crate::util;
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The assignment of the variables of a constraint system: the openings of
+/// the commitments to its high-level variables, the values of its public
+/// inputs, and the assignments of its multipliers.
+///
+/// A `Witness` is exported from a fully built
+/// [`Prover`](::r1cs::Prover) with
+/// [`Prover::witness`](::r1cs::Prover::witness), and a prover can be
+/// reconstructed from it and the [`Circuit`](::r1cs::Circuit)
+/// with [`Prover::from_witness`](::r1cs::Prover::from_witness), without
+/// re-running the gadget code.  This lets the witness be computed on one
+/// machine, such as the device of a user, and the proof be created on
+/// another.
+///
+/// The witness holds all the secrets of the proof, so it must only be sent
+/// to a trusted prover.  Its scalars are overwritten with zeros when it is
+/// dropped.
+///
+/// Like a [`Circuit`](::r1cs::Circuit), a witness cannot hold the
+/// assignments of the randomized phase or of vector-committed variables.
+#[derive(Clone, PartialEq)]
+pub struct Witness {
+	pub(super) v: Vec<Scalar>,
+	pub(super) v_blinding: Vec<Scalar>,
+	pub(super) public: Vec<Scalar>,
+	pub(super) a_L: Vec<Scalar>,
+	pub(super) a_R: Vec<Scalar>,
+	pub(super) a_O: Vec<Scalar>,
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for Witness {
+	fn drop(&mut self) {
+		util::clear_secrets(&mut self.v);
+		util::clear_secrets(&mut self.v_blinding);
+		util::clear_secrets(&mut self.a_L);
+		util::clear_secrets(&mut self.a_R);
+		util::clear_secrets(&mut self.a_O);
+	}
+}
+
+impl Witness {
+	/// Returns the values of the committed high-level variables.
+	pub fn values(&self) -> &[Scalar] {
+		&self.v
+	}
+
+	/// Returns the blinding factors of the commitments to the high-level
+	/// variables.
+	pub fn blindings(&self) -> &[Scalar] {
+		&self.v_blinding
+	}
+
+	/// Returns the values of the public inputs.
+	pub fn public_inputs(&self) -> &[Scalar] {
+		&self.public
+	}
+
+	/// Returns the number of multipliers.
+	pub fn multipliers(&self) -> usize {
+		self.a_L.len()
+	}
+
+	/// Returns a copy of the witness with the blinding factors of the
+	/// commitments set to zero, for a proving service that must not learn
+	/// them.
+	///
+	/// See the [`delegation`](::r1cs::delegation) module.
+	pub fn without_blindings(&self) -> Witness {
+		let mut witness = self.clone();
+		util::clear_secrets(&mut witness.v_blinding);
+		witness
+	}
+
+	/// Returns the size in bytes required to serialize the `Witness`.
+	pub fn serialized_size(&self) -> usize {
+		3 * 8 + 32 * (2 * self.v.len() + self.public.len() + 3 * self.a_L.len())
+	}
+
+	/// Serializes the witness into a byte array.
+	///
+	/// # Layout
+	///
+	/// The layout of the witness encoding is:
+	///
+	/// * the numbers of committed variables, public inputs and multipliers, as
+	///   64-bit little-endian integers,
+	/// * the values and then the blinding factors of the committed variables,
+	/// * the values of the public inputs,
+	/// * the left, right and output assignments of the multipliers,
+	///
+	/// with each scalar as 32 bytes.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(self.serialized_size());
+		buf.extend_from_slice(&(self.v.len() as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.public.len() as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.a_L.len() as u64).to_le_bytes());
+		let scalars = self
+			.v
+			.iter()
+			.chain(self.v_blinding.iter())
+			.chain(self.public.iter())
+			.chain(self.a_L.iter())
+			.chain(self.a_R.iter())
+			.chain(self.a_O.iter());
+		for x in scalars {
+			buf.extend_from_slice(x.as_bytes());
+		}
+		buf
+	}
+
+	/// Deserializes the witness from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `Witness`, or if a multiplier output is not the product of its
+	/// inputs.
+	pub fn from_bytes(slice: &[u8]) -> Result<Witness, R1CSError> {
+		if slice.len() < 3 * 8 {
+			return Err(R1CSError::FormatError);
+		}
+		let (counts, mut rest) = slice.split_at(3 * 8);
+		let count = |i: usize| -> Result<usize, R1CSError> {
+			let bytes = counts[8 * i..8 * (i + 1)]
+				.try_into()
+				.map_err(|_| R1CSError::FormatError)?;
+			let value = u64::from_le_bytes(bytes);
+			if value > (slice.len() / 32) as u64 {
+				return Err(R1CSError::FormatError);
+			}
+			Ok(value as usize)
+		};
+		let (m, p, n) = (count(0)?, count(1)?, count(2)?);
+		if rest.len() != 32 * (2 * m + p + 3 * n) {
+			return Err(R1CSError::FormatError);
+		}
+
+		let mut read_scalars = |len: usize| -> Result<Vec<Scalar>, R1CSError> {
+			let (head, tail) = rest.split_at(32 * len);
+			rest = tail;
+			head.chunks(32)
+				.map(|chunk| Scalar::from_canonical_bytes(util::read32(chunk)).ok_or(R1CSError::FormatError))
+				.collect()
+		};
+		let witness = Witness {
+			v: read_scalars(m)?,
+			v_blinding: read_scalars(m)?,
+			public: read_scalars(p)?,
+			a_L: read_scalars(n)?,
+			a_R: read_scalars(n)?,
+			a_O: read_scalars(n)?,
+		};
+
+		for i in 0..n {
+			if witness.a_L[i] * witness.a_R[i] != witness.a_O[i] {
+				return Err(R1CSError::FormatError);
+			}
+		}
+		Ok(witness)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl Encode for Witness {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for Witness {}
+
+#[cfg(feature = "scale")]
+impl Decode for Witness {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		Witness::from_bytes(&bytes).map_err(|_| "Invalid Witness encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Witness {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Witness {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid Witness")?;
+		Witness::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn example_witness() -> Witness {
+		let s = |x: u64| Scalar::from(x);
+		Witness {
+			v: vec![s(3), s(4)],
+			v_blinding: vec![s(10), s(11)],
+			public: vec![s(12)],
+			a_L: vec![s(3), s(2)],
+			a_R: vec![s(4), s(5)],
+			a_O: vec![s(12), s(10)],
+		}
+	}
+
+	#[test]
+	fn bytes_roundtrip() {
+		let witness = example_witness();
+		let bytes = witness.to_bytes();
+
+		assert_eq!(bytes.len(), witness.serialized_size());
+		assert!(Witness::from_bytes(&bytes) == Ok(witness));
+	}
+
+	#[test]
+	fn from_bytes_rejects_malformed_input() {
+		let bytes = example_witness().to_bytes();
+
+		// Truncated and trailing data.
+		assert!(Witness::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(Witness::from_bytes(&[&bytes[..], &[0u8][..]].concat()).is_err());
+
+		// A huge count.
+		let mut bad = bytes.clone();
+		bad[7] = 0xff;
+		assert!(Witness::from_bytes(&bad).is_err());
+
+		// A non-canonical scalar.
+		let mut bad = bytes.clone();
+		for byte in bad[3 * 8..4 * 8 + 24].iter_mut() {
+			*byte = 0xff;
+		}
+		assert!(Witness::from_bytes(&bad).is_err());
+
+		// A multiplier output that is not the product of its inputs.
+		let mut bad = bytes;
+		let last = bad.len() - 32;
+		bad[last] ^= 1;
+		assert!(Witness::from_bytes(&bad).is_err());
+	}
+}
@@ -11,47 +11,84 @@ use core::iter;
 
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec, vec::Vec};
 
 use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
 use merlin::Transcript;
 
 use crate::{
-	errors::MPCError,
+	errors::{MPCError, ShareError},
 	generators::{BulletproofGens, PedersenGens},
 	inner_product_proof,
 	range_proof::RangeProof,
-	transcript::TranscriptProtocol,
+	transcript::{ProofTranscript, TranscriptProtocol},
 };
 
 use rand_core::{CryptoRng, RngCore};
 
 use crate::util;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
 #[cfg(feature = "std")]
 use rand::thread_rng;
 
-use super::messages::*;
+use super::{
+	messages::{AuditTerms, *},
+	party::Party,
+};
 
 /// Used to construct a dealer for the aggregated rangeproof MPC protocol.
 pub struct Dealer {}
 
 impl Dealer {
 	/// Creates a new dealer coordinating `m` parties proving `n`-bit ranges.
-	pub fn new<'a, 'b>(
+	pub fn start<'a, 'b, Tr: ProofTranscript>(
 		bp_gens: &'b BulletproofGens,
 		pc_gens: &'b PedersenGens,
-		transcript: &'a mut Transcript,
+		transcript: &'a mut Tr,
 		n: usize,
 		m: usize,
-	) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
-		if !(n == 8 || n == 16 || n == 32 || n == 64) {
+	) -> Result<DealerAwaitingBitCommitments<'a, 'b, Tr>, MPCError> {
+		if !super::is_valid_bitsize(n) {
+			return Err(MPCError::InvalidBitsize);
+		}
+		if !m.is_power_of_two() {
+			return Err(MPCError::InvalidAggregation);
+		}
+		if bp_gens.gens_capacity < n.next_power_of_two() {
+			return Err(MPCError::InvalidGeneratorsLength);
+		}
+		if bp_gens.party_capacity < m {
+			return Err(MPCError::InvalidGeneratorsLength);
+		}
+
+		Dealer::new_with_bitsizes(bp_gens, pc_gens, transcript, &vec![n; m])
+	}
+
+	/// Creates a new dealer coordinating `bitsizes.len()` parties, where
+	/// party `j` proves a `bitsizes[j]`-bit range.
+	///
+	/// Every value is padded to the largest bitsize rounded up to a power
+	/// of two, which the parties must be constructed with using
+	/// [`Party::new_with_padding`](super::party::Party::new_with_padding).
+	/// If all the bitsizes are equal, this is the same as [`Dealer::start`].
+	pub fn new_with_bitsizes<'a, 'b, Tr: ProofTranscript>(
+		bp_gens: &'b BulletproofGens,
+		pc_gens: &'b PedersenGens,
+		transcript: &'a mut Tr,
+		bitsizes: &[usize],
+	) -> Result<DealerAwaitingBitCommitments<'a, 'b, Tr>, MPCError> {
+		if !bitsizes.iter().all(|&n_j| super::is_valid_bitsize(n_j)) {
 			return Err(MPCError::InvalidBitsize);
 		}
+		let m = bitsizes.len();
 		if !m.is_power_of_two() {
 			return Err(MPCError::InvalidAggregation);
 		}
-		if bp_gens.gens_capacity < n {
+		let n = bitsizes.iter().cloned().max().unwrap_or(0);
+		if bp_gens.gens_capacity < n.next_power_of_two() {
 			return Err(MPCError::InvalidGeneratorsLength);
 		}
 		if bp_gens.party_capacity < m {
@@ -72,7 +109,7 @@ impl Dealer {
 		// state.
 		let initial_transcript = transcript.clone();
 
-		transcript.rangeproof_domain_sep(n as u64, m as u64);
+		super::bitsizes_domain_sep(transcript, bitsizes);
 
 		Ok(DealerAwaitingBitCommitments {
 			bp_gens,
@@ -81,59 +118,91 @@ impl Dealer {
 			initial_transcript,
 			n,
 			m,
+			bitsizes: bitsizes.to_vec(),
 		})
 	}
 }
 
 /// A dealer waiting for the parties to send their [`BitCommitment`]s.
-pub struct DealerAwaitingBitCommitments<'a, 'b> {
+pub struct DealerAwaitingBitCommitments<'a, 'b, Tr = Transcript> {
 	bp_gens: &'b BulletproofGens,
 	pc_gens: &'b PedersenGens,
-	transcript: &'a mut Transcript,
+	transcript: &'a mut Tr,
 	/// The dealer keeps a copy of the initial transcript state, so
 	/// that it can attempt to verify the aggregated proof at the end.
-	initial_transcript: Transcript,
+	initial_transcript: Tr,
+	/// The largest bitsize of the parties' ranges.
 	n: usize,
 	m: usize,
+	bitsizes: Vec<usize>,
 }
 
-impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
+impl<'a, 'b, Tr: ProofTranscript> DealerAwaitingBitCommitments<'a, 'b, Tr> {
 	/// Receive each party's [`BitCommitment`]s and compute the
 	/// [`BitChallenge`].
 	pub fn receive_bit_commitments(
 		self,
 		bit_commitments: Vec<BitCommitment>,
-	) -> Result<(DealerAwaitingPolyCommitments<'a, 'b>, BitChallenge), MPCError> {
+	) -> Result<(DealerAwaitingPolyCommitments<'a, 'b, Tr>, BitChallenge), MPCError> {
 		if self.m != bit_commitments.len() {
-			return Err(MPCError::WrongNumBitCommitments);
+			return Err(MPCError::WrongNumBitCommitments {
+				expected: self.m,
+				actual: bit_commitments.len(),
+			});
 		}
+		self.receive_bit_commitments_with_dropouts(bit_commitments.into_iter().map(Some).collect())
+	}
 
-		// Commit each V_j individually
-		for vc in bit_commitments.iter() {
-			self.transcript.append_point(b"V", &vc.V_j);
+	/// Receive the [`BitCommitment`]s of the parties that responded, with
+	/// `None` in the positions of the parties that did not, and compute
+	/// the [`BitChallenge`].
+	///
+	/// The dealer drops the parties that did not respond, and plays in
+	/// their place a party proving the range of a zero value without any
+	/// randomness, like the values padding an aggregation.  The proof
+	/// then holds for the identity point in place of the commitments of
+	/// the dropped parties, which the verifier must be given instead; see
+	/// [`DealerAwaitingPolyCommitments::dropped_parties`].
+	pub fn receive_bit_commitments_with_dropouts(
+		self,
+		bit_commitments: Vec<Option<BitCommitment>>,
+	) -> Result<(DealerAwaitingPolyCommitments<'a, 'b, Tr>, BitChallenge), MPCError> {
+		let received = bit_commitments.iter().filter(|c| c.is_some()).count();
+		if self.m != bit_commitments.len() || received == 0 {
+			return Err(MPCError::WrongNumBitCommitments {
+				expected: self.m,
+				actual: received,
+			});
 		}
 
-		// Commit aggregated A_j, S_j
-		let A: RistrettoPoint = bit_commitments.iter().map(|vc| vc.A_j).sum();
-		self.transcript.append_point(b"A", &A.compress());
-
-		let S: RistrettoPoint = bit_commitments.iter().map(|vc| vc.S_j).sum();
-		self.transcript.append_point(b"S", &S.compress());
+		let padded_n = self.n.next_power_of_two();
+		let mut dropped = Vec::new();
+		let mut commitments = Vec::with_capacity(self.m);
+		for (j, commitment) in bit_commitments.into_iter().enumerate() {
+			match commitment {
+				Some(commitment) => commitments.push(commitment),
+				None => {
+					let (_, commitment) = Party::padding(self.bp_gens, self.pc_gens, j, self.bitsizes[j], padded_n)?;
+					commitments.push(commitment);
+					dropped.push(j);
+				}
+			}
+		}
 
-		let y = self.transcript.challenge_scalar(b"y");
-		let z = self.transcript.challenge_scalar(b"z");
-		let bit_challenge = BitChallenge { y, z };
+		let (A, S, bit_challenge) = challenge_bit_commitments(self.transcript, &commitments);
 
 		Ok((
 			DealerAwaitingPolyCommitments {
 				n: self.n,
 				m: self.m,
+				bitsizes: self.bitsizes,
+				dropped,
 				transcript: self.transcript,
 				initial_transcript: self.initial_transcript,
 				bp_gens: self.bp_gens,
 				pc_gens: self.pc_gens,
 				bit_challenge,
-				bit_commitments,
+				bit_commitments: commitments,
 				A,
 				S,
 			},
@@ -142,13 +211,52 @@ impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
 	}
 }
 
+/// Appends the parties' bit commitments to the transcript, and returns
+/// their sums \(A, S\) and the [`BitChallenge`].
+fn challenge_bit_commitments(
+	transcript: &mut impl ProofTranscript,
+	bit_commitments: &[BitCommitment],
+) -> (RistrettoPoint, RistrettoPoint, BitChallenge) {
+	// Commit each V_j individually
+	for vc in bit_commitments.iter() {
+		transcript.append_point(b"V", &vc.V_j);
+	}
+
+	// Commit aggregated A_j, S_j
+	let A: RistrettoPoint = bit_commitments.iter().map(|vc| vc.A_j).sum();
+	transcript.append_point(b"A", &A.compress());
+
+	let S: RistrettoPoint = bit_commitments.iter().map(|vc| vc.S_j).sum();
+	transcript.append_point(b"S", &S.compress());
+
+	let y = transcript.challenge_scalar(b"y");
+	let z = transcript.challenge_scalar(b"z");
+	(A, S, BitChallenge { y, z })
+}
+
+/// The outcome of the round of [`PolyCommitment`]s when the dealer can
+/// drop parties that did not respond.
+pub enum PolyCommitmentRound<'a, 'b, Tr = Transcript> {
+	/// Every remaining party responded, and the [`PolyChallenge`] should
+	/// be sent to them.
+	Complete(Box<DealerAwaitingProofShares<'a, 'b, Tr>>, PolyChallenge),
+	/// Some parties did not respond and were dropped.  The new
+	/// [`BitChallenge`] should be sent to the remaining parties, which
+	/// answer it with
+	/// [`PartyAwaitingPolyChallenge::restart`](super::party::PartyAwaitingPolyChallenge::restart).
+	Restarted(Box<DealerAwaitingPolyCommitments<'a, 'b, Tr>>, BitChallenge),
+}
+
 /// A dealer which has sent the [`BitChallenge`] to the parties and
 /// is waiting for their [`PolyCommitment`]s.
-pub struct DealerAwaitingPolyCommitments<'a, 'b> {
+pub struct DealerAwaitingPolyCommitments<'a, 'b, Tr = Transcript> {
 	n: usize,
 	m: usize,
-	transcript: &'a mut Transcript,
-	initial_transcript: Transcript,
+	bitsizes: Vec<usize>,
+	/// The positions of the parties dropped so far, in increasing order.
+	dropped: Vec<usize>,
+	transcript: &'a mut Tr,
+	initial_transcript: Tr,
 	bp_gens: &'b BulletproofGens,
 	pc_gens: &'b PedersenGens,
 	bit_challenge: BitChallenge,
@@ -159,17 +267,111 @@ pub struct DealerAwaitingPolyCommitments<'a, 'b> {
 	S: RistrettoPoint,
 }
 
-impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
+impl<'a, 'b, Tr: ProofTranscript> DealerAwaitingPolyCommitments<'a, 'b, Tr> {
+	/// Returns the positions of the parties the dealer has dropped, in
+	/// increasing order.  The verifier must be given the identity point
+	/// in place of their value commitments.
+	pub fn dropped_parties(&self) -> &[usize] {
+		&self.dropped
+	}
+
 	/// Receive [`PolyCommitment`]s from the parties and compute the
 	/// [`PolyChallenge`].
+	///
+	/// If the dealer has dropped parties, `poly_commitments` holds the
+	/// commitments of the remaining parties, in order of position.
 	pub fn receive_poly_commitments(
 		self,
 		poly_commitments: Vec<PolyCommitment>,
-	) -> Result<(DealerAwaitingProofShares<'a, 'b>, PolyChallenge), MPCError> {
-		if self.m != poly_commitments.len() {
-			return Err(MPCError::WrongNumPolyCommitments);
+	) -> Result<(DealerAwaitingProofShares<'a, 'b, Tr>, PolyChallenge), MPCError> {
+		let expected = self.m - self.dropped.len();
+		if expected != poly_commitments.len() {
+			return Err(MPCError::WrongNumPolyCommitments {
+				expected,
+				actual: poly_commitments.len(),
+			});
+		}
+
+		let mut poly_commitments = poly_commitments.into_iter();
+		let poly_commitments = (0..self.m)
+			.map(|j| {
+				if self.dropped.contains(&j) {
+					None
+				} else {
+					poly_commitments.next()
+				}
+			})
+			.collect();
+		match self.receive_poly_commitments_with_dropouts(poly_commitments)? {
+			PolyCommitmentRound::Complete(dealer, poly_challenge) => Ok((*dealer, poly_challenge)),
+			PolyCommitmentRound::Restarted(..) => unreachable!("no party was dropped in this round"),
+		}
+	}
+
+	/// Receive the [`PolyCommitment`]s of the parties that responded,
+	/// with `None` in the positions of the parties that did not.
+	///
+	/// The entries for parties dropped in an earlier round are ignored.
+	/// If every other party responded, this computes the
+	/// [`PolyChallenge`].  Otherwise, the dealer drops the parties that
+	/// did not respond, as in
+	/// [`DealerAwaitingBitCommitments::receive_bit_commitments_with_dropouts`],
+	/// and restarts the round with a new [`BitChallenge`] for the
+	/// remaining parties, which keep their bit commitments.
+	pub fn receive_poly_commitments_with_dropouts(
+		mut self,
+		poly_commitments: Vec<Option<PolyCommitment>>,
+	) -> Result<PolyCommitmentRound<'a, 'b, Tr>, MPCError> {
+		let missing: Vec<usize> = poly_commitments
+			.iter()
+			.enumerate()
+			.filter(|(j, c)| c.is_none() && !self.dropped.contains(j))
+			.map(|(j, _)| j)
+			.collect();
+		let expected = self.m - self.dropped.len();
+		if self.m != poly_commitments.len() || missing.len() == expected {
+			return Err(MPCError::WrongNumPolyCommitments {
+				expected,
+				actual: expected - missing.len().min(expected),
+			});
+		}
+		let padded_n = self.n.next_power_of_two();
+
+		if !missing.is_empty() {
+			for &j in missing.iter() {
+				let (_, commitment) = Party::padding(self.bp_gens, self.pc_gens, j, self.bitsizes[j], padded_n)?;
+				self.bit_commitments[j] = commitment;
+			}
+			self.dropped.extend(missing);
+			self.dropped.sort_unstable();
+
+			// Replay the protocol from the start without the dropped parties.
+			*self.transcript = self.initial_transcript.clone();
+			super::bitsizes_domain_sep(self.transcript, &self.bitsizes);
+			let (A, S, bit_challenge) = challenge_bit_commitments(self.transcript, &self.bit_commitments);
+			self.A = A;
+			self.S = S;
+			self.bit_challenge = bit_challenge;
+			return Ok(PolyCommitmentRound::Restarted(Box::new(self), bit_challenge));
 		}
 
+		// The dealer computes the messages of the parties it plays.
+		let mut padding = Vec::with_capacity(self.dropped.len());
+		let poly_commitments: Vec<PolyCommitment> = poly_commitments
+			.into_iter()
+			.enumerate()
+			.map(|(j, commitment)| {
+				if !self.dropped.contains(&j) {
+					return commitment.ok_or(MPCError::UnexpectedMessage);
+				}
+				let (party, _) = Party::padding(self.bp_gens, self.pc_gens, j, self.bitsizes[j], padded_n)?;
+				let (party, commitment) =
+					party.apply_challenge_with_blindings(&self.bit_challenge, Scalar::zero(), Scalar::zero());
+				padding.push(party);
+				Ok(commitment)
+			})
+			.collect::<Result<_, _>>()?;
+
 		// Commit sums of T_1_j's and T_2_j's
 		let T_1: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_1_j).sum();
 		let T_2: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_2_j).sum();
@@ -180,10 +382,18 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
 		let x = self.transcript.challenge_scalar(b"x");
 		let poly_challenge = PolyChallenge { x };
 
-		Ok((
-			DealerAwaitingProofShares {
+		let padding_shares = padding
+			.into_iter()
+			.map(|party| party.apply_challenge(&poly_challenge))
+			.collect::<Result<_, _>>()?;
+
+		Ok(PolyCommitmentRound::Complete(
+			Box::new(DealerAwaitingProofShares {
 				n: self.n,
 				m: self.m,
+				bitsizes: self.bitsizes,
+				dropped: self.dropped,
+				padding_shares,
 				transcript: self.transcript,
 				initial_transcript: self.initial_transcript,
 				bp_gens: self.bp_gens,
@@ -196,7 +406,7 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
 				poly_commitments,
 				T_1,
 				T_2,
-			},
+			}),
 			poly_challenge,
 		))
 	}
@@ -205,11 +415,16 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
 /// A dealer which has sent the [`PolyChallenge`] to the parties and
 /// is waiting to aggregate their [`ProofShare`]s into a
 /// [`RangeProof`].
-pub struct DealerAwaitingProofShares<'a, 'b> {
+pub struct DealerAwaitingProofShares<'a, 'b, Tr = Transcript> {
 	n: usize,
 	m: usize,
-	transcript: &'a mut Transcript,
-	initial_transcript: Transcript,
+	bitsizes: Vec<usize>,
+	dropped: Vec<usize>,
+	/// The proof shares of the parties the dealer plays in place of the
+	/// dropped parties, in the same order.
+	padding_shares: Vec<ProofShare>,
+	transcript: &'a mut Tr,
+	initial_transcript: Tr,
 	bp_gens: &'b BulletproofGens,
 	pc_gens: &'b PedersenGens,
 	bit_challenge: BitChallenge,
@@ -222,23 +437,58 @@ pub struct DealerAwaitingProofShares<'a, 'b> {
 	T_2: RistrettoPoint,
 }
 
-impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
+impl<'a, 'b, Tr: ProofTranscript> DealerAwaitingProofShares<'a, 'b, Tr> {
+	/// Returns the positions of the parties the dealer has dropped, in
+	/// increasing order.  The verifier must be given the identity point
+	/// in place of their value commitments.
+	pub fn dropped_parties(&self) -> &[usize] {
+		&self.dropped
+	}
+
+	/// Returns the proof shares of all positions, given the `proof_shares`
+	/// of the remaining parties in order of position.
+	fn all_shares(&self, proof_shares: &[ProofShare]) -> Result<Vec<ProofShare>, MPCError> {
+		let expected = self.m - self.dropped.len();
+		if expected != proof_shares.len() {
+			return Err(MPCError::WrongNumProofShares {
+				expected,
+				actual: proof_shares.len(),
+			});
+		}
+		if self.dropped.is_empty() {
+			return Ok(proof_shares.to_vec());
+		}
+
+		let mut proof_shares = proof_shares.iter();
+		let mut padding_shares = self.padding_shares.iter();
+		Ok((0..self.m)
+			.filter_map(|j| {
+				if self.dropped.contains(&j) {
+					padding_shares.next()
+				} else {
+					proof_shares.next()
+				}
+			})
+			.cloned()
+			.collect())
+	}
+
 	/// Assembles proof shares into an `RangeProof`.
 	///
 	/// Used as a helper function by `receive_trusted_shares` (which
 	/// just hands back the result) and `receive_shares` (which
-	/// validates the proof shares.
+	/// validates the proof shares.  The `proof_shares` include those of
+	/// the dropped parties.
 	fn assemble_shares(&mut self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
-		if self.m != proof_shares.len() {
-			return Err(MPCError::WrongNumProofShares);
-		}
+		// The proof is over the bitsize padded to a power of two.
+		let n = self.n.next_power_of_two();
 
 		// Validate lengths for each share
-		let mut bad_shares = Vec::<usize>::new(); // no allocations until we append
+		let mut bad_shares = Vec::new(); // no allocations until we append
 		for (j, share) in proof_shares.iter().enumerate() {
-			share.check_size(self.n, &self.bp_gens, j).unwrap_or_else(|_| {
-				bad_shares.push(j);
-			});
+			if let Err(e) = share.check_size(n, &self.bp_gens, j) {
+				bad_shares.push((j, e));
+			}
 		}
 
 		if bad_shares.len() > 0 {
@@ -257,10 +507,8 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 		let w = self.transcript.challenge_scalar(b"w");
 		let Q = w * self.pc_gens.B;
 
-		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(self.n * self.m).collect();
-		let H_factors: Vec<Scalar> = util::exp_iter(self.bit_challenge.y.invert())
-			.take(self.n * self.m)
-			.collect();
+		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(n * self.m).collect();
+		let H_factors: Vec<Scalar> = util::exp_iter(self.bit_challenge.y.invert()).take(n * self.m).collect();
 
 		let l_vec: Vec<Scalar> = proof_shares
 			.iter()
@@ -276,8 +524,8 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 			&Q,
 			&G_factors,
 			&H_factors,
-			self.bp_gens.G(self.n, self.m).cloned().collect(),
-			self.bp_gens.H(self.n, self.m).cloned().collect(),
+			self.bp_gens.G(n, self.m).cloned().collect(),
+			self.bp_gens.H(n, self.m).cloned().collect(),
 			l_vec,
 			r_vec,
 		);
@@ -294,6 +542,120 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 		})
 	}
 
+	/// Returns the parties whose `proof_shares` are malformed, in
+	/// increasing order, each with the first check its share fails.
+	///
+	/// Auditing each of \(m\) shares on its own costs \(m\)
+	/// multiscalar multiplications.  Instead, the checks of all shares are
+	/// combined with random weights into a single multiscalar
+	/// multiplication, and the parties are split in halves which are
+	/// checked in turn while their combined checks fail, so that finding
+	/// \(k\) malformed shares takes \(O(k \lg m)\) of them.  Only the
+	/// malformed shares are then audited on their own, to find the check
+	/// they fail.
+	fn audit_shares<T: RngCore + CryptoRng>(
+		&self,
+		proof_shares: &[ProofShare],
+		rng: &mut T,
+	) -> Vec<(usize, ShareError)> {
+		let weights: Vec<(Scalar, Scalar)> = (0..self.m)
+			.map(|_| (Scalar::random(rng), Scalar::random(rng)))
+			.collect();
+		// The closure borrows the fields it needs rather than `self`,
+		// whose transcript need not be shared between threads.
+		let (bp_gens, bitsizes, bit_commitments, poly_commitments) = (
+			self.bp_gens,
+			&self.bitsizes,
+			&self.bit_commitments,
+			&self.poly_commitments,
+		);
+		let (bit_challenge, poly_challenge) = (&self.bit_challenge, &self.poly_challenge);
+		let audit_terms = |j: usize| {
+			proof_shares[j].audit_terms(
+				bp_gens,
+				j,
+				bitsizes[j],
+				&bit_commitments[j],
+				bit_challenge,
+				&poly_commitments[j],
+				poly_challenge,
+				weights[j],
+			)
+		};
+		#[cfg(not(feature = "rayon"))]
+		let terms: Vec<_> = (0..self.m).map(audit_terms).collect();
+		#[cfg(feature = "rayon")]
+		let terms: Vec<_> = (0..self.m).into_par_iter().map(audit_terms).collect();
+
+		// The shares failing the checks which are not batched are
+		// malformed, and the others are searched.
+		let mut malformed = Vec::new();
+		let mut candidates = Vec::with_capacity(self.m);
+		for (j, terms) in terms.iter().enumerate() {
+			match terms {
+				Ok(terms) => candidates.push((j, terms)),
+				Err(_) => malformed.push(j),
+			}
+		}
+
+		let mut groups = vec![&candidates[..]];
+		while let Some(group) = groups.pop() {
+			if group.is_empty() || self.check_audit_terms(group) {
+				continue;
+			}
+			if group.len() == 1 {
+				malformed.push(group[0].0);
+				continue;
+			}
+			let (lo, hi) = group.split_at(group.len() / 2);
+			groups.push(hi);
+			groups.push(lo);
+		}
+		malformed.sort_unstable();
+
+		malformed
+			.into_iter()
+			.filter_map(|j| {
+				proof_shares[j]
+					.audit(
+						&self.bp_gens,
+						&self.pc_gens,
+						j,
+						self.bitsizes[j],
+						&self.bit_commitments[j],
+						&self.bit_challenge,
+						&self.poly_commitments[j],
+						&self.poly_challenge,
+					)
+					.err()
+					.map(|e| (j, e))
+			})
+			.collect()
+	}
+
+	/// Returns whether the combined checks of a `group` of shares hold.
+	fn check_audit_terms(&self, group: &[(usize, &AuditTerms)]) -> bool {
+		use curve25519_dalek::traits::IsIdentity;
+
+		let B_scalar: Scalar = group.iter().map(|(_, terms)| terms.B_scalar).sum();
+		let B_blinding_scalar: Scalar = group.iter().map(|(_, terms)| terms.B_blinding_scalar).sum();
+		let scalars: Vec<Scalar> = group
+			.iter()
+			.flat_map(|(_, terms)| terms.scalars.iter())
+			.chain(iter::once(&B_scalar))
+			.chain(iter::once(&B_blinding_scalar))
+			.cloned()
+			.collect();
+		let points: Vec<RistrettoPoint> = group
+			.iter()
+			.flat_map(|(_, terms)| terms.points.iter())
+			.chain(iter::once(&self.pc_gens.B))
+			.chain(iter::once(&self.pc_gens.B_blinding))
+			.cloned()
+			.collect();
+		util::vartime_multiscalar_mul(scalars, points).is_identity()
+	}
+
 	/// Assemble the final aggregated [`RangeProof`] from the given
 	/// `proof_shares`, then validate the proof to ensure that all
 	/// `ProofShare`s were well-formed.
@@ -323,34 +685,22 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 		proof_shares: &[ProofShare],
 		rng: &mut T,
 	) -> Result<RangeProof, MPCError> {
-		let proof = self.assemble_shares(proof_shares)?;
+		let proof_shares = self.all_shares(proof_shares)?;
+		let proof = self.assemble_shares(&proof_shares)?;
 
 		let Vs: Vec<_> = self.bit_commitments.iter().map(|vc| vc.V_j).collect();
 
-		// See comment in `Dealer::new` for why we use `initial_transcript`
+		// See comment in `Dealer::new_with_bitsizes` for why we use
+		// `initial_transcript`
 		let transcript = &mut self.initial_transcript;
 		if proof
-			.verify_multiple_with_rng(self.bp_gens, self.pc_gens, transcript, &Vs, self.n, rng)
+			.verify_multiple_with_bitsizes_with_rng(self.bp_gens, self.pc_gens, transcript, &Vs, &self.bitsizes, rng)
 			.is_ok()
 		{
 			Ok(proof)
 		} else {
 			// Proof verification failed. Now audit the parties:
-			let mut bad_shares = Vec::new();
-			for j in 0..self.m {
-				match proof_shares[j].audit_share(
-					&self.bp_gens,
-					&self.pc_gens,
-					j,
-					&self.bit_commitments[j],
-					&self.bit_challenge,
-					&self.poly_commitments[j],
-					&self.poly_challenge,
-				) {
-					Ok(_) => {}
-					Err(_) => bad_shares.push(j),
-				}
-			}
+			let bad_shares = self.audit_shares(&proof_shares, rng);
 			Err(MPCError::MalformedProofShares { bad_shares })
 		}
 	}
@@ -370,6 +720,7 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 	/// which validates that all shares are well-formed, or else
 	/// detects which party(ies) submitted malformed shares.
 	pub fn receive_trusted_shares(mut self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
-		self.assemble_shares(proof_shares)
+		let proof_shares = self.all_shares(proof_shares)?;
+		self.assemble_shares(&proof_shares)
 	}
 }
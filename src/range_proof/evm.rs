@@ -0,0 +1,426 @@
+//! Range proofs that a verifier on an EVM chain can check.
+//!
+//! With the `evm-compat` feature, the proofs of this module use an
+//! [`EvmTranscript`], whose Fiat–Shamir transform is built from Keccak-256
+//! and the `abi.encodePacked` encoding, and are encoded with
+//! [`RangeProof::to_evm_bytes`] as the `abi.encode` of their elements, so
+//! that a Solidity contract can decode them with `abi.decode` and replay
+//! the transcript with the `keccak256` builtin.  The group operations of the
+//! verification equation are left to a Ristretto precompile of the chain.
+//!
+//! The reference Solidity verifier and a test vector are in
+//! `tests/fixtures/evm`.
+
+#![allow(non_snake_case)]
+
+use alloc::vec::Vec;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use rand_core::{CryptoRng, RngCore};
+use sha3::{Digest, Keccak256};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::RangeProof;
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::InnerProductProof,
+	transcript::ProofTranscript,
+};
+
+/// The number of 32-byte words of the head of the ABI encoding of a range
+/// proof: seven static elements, the offsets of the \\(L\\) and \\(R\\)
+/// arrays, and the scalars \\(a, b\\).
+const HEAD_WORDS: usize = 11;
+
+/// A [`ProofTranscript`] that a Solidity contract can replay.
+///
+/// The transcript is a 32-byte state, initially zero, which each operation
+/// replaces with a Keccak-256 hash:
+///
+/// * appending a `message` with a `label` sets the state to
+///   `keccak256(abi.encodePacked(state, uint64(label.length), label,
+///   uint64(message.length), message))`, and a `u64` is appended as the 8-byte
+///   big-endian `abi.encodePacked(uint64(x))`,
+/// * a challenge of `len` bytes sets the state to `seed =
+///   keccak256(abi.encodePacked(state, uint64(label.length), label,
+///   uint64(len)))`, and is the little-endian encoding of the integer \\(\sum_i
+///   \mathrm{block}_i \cdot 2^{256 i}\\), truncated to `len` bytes, with
+///   `block_i = uint256(keccak256(abi.encodePacked(seed, uint64(i))))`.
+///
+/// A 64-byte challenge scalar is thus \\(\mathrm{block}_0 + 2^{256}
+/// \mathrm{block}_1 \bmod \ell\\).  The transcript is created by appending
+/// its label with the label `dom-sep`.  Scalars are appended in their
+/// little-endian encoding, as by any transcript.
+#[derive(Clone, Debug)]
+pub struct EvmTranscript {
+	state: [u8; 32],
+}
+
+impl EvmTranscript {
+	/// Creates a transcript with the domain separator `label`.
+	pub fn new(label: &'static [u8]) -> Self {
+		let mut transcript = EvmTranscript { state: [0u8; 32] };
+		transcript.append_message(b"dom-sep", label);
+		transcript
+	}
+
+	/// Returns the current state of the transcript, the `bytes32` a
+	/// Solidity contract replaying it would hold.
+	pub fn state(&self) -> [u8; 32] {
+		self.state
+	}
+}
+
+impl ProofTranscript for EvmTranscript {
+	fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+		let mut hasher = Keccak256::new();
+		hasher.update(&self.state);
+		hasher.update(&(label.len() as u64).to_be_bytes());
+		hasher.update(label);
+		hasher.update(&(message.len() as u64).to_be_bytes());
+		hasher.update(message);
+		self.state = hasher.finalize().into();
+	}
+
+	fn append_u64(&mut self, label: &'static [u8], x: u64) {
+		self.append_message(label, &x.to_be_bytes());
+	}
+
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+		let mut hasher = Keccak256::new();
+		hasher.update(&self.state);
+		hasher.update(&(label.len() as u64).to_be_bytes());
+		hasher.update(label);
+		hasher.update(&(dest.len() as u64).to_be_bytes());
+		self.state = hasher.finalize().into();
+
+		for (i, chunk) in dest.chunks_mut(32).enumerate() {
+			let mut hasher = Keccak256::new();
+			hasher.update(&self.state);
+			hasher.update(&(i as u64).to_be_bytes());
+			let mut block: [u8; 32] = hasher.finalize().into();
+			block.reverse();
+			chunk.copy_from_slice(&block[..chunk.len()]);
+		}
+	}
+}
+
+impl RangeProof {
+	/// Create an aggregated rangeproof for a set of values, in the encoding
+	/// of [`RangeProof::to_evm_bytes`].
+	///
+	/// This is [`RangeProof::prove_multiple_with_rng`] with an
+	/// [`EvmTranscript`], and is verified by
+	/// [`RangeProof::verify_multiple_evm_with_rng`] or by the reference
+	/// Solidity verifier.
+	pub fn prove_multiple_evm_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut EvmTranscript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(Vec<u8>, Vec<CompressedRistretto>), ProofError> {
+		let (proof, value_commitments) =
+			RangeProof::prove_multiple_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, rng)?;
+		Ok((proof.to_evm_bytes(), value_commitments))
+	}
+
+	/// Create an aggregated rangeproof for a set of values, in the encoding
+	/// of [`RangeProof::to_evm_bytes`].
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_multiple_evm_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple_evm(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut EvmTranscript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+	) -> Result<(Vec<u8>, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_evm_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, &mut thread_rng())
+	}
+
+	/// Verifies an aggregated rangeproof in the encoding of
+	/// [`RangeProof::to_evm_bytes`], created by
+	/// [`RangeProof::prove_multiple_evm_with_rng`].
+	///
+	/// Returns [`ProofError::FormatError`] if the proof is not a valid
+	/// encoding, and the errors of [`RangeProof::verify_multiple_with_rng`].
+	pub fn verify_multiple_evm_with_rng<T: RngCore + CryptoRng>(
+		proof: &[u8],
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut EvmTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		RangeProof::from_evm_bytes(proof)?.verify_multiple_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			value_commitments,
+			n,
+			rng,
+		)
+	}
+
+	/// Verifies an aggregated rangeproof in the encoding of
+	/// [`RangeProof::to_evm_bytes`].
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_evm_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_multiple_evm(
+		proof: &[u8],
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut EvmTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+	) -> Result<(), ProofError> {
+		RangeProof::verify_multiple_evm_with_rng(
+			proof,
+			bp_gens,
+			pc_gens,
+			transcript,
+			value_commitments,
+			n,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Serializes the proof as the Solidity `abi.encode` of its elements.
+	///
+	/// # Layout
+	///
+	/// The encoding decodes in Solidity with
+	///
+	/// ```text
+	/// (bytes32 A, bytes32 S, bytes32 T_1, bytes32 T_2,
+	///  uint256 t_x, uint256 t_x_blinding, uint256 e_blinding,
+	///  bytes32[] L, bytes32[] R, uint256 a, uint256 b)
+	///     = abi.decode(proof, (bytes32, bytes32, bytes32, bytes32,
+	///         uint256, uint256, uint256, bytes32[], bytes32[], uint256, uint256));
+	/// ```
+	///
+	/// The points are compressed Ristretto points, as in
+	/// [`RangeProof::to_bytes`], and the scalars are big-endian integers.
+	pub fn to_evm_bytes(&self) -> Vec<u8> {
+		let k = self.ipp_proof.L_vec.len();
+		let mut buf = Vec::with_capacity(32 * (HEAD_WORDS + 2 * (k + 1)));
+		buf.extend_from_slice(self.A.as_bytes());
+		buf.extend_from_slice(self.S.as_bytes());
+		buf.extend_from_slice(self.T_1.as_bytes());
+		buf.extend_from_slice(self.T_2.as_bytes());
+		for x in [self.t_x, self.t_x_blinding, self.e_blinding].iter() {
+			buf.extend_from_slice(&scalar_word(x));
+		}
+		buf.extend_from_slice(&u64_word(32 * HEAD_WORDS as u64));
+		buf.extend_from_slice(&u64_word(32 * (HEAD_WORDS + k + 1) as u64));
+		buf.extend_from_slice(&scalar_word(&self.ipp_proof.a));
+		buf.extend_from_slice(&scalar_word(&self.ipp_proof.b));
+		for points in [&self.ipp_proof.L_vec, &self.ipp_proof.R_vec].iter() {
+			buf.extend_from_slice(&u64_word(k as u64));
+			for P in points.iter() {
+				buf.extend_from_slice(P.as_bytes());
+			}
+		}
+		buf
+	}
+
+	/// Deserializes the proof from its encoding by
+	/// [`RangeProof::to_evm_bytes`].
+	///
+	/// Returns an error if the byte slice is not the canonical encoding of
+	/// a `RangeProof`, with offsets and lengths as written by
+	/// [`RangeProof::to_evm_bytes`] and scalars below the group order.
+	pub fn from_evm_bytes(slice: &[u8]) -> Result<RangeProof, ProofError> {
+		if slice.len() % 32 != 0 || slice.len() < 32 * (HEAD_WORDS + 2) {
+			return Err(ProofError::FormatError);
+		}
+		let words: Vec<[u8; 32]> = slice.chunks(32).map(crate::util::read32).collect();
+		let k = (words.len() - HEAD_WORDS - 2) / 2;
+		if words.len() != HEAD_WORDS + 2 * (k + 1) {
+			return Err(ProofError::FormatError);
+		}
+		let expected = [
+			(7, 32 * HEAD_WORDS),
+			(8, 32 * (HEAD_WORDS + k + 1)),
+			(HEAD_WORDS, k),
+			(HEAD_WORDS + k + 1, k),
+		];
+		for &(i, value) in expected.iter() {
+			if words[i] != u64_word(value as u64) {
+				return Err(ProofError::FormatError);
+			}
+		}
+
+		let scalar = |i: usize| -> Result<Scalar, ProofError> {
+			let mut bytes = words[i];
+			bytes.reverse();
+			Scalar::from_canonical_bytes(bytes).ok_or(ProofError::FormatError)
+		};
+		let points = |start: usize| -> Vec<CompressedRistretto> {
+			words[start..start + k]
+				.iter()
+				.map(|word| CompressedRistretto(*word))
+				.collect()
+		};
+
+		Ok(RangeProof {
+			A: CompressedRistretto(words[0]),
+			S: CompressedRistretto(words[1]),
+			T_1: CompressedRistretto(words[2]),
+			T_2: CompressedRistretto(words[3]),
+			t_x: scalar(4)?,
+			t_x_blinding: scalar(5)?,
+			e_blinding: scalar(6)?,
+			ipp_proof: InnerProductProof {
+				L_vec: points(HEAD_WORDS + 1),
+				R_vec: points(HEAD_WORDS + k + 2),
+				a: scalar(9)?,
+				b: scalar(10)?,
+			},
+		})
+	}
+}
+
+/// Returns the big-endian 32-byte word of `x`.
+fn scalar_word(x: &Scalar) -> [u8; 32] {
+	let mut word = x.to_bytes();
+	word.reverse();
+	word
+}
+
+/// Returns the big-endian 32-byte word of `x`.
+fn u64_word(x: u64) -> [u8; 32] {
+	let mut word = [0u8; 32];
+	word[24..].copy_from_slice(&x.to_be_bytes());
+	word
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::transcript::TranscriptProtocol;
+	use rand_chacha::ChaChaRng;
+	use rand_core::SeedableRng;
+
+	#[test]
+	fn evm_proofs_roundtrip() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 4);
+		let mut rng = ChaChaRng::from_seed([1u8; 32]);
+		let values = [3u64, 1 << 31, 17];
+		let blindings: Vec<_> = values.iter().map(|_| Scalar::random(&mut rng)).collect();
+
+		let mut transcript = EvmTranscript::new(b"EvmTest");
+		let (proof, commitments) = RangeProof::prove_multiple_evm_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&values,
+			&blindings,
+			32,
+			&mut rng,
+		)
+		.unwrap();
+		// Seven elements, two offsets, a and b, and two arrays of length
+		// lg(32 * 4).
+		assert_eq!(proof.len(), 32 * (HEAD_WORDS + 2 * 8));
+
+		let decoded = RangeProof::from_evm_bytes(&proof).unwrap();
+		assert_eq!(decoded.to_evm_bytes(), proof);
+
+		let verify = |proof: &[u8], label: &'static [u8], commitments: &[CompressedRistretto]| {
+			let mut transcript = EvmTranscript::new(label);
+			RangeProof::verify_multiple_evm(proof, &bp_gens, &pc_gens, &mut transcript, commitments, 32)
+		};
+		assert!(verify(&proof, b"EvmTest", &commitments).is_ok());
+		assert!(verify(&proof, b"OtherTest", &commitments).is_err());
+		assert!(verify(&proof, b"EvmTest", &commitments[..2]).is_err());
+
+		// The same proof does not verify with a Merlin transcript.
+		let mut transcript = merlin::Transcript::new(b"EvmTest");
+		assert!(decoded
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, 32)
+			.is_err());
+	}
+
+	#[test]
+	fn evm_proofs_with_labelled_gens() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new_with_label(8, 1, b"EvmApp");
+		let mut transcript = EvmTranscript::new(b"EvmTest");
+		let (proof, commitments) =
+			RangeProof::prove_multiple_evm(&bp_gens, &pc_gens, &mut transcript, &[5], &[Scalar::one()], 8).unwrap();
+
+		for (gens, valid) in vec![(bp_gens, true), (BulletproofGens::new(8, 1), false)] {
+			let mut transcript = EvmTranscript::new(b"EvmTest");
+			assert_eq!(
+				RangeProof::verify_multiple_evm(&proof, &gens, &pc_gens, &mut transcript, &commitments, 8).is_ok(),
+				valid
+			);
+		}
+	}
+
+	#[test]
+	fn from_evm_bytes_rejects_malformed_encodings() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let mut transcript = EvmTranscript::new(b"EvmTest");
+		let (proof, _) =
+			RangeProof::prove_multiple_evm(&bp_gens, &pc_gens, &mut transcript, &[5], &[Scalar::one()], 8).unwrap();
+		assert!(RangeProof::from_evm_bytes(&proof).is_ok());
+		assert!(RangeProof::from_evm_bytes(&proof[..proof.len() - 32]).is_err());
+		assert!(RangeProof::from_evm_bytes(&proof[..proof.len() - 1]).is_err());
+
+		for word in [7, 8, HEAD_WORDS].iter() {
+			let mut tampered = proof.clone();
+			tampered[32 * word + 31] ^= 1;
+			assert!(RangeProof::from_evm_bytes(&tampered).is_err());
+		}
+
+		// A scalar above the group order.
+		let mut tampered = proof;
+		tampered[32 * 4] = 0xff;
+		assert!(RangeProof::from_evm_bytes(&tampered).is_err());
+	}
+
+	#[test]
+	fn challenges_are_wide_keccak_blocks() {
+		let mut transcript = EvmTranscript::new(b"EvmTest");
+		let state = transcript.state();
+		let x = transcript.challenge_scalar(b"x");
+
+		let seed: [u8; 32] = Keccak256::new()
+			.chain(&state)
+			.chain(&1u64.to_be_bytes())
+			.chain(b"x")
+			.chain(&64u64.to_be_bytes())
+			.finalize()
+			.into();
+		assert_eq!(transcript.state(), seed);
+
+		let mut wide = [0u8; 64];
+		for (i, chunk) in wide.chunks_mut(32).enumerate() {
+			let block = Keccak256::new()
+				.chain(&seed)
+				.chain(&(i as u64).to_be_bytes())
+				.finalize();
+			chunk.copy_from_slice(&block);
+			chunk.reverse();
+		}
+		assert_eq!(x, Scalar::from_bytes_mod_order_wide(&wide));
+	}
+}
@@ -0,0 +1,329 @@
+//! Verification of single 64-bit range proofs without heap allocation.
+//!
+//! The general verifier sizes its buffers by the bitsize and aggregation
+//! size of the proof.  For a single 64-bit proof these are known in
+//! advance, so every intermediate value fits in a fixed-size array and the
+//! generators can be derived on the fly instead of being read from
+//! [`BulletproofGens`](::BulletproofGens).
+
+#![allow(non_snake_case)]
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{Identity, IsIdentity},
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::delta;
+#[cfg(feature = "alloc")]
+use super::RangeProof;
+use crate::{
+	errors::ProofError,
+	generators::{GeneratorsChain, PedersenGens},
+	transcript::TranscriptProtocol,
+	util,
+};
+
+/// The bitsize of the proofs accepted by the fixed-size verifier.
+const N: usize = 64;
+
+/// The number of rounds of the inner-product argument, \\(\lg n\\).
+const LG_N: usize = 6;
+
+/// The size in bytes of a single 64-bit range proof.
+const SINGLE_64_PROOF_SIZE: usize = (7 + 2 * LG_N + 2) * 32;
+
+/// Verifies a single 64-bit rangeproof, given in its
+/// [`RangeProof::to_bytes`](::RangeProof::to_bytes) encoding, for the value
+/// commitment \\(V\\).
+///
+/// The proof must be exactly 672 bytes long.  This accepts the same proofs
+/// as [`RangeProof::verify_single_with_rng`](::RangeProof::verify_single_with_rng)
+/// with `n = 64`, but does not allocate: the proof is read in place,
+/// intermediate values are kept in fixed-size arrays, and the generators
+/// of the first party are recomputed from their labels.  It is available
+/// without the `alloc` feature, for verifiers that have no allocator, at
+/// the cost of a slower verification.
+///
+/// `gens_label` is the [`label`](::BulletproofGens::label) of the
+/// [`BulletproofGens`](::BulletproofGens) the proof was created with, which
+/// is empty for [`BulletproofGens::new`](::BulletproofGens::new).
+pub fn verify_single_64_from_bytes_with_rng<T: RngCore + CryptoRng>(
+	proof: &[u8],
+	pc_gens: &PedersenGens,
+	gens_label: &[u8],
+	transcript: &mut Transcript,
+	V: &CompressedRistretto,
+	rng: &mut T,
+) -> Result<(), ProofError> {
+	if proof.len() != SINGLE_64_PROOF_SIZE {
+		return Err(ProofError::FormatError);
+	}
+
+	let point = |i: usize| CompressedRistretto(util::read32(&proof[i * 32..]));
+	let scalar = |i: usize| Scalar::from_canonical_bytes(util::read32(&proof[i * 32..])).ok_or(ProofError::FormatError);
+
+	let (A, S, T_1, T_2) = (point(0), point(1), point(2), point(3));
+	let t_x = scalar(4)?;
+	let t_x_blinding = scalar(5)?;
+	let e_blinding = scalar(6)?;
+	let L = |j: usize| point(7 + 2 * j);
+	let R = |j: usize| point(8 + 2 * j);
+	let a = scalar(7 + 2 * LG_N)?;
+	let b = scalar(8 + 2 * LG_N)?;
+
+	// Replay the transcript exactly as the general verifier does.
+	transcript.rangeproof_domain_sep(N as u64, 1);
+	transcript.append_point(b"V", V);
+	transcript.validate_and_append_point(b"A", &A)?;
+	transcript.validate_and_append_point(b"S", &S)?;
+
+	let y = transcript.challenge_scalar(b"y");
+	let z = transcript.challenge_scalar(b"z");
+	let zz = z * z;
+
+	transcript.validate_and_append_point(b"T_1", &T_1)?;
+	transcript.validate_and_append_point(b"T_2", &T_2)?;
+
+	let x = transcript.challenge_scalar(b"x");
+
+	transcript.append_scalar(b"t_x", &t_x);
+	transcript.append_scalar(b"t_x_blinding", &t_x_blinding);
+	transcript.append_scalar(b"e_blinding", &e_blinding);
+
+	let w = transcript.challenge_scalar(b"w");
+
+	// Challenge value for combining the two verification equations
+	let c = Scalar::random(rng);
+
+	transcript.innerproduct_domain_sep(N as u64);
+
+	let mut u_sq = [Scalar::zero(); LG_N];
+	let mut u_inv_sq = [Scalar::zero(); LG_N];
+	let mut allinv = Scalar::one();
+	for j in 0..LG_N {
+		transcript.validate_and_append_point(b"L", &L(j))?;
+		transcript.validate_and_append_point(b"R", &R(j))?;
+		let u = transcript.challenge_scalar(b"u");
+		let u_inv = u.invert();
+		allinv *= u_inv;
+		u_sq[j] = u * u;
+		u_inv_sq[j] = u_inv * u_inv;
+	}
+
+	let mut s = [Scalar::zero(); N];
+	s[0] = allinv;
+	for i in 1..N {
+		let lg_i = (32 - 1 - (i as u32).leading_zeros()) as usize;
+		let k = 1 << lg_i;
+		s[i] = s[i - k] * u_sq[(LG_N - 1) - lg_i];
+	}
+
+	let decompress = |P: &CompressedRistretto| P.decompress().ok_or(ProofError::VerificationError);
+
+	let mut check = decompress(&A)?
+		+ x * decompress(&S)?
+		+ (c * x) * decompress(&T_1)?
+		+ (c * x * x) * decompress(&T_2)?
+		+ (c * zz) * decompress(V)?
+		+ (w * (t_x - a * b) + c * (delta(N, 1, &y, &z) - t_x)) * pc_gens.B
+		+ (-e_blinding - c * t_x_blinding) * pc_gens.B_blinding;
+	for j in 0..LG_N {
+		check += u_sq[j] * decompress(&L(j))? + u_inv_sq[j] * decompress(&R(j))?;
+	}
+
+	let G = GeneratorsChain::for_party(gens_label, b'G', 0);
+	let H = GeneratorsChain::for_party(gens_label, b'H', 0);
+	let y_inv = y.invert();
+	let mut exp_y_inv = Scalar::one();
+	let mut exp_2 = Scalar::one();
+	let mut gens_check = RistrettoPoint::identity();
+	for (i, (G_i, H_i)) in G.zip(H).take(N).enumerate() {
+		gens_check += (-z - a * s[i]) * G_i + (z + exp_y_inv * (zz * exp_2 - b * s[N - 1 - i])) * H_i;
+		exp_y_inv *= y_inv;
+		exp_2 += exp_2;
+	}
+
+	if (check + gens_check).is_identity() {
+		Ok(())
+	} else {
+		Err(ProofError::VerificationError)
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl RangeProof {
+	/// Verifies a single 64-bit rangeproof, given in its
+	/// [`to_bytes`](RangeProof::to_bytes) encoding, for the value commitment
+	/// \\(V\\).
+	///
+	/// This is [`verify_single_64_from_bytes_with_rng`], which does not
+	/// allocate and is also available without the `alloc` feature.
+	pub fn verify_single_64_from_bytes_with_rng<T: RngCore + CryptoRng>(
+		proof: &[u8],
+		pc_gens: &PedersenGens,
+		gens_label: &[u8],
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		verify_single_64_from_bytes_with_rng(proof, pc_gens, gens_label, transcript, V, rng)
+	}
+
+	/// Verifies a single 64-bit rangeproof, given in its
+	/// [`to_bytes`](RangeProof::to_bytes) encoding, for the value commitment
+	/// \\(V\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_64_from_bytes_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_64_from_bytes(
+		proof: &[u8],
+		pc_gens: &PedersenGens,
+		gens_label: &[u8],
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+	) -> Result<(), ProofError> {
+		verify_single_64_from_bytes_with_rng(proof, pc_gens, gens_label, transcript, V, &mut thread_rng())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::generators::BulletproofGens;
+
+	#[test]
+	fn proof_size() {
+		assert_eq!(SINGLE_64_PROOF_SIZE, RangeProof::size(64, 1));
+	}
+
+	#[test]
+	fn agrees_with_general_verifier() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		let (proof, V) = RangeProof::prove_single(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"FixedTest"),
+			u64::max_value() - 7,
+			&blinding,
+			64,
+		)
+		.unwrap();
+		let bytes = proof.to_bytes();
+
+		assert!(
+			RangeProof::verify_single_64_from_bytes(&bytes, &pc_gens, b"", &mut Transcript::new(b"FixedTest"), &V)
+				.is_ok()
+		);
+		assert!(proof
+			.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(b"FixedTest"), &V, 64)
+			.is_ok());
+		assert!(verify_single_64_from_bytes_with_rng(
+			&bytes,
+			&pc_gens,
+			b"",
+			&mut Transcript::new(b"FixedTest"),
+			&V,
+			&mut rand::thread_rng()
+		)
+		.is_ok());
+
+		// A different transcript, commitment or proof is rejected.
+		assert!(
+			RangeProof::verify_single_64_from_bytes(&bytes, &pc_gens, b"", &mut Transcript::new(b"Other"), &V).is_err()
+		);
+		let other_V = pc_gens.commit(Scalar::from(3u64), blinding).compress();
+		assert!(RangeProof::verify_single_64_from_bytes(
+			&bytes,
+			&pc_gens,
+			b"",
+			&mut Transcript::new(b"FixedTest"),
+			&other_V
+		)
+		.is_err());
+		let mut bad = bytes.clone();
+		bad[4 * 32] ^= 1;
+		assert!(
+			RangeProof::verify_single_64_from_bytes(&bad, &pc_gens, b"", &mut Transcript::new(b"FixedTest"), &V)
+				.is_err()
+		);
+		assert_eq!(
+			RangeProof::verify_single_64_from_bytes(
+				&bytes[32..],
+				&pc_gens,
+				b"",
+				&mut Transcript::new(b"FixedTest"),
+				&V
+			),
+			Err(ProofError::FormatError)
+		);
+	}
+
+	#[test]
+	fn rejects_other_bitsizes() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
+		let (proof, V) = RangeProof::prove_single(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"FixedTest"),
+			5,
+			&Scalar::one(),
+			32,
+		)
+		.unwrap();
+
+		assert!(RangeProof::verify_single_64_from_bytes(
+			&proof.to_bytes(),
+			&pc_gens,
+			b"",
+			&mut Transcript::new(b"FixedTest"),
+			&V
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn verifies_proofs_with_labelled_gens() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new_with_label(64, 1, b"FixedApp");
+		let (proof, V) = RangeProof::prove_single(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"FixedTest"),
+			5,
+			&Scalar::one(),
+			64,
+		)
+		.unwrap();
+		let bytes = proof.to_bytes();
+
+		for &(label, valid) in &[(&b"FixedApp"[..], true), (b"", false), (b"OtherApp", false)] {
+			assert_eq!(
+				RangeProof::verify_single_64_from_bytes(
+					&bytes,
+					&pc_gens,
+					label,
+					&mut Transcript::new(b"FixedTest"),
+					&V
+				)
+				.is_ok(),
+				valid
+			);
+		}
+		assert!(proof
+			.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(b"FixedTest"), &V, 64)
+			.is_ok());
+	}
+}
@@ -0,0 +1,450 @@
+//! Creation of aggregated range proofs with less memory.
+//!
+//! The prover of [`RangeProof::prove_multiple`] runs the aggregation
+//! protocol with one party per value, and holds at once the blinding
+//! vectors \\(\mathbf{s}_L, \mathbf{s}_R\\), the coefficients of the
+//! vector polynomials \\(\mathbf{l}(X), \mathbf{r}(X)\\), their evaluations
+//! in the proof shares, the concatenated evaluations and the factors of the
+//! inner-product argument: twelve scalar vectors of length \\(n \cdot m\\).
+//!
+//! The low-memory prover derives the blinding vectors from a secret seed,
+//! so that it can stream them again instead of storing them, and streams
+//! the bits of the values and the coefficients of the vector polynomials
+//! in each pass over them.  It only materializes the four vectors of the
+//! inner-product argument: \\(\mathbf{l}(x), \mathbf{r}(x)\\) and their
+//! factors.  It takes about one more pass over the generators than the
+//! standard prover, and creates proofs in the same format, verified by the
+//! same verifiers.
+
+#![allow(non_snake_case)]
+
+use alloc::{vec, vec::Vec};
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+use digest::{ExtendableOutputDirty, Update, XofReader};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+use sha3::{Sha3XofReader, Shake256};
+use subtle::{Choice, ConditionallySelectable};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{bitsizes_domain_sep, check_capacity, messages::BitChallenge, padding_domain_sep, powers_of_2, RangeProof};
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::InnerProductProof,
+	transcript::TranscriptProtocol,
+	util,
+};
+
+/// The number of generators in each multiscalar multiplication of the
+/// commitment \\(S\\) to the blinding vectors.
+const CHUNK_SIZE: usize = 256;
+
+impl RangeProof {
+	/// Creates an aggregated rangeproof for a set of values, like
+	/// [`RangeProof::prove_multiple_with_rng`], storing a third of the
+	/// scalar vectors of its prover.
+	///
+	/// The blinding vectors are derived from a secret seed and streamed
+	/// with the bits of the values and the coefficients of the vector
+	/// polynomials, at the cost of more passes over them.  The proof has
+	/// the same format and is verified the same way.
+	pub fn prove_multiple_low_memory_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		if values.len() != blindings.len() {
+			return Err(ProofError::WrongNumBlindingFactors {
+				expected: values.len(),
+				actual: blindings.len(),
+			});
+		}
+		if values.is_empty() {
+			return Err(ProofError::InvalidAggregation);
+		}
+		check_capacity(n, values.len(), bp_gens.gens_capacity, bp_gens.party_capacity)?;
+
+		// Pad the aggregation to a power of two with zero values and zero
+		// blinding factors, whose commitments are the identity.
+		let padded_n = n.next_power_of_two();
+		let padded_m = values.len().next_power_of_two();
+		let blinding = |j: usize| blindings.get(j).copied().unwrap_or_else(Scalar::zero);
+
+		let mut value_commitments: Vec<CompressedRistretto> = (0..padded_m)
+			.map(|j| {
+				let v_j = values.get(j).copied().unwrap_or(0);
+				pc_gens.commit(Scalar::from(v_j), blinding(j)).compress()
+			})
+			.collect();
+
+		padding_domain_sep(transcript, values.len());
+		bitsizes_domain_sep(transcript, &vec![n; padded_m]);
+		for V in value_commitments.iter() {
+			transcript.append_point(b"V", V);
+		}
+
+		let mut a_blinding = Scalar::random(rng);
+		let mut s_blinding = Scalar::random(rng);
+		let mut seed = BlindingSeed([0u8; 32]);
+		rng.fill_bytes(&mut seed.0);
+
+		let (A, S) = commit_bits(
+			bp_gens, pc_gens, values, padded_n, padded_m, a_blinding, s_blinding, &seed,
+		);
+
+		transcript.append_point(b"A", &A.compress());
+		transcript.append_point(b"S", &S.compress());
+		let bit_challenge = BitChallenge {
+			y: transcript.challenge_scalar(b"y"),
+			z: transcript.challenge_scalar(b"z"),
+		};
+
+		// Compute the coefficients of t(X) = <l(X), r(X)>.
+		let mut t_poly = util::Poly2(Scalar::zero(), Scalar::zero(), Scalar::zero());
+		for [l_0, l_1, r_0, r_1] in coefficients(values, n, padded_n, padded_m, &bit_challenge, &seed) {
+			t_poly.0 += l_0 * r_0;
+			t_poly.1 += l_0 * r_1 + l_1 * r_0;
+			t_poly.2 += l_1 * r_1;
+		}
+
+		let mut t_1_blinding = Scalar::random(rng);
+		let mut t_2_blinding = Scalar::random(rng);
+		let T_1 = pc_gens.commit(t_poly.1, t_1_blinding);
+		let T_2 = pc_gens.commit(t_poly.2, t_2_blinding);
+
+		transcript.append_point(b"T_1", &T_1.compress());
+		transcript.append_point(b"T_2", &T_2.compress());
+		let x = transcript.challenge_scalar(b"x");
+
+		let z_2 = bit_challenge.z * bit_challenge.z;
+		let v_blinding: Scalar = util::exp_iter(bit_challenge.z)
+			.take(padded_m)
+			.enumerate()
+			.map(|(j, exp_z)| z_2 * exp_z * blinding(j))
+			.sum();
+		let t_x = t_poly.eval(x);
+		let t_x_blinding = util::Poly2(v_blinding, t_1_blinding, t_2_blinding).eval(x);
+		let e_blinding = a_blinding + s_blinding * x;
+
+		transcript.append_scalar(b"t_x", &t_x);
+		transcript.append_scalar(b"t_x_blinding", &t_x_blinding);
+		transcript.append_scalar(b"e_blinding", &e_blinding);
+
+		// Get a challenge value to combine statements for the IPP
+		let Q = transcript.challenge_scalar(b"w") * pc_gens.B;
+
+		let nm = padded_n * padded_m;
+		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(nm).collect();
+		let H_factors: Vec<Scalar> = util::exp_iter(bit_challenge.y.invert()).take(nm).collect();
+		let (l_vec, r_vec): (Vec<Scalar>, Vec<Scalar>) =
+			coefficients(values, n, padded_n, padded_m, &bit_challenge, &seed)
+				.map(|[l_0, l_1, r_0, r_1]| (l_0 + l_1 * x, r_0 + r_1 * x))
+				.unzip();
+
+		let ipp_proof = InnerProductProof::create(
+			transcript,
+			&Q,
+			&G_factors,
+			&H_factors,
+			bp_gens.G(padded_n, padded_m).cloned().collect(),
+			bp_gens.H(padded_n, padded_m).cloned().collect(),
+			l_vec,
+			r_vec,
+		);
+
+		util::clear_secret(&mut a_blinding);
+		util::clear_secret(&mut s_blinding);
+		util::clear_secret(&mut t_1_blinding);
+		util::clear_secret(&mut t_2_blinding);
+
+		value_commitments.truncate(values.len());
+		Ok((
+			RangeProof {
+				A: A.compress(),
+				S: S.compress(),
+				T_1: T_1.compress(),
+				T_2: T_2.compress(),
+				t_x,
+				t_x_blinding,
+				e_blinding,
+				ipp_proof,
+			},
+			value_commitments,
+		))
+	}
+
+	/// Creates an aggregated rangeproof for a set of values, storing a
+	/// third of the scalar vectors of the standard prover.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_multiple_low_memory_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple_low_memory(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_low_memory_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			values,
+			blindings,
+			n,
+			&mut thread_rng(),
+		)
+	}
+}
+
+/// Returns the commitments \\(A\\) to the bits of the values and
+/// \\(S\\) to the blinding vectors.
+#[allow(clippy::too_many_arguments)]
+fn commit_bits(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	values: &[u64],
+	padded_n: usize,
+	padded_m: usize,
+	a_blinding: Scalar,
+	s_blinding: Scalar,
+	seed: &BlindingSeed,
+) -> (RistrettoPoint, RistrettoPoint) {
+	// Compute A = <a_L, G> + <a_R, H> + a_blinding * B_blinding, adding
+	// G_i if the bit is one and -H_i if it is zero, in constant time.
+	let mut A = pc_gens.B_blinding * a_blinding;
+	let gens = bp_gens.G(padded_n, padded_m).zip(bp_gens.H(padded_n, padded_m));
+	for (bit, (G_i, H_i)) in bits(values, padded_n, padded_m).zip(gens) {
+		let mut point = -H_i;
+		point.conditional_assign(G_i, Choice::from(bit as u8));
+		A += point;
+	}
+
+	// Compute S = <s_L, G> + <s_R, H> + s_blinding * B_blinding, in
+	// chunks of the blinding vectors.
+	let mut S = pc_gens.B_blinding * s_blinding;
+	let mut gens = bp_gens.G(padded_n, padded_m).zip(bp_gens.H(padded_n, padded_m));
+	let mut stream = seed.blindings();
+	loop {
+		let chunk: Vec<_> = (&mut gens).take(CHUNK_SIZE).collect();
+		if chunk.is_empty() {
+			break;
+		}
+		let (mut s_L, mut s_R): (Vec<Scalar>, Vec<Scalar>) = (&mut stream).take(chunk.len()).unzip();
+		S += util::multiscalar_mul(
+			s_L.iter().chain(s_R.iter()),
+			chunk
+				.iter()
+				.map(|(G_i, _)| *G_i)
+				.chain(chunk.iter().map(|(_, H_i)| *H_i)),
+		);
+		util::clear_secrets(&mut s_L);
+		util::clear_secrets(&mut s_R);
+	}
+
+	(A, S)
+}
+
+/// The secret seed of the blinding vectors \\(\mathbf{s}_L, \mathbf{s}_R\\).
+struct BlindingSeed([u8; 32]);
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for BlindingSeed {
+	fn drop(&mut self) {
+		util::clear_secret(&mut self.0);
+	}
+}
+
+impl BlindingSeed {
+	/// Streams the pairs \\((s_{L,i}, s_{R,i})\\), squeezed from SHAKE256
+	/// seeded with the seed.
+	fn blindings(&self) -> BlindingStream {
+		let mut shake = Shake256::default();
+		shake.update(b"RangeProofLowMemory");
+		shake.update(self.0);
+		BlindingStream {
+			reader: shake.finalize_xof_dirty(),
+		}
+	}
+}
+
+struct BlindingStream {
+	reader: Sha3XofReader,
+}
+
+impl Iterator for BlindingStream {
+	type Item = (Scalar, Scalar);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let mut bytes = [0u8; 64];
+		self.reader.read(&mut bytes);
+		let s_L = Scalar::from_bytes_mod_order_wide(&bytes);
+		self.reader.read(&mut bytes);
+		let s_R = Scalar::from_bytes_mod_order_wide(&bytes);
+		Some((s_L, s_R))
+	}
+}
+
+/// Streams the bits of the values, each padded to `padded_n` bits, and of
+/// the zero values padding them to `padded_m` values.
+fn bits(values: &[u64], padded_n: usize, padded_m: usize) -> impl Iterator<Item = u64> + '_ {
+	(0..padded_m).flat_map(move |j| {
+		let v_j = values.get(j).copied().unwrap_or(0);
+		(0..padded_n).map(move |i| (v_j >> i) & 1)
+	})
+}
+
+/// Streams the coefficients \\([l_{0,i}, l_{1,i}, r_{0,i}, r_{1,i}]\\) of
+/// the vector polynomials \\(\mathbf{l}(X), \mathbf{r}(X)\\), computed as
+/// by each party of the aggregation protocol.
+fn coefficients<'a>(
+	values: &'a [u64],
+	n: usize,
+	padded_n: usize,
+	padded_m: usize,
+	bit_challenge: &BitChallenge,
+	seed: &BlindingSeed,
+) -> impl Iterator<Item = [Scalar; 4]> + 'a {
+	let BitChallenge { y, z } = *bit_challenge;
+	let z_2 = z * z;
+	// The offset z^2 * z^j * 2^i of each bit.
+	let offsets = util::exp_iter(z)
+		.take(padded_m)
+		.flat_map(move |exp_z| powers_of_2(n).take(padded_n).map(move |exp_2| z_2 * exp_z * exp_2));
+	bits(values, padded_n, padded_m)
+		.zip(offsets)
+		.zip(util::exp_iter(y))
+		.zip(seed.blindings())
+		.map(move |(((bit, offset), exp_y), (s_L, s_R))| {
+			let a_L = Scalar::from(bit);
+			let a_R = a_L - Scalar::one();
+			[a_L - z, s_L, exp_y * (a_R + z) + offset, exp_y * s_R]
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn check(values: &[u64], n: usize) {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 8);
+		let blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut thread_rng())).collect();
+
+		let (proof, commitments) = RangeProof::prove_multiple_low_memory(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"LowMemoryTest"),
+			values,
+			&blindings,
+			n,
+		)
+		.unwrap();
+		assert_eq!(commitments.len(), values.len());
+		for ((v, blinding), commitment) in values.iter().zip(blindings.iter()).zip(commitments.iter()) {
+			assert_eq!(pc_gens.commit(Scalar::from(*v), *blinding).compress(), *commitment);
+		}
+
+		let (reference, _) = RangeProof::prove_multiple(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"LowMemoryTest"),
+			values,
+			&blindings,
+			n,
+		)
+		.unwrap();
+		assert_eq!(proof.to_bytes().len(), reference.to_bytes().len());
+
+		assert!(proof
+			.verify_multiple(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"LowMemoryTest"),
+				&commitments,
+				n
+			)
+			.is_ok());
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut Transcript::new(b"OtherTest"), &commitments, n)
+			.is_err());
+	}
+
+	#[test]
+	fn proofs_verify() {
+		check(&[0], 8);
+		check(&[u64::max_value()], 64);
+		check(&[1, 2, 3], 16);
+		check(&[1 << 9, 3, 1023, 0, 5], 10);
+		check(&[4242344947, 3718732727, 2255562556, 2526146994, 1, 2, 3, 4], 32);
+	}
+
+	#[test]
+	fn out_of_range_values_do_not_verify() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let blindings = [Scalar::one(), Scalar::one()];
+		let (proof, commitments) = RangeProof::prove_multiple_low_memory(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"LowMemoryTest"),
+			&[3, 256],
+			&blindings,
+			8,
+		)
+		.unwrap();
+		assert!(proof
+			.verify_multiple(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"LowMemoryTest"),
+				&commitments,
+				8
+			)
+			.is_err());
+	}
+
+	#[test]
+	fn invalid_parameters() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 2);
+		let prove = |values: &[u64], blindings: &[Scalar], n| {
+			RangeProof::prove_multiple_low_memory(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"LowMemoryTest"),
+				values,
+				blindings,
+				n,
+			)
+			.map(|_| ())
+		};
+		let one = Scalar::one();
+
+		assert_eq!(
+			prove(&[1, 2], &[one], 8),
+			Err(ProofError::WrongNumBlindingFactors { expected: 2, actual: 1 })
+		);
+		assert_eq!(prove(&[], &[], 8), Err(ProofError::InvalidAggregation));
+		assert_eq!(prove(&[1], &[one], 0), Err(ProofError::InvalidBitsize));
+		assert_eq!(prove(&[1], &[one], 64), Err(ProofError::InvalidGeneratorsLength));
+		assert_eq!(
+			prove(&[1, 2, 3], &[one, one, one], 8),
+			Err(ProofError::InvalidGeneratorsLength)
+		);
+	}
+}
@@ -16,7 +16,23 @@ use curve25519_dalek::{
 	scalar::Scalar,
 };
 
-use crate::generators::{BulletproofGens, PedersenGens};
+use crate::{
+	errors::{MPCError, ShareError},
+	generators::{BulletproofGens, PedersenGens},
+	util,
+};
+
+/// Decompresses the point encoded in the 32 bytes of `data`.
+fn read_point(data: &[u8]) -> Result<RistrettoPoint, MPCError> {
+	CompressedRistretto(util::read32(data))
+		.decompress()
+		.ok_or(MPCError::FormatError)
+}
+
+/// Reads the canonical scalar encoded in the 32 bytes of `data`.
+fn read_scalar(data: &[u8]) -> Result<Scalar, MPCError> {
+	Scalar::from_canonical_bytes(util::read32(data)).ok_or(MPCError::FormatError)
+}
 
 /// A commitment to the bits of a party's value.
 #[derive(Copy, Clone, Debug)]
@@ -26,6 +42,33 @@ pub struct BitCommitment {
 	pub(super) S_j: RistrettoPoint,
 }
 
+impl BitCommitment {
+	/// Serializes the commitment into a byte array of the three
+	/// compressed points \(V_j, A_j, S_j\).
+	pub fn to_bytes(self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(3 * 32);
+		buf.extend_from_slice(self.V_j.as_bytes());
+		buf.extend_from_slice(self.A_j.compress().as_bytes());
+		buf.extend_from_slice(self.S_j.compress().as_bytes());
+		buf
+	}
+
+	/// Deserializes the commitment from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `BitCommitment`.
+	pub fn from_bytes(slice: &[u8]) -> Result<BitCommitment, MPCError> {
+		if slice.len() != 3 * 32 {
+			return Err(MPCError::FormatError);
+		}
+		Ok(BitCommitment {
+			V_j: CompressedRistretto(util::read32(&slice[0..])),
+			A_j: read_point(&slice[32..])?,
+			S_j: read_point(&slice[64..])?,
+		})
+	}
+}
+
 /// Challenge values derived from all parties' [`BitCommitment`]s.
 #[derive(Copy, Clone, Debug)]
 pub struct BitChallenge {
@@ -33,6 +76,31 @@ pub struct BitChallenge {
 	pub(super) z: Scalar,
 }
 
+impl BitChallenge {
+	/// Serializes the challenge into a byte array of the two scalars
+	/// \(y, z\).
+	pub fn to_bytes(self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(2 * 32);
+		buf.extend_from_slice(self.y.as_bytes());
+		buf.extend_from_slice(self.z.as_bytes());
+		buf
+	}
+
+	/// Deserializes the challenge from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `BitChallenge`.
+	pub fn from_bytes(slice: &[u8]) -> Result<BitChallenge, MPCError> {
+		if slice.len() != 2 * 32 {
+			return Err(MPCError::FormatError);
+		}
+		Ok(BitChallenge {
+			y: read_scalar(&slice[0..])?,
+			z: read_scalar(&slice[32..])?,
+		})
+	}
+}
+
 /// A commitment to a party's polynomial coefficents.
 #[derive(Copy, Clone, Debug)]
 pub struct PolyCommitment {
@@ -40,12 +108,55 @@ pub struct PolyCommitment {
 	pub(super) T_2_j: RistrettoPoint,
 }
 
+impl PolyCommitment {
+	/// Serializes the commitment into a byte array of the two compressed
+	/// points \(T_{1,j}, T_{2,j}\).
+	pub fn to_bytes(self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(2 * 32);
+		buf.extend_from_slice(self.T_1_j.compress().as_bytes());
+		buf.extend_from_slice(self.T_2_j.compress().as_bytes());
+		buf
+	}
+
+	/// Deserializes the commitment from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `PolyCommitment`.
+	pub fn from_bytes(slice: &[u8]) -> Result<PolyCommitment, MPCError> {
+		if slice.len() != 2 * 32 {
+			return Err(MPCError::FormatError);
+		}
+		Ok(PolyCommitment {
+			T_1_j: read_point(&slice[0..])?,
+			T_2_j: read_point(&slice[32..])?,
+		})
+	}
+}
+
 /// Challenge values derived from all parties' [`PolyCommitment`]s.
 #[derive(Copy, Clone, Debug)]
 pub struct PolyChallenge {
 	pub(super) x: Scalar,
 }
 
+impl PolyChallenge {
+	/// Serializes the challenge into the 32 bytes of the scalar \(x\).
+	pub fn to_bytes(self) -> Vec<u8> {
+		self.x.as_bytes().to_vec()
+	}
+
+	/// Deserializes the challenge from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `PolyChallenge`.
+	pub fn from_bytes(slice: &[u8]) -> Result<PolyChallenge, MPCError> {
+		if slice.len() != 32 {
+			return Err(MPCError::FormatError);
+		}
+		Ok(PolyChallenge { x: read_scalar(slice)? })
+	}
+}
+
 /// A party's proof share, ready for aggregation into the final
 /// [`RangeProof`](::RangeProof).
 #[derive(Clone, Debug)]
@@ -58,79 +169,108 @@ pub struct ProofShare {
 }
 
 impl ProofShare {
+	/// Serializes the share into a byte array of \(3 + 2n\) 32-byte
+	/// scalars, where \(n\) is the padded bitsize:
+	///
+	/// * the three scalars \(t_x, \tilde{t}_x, \tilde{e}\);
+	/// * the \(n\) elements of \(\mathbf{l}\);
+	/// * the \(n\) elements of \(\mathbf{r}\).
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity((3 + self.l_vec.len() + self.r_vec.len()) * 32);
+		buf.extend_from_slice(self.t_x.as_bytes());
+		buf.extend_from_slice(self.t_x_blinding.as_bytes());
+		buf.extend_from_slice(self.e_blinding.as_bytes());
+		for x in self.l_vec.iter().chain(self.r_vec.iter()) {
+			buf.extend_from_slice(x.as_bytes());
+		}
+		buf
+	}
+
+	/// Deserializes the share from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `ProofShare`.  The dealer checks the length of the vectors against
+	/// the bitsize of the proof when it receives the share.
+	pub fn from_bytes(slice: &[u8]) -> Result<ProofShare, MPCError> {
+		if slice.len() < 3 * 32 || (slice.len() - 3 * 32) % 64 != 0 {
+			return Err(MPCError::FormatError);
+		}
+		let n = (slice.len() - 3 * 32) / 64;
+		let scalars = slice[3 * 32..]
+			.chunks(32)
+			.map(read_scalar)
+			.collect::<Result<Vec<_>, _>>()?;
+		let (l_vec, r_vec) = scalars.split_at(n);
+
+		Ok(ProofShare {
+			t_x: read_scalar(&slice[0..])?,
+			t_x_blinding: read_scalar(&slice[32..])?,
+			e_blinding: read_scalar(&slice[64..])?,
+			l_vec: l_vec.to_vec(),
+			r_vec: r_vec.to_vec(),
+		})
+	}
+
 	/// Checks consistency of all sizes in the proof share and returns the size
 	/// of the l/r vector.
-	pub(super) fn check_size(&self, expected_n: usize, bp_gens: &BulletproofGens, j: usize) -> Result<(), ()> {
+	pub(super) fn check_size(&self, expected_n: usize, bp_gens: &BulletproofGens, j: usize) -> Result<(), ShareError> {
 		if self.l_vec.len() != expected_n {
-			return Err(());
+			return Err(ShareError::WrongSize);
 		}
 
 		if self.r_vec.len() != expected_n {
-			return Err(());
+			return Err(ShareError::WrongSize);
 		}
 
 		if expected_n > bp_gens.gens_capacity {
-			return Err(());
+			return Err(ShareError::WrongSize);
 		}
 
 		if j >= bp_gens.party_capacity {
-			return Err(());
+			return Err(ShareError::WrongSize);
 		}
 
 		Ok(())
 	}
 
 	/// Audit an individual proof share to determine whether it is
-	/// malformed.
-	pub(super) fn audit_share(
+	/// malformed, and if so, which check it fails first.  The `bitsize`
+	/// is the unpadded bitsize of the range.
+	///
+	/// The dealer audits the shares when the aggregated proof fails to
+	/// verify, but the audit only needs the messages of the protocol, so
+	/// a third party holding a record of them can check independently
+	/// which party `j` submitted a bad share: the share is checked
+	/// against the party's own [`BitCommitment`] and [`PolyCommitment`]
+	/// and the challenges the dealer sent to all parties.
+	#[allow(clippy::too_many_arguments)]
+	pub fn audit(
 		&self,
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
 		j: usize,
+		bitsize: usize,
 		bit_commitment: &BitCommitment,
 		bit_challenge: &BitChallenge,
 		poly_commitment: &PolyCommitment,
 		poly_challenge: &PolyChallenge,
-	) -> Result<(), ()> {
+	) -> Result<(), ShareError> {
 		use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
 
-		use crate::{inner_product_proof::inner_product, util};
+		use crate::inner_product_proof::inner_product;
 
 		let n = self.l_vec.len();
 
 		self.check_size(n, bp_gens, j)?;
 
-		let (y, z) = (&bit_challenge.y, &bit_challenge.z);
-		let x = &poly_challenge.x;
-
-		// Precompute some variables
-		let zz = z * z;
-		let minus_z = -z;
-		let z_j = util::scalar_exp_vartime(z, j as u64); // z^j
-		let y_jn = util::scalar_exp_vartime(y, (j * n) as u64); // y^(j*n)
-		let y_jn_inv = y_jn.invert(); // y^(-j*n)
-		let y_inv = y.invert(); // y^(-1)
-
 		if self.t_x != inner_product(&self.l_vec, &self.r_vec) {
-			return Err(());
+			return Err(ShareError::InconsistentInnerProduct);
 		}
 
-		let g = self.l_vec.iter().map(|l_i| minus_z - l_i);
-		let h = self
-			.r_vec
-			.iter()
-			.zip(util::exp_iter(Scalar::from(2u64)))
-			.zip(util::exp_iter(y_inv))
-			.map(|((r_i, exp_2), exp_y_inv)| {
-				z + exp_y_inv * y_jn_inv * (-r_i) + exp_y_inv * y_jn_inv * (zz * z_j * exp_2)
-			});
+		let scalars = self.check_scalars(j, bitsize, bit_challenge, poly_challenge);
 
 		let P_check = RistrettoPoint::vartime_multiscalar_mul(
-			iter::once(Scalar::one())
-				.chain(iter::once(*x))
-				.chain(iter::once(-self.e_blinding))
-				.chain(g)
-				.chain(h),
+			scalars.bit,
 			iter::once(&bit_commitment.A_j)
 				.chain(iter::once(&bit_commitment.S_j))
 				.chain(iter::once(&pc_gens.B_blinding))
@@ -138,20 +278,16 @@ impl ProofShare {
 				.chain(bp_gens.share(j).H(n)),
 		);
 		if !P_check.is_identity() {
-			return Err(());
+			return Err(ShareError::InconsistentBitCommitment);
 		}
 
-		let V_j = bit_commitment.V_j.decompress().ok_or(())?;
+		let V_j = bit_commitment
+			.V_j
+			.decompress()
+			.ok_or(ShareError::InvalidValueCommitment)?;
 
-		let sum_of_powers_y = util::sum_of_powers(&y, n);
-		let sum_of_powers_2 = util::sum_of_powers(&Scalar::from(2u64), n);
-		let delta = (z - zz) * sum_of_powers_y * y_jn - z * zz * sum_of_powers_2 * z_j;
 		let t_check = RistrettoPoint::vartime_multiscalar_mul(
-			iter::once(zz * z_j)
-				.chain(iter::once(*x))
-				.chain(iter::once(x * x))
-				.chain(iter::once(delta - self.t_x))
-				.chain(iter::once(-self.t_x_blinding)),
+			scalars.poly.iter(),
 			iter::once(&V_j)
 				.chain(iter::once(&poly_commitment.T_1_j))
 				.chain(iter::once(&poly_commitment.T_2_j))
@@ -162,7 +298,140 @@ impl ProofShare {
 		if t_check.is_identity() {
 			Ok(())
 		} else {
-			Err(())
+			Err(ShareError::InconsistentPolyCommitment)
 		}
 	}
+
+	/// Returns the terms of the checks of
+	/// [`audit`](ProofShare::audit) of the bit and polynomial
+	/// commitments, weighted by `weights` so that the checks of several
+	/// shares can be batched into a single multiscalar multiplication.
+	///
+	/// Returns an error if the share fails one of the other checks, or if
+	/// \(V_j\) is not a valid point.
+	#[allow(clippy::too_many_arguments)]
+	pub(super) fn audit_terms(
+		&self,
+		bp_gens: &BulletproofGens,
+		j: usize,
+		bitsize: usize,
+		bit_commitment: &BitCommitment,
+		bit_challenge: &BitChallenge,
+		poly_commitment: &PolyCommitment,
+		poly_challenge: &PolyChallenge,
+		weights: (Scalar, Scalar),
+	) -> Result<AuditTerms, ShareError> {
+		use crate::inner_product_proof::inner_product;
+
+		let n = self.l_vec.len();
+
+		self.check_size(n, bp_gens, j)?;
+
+		if self.t_x != inner_product(&self.l_vec, &self.r_vec) {
+			return Err(ShareError::InconsistentInnerProduct);
+		}
+
+		let V_j = bit_commitment
+			.V_j
+			.decompress()
+			.ok_or(ShareError::InvalidValueCommitment)?;
+
+		let CheckScalars { bit, poly } = self.check_scalars(j, bitsize, bit_challenge, poly_challenge);
+		let (bit_weight, poly_weight) = weights;
+
+		// The terms of B_blinding are merged, and those of B and
+		// B_blinding are kept apart, since all shares have them.
+		let scalars = iter::once(bit[0])
+			.chain(iter::once(bit[1]))
+			.chain(bit[3..].iter().cloned())
+			.map(|s| bit_weight * s)
+			.chain(poly[..3].iter().map(|s| poly_weight * s))
+			.collect();
+		let points = iter::once(bit_commitment.A_j)
+			.chain(iter::once(bit_commitment.S_j))
+			.chain(bp_gens.share(j).G(n).cloned())
+			.chain(bp_gens.share(j).H(n).cloned())
+			.chain(iter::once(V_j))
+			.chain(iter::once(poly_commitment.T_1_j))
+			.chain(iter::once(poly_commitment.T_2_j))
+			.collect();
+
+		Ok(AuditTerms {
+			scalars,
+			points,
+			B_scalar: poly_weight * poly[3],
+			B_blinding_scalar: bit_weight * bit[2] + poly_weight * poly[4],
+		})
+	}
+
+	/// Returns the scalars of the checks of
+	/// [`audit`](ProofShare::audit) of the bit and polynomial
+	/// commitments of party `j`.
+	fn check_scalars(
+		&self,
+		j: usize,
+		bitsize: usize,
+		bit_challenge: &BitChallenge,
+		poly_challenge: &PolyChallenge,
+	) -> CheckScalars {
+		let n = self.l_vec.len();
+		let (y, z) = (&bit_challenge.y, &bit_challenge.z);
+		let x = &poly_challenge.x;
+
+		// Precompute some variables
+		let zz = z * z;
+		let minus_z = -z;
+		let z_j = util::scalar_exp_vartime(z, j as u64); // z^j
+		let y_jn = util::scalar_exp_vartime(y, (j * n) as u64); // y^(j*n)
+		let y_jn_inv = y_jn.invert(); // y^(-j*n)
+		let y_inv = y.invert(); // y^(-1)
+
+		let g_scalars = self.l_vec.iter().map(|l_i| minus_z - l_i);
+		let h_scalars = self
+			.r_vec
+			.iter()
+			.zip(super::powers_of_2(bitsize))
+			.zip(util::exp_iter(y_inv))
+			.map(|((r_i, exp_2), exp_y_inv)| {
+				z + exp_y_inv * y_jn_inv * (-r_i) + exp_y_inv * y_jn_inv * (zz * z_j * exp_2)
+			});
+		let bit = iter::once(Scalar::one())
+			.chain(iter::once(*x))
+			.chain(iter::once(-self.e_blinding))
+			.chain(g_scalars)
+			.chain(h_scalars)
+			.collect();
+
+		let sum_of_powers_y = util::sum_of_powers(&y, n);
+		let sum_of_powers_2 = util::sum_of_powers(&Scalar::from(2u64), bitsize);
+		let delta = (z - zz) * sum_of_powers_y * y_jn - z * zz * sum_of_powers_2 * z_j;
+		let poly = [zz * z_j, *x, x * x, delta - self.t_x, -self.t_x_blinding];
+
+		CheckScalars { bit, poly }
+	}
+}
+
+/// The scalars of the checks of a proof share against the bit and
+/// polynomial commitments of its party.
+struct CheckScalars {
+	/// The scalars of \(A_j, S_j, \tilde{B}\), the party's
+	/// \(\mathbf{G}\) and its \(\mathbf{H}\).
+	bit: Vec<Scalar>,
+	/// The scalars of \(V_j, T_{1,j}, T_{2,j}, B, \tilde{B}\).
+	poly: [Scalar; 5],
+}
+
+/// The terms of the checks of a proof share, weighted for batching with
+/// the checks of other shares, returned by
+/// [`ProofShare::audit_terms`].
+pub(super) struct AuditTerms {
+	/// The scalars of `points`.
+	pub(super) scalars: Vec<Scalar>,
+	/// The points \(A_j, S_j\), the party's \(\mathbf{G}\) and
+	/// \(\mathbf{H}\), and \(V_j, T_{1,j}, T_{2,j}\).
+	pub(super) points: Vec<RistrettoPoint>,
+	/// The scalar of the Pedersen base \(B\).
+	pub(super) B_scalar: Scalar,
+	/// The scalar of the Pedersen base \(\tilde{B}\).
+	pub(super) B_blinding_scalar: Scalar,
 }
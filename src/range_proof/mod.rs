@@ -1,43 +1,85 @@
 #![allow(non_snake_case)]
 #![doc(include = "../../docs/range-proof-protocol.md")]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 #[cfg(feature = "std")]
 extern crate rand;
 
 #[cfg(feature = "std")]
 use self::rand::thread_rng;
-use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
 
-use core::iter;
+#[cfg(feature = "alloc")]
+use core::{cmp, iter, ops::RangeInclusive};
 
+use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "alloc")]
 use curve25519_dalek::{
 	ristretto::{CompressedRistretto, RistrettoPoint},
-	scalar::Scalar,
-	traits::{IsIdentity, VartimeMultiscalarMul},
+	traits::{Identity, IsIdentity, MultiscalarMul},
 };
+#[cfg(feature = "alloc")]
 use merlin::Transcript;
+#[cfg(feature = "alloc")]
+use subtle::{Choice, ConstantTimeEq};
 
+use crate::util;
+#[cfg(feature = "alloc")]
 use crate::{
 	errors::ProofError,
-	generators::{BulletproofGens, PedersenGens},
+	generators::{BulletproofGens, PedersenGens, VerificationKey},
 	inner_product_proof::InnerProductProof,
-	transcript::TranscriptProtocol,
-	util,
+	msm::{self, MsmBackend},
+	transcript::{self, ProofTranscript, TranscriptProtocol},
 };
 
+#[cfg(feature = "alloc")]
 use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-#[cfg(feature = "std")]
-use serde::de::Visitor;
-#[cfg(feature = "std")]
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
 // Modules for MPC protocol
 
+#[cfg(feature = "alloc")]
 pub mod dealer;
+#[cfg(feature = "alloc")]
 pub mod messages;
+#[cfg(feature = "alloc")]
 pub mod party;
+#[cfg(feature = "alloc")]
+pub mod session;
+
+#[cfg(feature = "evm-compat")]
+mod evm;
+mod fixed;
+#[cfg(feature = "alloc")]
+mod low_memory;
+#[cfg(feature = "static-gens")]
+mod raw;
+#[cfg(feature = "alloc")]
+mod rewind;
+#[cfg(feature = "alloc")]
+mod verifier;
+#[cfg(feature = "alloc")]
+mod wide;
+
+#[cfg(feature = "evm-compat")]
+pub use self::evm::EvmTranscript;
+pub use self::fixed::verify_single_64_from_bytes_with_rng;
+#[cfg(feature = "static-gens")]
+pub use self::raw::{verify_range_proof_raw, RAW_TRANSCRIPT_LABEL};
+#[cfg(feature = "alloc")]
+pub use self::{verifier::VerifierBuilder, wide::U128RangeProof};
 
 /// The `RangeProof` struct represents a proof that one or more values
 /// are in a range.
@@ -50,11 +92,29 @@ pub mod party;
 /// the values, are not included in the proof, and must be known to
 /// the verifier.
 ///
-/// This implementation requires that both the bitsize `n` and the
-/// aggregation size `m` be powers of two, so that `n = 8, 16, 32, 64`
-/// and `m = 1, 2, 4, 8, 16, ...`.  Note that the aggregation size is
-/// not given as an explicit parameter, but is determined by the
-/// number of values or commitments passed to the prover or verifier.
+/// The bitsize `n` can be any value with \\(1 \le n \le 64\\).  The
+/// inner-product argument requires vectors whose length is a power of
+/// two, so a proof for an `n`-bit range commits to `n` rounded up to the
+/// next power of two bits, with the extra bits fixed to zero; its size
+/// and cost are those of the padded bitsize.  Likewise, the aggregation
+/// size `m` can be any positive number: the prover pads the values with
+/// zeros, committed with a zero blinding factor, up to the next power of
+/// two, and the verifier pads the commitments with the matching identity
+/// points.  The number of values before padding is bound to the
+/// transcript, so a proof of `m` values does not verify with the padding
+/// passed as explicit commitments.  Note that the aggregation size is not given
+/// as an explicit parameter, but is determined by the number of values or
+/// commitments passed to the prover or verifier.
+///
+/// The values of an aggregated proof can also have different bitsizes,
+/// using [`RangeProof::prove_multiple_with_bitsizes_with_rng`]; each is
+/// then padded to the largest of them.
+///
+/// A proof received from an untrusted source should be decoded with
+/// [`RangeProof::from_bytes_strict`], which accepts a single encoding of
+/// each proof.  The verification functions reject proofs with points or
+/// scalars that are not canonically encoded with
+/// [`ProofError::FormatError`].
 ///
 /// # Note
 ///
@@ -62,6 +122,7 @@ pub mod party;
 /// protocol locally.  That API is exposed in the
 /// [`aggregation`](::range_proof_mpc) module and can be used to perform online
 /// aggregation between parties without revealing secret values to each other.
+#[cfg(feature = "alloc")]
 #[derive(Clone, Debug)]
 pub struct RangeProof {
 	/// Commitment to the bits of the value
@@ -83,6 +144,7 @@ pub struct RangeProof {
 	ipp_proof: InnerProductProof,
 }
 
+#[cfg(feature = "alloc")]
 impl RangeProof {
 	/// Create a rangeproof for a given pair of value `v` and
 	/// blinding scalar `v_blinding`.
@@ -136,7 +198,7 @@ impl RangeProof {
 	pub fn prove_single_with_rng<T: RngCore + CryptoRng>(
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		v: u64,
 		v_blinding: &Scalar,
 		n: usize,
@@ -154,7 +216,7 @@ impl RangeProof {
 	pub fn prove_single(
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		v: u64,
 		v_blinding: &Scalar,
 		n: usize,
@@ -212,24 +274,90 @@ impl RangeProof {
 	pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			values,
+			blindings,
+			&vec![n; values.len()],
+			rng,
+		)
+	}
+
+	/// Create a rangeproof for a set of values.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_multiple_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
 		values: &[u64],
 		blindings: &[Scalar],
 		n: usize,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, &mut thread_rng())
+	}
+
+	/// Create an aggregated rangeproof for a set of values with different
+	/// bitsizes, proving that `values[j]` lies in \([0, 2^{n_j})\) for
+	/// \(n_j = \) `bitsizes[j]`.
+	///
+	/// Every value is padded to the largest bitsize, so the proof has the
+	/// size and cost of an aggregated proof of that bitsize.  The bitsizes
+	/// are bound to the transcript, and the verifier must be given the
+	/// same ones.  If all the bitsizes are equal to `n`, this creates the
+	/// same proof as [`RangeProof::prove_multiple_with_rng`] with `n`.
+	///
+	/// Returns [`ProofError::InvalidBitsize`] if `bitsizes` and `values`
+	/// have different lengths.
+	pub fn prove_multiple_with_bitsizes_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		values: &[u64],
+		blindings: &[Scalar],
+		bitsizes: &[usize],
 		rng: &mut T,
 	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
 		use self::{dealer::*, party::*};
 
 		if values.len() != blindings.len() {
-			return Err(ProofError::WrongNumBlindingFactors);
+			return Err(ProofError::WrongNumBlindingFactors {
+				expected: values.len(),
+				actual: blindings.len(),
+			});
+		}
+		if values.is_empty() {
+			return Err(ProofError::InvalidAggregation);
+		}
+		if values.len() != bitsizes.len() {
+			return Err(ProofError::InvalidBitsize);
 		}
 
-		let dealer = Dealer::new(bp_gens, pc_gens, transcript, n, values.len())?;
+		// Pad the aggregation to a power of two with zero values and zero
+		// blinding factors, whose commitments are the identity.
+		let m = values.len();
+		let padding = m.next_power_of_two() - m;
+		let bitsizes = pad_bitsizes(bitsizes, m + padding);
+		let padded_n = bitsizes.iter().cloned().max().unwrap_or(0).next_power_of_two();
+
+		padding_domain_sep(transcript, m);
+		let dealer = Dealer::new_with_bitsizes(bp_gens, pc_gens, transcript, &bitsizes)?;
 
 		let parties: Vec<_> = values
 			.iter()
 			.zip(blindings.iter())
-			.map(|(&v, &v_blinding)| Party::new(bp_gens, pc_gens, v, v_blinding, n))
+			.chain(iter::repeat((&0, &Scalar::zero())).take(padding))
+			.zip(bitsizes.iter())
+			.map(|((&v, &v_blinding), &n_j)| Party::new_with_padding(bp_gens, pc_gens, v, v_blinding, n_j, padded_n))
 			// Collect the iterator of Results into a Result<Vec>, then unwrap it
 			.collect::<Result<Vec<_>, _>>()?;
 
@@ -242,7 +370,7 @@ impl RangeProof {
 			})
 			.unzip();
 
-		let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+		let value_commitments: Vec<_> = bit_commitments.iter().take(m).map(|c| c.V_j).collect();
 
 		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments)?;
 
@@ -264,19 +392,29 @@ impl RangeProof {
 		Ok((proof, value_commitments))
 	}
 
-	/// Create a rangeproof for a set of values.
+	/// Create an aggregated rangeproof for a set of values with different
+	/// bitsizes.
 	/// This is a convenience wrapper around
-	/// [`RangeProof::prove_multiple_with_rng`], passing in a threadsafe RNG.
+	/// [`RangeProof::prove_multiple_with_bitsizes_with_rng`], passing in a
+	/// threadsafe RNG.
 	#[cfg(feature = "std")]
-	pub fn prove_multiple(
+	pub fn prove_multiple_with_bitsizes(
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		values: &[u64],
 		blindings: &[Scalar],
-		n: usize,
+		bitsizes: &[usize],
 	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
-		RangeProof::prove_multiple_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, &mut thread_rng())
+		RangeProof::prove_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			values,
+			blindings,
+			bitsizes,
+			&mut thread_rng(),
+		)
 	}
 
 	/// Verifies a rangeproof for a given value commitment \\(V\\).
@@ -287,7 +425,7 @@ impl RangeProof {
 		&self,
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		V: &CompressedRistretto,
 		n: usize,
 		rng: &mut T,
@@ -304,115 +442,396 @@ impl RangeProof {
 		&self,
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		transcript: &mut impl ProofTranscript,
 		V: &CompressedRistretto,
 		n: usize,
 	) -> Result<(), ProofError> {
 		self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
 	}
 
-	/// Verifies an aggregated rangeproof for the given value commitments.
-	pub fn verify_multiple_with_rng<T: RngCore + CryptoRng>(
+	/// Verifies a rangeproof for a given value commitment \\(V\\), and
+	/// returns a digest binding the proof to the transcript.
+	///
+	/// The digest is a challenge of the transcript once every element of
+	/// the proof has been appended to it, so it depends on the transcript
+	/// label and any data appended to it before verification, on \\(V\\),
+	/// and on the proof.  It can thus serve as a unique identifier of the
+	/// proof, for instance for replay protection, without hashing the
+	/// serialized proof separately.
+	pub fn verify_single_with_binding_with_rng<T: RngCore + CryptoRng>(
 		&self,
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
-		value_commitments: &[CompressedRistretto],
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
 		n: usize,
 		rng: &mut T,
-	) -> Result<(), ProofError> {
-		let m = value_commitments.len();
+	) -> Result<[u8; 32], ProofError> {
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, rng)?;
+
+		// The final scalars of the inner-product argument are the only
+		// elements of the proof that verification does not append.
+		transcript.append_scalar(b"a", &self.ipp_proof.a);
+		transcript.append_scalar(b"b", &self.ipp_proof.b);
+		let mut binding = [0u8; 32];
+		transcript::challenge_bytes(transcript, b"binding", &mut binding);
+		Ok(binding)
+	}
 
-		// First, replay the "interactive" protocol using the proof
-		// data to recompute all challenges.
-		if !(n == 8 || n == 16 || n == 32 || n == 64) {
-			return Err(ProofError::InvalidBitsize);
-		}
-		if bp_gens.gens_capacity < n {
-			return Err(ProofError::InvalidGeneratorsLength);
-		}
-		if bp_gens.party_capacity < m {
-			return Err(ProofError::InvalidGeneratorsLength);
-		}
+	/// Verifies a rangeproof for a given value commitment \\(V\\), and
+	/// returns a digest binding the proof to the transcript.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_with_binding_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_with_binding(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<[u8; 32], ProofError> {
+		self.verify_single_with_binding_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+	}
 
-		transcript.rangeproof_domain_sep(n as u64, m as u64);
+	/// Create a rangeproof for a given pair of value `v` and
+	/// blinding scalar `v_blinding`, bound to the application-defined
+	/// `context` bytes.
+	///
+	/// The context is appended to the transcript with
+	/// [`TranscriptProtocol::context_domain_sep`] before proving, so the
+	/// proof only verifies with [`RangeProof::verify_single_with_context`]
+	/// and the same context.
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove_single_with_context_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		context: &[u8],
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		transcript.context_domain_sep(context);
+		RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, rng)
+	}
 
-		for V in value_commitments.iter() {
-			// Allow the commitments to be zero (0 value, 0 blinding)
-			// See https://github.com/dalek-cryptography/bulletproofs/pull/248#discussion_r255167177
-			transcript.append_point(b"V", V);
-		}
+	/// Create a rangeproof bound to the application-defined `context`.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_with_context_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_with_context(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		context: &[u8],
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_single_with_context_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			context,
+			v,
+			v_blinding,
+			n,
+			&mut thread_rng(),
+		)
+	}
 
-		transcript.validate_and_append_point(b"A", &self.A)?;
-		transcript.validate_and_append_point(b"S", &self.S)?;
+	/// Verifies a rangeproof for a given value commitment \\(V\\), created
+	/// with [`RangeProof::prove_single_with_context`] for the same
+	/// `context`.
+	#[allow(clippy::too_many_arguments)]
+	pub fn verify_single_with_context_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		context: &[u8],
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		transcript.context_domain_sep(context);
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, rng)
+	}
 
-		let y = transcript.challenge_scalar(b"y");
-		let z = transcript.challenge_scalar(b"z");
-		let zz = z * z;
-		let minus_z = -z;
+	/// Verifies a rangeproof bound to the application-defined `context`.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_with_context_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_with_context(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		context: &[u8],
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_with_context_with_rng(bp_gens, pc_gens, transcript, context, V, n, &mut thread_rng())
+	}
 
-		transcript.validate_and_append_point(b"T_1", &self.T_1)?;
-		transcript.validate_and_append_point(b"T_2", &self.T_2)?;
+	/// Verifies an aggregated rangeproof for the given value commitments.
+	pub fn verify_multiple_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_backend_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			value_commitments,
+			n,
+			&msm::Vartime,
+			rng,
+		)
+	}
 
-		let x = transcript.challenge_scalar(b"x");
+	/// Verifies an aggregated rangeproof for the given value commitments.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_multiple(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, value_commitments, n, &mut thread_rng())
+	}
 
-		transcript.append_scalar(b"t_x", &self.t_x);
-		transcript.append_scalar(b"t_x_blinding", &self.t_x_blinding);
-		transcript.append_scalar(b"e_blinding", &self.e_blinding);
+	/// Verifies an aggregated rangeproof for the given value commitments,
+	/// computing the final multiscalar multiplication with `backend`.
+	///
+	/// This accepts the same proofs as
+	/// [`RangeProof::verify_multiple_with_rng`], which uses the
+	/// [`msm::Vartime`] backend.
+	#[allow(clippy::too_many_arguments)]
+	pub fn verify_multiple_with_backend_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		backend: &dyn MsmBackend,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		check_capacity(
+			n,
+			value_commitments.len(),
+			bp_gens.gens_capacity,
+			bp_gens.party_capacity,
+		)?;
+		let c = Scalar::random(rng);
+		let terms = self.verification_terms(transcript, value_commitments, n, Scalar::one(), c)?;
 
-		let w = transcript.challenge_scalar(b"w");
+		check_terms_with_backend(bp_gens, pc_gens, terms, backend)
+	}
 
-		// Challenge value for batching statements to be verified
-		let c = Scalar::random(rng);
+	/// Verifies a rangeproof for a given value commitment \(V\) in
+	/// constant time, as in [`RangeProof::verify_multiple_ct_with_rng`].
+	pub fn verify_single_ct_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_ct_with_rng(bp_gens, pc_gens, transcript, &[*V], n, rng)
+	}
 
-		let (x_sq, x_inv_sq, s) = self.ipp_proof.verification_scalars(n * m, transcript)?;
-		let s_inv = s.iter().rev();
+	/// Verifies a rangeproof for a given value commitment \(V\) in
+	/// constant time.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_ct_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_ct(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_ct_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+	}
 
-		let a = self.ipp_proof.a;
-		let b = self.ipp_proof.b;
+	/// Verifies an aggregated rangeproof for the given value commitments,
+	/// in time that does not depend on which check of the proof fails.
+	///
+	/// This accepts the same proofs as
+	/// [`RangeProof::verify_multiple_with_rng`], but instead of returning
+	/// as soon as a check fails, it records the outcome of every check
+	/// without branching on it, and uses a constant-time multiscalar
+	/// multiplication, which is several times slower.  Any invalid proof
+	/// is rejected with [`ProofError::VerificationError`].  Errors about
+	/// the parameters, such as an unsupported bitsize or too few
+	/// generators, are still returned early, since they do not depend on
+	/// the proof.
+	///
+	/// The points of the proof are decompressed with `curve25519-dalek`,
+	/// which stops early on some invalid encodings, so the time taken can
+	/// still reveal that the proof is not canonically encoded.  Proofs
+	/// decoded with [`RangeProof::from_bytes_strict`] are canonical.
+	pub fn verify_multiple_ct_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		check_capacity(
+			n,
+			value_commitments.len(),
+			bp_gens.gens_capacity,
+			bp_gens.party_capacity,
+		)?;
+		let c = Scalar::random(rng);
+		let bitsizes = vec![n; value_commitments.len()];
+		let terms = self.verification_terms_with_bitsizes(
+			transcript,
+			value_commitments,
+			&bitsizes,
+			Scalar::one(),
+			c,
+			Timing::Constant,
+		)?;
+
+		check_terms_ct(bp_gens, pc_gens, terms)
+	}
 
-		// Construct concat_z_and_2, an iterator of the values of
-		// z^0 * \vec(2)^n || z^1 * \vec(2)^n || ... || z^(m-1) * \vec(2)^n
-		let powers_of_2: Vec<Scalar> = util::exp_iter(Scalar::from(2u64)).take(n).collect();
-		let concat_z_and_2: Vec<Scalar> = util::exp_iter(z)
-			.take(m)
-			.flat_map(|exp_z| powers_of_2.iter().map(move |exp_2| exp_2 * exp_z))
-			.collect();
+	/// Verifies an aggregated rangeproof for the given value commitments
+	/// in constant time.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_ct_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_multiple_ct(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_ct_with_rng(bp_gens, pc_gens, transcript, value_commitments, n, &mut thread_rng())
+	}
 
-		let g = s.iter().map(|s_i| minus_z - a * s_i);
-		let h = s_inv
-			.zip(util::exp_iter(y.invert()))
-			.zip(concat_z_and_2.iter())
-			.map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv));
+	/// Verifies an aggregated rangeproof for the given value commitments,
+	/// where `value_commitments[j]` commits to a `bitsizes[j]`-bit value,
+	/// as created by [`RangeProof::prove_multiple_with_bitsizes_with_rng`].
+	///
+	/// Returns [`ProofError::InvalidBitsize`] if `bitsizes` and
+	/// `value_commitments` have different lengths.
+	pub fn verify_multiple_with_bitsizes_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		bitsizes: &[usize],
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		if value_commitments.len() != bitsizes.len() {
+			return Err(ProofError::InvalidBitsize);
+		}
+		for &n_j in bitsizes {
+			check_capacity(
+				n_j,
+				value_commitments.len(),
+				bp_gens.gens_capacity,
+				bp_gens.party_capacity,
+			)?;
+		}
+		let c = Scalar::random(rng);
+		let terms = self.verification_terms_with_bitsizes(
+			transcript,
+			value_commitments,
+			bitsizes,
+			Scalar::one(),
+			c,
+			Timing::Variable,
+		)?;
+
+		check_terms(bp_gens, pc_gens, terms)
+	}
 
-		let value_commitment_scalars = util::exp_iter(z).take(m).map(|z_exp| c * zz * z_exp);
-		let basepoint_scalar = w * (self.t_x - a * b) + c * (delta(n, m, &y, &z) - self.t_x);
-
-		let mega_check = RistrettoPoint::optional_multiscalar_mul(
-			iter::once(Scalar::one())
-				.chain(iter::once(x))
-				.chain(iter::once(c * x))
-				.chain(iter::once(c * x * x))
-				.chain(x_sq.iter().cloned())
-				.chain(x_inv_sq.iter().cloned())
-				.chain(iter::once(-self.e_blinding - c * self.t_x_blinding))
-				.chain(iter::once(basepoint_scalar))
-				.chain(g)
-				.chain(h)
-				.chain(value_commitment_scalars),
-			iter::once(self.A.decompress())
-				.chain(iter::once(self.S.decompress()))
-				.chain(iter::once(self.T_1.decompress()))
-				.chain(iter::once(self.T_2.decompress()))
-				.chain(self.ipp_proof.L_vec.iter().map(|L| L.decompress()))
-				.chain(self.ipp_proof.R_vec.iter().map(|R| R.decompress()))
-				.chain(iter::once(Some(pc_gens.B_blinding)))
-				.chain(iter::once(Some(pc_gens.B)))
-				.chain(bp_gens.G(n, m).map(|&x| Some(x)))
-				.chain(bp_gens.H(n, m).map(|&x| Some(x)))
-				.chain(value_commitments.iter().map(|V| V.decompress())),
+	/// Verifies an aggregated rangeproof for value commitments with
+	/// different bitsizes.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_with_bitsizes_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_multiple_with_bitsizes(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		bitsizes: &[usize],
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			value_commitments,
+			bitsizes,
+			&mut thread_rng(),
 		)
-		.ok_or_else(|| ProofError::VerificationError)?;
+	}
+
+	/// Verifies an aggregated rangeproof for the given value commitments,
+	/// using the precomputed tables of `vk` for the fixed bases.
+	///
+	/// This accepts the same proofs as
+	/// [`RangeProof::verify_multiple_with_rng`] with the generators `vk` was
+	/// created from, and is faster when many proofs are verified with the
+	/// same key.  The padded bitsize and the number of values must fit in
+	/// the key.
+	pub fn verify_multiple_precomputed_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		vk: &VerificationKey,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		check_capacity(n, value_commitments.len(), vk.gens_capacity(), vk.party_capacity())?;
+		let c = Scalar::random(rng);
+		let terms = self.verification_terms(transcript, value_commitments, n, Scalar::one(), c)?;
+
+		let mega_check = vk
+			.optional_mixed_multiscalar_mul(
+				[terms.B_scalar, terms.B_blinding_scalar],
+				&terms.g_scalars,
+				&terms.h_scalars,
+				terms.n,
+				terms.dynamic_scalars,
+				terms.dynamic_points,
+			)
+			.ok_or(ProofError::VerificationError)?;
 
 		if mega_check.is_identity() {
 			Ok(())
@@ -421,50 +840,633 @@ impl RangeProof {
 		}
 	}
 
-	/// Verifies an aggregated rangeproof for the given value commitments.
+	/// Verifies an aggregated rangeproof for the given value commitments,
+	/// using the precomputed tables of `vk` for the fixed bases.
 	/// This is a convenience wrapper around
-	/// [`RangeProof::verify_multiple_with_rng`], passing in a threadsafe RNG.
+	/// [`RangeProof::verify_multiple_precomputed_with_rng`], passing in a
+	/// threadsafe RNG.
 	#[cfg(feature = "std")]
-	pub fn verify_multiple(
+	pub fn verify_multiple_precomputed(
 		&self,
-		bp_gens: &BulletproofGens,
-		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
+		vk: &VerificationKey,
+		transcript: &mut impl ProofTranscript,
 		value_commitments: &[CompressedRistretto],
 		n: usize,
 	) -> Result<(), ProofError> {
-		self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, value_commitments, n, &mut thread_rng())
+		self.verify_multiple_precomputed_with_rng(vk, transcript, value_commitments, n, &mut thread_rng())
 	}
 
-	/// Serializes the proof into a byte array of \\(2 \lg n + 9\\)
-	/// 32-byte elements, where \\(n\\) is the number of secret bits.
+	/// Verifies a batch of rangeproofs with a single multiscalar
+	/// multiplication.
 	///
-	/// # Layout
+	/// The `k`-th proof is checked against `transcripts[k]`,
+	/// `value_commitments[k]` and bitsize `bitsizes[k]`, exactly as
+	/// [`RangeProof::verify_multiple_with_rng`] would check it.  The
+	/// verification equations of all proofs are combined using random
+	/// weights, so the cost of the multiscalar multiplication over the
+	/// generators is paid only once for the whole batch.
 	///
-	/// The layout of the range proof encoding is:
+	/// Proofs in a batch may have different bitsizes and aggregation
+	/// sizes.  The `bp_gens` must have enough capacity for the largest
+	/// of them.
 	///
-	/// * four compressed Ristretto points \\(A,S,T_1,T_2\\),
-	/// * three scalars \\(t_x, \tilde{t}_x, \tilde{e}\\),
-	/// * \\(n\\) pairs of compressed Ristretto points
-	///   \\(L_0,R_0\dots,L_{n-1},R_{n-1}\\),
-	/// * two scalars \\(a, b\\).
-	pub fn to_bytes(&self) -> Vec<u8> {
-		// 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl.
-		let mut buf = Vec::with_capacity(7 * 32 + self.ipp_proof.serialized_size());
-		buf.extend_from_slice(self.A.as_bytes());
-		buf.extend_from_slice(self.S.as_bytes());
-		buf.extend_from_slice(self.T_1.as_bytes());
-		buf.extend_from_slice(self.T_2.as_bytes());
-		buf.extend_from_slice(self.t_x.as_bytes());
-		buf.extend_from_slice(self.t_x_blinding.as_bytes());
-		buf.extend_from_slice(self.e_blinding.as_bytes());
-		buf.extend(self.ipp_proof.to_bytes_iter());
-		buf
-	}
-
-	/// Deserializes the proof from a byte slice.
+	/// If the batch fails to verify, this function does not report
+	/// which proof was invalid; callers who need that information can
+	/// fall back to verifying the proofs individually.
 	///
-	/// Returns an error if the byte slice cannot be parsed into a `RangeProof`.
+	/// With the `rayon` feature, the proofs' transcripts are replayed and
+	/// their terms computed on separate threads, and the multiscalar
+	/// multiplication is split into chunks.
+	pub fn batch_verify_with_rng<T: RngCore + CryptoRng>(
+		proofs: &[RangeProof],
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcripts: &mut [Transcript],
+		value_commitments: &[&[CompressedRistretto]],
+		bitsizes: &[usize],
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		RangeProof::batch_verify_with_backend_with_rng(
+			proofs,
+			bp_gens,
+			pc_gens,
+			transcripts,
+			value_commitments,
+			bitsizes,
+			&msm::Vartime,
+			rng,
+		)
+	}
+
+	/// Verifies a batch of rangeproofs with a single multiscalar
+	/// multiplication, computed with `backend`.
+	///
+	/// This accepts the same batches as
+	/// [`RangeProof::batch_verify_with_rng`], which uses the
+	/// [`msm::Vartime`] backend.
+	#[allow(clippy::too_many_arguments)]
+	pub fn batch_verify_with_backend_with_rng<T: RngCore + CryptoRng>(
+		proofs: &[RangeProof],
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcripts: &mut [Transcript],
+		value_commitments: &[&[CompressedRistretto]],
+		bitsizes: &[usize],
+		backend: &dyn MsmBackend,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let k = proofs.len();
+		if transcripts.len() != k || value_commitments.len() != k || bitsizes.len() != k {
+			return Err(ProofError::WrongNumBatchInputs);
+		}
+		if k == 0 {
+			return Ok(());
+		}
+
+		for (vc, &bitsize) in value_commitments.iter().zip(bitsizes.iter()) {
+			check_capacity(bitsize, vc.len(), bp_gens.gens_capacity, bp_gens.party_capacity)?;
+		}
+
+		let max_n = bitsizes.iter().map(|n| n.next_power_of_two()).max().unwrap_or(0);
+		let max_m = value_commitments
+			.iter()
+			.map(|vc| vc.len().next_power_of_two())
+			.max()
+			.unwrap_or(0);
+
+		// Draw each proof's random weight and challenge up front, so that
+		// the proofs can be processed independently of each other.
+		let randomness: Vec<(Scalar, Scalar)> = (0..k).map(|_| (Scalar::random(rng), Scalar::random(rng))).collect();
+		let add_terms = |mut batch: BatchTerms, terms: Result<VerificationTerms, ProofError>| {
+			terms.map(|terms| {
+				batch.add(terms);
+				batch
+			})
+		};
+
+		#[cfg(not(feature = "rayon"))]
+		let batch = proofs
+			.iter()
+			.zip(transcripts.iter_mut())
+			.zip(value_commitments.iter())
+			.zip(bitsizes.iter())
+			.zip(randomness.iter())
+			.map(|((((proof, transcript), vc), &bitsize), &(weight, c))| {
+				proof.verification_terms(transcript, vc, bitsize, weight, c)
+			})
+			.try_fold(BatchTerms::new(max_n, max_m), add_terms)?;
+
+		#[cfg(feature = "rayon")]
+		let batch = proofs
+			.par_iter()
+			.zip(transcripts.par_iter_mut())
+			.zip(value_commitments.par_iter())
+			.zip(bitsizes.par_iter())
+			.zip(randomness.par_iter())
+			.map(|((((proof, transcript), vc), &bitsize), &(weight, c))| {
+				proof.verification_terms(transcript, vc, bitsize, weight, c)
+			})
+			.try_fold(|| BatchTerms::new(max_n, max_m), add_terms)
+			.try_reduce(|| BatchTerms::new(max_n, max_m), |a, b| Ok(a.merge(b)))?;
+
+		let BatchTerms {
+			dynamic_scalars,
+			dynamic_points,
+			B_scalar,
+			B_blinding_scalar,
+			g_scalars,
+			h_scalars,
+			..
+		} = batch;
+
+		let scalars: Vec<Scalar> = dynamic_scalars
+			.into_iter()
+			.chain(iter::once(B_blinding_scalar))
+			.chain(iter::once(B_scalar))
+			.chain(g_scalars)
+			.chain(h_scalars)
+			.collect();
+		let points: Vec<Option<RistrettoPoint>> = dynamic_points
+			.into_iter()
+			.chain(iter::once(Some(pc_gens.B_blinding)))
+			.chain(iter::once(Some(pc_gens.B)))
+			.chain(bp_gens.G(max_n, max_m).map(|&x| Some(x)))
+			.chain(bp_gens.H(max_n, max_m).map(|&x| Some(x)))
+			.collect();
+		let mega_check = backend
+			.optional_multiscalar_mul(&scalars, &points)
+			.ok_or(ProofError::VerificationError)?;
+
+		if mega_check.is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+
+	/// Verifies a batch of rangeproofs with a single multiscalar
+	/// multiplication.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::batch_verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn batch_verify(
+		proofs: &[RangeProof],
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcripts: &mut [Transcript],
+		value_commitments: &[&[CompressedRistretto]],
+		bitsizes: &[usize],
+	) -> Result<(), ProofError> {
+		RangeProof::batch_verify_with_rng(
+			proofs,
+			bp_gens,
+			pc_gens,
+			transcripts,
+			value_commitments,
+			bitsizes,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Create a proof, for the commitment to `v` with blinding
+	/// `v_blinding`, that `v` lies in `range`, the inclusive interval
+	/// `min..=max`.
+	///
+	/// This proves that both \\(v - min\\) and \\(max - v\\) lie in
+	/// \\([0, 2^n)\\), where `n` is the bitsize of \\(max - min\\), as a
+	/// single proof aggregated over two values.  The commitments to these
+	/// two values are derived from the commitment to `v`, so the verifier
+	/// only needs the commitment returned by this function and the bounds.
+	///
+	/// Returns [`ProofError::InvalidRange`] if `min > max` or if `v` lies
+	/// outside of the interval.
+	pub fn prove_range_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		v: u64,
+		v_blinding: &Scalar,
+		range: RangeInclusive<u64>,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		let (min, max) = range.into_inner();
+		if min > v || v > max {
+			return Err(ProofError::InvalidRange);
+		}
+
+		transcript.interval_domain_sep(min, max);
+
+		let values = [v - min, max - v];
+		let blindings = [*v_blinding, -v_blinding];
+		let (proof, _) = RangeProof::prove_multiple_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&values,
+			&blindings,
+			interval_bitsize(min, max),
+			rng,
+		)?;
+
+		Ok((proof, pc_gens.commit(Scalar::from(v), *v_blinding).compress()))
+	}
+
+	/// Create a proof that the value `v` lies in the inclusive interval
+	/// `range`.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_range_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_range(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		v: u64,
+		v_blinding: &Scalar,
+		range: RangeInclusive<u64>,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_range_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, range, &mut thread_rng())
+	}
+
+	/// Verifies a proof created by [`RangeProof::prove_range_with_rng`]
+	/// that the value committed to in \\(V\\) lies in `range`, the
+	/// inclusive interval `min..=max`.
+	pub fn verify_range_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		range: RangeInclusive<u64>,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let (min, max) = range.into_inner();
+		if min > max {
+			return Err(ProofError::InvalidRange);
+		}
+
+		transcript.interval_domain_sep(min, max);
+
+		// Commitments to v - min and max - v, with blindings
+		// v_blinding and -v_blinding respectively.
+		let V = V.decompress().ok_or(ProofError::FormatError)?;
+		let value_commitments = [
+			(V - Scalar::from(min) * pc_gens.B).compress(),
+			(Scalar::from(max) * pc_gens.B - V).compress(),
+		];
+
+		self.verify_multiple_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&value_commitments,
+			interval_bitsize(min, max),
+			rng,
+		)
+	}
+
+	/// Verifies a proof that the value committed to in \\(V\\) lies in
+	/// the inclusive interval `range`.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_range_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_range(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		range: RangeInclusive<u64>,
+	) -> Result<(), ProofError> {
+		self.verify_range_with_rng(bp_gens, pc_gens, transcript, V, range, &mut thread_rng())
+	}
+
+	/// Create a proof that the signed value `v` lies in
+	/// \\([-2^{n-1}, 2^{n-1})\\), for the commitment to `v` with blinding
+	/// `v_blinding`.
+	///
+	/// A negative value is committed to as the negation of its absolute
+	/// value.  This proves that \\(v + 2^{n-1}\\) lies in \\([0, 2^n)\\),
+	/// with a commitment offset from the commitment to `v`, so the verifier
+	/// only needs the commitment returned by this function.
+	///
+	/// Returns [`ProofError::InvalidRange`] if `v` lies outside of the
+	/// range.
+	pub fn prove_single_signed_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		v: i64,
+		v_blinding: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		if !is_valid_bitsize(n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+		let offset = 1u64 << (n - 1);
+		let shifted = i128::from(v) + i128::from(offset);
+		if shifted < 0 || shifted >> n != 0 {
+			return Err(ProofError::InvalidRange);
+		}
+
+		transcript.signed_domain_sep(n as u64);
+
+		let (proof, _) =
+			RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, shifted as u64, v_blinding, n, rng)?;
+
+		Ok((proof, pc_gens.commit(scalar_from_i64(v), *v_blinding).compress()))
+	}
+
+	/// Create a proof that the signed value `v` lies in
+	/// \\([-2^{n-1}, 2^{n-1})\\).
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_signed_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_signed(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		v: i64,
+		v_blinding: &Scalar,
+		n: usize,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_single_signed_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, &mut thread_rng())
+	}
+
+	/// Verifies a proof created by
+	/// [`RangeProof::prove_single_signed_with_rng`] that the signed value
+	/// committed to in \\(V\\) lies in \\([-2^{n-1}, 2^{n-1})\\).
+	pub fn verify_single_signed_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		if !is_valid_bitsize(n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+
+		transcript.signed_domain_sep(n as u64);
+
+		// The commitment to v + 2^(n-1), with the same blinding.
+		let V = V.decompress().ok_or(ProofError::FormatError)?;
+		let shifted = (V + Scalar::from(1u64 << (n - 1)) * pc_gens.B).compress();
+
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, &shifted, n, rng)
+	}
+
+	/// Verifies a proof that the signed value committed to in \\(V\\)
+	/// lies in \\([-2^{n-1}, 2^{n-1})\\).
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_signed_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_signed(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut impl ProofTranscript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_signed_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+	}
+
+	/// Replays the proof transcript and computes the terms this proof
+	/// contributes to the verification equation, scaled by `weight`.
+	///
+	/// The random challenge `c` combines the proof's two verification
+	/// equations into one.
+	fn verification_terms(
+		&self,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		weight: Scalar,
+		c: Scalar,
+	) -> Result<VerificationTerms, ProofError> {
+		let bitsizes = vec![n; value_commitments.len()];
+		self.verification_terms_with_bitsizes(transcript, value_commitments, &bitsizes, weight, c, Timing::Variable)
+	}
+
+	/// Computes the terms of
+	/// [`verification_terms`](RangeProof::verification_terms)
+	/// for value commitments with the given `bitsizes`, which must have
+	/// the same length, checking the proof with the given `timing`.
+	fn verification_terms_with_bitsizes(
+		&self,
+		transcript: &mut impl ProofTranscript,
+		value_commitments: &[CompressedRistretto],
+		bitsizes: &[usize],
+		weight: Scalar,
+		c: Scalar,
+		timing: Timing,
+	) -> Result<VerificationTerms, ProofError> {
+		if value_commitments.is_empty() {
+			return Err(ProofError::InvalidAggregation);
+		}
+		// The prover pads the aggregation with commitments to zero with a
+		// zero blinding factor, and binds the number of values it pads.
+		padding_domain_sep(transcript, value_commitments.len());
+		let m = value_commitments.len().next_power_of_two();
+		let value_commitments: Vec<CompressedRistretto> = value_commitments
+			.iter()
+			.cloned()
+			.chain(iter::repeat(CompressedRistretto::identity()))
+			.take(m)
+			.collect();
+
+		// First, replay the "interactive" protocol using the proof
+		// data to recompute all challenges.
+		if !bitsizes.iter().all(|&n_j| is_valid_bitsize(n_j)) {
+			return Err(ProofError::InvalidBitsize);
+		}
+		let bitsizes = pad_bitsizes(bitsizes, m);
+
+		bitsizes_domain_sep(transcript, &bitsizes);
+
+		for V in value_commitments.iter() {
+			// Allow the commitments to be zero (0 value, 0 blinding)
+			// See https://github.com/dalek-cryptography/bulletproofs/pull/248#discussion_r255167177
+			transcript.append_point(b"V", V);
+		}
+
+		self.verification_terms_after_commitments(transcript, &value_commitments, &bitsizes, weight, c, timing)
+	}
+
+	/// Continues [`verification_terms`](RangeProof::verification_terms)
+	/// once the domain separator and the `value_commitments`, already padded
+	/// to a power of two, have been appended to the transcript.  The
+	/// `bitsizes` are padded along with the commitments.
+	fn verification_terms_after_commitments<Tr: ProofTranscript>(
+		&self,
+		transcript: &mut Tr,
+		value_commitments: &[CompressedRistretto],
+		bitsizes: &[usize],
+		weight: Scalar,
+		c: Scalar,
+		timing: Timing,
+	) -> Result<VerificationTerms, ProofError> {
+		// Only proofs accepted by `from_bytes_strict` can verify.  In
+		// constant time, the points are checked when they are
+		// decompressed, and the scalars are canonical by construction.
+		if timing == Timing::Variable && !self.is_canonical() {
+			return Err(ProofError::FormatError);
+		}
+
+		let m = value_commitments.len();
+		let padded_n = bitsizes.iter().cloned().max().unwrap_or(0).next_power_of_two();
+
+		// A constant-time verifier records whether the points are valid
+		// rather than returning early.
+		let mut valid = Choice::from(1);
+		let mut append_point = |transcript: &mut Tr, label: &'static [u8], point: &CompressedRistretto| match timing {
+			Timing::Variable => transcript.validate_and_append_point(label, point),
+			Timing::Constant => {
+				valid &= transcript.append_and_check_point(label, point);
+				Ok(())
+			}
+		};
+
+		append_point(transcript, b"A", &self.A)?;
+		append_point(transcript, b"S", &self.S)?;
+
+		let y = transcript.challenge_scalar(b"y");
+		let z = transcript.challenge_scalar(b"z");
+		let zz = z * z;
+		let minus_z = -z;
+
+		append_point(transcript, b"T_1", &self.T_1)?;
+		append_point(transcript, b"T_2", &self.T_2)?;
+
+		let x = transcript.challenge_scalar(b"x");
+
+		transcript.append_scalar(b"t_x", &self.t_x);
+		transcript.append_scalar(b"t_x_blinding", &self.t_x_blinding);
+		transcript.append_scalar(b"e_blinding", &self.e_blinding);
+
+		let w = transcript.challenge_scalar(b"w");
+
+		let (x_sq, x_inv_sq, s) =
+			self.ipp_proof
+				.verification_scalars_with(padded_n * m, transcript, &mut append_point)?;
+		let s_inv = s.iter().rev();
+
+		let a = self.ipp_proof.a;
+		let b = self.ipp_proof.b;
+
+		// Construct concat_z_and_2, an iterator of the values of
+		// z^0 * \vec(2)^n_0 || z^1 * \vec(2)^n_1 || ... || z^(m-1) * \vec(2)^n_(m-1)
+		// where each \vec(2)^n_j is zero-padded to the padded bitsize.
+		let concat_z_and_2: Vec<Scalar> = util::exp_iter(z)
+			.zip(bitsizes.iter())
+			.flat_map(|(exp_z, &n_j)| powers_of_2(n_j).take(padded_n).map(move |exp_2| exp_2 * exp_z))
+			.collect();
+
+		let g_scalars = s.iter().map(|s_i| weight * (minus_z - a * s_i)).collect();
+		let h_scalars = s_inv
+			.zip(util::exp_iter(y.invert()))
+			.zip(concat_z_and_2.iter())
+			.map(|((s_i_inv, exp_y_inv), z_and_2)| weight * (z + exp_y_inv * (zz * z_and_2 - b * s_i_inv)))
+			.collect();
+
+		let value_commitment_scalars = util::exp_iter(z).take(m).map(|z_exp| c * zz * z_exp);
+		let basepoint_scalar =
+			w * (self.t_x - a * b) + c * (delta_with_bitsizes(padded_n, bitsizes, &y, &z) - self.t_x);
+
+		let dynamic_scalars = iter::once(Scalar::one())
+			.chain(iter::once(x))
+			.chain(iter::once(c * x))
+			.chain(iter::once(c * x * x))
+			.chain(x_sq.iter().cloned())
+			.chain(x_inv_sq.iter().cloned())
+			.chain(value_commitment_scalars)
+			.map(|scalar| weight * scalar)
+			.collect();
+		let dynamic_points = iter::once(self.A.decompress())
+			.chain(iter::once(self.S.decompress()))
+			.chain(iter::once(self.T_1.decompress()))
+			.chain(iter::once(self.T_2.decompress()))
+			.chain(self.ipp_proof.L_vec.iter().map(|L| L.decompress()))
+			.chain(self.ipp_proof.R_vec.iter().map(|R| R.decompress()))
+			.chain(value_commitments.iter().map(|V| V.decompress()))
+			.collect();
+
+		Ok(VerificationTerms {
+			n: padded_n,
+			m,
+			valid,
+			dynamic_scalars,
+			dynamic_points,
+			B_scalar: weight * basepoint_scalar,
+			B_blinding_scalar: weight * (-self.e_blinding - c * self.t_x_blinding),
+			g_scalars,
+			h_scalars,
+		})
+	}
+
+	/// Returns the size in bytes of an aggregated proof of `m` values of
+	/// `n` bits each, as returned by [`RangeProof::to_bytes`].
+	///
+	/// Both `n` and `m` are rounded up to a power of two, as by the prover.
+	pub fn size(n: usize, m: usize) -> usize {
+		// 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl,
+		// followed by the L, R points and scalars a, b of the ipp.
+		let lg_nm = Self::padded_nm(n, m).trailing_zeros() as usize;
+		(7 + 2 * lg_nm + 2) * 32
+	}
+
+	/// Returns the number of scalar multiplications performed by the
+	/// verifier of an aggregated proof of `m` values of `n` bits each.
+	///
+	/// Verification is a single multiscalar multiplication of this size,
+	/// so this is the dominant term of its cost.  Batch verification of
+	/// several proofs shares the \\(\mathbf{G}\\), \\(\mathbf{H}\\), \\(B\\)
+	/// and \\(\tilde{B}\\) terms.
+	pub fn verification_cost(n: usize, m: usize) -> usize {
+		let nm = Self::padded_nm(n, m);
+		let lg_nm = nm.trailing_zeros() as usize;
+		// B, B_blinding, G, H, A, S, T1, T2, V, and the L, R points of the ipp.
+		2 + 2 * nm + 4 + m.next_power_of_two() + 2 * lg_nm
+	}
+
+	fn padded_nm(n: usize, m: usize) -> usize {
+		n.next_power_of_two() * m.next_power_of_two()
+	}
+
+	/// Serializes the proof into a byte array of \\(2 \lg n + 9\\)
+	/// 32-byte elements, where \\(n\\) is the number of secret bits.
+	///
+	/// # Layout
+	///
+	/// The layout of the range proof encoding is:
+	///
+	/// * four compressed Ristretto points \\(A,S,T_1,T_2\\),
+	/// * three scalars \\(t_x, \tilde{t}_x, \tilde{e}\\),
+	/// * \\(n\\) pairs of compressed Ristretto points
+	///   \\(L_0,R_0\dots,L_{n-1},R_{n-1}\\),
+	/// * two scalars \\(a, b\\).
+	pub fn to_bytes(&self) -> Vec<u8> {
+		// 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl.
+		let mut buf = Vec::with_capacity(7 * 32 + self.ipp_proof.serialized_size());
+		buf.extend_from_slice(self.A.as_bytes());
+		buf.extend_from_slice(self.S.as_bytes());
+		buf.extend_from_slice(self.T_1.as_bytes());
+		buf.extend_from_slice(self.T_2.as_bytes());
+		buf.extend_from_slice(self.t_x.as_bytes());
+		buf.extend_from_slice(self.t_x_blinding.as_bytes());
+		buf.extend_from_slice(self.e_blinding.as_bytes());
+		buf.extend(self.ipp_proof.to_bytes_iter());
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a `RangeProof`.
 	pub fn from_bytes(slice: &[u8]) -> Result<RangeProof, ProofError> {
 		if slice.len() % 32 != 0 {
 			return Err(ProofError::FormatError);
@@ -473,354 +1475,1920 @@ impl RangeProof {
 			return Err(ProofError::FormatError);
 		}
 
-		use crate::util::read32;
+		use crate::util::read32;
+
+		let A = CompressedRistretto(read32(&slice[0..]));
+		let S = CompressedRistretto(read32(&slice[32..]));
+		let T_1 = CompressedRistretto(read32(&slice[2 * 32..]));
+		let T_2 = CompressedRistretto(read32(&slice[3 * 32..]));
+
+		let t_x = Scalar::from_canonical_bytes(read32(&slice[4 * 32..])).ok_or(ProofError::FormatError)?;
+		let t_x_blinding = Scalar::from_canonical_bytes(read32(&slice[5 * 32..])).ok_or(ProofError::FormatError)?;
+		let e_blinding = Scalar::from_canonical_bytes(read32(&slice[6 * 32..])).ok_or(ProofError::FormatError)?;
+
+		let ipp_proof = InnerProductProof::from_bytes(&slice[7 * 32..])?;
+
+		Ok(RangeProof {
+			A,
+			S,
+			T_1,
+			T_2,
+			t_x,
+			t_x_blinding,
+			e_blinding,
+			ipp_proof,
+		})
+	}
+
+	/// Deserializes the proof from a byte slice, accepting only the
+	/// canonical encoding of a proof.
+	///
+	/// In addition to the checks of [`RangeProof::from_bytes`], returns
+	/// [`ProofError::FormatError`] if any of the points is not the
+	/// canonical encoding of a Ristretto point.  Every proof therefore
+	/// has at most one encoding accepted by this function, and the
+	/// verification functions reject any proof it would not accept, so
+	/// that all nodes of a network agree on whether a given encoding is
+	/// valid.
+	pub fn from_bytes_strict(slice: &[u8]) -> Result<RangeProof, ProofError> {
+		let proof = RangeProof::from_bytes(slice)?;
+		if !proof.is_canonical() {
+			return Err(ProofError::FormatError);
+		}
+		Ok(proof)
+	}
+
+	/// Returns whether all points of the proof decompress and all of its
+	/// scalars are canonical.
+	fn is_canonical(&self) -> bool {
+		[self.A, self.S, self.T_1, self.T_2]
+			.iter()
+			.all(|P| P.decompress().is_some())
+			&& [self.t_x, self.t_x_blinding, self.e_blinding]
+				.iter()
+				.all(|s| s.is_canonical())
+			&& self.ipp_proof.is_canonical()
+	}
+
+	/// Serializes the proof into the versioned encoding, which prefixes
+	/// the encoding of [`RangeProof::to_bytes`] with a header.
+	///
+	/// # Layout
+	///
+	/// * a version tag byte, currently `1`,
+	/// * the length of the rest of the encoding as a little-endian `u32`,
+	/// * the encoding of [`RangeProof::to_bytes`].
+	pub fn to_bytes_versioned(&self) -> Vec<u8> {
+		util::encode_versioned(&self.to_bytes())
+	}
+
+	/// Deserializes a proof from the encoding of
+	/// [`RangeProof::to_bytes_versioned`].
+	///
+	/// Returns [`ProofError::FormatError`] if the version is unknown, if
+	/// the length does not match the slice, or if the rest of the slice
+	/// cannot be parsed into a `RangeProof`.
+	pub fn from_bytes_versioned(slice: &[u8]) -> Result<RangeProof, ProofError> {
+		RangeProof::from_bytes(util::decode_versioned(slice).ok_or(ProofError::FormatError)?)
+	}
+}
+
+// The SCALE encoding is the length-prefixed byte encoding from `to_bytes`.
+#[cfg(feature = "scale")]
+impl Encode for RangeProof {
+	fn size_hint(&self) -> usize {
+		let len = 7 * 32 + self.ipp_proof.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for RangeProof {}
+
+#[cfg(feature = "scale")]
+impl Decode for RangeProof {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		RangeProof::from_bytes(&bytes).map_err(|_| "Invalid RangeProof encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for RangeProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for RangeProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid RangeProof")?;
+		RangeProof::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+/// The terms a single rangeproof contributes to the verification
+/// equation, already scaled by the proof's batching weight.
+#[cfg(feature = "alloc")]
+struct VerificationTerms {
+	/// Padded bitsize of the proof.
+	n: usize,
+	/// Aggregation size of the proof.
+	m: usize,
+	/// Whether the checks deferred by a constant-time verifier passed.
+	valid: Choice,
+	/// Scalars for the points that are specific to this proof:
+	/// \\(A, S, T_1, T_2\\), the IPP \\(L\\) and \\(R\\) points
+	/// and the value commitments.
+	dynamic_scalars: Vec<Scalar>,
+	/// The points matching `dynamic_scalars`.
+	dynamic_points: Vec<Option<RistrettoPoint>>,
+	/// Scalar for the Pedersen base \\(B\\).
+	B_scalar: Scalar,
+	/// Scalar for the Pedersen blinding base \\(\tilde{B}\\).
+	B_blinding_scalar: Scalar,
+	/// Scalars for the `n * m` aggregated \\(\mathbf{G}\\) generators.
+	g_scalars: Vec<Scalar>,
+	/// Scalars for the `n * m` aggregated \\(\mathbf{H}\\) generators.
+	h_scalars: Vec<Scalar>,
+}
+
+/// The sum of the verification terms of the proofs in a batch.
+#[cfg(feature = "alloc")]
+struct BatchTerms {
+	/// Largest padded bitsize in the batch.
+	max_n: usize,
+	dynamic_scalars: Vec<Scalar>,
+	dynamic_points: Vec<Option<RistrettoPoint>>,
+	B_scalar: Scalar,
+	B_blinding_scalar: Scalar,
+	/// Scalars for the `max_n * max_m` aggregated \\(\mathbf{G}\\) generators.
+	g_scalars: Vec<Scalar>,
+	/// Scalars for the `max_n * max_m` aggregated \\(\mathbf{H}\\) generators.
+	h_scalars: Vec<Scalar>,
+}
+
+#[cfg(feature = "alloc")]
+impl BatchTerms {
+	fn new(max_n: usize, max_m: usize) -> Self {
+		BatchTerms {
+			max_n,
+			dynamic_scalars: Vec::new(),
+			dynamic_points: Vec::new(),
+			B_scalar: Scalar::zero(),
+			B_blinding_scalar: Scalar::zero(),
+			g_scalars: vec![Scalar::zero(); max_n * max_m],
+			h_scalars: vec![Scalar::zero(); max_n * max_m],
+		}
+	}
+
+	fn add(&mut self, terms: VerificationTerms) {
+		let n = terms.n;
+
+		self.dynamic_scalars.extend(terms.dynamic_scalars);
+		self.dynamic_points.extend(terms.dynamic_points);
+		self.B_scalar += terms.B_scalar;
+		self.B_blinding_scalar += terms.B_blinding_scalar;
+
+		// The generators for party j are laid out at [j * max_n .. (j + 1) * max_n],
+		// so that proofs with smaller n or m use a prefix of each party's segment.
+		for (i, (g, h)) in terms.g_scalars.into_iter().zip(terms.h_scalars).enumerate() {
+			let idx = (i / n) * self.max_n + (i % n);
+			self.g_scalars[idx] += g;
+			self.h_scalars[idx] += h;
+		}
+	}
+
+	#[cfg(feature = "rayon")]
+	fn merge(mut self, other: BatchTerms) -> Self {
+		self.dynamic_scalars.extend(other.dynamic_scalars);
+		self.dynamic_points.extend(other.dynamic_points);
+		self.B_scalar += other.B_scalar;
+		self.B_blinding_scalar += other.B_blinding_scalar;
+		for (acc, g) in self.g_scalars.iter_mut().zip(other.g_scalars) {
+			*acc += g;
+		}
+		for (acc, h) in self.h_scalars.iter_mut().zip(other.h_scalars) {
+			*acc += h;
+		}
+		self
+	}
+}
+
+/// Returns the bitsize of the range proofs used to show that a value
+/// lies in the interval \\([min, max]\\), i.e. the number of bits of
+/// \\(max - min\\).
+#[cfg(feature = "alloc")]
+fn interval_bitsize(min: u64, max: u64) -> usize {
+	cmp::max(1, 64 - (max - min).leading_zeros() as usize)
+}
+
+/// Returns the scalar of the signed value `v`, negative values being
+/// negated scalars.
+#[cfg(feature = "alloc")]
+fn scalar_from_i64(v: i64) -> Scalar {
+	if v < 0 {
+		-Scalar::from(v.unsigned_abs())
+	} else {
+		Scalar::from(v as u64)
+	}
+}
+
+/// Returns whether `n` is a bitsize supported by the range proof,
+/// i.e. \\(1 \le n \le 64\\).
+#[cfg(feature = "alloc")]
+fn is_valid_bitsize(n: usize) -> bool {
+	(1..=64).contains(&n)
+}
+
+/// Checks that `n` is a valid bitsize, and that generators with the given
+/// capacities suffice for an aggregated proof of `m` values of `n` bits,
+/// both padded to a power of two.
+#[cfg(feature = "alloc")]
+fn check_capacity(n: usize, m: usize, gens_capacity: usize, party_capacity: usize) -> Result<(), ProofError> {
+	if !is_valid_bitsize(n) {
+		return Err(ProofError::InvalidBitsize);
+	}
+	if gens_capacity < n.next_power_of_two() || party_capacity < m.next_power_of_two() {
+		return Err(ProofError::InvalidGeneratorsLength);
+	}
+	Ok(())
+}
+
+/// Whether a verifier may return as soon as a check of the proof fails,
+/// or must run all of them in constant time.
+#[cfg(feature = "alloc")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Timing {
+	Variable,
+	Constant,
+}
+
+/// Checks the verification equation of a single proof, given its terms.
+#[cfg(feature = "alloc")]
+fn check_terms(bp_gens: &BulletproofGens, pc_gens: &PedersenGens, terms: VerificationTerms) -> Result<(), ProofError> {
+	check_terms_with_backend(bp_gens, pc_gens, terms, &msm::Vartime)
+}
+
+/// Checks the verification equation of a single proof, given its terms,
+/// computing the multiscalar multiplication with `backend`.
+#[cfg(feature = "alloc")]
+fn check_terms_with_backend(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	terms: VerificationTerms,
+	backend: &dyn MsmBackend,
+) -> Result<(), ProofError> {
+	let scalars: Vec<Scalar> = terms
+		.dynamic_scalars
+		.into_iter()
+		.chain(iter::once(terms.B_blinding_scalar))
+		.chain(iter::once(terms.B_scalar))
+		.chain(terms.g_scalars)
+		.chain(terms.h_scalars)
+		.collect();
+	let points: Vec<Option<RistrettoPoint>> = terms
+		.dynamic_points
+		.into_iter()
+		.chain(iter::once(Some(pc_gens.B_blinding)))
+		.chain(iter::once(Some(pc_gens.B)))
+		.chain(bp_gens.G(terms.n, terms.m).map(|&x| Some(x)))
+		.chain(bp_gens.H(terms.n, terms.m).map(|&x| Some(x)))
+		.collect();
+	let mega_check = backend
+		.optional_multiscalar_mul(&scalars, &points)
+		.ok_or(ProofError::VerificationError)?;
+
+	if mega_check.is_identity() {
+		Ok(())
+	} else {
+		Err(ProofError::VerificationError)
+	}
+}
+
+/// Checks the verification equation of a single proof, given its terms
+/// computed with [`Timing::Constant`], without branching on the proof.
+#[cfg(feature = "alloc")]
+fn check_terms_ct(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	terms: VerificationTerms,
+) -> Result<(), ProofError> {
+	// Points that do not decompress are replaced by the identity, and
+	// make the proof invalid.
+	let mut valid = terms.valid;
+	let dynamic_points: Vec<RistrettoPoint> = terms
+		.dynamic_points
+		.into_iter()
+		.map(|P| {
+			valid &= Choice::from(P.is_some() as u8);
+			P.unwrap_or_else(RistrettoPoint::identity)
+		})
+		.collect();
+
+	let mega_check = RistrettoPoint::multiscalar_mul(
+		terms
+			.dynamic_scalars
+			.into_iter()
+			.chain(iter::once(terms.B_blinding_scalar))
+			.chain(iter::once(terms.B_scalar))
+			.chain(terms.g_scalars)
+			.chain(terms.h_scalars),
+		dynamic_points
+			.iter()
+			.chain(iter::once(&pc_gens.B_blinding))
+			.chain(iter::once(&pc_gens.B))
+			.chain(bp_gens.G(terms.n, terms.m))
+			.chain(bp_gens.H(terms.n, terms.m)),
+	);
+	valid &= mega_check.ct_eq(&RistrettoPoint::identity());
+
+	if bool::from(valid) {
+		Ok(())
+	} else {
+		Err(ProofError::VerificationError)
+	}
+}
+
+/// Returns the powers of two \\(2^0, \ldots, 2^{n-1}\\) followed by zeros,
+/// the coefficients of the bits of an `n`-bit value including the bits
+/// added to pad it to a power of two.
+#[cfg(feature = "alloc")]
+fn powers_of_2(n: usize) -> impl Iterator<Item = Scalar> {
+	util::exp_iter(Scalar::from(2u64))
+		.take(n)
+		.chain(iter::repeat(Scalar::zero()))
+}
+
+/// Compute
+/// \\[
+/// \delta(y,z) = (z - z^{2}) \langle \mathbf{1}, {\mathbf{y}}^{n \cdot m}
+/// \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1}, {\mathbf{2}}^{n
+/// \cdot m} \rangle \\]
+///
+/// where the powers of \\(y\\) run over the bitsize padded to a power of two.
+fn delta(n: usize, m: usize, y: &Scalar, z: &Scalar) -> Scalar {
+	let sum_y = util::sum_of_powers(y, n.next_power_of_two() * m);
+	let sum_2 = util::sum_of_powers(&Scalar::from(2u64), n);
+	let sum_z = util::sum_of_powers(z, m);
+
+	(z - z * z) * sum_y - z * z * z * sum_2 * sum_z
+}
+
+/// Compute \(\delta(y,z)\) as in [`delta`] for an aggregation of values
+/// with the given `bitsizes`, each padded to `padded_n` bits:
+/// \[
+/// \delta(y,z) = (z - z^{2}) \langle \mathbf{1}, {\mathbf{y}}^{n \cdot m}
+/// \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1},
+/// {\mathbf{2}}^{n_j} \rangle \]
+#[cfg(feature = "alloc")]
+fn delta_with_bitsizes(padded_n: usize, bitsizes: &[usize], y: &Scalar, z: &Scalar) -> Scalar {
+	let sum_y = util::sum_of_powers(y, padded_n * bitsizes.len());
+	let sum_z_and_2: Scalar = util::exp_iter(*z)
+		.zip(bitsizes.iter())
+		.map(|(exp_z, &n_j)| exp_z * util::sum_of_powers(&Scalar::from(2u64), n_j))
+		.sum();
+
+	(z - z * z) * sum_y - z * z * z * sum_z_and_2
+}
+
+/// Extends `bitsizes` to `m` entries with the largest of them, the
+/// bitsize given to the values padding an aggregation.
+#[cfg(feature = "alloc")]
+fn pad_bitsizes(bitsizes: &[usize], m: usize) -> Vec<usize> {
+	let n = bitsizes.iter().cloned().max().unwrap_or(0);
+	bitsizes.iter().cloned().chain(iter::repeat(n)).take(m).collect()
+}
+
+/// Appends the number `m` of values of an aggregated proof, if it is not a
+/// power of two and the proof is padded.  Otherwise the proof explicitly
+/// aggregating the padding, with commitments to zero, would also verify
+/// for the `m` values alone.
+#[cfg(feature = "alloc")]
+fn padding_domain_sep(transcript: &mut impl ProofTranscript, m: usize) {
+	if !m.is_power_of_two() {
+		transcript.rangeproof_padding_domain_sep(m as u64);
+	}
+}
+
+/// Appends the domain separator of an aggregated proof of values with the
+/// given `bitsizes`.  Aggregations of values of a single bitsize use the
+/// domain separator of [`RangeProof::prove_multiple_with_rng`], so their
+/// proofs do not depend on which API created them.
+#[cfg(feature = "alloc")]
+fn bitsizes_domain_sep(transcript: &mut impl ProofTranscript, bitsizes: &[usize]) {
+	let n = bitsizes.iter().cloned().max().unwrap_or(0);
+	if bitsizes.iter().all(|&n_j| n_j == n) {
+		transcript.rangeproof_domain_sep(n as u64, bitsizes.len() as u64);
+	} else {
+		transcript.rangeproof_bitsizes_domain_sep(n as u64, bitsizes);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::generators::PedersenGens;
+
+	#[test]
+	fn test_delta() {
+		let mut rng = rand::thread_rng();
+		let y = Scalar::random(&mut rng);
+		let z = Scalar::random(&mut rng);
+
+		// Choose n = 256 to ensure we overflow the group order during
+		// the computation, to check that that's done correctly
+		let n = 256;
+
+		// code copied from previous implementation
+		let z2 = z * z;
+		let z3 = z2 * z;
+		let mut power_g = Scalar::zero();
+		let mut exp_y = Scalar::one(); // start at y^0 = 1
+		let mut exp_2 = Scalar::one(); // start at 2^0 = 1
+		for _ in 0..n {
+			power_g += (z - z2) * exp_y - z3 * exp_2;
+
+			exp_y = exp_y * y; // y^i -> y^(i+1)
+			exp_2 = exp_2 + exp_2; // 2^i -> 2^(i+1)
+		}
+
+		assert_eq!(power_g, delta(n, 1, &y, &z),);
+	}
+
+	/// Given a bitsize `n`, test the following:
+	///
+	/// 1. Generate `m` random values and create a proof they are all in range;
+	/// 2. Serialize to wire format;
+	/// 3. Deserialize from wire format;
+	/// 4. Verify the proof.
+	fn singleparty_create_and_verify_helper(n: usize, m: usize) {
+		// Split the test into two scopes, so that it's explicit what
+		// data is shared between the prover and the verifier.
+
+		// Use bincode for serialization
+		//use bincode; // already present in lib.rs
+
+		// Both prover and verifier have access to the generators and the proof
+		let max_bitsize = 64;
+		let max_parties = 8;
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(max_bitsize, max_parties);
+
+		// Prover's scope
+		// let (proof_bytes, value_commitments) = {
+		//     use self::rand::Rng;
+		//     let mut rng = rand::thread_rng();
+
+		//     // 0. Create witness data
+		//     let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
+		//     let values: Vec<u64> = (0..m).map(|_| rng.gen_range(min,
+		// max)).collect();     let blindings: Vec<Scalar> = (0..m).map(|_|
+		// Scalar::random(&mut rng)).collect();
+
+		//     // 1. Create the proof
+		//     let mut transcript =
+		// Transcript::new(b"AggregatedRangeProofTest");     let (proof,
+		// value_commitments) = RangeProof::prove_multiple(         &bp_gens,
+		//         &pc_gens,
+		//         &mut transcript,
+		//         &values,
+		//         &blindings,
+		//         n,
+		//     )
+		//     .unwrap();
+
+		//     // 2. Return serialized proof and value commitments
+		//     (bincode::serialize(&proof).unwrap(), value_commitments)
+		// };
+
+		// println!("proof byte size = {}", proof_bytes.len());
+
+		// Verifier's scope
+		// {
+		//     // 3. Deserialize
+		//     let proof: RangeProof =
+		// bincode::deserialize(&proof_bytes).unwrap();
+
+		//     // 4. Verify with the same customization label as above
+		//     let mut transcript =
+		// Transcript::new(b"AggregatedRangeProofTest");
+
+		//     assert!(proof
+		//         .verify_multiple(&bp_gens, &pc_gens, &mut transcript,
+		// &value_commitments, n)         .is_ok());
+		// }
+	}
+
+	#[test]
+	fn create_and_verify_n_32_m_1() {
+		singleparty_create_and_verify_helper(32, 1);
+	}
+
+	use std::time::{Duration, Instant};
+
+	#[test]
+	fn create_and_verify_n_32_m_2() {
+		let start = Instant::now();
+		singleparty_create_and_verify_helper(32, 4);
+		println!(
+			"singleparty_create_and_verify_helper time for 2  = {:?}",
+			start.elapsed()
+		);
+	}
+
+	#[test]
+	fn create_and_verify_n_32_m_2_1() {
+		let start = Instant::now();
+		singleparty_create_and_verify_helper(32, 1);
+		singleparty_create_and_verify_helper(32, 1);
+		singleparty_create_and_verify_helper(32, 1);
+		singleparty_create_and_verify_helper(32, 1);
+		println!(
+			"singleparty_create_and_verify_helper time for 2*1  = {:?}",
+			start.elapsed()
+		);
+	}
+
+	#[test]
+	fn create_and_verify_n_32_m_4() {
+		singleparty_create_and_verify_helper(32, 4);
+	}
+
+	#[test]
+	fn create_and_verify_n_32_m_8() {
+		singleparty_create_and_verify_helper(32, 8);
+	}
+
+	#[test]
+	fn create_and_verify_n_64_m_1() {
+		singleparty_create_and_verify_helper(64, 1);
+	}
+
+	#[test]
+	fn create_and_verify_n_64_m_2() {
+		singleparty_create_and_verify_helper(64, 2);
+	}
+
+	#[test]
+	fn create_and_verify_n_64_m_4() {
+		singleparty_create_and_verify_helper(64, 4);
+	}
+
+	#[test]
+	fn create_and_verify_n_64_m_8() {
+		singleparty_create_and_verify_helper(64, 8);
+	}
+
+	#[test]
+	fn detect_dishonest_party_during_aggregation() {
+		use self::{dealer::*, party::*};
+
+		use crate::errors::{MPCError, ShareError};
+
+		// Simulate four parties, two of which will be dishonest and use a 64-bit value.
+		let m = 4;
+		let n = 32;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
+
+		use self::rand::Rng;
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+		// Parties 0, 2 are honest and use a 32-bit value
+		let v0 = rng.gen::<u32>() as u64;
+		let v0_blinding = Scalar::random(&mut rng);
+		let party0 = Party::new(&bp_gens, &pc_gens, v0, v0_blinding, n).unwrap();
+
+		let v2 = rng.gen::<u32>() as u64;
+		let v2_blinding = Scalar::random(&mut rng);
+		let party2 = Party::new(&bp_gens, &pc_gens, v2, v2_blinding, n).unwrap();
+
+		// Parties 1, 3 are dishonest and use a 64-bit value
+		let v1 = rng.gen::<u64>();
+		let v1_blinding = Scalar::random(&mut rng);
+		let party1 = Party::new(&bp_gens, &pc_gens, v1, v1_blinding, n).unwrap();
+
+		let v3 = rng.gen::<u64>();
+		let v3_blinding = Scalar::random(&mut rng);
+		let party3 = Party::new(&bp_gens, &pc_gens, v3, v3_blinding, n).unwrap();
+
+		let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+
+		let (party0, bit_com0) = party0.assign_position(0).unwrap();
+		let (party1, bit_com1) = party1.assign_position(1).unwrap();
+		let (party2, bit_com2) = party2.assign_position(2).unwrap();
+		let (party3, bit_com3) = party3.assign_position(3).unwrap();
+
+		let (dealer, bit_challenge) = dealer
+			.receive_bit_commitments(vec![bit_com0, bit_com1, bit_com2, bit_com3])
+			.unwrap();
 
-		let A = CompressedRistretto(read32(&slice[0..]));
-		let S = CompressedRistretto(read32(&slice[32..]));
-		let T_1 = CompressedRistretto(read32(&slice[2 * 32..]));
-		let T_2 = CompressedRistretto(read32(&slice[3 * 32..]));
+		let (party0, poly_com0) = party0.apply_challenge(&bit_challenge);
+		let (party1, poly_com1) = party1.apply_challenge(&bit_challenge);
+		let (party2, poly_com2) = party2.apply_challenge(&bit_challenge);
+		let (party3, poly_com3) = party3.apply_challenge(&bit_challenge);
 
-		let t_x = Scalar::from_canonical_bytes(read32(&slice[4 * 32..])).ok_or(ProofError::FormatError)?;
-		let t_x_blinding = Scalar::from_canonical_bytes(read32(&slice[5 * 32..])).ok_or(ProofError::FormatError)?;
-		let e_blinding = Scalar::from_canonical_bytes(read32(&slice[6 * 32..])).ok_or(ProofError::FormatError)?;
+		let (dealer, poly_challenge) = dealer
+			.receive_poly_commitments(vec![poly_com0, poly_com1, poly_com2, poly_com3])
+			.unwrap();
 
-		let ipp_proof = InnerProductProof::from_bytes(&slice[7 * 32..])?;
+		let share0 = party0.apply_challenge(&poly_challenge).unwrap();
+		let share1 = party1.apply_challenge(&poly_challenge).unwrap();
+		let share2 = party2.apply_challenge(&poly_challenge).unwrap();
+		let share3 = party3.apply_challenge(&poly_challenge).unwrap();
 
-		Ok(RangeProof {
-			A,
-			S,
-			T_1,
-			T_2,
-			t_x,
-			t_x_blinding,
-			e_blinding,
-			ipp_proof,
-		})
+		match dealer.receive_shares(&[share0, share1, share2, share3]) {
+			Err(MPCError::MalformedProofShares { bad_shares }) => {
+				assert_eq!(bad_shares, vec![
+					(1, ShareError::InconsistentPolyCommitment),
+					(3, ShareError::InconsistentPolyCommitment)
+				]);
+			}
+			Err(_) => {
+				panic!("Got wrong error type from malformed shares");
+			}
+			Ok(_) => {
+				panic!("The proof was malformed, but it was not detected");
+			}
+		}
 	}
-}
 
-#[cfg(feature = "std")]
-impl Serialize for RangeProof {
-	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-	where
-		S: Serializer,
-	{
-		serializer.serialize_bytes(&self.to_bytes()[..])
-	}
-}
+	#[test]
+	fn report_failed_share_checks() {
+		use self::{dealer::*, messages::ProofShare, party::*};
+		use crate::errors::{MPCError, ShareError};
 
-#[cfg(feature = "std")]
-impl<'de> Deserialize<'de> for RangeProof {
-	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-	where
-		D: Deserializer<'de>,
-	{
-		struct RangeProofVisitor;
+		let m = 2;
+		let n = 8;
 
-		impl<'de> Visitor<'de> for RangeProofVisitor {
-			type Value = RangeProof;
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
 
-			fn expecting(&self, formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-				formatter.write_str("a valid RangeProof")
+		// Runs the protocol with two honest parties, letting `tamper`
+		// modify their shares before the dealer receives them.
+		let run = |tamper: &dyn Fn(&mut [ProofShare])| {
+			let mut rng = rand::thread_rng();
+			let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+			let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+			let party0 = Party::new(&bp_gens, &pc_gens, 3, Scalar::random(&mut rng), n).unwrap();
+			let party1 = Party::new(&bp_gens, &pc_gens, 5, Scalar::random(&mut rng), n).unwrap();
+
+			let (party0, bit_com0) = party0.assign_position(0).unwrap();
+			let (party1, bit_com1) = party1.assign_position(1).unwrap();
+			let (dealer, bit_challenge) = dealer.receive_bit_commitments(vec![bit_com0, bit_com1]).unwrap();
+
+			let (party0, poly_com0) = party0.apply_challenge(&bit_challenge);
+			let (party1, poly_com1) = party1.apply_challenge(&bit_challenge);
+			let (dealer, poly_challenge) = dealer.receive_poly_commitments(vec![poly_com0, poly_com1]).unwrap();
+
+			let mut shares = [
+				party0.apply_challenge(&poly_challenge).unwrap(),
+				party1.apply_challenge(&poly_challenge).unwrap(),
+			];
+			tamper(&mut shares);
+			dealer.receive_shares(&shares).unwrap_err()
+		};
+
+		assert_eq!(
+			run(&|shares| {
+				shares[1].l_vec.pop();
+			}),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![(1, ShareError::WrongSize)]
 			}
-
-			fn visit_bytes<E>(self, v: &[u8]) -> Result<RangeProof, E>
-			where
-				E: serde::de::Error,
-			{
-				// Using Error::custom requires T: Display, which our error
-				// type only implements when it implements std::error::Error.
-				#[cfg(feature = "std")]
-				return RangeProof::from_bytes(v).map_err(serde::de::Error::custom);
-				// In no-std contexts, drop the error message.
-				#[cfg(not(feature = "std"))]
-				return RangeProof::from_bytes(v).map_err(|_| serde::de::Error::custom("deserialization error"));
+		);
+		assert_eq!(
+			run(&|shares| shares[0].t_x += Scalar::one()),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![(0, ShareError::InconsistentInnerProduct)]
 			}
-		}
+		);
+		assert_eq!(
+			run(&|shares| shares[1].e_blinding += Scalar::one()),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![(1, ShareError::InconsistentBitCommitment)]
+			}
+		);
+		let err = run(&|shares| shares[0].t_x_blinding += Scalar::one());
+		assert_eq!(err, MPCError::MalformedProofShares {
+			bad_shares: vec![(0, ShareError::InconsistentPolyCommitment)]
+		});
+		assert_eq!(
+			err.to_string(),
+			"Malformed proof shares: party 0: t_x does not match the polynomial commitment;"
+		);
+	}
+
+	#[test]
+	fn audit_finds_malformed_shares_among_many_parties() {
+		use self::{dealer::*, party::*};
+		use crate::errors::{MPCError, ShareError};
+
+		let m = 16;
+		let n = 8;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+		let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+
+		let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+			.map(|j| {
+				let party = Party::new(&bp_gens, &pc_gens, j as u64, Scalar::random(&mut rng), n).unwrap();
+				party.assign_position(j).unwrap()
+			})
+			.unzip();
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+			.into_iter()
+			.map(|party| party.apply_challenge(&bit_challenge))
+			.unzip();
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
 
-		deserializer.deserialize_bytes(RangeProofVisitor)
+		let mut shares: Vec<_> = parties
+			.into_iter()
+			.map(|party| party.apply_challenge(&poly_challenge).unwrap())
+			.collect();
+		shares[2].t_x += Scalar::one();
+		shares[9].e_blinding += Scalar::one();
+		shares[10].t_x_blinding += Scalar::one();
+
+		assert_eq!(
+			dealer.receive_shares(&shares).unwrap_err(),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![
+					(2, ShareError::InconsistentInnerProduct),
+					(9, ShareError::InconsistentBitCommitment),
+					(10, ShareError::InconsistentPolyCommitment),
+				]
+			}
+		);
 	}
-}
 
-/// Compute
-/// \\[
-/// \delta(y,z) = (z - z^{2}) \langle \mathbf{1}, {\mathbf{y}}^{n \cdot m}
-/// \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1}, {\mathbf{2}}^{n
-/// \cdot m} \rangle \\]
-fn delta(n: usize, m: usize, y: &Scalar, z: &Scalar) -> Scalar {
-	let sum_y = util::sum_of_powers(y, n * m);
-	let sum_2 = util::sum_of_powers(&Scalar::from(2u64), n);
-	let sum_z = util::sum_of_powers(z, m);
+	#[test]
+	fn audit_shares_from_recorded_messages() {
+		use self::{dealer::*, messages::*, party::*};
+		use crate::errors::{MPCError, ShareError};
 
-	(z - z * z) * sum_y - z * z * z * sum_2 * sum_z
-}
+		let m = 4;
+		let n = 8;
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+		let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
 
-	use crate::generators::PedersenGens;
+		let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+			.map(|j| {
+				let party = Party::new(&bp_gens, &pc_gens, j as u64, Scalar::random(&mut rng), n).unwrap();
+				party.assign_position(j).unwrap()
+			})
+			.unzip();
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments.clone()).unwrap();
+
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+			.into_iter()
+			.map(|party| party.apply_challenge(&bit_challenge))
+			.unzip();
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments.clone()).unwrap();
+
+		let mut shares: Vec<_> = parties
+			.into_iter()
+			.map(|party| party.apply_challenge(&poly_challenge).unwrap())
+			.collect();
+		shares[3].e_blinding += Scalar::one();
+
+		// The auditor only sees the encoded messages of the protocol.
+		let bit_commitments: Vec<_> = bit_commitments.into_iter().map(BitCommitment::to_bytes).collect();
+		let poly_commitments: Vec<_> = poly_commitments.into_iter().map(PolyCommitment::to_bytes).collect();
+		let recorded_shares: Vec<_> = shares.iter().map(ProofShare::to_bytes).collect();
+		let (bit_challenge, poly_challenge) = (bit_challenge.to_bytes(), poly_challenge.to_bytes());
+
+		let bit_challenge = BitChallenge::from_bytes(&bit_challenge).unwrap();
+		let poly_challenge = PolyChallenge::from_bytes(&poly_challenge).unwrap();
+		let audits: Vec<_> = (0..m)
+			.map(|j| {
+				ProofShare::from_bytes(&recorded_shares[j]).unwrap().audit(
+					&bp_gens,
+					&pc_gens,
+					j,
+					n,
+					&BitCommitment::from_bytes(&bit_commitments[j]).unwrap(),
+					&bit_challenge,
+					&PolyCommitment::from_bytes(&poly_commitments[j]).unwrap(),
+					&poly_challenge,
+				)
+			})
+			.collect();
+		assert_eq!(audits, vec![
+			Ok(()),
+			Ok(()),
+			Ok(()),
+			Err(ShareError::InconsistentBitCommitment)
+		]);
+
+		// The auditor agrees with the dealer.
+		assert_eq!(
+			dealer.receive_shares(&shares).unwrap_err(),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![(3, ShareError::InconsistentBitCommitment)]
+			}
+		);
+	}
 
 	#[test]
-	fn test_delta() {
+	fn detect_dishonest_dealer_during_aggregation() {
+		use self::{dealer::*, party::*};
+		use crate::errors::MPCError;
+
+		// Simulate one party
+		let m = 1;
+		let n = 32;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
+
+		use self::rand::Rng;
 		let mut rng = rand::thread_rng();
-		let y = Scalar::random(&mut rng);
-		let z = Scalar::random(&mut rng);
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
 
-		// Choose n = 256 to ensure we overflow the group order during
-		// the computation, to check that that's done correctly
-		let n = 256;
+		let v0 = rng.gen::<u32>() as u64;
+		let v0_blinding = Scalar::random(&mut rng);
+		let party0 = Party::new(&bp_gens, &pc_gens, v0, v0_blinding, n).unwrap();
 
-		// code copied from previous implementation
-		let z2 = z * z;
-		let z3 = z2 * z;
-		let mut power_g = Scalar::zero();
-		let mut exp_y = Scalar::one(); // start at y^0 = 1
-		let mut exp_2 = Scalar::one(); // start at 2^0 = 1
-		for _ in 0..n {
-			power_g += (z - z2) * exp_y - z3 * exp_2;
+		let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
 
-			exp_y = exp_y * y; // y^i -> y^(i+1)
-			exp_2 = exp_2 + exp_2; // 2^i -> 2^(i+1)
-		}
+		// Now do the protocol flow as normal....
 
-		assert_eq!(power_g, delta(n, 1, &y, &z),);
+		let (party0, bit_com0) = party0.assign_position(0).unwrap();
+
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(vec![bit_com0]).unwrap();
+
+		let (party0, poly_com0) = party0.apply_challenge(&bit_challenge);
+
+		let (_dealer, mut poly_challenge) = dealer.receive_poly_commitments(vec![poly_com0]).unwrap();
+
+		// But now simulate a malicious dealer choosing x = 0
+		poly_challenge.x = Scalar::zero();
+
+		let maybe_share0 = party0.apply_challenge(&poly_challenge);
+
+		assert!(maybe_share0.unwrap_err() == MPCError::MaliciousDealer);
 	}
 
-	/// Given a bitsize `n`, test the following:
-	///
-	/// 1. Generate `m` random values and create a proof they are all in range;
-	/// 2. Serialize to wire format;
-	/// 3. Deserialize from wire format;
-	/// 4. Verify the proof.
-	fn singleparty_create_and_verify_helper(n: usize, m: usize) {
-		// Split the test into two scopes, so that it's explicit what
-		// data is shared between the prover and the verifier.
+	/// Creates `m` random `n`-bit values and an aggregated proof for them.
+	fn batch_helper_prove(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		n: usize,
+		m: usize,
+	) -> (RangeProof, Vec<CompressedRistretto>) {
+		use rand::Rng;
+		let mut rng = rand::thread_rng();
 
-		// Use bincode for serialization
-		//use bincode; // already present in lib.rs
+		let max = ((1u128 << n) - 1) as u64;
+		let values: Vec<u64> = (0..m).map(|_| rng.gen_range(0, max)).collect();
+		let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
 
-		// Both prover and verifier have access to the generators and the proof
-		let max_bitsize = 64;
-		let max_parties = 8;
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		RangeProof::prove_multiple(bp_gens, pc_gens, &mut transcript, &values, &blindings, n).unwrap()
+	}
+
+	#[test]
+	fn padding_is_bound_to_the_number_of_values() {
 		let pc_gens = PedersenGens::default();
-		let bp_gens = BulletproofGens::new(max_bitsize, max_parties);
+		let bp_gens = BulletproofGens::new(32, 4);
+		let blindings = [
+			Scalar::from(1u64),
+			Scalar::from(2u64),
+			Scalar::from(3u64),
+			Scalar::zero(),
+		];
+
+		// A proof of three values does not verify for them and an explicit
+		// commitment to zero, the padding the prover added.
+		let mut transcript = Transcript::new(b"PaddingTest");
+		let (proof, vc) =
+			RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[1, 2, 3], &blindings[..3], 32).unwrap();
+		let mut padded_vc = vc;
+		padded_vc.push(CompressedRistretto::identity());
+		let mut transcript = Transcript::new(b"PaddingTest");
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &padded_vc, 32)
+			.is_err());
+
+		// Nor does a proof of four values, the last of them zero, verify
+		// for the first three alone.
+		let mut transcript = Transcript::new(b"PaddingTest");
+		let (proof, padded_vc) =
+			RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[1, 2, 3, 0], &blindings, 32).unwrap();
+		assert_eq!(padded_vc[3], CompressedRistretto::identity());
+		let mut transcript = Transcript::new(b"PaddingTest");
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &padded_vc[..3], 32)
+			.is_err());
+		let mut transcript = Transcript::new(b"PaddingTest");
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &padded_vc, 32)
+			.is_ok());
+	}
 
-		// Prover's scope
-		// let (proof_bytes, value_commitments) = {
-		//     use self::rand::Rng;
-		//     let mut rng = rand::thread_rng();
+	#[test]
+	fn batch_verify_mixed_sizes() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 8);
 
-		//     // 0. Create witness data
-		//     let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
-		//     let values: Vec<u64> = (0..m).map(|_| rng.gen_range(min,
-		// max)).collect();     let blindings: Vec<Scalar> = (0..m).map(|_|
-		// Scalar::random(&mut rng)).collect();
+		let params = [(8, 1), (64, 4), (32, 2), (16, 8), (64, 1)];
+		let (proofs, commitments): (Vec<_>, Vec<_>) = params
+			.iter()
+			.map(|&(n, m)| batch_helper_prove(&bp_gens, &pc_gens, n, m))
+			.unzip();
+		let commitments: Vec<&[CompressedRistretto]> = commitments.iter().map(|vc| &vc[..]).collect();
+		let bitsizes: Vec<usize> = params.iter().map(|&(n, _)| n).collect();
 
-		//     // 1. Create the proof
-		//     let mut transcript =
-		// Transcript::new(b"AggregatedRangeProofTest");     let (proof,
-		// value_commitments) = RangeProof::prove_multiple(         &bp_gens,
-		//         &pc_gens,
-		//         &mut transcript,
-		//         &values,
-		//         &blindings,
-		//         n,
-		//     )
-		//     .unwrap();
+		let mut transcripts = vec![Transcript::new(b"BatchRangeProofTest"); params.len()];
+		assert!(
+			RangeProof::batch_verify(&proofs, &bp_gens, &pc_gens, &mut transcripts, &commitments, &bitsizes).is_ok()
+		);
 
-		//     // 2. Return serialized proof and value commitments
-		//     (bincode::serialize(&proof).unwrap(), value_commitments)
-		// };
+		// Swapping the commitments of two proofs with the same parameters must fail.
+		let (proof_a, vc_a) = batch_helper_prove(&bp_gens, &pc_gens, 32, 2);
+		let (proof_b, vc_b) = batch_helper_prove(&bp_gens, &pc_gens, 32, 2);
+		let mut transcripts = vec![Transcript::new(b"BatchRangeProofTest"); 2];
+		assert_eq!(
+			RangeProof::batch_verify(
+				&[proof_a, proof_b],
+				&bp_gens,
+				&pc_gens,
+				&mut transcripts,
+				&[&vc_b, &vc_a],
+				&[32, 32],
+			),
+			Err(ProofError::VerificationError)
+		);
+	}
 
-		// println!("proof byte size = {}", proof_bytes.len());
+	#[test]
+	fn verify_with_pippenger_backend() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 2);
+		let backend = msm::Pippenger::new(5).unwrap();
+
+		let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, 32, 2);
+		let verify = |vc: &[CompressedRistretto]| {
+			proof.verify_multiple_with_backend_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"BatchRangeProofTest"),
+				vc,
+				32,
+				&backend,
+				&mut rand::thread_rng(),
+			)
+		};
+		assert!(verify(&vc).is_ok());
+		assert_eq!(verify(&[vc[1], vc[0]]), Err(ProofError::VerificationError));
+
+		let mut transcripts = vec![Transcript::new(b"BatchRangeProofTest"); 2];
+		assert!(RangeProof::batch_verify_with_backend_with_rng(
+			&[proof.clone(), proof.clone()],
+			&bp_gens,
+			&pc_gens,
+			&mut transcripts,
+			&[&vc, &vc],
+			&[32, 32],
+			&backend,
+			&mut rand::thread_rng(),
+		)
+		.is_ok());
+	}
 
-		// Verifier's scope
-		// {
-		//     // 3. Deserialize
-		//     let proof: RangeProof =
-		// bincode::deserialize(&proof_bytes).unwrap();
+	#[test]
+	fn batch_verify_many_proofs() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
 
-		//     // 4. Verify with the same customization label as above
-		//     let mut transcript =
-		// Transcript::new(b"AggregatedRangeProofTest");
+		// Enough proofs to be split across threads with `rayon`.
+		let (proofs, commitments): (Vec<_>, Vec<_>) =
+			(0..24).map(|_| batch_helper_prove(&bp_gens, &pc_gens, 64, 1)).unzip();
+		let commitments: Vec<&[CompressedRistretto]> = commitments.iter().map(|vc| &vc[..]).collect();
+		let bitsizes = vec![64; proofs.len()];
+
+		let mut transcripts = vec![Transcript::new(b"BatchRangeProofTest"); proofs.len()];
+		assert!(
+			RangeProof::batch_verify(&proofs, &bp_gens, &pc_gens, &mut transcripts, &commitments, &bitsizes).is_ok()
+		);
+
+		// A single bad transcript anywhere in the batch must fail it.
+		for &bad in &[0, 13, 23] {
+			let mut transcripts = vec![Transcript::new(b"BatchRangeProofTest"); proofs.len()];
+			transcripts[bad] = Transcript::new(b"Other");
+			assert_eq!(
+				RangeProof::batch_verify(&proofs, &bp_gens, &pc_gens, &mut transcripts, &commitments, &bitsizes),
+				Err(ProofError::VerificationError)
+			);
+		}
+	}
+
+	#[test]
+	fn verify_with_precomputed_key() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
+		let vk = VerificationKey::new(&pc_gens, &bp_gens, 32, 4).unwrap();
+
+		for &(n, m) in &[(32, 4), (32, 1), (8, 2), (5, 1)] {
+			let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, n, m);
+
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			assert!(proof.verify_multiple_precomputed(&vk, &mut transcript, &vc, n).is_ok());
 
-		//     assert!(proof
-		//         .verify_multiple(&bp_gens, &pc_gens, &mut transcript,
-		// &value_commitments, n)         .is_ok());
-		// }
+			let mut transcript = Transcript::new(b"Other");
+			assert_eq!(
+				proof.verify_multiple_precomputed(&vk, &mut transcript, &vc, n),
+				Err(ProofError::VerificationError)
+			);
+		}
+
+		// Proofs that don't fit in the key are rejected.
+		let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, 64, 1);
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			proof.verify_multiple_precomputed(&vk, &mut transcript, &vc, 64),
+			Err(ProofError::InvalidGeneratorsLength)
+		);
+		assert!(VerificationKey::new(&pc_gens, &bp_gens, 128, 1).is_err());
+		assert!(VerificationKey::new(&pc_gens, &bp_gens, 64, 8).is_err());
 	}
 
 	#[test]
-	fn create_and_verify_n_32_m_1() {
-		singleparty_create_and_verify_helper(32, 1);
-	}
+	fn create_and_verify_non_power_of_two_aggregation() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 8);
+
+		for &m in &[3, 5, 6, 7] {
+			let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, 32, m);
+			assert_eq!(vc.len(), m);
+			assert_eq!(proof.to_bytes().len(), RangeProof::size(32, m));
+
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			assert!(proof
+				.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &vc, 32)
+				.is_ok());
+
+			// The number of values is bound to the proof, so passing a
+			// padding commitment explicitly must fail.
+			let mut padded_vc = vc.clone();
+			padded_vc.push(CompressedRistretto::identity());
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			assert!(proof
+				.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &padded_vc, 32)
+				.is_err());
+
+			// Dropping a value must fail.
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			assert!(proof
+				.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &vc[..m - 1], 32)
+				.is_err());
+		}
 
-	use std::time::{Duration, Instant};
+		// The padded aggregation size must fit in the generators.
+		let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, 32, 5);
+		let small_gens = BulletproofGens::new(32, 5);
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			proof.verify_multiple(&small_gens, &pc_gens, &mut transcript, &vc, 32),
+			Err(ProofError::InvalidGeneratorsLength)
+		);
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			RangeProof::prove_multiple(&small_gens, &pc_gens, &mut transcript, &[1; 5], &[Scalar::one(); 5], 32).err(),
+			Some(ProofError::InvalidGeneratorsLength)
+		);
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[], &[], 32).err(),
+			Some(ProofError::InvalidAggregation)
+		);
+	}
 
 	#[test]
-	fn create_and_verify_n_32_m_2() {
-		let start = Instant::now();
-		singleparty_create_and_verify_helper(32, 4);
-		println!(
-			"singleparty_create_and_verify_helper time for 2  = {:?}",
-			start.elapsed()
+	fn batch_verify_wrong_num_inputs() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
+
+		let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, 64, 1);
+		let mut transcripts = vec![Transcript::new(b"BatchRangeProofTest"); 2];
+		assert_eq!(
+			RangeProof::batch_verify(&[proof], &bp_gens, &pc_gens, &mut transcripts, &[&vc], &[64]),
+			Err(ProofError::WrongNumBatchInputs)
 		);
 	}
 
 	#[test]
-	fn create_and_verify_n_32_m_2_1() {
-		let start = Instant::now();
-		singleparty_create_and_verify_helper(32, 1);
-		singleparty_create_and_verify_helper(32, 1);
-		singleparty_create_and_verify_helper(32, 1);
-		singleparty_create_and_verify_helper(32, 1);
-		println!(
-			"singleparty_create_and_verify_helper time for 2*1  = {:?}",
-			start.elapsed()
-		);
+	fn create_and_verify_arbitrary_bitsizes() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
+
+		for &(n, m) in &[(1, 1), (5, 2), (20, 1), (48, 1), (63, 4)] {
+			let (proof, vc) = batch_helper_prove(&bp_gens, &pc_gens, n, m);
+
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			assert!(proof
+				.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &vc, n)
+				.is_ok());
+
+			// The bitsize is bound to the proof, so it does not verify
+			// against the padded bitsize.
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			if n != n.next_power_of_two() {
+				assert!(proof
+					.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &vc, n.next_power_of_two())
+					.is_err());
+			}
+		}
 	}
 
 	#[test]
-	fn create_and_verify_n_32_m_4() {
-		singleparty_create_and_verify_helper(32, 4);
+	fn arbitrary_bitsize_rejects_out_of_range_value() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		// 2^48 does not fit in 48 bits, but fits in the padded 64 bits.
+		let mut transcript = Transcript::new(b"OutOfRangeTest");
+		let (proof, vc) =
+			RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 1 << 48, &blinding, 48).unwrap();
+
+		let mut transcript = Transcript::new(b"OutOfRangeTest");
+		assert_eq!(
+			proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &vc, 48),
+			Err(ProofError::VerificationError)
+		);
 	}
 
 	#[test]
-	fn create_and_verify_n_32_m_8() {
-		singleparty_create_and_verify_helper(32, 8);
+	fn invalid_bitsizes() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(128, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		for &n in &[0, 65, 128] {
+			let mut transcript = Transcript::new(b"InvalidBitsizeTest");
+			assert_eq!(
+				RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 0, &blinding, n).unwrap_err(),
+				ProofError::InvalidBitsize
+			);
+		}
 	}
 
 	#[test]
-	fn create_and_verify_n_64_m_1() {
-		singleparty_create_and_verify_helper(64, 1);
+	fn create_and_verify_heterogeneous_bitsizes() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
+		let mut rng = rand::thread_rng();
+
+		// One 32-bit and two 64-bit amounts, padded to an aggregation of 4.
+		let values = [u32::MAX as u64, u64::MAX, 1 << 40];
+		let bitsizes = [32, 64, 64];
+		let blindings: Vec<_> = (0..3).map(|_| Scalar::random(&mut rng)).collect();
+
+		let mut transcript = Transcript::new(b"HeterogeneousTest");
+		let (proof, vc) = RangeProof::prove_multiple_with_bitsizes(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&values,
+			&blindings,
+			&bitsizes,
+		)
+		.unwrap();
+		assert_eq!(proof.to_bytes().len(), RangeProof::size(64, 3));
+
+		let verify = |bitsizes: &[usize]| {
+			let mut transcript = Transcript::new(b"HeterogeneousTest");
+			proof.verify_multiple_with_bitsizes(&bp_gens, &pc_gens, &mut transcript, &vc, bitsizes)
+		};
+		assert!(verify(&bitsizes).is_ok());
+		assert!(verify(&[64, 64, 64]).is_err());
+		assert!(verify(&[64, 32, 64]).is_err());
+		assert_eq!(verify(&[32, 64]), Err(ProofError::InvalidBitsize));
+
+		// A value that does not fit in its own bitsize is rejected, even
+		// though it fits in the padded bitsize.
+		let mut transcript = Transcript::new(b"HeterogeneousTest");
+		let (proof, vc) = RangeProof::prove_multiple_with_bitsizes(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&[1 << 32, 0],
+			&blindings[..2],
+			&[32, 64],
+		)
+		.unwrap();
+		let mut transcript = Transcript::new(b"HeterogeneousTest");
+		assert_eq!(
+			proof.verify_multiple_with_bitsizes(&bp_gens, &pc_gens, &mut transcript, &vc, &[32, 64]),
+			Err(ProofError::VerificationError)
+		);
 	}
 
 	#[test]
-	fn create_and_verify_n_64_m_2() {
-		singleparty_create_and_verify_helper(64, 2);
+	fn equal_bitsizes_match_prove_multiple() {
+		use rand_chacha::ChaChaRng;
+		use rand_core::SeedableRng;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 2);
+		let blindings = [Scalar::from(7u64), Scalar::from(11u64)];
+
+		let mut transcript = Transcript::new(b"HeterogeneousTest");
+		let (proof, vc) = RangeProof::prove_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&[3, 5],
+			&blindings,
+			32,
+			&mut ChaChaRng::from_seed([1u8; 32]),
+		)
+		.unwrap();
+		let mut transcript = Transcript::new(b"HeterogeneousTest");
+		let (other, _) = RangeProof::prove_multiple_with_bitsizes_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&[3, 5],
+			&blindings,
+			&[32, 32],
+			&mut ChaChaRng::from_seed([1u8; 32]),
+		)
+		.unwrap();
+		assert_eq!(proof.to_bytes(), other.to_bytes());
+
+		let mut transcript = Transcript::new(b"HeterogeneousTest");
+		assert!(proof
+			.verify_multiple_with_bitsizes(&bp_gens, &pc_gens, &mut transcript, &vc, &[32, 32])
+			.is_ok());
 	}
 
 	#[test]
-	fn create_and_verify_n_64_m_4() {
-		singleparty_create_and_verify_helper(64, 4);
+	fn detect_dishonest_party_with_heterogeneous_bitsizes() {
+		use self::{dealer::*, party::*};
+
+		use crate::errors::{MPCError, ShareError};
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+		// Party 0 is honest, party 1 uses a value that only fits in the
+		// bits padding its 16-bit range to 64 bits.
+		let party0 = Party::new_with_padding(&bp_gens, &pc_gens, u64::MAX, Scalar::random(&mut rng), 64, 64).unwrap();
+		let party1 = Party::new_with_padding(&bp_gens, &pc_gens, 1 << 16, Scalar::random(&mut rng), 16, 64).unwrap();
+		assert!(Party::new_with_padding(&bp_gens, &pc_gens, 0, Scalar::zero(), 16, 8).is_err());
+
+		let dealer = Dealer::new_with_bitsizes(&bp_gens, &pc_gens, &mut transcript, &[64, 16]).unwrap();
+
+		let (party0, bit_com0) = party0.assign_position(0).unwrap();
+		let (party1, bit_com1) = party1.assign_position(1).unwrap();
+
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(vec![bit_com0, bit_com1]).unwrap();
+
+		let (party0, poly_com0) = party0.apply_challenge(&bit_challenge);
+		let (party1, poly_com1) = party1.apply_challenge(&bit_challenge);
+
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(vec![poly_com0, poly_com1]).unwrap();
+
+		let share0 = party0.apply_challenge(&poly_challenge).unwrap();
+		let share1 = party1.apply_challenge(&poly_challenge).unwrap();
+
+		assert_eq!(
+			dealer.receive_shares(&[share0, share1]).unwrap_err(),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![(1, ShareError::InconsistentPolyCommitment)]
+			}
+		);
 	}
 
 	#[test]
-	fn create_and_verify_n_64_m_8() {
-		singleparty_create_and_verify_helper(64, 8);
+	fn aggregate_over_sessions() {
+		use self::{party::*, session::*};
+
+		use crate::errors::MPCError;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let mut rng = rand::thread_rng();
+		let bitsizes = [32, 64];
+		let values = [7u64, 1 << 50];
+
+		let mut transcript = Transcript::new(b"SessionTest");
+		let mut dealer = DealerSession::new(&bp_gens, &pc_gens, &mut transcript, &bitsizes).unwrap();
+
+		// Each party starts its session and sends its bit commitment.
+		let mut parties = Vec::new();
+		let mut messages = Vec::new();
+		let mut commitments = Vec::new();
+		for j in 0..2 {
+			let v_blinding = Scalar::random(&mut rng);
+			commitments.push(pc_gens.commit(values[j].into(), v_blinding).compress());
+			let party = Party::new_with_padding(&bp_gens, &pc_gens, values[j], v_blinding, bitsizes[j], 64).unwrap();
+			let (party, message) = PartySession::start(party, j).unwrap();
+			parties.push(party);
+			messages.push(message);
+		}
+
+		// Run the rounds, sending the parties' messages in reverse order.
+		let proof = 'rounds: loop {
+			let mut challenge = None;
+			for j in (0..2).rev() {
+				match dealer.receive(j, &messages[j]).unwrap() {
+					DealerOutput::Pending => assert_eq!(j, 1),
+					DealerOutput::Broadcast(bytes) => challenge = Some(bytes),
+					DealerOutput::Proof(proof) => break 'rounds proof,
+				}
+			}
+			let challenge = challenge.unwrap();
+			assert_eq!(parties[0].receive(&challenge[1..]), Err(MPCError::FormatError));
+			messages = parties.iter_mut().map(|p| p.receive(&challenge).unwrap()).collect();
+		};
+		assert!(matches!(
+			dealer.receive(0, &messages[0]),
+			Err(MPCError::UnexpectedMessage)
+		));
+		assert_eq!(parties[0].receive(&[0u8; 32]), Err(MPCError::UnexpectedMessage));
+
+		let mut transcript = Transcript::new(b"SessionTest");
+		assert!(proof
+			.verify_multiple_with_bitsizes(&bp_gens, &pc_gens, &mut transcript, &commitments, &bitsizes)
+			.is_ok());
 	}
 
 	#[test]
-	fn detect_dishonest_party_during_aggregation() {
+	fn drop_unresponsive_parties() {
 		use self::{dealer::*, party::*};
+		use curve25519_dalek::ristretto::CompressedRistretto;
+
+		let m = 4;
+		let n = 16;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"DropoutTest");
+
+		let values = [3u64, 5, 7, 11];
+		let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+		let mut parties = Vec::new();
+		let mut bit_commitments = Vec::new();
+		for j in 0..m {
+			let party = Party::new(&bp_gens, &pc_gens, values[j], blindings[j], n).unwrap();
+			let (party, bit_commitment) = party.assign_position(j).unwrap();
+			parties.push(party);
+			bit_commitments.push(Some(bit_commitment));
+		}
+
+		// Party 1 does not send its bit commitment.
+		bit_commitments[1] = None;
+		let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments_with_dropouts(bit_commitments).unwrap();
+		assert_eq!(dealer.dropped_parties(), &[1]);
+
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) =
+			parties.into_iter().map(|p| p.apply_challenge(&bit_challenge)).unzip();
+
+		// Party 2 does not send its polynomial commitment, so the dealer
+		// restarts the round without it.
+		let poly_commitments = poly_commitments
+			.into_iter()
+			.enumerate()
+			.map(|(j, poly_commitment)| if j == 2 { None } else { Some(poly_commitment) })
+			.collect();
+		let (dealer, bit_challenge) = match dealer.receive_poly_commitments_with_dropouts(poly_commitments).unwrap() {
+			PolyCommitmentRound::Restarted(dealer, bit_challenge) => (*dealer, bit_challenge),
+			PolyCommitmentRound::Complete(..) => panic!("The dealer did not restart the round"),
+		};
+		assert_eq!(dealer.dropped_parties(), &[1, 2]);
+
+		// The remaining parties answer the new challenge, and the dealer
+		// takes their messages in order of position.
+		let mut parties = parties.into_iter();
+		let (party0, poly_com0) = parties.next().unwrap().restart(&pc_gens, &bit_challenge);
+		let (party3, poly_com3) = parties.nth(2).unwrap().restart(&pc_gens, &bit_challenge);
+
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(vec![poly_com0, poly_com3]).unwrap();
+		assert_eq!(dealer.dropped_parties(), &[1, 2]);
+
+		let share0 = party0.apply_challenge(&poly_challenge).unwrap();
+		let share3 = party3.apply_challenge(&poly_challenge).unwrap();
+		let proof = dealer.receive_shares(&[share0, share3]).unwrap();
+
+		// The proof holds for the identity in place of the dropped
+		// parties' commitments.
+		let mut commitments: Vec<CompressedRistretto> = (0..m)
+			.map(|j| pc_gens.commit(values[j].into(), blindings[j]).compress())
+			.collect();
+		let mut transcript = Transcript::new(b"DropoutTest");
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, n)
+			.is_err());
+
+		commitments[1] = CompressedRistretto::identity();
+		commitments[2] = CompressedRistretto::identity();
+		let mut transcript = Transcript::new(b"DropoutTest");
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, n)
+			.is_ok());
+	}
+
+	#[test]
+	fn drop_missing_parties_from_session() {
+		use self::{party::*, session::*};
+		use curve25519_dalek::ristretto::CompressedRistretto;
 
 		use crate::errors::MPCError;
 
-		// Simulate four parties, two of which will be dishonest and use a 64-bit value.
 		let m = 4;
-		let n = 32;
+		let n = 8;
 
 		let pc_gens = PedersenGens::default();
 		let bp_gens = BulletproofGens::new(n, m);
-
-		use self::rand::Rng;
 		let mut rng = rand::thread_rng();
-		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+		let mut transcript = Transcript::new(b"SessionDropoutTest");
+		let mut dealer = DealerSession::new(&bp_gens, &pc_gens, &mut transcript, &[n; 4]).unwrap();
+
+		let mut parties = Vec::new();
+		let mut messages = Vec::new();
+		let mut commitments = Vec::new();
+		for j in 0..m {
+			let v_blinding = Scalar::random(&mut rng);
+			commitments.push(pc_gens.commit((j as u64).into(), v_blinding).compress());
+			let party = Party::new(&bp_gens, &pc_gens, j as u64, v_blinding, n).unwrap();
+			let (party, message) = PartySession::start(party, j).unwrap();
+			parties.push(party);
+			messages.push(message);
+		}
 
-		// Parties 0, 2 are honest and use a 32-bit value
-		let v0 = rng.gen::<u32>() as u64;
-		let v0_blinding = Scalar::random(&mut rng);
-		let party0 = Party::new(&bp_gens, &pc_gens, v0, v0_blinding, n).unwrap();
+		// Party 3 never sends its bit commitment.
+		for (j, message) in messages.iter().enumerate().take(3) {
+			assert!(matches!(dealer.receive(j, message), Ok(DealerOutput::Pending)));
+		}
+		let challenge = match dealer.drop_missing().unwrap() {
+			DealerOutput::Broadcast(challenge) => challenge,
+			_ => panic!("The dealer did not continue without party 3"),
+		};
+		assert_eq!(dealer.dropped_parties(), &[3]);
+		assert!(matches!(
+			dealer.receive(3, &messages[3]),
+			Err(MPCError::UnexpectedMessage)
+		));
+
+		// Party 2 never sends its polynomial commitment, so the dealer
+		// restarts the round without it.
+		let messages: Vec<_> = parties[..3]
+			.iter_mut()
+			.map(|p| p.receive(&challenge).unwrap())
+			.collect();
+		assert!(matches!(
+			dealer.drop_missing(),
+			Err(MPCError::WrongNumPolyCommitments { .. })
+		));
+		for (j, message) in messages.iter().enumerate().take(2) {
+			assert!(matches!(dealer.receive(j, message), Ok(DealerOutput::Pending)));
+		}
+		let challenge = match dealer.drop_missing().unwrap() {
+			DealerOutput::Broadcast(challenge) => challenge,
+			_ => panic!("The dealer did not restart the round"),
+		};
+		assert_eq!(dealer.dropped_parties(), &[2, 3]);
+
+		// The remaining parties answer the new bit challenge, then the
+		// polynomial challenge.
+		let messages: Vec<_> = parties[..2]
+			.iter_mut()
+			.map(|p| p.receive(&challenge).unwrap())
+			.collect();
+		assert!(matches!(dealer.receive(0, &messages[0]), Ok(DealerOutput::Pending)));
+		let challenge = match dealer.receive(1, &messages[1]).unwrap() {
+			DealerOutput::Broadcast(challenge) => challenge,
+			_ => panic!("The dealer did not send the polynomial challenge"),
+		};
+
+		// Parties cannot be dropped once they have the polynomial challenge.
+		let messages: Vec<_> = parties[..2]
+			.iter_mut()
+			.map(|p| p.receive(&challenge).unwrap())
+			.collect();
+		assert!(matches!(dealer.receive(0, &messages[0]), Ok(DealerOutput::Pending)));
+		assert!(matches!(
+			dealer.drop_missing(),
+			Err(MPCError::WrongNumProofShares { expected: 2, actual: 1 })
+		));
+		let proof = match dealer.receive(1, &messages[1]).unwrap() {
+			DealerOutput::Proof(proof) => proof,
+			_ => panic!("The dealer did not assemble the proof"),
+		};
+
+		commitments[2] = CompressedRistretto::identity();
+		commitments[3] = CompressedRistretto::identity();
+		let mut transcript = Transcript::new(b"SessionDropoutTest");
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, n)
+			.is_ok());
+	}
 
-		let v2 = rng.gen::<u32>() as u64;
-		let v2_blinding = Scalar::random(&mut rng);
-		let party2 = Party::new(&bp_gens, &pc_gens, v2, v2_blinding, n).unwrap();
+	#[test]
+	fn message_encodings() {
+		use self::{messages::*, party::*};
 
-		// Parties 1, 3 are dishonest and use a 64-bit value
-		let v1 = rng.gen::<u64>();
-		let v1_blinding = Scalar::random(&mut rng);
-		let party1 = Party::new(&bp_gens, &pc_gens, v1, v1_blinding, n).unwrap();
+		use crate::errors::MPCError;
 
-		let v3 = rng.gen::<u64>();
-		let v3_blinding = Scalar::random(&mut rng);
-		let party3 = Party::new(&bp_gens, &pc_gens, v3, v3_blinding, n).unwrap();
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let bit_challenge = BitChallenge {
+			y: Scalar::from(3u64),
+			z: Scalar::from(5u64),
+		};
+		let poly_challenge = PolyChallenge { x: Scalar::from(7u64) };
+
+		let party = Party::new(&bp_gens, &pc_gens, 9, Scalar::from(11u64), 8).unwrap();
+		let (party, bit_commitment) = party.assign_position(0).unwrap();
+		let (party, poly_commitment) = party.apply_challenge(&bit_challenge);
+		let share = party.apply_challenge(&poly_challenge).unwrap();
+
+		let bytes = bit_commitment.to_bytes();
+		assert_eq!(BitCommitment::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+		let bytes = bit_challenge.to_bytes();
+		assert_eq!(BitChallenge::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+		let bytes = poly_commitment.to_bytes();
+		assert_eq!(PolyCommitment::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+		let bytes = poly_challenge.to_bytes();
+		assert_eq!(PolyChallenge::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+		let bytes = share.to_bytes();
+		assert_eq!(bytes.len(), (3 + 2 * 8) * 32);
+		assert_eq!(ProofShare::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+
+		assert_eq!(ProofShare::from_bytes(&bytes[32..]).unwrap_err(), MPCError::FormatError);
+		assert_eq!(
+			BitChallenge::from_bytes(&[0xff; 64]).unwrap_err(),
+			MPCError::FormatError
+		);
+		assert_eq!(
+			PolyCommitment::from_bytes(&[0xff; 64]).unwrap_err(),
+			MPCError::FormatError
+		);
+	}
+
+	#[test]
+	fn detect_dishonest_party_with_arbitrary_bitsize() {
+		use self::{dealer::*, party::*};
+
+		use crate::errors::{MPCError, ShareError};
+
+		let m = 2;
+		let n = 20;
 
-		let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, m);
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+		// Party 0 is honest, party 1 uses a value that only fits in the padding bits.
+		let party0 = Party::new(&bp_gens, &pc_gens, (1 << n) - 1, Scalar::random(&mut rng), n).unwrap();
+		let party1 = Party::new(&bp_gens, &pc_gens, 1 << n, Scalar::random(&mut rng), n).unwrap();
+
+		let dealer = Dealer::start(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
 
 		let (party0, bit_com0) = party0.assign_position(0).unwrap();
 		let (party1, bit_com1) = party1.assign_position(1).unwrap();
-		let (party2, bit_com2) = party2.assign_position(2).unwrap();
-		let (party3, bit_com3) = party3.assign_position(3).unwrap();
 
-		let (dealer, bit_challenge) = dealer
-			.receive_bit_commitments(vec![bit_com0, bit_com1, bit_com2, bit_com3])
-			.unwrap();
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(vec![bit_com0, bit_com1]).unwrap();
 
 		let (party0, poly_com0) = party0.apply_challenge(&bit_challenge);
 		let (party1, poly_com1) = party1.apply_challenge(&bit_challenge);
-		let (party2, poly_com2) = party2.apply_challenge(&bit_challenge);
-		let (party3, poly_com3) = party3.apply_challenge(&bit_challenge);
 
-		let (dealer, poly_challenge) = dealer
-			.receive_poly_commitments(vec![poly_com0, poly_com1, poly_com2, poly_com3])
-			.unwrap();
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(vec![poly_com0, poly_com1]).unwrap();
 
 		let share0 = party0.apply_challenge(&poly_challenge).unwrap();
 		let share1 = party1.apply_challenge(&poly_challenge).unwrap();
-		let share2 = party2.apply_challenge(&poly_challenge).unwrap();
-		let share3 = party3.apply_challenge(&poly_challenge).unwrap();
 
-		match dealer.receive_shares(&[share0, share1, share2, share3]) {
-			Err(MPCError::MalformedProofShares { bad_shares }) => {
-				assert_eq!(bad_shares, vec![1, 3]);
-			}
-			Err(_) => {
-				panic!("Got wrong error type from malformed shares");
-			}
-			Ok(_) => {
-				panic!("The proof was malformed, but it was not detected");
+		assert_eq!(
+			dealer.receive_shares(&[share0, share1]).unwrap_err(),
+			MPCError::MalformedProofShares {
+				bad_shares: vec![(1, ShareError::InconsistentPolyCommitment)]
 			}
+		);
+	}
+
+	#[test]
+	fn prove_and_verify_intervals() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let mut rng = rand::thread_rng();
+
+		let cases = [
+			(18, 18, 65),
+			(65, 18, 65),
+			(1000, 1000, 1000),
+			(0, 0, u64::MAX),
+			(u64::MAX, 0, u64::MAX),
+			(1 << 40, 1 << 32, (1 << 48) + 5),
+		];
+		for &(v, min, max) in &cases {
+			let blinding = Scalar::random(&mut rng);
+			let mut transcript = Transcript::new(b"IntervalProofTest");
+			let (proof, V) =
+				RangeProof::prove_range(&bp_gens, &pc_gens, &mut transcript, v, &blinding, min..=max).unwrap();
+			assert_eq!(V, pc_gens.commit(Scalar::from(v), blinding).compress());
+
+			let mut transcript = Transcript::new(b"IntervalProofTest");
+			assert!(proof
+				.verify_range(&bp_gens, &pc_gens, &mut transcript, &V, min..=max)
+				.is_ok());
+
+			// The proof does not verify for a different interval.
+			let mut transcript = Transcript::new(b"IntervalProofTest");
+			assert!(proof
+				.verify_range(&bp_gens, &pc_gens, &mut transcript, &V, min..=max.wrapping_add(1))
+				.is_err());
 		}
 	}
 
 	#[test]
-	fn detect_dishonest_dealer_during_aggregation() {
-		use self::{dealer::*, party::*};
-		use crate::errors::MPCError;
+	fn invalid_intervals() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		for &(v, min, max) in &[(17, 18, 65), (66, 18, 65), (20, 65, 18)] {
+			let mut transcript = Transcript::new(b"IntervalProofTest");
+			assert_eq!(
+				RangeProof::prove_range(&bp_gens, &pc_gens, &mut transcript, v, &blinding, min..=max).unwrap_err(),
+				ProofError::InvalidRange
+			);
+		}
+	}
 
-		// Simulate one party
-		let m = 1;
-		let n = 32;
+	#[test]
+	fn prove_and_verify_signed_values() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		let cases = [
+			(-5, 8),
+			(-128, 8),
+			(127, 8),
+			(0, 1),
+			(-1, 1),
+			(i64::MIN, 64),
+			(i64::MAX, 64),
+		];
+		for &(v, n) in &cases {
+			let mut transcript = Transcript::new(b"SignedProofTest");
+			let (proof, V) =
+				RangeProof::prove_single_signed(&bp_gens, &pc_gens, &mut transcript, v, &blinding, n).unwrap();
+			assert_eq!(V, pc_gens.commit(scalar_from_i64(v), blinding).compress());
+
+			let mut transcript = Transcript::new(b"SignedProofTest");
+			assert!(proof
+				.verify_single_signed(&bp_gens, &pc_gens, &mut transcript, &V, n)
+				.is_ok());
+
+			// The proof is not an unsigned proof for the same commitment.
+			let mut transcript = Transcript::new(b"SignedProofTest");
+			assert!(proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &V, n).is_err());
+		}
 
+		for &(v, n) in &[(-129, 8), (128, 8), (1, 1), (-2, 1)] {
+			let mut transcript = Transcript::new(b"SignedProofTest");
+			assert_eq!(
+				RangeProof::prove_single_signed(&bp_gens, &pc_gens, &mut transcript, v, &blinding, n).unwrap_err(),
+				ProofError::InvalidRange
+			);
+		}
+	}
+
+	#[test]
+	fn context_binds_proof() {
 		let pc_gens = PedersenGens::default();
-		let bp_gens = BulletproofGens::new(n, m);
+		let bp_gens = BulletproofGens::new(64, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		let mut transcript = Transcript::new(b"ContextProofTest");
+		let (proof, V) =
+			RangeProof::prove_single_with_context(&bp_gens, &pc_gens, &mut transcript, b"chain-1", 42, &blinding, 32)
+				.unwrap();
+
+		let verify = |context: &[u8]| {
+			let mut transcript = Transcript::new(b"ContextProofTest");
+			proof.verify_single_with_context(&bp_gens, &pc_gens, &mut transcript, context, &V, 32)
+		};
+		assert!(verify(b"chain-1").is_ok());
+		assert!(verify(b"chain-2").is_err());
+		assert!(verify(b"").is_err());
+
+		// Binding the context by hand is equivalent.
+		let mut transcript = Transcript::new(b"ContextProofTest");
+		transcript.context_domain_sep(b"chain-1");
+		assert!(proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 32).is_ok());
+
+		let mut transcript = Transcript::new(b"ContextProofTest");
+		assert!(proof
+			.verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 32)
+			.is_err());
+	}
 
-		use self::rand::Rng;
-		let mut rng = rand::thread_rng();
-		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+	#[test]
+	fn binding_identifies_proof_and_transcript() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+
+		let prove = || {
+			let mut transcript = Transcript::new(b"BindingProofTest");
+			RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 42, &blinding, 32).unwrap()
+		};
+		let (proof, V) = prove();
+		let (other_proof, other_V) = prove();
+		assert_eq!(V, other_V);
+
+		let binding = |proof: &RangeProof, label: &'static [u8]| {
+			let mut transcript = Transcript::new(label);
+			proof.verify_single_with_binding(&bp_gens, &pc_gens, &mut transcript, &V, 32)
+		};
+		let digest = binding(&proof, b"BindingProofTest").unwrap();
+		assert_eq!(binding(&proof, b"BindingProofTest").unwrap(), digest);
+		assert_ne!(binding(&other_proof, b"BindingProofTest").unwrap(), digest);
+		assert_eq!(
+			binding(&proof, b"OtherProofTest").unwrap_err(),
+			ProofError::VerificationError
+		);
+	}
 
-		let v0 = rng.gen::<u32>() as u64;
-		let v0_blinding = Scalar::random(&mut rng);
-		let party0 = Party::new(&bp_gens, &pc_gens, v0, v0_blinding, n).unwrap();
+	#[test]
+	fn versioned_encoding() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 2);
+
+		let (proof, _) = batch_helper_prove(&bp_gens, &pc_gens, 32, 2);
+		let bytes = proof.to_bytes();
+		let versioned = proof.to_bytes_versioned();
+		assert_eq!(versioned[0], 1);
+		assert_eq!(&versioned[1..5], &(bytes.len() as u32).to_le_bytes()[..]);
+		assert_eq!(&versioned[5..], &bytes[..]);
+		assert_eq!(RangeProof::from_bytes_versioned(&versioned).unwrap().to_bytes(), bytes);
+
+		// Unknown versions, truncations and trailing bytes are rejected.
+		let mut unknown = versioned.clone();
+		unknown[0] = 2;
+		assert_eq!(
+			RangeProof::from_bytes_versioned(&unknown).unwrap_err(),
+			ProofError::FormatError
+		);
+		assert!(RangeProof::from_bytes_versioned(&versioned[..versioned.len() - 32]).is_err());
+		let mut trailing = versioned;
+		trailing.push(0);
+		assert!(RangeProof::from_bytes_versioned(&trailing).is_err());
+		assert!(RangeProof::from_bytes_versioned(&[]).is_err());
+	}
 
-		let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+	#[test]
+	fn constant_time_verification() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 2);
+
+		let (proof, value_commitments) = batch_helper_prove(&bp_gens, &pc_gens, 32, 2);
+		let verify_ct = |proof: &RangeProof, value_commitments: &[CompressedRistretto]| {
+			let mut transcript = Transcript::new(b"BatchRangeProofTest");
+			proof.verify_multiple_ct(&bp_gens, &pc_gens, &mut transcript, value_commitments, 32)
+		};
+		assert!(verify_ct(&proof, &value_commitments).is_ok());
+		assert_eq!(
+			verify_ct(&proof, &value_commitments[..1]),
+			Err(ProofError::VerificationError)
+		);
 
-		// Now do the protocol flow as normal....
+		// Failures the variable-time verifier reports differently are
+		// all verification errors.
+		let mut bytes = proof.to_bytes();
+		bytes[..32].copy_from_slice(CompressedRistretto::identity().as_bytes());
+		let identity = RangeProof::from_bytes(&bytes).unwrap();
+		assert_eq!(
+			verify_ct(&identity, &value_commitments),
+			Err(ProofError::VerificationError)
+		);
+		bytes[..32].copy_from_slice(&[0xffu8; 32]);
+		let invalid = RangeProof::from_bytes(&bytes).unwrap();
+		assert_eq!(
+			verify_ct(&invalid, &value_commitments),
+			Err(ProofError::VerificationError)
+		);
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			invalid.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 32),
+			Err(ProofError::FormatError)
+		);
 
-		let (party0, bit_com0) = party0.assign_position(0).unwrap();
+		// Parameter errors do not depend on the proof.
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			proof.verify_multiple_ct(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 64),
+			Err(ProofError::InvalidGeneratorsLength)
+		);
+	}
 
-		let (dealer, bit_challenge) = dealer.receive_bit_commitments(vec![bit_com0]).unwrap();
+	#[test]
+	fn strict_decoding() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 2);
+
+		let (proof, value_commitments) = batch_helper_prove(&bp_gens, &pc_gens, 32, 2);
+		let bytes = proof.to_bytes();
+		assert_eq!(RangeProof::from_bytes_strict(&bytes).unwrap().to_bytes(), bytes);
+
+		// The field modulus is a non-canonical encoding of zero, which
+		// only the lenient decoding accepts.
+		let mut modulus = [0xffu8; 32];
+		modulus[0] = 0xed;
+		modulus[31] = 0x7f;
+		let mut non_canonical = bytes.clone();
+		non_canonical[32..64].copy_from_slice(&modulus);
+		assert_eq!(
+			RangeProof::from_bytes_strict(&non_canonical).unwrap_err(),
+			ProofError::FormatError
+		);
+		let lenient = RangeProof::from_bytes(&non_canonical).unwrap();
+		let mut transcript = Transcript::new(b"BatchRangeProofTest");
+		assert_eq!(
+			lenient.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 32),
+			Err(ProofError::FormatError)
+		);
 
-		let (party0, poly_com0) = party0.apply_challenge(&bit_challenge);
+		// Scalars must be reduced in both decodings.
+		let mut unreduced = bytes;
+		unreduced[4 * 32..5 * 32].copy_from_slice(&[0xffu8; 32]);
+		assert!(RangeProof::from_bytes(&unreduced).is_err());
+		assert!(RangeProof::from_bytes_strict(&unreduced).is_err());
+	}
 
-		let (_dealer, mut poly_challenge) = dealer.receive_poly_commitments(vec![poly_com0]).unwrap();
+	#[test]
+	#[cfg(feature = "scale")]
+	fn scale_roundtrip() {
+		use crate::errors::{MPCError, ShareError};
 
-		// But now simulate a malicious dealer choosing x = 0
-		poly_challenge.x = Scalar::zero();
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
+
+		let (proof, _) = batch_helper_prove(&bp_gens, &pc_gens, 16, 4);
+		let encoded = proof.encode();
+		assert_eq!(encoded.len(), proof.size_hint());
+		assert_eq!(&encoded[2..], &proof.to_bytes()[..]);
+		let decoded = RangeProof::decode(&mut &encoded[..]).unwrap();
+		assert_eq!(decoded.to_bytes(), proof.to_bytes());
+
+		// A truncated encoding must be rejected.
+		assert!(RangeProof::decode(&mut &encoded[..encoded.len() - 1]).is_err());
+
+		let err = ProofError::ProvingError(MPCError::MalformedProofShares {
+			bad_shares: vec![(1, ShareError::WrongSize), (3, ShareError::InconsistentBitCommitment)],
+		});
+		assert_eq!(ProofError::decode(&mut &err.encode()[..]), Ok(err));
+		let err = ProofError::ProvingError(MPCError::WrongNumProofShares { expected: 4, actual: 2 });
+		assert_eq!(ProofError::decode(&mut &err.encode()[..]), Ok(err));
+	}
 
-		let maybe_share0 = party0.apply_challenge(&poly_challenge);
+	#[test]
+	fn size_and_verification_cost() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
 
-		assert!(maybe_share0.unwrap_err() == MPCError::MaliciousDealer);
+		for (n, m) in [(8, 1), (32, 2), (64, 4), (20, 2)].iter() {
+			let (proof, _) = batch_helper_prove(&bp_gens, &pc_gens, *n, *m);
+			assert_eq!(RangeProof::size(*n, *m), proof.to_bytes().len());
+		}
+
+		assert_eq!(RangeProof::size(64, 1), 672);
+		assert_eq!(RangeProof::verification_cost(64, 1), 147);
+		assert_eq!(RangeProof::verification_cost(20, 2), 2 + 2 * 64 + 4 + 2 + 12);
 	}
 }
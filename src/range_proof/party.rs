@@ -13,13 +13,8 @@
 extern crate alloc;
 
 use alloc::vec::Vec;
-use clear_on_drop::clear::Clear;
-use core::iter;
-use curve25519_dalek::{
-	ristretto::{CompressedRistretto, RistrettoPoint},
-	scalar::Scalar,
-	traits::MultiscalarMul,
-};
+use core::{iter, mem};
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use rand_core::{CryptoRng, RngCore};
 
 use crate::{
@@ -46,10 +41,27 @@ impl Party {
 		v_blinding: Scalar,
 		n: usize,
 	) -> Result<PartyAwaitingPosition<'a>, MPCError> {
-		if !(n == 8 || n == 16 || n == 32 || n == 64) {
+		Party::new_with_padding(bp_gens, pc_gens, v, v_blinding, n, n.next_power_of_two())
+	}
+
+	/// Constructs a `PartyAwaitingPosition` for an `n`-bit range in an
+	/// aggregation whose values are padded to `padded_n` bits.
+	///
+	/// When the parties prove ranges of different bitsizes, `padded_n` is
+	/// the largest of them rounded up to a power of two, and must be the
+	/// same for every party.
+	pub fn new_with_padding<'a>(
+		bp_gens: &'a BulletproofGens,
+		pc_gens: &'a PedersenGens,
+		v: u64,
+		v_blinding: Scalar,
+		n: usize,
+		padded_n: usize,
+	) -> Result<PartyAwaitingPosition<'a>, MPCError> {
+		if !super::is_valid_bitsize(n) || !padded_n.is_power_of_two() || padded_n < n || padded_n > 64 {
 			return Err(MPCError::InvalidBitsize);
 		}
-		if bp_gens.gens_capacity < n {
+		if bp_gens.gens_capacity < padded_n {
 			return Err(MPCError::InvalidGeneratorsLength);
 		}
 
@@ -59,11 +71,33 @@ impl Party {
 			bp_gens,
 			pc_gens,
 			n,
+			padded_n,
 			v,
 			v_blinding,
 			V,
 		})
 	}
+
+	/// Constructs the party that the dealer plays in place of a dropped
+	/// party at position `j`.  It proves the range of a zero value
+	/// without any randomness, like the parties padding an aggregation,
+	/// so the dealer can compute all of its messages.
+	pub(super) fn padding<'a>(
+		bp_gens: &'a BulletproofGens,
+		pc_gens: &'a PedersenGens,
+		j: usize,
+		n: usize,
+		padded_n: usize,
+	) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+		let zeros: Vec<Scalar> = iter::repeat(Scalar::zero()).take(padded_n).collect();
+		Party::new_with_padding(bp_gens, pc_gens, 0, Scalar::zero(), n, padded_n)?.assign_position_with_secrets(
+			j,
+			Scalar::zero(),
+			Scalar::zero(),
+			zeros.clone(),
+			zeros,
+		)
+	}
 }
 
 /// A party waiting for the dealer to assign their position in the aggregation.
@@ -71,6 +105,7 @@ pub struct PartyAwaitingPosition<'a> {
 	bp_gens: &'a BulletproofGens,
 	pc_gens: &'a PedersenGens,
 	n: usize,
+	padded_n: usize,
 	v: u64,
 	v_blinding: Scalar,
 	V: CompressedRistretto,
@@ -90,20 +125,52 @@ impl<'a> PartyAwaitingPosition<'a> {
 		self,
 		j: usize,
 		rng: &mut T,
+	) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+		let a_blinding = Scalar::random(rng);
+		let s_blinding = Scalar::random(rng);
+		self.assign_position_with_blindings(j, a_blinding, s_blinding, rng)
+	}
+
+	/// Assigns a position in the aggregated proof to this party, using the
+	/// given blinding factors for \(A\) and \(S\) and drawing the rest
+	/// of the bit commitment's randomness from `rng`.
+	pub(crate) fn assign_position_with_blindings<T: RngCore + CryptoRng>(
+		self,
+		j: usize,
+		a_blinding: Scalar,
+		s_blinding: Scalar,
+		rng: &mut T,
+	) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+		let s_L: Vec<Scalar> = (0..self.padded_n).map(|_| Scalar::random(rng)).collect();
+		let s_R: Vec<Scalar> = (0..self.padded_n).map(|_| Scalar::random(rng)).collect();
+		self.assign_position_with_secrets(j, a_blinding, s_blinding, s_L, s_R)
+	}
+
+	/// Assigns a position in the aggregated proof to this party, using the
+	/// given blinding factors for \(A\) and \(S\) and blinding vectors
+	/// \(\mathbf{s}_L, \mathbf{s}_R\).
+	fn assign_position_with_secrets(
+		self,
+		j: usize,
+		a_blinding: Scalar,
+		s_blinding: Scalar,
+		s_L: Vec<Scalar>,
+		s_R: Vec<Scalar>,
 	) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
 		if self.bp_gens.party_capacity <= j {
 			return Err(MPCError::InvalidGeneratorsLength);
 		}
 
 		let bp_share = self.bp_gens.share(j);
+		// The bits past `n` are padding and are always zero.
+		let padded_n = self.padded_n;
 
-		let a_blinding = Scalar::random(rng);
 		// Compute A = <a_L, G> + <a_R, H> + a_blinding * B_blinding
 		let mut A = self.pc_gens.B_blinding * a_blinding;
 
 		use subtle::{Choice, ConditionallySelectable};
 		let mut i = 0;
-		for (G_i, H_i) in bp_share.G(self.n).zip(bp_share.H(self.n)) {
+		for (G_i, H_i) in bp_share.G(padded_n).zip(bp_share.H(padded_n)) {
 			// If v_i = 0, we add a_L[i] * G[i] + a_R[i] * H[i] = - H[i]
 			// If v_i = 1, we add a_L[i] * G[i] + a_R[i] * H[i] =   G[i]
 			let v_i = Choice::from(((self.v >> i) & 1) as u8);
@@ -113,16 +180,12 @@ impl<'a> PartyAwaitingPosition<'a> {
 			i += 1;
 		}
 
-		let s_blinding = Scalar::random(rng);
-		let s_L: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(rng)).collect();
-		let s_R: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(rng)).collect();
-
 		// Compute S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-		let S = RistrettoPoint::multiscalar_mul(
+		let S = util::multiscalar_mul(
 			iter::once(&s_blinding).chain(s_L.iter()).chain(s_R.iter()),
 			iter::once(&self.pc_gens.B_blinding)
-				.chain(bp_share.G(self.n))
-				.chain(bp_share.H(self.n)),
+				.chain(bp_share.G(padded_n))
+				.chain(bp_share.H(padded_n)),
 		);
 
 		// Return next state and all commitments
@@ -133,6 +196,7 @@ impl<'a> PartyAwaitingPosition<'a> {
 		};
 		let next_state = PartyAwaitingBitChallenge {
 			n: self.n,
+			padded_n,
 			v: self.v,
 			v_blinding: self.v_blinding,
 			pc_gens: self.pc_gens,
@@ -149,8 +213,8 @@ impl<'a> PartyAwaitingPosition<'a> {
 /// Overwrite secrets with null bytes when they go out of scope.
 impl<'a> Drop for PartyAwaitingPosition<'a> {
 	fn drop(&mut self) {
-		self.v.clear();
-		self.v_blinding.clear();
+		util::clear_secret(&mut self.v);
+		util::clear_secret(&mut self.v_blinding);
 	}
 }
 
@@ -158,6 +222,7 @@ impl<'a> Drop for PartyAwaitingPosition<'a> {
 /// and is waiting for the aggregated value challenge from the dealer.
 pub struct PartyAwaitingBitChallenge<'a> {
 	n: usize, // bitsize of the range
+	padded_n: usize,
 	v: u64,
 	v_blinding: Scalar,
 	j: usize,
@@ -169,6 +234,11 @@ pub struct PartyAwaitingBitChallenge<'a> {
 }
 
 impl<'a> PartyAwaitingBitChallenge<'a> {
+	/// Returns the Pedersen generators the party was created with.
+	pub(super) fn pc_gens(&self) -> &'a PedersenGens {
+		self.pc_gens
+	}
+
 	/// Receive a [`BitChallenge`] from the dealer and use it to
 	/// compute commitments to the party's polynomial coefficients.
 	#[cfg(feature = "std")]
@@ -183,7 +253,21 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 		vc: &BitChallenge,
 		rng: &mut T,
 	) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
-		let n = self.n;
+		let t_1_blinding = Scalar::random(rng);
+		let t_2_blinding = Scalar::random(rng);
+		self.apply_challenge_with_blindings(vc, t_1_blinding, t_2_blinding)
+	}
+
+	/// Receive a [`BitChallenge`] from the dealer and use it to
+	/// compute commitments to the party's polynomial coefficients, using
+	/// the given blinding factors for \(T_1\) and \(T_2\).
+	pub(crate) fn apply_challenge_with_blindings(
+		mut self,
+		vc: &BitChallenge,
+		t_1_blinding: Scalar,
+		t_2_blinding: Scalar,
+	) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+		let n = self.padded_n;
 		let offset_y = util::scalar_exp_vartime(&vc.y, (self.j * n) as u64);
 		let offset_z = util::scalar_exp_vartime(&vc.z, self.j as u64);
 
@@ -193,8 +277,7 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 
 		let offset_zz = vc.z * vc.z * offset_z;
 		let mut exp_y = offset_y; // start at y^j
-		let mut exp_2 = Scalar::one(); // start at 2^0 = 1
-		for i in 0..n {
+		for (i, exp_2) in super::powers_of_2(self.n).take(n).enumerate() {
 			let a_L_i = Scalar::from((self.v >> i) & 1);
 			let a_R_i = a_L_i - Scalar::one();
 
@@ -204,20 +287,23 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 			r_poly.1[i] = exp_y * self.s_R[i];
 
 			exp_y *= vc.y; // y^i -> y^(i+1)
-			exp_2 = exp_2 + exp_2; // 2^i -> 2^(i+1)
 		}
 
 		let t_poly = l_poly.inner_product(&r_poly);
 
 		// Generate x by committing to T_1, T_2 (line 49-54)
-		let t_1_blinding = Scalar::random(rng);
-		let t_2_blinding = Scalar::random(rng);
 		let T_1 = self.pc_gens.commit(t_poly.1, t_1_blinding);
 		let T_2 = self.pc_gens.commit(t_poly.2, t_2_blinding);
 
 		let poly_commitment = PolyCommitment { T_1_j: T_1, T_2_j: T_2 };
 
 		let papc = PartyAwaitingPolyChallenge {
+			n: self.n,
+			padded_n: self.padded_n,
+			v: self.v,
+			j: self.j,
+			s_L: mem::take(&mut self.s_L),
+			s_R: mem::take(&mut self.s_R),
 			v_blinding: self.v_blinding,
 			a_blinding: self.a_blinding,
 			s_blinding: self.s_blinding,
@@ -236,29 +322,28 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 /// Overwrite secrets with null bytes when they go out of scope.
 impl<'a> Drop for PartyAwaitingBitChallenge<'a> {
 	fn drop(&mut self) {
-		self.v.clear();
-		self.v_blinding.clear();
-		self.a_blinding.clear();
-		self.s_blinding.clear();
-
-		// Important: due to how ClearOnDrop auto-implements InitializableFromZeroed
-		// for T: Default, calling .clear() on Vec compiles, but does not
-		// clear the content. Instead, it only clears the Vec's header.
-		// Clearing the underlying buffer item-by-item will do the job, but will
-		// keep the header as-is, which is fine since the header does not contain
-		// secrets.
-		for e in self.s_L.iter_mut() {
-			e.clear();
-		}
-		for e in self.s_R.iter_mut() {
-			e.clear();
-		}
+		util::clear_secret(&mut self.v);
+		util::clear_secret(&mut self.v_blinding);
+		util::clear_secret(&mut self.a_blinding);
+		util::clear_secret(&mut self.s_blinding);
+		util::clear_secrets(&mut self.s_L);
+		util::clear_secrets(&mut self.s_R);
 	}
 }
 
 /// A party which has committed to their polynomial coefficents
 /// and is waiting for the polynomial challenge from the dealer.
+///
+/// The party keeps the secrets of its bit commitment until it computes
+/// its proof share, so that it can answer a new [`BitChallenge`] if the
+/// dealer restarts the round without some parties.
 pub struct PartyAwaitingPolyChallenge {
+	n: usize,
+	padded_n: usize,
+	v: u64,
+	j: usize,
+	s_L: Vec<Scalar>,
+	s_R: Vec<Scalar>,
 	offset_zz: Scalar,
 	l_poly: util::VecPoly1,
 	r_poly: util::VecPoly1,
@@ -271,6 +356,46 @@ pub struct PartyAwaitingPolyChallenge {
 }
 
 impl PartyAwaitingPolyChallenge {
+	/// Receive a new [`BitChallenge`] from a dealer which dropped some
+	/// parties and restarted the round of polynomial commitments, and
+	/// recompute the party's polynomial commitments for it.
+	/// This is a convenience wrapper around
+	/// [`PartyAwaitingPolyChallenge::restart_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn restart(self, pc_gens: &PedersenGens, vc: &BitChallenge) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+		self.restart_with_rng(pc_gens, vc, &mut thread_rng())
+	}
+
+	/// Receive a new [`BitChallenge`] from a dealer which dropped some
+	/// parties and restarted the round of polynomial commitments, and
+	/// recompute the party's polynomial commitments for it.
+	///
+	/// The bit commitment is reused.  This is safe because the party has
+	/// not revealed a proof share for the previous challenge, and the
+	/// polynomial commitments are hiding.  `pc_gens` must be the
+	/// generators the party was created with.
+	pub fn restart_with_rng<T: RngCore + CryptoRng>(
+		mut self,
+		pc_gens: &PedersenGens,
+		vc: &BitChallenge,
+		rng: &mut T,
+	) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+		let party = PartyAwaitingBitChallenge {
+			n: self.n,
+			padded_n: self.padded_n,
+			v: self.v,
+			v_blinding: self.v_blinding,
+			j: self.j,
+			pc_gens,
+			a_blinding: self.a_blinding,
+			s_blinding: self.s_blinding,
+			s_L: mem::take(&mut self.s_L),
+			s_R: mem::take(&mut self.s_R),
+		};
+		party.apply_challenge_with_rng(vc, rng)
+	}
+
 	/// Receive a [`PolyChallenge`] from the dealer and compute the
 	/// party's proof share.
 	pub fn apply_challenge(self, pc: &PolyChallenge) -> Result<ProofShare, MPCError> {
@@ -301,11 +426,14 @@ impl PartyAwaitingPolyChallenge {
 /// Overwrite secrets with null bytes when they go out of scope.
 impl Drop for PartyAwaitingPolyChallenge {
 	fn drop(&mut self) {
-		self.v_blinding.clear();
-		self.a_blinding.clear();
-		self.s_blinding.clear();
-		self.t_1_blinding.clear();
-		self.t_2_blinding.clear();
+		util::clear_secret(&mut self.v);
+		util::clear_secrets(&mut self.s_L);
+		util::clear_secrets(&mut self.s_R);
+		util::clear_secret(&mut self.v_blinding);
+		util::clear_secret(&mut self.a_blinding);
+		util::clear_secret(&mut self.s_blinding);
+		util::clear_secret(&mut self.t_1_blinding);
+		util::clear_secret(&mut self.t_2_blinding);
 
 		// Note: polynomials r_poly, l_poly and t_poly
 		// are cleared within their own Drop impls.
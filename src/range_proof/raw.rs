@@ -0,0 +1,138 @@
+//! Verification of single range proofs through a signature without
+//! generic parameters, for host functions and chain extensions.
+//!
+//! A Substrate host function or chain extension can only pass plain data
+//! across the boundary, and is called once per proof, so
+//! [`verify_range_proof_raw`] takes its inputs as bytes and uses the
+//! process-wide generators of [`static_bp_gens`] and [`static_pc_gens`]
+//! rather than creating them per call.
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use merlin::Transcript;
+
+use super::RangeProof;
+use crate::{
+	errors::ProofError,
+	generators::{static_bp_gens, static_pc_gens},
+};
+
+/// The label of the transcripts the proofs are verified against.
+///
+/// The `label` passed to [`verify_range_proof_raw`] is bound to the proof
+/// as its context, so a proof must be created with
+/// [`RangeProof::prove_single_with_context`] on a transcript created with
+/// this label and the same context.
+pub const RAW_TRANSCRIPT_LABEL: &[u8] = b"BulletproofsRaw";
+
+/// Verifies a single range proof, in the encoding of
+/// [`RangeProof::to_bytes`], that the value committed to by `commitment`
+/// is an `n`-bit number, bound to `label`.
+///
+/// The proof must be created with the generators of
+/// [`BulletproofGens::new`](crate::BulletproofGens::new) and
+/// [`PedersenGens::default`](crate::PedersenGens): proofs created with
+/// generators derived from an application label, with
+/// [`BulletproofGens::new_with_label`](crate::BulletproofGens::new_with_label),
+/// do not verify.
+///
+/// Returns [`ProofError::FormatError`] if the proof is malformed,
+/// [`ProofError::InvalidBitsize`] if `n` is not in 1..=64,
+/// [`ProofError::InvalidGeneratorsLength`] if `n` exceeds the capacity of
+/// [`static_bp_gens`], and [`ProofError::VerificationError`] if the proof
+/// is invalid.
+pub fn verify_range_proof_raw(proof: &[u8], commitment: &[u8; 32], n: u8, label: &[u8]) -> Result<(), ProofError> {
+	let proof = RangeProof::from_bytes(proof)?;
+	let mut transcript = Transcript::new(RAW_TRANSCRIPT_LABEL);
+	proof.verify_single_with_context(
+		static_bp_gens(),
+		static_pc_gens(),
+		&mut transcript,
+		label,
+		&CompressedRistretto(*commitment),
+		usize::from(n),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use curve25519_dalek::scalar::Scalar;
+
+	use crate::generators::{BulletproofGens, PedersenGens};
+
+	fn prove(v: u64, n: usize, label: &[u8]) -> (Vec<u8>, [u8; 32]) {
+		let (proof, commitment) = RangeProof::prove_single_with_context(
+			&BulletproofGens::new(64, 1),
+			&PedersenGens::default(),
+			&mut Transcript::new(RAW_TRANSCRIPT_LABEL),
+			label,
+			v,
+			&Scalar::random(&mut rand::thread_rng()),
+			n,
+		)
+		.unwrap();
+		(proof.to_bytes(), commitment.to_bytes())
+	}
+
+	#[test]
+	fn verifies_proofs_bound_to_the_label() {
+		for &n in &[1u8, 8, 16, 32, 40, 64] {
+			let (proof, commitment) = prove(u64::max_value() >> (64 - n), usize::from(n), b"asset 1");
+			assert!(verify_range_proof_raw(&proof, &commitment, n, b"asset 1").is_ok());
+			assert_eq!(
+				verify_range_proof_raw(&proof, &commitment, n, b"asset 2"),
+				Err(ProofError::VerificationError)
+			);
+		}
+	}
+
+	#[test]
+	fn rejects_proofs_with_labelled_gens() {
+		let (proof, commitment) = RangeProof::prove_single_with_context(
+			&BulletproofGens::new_with_label(64, 1, b"RawApp"),
+			&PedersenGens::default(),
+			&mut Transcript::new(RAW_TRANSCRIPT_LABEL),
+			b"label",
+			7,
+			&Scalar::one(),
+			32,
+		)
+		.unwrap();
+		assert_eq!(
+			verify_range_proof_raw(&proof.to_bytes(), &commitment.to_bytes(), 32, b"label"),
+			Err(ProofError::VerificationError)
+		);
+	}
+
+	#[test]
+	fn rejects_invalid_inputs() {
+		let (proof, commitment) = prove(7, 32, b"label");
+		let (_, other) = prove(7, 32, b"label");
+
+		assert_eq!(
+			verify_range_proof_raw(&proof, &other, 32, b"label"),
+			Err(ProofError::VerificationError)
+		);
+		assert_eq!(
+			verify_range_proof_raw(&proof, &commitment, 64, b"label"),
+			Err(ProofError::VerificationError)
+		);
+		assert_eq!(
+			verify_range_proof_raw(&proof, &commitment, 65, b"label"),
+			Err(ProofError::InvalidBitsize)
+		);
+		assert_eq!(
+			verify_range_proof_raw(&proof, &commitment, 0, b"label"),
+			Err(ProofError::InvalidBitsize)
+		);
+		assert_eq!(
+			verify_range_proof_raw(&proof[1..], &commitment, 32, b"label"),
+			Err(ProofError::FormatError)
+		);
+		assert_eq!(
+			verify_range_proof_raw(&proof, &[0xff; 32], 32, b"label"),
+			Err(ProofError::VerificationError)
+		);
+	}
+}
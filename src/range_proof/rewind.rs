@@ -0,0 +1,302 @@
+//! Range proofs that can be rewound to recover the committed value and
+//! blinding factor.
+//!
+//! The prover derives the blinding factors \\(\tilde{a}, \tilde{s},
+//! \tau_1, \tau_2\\) from a rewind key, the transcript and the value
+//! commitment instead of sampling them, and adds the value to
+//! \\(\tilde{a}\\).  Anyone who holds the rewind key can then rederive
+//! them from the proof's transcript and solve
+//! \\(\tilde{e} = \tilde{a} + \tilde{s} x\\) for the value and
+//! \\(\tilde{t}(x) = z^2 \gamma + \tau_1 x + \tau_2 x^2\\) for the
+//! blinding factor \\(\gamma\\).
+//!
+//! The vectors \\(\mathbf{s}_L, \mathbf{s}_R\\) are derived from the same
+//! key, so the proof is a deterministic function of its inputs.  This
+//! prevents the nonces from being reused with different challenges.
+
+#![allow(non_snake_case)]
+
+use alloc::vec;
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+use super::{dealer::Dealer, is_valid_bitsize, party::Party, RangeProof};
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	transcript::{self, TranscriptProtocol},
+	util,
+};
+
+/// The blinding factors of a rewindable proof that the holder of the
+/// rewind key can rederive.
+struct RewindNonces {
+	a_blinding: Scalar,
+	s_blinding: Scalar,
+	t_1_blinding: Scalar,
+	t_2_blinding: Scalar,
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for RewindNonces {
+	fn drop(&mut self) {
+		util::clear_secret(&mut self.a_blinding);
+		util::clear_secret(&mut self.s_blinding);
+		util::clear_secret(&mut self.t_1_blinding);
+		util::clear_secret(&mut self.t_2_blinding);
+	}
+}
+
+impl RewindNonces {
+	/// Derives the nonces of the proof of an `n`-bit value committed to by
+	/// `V`, and returns them with the generator of the remaining randomness.
+	///
+	/// The `transcript` must be in the state the proof starts from.
+	fn derive(transcript: &Transcript, rewind_key: &[u8], n: usize, V: &CompressedRistretto) -> (Self, NonceRng) {
+		// The nonce transcript is keyed with a secret, so it is kept out of
+		// the transcript-debug logs.
+		let mut nonce_transcript = transcript.clone();
+		transcript::unrecorded(|| {
+			nonce_transcript.rewind_domain_sep(n as u64);
+			nonce_transcript.append_point(b"V", V);
+		});
+		nonce_transcript.append_message(b"rewind_key", rewind_key);
+
+		let mut rng = NonceRng(nonce_transcript);
+		let nonces = RewindNonces {
+			a_blinding: Scalar::random(&mut rng),
+			s_blinding: Scalar::random(&mut rng),
+			t_1_blinding: Scalar::random(&mut rng),
+			t_2_blinding: Scalar::random(&mut rng),
+		};
+		(nonces, rng)
+	}
+}
+
+/// A deterministic generator of nonces, squeezed from a transcript keyed
+/// with the rewind key.
+struct NonceRng(Transcript);
+
+impl RngCore for NonceRng {
+	fn next_u32(&mut self) -> u32 {
+		rand_core::impls::next_u32_via_fill(self)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		rand_core::impls::next_u64_via_fill(self)
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		self.0.challenge_bytes(b"nonce", dest);
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+impl CryptoRng for NonceRng {}
+
+impl RangeProof {
+	/// Create a rangeproof for a given pair of value `v` and blinding
+	/// scalar `v_blinding`, from which the holder of `rewind_key` can
+	/// recover both with [`RangeProof::rewind_single`].
+	///
+	/// The proof verifies like any other single rangeproof, and reveals
+	/// nothing about `v` to verifiers who do not hold the rewind key.  All
+	/// of its randomness is derived from `rewind_key`, the transcript and
+	/// the value commitment, so no RNG is needed: proving the same value
+	/// twice with the same inputs produces the same proof.
+	///
+	/// Anyone who holds `rewind_key` can open the commitment, so it must be
+	/// kept as secret as the value itself.
+	pub fn prove_single_with_rewind(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		rewind_key: &[u8],
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		let party = Party::new(bp_gens, pc_gens, v, *v_blinding, n)?;
+		let V = pc_gens.commit(v.into(), *v_blinding).compress();
+		let (nonces, mut rng) = RewindNonces::derive(transcript, rewind_key, n, &V);
+
+		let dealer = Dealer::start(bp_gens, pc_gens, transcript, n, 1)?;
+
+		// Embed the value in the blinding factor of A, from which it can
+		// be recovered as e_blinding - s_blinding * x.
+		let a_blinding = nonces.a_blinding + Scalar::from(v);
+		let (party, bit_commitment) =
+			party.assign_position_with_blindings(0, a_blinding, nonces.s_blinding, &mut rng)?;
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(vec![bit_commitment])?;
+
+		let (party, poly_commitment) =
+			party.apply_challenge_with_blindings(&bit_challenge, nonces.t_1_blinding, nonces.t_2_blinding);
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(vec![poly_commitment])?;
+
+		let proof_share = party.apply_challenge(&poly_challenge)?;
+		let proof = dealer.receive_trusted_shares(&[proof_share])?;
+
+		Ok((proof, V))
+	}
+
+	/// Recovers the value and blinding factor of the value commitment
+	/// \\(V\\) from a proof created by
+	/// [`RangeProof::prove_single_with_rewind`] with the same `rewind_key`.
+	///
+	/// The transcript must be in the same state as the prover's, and is
+	/// left in the state after the challenge \\(x\\).  Returns an error if
+	/// the proof was not created with `rewind_key` for \\(V\\).
+	///
+	/// Rewinding only checks that the recovered opening matches \\(V\\):
+	/// it does not verify the proof.
+	pub fn rewind_single(
+		&self,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		rewind_key: &[u8],
+	) -> Result<(u64, Scalar), ProofError> {
+		if !is_valid_bitsize(n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+		let (nonces, _) = RewindNonces::derive(transcript, rewind_key, n, V);
+
+		// Replay the transcript up to the last challenge the blinding
+		// factors depend on.
+		transcript.rangeproof_domain_sep(n as u64, 1);
+		transcript.append_point(b"V", V);
+		transcript.validate_and_append_point(b"A", &self.A)?;
+		transcript.validate_and_append_point(b"S", &self.S)?;
+
+		let _y = transcript.challenge_scalar(b"y");
+		let z = transcript.challenge_scalar(b"z");
+
+		transcript.validate_and_append_point(b"T_1", &self.T_1)?;
+		transcript.validate_and_append_point(b"T_2", &self.T_2)?;
+
+		let x = transcript.challenge_scalar(b"x");
+
+		let v_scalar = self.e_blinding - nonces.s_blinding * x - nonces.a_blinding;
+		let bytes = v_scalar.as_bytes();
+		if bytes[8..].iter().any(|&byte| byte != 0) {
+			return Err(ProofError::VerificationError);
+		}
+		let mut v_bytes = [0u8; 8];
+		v_bytes.copy_from_slice(&bytes[..8]);
+		let v = u64::from_le_bytes(v_bytes);
+		if n < 64 && v >> n != 0 {
+			return Err(ProofError::VerificationError);
+		}
+
+		let v_blinding = (self.t_x_blinding - nonces.t_1_blinding * x - nonces.t_2_blinding * x * x) * (z * z).invert();
+
+		if pc_gens.commit(Scalar::from(v), v_blinding).compress() != *V {
+			return Err(ProofError::VerificationError);
+		}
+
+		Ok((v, v_blinding))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rewind_recovers_opening() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+		let key = b"wallet rewind key";
+
+		for &(v, n) in [(0u64, 8), (255, 8), (1037578891, 32), (u64::max_value(), 64)].iter() {
+			let (proof, V) = RangeProof::prove_single_with_rewind(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"RewindTest"),
+				v,
+				&blinding,
+				n,
+				key,
+			)
+			.unwrap();
+
+			assert!(proof
+				.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(b"RewindTest"), &V, n)
+				.is_ok());
+			assert_eq!(
+				proof.rewind_single(&pc_gens, &mut Transcript::new(b"RewindTest"), &V, n, key),
+				Ok((v, blinding))
+			);
+		}
+	}
+
+	#[test]
+	fn rewind_requires_key_and_transcript() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 1);
+		let blinding = Scalar::random(&mut rand::thread_rng());
+		let key = b"wallet rewind key";
+
+		let (proof, V) = RangeProof::prove_single_with_rewind(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"RewindTest"),
+			42,
+			&blinding,
+			32,
+			key,
+		)
+		.unwrap();
+
+		assert!(proof
+			.rewind_single(&pc_gens, &mut Transcript::new(b"RewindTest"), &V, 32, b"other key")
+			.is_err());
+		assert!(proof
+			.rewind_single(&pc_gens, &mut Transcript::new(b"Other"), &V, 32, key)
+			.is_err());
+
+		// Proofs made without a rewind key cannot be rewound.
+		let (proof, V) = RangeProof::prove_single(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"RewindTest"),
+			42,
+			&blinding,
+			32,
+		)
+		.unwrap();
+		assert!(proof
+			.rewind_single(&pc_gens, &mut Transcript::new(b"RewindTest"), &V, 32, key)
+			.is_err());
+	}
+
+	#[test]
+	fn proofs_are_deterministic() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 1);
+		let prove = || {
+			RangeProof::prove_single_with_rewind(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"RewindTest"),
+				7,
+				&Scalar::one(),
+				32,
+				b"key",
+			)
+			.unwrap()
+			.0
+			.to_bytes()
+		};
+
+		assert_eq!(prove(), prove());
+	}
+}
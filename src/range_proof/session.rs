@@ -0,0 +1,337 @@
+//! The `session` module wraps the [`dealer`](super::dealer) and
+//! [`party`](super::party) state machines into sessions that consume and
+//! produce encoded messages, so that the aggregation protocol can be run
+//! between machines over a transport of the caller's choosing.
+//!
+//! The sessions do not perform any I/O themselves and never block: each
+//! call handles one received message and returns the messages to send in
+//! response, if any.  An asynchronous application awaits messages from its
+//! transport and feeds them to the session, and a synchronous one can do
+//! the same with blocking reads.
+//!
+//! The protocol has three rounds.  Each party sends the dealer its
+//! [`BitCommitment`], then its [`PolyCommitment`], then its [`ProofShare`],
+//! and once the dealer has received a message from every party it
+//! broadcasts the [`BitChallenge`], then the [`PolyChallenge`], and
+//! finally assembles the [`RangeProof`].
+//!
+//! A dealer which stops waiting for some parties can drop them with
+//! [`DealerSession::drop_missing`] and continue without them, as
+//! described in
+//! [`DealerAwaitingBitCommitments::receive_bit_commitments_with_dropouts`].
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::mem;
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+	errors::MPCError,
+	generators::{BulletproofGens, PedersenGens},
+	range_proof::RangeProof,
+};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{dealer::*, messages::*, party::*};
+
+/// What a [`DealerSession`] produces after receiving a message.
+#[allow(clippy::large_enum_variant)]
+pub enum DealerOutput {
+	/// The dealer is waiting for messages from more parties.
+	Pending,
+	/// Every party has sent its message for the round, and the encoded
+	/// challenge should be broadcast to all of them.
+	Broadcast(Vec<u8>),
+	/// Every party has sent its proof share, and the aggregated proof is
+	/// complete.
+	Proof(RangeProof),
+}
+
+#[allow(clippy::large_enum_variant)]
+enum DealerState<'a, 'b> {
+	AwaitingBitCommitments(DealerAwaitingBitCommitments<'a, 'b>, Vec<Option<BitCommitment>>),
+	AwaitingPolyCommitments(DealerAwaitingPolyCommitments<'a, 'b>, Vec<Option<PolyCommitment>>),
+	AwaitingProofShares(DealerAwaitingProofShares<'a, 'b>, Vec<Option<ProofShare>>),
+	Done,
+}
+
+/// The dealer's side of an aggregation protocol run over a transport.
+pub struct DealerSession<'a, 'b> {
+	m: usize,
+	dropped: Vec<usize>,
+	state: DealerState<'a, 'b>,
+}
+
+impl<'a, 'b> DealerSession<'a, 'b> {
+	/// Starts a session for `bitsizes.len()` parties, where party `j`
+	/// proves a `bitsizes[j]`-bit range, as with
+	/// [`Dealer::new_with_bitsizes`].
+	pub fn new(
+		bp_gens: &'b BulletproofGens,
+		pc_gens: &'b PedersenGens,
+		transcript: &'a mut Transcript,
+		bitsizes: &[usize],
+	) -> Result<Self, MPCError> {
+		let m = bitsizes.len();
+		let dealer = Dealer::new_with_bitsizes(bp_gens, pc_gens, transcript, bitsizes)?;
+		Ok(DealerSession {
+			m,
+			dropped: Vec::new(),
+			state: DealerState::AwaitingBitCommitments(dealer, empty_round(m)),
+		})
+	}
+
+	/// Handles the encoded message `bytes` received from party `j`.
+	/// This is a convenience wrapper around
+	/// [`DealerSession::receive_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn receive(&mut self, j: usize, bytes: &[u8]) -> Result<DealerOutput, MPCError> {
+		self.receive_with_rng(j, bytes, &mut thread_rng())
+	}
+
+	/// Handles the encoded message `bytes` received from party `j`.
+	///
+	/// The message is decoded as the one the dealer expects from the
+	/// parties in the current round, and a party that sends twice in a
+	/// round replaces its earlier message.  Returns
+	/// [`MPCError::UnexpectedMessage`] if there is no party `j`, party
+	/// `j` was dropped, or the session has ended.
+	///
+	/// The session ends when the proof is complete, or when the dealer
+	/// rejects the messages of a round, for instance because some of the
+	/// proof shares were malformed.
+	pub fn receive_with_rng<T: RngCore + CryptoRng>(
+		&mut self,
+		j: usize,
+		bytes: &[u8],
+		rng: &mut T,
+	) -> Result<DealerOutput, MPCError> {
+		if j >= self.m || self.dropped.contains(&j) {
+			return Err(MPCError::UnexpectedMessage);
+		}
+		let dropped = &self.dropped;
+		let complete = match &mut self.state {
+			DealerState::AwaitingBitCommitments(_, round) => {
+				round[j] = Some(BitCommitment::from_bytes(bytes)?);
+				is_complete(round, dropped)
+			}
+			DealerState::AwaitingPolyCommitments(_, round) => {
+				round[j] = Some(PolyCommitment::from_bytes(bytes)?);
+				is_complete(round, dropped)
+			}
+			DealerState::AwaitingProofShares(_, round) => {
+				round[j] = Some(ProofShare::from_bytes(bytes)?);
+				is_complete(round, dropped)
+			}
+			DealerState::Done => return Err(MPCError::UnexpectedMessage),
+		};
+		if !complete {
+			return Ok(DealerOutput::Pending);
+		}
+		self.finish_round(rng)
+	}
+
+	/// Drops the parties which have not sent their message for the
+	/// current round, and continues without them.
+	/// This is a convenience wrapper around
+	/// [`DealerSession::drop_missing_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn drop_missing(&mut self) -> Result<DealerOutput, MPCError> {
+		self.drop_missing_with_rng(&mut thread_rng())
+	}
+
+	/// Drops the parties which have not sent their message for the
+	/// current round, and continues without them.
+	///
+	/// In the round of polynomial commitments, this restarts the round
+	/// with a new [`BitChallenge`] to broadcast to the remaining parties.
+	/// Parties cannot be dropped once others may have sent their proof
+	/// shares, since the shares of the other parties do not make a proof
+	/// without them; in that round this returns
+	/// [`MPCError::WrongNumProofShares`] and the session continues to
+	/// wait.  Likewise, the session continues to wait if no party has
+	/// sent its message for the round.
+	///
+	/// The verifier must be given the identity point in place of the
+	/// value commitments of the dropped parties, whose positions are
+	/// returned by [`DealerSession::dropped_parties`].
+	pub fn drop_missing_with_rng<T: RngCore + CryptoRng>(&mut self, rng: &mut T) -> Result<DealerOutput, MPCError> {
+		let expected = self.m - self.dropped.len();
+		match &self.state {
+			DealerState::AwaitingBitCommitments(_, round) if received(round) == 0 => {
+				Err(MPCError::WrongNumBitCommitments { expected, actual: 0 })
+			}
+			DealerState::AwaitingPolyCommitments(_, round) if received(round) == 0 => {
+				Err(MPCError::WrongNumPolyCommitments { expected, actual: 0 })
+			}
+			DealerState::AwaitingProofShares(_, round) if received(round) < expected => {
+				Err(MPCError::WrongNumProofShares {
+					expected,
+					actual: received(round),
+				})
+			}
+			_ => self.finish_round(rng),
+		}
+	}
+
+	/// Returns the positions of the parties the dealer has dropped, in
+	/// increasing order.
+	pub fn dropped_parties(&self) -> &[usize] {
+		&self.dropped
+	}
+
+	/// Hands the messages of the current round to the dealer, treating
+	/// the parties which have not sent theirs as dropped.
+	fn finish_round<T: RngCore + CryptoRng>(&mut self, rng: &mut T) -> Result<DealerOutput, MPCError> {
+		match mem::replace(&mut self.state, DealerState::Done) {
+			DealerState::AwaitingBitCommitments(dealer, round) => {
+				let (dealer, bit_challenge) = dealer.receive_bit_commitments_with_dropouts(round)?;
+				self.dropped = dealer.dropped_parties().to_vec();
+				self.state = DealerState::AwaitingPolyCommitments(dealer, empty_round(self.m));
+				Ok(DealerOutput::Broadcast(bit_challenge.to_bytes()))
+			}
+			DealerState::AwaitingPolyCommitments(dealer, round) => {
+				match dealer.receive_poly_commitments_with_dropouts(round)? {
+					PolyCommitmentRound::Complete(dealer, poly_challenge) => {
+						self.state = DealerState::AwaitingProofShares(*dealer, empty_round(self.m));
+						Ok(DealerOutput::Broadcast(poly_challenge.to_bytes()))
+					}
+					PolyCommitmentRound::Restarted(dealer, bit_challenge) => {
+						self.dropped = dealer.dropped_parties().to_vec();
+						self.state = DealerState::AwaitingPolyCommitments(*dealer, empty_round(self.m));
+						Ok(DealerOutput::Broadcast(bit_challenge.to_bytes()))
+					}
+				}
+			}
+			DealerState::AwaitingProofShares(dealer, round) => {
+				let proof = dealer.receive_shares_with_rng(&take_round(round), rng)?;
+				Ok(DealerOutput::Proof(proof))
+			}
+			DealerState::Done => Err(MPCError::UnexpectedMessage),
+		}
+	}
+}
+
+#[allow(clippy::large_enum_variant)]
+enum PartyState<'a> {
+	AwaitingBitChallenge(PartyAwaitingBitChallenge<'a>),
+	AwaitingPolyChallenge(PartyAwaitingPolyChallenge),
+	Done,
+}
+
+/// A party's side of an aggregation protocol run over a transport.
+pub struct PartySession<'a> {
+	pc_gens: &'a PedersenGens,
+	state: PartyState<'a>,
+}
+
+impl<'a> PartySession<'a> {
+	/// Starts a session for `party` at position `j` in the aggregation.
+	/// This is a convenience wrapper around
+	/// [`PartySession::start_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn start(party: PartyAwaitingPosition<'a>, j: usize) -> Result<(Self, Vec<u8>), MPCError> {
+		PartySession::start_with_rng(party, j, &mut thread_rng())
+	}
+
+	/// Starts a session for `party` at position `j` in the aggregation.
+	///
+	/// Returns the session and the encoded [`BitCommitment`] to send to
+	/// the dealer.
+	pub fn start_with_rng<T: RngCore + CryptoRng>(
+		party: PartyAwaitingPosition<'a>,
+		j: usize,
+		rng: &mut T,
+	) -> Result<(Self, Vec<u8>), MPCError> {
+		let (party, bit_commitment) = party.assign_position_with_rng(j, rng)?;
+		let session = PartySession {
+			pc_gens: party.pc_gens(),
+			state: PartyState::AwaitingBitChallenge(party),
+		};
+		Ok((session, bit_commitment.to_bytes()))
+	}
+
+	/// Handles the encoded challenge `bytes` broadcast by the dealer.
+	/// This is a convenience wrapper around
+	/// [`PartySession::receive_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn receive(&mut self, bytes: &[u8]) -> Result<Vec<u8>, MPCError> {
+		self.receive_with_rng(bytes, &mut thread_rng())
+	}
+
+	/// Handles the encoded challenge `bytes` broadcast by the dealer.
+	///
+	/// Returns the encoded [`PolyCommitment`] in response to the
+	/// [`BitChallenge`], and the encoded [`ProofShare`] in response to
+	/// the [`PolyChallenge`], which ends the session.  If the dealer
+	/// restarts the round of polynomial commitments with a new
+	/// [`BitChallenge`], the party answers it with a new encoded
+	/// [`PolyCommitment`].  Returns [`MPCError::UnexpectedMessage`] if the
+	/// session has ended.
+	pub fn receive_with_rng<T: RngCore + CryptoRng>(&mut self, bytes: &[u8], rng: &mut T) -> Result<Vec<u8>, MPCError> {
+		match mem::replace(&mut self.state, PartyState::Done) {
+			PartyState::AwaitingBitChallenge(party) => match BitChallenge::from_bytes(bytes) {
+				Ok(bit_challenge) => {
+					let (party, poly_commitment) = party.apply_challenge_with_rng(&bit_challenge, rng);
+					self.state = PartyState::AwaitingPolyChallenge(party);
+					Ok(poly_commitment.to_bytes())
+				}
+				Err(e) => {
+					self.state = PartyState::AwaitingBitChallenge(party);
+					Err(e)
+				}
+			},
+			// A restart, told apart from the polynomial challenge by its size.
+			PartyState::AwaitingPolyChallenge(party) if bytes.len() == 2 * 32 => {
+				match BitChallenge::from_bytes(bytes) {
+					Ok(bit_challenge) => {
+						let (party, poly_commitment) = party.restart_with_rng(self.pc_gens, &bit_challenge, rng);
+						self.state = PartyState::AwaitingPolyChallenge(party);
+						Ok(poly_commitment.to_bytes())
+					}
+					Err(e) => {
+						self.state = PartyState::AwaitingPolyChallenge(party);
+						Err(e)
+					}
+				}
+			}
+			PartyState::AwaitingPolyChallenge(party) => match PolyChallenge::from_bytes(bytes) {
+				Ok(poly_challenge) => Ok(party.apply_challenge(&poly_challenge)?.to_bytes()),
+				Err(e) => {
+					self.state = PartyState::AwaitingPolyChallenge(party);
+					Err(e)
+				}
+			},
+			PartyState::Done => Err(MPCError::UnexpectedMessage),
+		}
+	}
+}
+
+/// Returns the slots for one message from each of `m` parties.
+fn empty_round<M>(m: usize) -> Vec<Option<M>> {
+	(0..m).map(|_| None).collect()
+}
+
+/// Returns whether every party not `dropped` has sent its message for the
+/// round.
+fn is_complete<M>(round: &[Option<M>], dropped: &[usize]) -> bool {
+	round
+		.iter()
+		.enumerate()
+		.all(|(j, message)| message.is_some() || dropped.contains(&j))
+}
+
+/// Returns the number of messages received in the round.
+fn received<M>(round: &[Option<M>]) -> usize {
+	round.iter().filter(|message| message.is_some()).count()
+}
+
+/// Returns the messages of a complete round, in the order of the parties.
+fn take_round<M>(round: Vec<Option<M>>) -> Vec<M> {
+	round.into_iter().flatten().collect()
+}
@@ -0,0 +1,220 @@
+//! Verification of aggregated range proofs whose value commitments are
+//! supplied one at a time.
+
+#![allow(non_snake_case)]
+
+use alloc::{vec, vec::Vec};
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar, traits::Identity};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{check_capacity, check_terms, is_valid_bitsize, padding_domain_sep, RangeProof, Timing};
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	transcript::{ProofTranscript, TranscriptProtocol},
+};
+
+/// Verifies an aggregated [`RangeProof`] for value commitments that are
+/// added one at a time, rather than passed as a slice to
+/// [`RangeProof::verify_multiple_with_rng`].
+///
+/// The number of values must be known up front, because the transcript
+/// commits to it before the first value commitment.  Each commitment is
+/// appended to the transcript as soon as it is added.  The challenges of
+/// the proof depend on all of them, so the builder still keeps the
+/// compressed commitments until
+/// [`verify_with_rng`](VerifierBuilder::verify_with_rng) is called.
+///
+/// This accepts exactly the same proofs as
+/// [`RangeProof::verify_multiple_with_rng`] with the commitments in the
+/// order they were added.
+pub struct VerifierBuilder<'t, Tr = Transcript> {
+	transcript: &'t mut Tr,
+	n: usize,
+	m: usize,
+	value_commitments: Vec<CompressedRistretto>,
+}
+
+impl<'t, Tr: ProofTranscript> VerifierBuilder<'t, Tr> {
+	/// Starts verifying an aggregated proof of `m` values of `n` bits each,
+	/// appending the proof's domain separator to `transcript`.
+	///
+	/// Returns an error if `n` is not a valid bitsize or if `m` is zero.
+	pub fn new(transcript: &'t mut Tr, n: usize, m: usize) -> Result<Self, ProofError> {
+		if !is_valid_bitsize(n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+		if m == 0 {
+			return Err(ProofError::InvalidAggregation);
+		}
+		let padded_m = m.next_power_of_two();
+		padding_domain_sep(transcript, m);
+		transcript.rangeproof_domain_sep(n as u64, padded_m as u64);
+
+		Ok(VerifierBuilder {
+			transcript,
+			n,
+			m,
+			value_commitments: Vec::with_capacity(padded_m),
+		})
+	}
+
+	/// Adds the next value commitment \\(V\\) and appends it to the
+	/// transcript.
+	///
+	/// Returns an error if all `m` commitments have already been added.
+	pub fn add_commitment(&mut self, V: CompressedRistretto) -> Result<(), ProofError> {
+		if self.value_commitments.len() == self.m {
+			return Err(ProofError::InvalidAggregation);
+		}
+		// Allow the commitments to be zero (0 value, 0 blinding), as
+		// `verify_multiple` does.
+		self.transcript.append_point(b"V", &V);
+		self.value_commitments.push(V);
+		Ok(())
+	}
+
+	/// Returns the number of value commitments added so far.
+	pub fn num_commitments(&self) -> usize {
+		self.value_commitments.len()
+	}
+
+	/// Verifies `proof` for the added value commitments.
+	///
+	/// Returns an error if fewer than `m` commitments were added.
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		mut self,
+		proof: &RangeProof,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		if self.value_commitments.len() != self.m {
+			return Err(ProofError::InvalidAggregation);
+		}
+		check_capacity(self.n, self.m, bp_gens.gens_capacity, bp_gens.party_capacity)?;
+
+		// The prover pads the aggregation with commitments to zero with a
+		// zero blinding factor.
+		for _ in self.m..self.m.next_power_of_two() {
+			let V = CompressedRistretto::identity();
+			self.transcript.append_point(b"V", &V);
+			self.value_commitments.push(V);
+		}
+
+		let c = Scalar::random(rng);
+		let terms = proof.verification_terms_after_commitments(
+			self.transcript,
+			&self.value_commitments,
+			&vec![self.n; self.value_commitments.len()],
+			Scalar::one(),
+			c,
+			Timing::Variable,
+		)?;
+
+		check_terms(bp_gens, pc_gens, terms)
+	}
+
+	/// Verifies `proof` for the added value commitments.
+	///
+	/// This is a convenience wrapper around
+	/// [`VerifierBuilder::verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		self,
+		proof: &RangeProof,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+	) -> Result<(), ProofError> {
+		self.verify_with_rng(proof, bp_gens, pc_gens, &mut thread_rng())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn prove(values: &[u64]) -> (RangeProof, Vec<CompressedRistretto>) {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 4);
+		let blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut thread_rng())).collect();
+
+		RangeProof::prove_multiple(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"VerifierBuilderTest"),
+			values,
+			&blindings,
+			32,
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn streamed_commitments_verify() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 4);
+		let (proof, commitments) = prove(&[1, 2, 3]);
+
+		let mut transcript = Transcript::new(b"VerifierBuilderTest");
+		let mut builder = VerifierBuilder::new(&mut transcript, 32, 3).unwrap();
+		for V in commitments.iter() {
+			builder.add_commitment(*V).unwrap();
+		}
+		assert_eq!(builder.num_commitments(), 3);
+		assert!(builder.verify(&proof, &bp_gens, &pc_gens).is_ok());
+
+		// The commitments must be added in order.
+		let mut transcript = Transcript::new(b"VerifierBuilderTest");
+		let mut builder = VerifierBuilder::new(&mut transcript, 32, 3).unwrap();
+		for V in commitments.iter().rev() {
+			builder.add_commitment(*V).unwrap();
+		}
+		assert!(builder.verify(&proof, &bp_gens, &pc_gens).is_err());
+	}
+
+	#[test]
+	fn wrong_number_of_commitments() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 4);
+		let (proof, commitments) = prove(&[1, 2, 3]);
+
+		let mut transcript = Transcript::new(b"VerifierBuilderTest");
+		assert!(VerifierBuilder::new(&mut transcript, 32, 0).is_err());
+		assert!(VerifierBuilder::new(&mut transcript, 65, 3).is_err());
+
+		let mut transcript = Transcript::new(b"VerifierBuilderTest");
+		let mut builder = VerifierBuilder::new(&mut transcript, 32, 3).unwrap();
+		for V in commitments[..2].iter() {
+			builder.add_commitment(*V).unwrap();
+		}
+		assert_eq!(
+			builder.verify(&proof, &bp_gens, &pc_gens),
+			Err(ProofError::InvalidAggregation)
+		);
+
+		let mut transcript = Transcript::new(b"VerifierBuilderTest");
+		let mut builder = VerifierBuilder::new(&mut transcript, 32, 3).unwrap();
+		for V in commitments.iter() {
+			builder.add_commitment(*V).unwrap();
+		}
+		assert_eq!(
+			builder.add_commitment(commitments[0]),
+			Err(ProofError::InvalidAggregation)
+		);
+
+		// The padding cannot be added as an explicit commitment to zero.
+		let mut transcript = Transcript::new(b"VerifierBuilderTest");
+		let mut builder = VerifierBuilder::new(&mut transcript, 32, 4).unwrap();
+		for V in commitments.iter() {
+			builder.add_commitment(*V).unwrap();
+		}
+		builder.add_commitment(CompressedRistretto::identity()).unwrap();
+		assert!(builder.verify(&proof, &bp_gens, &pc_gens).is_err());
+	}
+}
@@ -0,0 +1,254 @@
+//! Range proofs for values of up to 128 bits.
+//!
+//! A value \\(v < 2^n\\) with \\(64 < n \le 128\\) is split into a low
+//! limb \\(v_0 = v \bmod 2^{64}\\) and a high limb \\(v_1 = v >> 64\\),
+//! which are proven to lie in \\([0, 2^{64})\\) and \\([0, 2^{n-64})\\)
+//! by a single proof aggregated over the two limbs.  The blinding factors
+//! of the limbs are chosen so that their commitments satisfy
+//! \\[
+//! V = V_0 + 2^{64} \cdot V_1,
+//! \\]
+//! so the proof only carries the commitment to the low limb, and the
+//! verifier derives the commitment to the high limb from the commitment
+//! to the value.
+
+#![allow(non_snake_case)]
+
+use alloc::vec::Vec;
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::RangeProof;
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	util,
+};
+
+/// The bitsize of a limb.
+const LIMB_BITS: usize = 64;
+
+/// A range proof for a value of up to 128 bits, created with
+/// [`RangeProof::prove_single_u128`].
+///
+/// It is an aggregated [`RangeProof`] over the two 64-bit limbs of the
+/// value, along with the commitment to the low limb.
+#[derive(Clone, Debug)]
+pub struct U128RangeProof {
+	/// The commitment to the low limb of the value.
+	V_low: CompressedRistretto,
+	/// The aggregated proof for the limbs.
+	proof: RangeProof,
+}
+
+impl RangeProof {
+	/// Create a rangeproof that the `u128` value `v`, committed to with
+	/// blinding scalar `v_blinding`, lies in \\([0, 2^n)\\), for
+	/// \\(64 < n \le 128\\).
+	///
+	/// The proof is aggregated over the two 64-bit limbs of the value, so
+	/// `bp_gens` must have a capacity of at least 64 generators for 2
+	/// parties.  For \\(n \le 64\\), use
+	/// [`RangeProof::prove_single_with_rng`] instead.
+	///
+	/// Returns [`ProofError::InvalidBitsize`] if `n` is outside of
+	/// \\((64, 128]\\).
+	pub fn prove_single_u128_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u128,
+		v_blinding: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(U128RangeProof, CompressedRistretto), ProofError> {
+		if !is_valid_u128_bitsize(n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+
+		// Split the blinding factor so that the commitments to the limbs
+		// add up to the commitment to the value.
+		let low_blinding = Scalar::random(rng);
+		let mut high_blinding = (v_blinding - low_blinding) * limb_base().invert();
+
+		let values = [v as u64, (v >> LIMB_BITS) as u64];
+		let (proof, limb_commitments) = RangeProof::prove_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&values,
+			&[low_blinding, high_blinding],
+			&[LIMB_BITS, n - LIMB_BITS],
+			rng,
+		)?;
+		util::clear_secret(&mut high_blinding);
+
+		let V = pc_gens.commit(scalar_from_u128(v), *v_blinding).compress();
+		Ok((
+			U128RangeProof {
+				V_low: limb_commitments[0],
+				proof,
+			},
+			V,
+		))
+	}
+
+	/// Create a rangeproof for a `u128` value.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_u128_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_u128(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u128,
+		v_blinding: &Scalar,
+		n: usize,
+	) -> Result<(U128RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_single_u128_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, &mut thread_rng())
+	}
+}
+
+impl U128RangeProof {
+	/// Verifies a proof created by
+	/// [`RangeProof::prove_single_u128_with_rng`] that the value committed
+	/// to in \\(V\\) lies in \\([0, 2^n)\\).
+	pub fn verify_single_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		if !is_valid_u128_bitsize(n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+
+		// The commitment to the high limb, (V - V_low) / 2^64.
+		let V = V.decompress().ok_or(ProofError::FormatError)?;
+		let V_low = self.V_low.decompress().ok_or(ProofError::FormatError)?;
+		let V_high = (V - V_low) * limb_base().invert();
+
+		self.proof.verify_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&[self.V_low, V_high.compress()],
+			&[LIMB_BITS, n - LIMB_BITS],
+			rng,
+		)
+	}
+
+	/// Verifies a proof that the value committed to in \\(V\\) lies in
+	/// \\([0, 2^n)\\).
+	/// This is a convenience wrapper around
+	/// [`U128RangeProof::verify_single_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+	}
+
+	/// Serializes the proof into a byte array of the commitment to the
+	/// low limb, followed by the [`RangeProof::to_bytes`] encoding of the
+	/// aggregated proof.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let proof = self.proof.to_bytes();
+		let mut buf = Vec::with_capacity(32 + proof.len());
+		buf.extend_from_slice(self.V_low.as_bytes());
+		buf.extend_from_slice(&proof);
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice created by
+	/// [`U128RangeProof::to_bytes`].
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `U128RangeProof`.
+	pub fn from_bytes(slice: &[u8]) -> Result<U128RangeProof, ProofError> {
+		if slice.len() < 32 {
+			return Err(ProofError::FormatError);
+		}
+		Ok(U128RangeProof {
+			V_low: CompressedRistretto(util::read32(slice)),
+			proof: RangeProof::from_bytes(&slice[32..])?,
+		})
+	}
+}
+
+fn is_valid_u128_bitsize(n: usize) -> bool {
+	(LIMB_BITS + 1..=2 * LIMB_BITS).contains(&n)
+}
+
+/// Returns \\(2^{64}\\), the weight of the high limb.
+fn limb_base() -> Scalar {
+	Scalar::from(u64::max_value()) + Scalar::one()
+}
+
+fn scalar_from_u128(v: u128) -> Scalar {
+	let mut bytes = [0u8; 32];
+	bytes[..16].copy_from_slice(&v.to_le_bytes());
+	Scalar::from_bits(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn u128_range_proofs() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+		let v_blinding = Scalar::from(7u64);
+
+		for &(v, n) in &[(u128::max_value(), 128), (1u128 << 64, 65), (12345, 96)] {
+			let mut transcript = Transcript::new(b"U128RangeProofTest");
+			let (proof, V) =
+				RangeProof::prove_single_u128(&bp_gens, &pc_gens, &mut transcript, v, &v_blinding, n).unwrap();
+			assert_eq!(V, pc_gens.commit(scalar_from_u128(v), v_blinding).compress());
+
+			let proof = U128RangeProof::from_bytes(&proof.to_bytes()).unwrap();
+			let mut transcript = Transcript::new(b"U128RangeProofTest");
+			assert!(proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &V, n).is_ok());
+
+			// The proof does not verify for another value.
+			let other = pc_gens
+				.commit(scalar_from_u128(v) + Scalar::one(), v_blinding)
+				.compress();
+			let mut transcript = Transcript::new(b"U128RangeProofTest");
+			assert!(proof
+				.verify_single(&bp_gens, &pc_gens, &mut transcript, &other, n)
+				.is_err());
+		}
+
+		// A value above the bitsize does not verify.
+		let mut transcript = Transcript::new(b"U128RangeProofTest");
+		let (proof, V) =
+			RangeProof::prove_single_u128(&bp_gens, &pc_gens, &mut transcript, 1 << 100, &v_blinding, 96).unwrap();
+		let mut transcript = Transcript::new(b"U128RangeProofTest");
+		assert!(proof
+			.verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 96)
+			.is_err());
+
+		let mut transcript = Transcript::new(b"U128RangeProofTest");
+		assert_eq!(
+			RangeProof::prove_single_u128(&bp_gens, &pc_gens, &mut transcript, 1, &v_blinding, 64).err(),
+			Some(ProofError::InvalidBitsize)
+		);
+	}
+}
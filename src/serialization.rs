@@ -0,0 +1,120 @@
+//! Helpers shared by the `serde` implementations of the proof and
+//! generator types.
+//!
+//! Every type is serialized from its canonical byte encoding.  Binary
+//! formats (such as `bincode`) receive the bytes as-is, while
+//! human-readable formats (such as JSON) receive a lowercase hex string.
+//! Human-readable input may also hold the bytes as an array of numbers,
+//! the encoding of the proofs before the hex strings were introduced.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use serde::{
+	de::{self, SeqAccess, Visitor},
+	Deserializer, Serializer,
+};
+
+use crate::util::decode_hex;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Serializes `bytes` as a byte string, or as a hex string if the
+/// serializer is human-readable.
+pub fn serialize_bytes<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	if serializer.is_human_readable() {
+		serializer.serialize_str(&encode_hex(bytes))
+	} else {
+		serializer.serialize_bytes(bytes)
+	}
+}
+
+/// Deserializes a byte encoding written by [`serialize_bytes`], or, from a
+/// human-readable format, an array of bytes.
+///
+/// The `expecting` string describes the encoded type in error messages.
+pub fn deserialize_bytes<'de, D>(deserializer: D, expecting: &'static str) -> Result<Vec<u8>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	if deserializer.is_human_readable() {
+		deserializer.deserialize_any(BytesVisitor(expecting))
+	} else {
+		deserializer.deserialize_bytes(BytesVisitor(expecting))
+	}
+}
+
+struct BytesVisitor(&'static str);
+
+impl<'de> Visitor<'de> for BytesVisitor {
+	type Value = Vec<u8>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		formatter.write_str(self.0)
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Vec<u8>, E>
+	where
+		E: de::Error,
+	{
+		decode_hex(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+	}
+
+	fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<u8>, E>
+	where
+		E: de::Error,
+	{
+		Ok(v.to_vec())
+	}
+
+	fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Vec<u8>, E>
+	where
+		E: de::Error,
+	{
+		Ok(v)
+	}
+
+	// Formats without a native byte string type encode bytes as a sequence.
+	fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+	where
+		A: SeqAccess<'de>,
+	{
+		let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+		while let Some(byte) = seq.next_element()? {
+			bytes.push(byte);
+		}
+		Ok(bytes)
+	}
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+	let mut hex = String::with_capacity(2 * bytes.len());
+	for byte in bytes {
+		hex.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+		hex.push(HEX_DIGITS[(byte & 0x0f) as usize] as char);
+	}
+	hex
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn hex_roundtrip() {
+		let bytes: Vec<u8> = (0..=255).collect();
+		let hex = encode_hex(&bytes);
+		assert_eq!(&hex[..8], "00010203");
+		assert_eq!(decode_hex(&hex), Some(bytes));
+		assert_eq!(decode_hex("DEADbeef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+	}
+
+	#[test]
+	fn hex_rejects_malformed_input() {
+		assert_eq!(decode_hex("abc"), None);
+		assert_eq!(decode_hex("zz"), None);
+	}
+}
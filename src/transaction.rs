@@ -0,0 +1,468 @@
+//! The `transaction` module contains confidential transactions: transfers
+//! between Pedersen commitments that hide the amounts, but prove that no
+//! value is created.
+//!
+//! A [`ConfidentialTx`] spends inputs committed to by
+//! \\(V_i = v_i B + \tilde{v}_i \tilde{B}\\) into outputs committed to by
+//! \\(W_j = w_j B + \tilde{w}_j \tilde{B}\\) and a public fee \\(f\\).  It
+//! proves that
+//! \\[
+//! \sum_i v_i = \sum_j w_j + f
+//! \\]
+//! by showing knowledge of the excess blinding factor
+//! \\(\sum_i \tilde{v}_i - \sum_j \tilde{w}_j\\) as the discrete logarithm
+//! of \\(\sum_i V_i - \sum_j W_j - f B\\) to the base \\(\tilde{B}\\), and
+//! that no output wraps around the group order with an aggregated range
+//! proof over the outputs.
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::iter;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar, traits::IsIdentity};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	range_proof::RangeProof,
+	transcript::TranscriptProtocol,
+	util,
+};
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A confidential transaction, with the commitments to its outputs, its
+/// public fee, and the proofs that it conserves value.
+///
+/// The commitments to the inputs are not part of the transaction: they
+/// are usually outputs of earlier transactions, and are passed to
+/// [`ConfidentialTx::verify_with_rng`].
+#[derive(Clone, Debug)]
+pub struct ConfidentialTx {
+	/// The public fee
+	fee: u64,
+	/// Commitments to the output values
+	outputs: Vec<CompressedRistretto>,
+	/// Commitment to the nonce of the excess proof
+	R: CompressedRistretto,
+	/// Response of the excess proof
+	s: Scalar,
+	/// Aggregated range proof over the outputs
+	range_proof: RangeProof,
+}
+
+impl ConfidentialTx {
+	/// Creates a transaction spending the inputs with values
+	/// `input_values` and blinding factors `input_blindings` into outputs
+	/// with values `output_values` and blinding factors
+	/// `output_blindings`, and the public `fee`.
+	///
+	/// The outputs are proven to lie in \\([0, 2^n)\\), so `bp_gens` must
+	/// have a capacity of at least `n` generators for the number of
+	/// outputs rounded up to a power of two.
+	///
+	/// Returns the transaction and the commitments to the inputs, or
+	/// [`ProofError::UnbalancedTransaction`] if the inputs do not add up
+	/// to the outputs and the fee.
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		input_values: &[u64],
+		input_blindings: &[Scalar],
+		output_values: &[u64],
+		output_blindings: &[Scalar],
+		fee: u64,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(ConfidentialTx, Vec<CompressedRistretto>), ProofError> {
+		if input_values.len() != input_blindings.len() {
+			return Err(ProofError::WrongNumBlindingFactors {
+				expected: input_values.len(),
+				actual: input_blindings.len(),
+			});
+		}
+		if output_values.len() != output_blindings.len() {
+			return Err(ProofError::WrongNumBlindingFactors {
+				expected: output_values.len(),
+				actual: output_blindings.len(),
+			});
+		}
+		let inputs_sum: u128 = input_values.iter().map(|&v| u128::from(v)).sum();
+		let outputs_sum: u128 = output_values.iter().map(|&v| u128::from(v)).sum();
+		if inputs_sum != outputs_sum + u128::from(fee) {
+			return Err(ProofError::UnbalancedTransaction);
+		}
+
+		let inputs: Vec<CompressedRistretto> = input_values
+			.iter()
+			.zip(input_blindings.iter())
+			.map(|(&v, v_blinding)| pc_gens.commit(Scalar::from(v), *v_blinding).compress())
+			.collect();
+
+		transcript.confidential_tx_domain_sep(inputs.len() as u64, output_values.len() as u64, fee);
+		for V in inputs.iter() {
+			transcript.append_point(b"V_in", V);
+		}
+
+		let (range_proof, outputs) = RangeProof::prove_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			output_values,
+			output_blindings,
+			&vec![n; output_values.len()],
+			rng,
+		)?;
+
+		// Prove knowledge of the excess blinding factor.
+		let mut excess: Scalar = input_blindings.iter().sum::<Scalar>() - output_blindings.iter().sum::<Scalar>();
+		let mut nonce = Scalar::random(rng);
+		let R = (nonce * pc_gens.B_blinding).compress();
+		transcript.append_point(b"R", &R);
+		let c = transcript.challenge_scalar(b"c");
+		let s = nonce + c * excess;
+
+		util::clear_secret(&mut excess);
+		util::clear_secret(&mut nonce);
+
+		Ok((
+			ConfidentialTx {
+				fee,
+				outputs,
+				R,
+				s,
+				range_proof,
+			},
+			inputs,
+		))
+	}
+
+	/// Creates a transaction spending the inputs into the outputs and the
+	/// public `fee`.
+	///
+	/// This is a convenience wrapper around
+	/// [`ConfidentialTx::prove_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	#[allow(clippy::too_many_arguments)]
+	pub fn prove(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		input_values: &[u64],
+		input_blindings: &[Scalar],
+		output_values: &[u64],
+		output_blindings: &[Scalar],
+		fee: u64,
+		n: usize,
+	) -> Result<(ConfidentialTx, Vec<CompressedRistretto>), ProofError> {
+		ConfidentialTx::prove_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			input_values,
+			input_blindings,
+			output_values,
+			output_blindings,
+			fee,
+			n,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies that the transaction spends the inputs committed to by
+	/// `inputs` into its outputs and fee, and that its outputs lie in
+	/// \\([0, 2^n)\\).
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		inputs: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		transcript.confidential_tx_domain_sep(inputs.len() as u64, self.outputs.len() as u64, self.fee);
+		for V in inputs.iter() {
+			transcript.append_point(b"V_in", V);
+		}
+
+		self.range_proof.verify_multiple_with_bitsizes_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&self.outputs,
+			&vec![n; self.outputs.len()],
+			rng,
+		)?;
+
+		transcript.validate_and_append_point(b"R", &self.R)?;
+		let c = transcript.challenge_scalar(b"c");
+
+		// Check that s * B_blinding = R + c * (sum(V_in) - sum(V_out) - fee * B).
+		let check = util::optional_multiscalar_mul(
+			iter::once(self.s)
+				.chain(iter::once(c * Scalar::from(self.fee)))
+				.chain(iter::once(-Scalar::one()))
+				.chain(inputs.iter().map(|_| -c))
+				.chain(self.outputs.iter().map(|_| c)),
+			iter::once(Some(pc_gens.B_blinding))
+				.chain(iter::once(Some(pc_gens.B)))
+				.chain(iter::once(self.R.decompress()))
+				.chain(inputs.iter().map(|V| V.decompress()))
+				.chain(self.outputs.iter().map(|V| V.decompress())),
+		)
+		.ok_or(ProofError::VerificationError)?;
+
+		if check.is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+
+	/// Verifies that the transaction spends the inputs committed to by
+	/// `inputs` into its outputs and fee.
+	///
+	/// This is a convenience wrapper around
+	/// [`ConfidentialTx::verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		inputs: &[CompressedRistretto],
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_with_rng(bp_gens, pc_gens, transcript, inputs, n, &mut thread_rng())
+	}
+
+	/// Returns the public fee of the transaction.
+	pub fn fee(&self) -> u64 {
+		self.fee
+	}
+
+	/// Returns the commitments to the outputs of the transaction.
+	pub fn outputs(&self) -> &[CompressedRistretto] {
+		&self.outputs
+	}
+
+	/// Serializes the transaction into a byte array.
+	///
+	/// # Layout
+	///
+	/// The layout of the transaction encoding is:
+	///
+	/// * the fee and the number \\(m\\) of outputs, as little-endian `u64`s,
+	/// * \\(m\\) compressed Ristretto points, the commitments to the outputs,
+	/// * a compressed Ristretto point \\(R\\) and a scalar \\(s\\), the proof
+	///   of the excess blinding factor,
+	/// * the [`RangeProof::to_bytes`] encoding of the range proof over the
+	///   outputs.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let range_proof = self.range_proof.to_bytes();
+		let mut buf = Vec::with_capacity(16 + (self.outputs.len() + 2) * 32 + range_proof.len());
+		buf.extend_from_slice(&self.fee.to_le_bytes());
+		buf.extend_from_slice(&(self.outputs.len() as u64).to_le_bytes());
+		for V in self.outputs.iter() {
+			buf.extend_from_slice(V.as_bytes());
+		}
+		buf.extend_from_slice(self.R.as_bytes());
+		buf.extend_from_slice(self.s.as_bytes());
+		buf.extend_from_slice(&range_proof);
+		buf
+	}
+
+	/// Deserializes the transaction from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `ConfidentialTx`.
+	pub fn from_bytes(slice: &[u8]) -> Result<ConfidentialTx, ProofError> {
+		use crate::util::read32;
+
+		if slice.len() < 16 {
+			return Err(ProofError::FormatError);
+		}
+		let mut word = [0u8; 8];
+		word.copy_from_slice(&slice[0..8]);
+		let fee = u64::from_le_bytes(word);
+		word.copy_from_slice(&slice[8..16]);
+		let m = u64::from_le_bytes(word);
+
+		// Check the number of outputs against the length before using it.
+		let rest = &slice[16..];
+		if m > (rest.len() / 32) as u64 || rest.len() < (m as usize + 2) * 32 {
+			return Err(ProofError::FormatError);
+		}
+		let m = m as usize;
+
+		Ok(ConfidentialTx {
+			fee,
+			outputs: rest[..m * 32]
+				.chunks(32)
+				.map(|chunk| CompressedRistretto(read32(chunk)))
+				.collect(),
+			R: CompressedRistretto(read32(&rest[m * 32..])),
+			s: Scalar::from_canonical_bytes(read32(&rest[(m + 1) * 32..])).ok_or(ProofError::FormatError)?,
+			range_proof: RangeProof::from_bytes(&rest[(m + 2) * 32..])?,
+		})
+	}
+}
+
+// The SCALE encoding is the length-prefixed byte encoding from `to_bytes`.
+#[cfg(feature = "scale")]
+impl Encode for ConfidentialTx {
+	fn size_hint(&self) -> usize {
+		let len = self.to_bytes().len();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for ConfidentialTx {}
+
+#[cfg(feature = "scale")]
+impl Decode for ConfidentialTx {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		ConfidentialTx::from_bytes(&bytes).map_err(|_| "Invalid ConfidentialTx encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ConfidentialTx {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ConfidentialTx {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid ConfidentialTx")?;
+		ConfidentialTx::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn blindings(k: usize) -> Vec<Scalar> {
+		(0..k).map(|_| Scalar::random(&mut thread_rng())).collect()
+	}
+
+	#[test]
+	fn balanced_transaction() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
+		let (input_blindings, output_blindings) = (blindings(2), blindings(3));
+
+		let (tx, inputs) = ConfidentialTx::prove(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"ConfidentialTxTest"),
+			&[100, 50],
+			&input_blindings,
+			&[70, 60, 15],
+			&output_blindings,
+			5,
+			64,
+		)
+		.unwrap();
+		assert_eq!(tx.fee(), 5);
+		assert_eq!(
+			tx.outputs()[2],
+			pc_gens.commit(Scalar::from(15u64), output_blindings[2]).compress()
+		);
+
+		let tx = ConfidentialTx::from_bytes(&tx.to_bytes()).unwrap();
+		assert!(tx
+			.verify(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"ConfidentialTxTest"),
+				&inputs,
+				64
+			)
+			.is_ok());
+
+		// The transaction does not spend other inputs.
+		let other = [
+			inputs[0],
+			pc_gens.commit(Scalar::from(51u64), input_blindings[1]).compress(),
+		];
+		assert!(tx
+			.verify(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"ConfidentialTxTest"),
+				&other,
+				64
+			)
+			.is_err());
+
+		// A changed fee breaks the conservation of value.
+		let mut bytes = tx.to_bytes();
+		bytes[0] += 1;
+		let tampered = ConfidentialTx::from_bytes(&bytes).unwrap();
+		assert!(tampered
+			.verify(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"ConfidentialTxTest"),
+				&inputs,
+				64
+			)
+			.is_err());
+	}
+
+	#[test]
+	fn unbalanced_transaction() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 2);
+
+		assert_eq!(
+			ConfidentialTx::prove(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"ConfidentialTxTest"),
+				&[100],
+				&blindings(1),
+				&[60, 41],
+				&blindings(2),
+				0,
+				64,
+			)
+			.unwrap_err(),
+			ProofError::UnbalancedTransaction
+		);
+		assert!(ConfidentialTx::from_bytes(&[0u8; 15]).is_err());
+	}
+}
@@ -1,20 +1,179 @@
-//! Defines a `TranscriptProtocol` trait for using a Merlin transcript.
+//! Defines a `TranscriptProtocol` trait for using a Merlin transcript, and
+//! a `ProofTranscript` trait for replacing Merlin with another Fiat–Shamir
+//! transform.
 
-use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar, traits::Identity};
+use digest::Digest;
 use merlin::Transcript;
+use subtle::{Choice, ConstantTimeEq};
 
 use crate::errors::ProofError;
 
+/// The Fiat–Shamir transform of a proof: a transcript that data is appended
+/// to and challenges are derived from.
+///
+/// The proofs of this crate are defined over a Merlin [`Transcript`], which
+/// implements this trait.  An integrator who must match a verifier with a
+/// different transform, such as a smart contract hashing the transcript
+/// with Keccak, can implement it for their own type, or use a
+/// [`HashTranscript`], and pass it to the proofs that are generic over it:
+/// the [`InnerProductProof`](::InnerProductProof), the aggregated range
+/// proofs of [`RangeProof`](::RangeProof), its
+/// [`VerifierBuilder`](::VerifierBuilder) and the range proof
+/// [`Dealer`](::range_proof_mpc::dealer::Dealer).  The domain separators and
+/// encodings of [`TranscriptProtocol`] are the same for any transcript.
+///
+/// The challenges must be bound to everything appended before them, and
+/// the labels must be part of what is hashed, so that the data of one
+/// proof cannot be reinterpreted as that of another.
+pub trait ProofTranscript: Clone {
+	/// Append a `message` with the given `label`.
+	fn append_message(&mut self, label: &'static [u8], message: &[u8]);
+
+	/// Append `x` with the given `label`, as 8 little-endian bytes.
+	fn append_u64(&mut self, label: &'static [u8], x: u64) {
+		self.append_message(label, &x.to_le_bytes());
+	}
+
+	/// Fill `dest` with a challenge bound to the transcript so far, with
+	/// the given `label`.
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]);
+}
+
+impl ProofTranscript for Transcript {
+	fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+		Transcript::append_message(self, label, message);
+	}
+
+	fn append_u64(&mut self, label: &'static [u8], x: u64) {
+		Transcript::append_u64(self, label, x);
+	}
+
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+		Transcript::challenge_bytes(self, label, dest);
+	}
+}
+
+/// A [`ProofTranscript`] hashing its data with the hash function `D`, for
+/// matching Fiat–Shamir implementations that do not use Merlin.
+///
+/// The transcript is a running hash of its operations.  Appending a message
+/// hashes the length of its label, the label, the length of the message
+/// and the message, with lengths as 8 little-endian bytes.  A challenge is
+/// derived by hashing the running hash state with the tag `challenge`, the
+/// length of the label, the label, the length of the challenge and a block
+/// counter, as many times as needed to fill it, and the challenge is then
+/// appended to the transcript as a message with the label.
+///
+/// For instance, `HashTranscript<sha3::Keccak256>` hashes with Keccak-256
+/// and `HashTranscript<sha2::Sha512>` with SHA-512.
+#[derive(Clone)]
+pub struct HashTranscript<D> {
+	hasher: D,
+}
+
+impl<D: Digest + Clone> HashTranscript<D> {
+	/// Creates a transcript with the domain separator `label`, appended
+	/// with the label `dom-sep`.
+	pub fn new(label: &'static [u8]) -> Self {
+		let mut transcript = HashTranscript { hasher: D::new() };
+		transcript.append_message(b"dom-sep", label);
+		transcript
+	}
+}
+
+impl<D: Digest + Clone> ProofTranscript for HashTranscript<D> {
+	fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+		self.hasher.update(&(label.len() as u64).to_le_bytes());
+		self.hasher.update(label);
+		self.hasher.update(&(message.len() as u64).to_le_bytes());
+		self.hasher.update(message);
+	}
+
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+		let mut seed = self.hasher.clone();
+		seed.update(b"challenge");
+		seed.update(&(label.len() as u64).to_le_bytes());
+		seed.update(label);
+		seed.update(&(dest.len() as u64).to_le_bytes());
+
+		for (i, block) in dest.chunks_mut(D::output_size()).enumerate() {
+			let mut hasher = seed.clone();
+			hasher.update(&(i as u64).to_le_bytes());
+			block.copy_from_slice(&hasher.finalize()[..block.len()]);
+		}
+		self.append_message(label, dest);
+	}
+}
+
+/// Extends a [`ProofTranscript`], such as a Merlin [`Transcript`], with the
+/// domain separators and encodings used by the proofs in this crate.
+///
+/// Applications can use it to bind their own data into a transcript
+/// before proving, for instance with
+/// [`TranscriptProtocol::context_domain_sep`].
 pub trait TranscriptProtocol {
 	/// Append a domain separator for an `n`-bit, `m`-party range proof.
 	fn rangeproof_domain_sep(&mut self, n: u64, m: u64);
 
+	/// Append a domain separator for an aggregated range proof of values
+	/// with different `bitsizes`, each padded to `n` bits.
+	fn rangeproof_bitsizes_domain_sep(&mut self, n: u64, bitsizes: &[usize]);
+
+	/// Append a domain separator for an aggregated range proof of `m`
+	/// values, padded with zero values to the next power of two.
+	fn rangeproof_padding_domain_sep(&mut self, m: u64);
+
+	/// Append a domain separator for deriving the nonces of a rewindable
+	/// `n`-bit range proof.
+	fn rewind_domain_sep(&mut self, n: u64);
+
+	/// Append a domain separator for a proof that a value lies in the
+	/// interval `[min, max]`.
+	fn interval_domain_sep(&mut self, min: u64, max: u64);
+
+	/// Append a domain separator for a proof that a signed value lies in
+	/// \\([-2^{n-1}, 2^{n-1})\\).
+	fn signed_domain_sep(&mut self, n: u64);
+
+	/// Append a domain separator for a range proof over an ElGamal
+	/// ciphertext.
+	fn elgamal_domain_sep(&mut self);
+
 	/// Append a domain separator for a length-`n` inner product proof.
 	fn innerproduct_domain_sep(&mut self, n: u64);
 
+	/// Append a domain separator for a proof linking a commitment to a
+	/// length-`k` vector to commitments to its entries.
+	fn vector_link_domain_sep(&mut self, k: u64);
+
+	/// Append a domain separator for a proof of the evaluation of a
+	/// committed polynomial with `n` coefficients.
+	fn poly_commit_domain_sep(&mut self, n: u64);
+
+	/// Append a domain separator for a confidential transaction with
+	/// `inputs` inputs, `outputs` outputs and a public `fee`.
+	fn confidential_tx_domain_sep(&mut self, inputs: u64, outputs: u64, fee: u64);
+
+	/// Append a domain separator for a mixer transfer from a tree of notes
+	/// of the given `depth`, with values of `value_bits` bits.
+	fn mixer_domain_sep(&mut self, depth: u64, value_bits: u64);
+
+	/// Append a domain separator binding application-defined `context`
+	/// bytes, such as a chain id, an epoch or an asset id, to the proof.
+	fn context_domain_sep(&mut self, context: &[u8]);
+
 	/// Append a domain separator for a constraint system.
 	fn r1cs_domain_sep(&mut self);
 
+	/// Append a domain separator for a batch of `k` constraint systems
+	/// proven together.
+	fn r1cs_batch_domain_sep(&mut self, k: u64);
+
+	/// Append a domain separator for a CS split into `k` segments of
+	/// `chunk_size` multipliers.
+	fn r1cs_chunked_domain_sep(&mut self, k: u64, chunk_size: u64);
+
 	/// Commit a domain separator for a CS without randomized constraints.
 	fn r1cs_1phase_domain_sep(&mut self);
 
@@ -35,40 +194,161 @@ pub trait TranscriptProtocol {
 		point: &CompressedRistretto,
 	) -> Result<(), ProofError>;
 
+	/// Append a `point` with the given `label`, and return whether it is
+	/// not the identity.  Unlike
+	/// [`TranscriptProtocol::validate_and_append_point`], the point is
+	/// appended either way, and the check runs in constant time.
+	fn append_and_check_point(&mut self, label: &'static [u8], point: &CompressedRistretto) -> Choice;
+
 	/// Compute a `label`ed challenge variable.
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar;
 }
 
-impl TranscriptProtocol for Transcript {
+/// Appends `message` to `transcript`, recording it with the
+/// `transcript-debug` feature.
+///
+/// The proofs of this crate append to their transcripts through this
+/// function and the [`TranscriptProtocol`] methods rather than directly, so
+/// that [`transcript_debug::record`](::transcript_debug::record) sees every
+/// operation.
+pub(crate) fn append_message<T: ProofTranscript>(transcript: &mut T, label: &'static [u8], message: &[u8]) {
+	#[cfg(feature = "transcript-debug")]
+	crate::transcript_debug::push(crate::transcript_debug::Operation::Append, label, message);
+	ProofTranscript::append_message(transcript, label, message);
+}
+
+/// Appends `x` to `transcript` like [`ProofTranscript::append_u64`],
+/// recording it with the `transcript-debug` feature.
+pub(crate) fn append_u64<T: ProofTranscript>(transcript: &mut T, label: &'static [u8], x: u64) {
+	#[cfg(feature = "transcript-debug")]
+	crate::transcript_debug::push(crate::transcript_debug::Operation::Append, label, &x.to_le_bytes());
+	ProofTranscript::append_u64(transcript, label, x);
+}
+
+/// Fills `dest` with a challenge from `transcript`, recording it with the
+/// `transcript-debug` feature.
+pub(crate) fn challenge_bytes<T: ProofTranscript>(transcript: &mut T, label: &'static [u8], dest: &mut [u8]) {
+	ProofTranscript::challenge_bytes(transcript, label, dest);
+	#[cfg(feature = "transcript-debug")]
+	crate::transcript_debug::push(crate::transcript_debug::Operation::Challenge, label, dest);
+}
+
+/// Runs `f` without recording its transcript operations, for transcripts
+/// that only the prover builds, such as the one it derives secret nonces
+/// from.
+#[cfg(feature = "transcript-debug")]
+pub(crate) fn unrecorded<T, F: FnOnce() -> T>(f: F) -> T {
+	crate::transcript_debug::unrecorded(f)
+}
+
+#[cfg(all(feature = "alloc", not(feature = "transcript-debug")))]
+pub(crate) fn unrecorded<T, F: FnOnce() -> T>(f: F) -> T {
+	f()
+}
+
+impl<T: ProofTranscript> TranscriptProtocol for T {
 	fn rangeproof_domain_sep(&mut self, n: u64, m: u64) {
-		self.append_message(b"dom-sep", b"rangeproof v1");
-		self.append_u64(b"n", n);
-		self.append_u64(b"m", m);
+		append_message(self, b"dom-sep", b"rangeproof v1");
+		append_u64(self, b"n", n);
+		append_u64(self, b"m", m);
+	}
+
+	fn rangeproof_bitsizes_domain_sep(&mut self, n: u64, bitsizes: &[usize]) {
+		append_message(self, b"dom-sep", b"rangeproof-bitsizes v1");
+		append_u64(self, b"n", n);
+		append_u64(self, b"m", bitsizes.len() as u64);
+		for &n_j in bitsizes {
+			append_u64(self, b"n_j", n_j as u64);
+		}
+	}
+
+	fn rangeproof_padding_domain_sep(&mut self, m: u64) {
+		append_message(self, b"dom-sep", b"rangeproof-padding v1");
+		append_u64(self, b"m", m);
+	}
+
+	fn rewind_domain_sep(&mut self, n: u64) {
+		append_message(self, b"dom-sep", b"rangeproof-rewind v1");
+		append_u64(self, b"n", n);
+	}
+
+	fn interval_domain_sep(&mut self, min: u64, max: u64) {
+		append_message(self, b"dom-sep", b"interval v1");
+		append_u64(self, b"min", min);
+		append_u64(self, b"max", max);
+	}
+
+	fn signed_domain_sep(&mut self, n: u64) {
+		append_message(self, b"dom-sep", b"signed-rangeproof v1");
+		append_u64(self, b"n", n);
+	}
+
+	fn elgamal_domain_sep(&mut self) {
+		append_message(self, b"dom-sep", b"elgamal-rangeproof v1");
 	}
 
 	fn innerproduct_domain_sep(&mut self, n: u64) {
-		self.append_message(b"dom-sep", b"ipp v1");
-		self.append_u64(b"n", n);
+		append_message(self, b"dom-sep", b"ipp v1");
+		append_u64(self, b"n", n);
+	}
+
+	fn vector_link_domain_sep(&mut self, k: u64) {
+		append_message(self, b"dom-sep", b"vector-link v1");
+		append_u64(self, b"k", k);
+	}
+
+	fn poly_commit_domain_sep(&mut self, n: u64) {
+		append_message(self, b"dom-sep", b"poly-commit v1");
+		append_u64(self, b"n", n);
+	}
+
+	fn confidential_tx_domain_sep(&mut self, inputs: u64, outputs: u64, fee: u64) {
+		append_message(self, b"dom-sep", b"confidential-tx v1");
+		append_u64(self, b"inputs", inputs);
+		append_u64(self, b"outputs", outputs);
+		append_u64(self, b"fee", fee);
+	}
+
+	fn mixer_domain_sep(&mut self, depth: u64, value_bits: u64) {
+		append_message(self, b"dom-sep", b"mixer v1");
+		append_u64(self, b"depth", depth);
+		append_u64(self, b"value_bits", value_bits);
+	}
+
+	fn context_domain_sep(&mut self, context: &[u8]) {
+		append_message(self, b"dom-sep", b"context v1");
+		append_message(self, b"context", context);
 	}
 
 	fn r1cs_domain_sep(&mut self) {
-		self.append_message(b"dom-sep", b"r1cs v1");
+		append_message(self, b"dom-sep", b"r1cs v1");
+	}
+
+	fn r1cs_batch_domain_sep(&mut self, k: u64) {
+		append_message(self, b"dom-sep", b"r1cs-batch v1");
+		append_u64(self, b"k", k);
+	}
+
+	fn r1cs_chunked_domain_sep(&mut self, k: u64, chunk_size: u64) {
+		append_message(self, b"dom-sep", b"r1cs-chunked v1");
+		append_u64(self, b"k", k);
+		append_u64(self, b"chunk_size", chunk_size);
 	}
 
 	fn r1cs_1phase_domain_sep(&mut self) {
-		self.append_message(b"dom-sep", b"r1cs-1phase");
+		append_message(self, b"dom-sep", b"r1cs-1phase");
 	}
 
 	fn r1cs_2phase_domain_sep(&mut self) {
-		self.append_message(b"dom-sep", b"r1cs-2phase");
+		append_message(self, b"dom-sep", b"r1cs-2phase");
 	}
 
 	fn append_scalar(&mut self, label: &'static [u8], scalar: &Scalar) {
-		self.append_message(label, scalar.as_bytes());
+		append_message(self, label, scalar.as_bytes());
 	}
 
 	fn append_point(&mut self, label: &'static [u8], point: &CompressedRistretto) {
-		self.append_message(label, point.as_bytes());
+		append_message(self, label, point.as_bytes());
 	}
 
 	fn validate_and_append_point(
@@ -81,14 +361,75 @@ impl TranscriptProtocol for Transcript {
 		if point.is_identity() {
 			Err(ProofError::VerificationError)
 		} else {
-			Ok(self.append_message(label, point.as_bytes()))
+			append_message(self, label, point.as_bytes());
+			Ok(())
 		}
 	}
 
+	fn append_and_check_point(&mut self, label: &'static [u8], point: &CompressedRistretto) -> Choice {
+		append_message(self, label, point.as_bytes());
+		!point.as_bytes().ct_eq(CompressedRistretto::identity().as_bytes())
+	}
+
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
 		let mut buf = [0u8; 64];
-		self.challenge_bytes(label, &mut buf);
+		challenge_bytes(self, label, &mut buf);
 
 		Scalar::from_bytes_mod_order_wide(&buf)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sha3::{Keccak256, Sha3_512};
+
+	fn challenge<T: ProofTranscript>(transcript: &mut T, len: usize) -> Vec<u8> {
+		let mut buf = vec![0u8; len];
+		ProofTranscript::challenge_bytes(transcript, b"c", &mut buf);
+		buf
+	}
+
+	#[test]
+	fn hash_transcript_binds_its_operations() {
+		let transcript = |label: &'static [u8], message: &[u8]| {
+			let mut transcript = HashTranscript::<Sha3_512>::new(b"test");
+			ProofTranscript::append_message(&mut transcript, label, message);
+			transcript
+		};
+		let c = challenge(&mut transcript(b"a", b"bc"), 32);
+		assert_eq!(challenge(&mut transcript(b"a", b"bc"), 32), c);
+		// The lengths are hashed, so moving bytes from the label to the
+		// message changes the challenge.
+		assert_ne!(challenge(&mut transcript(b"ab", b"c"), 32), c);
+		assert_ne!(challenge(&mut transcript(b"a", b"bd"), 32), c);
+
+		// Each challenge is appended, so the next one differs.
+		let mut t = transcript(b"a", b"bc");
+		assert_eq!(challenge(&mut t, 32), c);
+		assert_ne!(challenge(&mut t, 32), c);
+	}
+
+	#[test]
+	fn hash_transcript_fills_long_challenges() {
+		let mut transcript = HashTranscript::<Keccak256>::new(b"test");
+		let c = challenge(&mut transcript.clone(), 100);
+		// The blocks of a challenge differ, and a shorter challenge is not a
+		// prefix of a longer one.
+		assert_ne!(c[..32], c[32..64]);
+		assert_ne!(challenge(&mut transcript, 32)[..], c[..32]);
+	}
+
+	#[test]
+	fn domain_separators_use_the_transcript_encoding() {
+		let mut protocol = HashTranscript::<Sha3_512>::new(b"test");
+		protocol.rangeproof_domain_sep(64, 1);
+
+		let mut manual = HashTranscript::<Sha3_512>::new(b"test");
+		ProofTranscript::append_message(&mut manual, b"dom-sep", b"rangeproof v1");
+		ProofTranscript::append_message(&mut manual, b"n", &64u64.to_le_bytes());
+		ProofTranscript::append_message(&mut manual, b"m", &1u64.to_le_bytes());
+
+		assert_eq!(protocol.challenge_scalar(b"x"), manual.challenge_scalar(b"x"));
+	}
+}
@@ -0,0 +1,240 @@
+//! Recording of Fiat–Shamir transcripts, for debugging proofs that do not
+//! verify.
+//!
+//! A prover and a verifier only agree on the challenges of a proof if they
+//! append the same data to their transcripts in the same order.  With the
+//! `transcript-debug` feature, every append and challenge made through
+//! [`TranscriptProtocol`](::TranscriptProtocol) or by the proofs of this
+//! crate is recorded while a closure passed to [`record`] runs on the
+//! current thread, and the logs of the prover and the verifier can be
+//! compared with [`TranscriptLog::first_divergence`]:
+//!
+//! ```ignore
+//! let (proof, prover_log) = record(|| prover.prove(&bp_gens));
+//! let (result, verifier_log) = record(|| verifier.verify(&proof?, &pc_gens, &bp_gens));
+//! if let Some(divergence) = prover_log.first_divergence(&verifier_log) {
+//!     println!("{}", divergence);
+//! }
+//! ```
+//!
+//! Data appended by the application directly with the methods of
+//! [`Transcript`](merlin::Transcript) is not recorded, and neither are the
+//! witness and the secret keys the prover feeds to its random number
+//! generators.  The log does record the challenges, so it must not be
+//! shared where they are secret.
+
+use std::{cell::RefCell, fmt, mem, vec::Vec};
+
+/// The kind of a transcript operation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Operation {
+	/// A message appended to the transcript.
+	Append,
+	/// A challenge squeezed from the transcript.
+	Challenge,
+}
+
+/// A recorded transcript operation, with its label and the bytes appended
+/// or squeezed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+	/// The kind of the operation.
+	pub operation: Operation,
+	/// The label of the operation.
+	pub label: &'static [u8],
+	/// The bytes of the message or challenge.
+	pub value: Vec<u8>,
+}
+
+impl fmt::Display for Entry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let operation = match self.operation {
+			Operation::Append => "append",
+			Operation::Challenge => "challenge",
+		};
+		write!(f, "{} {:?}: ", operation, String::from_utf8_lossy(self.label))?;
+		for byte in self.value.iter() {
+			write!(f, "{:02x}", byte)?;
+		}
+		Ok(())
+	}
+}
+
+/// The transcript operations recorded by [`record`], in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TranscriptLog {
+	entries: Vec<Entry>,
+}
+
+impl TranscriptLog {
+	/// Returns the recorded operations.
+	pub fn entries(&self) -> &[Entry] {
+		&self.entries
+	}
+
+	/// Returns the first operation at which `self` and `other` differ, or
+	/// `None` if they recorded the same operations.
+	///
+	/// When one log is a prefix of the other, the divergence is at the end
+	/// of the shorter one.
+	pub fn first_divergence(&self, other: &TranscriptLog) -> Option<Divergence> {
+		let len = self.entries.len().max(other.entries.len());
+		(0..len)
+			.find(|&i| self.entries.get(i) != other.entries.get(i))
+			.map(|index| Divergence {
+				index,
+				left: self.entries.get(index).cloned(),
+				right: other.entries.get(index).cloned(),
+			})
+	}
+}
+
+impl fmt::Display for TranscriptLog {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, entry) in self.entries.iter().enumerate() {
+			writeln!(f, "{}: {}", i, entry)?;
+		}
+		Ok(())
+	}
+}
+
+/// The first operation at which two [`TranscriptLog`]s differ, as returned
+/// by [`TranscriptLog::first_divergence`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+	/// The index of the operation in both logs.
+	pub index: usize,
+	/// The operation of the log `first_divergence` was called on, or
+	/// `None` if it ends before `index`.
+	pub left: Option<Entry>,
+	/// The operation of the other log, or `None` if it ends before
+	/// `index`.
+	pub right: Option<Entry>,
+}
+
+impl fmt::Display for Divergence {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let show = |entry: &Option<Entry>| match entry {
+			Some(entry) => entry.to_string(),
+			None => "end of log".to_string(),
+		};
+		write!(
+			f,
+			"transcripts diverge at operation {}:\n  left:  {}\n  right: {}",
+			self.index,
+			show(&self.left),
+			show(&self.right)
+		)
+	}
+}
+
+thread_local! {
+	static LOG: RefCell<Option<Vec<Entry>>> = RefCell::new(None);
+}
+
+/// Runs `f`, recording the transcript operations it makes on the current
+/// thread, and returns its result with the log.
+///
+/// Calls to `record` can be nested, in which case the inner call records
+/// its operations instead of the outer one.
+pub fn record<T, F: FnOnce() -> T>(f: F) -> (T, TranscriptLog) {
+	let outer = LOG.with(|log| log.replace(Some(Vec::new())));
+	let result = f();
+	let entries = LOG.with(|log| mem::replace(&mut *log.borrow_mut(), outer));
+	(result, TranscriptLog {
+		entries: entries.unwrap_or_default(),
+	})
+}
+
+/// Runs `f` without recording the transcript operations it makes, e.g.
+/// on a transcript cloned to derive secret nonces.
+pub(crate) fn unrecorded<T, F: FnOnce() -> T>(f: F) -> T {
+	let outer = LOG.with(|log| log.replace(None));
+	let result = f();
+	LOG.with(|log| *log.borrow_mut() = outer);
+	result
+}
+
+/// Records an operation, if a [`record`] call is running.
+pub(crate) fn push(operation: Operation, label: &'static [u8], value: &[u8]) {
+	LOG.with(|log| {
+		if let Some(entries) = log.borrow_mut().as_mut() {
+			entries.push(Entry {
+				operation,
+				label,
+				value: value.to_vec(),
+			});
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use merlin::Transcript;
+
+	use crate::{transcript::TranscriptProtocol, BulletproofGens, PedersenGens, RangeProof};
+	use curve25519_dalek::scalar::Scalar;
+
+	#[test]
+	fn find_first_divergence() {
+		let run = |n: u64, m: u64| {
+			record(|| {
+				let mut transcript = Transcript::new(b"TranscriptDebugTest");
+				transcript.rangeproof_domain_sep(n, m);
+				transcript.challenge_scalar(b"y")
+			})
+		};
+
+		let (y, log) = run(64, 1);
+		let (y_again, same_log) = run(64, 1);
+		assert_eq!(y, y_again);
+		assert_eq!(log.entries().len(), 4);
+		assert_eq!(log.first_divergence(&same_log), None);
+
+		let (_, other_log) = run(64, 2);
+		let divergence = log.first_divergence(&other_log).unwrap();
+		assert_eq!(divergence.index, 2);
+		assert_eq!(divergence.left.unwrap().label, b"m");
+		assert_eq!(divergence.right.unwrap().to_string(), "append \"m\": 0200000000000000");
+
+		// Nothing is recorded outside of `record`, and a log that ends
+		// early diverges at its end.
+		let mut transcript = Transcript::new(b"TranscriptDebugTest");
+		transcript.challenge_scalar(b"y");
+		let (_, prefix) = record(|| unrecorded(|| transcript.challenge_scalar(b"y")));
+		assert_eq!(log.first_divergence(&prefix).unwrap().index, 0);
+		assert_eq!(log.first_divergence(&prefix).unwrap().right, None);
+	}
+
+	#[test]
+	fn prover_and_verifier_logs_match() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(32, 1);
+
+		let ((proof, commitment), prover_log) = record(|| {
+			let mut transcript = Transcript::new(b"TranscriptDebugTest");
+			RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 7, &Scalar::from(3u64), 32).unwrap()
+		});
+
+		let (result, verifier_log) = record(|| {
+			let mut transcript = Transcript::new(b"TranscriptDebugTest");
+			proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &commitment, 32)
+		});
+		assert!(result.is_ok());
+		assert_eq!(prover_log.first_divergence(&verifier_log), None);
+
+		// A verifier that appends different context diverges where the
+		// context is appended, and squeezes different challenges after it.
+		let (result, other_log) = record(|| {
+			let mut transcript = Transcript::new(b"TranscriptDebugTest");
+			transcript.context_domain_sep(b"other");
+			proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &commitment, 32)
+		});
+		assert!(result.is_err());
+		let divergence = prover_log.first_divergence(&other_log).unwrap();
+		assert_eq!(divergence.index, 0);
+		assert_eq!(divergence.right.unwrap().value, b"context v1");
+	}
+}
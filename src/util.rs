@@ -1,16 +1,34 @@
 #![deny(missing_docs)]
 #![allow(non_snake_case)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
 use alloc::{vec, vec::Vec};
+#[cfg(all(feature = "alloc", not(feature = "zeroize")))]
 use clear_on_drop::clear::Clear;
+#[cfg(feature = "alloc")]
+use core::borrow::Borrow;
 use curve25519_dalek::scalar::Scalar;
-
+#[cfg(feature = "rayon")]
+use curve25519_dalek::traits::Identity;
+#[cfg(feature = "alloc")]
+use curve25519_dalek::{
+	ristretto::RistrettoPoint,
+	traits::{MultiscalarMul, VartimeMultiscalarMul},
+};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(all(feature = "alloc", feature = "zeroize"))]
+use zeroize::Zeroize;
+
+#[cfg(feature = "alloc")]
 use crate::inner_product_proof::inner_product;
 
 /// Represents a degree-1 vector polynomial \\(\mathbf{a} + \mathbf{b} \cdot
 /// x\\).
+#[cfg(feature = "alloc")]
 pub struct VecPoly1(pub Vec<Scalar>, pub Vec<Scalar>);
 
 /// Represents a degree-3 vector polynomial
@@ -20,6 +38,7 @@ pub struct VecPoly1(pub Vec<Scalar>, pub Vec<Scalar>);
 pub struct VecPoly3(pub Vec<Scalar>, pub Vec<Scalar>, pub Vec<Scalar>, pub Vec<Scalar>);
 
 /// Represents a degree-2 scalar polynomial \\(a + b \cdot x + c \cdot x^2\\)
+#[cfg(feature = "alloc")]
 pub struct Poly2(pub Scalar, pub Scalar, pub Scalar);
 
 /// Represents a degree-6 scalar polynomial, without the zeroth degree
@@ -63,6 +82,7 @@ pub fn exp_iter(x: Scalar) -> ScalarExp {
 	ScalarExp { x, next_exp_x }
 }
 
+#[cfg(feature = "alloc")]
 pub fn add_vec(a: &[Scalar], b: &[Scalar]) -> Vec<Scalar> {
 	if a.len() != b.len() {
 		// throw some error
@@ -75,6 +95,7 @@ pub fn add_vec(a: &[Scalar], b: &[Scalar]) -> Vec<Scalar> {
 	out
 }
 
+#[cfg(feature = "alloc")]
 impl VecPoly1 {
 	pub fn zero(n: usize) -> Self {
 		VecPoly1(vec![Scalar::zero(); n], vec![Scalar::zero(); n])
@@ -144,6 +165,7 @@ impl VecPoly3 {
 	}
 }
 
+#[cfg(feature = "alloc")]
 impl Poly2 {
 	pub fn eval(&self, x: Scalar) -> Scalar {
 		self.0 + x * (self.1 + x * self.2)
@@ -157,52 +179,69 @@ impl Poly6 {
 	}
 }
 
+/// Overwrites a secret with zeros.
+///
+/// With the `zeroize` feature, this uses the `zeroize` crate, whose
+/// writes are guaranteed not to be optimized away.
+#[cfg(all(feature = "alloc", feature = "zeroize"))]
+pub fn clear_secret<T: Zeroize>(secret: &mut T) {
+	secret.zeroize();
+}
+
+/// Overwrites a secret with zeros.
+#[cfg(all(feature = "alloc", not(feature = "zeroize")))]
+pub fn clear_secret<T: Clear>(secret: &mut T) {
+	secret.clear();
+}
+
+/// Overwrites a slice of secret scalars with zeros, leaving its length
+/// unchanged.
+///
+/// Calling `clear()` on a `Vec` only clears its header, so secret
+/// vectors must be cleared through this function instead.
+#[cfg(feature = "alloc")]
+pub fn clear_secrets(secrets: &mut [Scalar]) {
+	for secret in secrets.iter_mut() {
+		clear_secret(secret);
+	}
+}
+
+#[cfg(feature = "alloc")]
 impl Drop for VecPoly1 {
 	fn drop(&mut self) {
-		for e in self.0.iter_mut() {
-			e.clear();
-		}
-		for e in self.1.iter_mut() {
-			e.clear();
-		}
+		clear_secrets(&mut self.0);
+		clear_secrets(&mut self.1);
 	}
 }
 
+#[cfg(feature = "alloc")]
 impl Drop for Poly2 {
 	fn drop(&mut self) {
-		self.0.clear();
-		self.1.clear();
-		self.2.clear();
+		clear_secret(&mut self.0);
+		clear_secret(&mut self.1);
+		clear_secret(&mut self.2);
 	}
 }
 
 #[cfg(feature = "yoloproofs")]
 impl Drop for VecPoly3 {
 	fn drop(&mut self) {
-		for e in self.0.iter_mut() {
-			e.clear();
-		}
-		for e in self.1.iter_mut() {
-			e.clear();
-		}
-		for e in self.2.iter_mut() {
-			e.clear();
-		}
-		for e in self.3.iter_mut() {
-			e.clear();
-		}
+		clear_secrets(&mut self.0);
+		clear_secrets(&mut self.1);
+		clear_secrets(&mut self.2);
+		clear_secrets(&mut self.3);
 	}
 }
 
 #[cfg(feature = "yoloproofs")]
 impl Drop for Poly6 {
 	fn drop(&mut self) {
-		self.t1.clear();
-		self.t2.clear();
-		self.t3.clear();
-		self.t4.clear();
-		self.t5.clear();
-		self.t6.clear();
+		clear_secret(&mut self.t1);
+		clear_secret(&mut self.t2);
+		clear_secret(&mut self.t3);
+		clear_secret(&mut self.t4);
+		clear_secret(&mut self.t5);
+		clear_secret(&mut self.t6);
 	}
 }
 
@@ -210,6 +249,7 @@ impl Drop for Poly6 {
 /// with (1 to 2)*lg(n) scalar multiplications.
 /// TODO: a consttime version of this would be awfully similar to a Montgomery
 /// ladder.
+#[cfg(feature = "alloc")]
 pub fn scalar_exp_vartime(x: &Scalar, mut n: u64) -> Scalar {
 	let mut result = Scalar::one();
 	let mut aux = *x; // x, x^2, x^4, x^8, ...
@@ -252,6 +292,133 @@ fn sum_of_powers_slow(x: &Scalar, n: usize) -> Scalar {
 	exp_iter(*x).take(n).sum()
 }
 
+/// The smallest number of terms handed to a single thread when splitting
+/// work with the `rayon` feature.
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_MIN: usize = 64;
+
+/// Computes a constant-time multiscalar multiplication.
+///
+/// With the `rayon` feature, the terms are split into chunks which are
+/// multiplied on separate threads.
+#[cfg(feature = "alloc")]
+pub fn multiscalar_mul<I, J>(scalars: I, points: J) -> RistrettoPoint
+where
+	I: IntoIterator,
+	I::Item: Borrow<Scalar>,
+	J: IntoIterator,
+	J::Item: Borrow<RistrettoPoint>,
+{
+	#[cfg(not(feature = "rayon"))]
+	return RistrettoPoint::multiscalar_mul(scalars, points);
+
+	#[cfg(feature = "rayon")]
+	{
+		// The scalars may be secret, so clear our copy of them.
+		let mut scalars: Vec<Scalar> = scalars.into_iter().map(|s| *s.borrow()).collect();
+		let points: Vec<RistrettoPoint> = points.into_iter().map(|p| *p.borrow()).collect();
+		let result = par_multiscalar_mul(&scalars, &points, |s, p| {
+			RistrettoPoint::multiscalar_mul(s.iter(), p.iter())
+		});
+		clear_secrets(&mut scalars);
+		result
+	}
+}
+
+/// Computes a variable-time multiscalar multiplication.
+///
+/// With the `rayon` feature, the terms are split into chunks which are
+/// multiplied on separate threads.
+#[cfg(feature = "alloc")]
+pub fn vartime_multiscalar_mul<I, J>(scalars: I, points: J) -> RistrettoPoint
+where
+	I: IntoIterator,
+	I::Item: Borrow<Scalar>,
+	J: IntoIterator,
+	J::Item: Borrow<RistrettoPoint>,
+{
+	#[cfg(not(feature = "rayon"))]
+	return RistrettoPoint::vartime_multiscalar_mul(scalars, points);
+
+	#[cfg(feature = "rayon")]
+	{
+		let scalars: Vec<Scalar> = scalars.into_iter().map(|s| *s.borrow()).collect();
+		let points: Vec<RistrettoPoint> = points.into_iter().map(|p| *p.borrow()).collect();
+		par_multiscalar_mul(&scalars, &points, |s, p| {
+			RistrettoPoint::vartime_multiscalar_mul(s.iter(), p.iter())
+		})
+	}
+}
+
+/// Computes a variable-time multiscalar multiplication, or returns `None`
+/// if any of the points is `None`.
+///
+/// With the `rayon` feature, the terms are split into chunks which are
+/// multiplied on separate threads.
+#[cfg(feature = "alloc")]
+pub fn optional_multiscalar_mul<I, J>(scalars: I, points: J) -> Option<RistrettoPoint>
+where
+	I: IntoIterator,
+	I::Item: Borrow<Scalar>,
+	J: IntoIterator<Item = Option<RistrettoPoint>>,
+{
+	#[cfg(not(feature = "rayon"))]
+	return RistrettoPoint::optional_multiscalar_mul(scalars, points);
+
+	#[cfg(feature = "rayon")]
+	{
+		let scalars: Vec<Scalar> = scalars.into_iter().map(|s| *s.borrow()).collect();
+		let points: Vec<RistrettoPoint> = points.into_iter().collect::<Option<_>>()?;
+		Some(par_multiscalar_mul(&scalars, &points, |s, p| {
+			RistrettoPoint::vartime_multiscalar_mul(s, p)
+		}))
+	}
+}
+
+#[cfg(feature = "rayon")]
+fn par_multiscalar_mul<F>(scalars: &[Scalar], points: &[RistrettoPoint], mul: F) -> RistrettoPoint
+where
+	F: Fn(&[Scalar], &[RistrettoPoint]) -> RistrettoPoint + Sync,
+{
+	assert_eq!(scalars.len(), points.len());
+	let chunk = par_chunk_len(scalars.len());
+	scalars
+		.par_chunks(chunk)
+		.zip(points.par_chunks(chunk))
+		.map(|(s, p)| mul(s, p))
+		.reduce(RistrettoPoint::identity, |a, b| a + b)
+}
+
+/// Returns the chunk length that splits `len` items evenly across the
+/// threads of the current pool, with at least `PAR_CHUNK_MIN` per chunk.
+#[cfg(feature = "rayon")]
+fn par_chunk_len(len: usize) -> usize {
+	let threads = rayon::current_num_threads();
+	core::cmp::max(PAR_CHUNK_MIN, (len + threads - 1) / threads)
+}
+
+/// Replaces each `lo[i]` by `f(i, &lo[i], &hi[i])`.
+///
+/// With the `rayon` feature, the items are processed on separate threads.
+#[cfg(feature = "alloc")]
+pub fn fold_in_place<T, F>(lo: &mut [T], hi: &[T], f: F)
+where
+	T: Send + Sync,
+	F: Fn(usize, &T, &T) -> T + Send + Sync,
+{
+	#[cfg(not(feature = "rayon"))]
+	for (i, (l, h)) in lo.iter_mut().zip(hi.iter()).enumerate() {
+		*l = f(i, l, h);
+	}
+
+	#[cfg(feature = "rayon")]
+	lo.par_iter_mut()
+		.zip(hi.par_iter())
+		.with_min_len(PAR_CHUNK_MIN)
+		.enumerate()
+		.for_each(|(i, (l, h))| *l = f(i, l, h));
+}
+
 /// Given `data` with `len >= 32`, return the first 32 bytes.
 pub fn read32(data: &[u8]) -> [u8; 32] {
 	let mut buf32 = [0u8; 32];
@@ -259,9 +426,65 @@ pub fn read32(data: &[u8]) -> [u8; 32] {
 	buf32
 }
 
+/// Decodes a hex string, in either case, or returns `None` if it is
+/// malformed.
+#[cfg(feature = "alloc")]
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+	fn nibble(c: u8) -> Option<u8> {
+		match c {
+			b'0'..=b'9' => Some(c - b'0'),
+			b'a'..=b'f' => Some(c - b'a' + 10),
+			b'A'..=b'F' => Some(c - b'A' + 10),
+			_ => None,
+		}
+	}
+
+	let hex = hex.as_bytes();
+	if hex.len() % 2 != 0 {
+		return None;
+	}
+	hex.chunks(2)
+		.map(|pair| Some((nibble(pair[0])? << 4) | nibble(pair[1])?))
+		.collect()
+}
+
+/// The version tag of the versioned proof encoding.
+#[cfg(feature = "alloc")]
+pub const ENCODING_VERSION: u8 = 1;
+
+/// Prefixes the proof encoding `payload` with the version tag and its
+/// length as a little-endian `u32`.
+#[cfg(feature = "alloc")]
+pub fn encode_versioned(payload: &[u8]) -> Vec<u8> {
+	use byteorder::{ByteOrder, LittleEndian};
+
+	let mut buf = vec![0u8; 5 + payload.len()];
+	buf[0] = ENCODING_VERSION;
+	LittleEndian::write_u32(&mut buf[1..5], payload.len() as u32);
+	buf[5..].copy_from_slice(payload);
+	buf
+}
+
+/// Returns the payload of an encoding written by [`encode_versioned`], or
+/// `None` if its version is unknown or its length does not match.
+#[cfg(feature = "alloc")]
+pub fn decode_versioned(slice: &[u8]) -> Option<&[u8]> {
+	use byteorder::{ByteOrder, LittleEndian};
+
+	if slice.len() < 5 || slice[0] != ENCODING_VERSION {
+		return None;
+	}
+	let len = LittleEndian::read_u32(&slice[1..5]) as usize;
+	if slice.len() - 5 != len {
+		return None;
+	}
+	Some(&slice[5..])
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use clear_on_drop::clear::Clear;
 
 	#[test]
 	fn exp_2_is_powers_of_2() {
@@ -361,6 +584,17 @@ mod tests {
 		assert_eq!(v[1], Scalar::zero());
 	}
 
+	#[test]
+	fn clear_secrets_keeps_length() {
+		let mut v = vec![Scalar::from(24u64), Scalar::from(42u64)];
+		clear_secrets(&mut v);
+		assert_eq!(v, vec![Scalar::zero(); 2]);
+
+		let mut s = Scalar::from(7u64);
+		clear_secret(&mut s);
+		assert_eq!(s, Scalar::zero());
+	}
+
 	#[test]
 	fn tuple_of_scalars_clear_on_drop() {
 		let mut v = Poly2(Scalar::from(24u64), Scalar::from(42u64), Scalar::from(255u64));
@@ -380,4 +614,45 @@ mod tests {
 		assert_eq!(v.1, Scalar::zero());
 		assert_eq!(v.2, Scalar::zero());
 	}
+
+	#[test]
+	fn multiscalar_mul_matches_naive_sum() {
+		use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+
+		// Long enough to be split into several chunks with `rayon`.
+		let n = 300;
+		let scalars: Vec<Scalar> = (0..n).map(|i| Scalar::from(3 * i as u64 + 1)).collect();
+		let points: Vec<RistrettoPoint> = (0..n)
+			.map(|i| Scalar::from(i as u64 + 7) * RISTRETTO_BASEPOINT_POINT)
+			.collect();
+		let expected: RistrettoPoint = scalars.iter().zip(points.iter()).map(|(s, p)| s * p).sum();
+
+		assert_eq!(multiscalar_mul(&scalars, &points), expected);
+		assert_eq!(vartime_multiscalar_mul(&scalars, &points), expected);
+		assert_eq!(
+			optional_multiscalar_mul(&scalars, points.iter().map(|p| Some(*p))),
+			Some(expected)
+		);
+		assert_eq!(
+			optional_multiscalar_mul(
+				&scalars,
+				points
+					.iter()
+					.enumerate()
+					.map(|(i, p)| if i == 150 { None } else { Some(*p) })
+			),
+			None
+		);
+	}
+
+	#[test]
+	fn fold_in_place_uses_matching_indices() {
+		let mut lo: Vec<u64> = (0..200).collect();
+		let hi: Vec<u64> = (200..400).collect();
+		fold_in_place(&mut lo, &hi, |i, l, h| i as u64 * 1000 + l + h);
+
+		for (i, x) in lo.iter().enumerate() {
+			assert_eq!(*x, i as u64 * 1000 + 2 * i as u64 + 200);
+		}
+	}
 }
@@ -0,0 +1,504 @@
+//! The `vector_commitment` module contains Pedersen commitments to vectors
+//! of scalars, and proofs linking them to commitments to the individual
+//! entries.
+//!
+//! A [`VectorCommitment`] commits to \\(\mathbf{v}\\) as
+//! \\(C = \langle \mathbf{v}, \mathbf{G} \rangle + \tilde{v}\tilde{B}\\),
+//! using the \\(\mathbf{G}\\) generators of the first party of a
+//! [`BulletproofGens`].  A [`VectorLinkProof`] shows that the entries of
+//! \\(\mathbf{v}\\) are also committed to by Pedersen commitments
+//! \\(V_i = v_i B + \tilde{v}_i \tilde{B}\\), which can be used as
+//! high-level variables of a constraint system.  A witness vector can so
+//! be committed to once, and linked to fresh commitments for each circuit
+//! it is used in.
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::IsIdentity,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	transcript::TranscriptProtocol,
+	util,
+};
+
+#[cfg(feature = "scale")]
+use codec::{Compact, Decode, Encode, EncodeLike, Input, Output};
+
+#[cfg(feature = "serde")]
+use crate::serialization;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A Pedersen commitment \\(\langle \mathbf{v}, \mathbf{G} \rangle +
+/// \tilde{v}\tilde{B}\\) to a vector of scalars \\(\mathbf{v}\\) with a
+/// single blinding factor \\(\tilde{v}\\), in compressed form.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct VectorCommitment(pub CompressedRistretto);
+
+impl VectorCommitment {
+	/// Commits to `values` with the blinding factor `blinding`.
+	///
+	/// Returns an error if `bp_gens` has fewer than `values.len()`
+	/// generators per party.
+	pub fn commit(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		values: &[Scalar],
+		blinding: Scalar,
+	) -> Result<Self, ProofError> {
+		Ok(VectorCommitment(
+			commit_vector(bp_gens, pc_gens, values, &blinding)?.compress(),
+		))
+	}
+
+	/// Checks that this is a commitment to `values` with the blinding
+	/// factor `blinding`.
+	pub fn verify_opening(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		values: &[Scalar],
+		blinding: Scalar,
+	) -> bool {
+		match commit_vector(bp_gens, pc_gens, values, &blinding) {
+			Ok(point) => point.compress() == self.0,
+			Err(_) => false,
+		}
+	}
+}
+
+/// A proof that the entries of the vector committed to by a
+/// [`VectorCommitment`] are committed to by a list of Pedersen
+/// commitments, one per entry.
+///
+/// The verifier draws a challenge \\(e\\) and the proof shows knowledge of
+/// an opening of \\(C\\) and of \\(\sum_i e^i V_i\\) with the same values,
+/// which by the binding property of the commitments and the randomness
+/// of \\(e\\) implies that each \\(V_i\\) commits to \\(v_i\\).
+#[derive(Clone, Debug)]
+pub struct VectorLinkProof {
+	/// Commitment to the vector nonces
+	A_vec: CompressedRistretto,
+	/// Commitment to the combined value nonces
+	A_values: CompressedRistretto,
+	/// Response for the blinding factor of the vector commitment
+	s_blinding: Scalar,
+	/// Response for the combined blinding factors of the value commitments
+	s_value_blinding: Scalar,
+	/// Responses for the entries of the vector
+	s_values: Vec<Scalar>,
+}
+
+impl VectorLinkProof {
+	/// Commits to `values` both as a [`VectorCommitment`] with the
+	/// blinding factor `blinding`, and entry by entry with the blinding
+	/// factors `value_blindings`, and proves that the commitments agree.
+	///
+	/// Returns the proof, the vector commitment and the commitments to the
+	/// entries.
+	pub fn prove_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[Scalar],
+		blinding: &Scalar,
+		value_blindings: &[Scalar],
+		rng: &mut T,
+	) -> Result<(VectorLinkProof, VectorCommitment, Vec<CompressedRistretto>), ProofError> {
+		if values.len() != value_blindings.len() {
+			return Err(ProofError::WrongNumBlindingFactors {
+				expected: values.len(),
+				actual: value_blindings.len(),
+			});
+		}
+		let k = values.len();
+
+		let C = commit_vector(bp_gens, pc_gens, values, blinding)?.compress();
+		let value_commitments: Vec<CompressedRistretto> = values
+			.iter()
+			.zip(value_blindings.iter())
+			.map(|(v, v_blinding)| pc_gens.commit(*v, *v_blinding).compress())
+			.collect();
+
+		transcript.vector_link_domain_sep(k as u64);
+		transcript.append_point(b"C", &C);
+		for V in value_commitments.iter() {
+			transcript.append_point(b"V", V);
+		}
+		let e = transcript.challenge_scalar(b"e");
+
+		let mut values_nonces: Vec<Scalar> = (0..k).map(|_| Scalar::random(rng)).collect();
+		let mut blinding_nonce = Scalar::random(rng);
+		let mut value_blinding_nonce = Scalar::random(rng);
+
+		let A_vec = commit_vector(bp_gens, pc_gens, &values_nonces, &blinding_nonce)?.compress();
+		let combined_nonce: Scalar = values_nonces
+			.iter()
+			.zip(util::exp_iter(e))
+			.map(|(r, exp_e)| r * exp_e)
+			.sum();
+		let A_values = pc_gens.commit(combined_nonce, value_blinding_nonce).compress();
+
+		transcript.append_point(b"A_vec", &A_vec);
+		transcript.append_point(b"A_values", &A_values);
+		let c = transcript.challenge_scalar(b"c");
+
+		let combined_blinding: Scalar = value_blindings
+			.iter()
+			.zip(util::exp_iter(e))
+			.map(|(v_blinding, exp_e)| v_blinding * exp_e)
+			.sum();
+		let proof = VectorLinkProof {
+			A_vec,
+			A_values,
+			s_blinding: blinding_nonce + c * blinding,
+			s_value_blinding: value_blinding_nonce + c * combined_blinding,
+			s_values: values_nonces
+				.iter()
+				.zip(values.iter())
+				.map(|(r, v)| r + c * v)
+				.collect(),
+		};
+
+		util::clear_secrets(&mut values_nonces);
+		util::clear_secret(&mut blinding_nonce);
+		util::clear_secret(&mut value_blinding_nonce);
+
+		Ok((proof, VectorCommitment(C), value_commitments))
+	}
+
+	/// Commits to `values` both as a [`VectorCommitment`] and entry by
+	/// entry, and proves that the commitments agree.
+	///
+	/// This is a convenience wrapper around
+	/// [`VectorLinkProof::prove_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[Scalar],
+		blinding: &Scalar,
+		value_blindings: &[Scalar],
+	) -> Result<(VectorLinkProof, VectorCommitment, Vec<CompressedRistretto>), ProofError> {
+		VectorLinkProof::prove_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			values,
+			blinding,
+			value_blindings,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies that the entries of the vector committed to by
+	/// `commitment` are committed to by `value_commitments`, in order.
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		commitment: &VectorCommitment,
+		value_commitments: &[CompressedRistretto],
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let k = value_commitments.len();
+		if self.s_values.len() != k {
+			return Err(ProofError::VerificationError);
+		}
+		if bp_gens.gens_capacity < k {
+			return Err(ProofError::InvalidGeneratorsLength);
+		}
+
+		transcript.vector_link_domain_sep(k as u64);
+		transcript.append_point(b"C", &commitment.0);
+		for V in value_commitments.iter() {
+			transcript.append_point(b"V", V);
+		}
+		let e = transcript.challenge_scalar(b"e");
+
+		transcript.validate_and_append_point(b"A_vec", &self.A_vec)?;
+		transcript.validate_and_append_point(b"A_values", &self.A_values)?;
+		let c = transcript.challenge_scalar(b"c");
+
+		// Random weight for combining the two verification equations
+		let w = Scalar::random(rng);
+
+		let combined_s: Scalar = self
+			.s_values
+			.iter()
+			.zip(util::exp_iter(e))
+			.map(|(s, exp_e)| s * exp_e)
+			.sum();
+
+		let mega_check = util::optional_multiscalar_mul(
+			self.s_values
+				.iter()
+				.cloned()
+				.chain(iter::once(w * combined_s))
+				.chain(iter::once(self.s_blinding + w * self.s_value_blinding))
+				.chain(iter::once(-Scalar::one()))
+				.chain(iter::once(-w))
+				.chain(iter::once(-c))
+				.chain(util::exp_iter(e).take(k).map(|exp_e| -w * c * exp_e)),
+			bp_gens
+				.share(0)
+				.G(k)
+				.map(|G_i| Some(*G_i))
+				.chain(iter::once(Some(pc_gens.B)))
+				.chain(iter::once(Some(pc_gens.B_blinding)))
+				.chain(iter::once(self.A_vec.decompress()))
+				.chain(iter::once(self.A_values.decompress()))
+				.chain(iter::once(commitment.0.decompress()))
+				.chain(value_commitments.iter().map(|V| V.decompress())),
+		)
+		.ok_or(ProofError::VerificationError)?;
+
+		if mega_check.is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+
+	/// Verifies that the entries of the vector committed to by
+	/// `commitment` are committed to by `value_commitments`, in order.
+	///
+	/// This is a convenience wrapper around
+	/// [`VectorLinkProof::verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		commitment: &VectorCommitment,
+		value_commitments: &[CompressedRistretto],
+	) -> Result<(), ProofError> {
+		self.verify_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			commitment,
+			value_commitments,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Returns the size in bytes required to serialize the proof.
+	pub fn serialized_size(&self) -> usize {
+		(4 + self.s_values.len()) * 32
+	}
+
+	/// Serializes the proof into a byte array of \\(k + 4\\) 32-byte
+	/// elements, where \\(k\\) is the length of the vector.
+	///
+	/// # Layout
+	///
+	/// The layout of the proof encoding is:
+	///
+	/// * two compressed Ristretto points \\(A_{vec}, A_{values}\\),
+	/// * two scalars \\(s_{blinding}, s_{value\\_blinding}\\),
+	/// * \\(k\\) scalars, the responses for the entries of the vector.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(self.serialized_size());
+		buf.extend_from_slice(self.A_vec.as_bytes());
+		buf.extend_from_slice(self.A_values.as_bytes());
+		buf.extend_from_slice(self.s_blinding.as_bytes());
+		buf.extend_from_slice(self.s_value_blinding.as_bytes());
+		for s in self.s_values.iter() {
+			buf.extend_from_slice(s.as_bytes());
+		}
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `VectorLinkProof`.
+	pub fn from_bytes(slice: &[u8]) -> Result<VectorLinkProof, ProofError> {
+		if slice.len() % 32 != 0 || slice.len() < 4 * 32 {
+			return Err(ProofError::FormatError);
+		}
+
+		use crate::util::read32;
+
+		let scalar = |chunk: &[u8]| Scalar::from_canonical_bytes(read32(chunk)).ok_or(ProofError::FormatError);
+
+		Ok(VectorLinkProof {
+			A_vec: CompressedRistretto(read32(&slice[0..])),
+			A_values: CompressedRistretto(read32(&slice[32..])),
+			s_blinding: scalar(&slice[2 * 32..])?,
+			s_value_blinding: scalar(&slice[3 * 32..])?,
+			s_values: slice[4 * 32..].chunks(32).map(scalar).collect::<Result<_, _>>()?,
+		})
+	}
+}
+
+/// Computes \\(\langle \mathbf{v}, \mathbf{G} \rangle + \tilde{v}\tilde{B}\\)
+/// over the generators of the first party.
+fn commit_vector(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	values: &[Scalar],
+	blinding: &Scalar,
+) -> Result<RistrettoPoint, ProofError> {
+	if bp_gens.gens_capacity < values.len() {
+		return Err(ProofError::InvalidGeneratorsLength);
+	}
+
+	Ok(util::multiscalar_mul(
+		values.iter().chain(iter::once(blinding)),
+		bp_gens.share(0).G(values.len()).chain(iter::once(&pc_gens.B_blinding)),
+	))
+}
+
+// The SCALE encoding is the length-prefixed byte encoding from `to_bytes`.
+#[cfg(feature = "scale")]
+impl Encode for VectorLinkProof {
+	fn size_hint(&self) -> usize {
+		let len = self.serialized_size();
+		Compact(len as u32).size_hint() + len
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.to_bytes().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for VectorLinkProof {}
+
+#[cfg(feature = "scale")]
+impl Decode for VectorLinkProof {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let bytes = Vec::<u8>::decode(input)?;
+		VectorLinkProof::from_bytes(&bytes).map_err(|_| "Invalid VectorLinkProof encoding".into())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for VectorLinkProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serialization::serialize_bytes(&self.to_bytes(), serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for VectorLinkProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let bytes = serialization::deserialize_bytes(deserializer, "a valid VectorLinkProof")?;
+		VectorLinkProof::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scalars(values: &[u64]) -> Vec<Scalar> {
+		values.iter().map(|v| Scalar::from(*v)).collect()
+	}
+
+	#[test]
+	fn commit_and_open() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(4, 1);
+		let values = scalars(&[1, 2, 3]);
+		let blinding = Scalar::from(9u64);
+
+		let C = VectorCommitment::commit(&bp_gens, &pc_gens, &values, blinding).unwrap();
+		assert!(C.verify_opening(&bp_gens, &pc_gens, &values, blinding));
+		assert!(!C.verify_opening(&bp_gens, &pc_gens, &scalars(&[1, 3, 2]), blinding));
+		assert!(!C.verify_opening(&bp_gens, &pc_gens, &values, Scalar::one()));
+
+		assert_eq!(
+			VectorCommitment::commit(&bp_gens, &pc_gens, &scalars(&[1, 2, 3, 4, 5]), blinding),
+			Err(ProofError::InvalidGeneratorsLength)
+		);
+	}
+
+	#[test]
+	fn link_proof() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(8, 1);
+		let values = scalars(&[5, 0, 7, 11, 13]);
+		let blinding = Scalar::random(&mut thread_rng());
+		let value_blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut thread_rng())).collect();
+
+		let (proof, C, Vs) = VectorLinkProof::prove(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"VectorLinkTest"),
+			&values,
+			&blinding,
+			&value_blindings,
+		)
+		.unwrap();
+
+		assert!(C.verify_opening(&bp_gens, &pc_gens, &values, blinding));
+		assert!(proof
+			.verify(&bp_gens, &pc_gens, &mut Transcript::new(b"VectorLinkTest"), &C, &Vs)
+			.is_ok());
+
+		// The entries must be committed to in order.
+		let mut swapped = Vs.clone();
+		swapped.swap(0, 2);
+		assert!(proof
+			.verify(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"VectorLinkTest"),
+				&C,
+				&swapped
+			)
+			.is_err());
+
+		// A commitment to a different value is rejected.
+		let mut other = Vs.clone();
+		other[1] = pc_gens.commit(Scalar::one(), value_blindings[1]).compress();
+		assert!(proof
+			.verify(&bp_gens, &pc_gens, &mut Transcript::new(b"VectorLinkTest"), &C, &other)
+			.is_err());
+		assert!(proof
+			.verify(
+				&bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"VectorLinkTest"),
+				&C,
+				&Vs[..4]
+			)
+			.is_err());
+
+		let decoded = VectorLinkProof::from_bytes(&proof.to_bytes()).unwrap();
+		assert_eq!(decoded.to_bytes().len(), proof.serialized_size());
+		assert!(decoded
+			.verify(&bp_gens, &pc_gens, &mut Transcript::new(b"VectorLinkTest"), &C, &Vs)
+			.is_ok());
+		let bytes = proof.to_bytes();
+		assert!(VectorLinkProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+	}
+}
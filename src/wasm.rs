@@ -0,0 +1,170 @@
+//! `wasm-bindgen` bindings for single-value range proofs and Pedersen
+//! commitments.
+//!
+//! The bindings take and return byte arrays, so they can be called from
+//! JavaScript without further glue:
+//!
+//! * scalars (the blinding factors) are 32-byte canonical little-endian
+//!   encodings;
+//! * commitments are 32-byte compressed Ristretto points;
+//! * proofs use the encoding of [`RangeProof::to_bytes`].
+//!
+//! Each proof is bound to application-defined `context` bytes, as with
+//! [`RangeProof::prove_single_with_context`], in place of a transcript.
+//! Errors are thrown as strings holding the message of the
+//! [`ProofError`].
+//!
+//! The `wasm` feature also enables the `wasm-bindgen` backend of
+//! `getrandom`, so the proofs draw their randomness from
+//! `crypto.getRandomValues` on `wasm32-unknown-unknown`.
+
+use std::vec::Vec;
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+use wasm_bindgen::prelude::*;
+
+use crate::{errors::ProofError, BulletproofGens, PedersenGens, RangeProof};
+
+/// The transcript label of the proofs created by the bindings.
+const TRANSCRIPT_LABEL: &[u8] = b"WasmRangeProof";
+
+/// A range proof and the commitment to the value it is about, as returned
+/// by [`prove_single`].
+#[wasm_bindgen]
+pub struct SingleRangeProof {
+	proof: Vec<u8>,
+	commitment: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl SingleRangeProof {
+	/// Returns the encoding of the proof.
+	#[wasm_bindgen(getter)]
+	pub fn proof(&self) -> Vec<u8> {
+		self.proof.clone()
+	}
+
+	/// Returns the compressed commitment to the value.
+	#[wasm_bindgen(getter)]
+	pub fn commitment(&self) -> Vec<u8> {
+		self.commitment.clone()
+	}
+}
+
+/// Returns the Pedersen commitment to `value` with the 32-byte
+/// `blinding` factor, with the default [`PedersenGens`].
+#[wasm_bindgen]
+pub fn commit(value: u64, blinding: &[u8]) -> Result<Vec<u8>, JsValue> {
+	commit_bytes(value, blinding).map_err(to_js)
+}
+
+/// Proves that `value` lies in `[0, 2^bitsize)`, with the 32-byte
+/// `blinding` factor of its commitment, and binds the proof to `context`.
+#[wasm_bindgen]
+pub fn prove_single(value: u64, blinding: &[u8], bitsize: usize, context: &[u8]) -> Result<SingleRangeProof, JsValue> {
+	prove_single_bytes(value, blinding, bitsize, context).map_err(to_js)
+}
+
+/// Verifies a proof created by [`prove_single`] for the same `bitsize`
+/// and `context`.
+///
+/// Returns whether the proof is valid, and throws if the proof or the
+/// commitment is malformed.
+#[wasm_bindgen]
+pub fn verify_single(proof: &[u8], commitment: &[u8], bitsize: usize, context: &[u8]) -> Result<bool, JsValue> {
+	match verify_single_bytes(proof, commitment, bitsize, context) {
+		Ok(()) => Ok(true),
+		Err(ProofError::VerificationError) => Ok(false),
+		Err(e) => Err(to_js(e)),
+	}
+}
+
+fn to_js(e: ProofError) -> JsValue {
+	JsValue::from_str(&e.to_string())
+}
+
+fn scalar_from_slice(bytes: &[u8]) -> Result<Scalar, ProofError> {
+	if bytes.len() != 32 {
+		return Err(ProofError::FormatError);
+	}
+	let mut buf = [0u8; 32];
+	buf.copy_from_slice(bytes);
+	Scalar::from_canonical_bytes(buf).ok_or(ProofError::FormatError)
+}
+
+fn point_from_slice(bytes: &[u8]) -> Result<CompressedRistretto, ProofError> {
+	if bytes.len() != 32 {
+		return Err(ProofError::FormatError);
+	}
+	Ok(CompressedRistretto::from_slice(bytes))
+}
+
+fn commit_bytes(value: u64, blinding: &[u8]) -> Result<Vec<u8>, ProofError> {
+	let blinding = scalar_from_slice(blinding)?;
+	let commitment = PedersenGens::default().commit(Scalar::from(value), blinding);
+	Ok(commitment.compress().to_bytes().to_vec())
+}
+
+fn prove_single_bytes(
+	value: u64,
+	blinding: &[u8],
+	bitsize: usize,
+	context: &[u8],
+) -> Result<SingleRangeProof, ProofError> {
+	let blinding = scalar_from_slice(blinding)?;
+	let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+	let (proof, commitment) = RangeProof::prove_single_with_context(
+		&BulletproofGens::new(64, 1),
+		&PedersenGens::default(),
+		&mut transcript,
+		context,
+		value,
+		&blinding,
+		bitsize,
+	)?;
+	Ok(SingleRangeProof {
+		proof: proof.to_bytes(),
+		commitment: commitment.to_bytes().to_vec(),
+	})
+}
+
+fn verify_single_bytes(proof: &[u8], commitment: &[u8], bitsize: usize, context: &[u8]) -> Result<(), ProofError> {
+	let proof = RangeProof::from_bytes(proof)?;
+	let commitment = point_from_slice(commitment)?;
+	let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+	proof.verify_single_with_context(
+		&BulletproofGens::new(64, 1),
+		&PedersenGens::default(),
+		&mut transcript,
+		context,
+		&commitment,
+		bitsize,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn prove_and_verify_bytes() {
+		let blinding = Scalar::from(5u64).to_bytes();
+		let proof = prove_single_bytes(1000, &blinding, 32, b"context").unwrap();
+		assert_eq!(proof.commitment(), commit_bytes(1000, &blinding).unwrap());
+
+		assert!(verify_single_bytes(&proof.proof(), &proof.commitment(), 32, b"context").is_ok());
+		assert_eq!(
+			verify_single_bytes(&proof.proof(), &proof.commitment(), 32, b"other"),
+			Err(ProofError::VerificationError)
+		);
+		assert_eq!(
+			verify_single_bytes(&proof.proof(), &proof.commitment()[..31], 32, b"context"),
+			Err(ProofError::FormatError)
+		);
+		assert_eq!(
+			prove_single_bytes(1000, &[0xff; 32], 32, b"context").err(),
+			Some(ProofError::FormatError)
+		);
+	}
+}
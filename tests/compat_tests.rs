@@ -0,0 +1,230 @@
+#![allow(non_snake_case)]
+
+// Checks this crate against the test vectors in `tests/vectors`, which
+// other implementations of the protocol can use to check their wire
+// compatibility with it.
+//
+// The vectors contain the Pedersen generators, commitments, transcript
+// challenges and range proofs, all hex-encoded.  The range proofs with
+// the source `upstream-1.0.0` were created by version 1.0.0 of the
+// dalek-cryptography implementation and must keep verifying.  The others
+// were created by this crate from a seeded RNG, and must be reproduced
+// byte for byte.
+
+use rand_core::SeedableRng;
+
+use rand_chacha::ChaChaRng;
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+
+use merlin::Transcript;
+
+use serde_json::Value;
+
+use webb_bulletproofs::{BulletproofGens, PedersenGens, RangeProof, TranscriptProtocol};
+
+const RANGE_PROOF_VECTORS: &str = include_str!("vectors/range_proof.json");
+
+fn vectors() -> Value {
+	serde_json::from_str(RANGE_PROOF_VECTORS).unwrap()
+}
+
+fn bytes(value: &Value) -> Vec<u8> {
+	hex::decode(value.as_str().unwrap()).unwrap()
+}
+
+fn point(value: &Value) -> CompressedRistretto {
+	CompressedRistretto::from_slice(&bytes(value))
+}
+
+fn scalar(value: &Value) -> Scalar {
+	let mut buf = [0u8; 32];
+	buf.copy_from_slice(&bytes(value));
+	Scalar::from_canonical_bytes(buf).unwrap()
+}
+
+fn usize_field(value: &Value, key: &str) -> usize {
+	value[key].as_u64().unwrap() as usize
+}
+
+fn label(value: &Value) -> &'static [u8] {
+	// Merlin labels are static, so the vectors may only use these ones.
+	match value["label"].as_str().unwrap() {
+		"Deserialize-And-Verify Test" => b"Deserialize-And-Verify Test",
+		"CompatTest" => b"CompatTest",
+		other => panic!("unknown transcript label {}", other),
+	}
+}
+
+#[test]
+fn pedersen_gens_match() {
+	let vectors = vectors();
+	let pc_gens = PedersenGens::default();
+	assert_eq!(point(&vectors["pedersen_gens"]["B"]), pc_gens.B.compress());
+	assert_eq!(
+		point(&vectors["pedersen_gens"]["B_blinding"]),
+		pc_gens.B_blinding.compress()
+	);
+}
+
+#[test]
+fn commitments_match() {
+	let pc_gens = PedersenGens::default();
+	for vector in vectors()["commitments"].as_array().unwrap() {
+		let value = Scalar::from(vector["value"].as_u64().unwrap());
+		let commitment = pc_gens.commit(value, scalar(&vector["blinding"])).compress();
+		assert_eq!(commitment, point(&vector["commitment"]));
+	}
+}
+
+#[test]
+fn transcript_challenges_match() {
+	for vector in vectors()["transcripts"].as_array().unwrap() {
+		let mut transcript = Transcript::new(label(vector));
+		transcript.rangeproof_domain_sep(vector["n"].as_u64().unwrap(), vector["m"].as_u64().unwrap());
+		for V in vector["commitments"].as_array().unwrap() {
+			transcript.append_point(b"V", &point(V));
+		}
+		assert_eq!(transcript.challenge_scalar(b"y"), scalar(&vector["challenge"]));
+	}
+}
+
+#[test]
+fn range_proofs_match() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 8);
+
+	for vector in vectors()["range_proofs"].as_array().unwrap() {
+		let (n, m) = (usize_field(vector, "n"), usize_field(vector, "m"));
+		let proof_bytes = bytes(&vector["proof"]);
+		let commitments: Vec<CompressedRistretto> =
+			vector["commitments"].as_array().unwrap().iter().map(point).collect();
+		assert_eq!(commitments.len(), m);
+
+		let proof = RangeProof::from_bytes_strict(&proof_bytes).unwrap();
+		assert_eq!(proof.to_bytes(), proof_bytes);
+		let mut transcript = Transcript::new(label(vector));
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, n)
+			.is_ok());
+
+		if let Some(seed) = vector["seed"].as_u64() {
+			let values: Vec<u64> = vector["values"]
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|v| v.as_u64().unwrap())
+				.collect();
+			let (reproduced, reproduced_commitments) = prove_seeded(&bp_gens, &pc_gens, n, &values, seed as u8);
+			assert_eq!(reproduced.to_bytes(), proof_bytes);
+			assert_eq!(reproduced_commitments, commitments);
+		}
+	}
+}
+
+/// Creates a range proof with blinding factors and randomness drawn from
+/// a `ChaChaRng` seeded with `[seed; 32]`.
+fn prove_seeded(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	n: usize,
+	values: &[u64],
+	seed: u8,
+) -> (RangeProof, Vec<CompressedRistretto>) {
+	let mut rng = ChaChaRng::from_seed([seed; 32]);
+	let blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut rng)).collect();
+	let mut transcript = Transcript::new(b"CompatTest");
+	RangeProof::prove_multiple_with_rng(bp_gens, pc_gens, &mut transcript, values, &blindings, n, &mut rng).unwrap()
+}
+
+// This function generates the vectors created by this crate and dumps
+// them to stdout, for `tests/vectors/range_proof.json`.  The upstream
+// range proofs are copied from `deserialize_and_verify` in
+// `tests/range_proof.rs`.  It can be run by uncommenting the #[test]
+// annotation.  We allow(dead_code) to ensure that it continues to compile.
+//#[test]
+#[allow(dead_code)]
+fn generate_test_vectors() {
+	use serde_json::json;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 8);
+	let encode_point = |P: &CompressedRistretto| hex::encode(P.as_bytes());
+	let encode_scalar = |s: &Scalar| hex::encode(s.as_bytes());
+
+	let mut rng = ChaChaRng::from_seed([42u8; 32]);
+	let commitments: Vec<Value> = [0u64, 1, 1037, u64::max_value()]
+		.iter()
+		.map(|&value| {
+			let blinding = Scalar::random(&mut rng);
+			json!({
+				"value": value,
+				"blinding": encode_scalar(&blinding),
+				"commitment": encode_point(&pc_gens.commit(Scalar::from(value), blinding).compress()),
+			})
+		})
+		.collect();
+
+	let transcripts: Vec<Value> = [(8u64, 1u64), (64, 2)]
+		.iter()
+		.map(|&(n, m)| {
+			let points: Vec<CompressedRistretto> = (0..m)
+				.map(|_| {
+					pc_gens
+						.commit(Scalar::random(&mut rng), Scalar::random(&mut rng))
+						.compress()
+				})
+				.collect();
+			let mut transcript = Transcript::new(b"CompatTest");
+			transcript.rangeproof_domain_sep(n, m);
+			for V in &points {
+				transcript.append_point(b"V", V);
+			}
+			json!({
+				"label": "CompatTest",
+				"n": n,
+				"m": m,
+				"commitments": points.iter().map(encode_point).collect::<Vec<_>>(),
+				"challenge": encode_scalar(&transcript.challenge_scalar(b"y")),
+			})
+		})
+		.collect();
+
+	let range_proofs: Vec<Value> = [
+		(8usize, vec![0u64]),
+		(32, vec![7, 1 << 31]),
+		(64, vec![1, 2, 3, u64::max_value()]),
+	]
+	.iter()
+	.enumerate()
+	.map(|(seed, (n, values))| {
+		let (proof, commitments) = prove_seeded(&bp_gens, &pc_gens, *n, values, seed as u8);
+		json!({
+			"source": "webb-bulletproofs",
+			"label": "CompatTest",
+			"n": n,
+			"m": values.len(),
+			"seed": seed,
+			"values": values,
+			"commitments": commitments.iter().map(encode_point).collect::<Vec<_>>(),
+			"proof": hex::encode(proof.to_bytes()),
+		})
+	})
+	.collect();
+
+	println!(
+		"{}",
+		serde_json::to_string_pretty(&json!({
+			"pedersen_gens": {
+				"B": encode_point(&pc_gens.B.compress()),
+				"B_blinding": encode_point(&pc_gens.B_blinding.compress()),
+			},
+			"commitments": commitments,
+			"transcripts": transcripts,
+			"range_proofs": range_proofs,
+		}))
+		.unwrap()
+	);
+
+	panic!();
+}
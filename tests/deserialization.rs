@@ -0,0 +1,136 @@
+#![allow(non_snake_case)]
+
+// Property tests for the decoders of the proofs: on arbitrary bytes and on
+// mutations of valid encodings, they must return an error rather than
+// panic, and the strict decoders must only accept canonical encodings.
+// The `fuzz` directory has `cargo-fuzz` targets for the same decoders.
+
+use rand::Rng;
+use rand_core::SeedableRng;
+
+use rand_chacha::ChaChaRng;
+
+use curve25519_dalek::scalar::Scalar;
+
+use merlin::Transcript;
+
+use webb_bulletproofs::{BulletproofGens, InnerProductProof, PedersenGens, RangeProof};
+
+/// The number of random inputs tried per decoder and per strategy.
+const CASES: usize = 2000;
+
+/// Returns `CASES` inputs derived from `valid`: random bytes of nearby
+/// lengths, truncations and extensions, and copies with some bytes
+/// replaced or some 32-byte words set to non-canonical encodings.
+fn mutations(valid: &[u8], seed: u8) -> Vec<Vec<u8>> {
+	let mut rng = ChaChaRng::from_seed([seed; 32]);
+	let mut inputs = Vec::with_capacity(4 * CASES);
+
+	for _ in 0..CASES {
+		let len = rng.gen_range(0, valid.len() + 96);
+		inputs.push((0..len).map(|_| rng.gen()).collect());
+	}
+
+	for len in 0..valid.len() {
+		inputs.push(valid[..len].to_vec());
+	}
+	for extra in 1..=64 {
+		let mut input = valid.to_vec();
+		input.extend((0..extra).map(|_| rng.gen::<u8>()));
+		inputs.push(input);
+	}
+
+	for _ in 0..CASES {
+		let mut input = valid.to_vec();
+		for _ in 0..rng.gen_range(1, 4) {
+			let i = rng.gen_range(0, input.len());
+			input[i] = rng.gen();
+		}
+		inputs.push(input);
+	}
+
+	// All bits set is neither a canonical scalar nor a canonical point;
+	// the order of the group is the smallest non-canonical scalar.
+	let non_canonical: [[u8; 32]; 2] = [[0xff; 32], {
+		let mut l = (-Scalar::one()).to_bytes();
+		l[0] += 1;
+		l
+	}];
+	for _ in 0..CASES {
+		let mut input = valid.to_vec();
+		let word = rng.gen_range(0, input.len() / 32);
+		let start = input.len() % 32 + 32 * word;
+		input[start..start + 32].copy_from_slice(&non_canonical[rng.gen_range(0, 2)]);
+		inputs.push(input);
+	}
+
+	inputs
+}
+
+fn range_proof_bytes() -> Vec<u8> {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 2);
+	let mut rng = ChaChaRng::from_seed([1u8; 32]);
+	let (proof, _) = RangeProof::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut Transcript::new(b"DeserializationTest"),
+		&[7, 1037],
+		&[Scalar::random(&mut rng), Scalar::random(&mut rng)],
+		32,
+		&mut rng,
+	)
+	.unwrap();
+	proof.to_bytes()
+}
+
+#[test]
+fn range_proof_decoding_never_panics() {
+	let valid = range_proof_bytes();
+	assert!(RangeProof::from_bytes_strict(&valid).is_ok());
+
+	for input in mutations(&valid, 2) {
+		let _ = RangeProof::from_bytes(&input);
+		let _ = RangeProof::from_bytes_versioned(&input);
+		if let Ok(proof) = RangeProof::from_bytes_strict(&input) {
+			assert_eq!(proof.to_bytes(), input);
+		}
+	}
+}
+
+#[test]
+fn inner_product_proof_decoding_never_panics() {
+	// The inner-product proof is the tail of the range proof.
+	let valid = range_proof_bytes()[7 * 32..].to_vec();
+	assert!(InnerProductProof::from_bytes(&valid).is_ok());
+
+	for input in mutations(&valid, 3) {
+		if let Ok(proof) = InnerProductProof::from_bytes(&input) {
+			assert_eq!(proof.to_bytes(), input);
+		}
+	}
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn r1cs_proof_decoding_never_panics() {
+	use webb_bulletproofs::r1cs::{ConstraintSystem, Prover, R1CSProof};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let mut transcript = Transcript::new(b"DeserializationTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (_, x) = prover.commit(Scalar::from(3u64), Scalar::from(5u64));
+	let (_, _, x2) = prover.multiply(x.into(), x.into());
+	prover.constrain(x2 - Scalar::from(9u64));
+	let valid = prover.prove(&bp_gens).unwrap().to_bytes();
+	assert!(R1CSProof::from_bytes_strict(&valid).is_ok());
+
+	for input in mutations(&valid, 4) {
+		let _ = R1CSProof::from_bytes(&input);
+		let _ = R1CSProof::from_bytes_versioned(&input);
+		if let Ok(proof) = R1CSProof::from_bytes_strict(&input) {
+			assert_eq!(proof.to_bytes(), input);
+		}
+	}
+}
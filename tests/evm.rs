@@ -0,0 +1,251 @@
+#![allow(non_snake_case)]
+
+use rand_core::SeedableRng;
+
+use rand_chacha::ChaChaRng;
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{IsIdentity, VartimeMultiscalarMul},
+};
+
+use webb_bulletproofs::{BulletproofGens, EvmTranscript, PedersenGens, RangeProof, TranscriptProtocol};
+
+// The test vector of `fixtures/evm`, which the tests of the reference
+// Solidity verifier `fixtures/evm/RangeProofVerifier.sol` check against.
+const TRANSCRIPT_LABEL: &[u8] = b"EVM Range Proof";
+const VALUE: u64 = 200;
+const BITSIZE: usize = 8;
+
+fn fixture() -> serde_json::Value {
+	serde_json::from_str(include_str!("fixtures/evm/range_proof_n8.json")).unwrap()
+}
+
+/// Returns the bytes of a `0x`-prefixed hex string of the test vector.
+fn fixture_bytes(value: &serde_json::Value) -> Vec<u8> {
+	hex::decode(value.as_str().unwrap().trim_start_matches("0x")).unwrap()
+}
+
+fn fixture_point(value: &serde_json::Value) -> CompressedRistretto {
+	CompressedRistretto::from_slice(&fixture_bytes(value))
+}
+
+/// Returns the big-endian `uint256` of a scalar, as a Solidity contract
+/// holds it.
+fn uint256(x: &Scalar) -> String {
+	let mut bytes = x.to_bytes();
+	bytes.reverse();
+	format!("0x{}", hex::encode(bytes))
+}
+
+#[test]
+fn test_vector_is_reproducible() {
+	let vector = fixture();
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(BITSIZE, 1);
+	let mut rng = ChaChaRng::from_seed([89u8; 32]);
+	let blinding = Scalar::random(&mut rng);
+
+	let mut transcript = EvmTranscript::new(TRANSCRIPT_LABEL);
+	let (proof, commitments) = RangeProof::prove_multiple_evm_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript,
+		&[VALUE],
+		&[blinding],
+		BITSIZE,
+		&mut rng,
+	)
+	.unwrap();
+
+	assert_eq!(vector["label"].as_str().unwrap().as_bytes(), TRANSCRIPT_LABEL);
+	assert_eq!(vector["n"].as_u64().unwrap(), BITSIZE as u64);
+	assert_eq!(vector["m"].as_u64().unwrap(), 1);
+	assert_eq!(fixture_bytes(&vector["proof"]), proof);
+	assert_eq!(fixture_point(&vector["commitments"][0]), commitments[0]);
+
+	let generators = &vector["generators"];
+	assert_eq!(fixture_point(&generators["B"]), pc_gens.B.compress());
+	assert_eq!(fixture_point(&generators["B_blinding"]), pc_gens.B_blinding.compress());
+	for i in 0..BITSIZE {
+		assert_eq!(fixture_point(&generators["G"][i]), bp_gens.G_vec[0][i].compress());
+		assert_eq!(fixture_point(&generators["H"][i]), bp_gens.H_vec[0][i].compress());
+	}
+}
+
+#[test]
+fn test_vector_verifies() {
+	let vector = fixture();
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(BITSIZE, 1);
+	let proof = fixture_bytes(&vector["proof"]);
+	let commitment = fixture_point(&vector["commitments"][0]);
+
+	let mut transcript = EvmTranscript::new(TRANSCRIPT_LABEL);
+	assert!(
+		RangeProof::verify_multiple_evm(&proof, &bp_gens, &pc_gens, &mut transcript, &[commitment], BITSIZE).is_ok()
+	);
+
+	let mut transcript = EvmTranscript::new(TRANSCRIPT_LABEL);
+	let other = pc_gens.commit(Scalar::from(VALUE + 1), Scalar::zero()).compress();
+	assert!(RangeProof::verify_multiple_evm(&proof, &bp_gens, &pc_gens, &mut transcript, &[other], BITSIZE).is_err());
+}
+
+/// The elements of a proof, decoded from its `abi.encode` as the reference
+/// verifier does.
+struct EvmProof {
+	A: CompressedRistretto,
+	S: CompressedRistretto,
+	T_1: CompressedRistretto,
+	T_2: CompressedRistretto,
+	t_x: Scalar,
+	t_x_blinding: Scalar,
+	e_blinding: Scalar,
+	L_vec: Vec<CompressedRistretto>,
+	R_vec: Vec<CompressedRistretto>,
+	a: Scalar,
+	b: Scalar,
+}
+
+impl EvmProof {
+	fn decode(proof: &[u8]) -> EvmProof {
+		let word = |i: usize| &proof[32 * i..32 * (i + 1)];
+		let point = |i: usize| CompressedRistretto::from_slice(word(i));
+		let scalar = |i: usize| {
+			let mut bytes = [0u8; 32];
+			bytes.copy_from_slice(word(i));
+			bytes.reverse();
+			Scalar::from_canonical_bytes(bytes).unwrap()
+		};
+		let lg_n = (proof.len() / 32 - 13) / 2;
+		EvmProof {
+			A: point(0),
+			S: point(1),
+			T_1: point(2),
+			T_2: point(3),
+			t_x: scalar(4),
+			t_x_blinding: scalar(5),
+			e_blinding: scalar(6),
+			L_vec: (0..lg_n).map(|i| point(12 + i)).collect(),
+			R_vec: (0..lg_n).map(|i| point(13 + lg_n + i)).collect(),
+			a: scalar(9),
+			b: scalar(10),
+		}
+	}
+}
+
+/// The challenges the reference verifier derives from the transcript.
+struct Challenges {
+	y: Scalar,
+	z: Scalar,
+	x: Scalar,
+	w: Scalar,
+	u: Vec<Scalar>,
+	c: Scalar,
+}
+
+impl Challenges {
+	fn replay(proof: &EvmProof, commitment: &CompressedRistretto) -> Challenges {
+		let mut transcript = EvmTranscript::new(TRANSCRIPT_LABEL);
+		transcript.rangeproof_domain_sep(BITSIZE as u64, 1);
+		transcript.append_point(b"V", commitment);
+		transcript.append_point(b"A", &proof.A);
+		transcript.append_point(b"S", &proof.S);
+		// The fields are evaluated in order, replaying the transcript.
+		Challenges {
+			y: transcript.challenge_scalar(b"y"),
+			z: transcript.challenge_scalar(b"z"),
+			x: {
+				transcript.append_point(b"T_1", &proof.T_1);
+				transcript.append_point(b"T_2", &proof.T_2);
+				transcript.challenge_scalar(b"x")
+			},
+			w: {
+				transcript.append_scalar(b"t_x", &proof.t_x);
+				transcript.append_scalar(b"t_x_blinding", &proof.t_x_blinding);
+				transcript.append_scalar(b"e_blinding", &proof.e_blinding);
+				transcript.challenge_scalar(b"w")
+			},
+			u: {
+				transcript.innerproduct_domain_sep(BITSIZE as u64);
+				let mut u = Vec::new();
+				for (L, R) in proof.L_vec.iter().zip(proof.R_vec.iter()) {
+					transcript.append_point(b"L", L);
+					transcript.append_point(b"R", R);
+					u.push(transcript.challenge_scalar(b"u"));
+				}
+				u
+			},
+			// The contract derives the weight of the commitment check from
+			// the transcript instead of sampling it.
+			c: transcript.challenge_scalar(b"c"),
+		}
+	}
+}
+
+// Checks the test vector step by step as `RangeProofVerifier.sol` does:
+// the contract replays the transcript, computes the scalars of the
+// verification equation, and hands the points and scalars to the
+// Ristretto multiscalar multiplication precompile.
+#[test]
+fn test_vector_reference_verifier() {
+	let vector = fixture();
+	let n = BITSIZE;
+	let commitment = fixture_point(&vector["commitments"][0]);
+	let proof = EvmProof::decode(&fixture_bytes(&vector["proof"]));
+	let ch = Challenges::replay(&proof, &commitment);
+
+	for (name, challenge) in ["y", "z", "x", "w"].iter().zip([ch.y, ch.z, ch.x, ch.w].iter()) {
+		assert_eq!(
+			vector["challenges"][name].as_str().unwrap(),
+			uint256(challenge),
+			"challenge {}",
+			name
+		);
+	}
+
+	let lg_n = ch.u.len();
+	let mut s = vec![ch.u.iter().fold(Scalar::one(), |acc, u_k| acc * u_k.invert())];
+	for i in 1..n {
+		let lg_i = 63 - (i as u64).leading_zeros() as usize;
+		let u_lg_i = ch.u[lg_n - 1 - lg_i];
+		s.push(s[i - (1 << lg_i)] * u_lg_i * u_lg_i);
+	}
+
+	let zz = ch.z * ch.z;
+	let mut scalars = Vec::new();
+	let (mut sum_y, mut exp_y) = (Scalar::zero(), Scalar::one());
+	for s_i in s.iter() {
+		scalars.push(-ch.z - proof.a * s_i);
+		sum_y += exp_y;
+		exp_y *= ch.y;
+	}
+	let (y_inv, mut exp_y_inv, mut exp_2) = (ch.y.invert(), Scalar::one(), Scalar::one());
+	for s_i_inv in s.iter().rev() {
+		scalars.push(ch.z + exp_y_inv * (zz * exp_2 - proof.b * s_i_inv));
+		exp_y_inv *= y_inv;
+		exp_2 += exp_2;
+	}
+	let delta = (ch.z - zz) * sum_y - zz * ch.z * (exp_2 - Scalar::one());
+	scalars.push(ch.w * (proof.t_x - proof.a * proof.b) + ch.c * (delta - proof.t_x));
+	scalars.push(-proof.e_blinding - ch.c * proof.t_x_blinding);
+	scalars.extend_from_slice(&[Scalar::one(), ch.x, ch.c * ch.x, ch.c * ch.x * ch.x]);
+	scalars.extend(ch.u.iter().map(|u_k| u_k * u_k));
+	scalars.extend(ch.u.iter().map(|u_k| (u_k * u_k).invert()));
+	scalars.push(ch.c * zz);
+
+	let generators = &vector["generators"];
+	let points: Vec<RistrettoPoint> = (0..n)
+		.map(|i| &generators["G"][i])
+		.chain((0..n).map(|i| &generators["H"][i]))
+		.chain(vec![&generators["B"], &generators["B_blinding"]])
+		.map(fixture_point)
+		.chain(vec![proof.A, proof.S, proof.T_1, proof.T_2])
+		.chain(proof.L_vec.iter().chain(proof.R_vec.iter()).cloned())
+		.chain(std::iter::once(commitment))
+		.map(|P| P.decompress().unwrap())
+		.collect();
+
+	assert!(RistrettoPoint::vartime_multiscalar_mul(&scalars, &points).is_identity());
+}
@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.4;
+
+// Reference verifier for the range proofs of webb-bulletproofs built with the
+// `evm-compat` feature, as returned by `RangeProof::prove_multiple_evm`.
+//
+// The contract replays the Keccak-256 transcript of `EvmTranscript`, computes
+// the scalars of the verification equation, and leaves the multiscalar
+// multiplication of the Ristretto points to a precompile of the chain, since
+// the EVM has no Ristretto arithmetic.  Instead of sampling the weight `c` of
+// the commitment check, the contract derives it from the transcript.
+//
+// `range_proof_n8.json` is a test vector for this contract: the generators to
+// deploy it with, a proof with its commitment, and the challenges y, z, x, w
+// that the contract derives while verifying it.  `tests/evm.rs` checks the
+// vector against the crate by the same steps as the contract.
+
+/// A precompile for multiscalar multiplications of Ristretto points.
+interface IRistrettoMSM {
+    /// Returns whether `scalars[0] * points[0] + ... + scalars[k] * points[k]`
+    /// is the identity, for compressed Ristretto `points` and `scalars`
+    /// below the group order.  Returns false if a point is not a valid
+    /// encoding.
+    function isIdentity(bytes32[] calldata points, uint256[] calldata scalars) external view returns (bool);
+}
+
+/// The `EvmTranscript` of webb-bulletproofs, with the operations of its
+/// `TranscriptProtocol` used by range proofs.
+library EvmTranscript {
+    /// The order of the Ristretto group.
+    uint256 internal constant L = 0x1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed;
+    /// 2^256 mod L.
+    uint256 internal constant TWO_256_MOD_L = 0x0ffffffffffffffffffffffffffffffec6ef5bf4737dcf70d6ec31748d98951d;
+
+    struct Transcript {
+        bytes32 state;
+    }
+
+    function init(bytes memory label) internal pure returns (Transcript memory t) {
+        appendMessage(t, "dom-sep", label);
+    }
+
+    function appendMessage(Transcript memory t, bytes memory label, bytes memory message) internal pure {
+        t.state = keccak256(
+            abi.encodePacked(t.state, uint64(label.length), label, uint64(message.length), message)
+        );
+    }
+
+    function appendU64(Transcript memory t, bytes memory label, uint64 x) internal pure {
+        appendMessage(t, label, abi.encodePacked(x));
+    }
+
+    function appendPoint(Transcript memory t, bytes memory label, bytes32 point) internal pure {
+        appendMessage(t, label, abi.encodePacked(point));
+    }
+
+    /// Appends the little-endian encoding of the scalar `x`.
+    function appendScalar(Transcript memory t, bytes memory label, uint256 x) internal pure {
+        appendMessage(t, label, abi.encodePacked(reverse256(x)));
+    }
+
+    /// Returns the scalar of a 64-byte challenge, block0 + 2^256 * block1 mod L.
+    function challengeScalar(Transcript memory t, bytes memory label) internal pure returns (uint256) {
+        bytes32 seed = keccak256(abi.encodePacked(t.state, uint64(label.length), label, uint64(64)));
+        t.state = seed;
+        uint256 block0 = uint256(keccak256(abi.encodePacked(seed, uint64(0))));
+        uint256 block1 = uint256(keccak256(abi.encodePacked(seed, uint64(1))));
+        return addmod(block0, mulmod(block1, TWO_256_MOD_L, L), L);
+    }
+
+    /// Reverses the byte order of a word.
+    function reverse256(uint256 input) internal pure returns (uint256 v) {
+        v = input;
+        v = ((v & 0xFF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00) >> 8) |
+            ((v & 0x00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF00FF) << 8);
+        v = ((v & 0xFFFF0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF0000) >> 16) |
+            ((v & 0x0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF0000FFFF) << 16);
+        v = ((v & 0xFFFFFFFF00000000FFFFFFFF00000000FFFFFFFF00000000FFFFFFFF00000000) >> 32) |
+            ((v & 0x00000000FFFFFFFF00000000FFFFFFFF00000000FFFFFFFF00000000FFFFFFFF) << 32);
+        v = ((v & 0xFFFFFFFFFFFFFFFF0000000000000000FFFFFFFFFFFFFFFF0000000000000000) >> 64) |
+            ((v & 0x0000000000000000FFFFFFFFFFFFFFFF0000000000000000FFFFFFFFFFFFFFFF) << 64);
+        v = (v >> 128) | (v << 128);
+    }
+}
+
+contract RangeProofVerifier {
+    using EvmTranscript for EvmTranscript.Transcript;
+
+    uint256 internal constant L = EvmTranscript.L;
+
+    /// The elements of a proof, in the order of `RangeProof::to_evm_bytes`.
+    struct Proof {
+        bytes32 A;
+        bytes32 S;
+        bytes32 T1;
+        bytes32 T2;
+        uint256 tx;
+        uint256 txBlinding;
+        uint256 eBlinding;
+        bytes32[] L;
+        bytes32[] R;
+        uint256 a;
+        uint256 b;
+    }
+
+    struct Challenges {
+        uint256 y;
+        uint256 z;
+        uint256 x;
+        uint256 w;
+        uint256 c;
+        uint256[] u;
+    }
+
+    IRistrettoMSM public immutable msm;
+    /// The bitsize of the proved values.
+    uint64 public immutable n;
+    /// The Pedersen generators `PedersenGens::B` and `B_blinding`.
+    bytes32 public immutable B;
+    bytes32 public immutable B_blinding;
+    /// The first `n` generators of each party, party-major:
+    /// `G[j * n + i]` is `BulletproofGens::G_vec[j][i]`, and likewise for `H`.
+    bytes32[] public G;
+    bytes32[] public H;
+
+    constructor(
+        IRistrettoMSM msm_,
+        uint64 n_,
+        bytes32 B_,
+        bytes32 B_blinding_,
+        bytes32[] memory G_,
+        bytes32[] memory H_
+    ) {
+        require(n_ == 8 || n_ == 16 || n_ == 32 || n_ == 64, "invalid bitsize");
+        require(G_.length == H_.length && G_.length % n_ == 0, "invalid generators");
+        msm = msm_;
+        n = n_;
+        B = B_;
+        B_blinding = B_blinding_;
+        G = G_;
+        H = H_;
+    }
+
+    /// Verifies an aggregated proof that the values of `commitments` are
+    /// `n`-bit integers, for a transcript created with the domain
+    /// separator `label`.
+    function verify(
+        bytes calldata label,
+        bytes calldata proof,
+        bytes32[] calldata commitments
+    ) external view returns (bool) {
+        Proof memory p = decodeProof(proof);
+        uint256 m = 1;
+        while (m < commitments.length) {
+            m <<= 1;
+        }
+        uint256 nm = n * m;
+        if (commitments.length == 0 || nm > G.length || (uint256(1) << p.L.length) != nm || p.R.length != p.L.length) {
+            return false;
+        }
+        if (p.tx >= L || p.txBlinding >= L || p.eBlinding >= L || p.a >= L || p.b >= L) {
+            return false;
+        }
+
+        // The prover pads the aggregation with commitments to zero, whose
+        // encoding is the zero word.
+        bytes32[] memory V = new bytes32[](m);
+        for (uint256 j = 0; j < commitments.length; j++) {
+            V[j] = commitments[j];
+        }
+
+        (bool valid, Challenges memory ch) = challenges(label, p, V);
+        if (!valid) {
+            return false;
+        }
+        (bytes32[] memory points, uint256[] memory scalars) = terms(p, ch, V);
+        return msm.isIdentity(points, scalars);
+    }
+
+    function decodeProof(bytes calldata proof) internal pure returns (Proof memory p) {
+        (p.A, p.S, p.T1, p.T2, p.tx, p.txBlinding, p.eBlinding, p.L, p.R, p.a, p.b) = abi.decode(
+            proof,
+            (bytes32, bytes32, bytes32, bytes32, uint256, uint256, uint256, bytes32[], bytes32[], uint256, uint256)
+        );
+    }
+
+    /// Replays the transcript of the proof, returning false if one of its
+    /// points is the identity.
+    function challenges(
+        bytes calldata label,
+        Proof memory p,
+        bytes32[] memory V
+    ) internal view returns (bool valid, Challenges memory ch) {
+        EvmTranscript.Transcript memory t = EvmTranscript.init(label);
+        t.appendMessage("dom-sep", "rangeproof v1");
+        t.appendU64("n", n);
+        t.appendU64("m", uint64(V.length));
+        for (uint256 j = 0; j < V.length; j++) {
+            t.appendPoint("V", V[j]);
+        }
+
+        if (p.A == bytes32(0) || p.S == bytes32(0) || p.T1 == bytes32(0) || p.T2 == bytes32(0)) {
+            return (false, ch);
+        }
+        t.appendPoint("A", p.A);
+        t.appendPoint("S", p.S);
+        ch.y = t.challengeScalar("y");
+        ch.z = t.challengeScalar("z");
+        t.appendPoint("T_1", p.T1);
+        t.appendPoint("T_2", p.T2);
+        ch.x = t.challengeScalar("x");
+        t.appendScalar("t_x", p.tx);
+        t.appendScalar("t_x_blinding", p.txBlinding);
+        t.appendScalar("e_blinding", p.eBlinding);
+        ch.w = t.challengeScalar("w");
+
+        t.appendMessage("dom-sep", "ipp v1");
+        t.appendU64("n", uint64(1) << p.L.length);
+        ch.u = new uint256[](p.L.length);
+        for (uint256 k = 0; k < p.L.length; k++) {
+            if (p.L[k] == bytes32(0) || p.R[k] == bytes32(0)) {
+                return (false, ch);
+            }
+            t.appendPoint("L", p.L[k]);
+            t.appendPoint("R", p.R[k]);
+            ch.u[k] = t.challengeScalar("u");
+        }
+        ch.c = t.challengeScalar("c");
+        valid = true;
+    }
+
+    /// Returns the points and scalars of the verification equation
+    ///
+    ///     sum_i g_i G_i + h_i H_i + b B + b' B_blinding + A + x S + c x T_1
+    ///       + c x^2 T_2 + sum_k u_k^2 L_k + u_k^-2 R_k + sum_j c z^(j+2) V_j = 0,
+    ///
+    /// in this order.
+    function terms(
+        Proof memory p,
+        Challenges memory ch,
+        bytes32[] memory V
+    ) internal view returns (bytes32[] memory points, uint256[] memory scalars) {
+        uint256 nm = uint256(1) << p.L.length;
+        uint256 size = 2 * nm + 6 + 2 * p.L.length + V.length;
+        points = new bytes32[](size);
+        scalars = new uint256[](size);
+
+        uint256[] memory s = innerProductTerms(p, ch, points, scalars);
+        uint256 sumY = generatorTerms(p, ch, s, points, scalars);
+        commitmentTerms(p, ch, V, sumY, points, scalars);
+    }
+
+    /// Writes the terms of the `L_k` and `R_k`, and returns the scalars
+    /// `s_i` of the inner-product argument.
+    function innerProductTerms(
+        Proof memory p,
+        Challenges memory ch,
+        bytes32[] memory points,
+        uint256[] memory scalars
+    ) internal view returns (uint256[] memory s) {
+        uint256 lgNM = p.L.length;
+        uint256 offset = 2 * (uint256(1) << lgNM) + 6;
+        uint256[] memory uSq = new uint256[](lgNM);
+        s = new uint256[](uint256(1) << lgNM);
+        s[0] = 1;
+        for (uint256 k = 0; k < lgNM; k++) {
+            uint256 uInv = invert(ch.u[k]);
+            uSq[k] = mulmod(ch.u[k], ch.u[k], L);
+            s[0] = mulmod(s[0], uInv, L);
+            points[offset + k] = p.L[k];
+            scalars[offset + k] = uSq[k];
+            points[offset + lgNM + k] = p.R[k];
+            scalars[offset + lgNM + k] = mulmod(uInv, uInv, L);
+        }
+        uint256 lgI = 0;
+        for (uint256 i = 1; i < s.length; i++) {
+            if (i == uint256(2) << lgI) {
+                lgI++;
+            }
+            s[i] = mulmod(s[i - (uint256(1) << lgI)], uSq[lgNM - 1 - lgI], L);
+        }
+    }
+
+    /// Writes the terms of the generators `G_i` and `H_i`, and returns
+    /// `sum_i y^i`.
+    function generatorTerms(
+        Proof memory p,
+        Challenges memory ch,
+        uint256[] memory s,
+        bytes32[] memory points,
+        uint256[] memory scalars
+    ) internal view returns (uint256 sumY) {
+        uint256 nm = s.length;
+        uint256 yInv = invert(ch.y);
+        uint256 expY = 1;
+        uint256 expYInv = 1;
+        // z^2 z^j for the values j.
+        uint256 zzExpZ = mulmod(ch.z, ch.z, L);
+        for (uint256 i = 0; i < nm; i++) {
+            if (i > 0 && i % n == 0) {
+                zzExpZ = mulmod(zzExpZ, ch.z, L);
+            }
+            points[i] = G[i];
+            scalars[i] = addmod(L - ch.z, L - mulmod(p.a, s[i], L), L);
+            points[nm + i] = H[i];
+            uint256 zAnd2 = mulmod(zzExpZ, uint256(1) << (i % n), L);
+            scalars[nm + i] = addmod(
+                ch.z,
+                mulmod(expYInv, addmod(zAnd2, L - mulmod(p.b, s[nm - 1 - i], L), L), L),
+                L
+            );
+            sumY = addmod(sumY, expY, L);
+            expY = mulmod(expY, ch.y, L);
+            expYInv = mulmod(expYInv, yInv, L);
+        }
+    }
+
+    /// Writes the terms of the Pedersen generators, of `A, S, T_1, T_2`
+    /// and of the commitments `V_j`.
+    function commitmentTerms(
+        Proof memory p,
+        Challenges memory ch,
+        bytes32[] memory V,
+        uint256 sumY,
+        bytes32[] memory points,
+        uint256[] memory scalars
+    ) internal view {
+        uint256 offset = 2 * (uint256(1) << p.L.length);
+        uint256 zz = mulmod(ch.z, ch.z, L);
+        uint256 sumZ = 0;
+        uint256 expZ = 1;
+        for (uint256 j = 0; j < V.length; j++) {
+            points[offset + 6 + 2 * p.L.length + j] = V[j];
+            scalars[offset + 6 + 2 * p.L.length + j] = mulmod(ch.c, mulmod(zz, expZ, L), L);
+            sumZ = addmod(sumZ, expZ, L);
+            expZ = mulmod(expZ, ch.z, L);
+        }
+        // delta(y, z) = (z - z^2) sum_i y^i - z^3 (2^n - 1) sum_j z^j
+        uint256 delta = addmod(
+            mulmod(addmod(ch.z, L - zz, L), sumY, L),
+            L - mulmod(mulmod(zz, ch.z, L), mulmod((uint256(1) << n) - 1, sumZ, L), L),
+            L
+        );
+
+        points[offset] = B;
+        scalars[offset] = addmod(
+            mulmod(ch.w, addmod(p.tx, L - mulmod(p.a, p.b, L), L), L),
+            mulmod(ch.c, addmod(delta, L - p.tx, L), L),
+            L
+        );
+        points[offset + 1] = B_blinding;
+        scalars[offset + 1] = addmod(L - p.eBlinding, L - mulmod(ch.c, p.txBlinding, L), L);
+        points[offset + 2] = p.A;
+        scalars[offset + 2] = 1;
+        points[offset + 3] = p.S;
+        scalars[offset + 3] = ch.x;
+        points[offset + 4] = p.T1;
+        scalars[offset + 4] = mulmod(ch.c, ch.x, L);
+        points[offset + 5] = p.T2;
+        scalars[offset + 5] = mulmod(scalars[offset + 4], ch.x, L);
+    }
+
+    /// Returns x^(L - 2) mod L, the inverse of a nonzero scalar, with the
+    /// modexp precompile.
+    function invert(uint256 x) internal view returns (uint256) {
+        (bool ok, bytes memory result) = address(0x05).staticcall(abi.encode(32, 32, 32, x, L - 2, L));
+        require(ok && x != 0, "invalid inverse");
+        return abi.decode(result, (uint256));
+    }
+}
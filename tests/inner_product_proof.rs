@@ -0,0 +1,59 @@
+#![allow(non_snake_case)]
+
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar, traits::VartimeMultiscalarMul};
+use merlin::Transcript;
+use rand_chacha::ChaChaRng;
+use rand_core::SeedableRng;
+use sha3::Sha3_512;
+use webb_bulletproofs::{inner_product, BulletproofGens, InnerProductProof};
+
+#[test]
+fn create_and_verify() {
+	let n = 16;
+	let mut rng = ChaChaRng::from_seed([3u8; 32]);
+	let bp_gens = BulletproofGens::new(n, 1);
+	let G = &bp_gens.G_vec[0][..n];
+	let H = &bp_gens.H_vec[0][..n];
+	let Q = RistrettoPoint::hash_from_bytes::<Sha3_512>(b"ipp test point");
+
+	let a: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+	let b: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+	let ones = vec![Scalar::one(); n];
+
+	// P = <a, G> + <b, H> + <a, b> Q
+	let P = RistrettoPoint::vartime_multiscalar_mul(
+		a.iter().chain(b.iter()).chain(Some(&inner_product(&a, &b))),
+		G.iter().chain(H.iter()).chain(Some(&Q)),
+	);
+
+	let proof = InnerProductProof::create(
+		&mut Transcript::new(b"IppTest"),
+		&Q,
+		&ones,
+		&ones,
+		G.to_vec(),
+		H.to_vec(),
+		a,
+		b,
+	);
+	assert_eq!(proof.serialized_size(), (2 * 4 + 2) * 32);
+
+	let verify = |proof: &InnerProductProof, P: &RistrettoPoint| {
+		proof.verify(n, &mut Transcript::new(b"IppTest"), &ones, &ones, P, &Q, G, H)
+	};
+	assert!(verify(&proof, &P).is_ok());
+	assert!(verify(&proof, &(P + Q)).is_err());
+
+	let decoded = InnerProductProof::from_bytes(&proof.to_bytes()).unwrap();
+	assert!(verify(&decoded, &P).is_ok());
+	let decoded: InnerProductProof = bincode::deserialize(&bincode::serialize(&proof).unwrap()).unwrap();
+	assert!(verify(&decoded, &P).is_ok());
+
+	// The verification scalars fold the check into one multiscalar
+	// multiplication; there is one challenge per round.
+	let (u_sq, u_inv_sq, s) = proof.verification_scalars(n, &mut Transcript::new(b"IppTest")).unwrap();
+	assert_eq!((u_sq.len(), u_inv_sq.len(), s.len()), (4, 4, n));
+	assert!(proof
+		.verification_scalars(2 * n, &mut Transcript::new(b"IppTest"))
+		.is_err());
+}
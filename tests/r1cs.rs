@@ -2,8 +2,10 @@
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use merlin::Transcript;
 use rand::{seq::SliceRandom, thread_rng};
+use rand_chacha::ChaChaRng;
+use rand_core::SeedableRng;
 use std::time::{Duration, Instant};
-use webb_bulletproofs::{r1cs::*, BulletproofGens, PedersenGens};
+use webb_bulletproofs::{r1cs::*, BulletproofGens, PedersenGens, VectorLinkProof, VerificationKey};
 
 // Shuffle gadget (documented in markdown file)
 
@@ -108,6 +110,18 @@ impl ShuffleProof {
 		input_commitments: &Vec<CompressedRistretto>,
 		output_commitments: &Vec<CompressedRistretto>,
 	) -> Result<(), R1CSError> {
+		let verifier = ShuffleProof::verifier(transcript, input_commitments, output_commitments)?;
+
+		verifier.verify(&self.0, &pc_gens, &bp_gens)
+	}
+
+	/// Builds the shuffle constraint system for the given commitments,
+	/// ready to verify a proof either on its own or as part of a batch.
+	pub fn verifier<'a>(
+		transcript: &'a mut Transcript,
+		input_commitments: &Vec<CompressedRistretto>,
+		output_commitments: &Vec<CompressedRistretto>,
+	) -> Result<Verifier<'a>, R1CSError> {
 		// Apply a domain separator with the shuffle parameters to the transcript
 		// XXX should this be part of the gadget?
 		let k = input_commitments.len();
@@ -122,7 +136,7 @@ impl ShuffleProof {
 
 		ShuffleProof::gadget(&mut verifier, input_vars, output_vars)?;
 
-		verifier.verify(&self.0, &pc_gens, &bp_gens)
+		Ok(verifier)
 	}
 }
 
@@ -211,6 +225,58 @@ fn shuffle_gadget_test_42() {
 	kshuffle_helper(100);
 }
 
+#[test]
+fn shuffle_gadget_batch_verify() {
+	use rand::Rng;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut rng = rand::thread_rng();
+
+	// Shuffles of different sizes, so the proofs use different numbers of
+	// generators.
+	let sizes = [1, 3, 8, 17];
+	let instances: Vec<_> = sizes
+		.iter()
+		.map(|&k| {
+			let input: Vec<Scalar> = (0..k).map(|_| Scalar::from(rng.gen::<u64>())).collect();
+			let mut output = input.clone();
+			output.shuffle(&mut rng);
+
+			let mut prover_transcript = Transcript::new(b"ShuffleProofTest");
+			ShuffleProof::prove(&pc_gens, &bp_gens, &mut prover_transcript, &input, &output).unwrap()
+		})
+		.collect();
+
+	let mut transcripts = vec![Transcript::new(b"ShuffleProofTest"); sizes.len()];
+	let batch: Vec<_> = transcripts
+		.iter_mut()
+		.zip(instances.iter())
+		.map(|(transcript, (proof, input_commitments, output_commitments))| {
+			let verifier = ShuffleProof::verifier(transcript, input_commitments, output_commitments).unwrap();
+			(verifier, &proof.0)
+		})
+		.collect();
+	assert!(Verifier::batch_verify(batch, &pc_gens, &bp_gens).is_ok());
+
+	// Tampering with a single commitment must fail the whole batch.
+	let mut transcripts = vec![Transcript::new(b"ShuffleProofTest"); sizes.len()];
+	let batch: Vec<_> = transcripts
+		.iter_mut()
+		.zip(instances.iter())
+		.enumerate()
+		.map(|(i, (transcript, (proof, input_commitments, output_commitments)))| {
+			let mut output_commitments = output_commitments.clone();
+			if i == 1 {
+				output_commitments[0] = instances[0].2[0];
+			}
+			let verifier = ShuffleProof::verifier(transcript, input_commitments, &output_commitments).unwrap();
+			(verifier, &proof.0)
+		})
+		.collect();
+	assert!(Verifier::batch_verify(batch, &pc_gens, &bp_gens).is_err());
+}
+
 /// Constrains (a1 + a2) * (b1 + b2) = (c1 + c2)
 fn example_gadget<CS: ConstraintSystem>(
 	cs: &mut CS,
@@ -370,7 +436,7 @@ pub fn range_proof<CS: ConstraintSystem>(
 		// Add `-b_i*2^i` to the linear combination
 		// in order to form the following constraint by the end of the loop:
 		// v = Sum(b_i * 2^i, i = 0..n-1)
-		v = v - b * exp_2;
+		v -= b * exp_2;
 
 		exp_2 = exp_2 + exp_2;
 	}
@@ -431,3 +497,1061 @@ fn range_proof_helper(v_val: u64, n: usize) -> Result<(), R1CSError> {
 	// Verifier verifies proof
 	Ok(verifier.verify(&proof, &pc_gens, &bp_gens)?)
 }
+
+fn metrics_gadget<CS: RandomizableConstraintSystem>(cs: &mut CS, a: Variable, b: Variable) -> Result<(), R1CSError> {
+	assert_eq!(cs.metrics(), Metrics {
+		multipliers: 0,
+		constraints: 0,
+		committed_variables: 2,
+		vector_committed_variables: 0,
+		vector_commitments: 0,
+		phase: Phase::NonRandomized,
+	});
+
+	let (_, _, o) = cs.multiply(a.into(), b.into());
+	cs.constrain(o - Scalar::from(6u64));
+	assert_eq!(cs.metrics().multipliers, 1);
+	assert_eq!(cs.metrics().constraints, 3);
+
+	cs.specify_randomized_constraints(move |cs| {
+		let metrics = cs.metrics();
+		assert_eq!(metrics.phase, Phase::Randomized);
+		assert_eq!(metrics.multipliers, 1);
+		assert_eq!(metrics.committed_variables, 2);
+
+		let z = cs.challenge_scalar(b"metrics challenge");
+		cs.constrain((a - b + Scalar::one()) * z);
+		assert_eq!(cs.metrics().constraints, metrics.constraints + 1);
+		Ok(())
+	})
+}
+
+#[test]
+fn constraint_system_metrics() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"MetricsTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(2u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(3u64), Scalar::from(11u64));
+	metrics_gadget(&mut prover, a, b).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	// No multipliers were added in the randomized phase, so the proof has
+	// no second-phase commitments.
+	let metrics = Metrics {
+		multipliers: 1,
+		constraints: 4,
+		committed_variables: 2,
+		vector_committed_variables: 0,
+		vector_commitments: 0,
+		phase: Phase::NonRandomized,
+	};
+	assert_eq!(R1CSProof::size(&metrics), proof.to_bytes().len());
+	assert_eq!(
+		R1CSProof::size(&Metrics {
+			phase: Phase::Randomized,
+			..metrics
+		}),
+		proof.to_bytes().len() + 3 * 32
+	);
+	assert_eq!(R1CSProof::verification_cost(&metrics), 2 + 2 + 6 + 2 + 5);
+
+	let mut verifier_transcript = Transcript::new(b"MetricsTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	metrics_gadget(&mut verifier, a, b).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+#[test]
+fn reserve_hints_do_not_change_the_circuit() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let n = 64;
+
+	let prove = |reserve: bool| {
+		let mut transcript = Transcript::new(b"ReserveTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		if reserve {
+			prover.reserve_multipliers(n);
+			prover.reserve_constraints(n + 1);
+		}
+		let (com, var) = prover.commit(Scalar::from(u64::max_value()), Scalar::from(7u64));
+		gadgets::range_check(&mut prover, var.into(), n).unwrap();
+		let digest = prover.circuit_digest();
+		(com, digest, prover.prove(&bp_gens).unwrap())
+	};
+	let (com, digest, proof) = prove(true);
+	assert_eq!(prove(false).1, digest);
+
+	let mut transcript = Transcript::new(b"ReserveTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	verifier.reserve_multipliers(n);
+	verifier.reserve_constraints(n + 1);
+	let var = verifier.commit(com);
+	gadgets::range_check(&mut verifier, var.into(), n).unwrap();
+	assert_eq!(verifier.circuit_digest(), digest);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+fn sparse_gadget<CS: ConstraintSystem>(cs: &mut CS, a: Variable, b: Variable) {
+	// a * b = 12, with b = 4 stated through zero and cancelling terms,
+	// which the verifier skips when flattening the constraints.
+	let (l, r, o) = cs.multiply(a.into(), b.into());
+	cs.constrain(o - Scalar::from(12u64));
+	cs.constrain(
+		vec![
+			(l, Scalar::zero()),
+			(r, Scalar::one()),
+			(Variable::One(), -Scalar::from(4u64)),
+		]
+		.into_iter()
+		.collect(),
+	);
+	cs.constrain(LinearCombination::from(l) - l + l - l + b - Scalar::from(4u64));
+	for _ in 0..6 {
+		cs.multiply(a.into(), Scalar::zero().into());
+	}
+}
+
+#[test]
+fn verify_constraints_with_zero_terms() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"SparseTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	sparse_gadget(&mut prover, a, b);
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verify = |com_b| {
+		let mut verifier_transcript = Transcript::new(b"SparseTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let a = verifier.commit(com_a);
+		let b = verifier.commit(com_b);
+		sparse_gadget(&mut verifier, a, b);
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(com_b).is_ok());
+	assert!(verify(pc_gens.commit(Scalar::from(4u64), Scalar::from(12u64)).compress()).is_err());
+}
+
+/// Constrains `x[i] * y[i] = z[i]` for each `i`, with one `multiply_many`
+/// or one `multiply` per product.
+fn products_gadget<CS: ConstraintSystem>(cs: &mut CS, x: &[Variable], y: &[Variable], z: &[u64], batched: bool) {
+	let pairs: Vec<(LinearCombination, LinearCombination)> = x
+		.iter()
+		.zip(y.iter())
+		.map(|(x, y)| ((*x).into(), (*y).into()))
+		.collect();
+	let gates = if batched {
+		cs.multiply_many(&pairs)
+	} else {
+		pairs.into_iter().map(|(l, r)| cs.multiply(l, r)).collect()
+	};
+	for ((_, _, o), z) in gates.into_iter().zip(z.iter()) {
+		cs.constrain(o - *z);
+	}
+}
+
+#[test]
+fn multiply_many_matches_multiply() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let (x, y) = ([2u64, 3, 5], [7u64, 11, 13]);
+	let z: Vec<u64> = x.iter().zip(y.iter()).map(|(x, y)| x * y).collect();
+
+	let prove = |batched: bool| {
+		let mut transcript = Transcript::new(b"MultiplyManyTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (com_x, x): (Vec<_>, Vec<_>) = x
+			.iter()
+			.map(|x| prover.commit(Scalar::from(*x), Scalar::from(17u64)))
+			.unzip();
+		let (com_y, y): (Vec<_>, Vec<_>) = y
+			.iter()
+			.map(|y| prover.commit(Scalar::from(*y), Scalar::from(19u64)))
+			.unzip();
+		// Start with a half-assigned multiplier.
+		prover.allocate(Some(Scalar::one())).unwrap();
+		products_gadget(&mut prover, &x, &y, &z, batched);
+		let digest = prover.circuit_digest();
+		(com_x, com_y, digest, prover.prove(&bp_gens).unwrap())
+	};
+	let (com_x, com_y, digest, proof) = prove(true);
+	assert_eq!(prove(false).2, digest);
+
+	for &batched in &[true, false] {
+		let mut transcript = Transcript::new(b"MultiplyManyTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let x: Vec<_> = com_x.iter().map(|com| verifier.commit(*com)).collect();
+		let y: Vec<_> = com_y.iter().map(|com| verifier.commit(*com)).collect();
+		verifier.allocate(None).unwrap();
+		products_gadget(&mut verifier, &x, &y, &z, batched);
+		assert_eq!(verifier.circuit_digest(), digest);
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+	}
+}
+
+fn circuit_gadget<CS: ConstraintSystem>(cs: &mut CS, a: Variable, b: Variable) -> Result<(), R1CSError> {
+	// a * b = 12, with a in [0, 16)
+	let (_, _, o) = cs.multiply(a.into(), b.into());
+	cs.constrain(o - Scalar::from(12u64));
+	gadgets::range_check(cs, a.into(), 4)
+}
+
+#[test]
+fn batch_verify_proofs_of_one_circuit() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let proofs: Vec<_> = [(3u64, 4u64), (2, 6), (1, 12)]
+		.iter()
+		.map(|&(a, b)| {
+			let mut transcript = Transcript::new(b"BatchProofsTest");
+			let mut prover = Prover::new(&pc_gens, &mut transcript);
+			let (com_a, a) = prover.commit(Scalar::from(a), Scalar::random(&mut thread_rng()));
+			let (com_b, b) = prover.commit(Scalar::from(b), Scalar::random(&mut thread_rng()));
+			circuit_gadget(&mut prover, a, b).unwrap();
+			(prover.prove(&bp_gens).unwrap(), vec![com_a, com_b])
+		})
+		.collect();
+
+	let verify = |proofs: &[(R1CSProof, Vec<CompressedRistretto>)]| {
+		Verifier::batch_verify_proofs(
+			proofs,
+			&Transcript::new(b"BatchProofsTest"),
+			|cs, vars| circuit_gadget(cs, vars[0], vars[1]),
+			&pc_gens,
+			&bp_gens,
+		)
+	};
+	assert!(verify(&proofs).is_ok());
+
+	let mut swapped = proofs.clone();
+	swapped[1].1.swap(0, 1);
+	assert_eq!(verify(&swapped), Err(R1CSError::VerificationError));
+}
+
+#[test]
+fn versioned_proof_encoding() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut transcript = Transcript::new(b"VersionedTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	circuit_gadget(&mut prover, a, b).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let versioned = proof.to_bytes_versioned();
+	assert_eq!(versioned[0], 1);
+	assert_eq!(&versioned[5..], &proof.to_bytes()[..]);
+	let proof = R1CSProof::from_bytes_versioned(&versioned).unwrap();
+
+	let mut transcript = Transcript::new(b"VersionedTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	circuit_gadget(&mut verifier, a, b).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	let mut unknown = versioned.clone();
+	unknown[0] = 0;
+	assert_eq!(
+		R1CSProof::from_bytes_versioned(&unknown).unwrap_err(),
+		R1CSError::FormatError
+	);
+	assert_eq!(
+		R1CSProof::from_bytes_versioned(&versioned[..versioned.len() - 1]).unwrap_err(),
+		R1CSError::FormatError
+	);
+}
+
+#[test]
+fn combined_proof_of_small_circuits() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 1);
+	let factors = [(3u64, 4u64), (2, 6), (1, 12), (4, 3)];
+
+	let mut member_transcripts: Vec<Transcript> = (0..4).map(|_| Transcript::new(b"BatchMember")).collect();
+	let mut commitments = Vec::new();
+	let provers = member_transcripts
+		.iter_mut()
+		.zip(factors.iter())
+		.map(|(transcript, &(a, b))| {
+			let mut prover = Prover::new(&pc_gens, transcript);
+			let (com_a, a) = prover.commit(Scalar::from(a), Scalar::random(&mut thread_rng()));
+			let (com_b, b) = prover.commit(Scalar::from(b), Scalar::random(&mut thread_rng()));
+			circuit_gadget(&mut prover, a, b).unwrap();
+			commitments.push((com_a, com_b));
+			prover
+		})
+		.collect();
+	let mut transcript = Transcript::new(b"BatchTest");
+	let proof = Prover::prove_combined(&pc_gens, &mut transcript, provers, &bp_gens).unwrap();
+
+	// The proof is as large as a single proof for 4 times the multipliers.
+	let single_size = 1 + (11 + 2 * 3 + 2) * 32;
+	assert_eq!(proof.serialized_size(), 1 + (11 + 2 * 5 + 2) * 32);
+	assert!(proof.serialized_size() < 2 * single_size);
+
+	let verify = |commitments: &[(CompressedRistretto, CompressedRistretto)]| {
+		let mut member_transcripts: Vec<Transcript> = (0..4).map(|_| Transcript::new(b"BatchMember")).collect();
+		let verifiers = member_transcripts
+			.iter_mut()
+			.zip(commitments.iter())
+			.map(|(transcript, &(com_a, com_b))| {
+				let mut verifier = Verifier::new(transcript);
+				let a = verifier.commit(com_a);
+				let b = verifier.commit(com_b);
+				circuit_gadget(&mut verifier, a, b).unwrap();
+				verifier
+			})
+			.collect();
+		let mut transcript = Transcript::new(b"BatchTest");
+		Verifier::verify_combined(&mut transcript, verifiers, &proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(&commitments).is_ok());
+
+	// The constraint systems are bound to their order.
+	let mut swapped = commitments.clone();
+	swapped.swap(0, 1);
+	assert_eq!(verify(&swapped), Err(R1CSError::VerificationError));
+	assert_eq!(
+		verify(&commitments[..3]),
+		Err(R1CSError::WrongNumMultipliers {
+			expected: 16,
+			actual: 32
+		})
+	);
+
+	// Randomized constraints cannot be batched.
+	let mut member_transcript = Transcript::new(b"BatchMember");
+	let mut prover = Prover::new(&pc_gens, &mut member_transcript);
+	let (_, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (_, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	value_of_gadget(&mut prover, a, b, true).unwrap();
+	let mut transcript = Transcript::new(b"BatchTest");
+	assert_eq!(
+		Prover::prove_combined(&pc_gens, &mut transcript, vec![prover], &bp_gens).unwrap_err(),
+		R1CSError::RandomizedConstraints
+	);
+}
+
+#[test]
+fn strict_proof_decoding() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut transcript = Transcript::new(b"StrictDecodingTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (_, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (_, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	circuit_gadget(&mut prover, a, b).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let bytes = proof.to_bytes();
+	assert_eq!(R1CSProof::from_bytes_strict(&bytes).unwrap().to_bytes(), bytes);
+
+	// A one-phase proof re-encoded with identity second-phase
+	// commitments decodes to the same proof, but is not canonical.
+	let mut two_phase = vec![1u8];
+	two_phase.extend_from_slice(&bytes[1..97]);
+	two_phase.extend_from_slice(&[0u8; 96]);
+	two_phase.extend_from_slice(&bytes[97..]);
+	assert_eq!(R1CSProof::from_bytes(&two_phase).unwrap().to_bytes(), bytes);
+	assert_eq!(
+		R1CSProof::from_bytes_strict(&two_phase).unwrap_err(),
+		R1CSError::FormatError
+	);
+
+	// The field modulus is a non-canonical encoding of zero.
+	let mut non_canonical = bytes;
+	non_canonical[1] = 0xed;
+	for byte in non_canonical[2..32].iter_mut() {
+		*byte = 0xff;
+	}
+	non_canonical[32] = 0x7f;
+	assert!(R1CSProof::from_bytes(&non_canonical).is_ok());
+	assert_eq!(
+		R1CSProof::from_bytes_strict(&non_canonical).unwrap_err(),
+		R1CSError::FormatError
+	);
+}
+
+#[test]
+fn seeded_rng_proofs_are_reproducible() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let prove = |seed: u8| {
+		let mut transcript = Transcript::new(b"SeededRngTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+		let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+		circuit_gadget(&mut prover, a, b).unwrap();
+		let proof = prover
+			.prove_with_rng(&bp_gens, &mut ChaChaRng::from_seed([seed; 32]))
+			.unwrap();
+		(proof.to_bytes(), com_a, com_b)
+	};
+
+	let (proof_bytes, com_a, com_b) = prove(1);
+	assert_eq!(prove(1).0, proof_bytes);
+	assert_ne!(prove(2).0, proof_bytes);
+
+	let mut transcript = Transcript::new(b"SeededRngTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	circuit_gadget(&mut verifier, a, b).unwrap();
+	let proof = R1CSProof::from_bytes(&proof_bytes).unwrap();
+	assert!(verifier
+		.verify_with_rng(&proof, &pc_gens, &bp_gens, &mut ChaChaRng::from_seed([0u8; 32]))
+		.is_ok());
+}
+
+#[test]
+fn verifier_from_stored_circuit() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"CircuitTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	circuit_gadget(&mut prover, a, b).unwrap();
+	let prover_circuit = prover.circuit().unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"CircuitTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	circuit_gadget(&mut verifier, a, b).unwrap();
+	let circuit = verifier.circuit().unwrap();
+	assert_eq!(circuit, prover_circuit);
+	assert_eq!(circuit.committed_variables(), 2);
+	assert_eq!(circuit.multipliers(), 5);
+
+	// Store and reload the circuit.
+	let circuit = Circuit::from_bytes(&circuit.to_bytes()).unwrap();
+
+	let mut transcript = Transcript::new(b"CircuitTest");
+	let verifier = Verifier::from_circuit(&mut transcript, &circuit, &[com_a, com_b]).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	let mut transcript = Transcript::new(b"CircuitTest");
+	let verifier = Verifier::from_circuit(&mut transcript, &circuit, &[com_b, com_a]).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+
+	let mut transcript = Transcript::new(b"CircuitTest");
+	assert_eq!(
+		Verifier::from_circuit(&mut transcript, &circuit, &[com_a]).err(),
+		Some(R1CSError::WrongNumCommitments { expected: 2, actual: 1 })
+	);
+}
+
+/// Constrains the product of `a` and `b` to be the public input `c`.
+fn public_product_gadget<CS: ConstraintSystem>(cs: &mut CS, a: Variable, b: Variable, c: Variable) {
+	let (_, _, ab) = cs.multiply(a.into(), b.into());
+	cs.constrain(ab - c);
+}
+
+#[test]
+fn public_inputs() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"PublicInputTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	let c = prover.allocate_public(Scalar::from(12u64));
+	public_product_gadget(&mut prover, a, b, c);
+	let prover_circuit = prover.circuit().unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verify = |c: u64| {
+		let mut transcript = Transcript::new(b"PublicInputTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let a = verifier.commit(com_a);
+		let b = verifier.commit(com_b);
+		let c = verifier.allocate_public(Scalar::from(c));
+		public_product_gadget(&mut verifier, a, b, c);
+		assert_eq!(verifier.circuit().unwrap(), prover_circuit);
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(12).is_ok());
+	assert_eq!(verify(13), Err(R1CSError::VerificationError));
+
+	// The values of the public inputs are not part of the circuit.
+	assert_eq!(prover_circuit.public_inputs(), 1);
+	let circuit = Circuit::from_bytes(&prover_circuit.to_bytes()).unwrap();
+
+	let mut transcript = Transcript::new(b"PublicInputTest");
+	let mut verifier = Verifier::from_circuit(&mut transcript, &circuit, &[com_a, com_b]).unwrap();
+	verifier.allocate_public(Scalar::from(12u64));
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	let mut transcript = Transcript::new(b"PublicInputTest");
+	let verifier = Verifier::from_circuit(&mut transcript, &circuit, &[com_a, com_b]).unwrap();
+	assert_eq!(
+		verifier.verify(&proof, &pc_gens, &bp_gens),
+		Err(R1CSError::WrongNumPublicInputs { expected: 1, actual: 0 })
+	);
+}
+
+#[test]
+fn verify_with_circuit_verifying_key() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"VerifyingKeyTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	let c = prover.allocate_public(Scalar::from(12u64));
+	public_product_gadget(&mut prover, a, b, c);
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	// Run the gadget code once, with placeholder inputs.
+	let mut transcript = Transcript::new(b"VerifyingKeyTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let a = verifier.commit(CompressedRistretto::default());
+	let b = verifier.commit(CompressedRistretto::default());
+	let c = verifier.allocate_public(Scalar::zero());
+	public_product_gadget(&mut verifier, a, b, c);
+	let key = verifier.finalize().unwrap();
+
+	// Store and reload the key.
+	let key = CircuitVerifyingKey::from_bytes(&key.to_bytes()).unwrap();
+	assert_eq!(key.circuit().multipliers(), 1);
+
+	let verify = |commitments: &[CompressedRistretto], public_inputs: &[Scalar]| {
+		let mut transcript = Transcript::new(b"VerifyingKeyTest");
+		key.verify(&mut transcript, &proof, commitments, public_inputs, &pc_gens, &bp_gens)
+	};
+	assert!(verify(&[com_a, com_b], &[Scalar::from(12u64)]).is_ok());
+	assert_eq!(
+		verify(&[com_a, com_b], &[Scalar::from(13u64)]),
+		Err(R1CSError::VerificationError)
+	);
+	assert_eq!(
+		verify(&[com_a, com_b], &[]),
+		Err(R1CSError::WrongNumPublicInputs { expected: 1, actual: 0 })
+	);
+	assert_eq!(
+		verify(&[com_a], &[Scalar::from(12u64)]),
+		Err(R1CSError::WrongNumCommitments { expected: 2, actual: 1 })
+	);
+
+	// Randomized constraints cannot be part of a key.
+	let mut transcript = Transcript::new(b"VerifyingKeyTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let x = verifier.commit(CompressedRistretto::default());
+	let y = verifier.commit(CompressedRistretto::default());
+	ShuffleProof::gadget(&mut verifier, vec![x, x], vec![y, y]).unwrap();
+	assert_eq!(verifier.finalize().err(), Some(R1CSError::RandomizedConstraints));
+}
+
+#[test]
+fn prove_from_exported_witness() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// Build the constraint system on one side, and export its circuit and
+	// witness.
+	let (circuit, witness, com_a, com_b) = {
+		let mut transcript = Transcript::new(b"WitnessTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+		let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+		let c = prover.allocate_public(Scalar::from(12u64));
+		public_product_gadget(&mut prover, a, b, c);
+		let circuit = prover.circuit().unwrap().to_bytes();
+		let witness = prover.witness().unwrap().to_bytes();
+		(circuit, witness, com_a, com_b)
+	};
+
+	// Prove on the other side, without the gadget code.
+	let circuit = Circuit::from_bytes(&circuit).unwrap();
+	let witness = Witness::from_bytes(&witness).unwrap();
+	assert_eq!(witness.public_inputs(), &[Scalar::from(12u64)]);
+	let mut transcript = Transcript::new(b"WitnessTest");
+	let prover = Prover::from_witness(&pc_gens, &mut transcript, &circuit, &witness).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut transcript = Transcript::new(b"WitnessTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	let c = verifier.allocate_public(Scalar::from(12u64));
+	public_product_gadget(&mut verifier, a, b, c);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	// A witness for a larger circuit, or one that does not satisfy it.
+	let mut transcript = Transcript::new(b"WitnessTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (_, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (_, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	let c = prover.allocate_public(Scalar::from(12u64));
+	public_product_gadget(&mut prover, a, b, c);
+	prover.multiply(a.into(), b.into());
+	let larger = prover.witness().unwrap();
+
+	let mut transcript = Transcript::new(b"WitnessTest");
+	assert_eq!(
+		Prover::from_witness(&pc_gens, &mut transcript, &circuit, &larger).err(),
+		Some(R1CSError::WrongNumMultipliers { expected: 1, actual: 2 })
+	);
+
+	// Set the public input, after the two commitment openings, to 13.
+	let mut bytes = witness.to_bytes();
+	bytes[3 * 8 + 4 * 32] = 13;
+	let unsatisfied = Witness::from_bytes(&bytes).unwrap();
+	let mut transcript = Transcript::new(b"WitnessTest");
+	assert_eq!(
+		Prover::from_witness(&pc_gens, &mut transcript, &circuit, &unsatisfied).err(),
+		Some(R1CSError::UnsatisfiedConstraint { index: 2 })
+	);
+}
+
+#[test]
+fn delegated_proving() {
+	use webb_bulletproofs::r1cs::delegation::DelegationSecret;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let mut rng = ChaChaRng::from_seed([7u8; 32]);
+
+	// The client builds the constraint system, and keeps the blinding
+	// factors.
+	let blindings = vec![Scalar::random(&mut rng), Scalar::random(&mut rng)];
+	let mut transcript = Transcript::new(b"DelegationTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), blindings[0]);
+	let (com_b, b) = prover.commit(Scalar::from(4u64), blindings[1]);
+	let c = prover.allocate_public(Scalar::from(12u64));
+	public_product_gadget(&mut prover, a, b, c);
+	let circuit = prover.circuit().unwrap();
+	let witness = prover.witness().unwrap().without_blindings();
+	drop(prover);
+	assert_eq!(witness.blindings(), &[Scalar::zero(), Scalar::zero()]);
+	let (secret, blinding_commitment) = DelegationSecret::new(blindings, &pc_gens, &mut rng);
+
+	// The service proves without the blinding factors.
+	let mut transcript = Transcript::new(b"DelegationTest");
+	let prover = Prover::from_blinded_witness(&pc_gens, &mut transcript, &circuit, &witness, &[com_a, com_b]).unwrap();
+	let proof = prover
+		.prove_delegated_with_rng(
+			&bp_gens,
+			&blinding_commitment,
+			|challenge| {
+				assert_eq!(challenge.weights().len(), 2);
+				secret.respond(challenge)
+			},
+			&mut rng,
+		)
+		.unwrap();
+
+	// The client checks the proof.
+	let mut transcript = Transcript::new(b"DelegationTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	let c = verifier.allocate_public(Scalar::from(12u64));
+	public_product_gadget(&mut verifier, a, b, c);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	// Blinded commitments cannot be proven without the client.
+	let mut transcript = Transcript::new(b"DelegationTest");
+	let prover = Prover::from_blinded_witness(&pc_gens, &mut transcript, &circuit, &witness, &[com_a, com_b]).unwrap();
+	assert_eq!(prover.prove(&bp_gens).err(), Some(R1CSError::MissingBlindingFactors));
+
+	let mut transcript = Transcript::new(b"DelegationTest");
+	assert_eq!(
+		Prover::from_blinded_witness(&pc_gens, &mut transcript, &circuit, &witness, &[com_a]).err(),
+		Some(R1CSError::WrongNumCommitments { expected: 2, actual: 1 })
+	);
+}
+
+#[test]
+fn randomized_circuits_cannot_be_exported() {
+	let mut transcript = Transcript::new(b"CircuitTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let x = verifier.commit(CompressedRistretto::default());
+	let y = verifier.commit(CompressedRistretto::default());
+	ShuffleProof::gadget(&mut verifier, vec![x, x], vec![y, y]).unwrap();
+	assert_eq!(verifier.circuit(), Err(R1CSError::RandomizedConstraints));
+}
+
+/// Constrains a * b = o, then (a + z) * (b + z) = o + z * (a + b) + z^2 with
+/// the randomized multiplier allocated from the prover's assignments.
+fn value_of_gadget<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	a: Variable,
+	b: Variable,
+	is_prover: bool,
+) -> Result<(), R1CSError> {
+	let (_, _, o) = cs.multiply(a.into(), b.into());
+	cs.specify_randomized_constraints(move |cs| {
+		let z = cs.challenge_scalar(b"value_of challenge");
+		assert_eq!(cs.value_of(o).is_some(), is_prover);
+		assert_eq!(cs.value_of(Variable::MultiplierLeft(100)), None);
+
+		let assignments = match (cs.value_of(a), cs.value_of(b)) {
+			(Some(a_value), Some(b_value)) => Some((a_value + z, b_value + z)),
+			_ => None,
+		};
+		let (left, right, out) = cs.allocate_multiplier(assignments)?;
+		cs.constrain(left - a - z);
+		cs.constrain(right - b - z);
+		cs.constrain(out - o - (a + b) * z - z * z);
+		Ok(())
+	})
+}
+
+#[test]
+fn randomized_phase_reads_assignments() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"ValueOfTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	value_of_gadget(&mut prover, a, b, true).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"ValueOfTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	value_of_gadget(&mut verifier, a, b, false).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+#[test]
+fn circuit_digest_detects_mismatch() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"CircuitTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	circuit_gadget(&mut prover, a, b).unwrap();
+	let prover_digest = prover.circuit_digest();
+	assert_eq!(prover_digest, prover.circuit().unwrap().digest());
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"CircuitTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	circuit_gadget(&mut verifier, a, b).unwrap();
+	assert_eq!(verifier.circuit_digest(), prover_digest);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	// An extra, satisfied constraint changes the circuit.
+	let mut verifier_transcript = Transcript::new(b"CircuitTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let a = verifier.commit(com_a);
+	let b = verifier.commit(com_b);
+	circuit_gadget(&mut verifier, a, b).unwrap();
+	verifier.constrain(LinearCombination::default());
+	assert_ne!(verifier.circuit_digest(), prover_digest);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+}
+
+#[test]
+fn verifier_reports_shape_mismatch() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+
+	let mut prover_transcript = Transcript::new(b"ShapeTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	circuit_gadget(&mut prover, a, b).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verify = |proof: &R1CSProof, build: &dyn Fn(&mut Verifier)| {
+		let mut verifier_transcript = Transcript::new(b"ShapeTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let a = verifier.commit(com_a);
+		let b = verifier.commit(com_b);
+		circuit_gadget(&mut verifier, a, b).unwrap();
+		build(&mut verifier);
+		verifier.verify(proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(&proof, &|_| {}).is_ok());
+
+	// The circuit has 5 multipliers, so 4 more change the padded size.
+	assert_eq!(
+		verify(&proof, &|verifier| {
+			for _ in 0..4 {
+				verifier.allocate_multiplier(None).unwrap();
+			}
+		}),
+		Err(R1CSError::WrongNumMultipliers {
+			expected: 16,
+			actual: 8
+		})
+	);
+	// A multiplier in the randomized phase that the prover did not have.
+	assert_eq!(
+		verify(&proof, &|verifier| {
+			verifier
+				.specify_randomized_constraints(|cs| cs.allocate_multiplier(None).map(|_| ()))
+				.unwrap();
+		}),
+		Err(R1CSError::WrongNumMultipliers { expected: 8, actual: 8 })
+	);
+	assert_eq!(
+		verify(&proof, &|verifier| verifier
+			.constrain(Variable::MultiplierOutput(5).into())),
+		Err(R1CSError::WrongNumMultipliers { expected: 6, actual: 5 })
+	);
+	assert_eq!(
+		verify(&proof, &|verifier| verifier.constrain(Variable::Committed(2).into())),
+		Err(R1CSError::WrongNumCommitments { expected: 3, actual: 2 })
+	);
+	// A satisfied extra constraint still changes the circuit.
+	assert_eq!(
+		verify(&proof, &|verifier| verifier.constrain(LinearCombination::default())),
+		Err(R1CSError::VerificationError)
+	);
+
+	// A point that is not a valid encoding makes the proof malformed.
+	let mut bytes = proof.to_bytes();
+	for byte in bytes[1..33].iter_mut() {
+		*byte = 0xff;
+	}
+	let malformed = R1CSProof::from_bytes(&bytes).unwrap();
+	assert_eq!(verify(&malformed, &|_| {}), Err(R1CSError::FormatError));
+}
+
+#[test]
+fn prover_reports_missing_assignment() {
+	let pc_gens = PedersenGens::default();
+	let mut transcript = Transcript::new(b"MissingAssignmentTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (_, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (..) = prover.multiply(a.into(), a.into());
+
+	let err = prover.allocate_multiplier(None).unwrap_err();
+	assert_eq!(err, R1CSError::MissingAssignment { gate: 1 });
+	assert_eq!(
+		err.to_string(),
+		"Variable of multiplier 1 does not have a value assignment."
+	);
+}
+
+#[test]
+#[should_panic(expected = "constraint `a is 4` is not satisfied")]
+fn prover_reports_unsatisfied_constraint_label() {
+	let pc_gens = PedersenGens::default();
+	let mut transcript = Transcript::new(b"ConstraintLabelTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (_, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	prover.constrain_labeled(a - 3u64, "a is 3");
+	prover.constrain_labeled(a - 4u64, "a is 4");
+}
+
+#[test]
+fn constraint_labels_are_not_part_of_the_circuit() {
+	let digest = |labeled: bool| {
+		let mut transcript = Transcript::new(b"ConstraintLabelTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let a = verifier.commit(CompressedRistretto::default());
+		if labeled {
+			verifier.constrain_labeled(a - 3u64, "a is 3");
+		} else {
+			verifier.constrain(a - 3u64);
+		}
+		verifier.circuit_digest()
+	};
+	assert_eq!(digest(true), digest(false));
+}
+
+#[test]
+fn verify_with_precomputed_key() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let vk = VerificationKey::new(&pc_gens, &bp_gens, 8, 1).unwrap();
+	let small_vk = VerificationKey::new(&pc_gens, &bp_gens, 4, 1).unwrap();
+
+	let mut prover_transcript = Transcript::new(b"CircuitTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com_a, a) = prover.commit(Scalar::from(3u64), Scalar::from(10u64));
+	let (com_b, b) = prover.commit(Scalar::from(4u64), Scalar::from(11u64));
+	circuit_gadget(&mut prover, a, b).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verify = |vk: &VerificationKey, commitments: [CompressedRistretto; 2]| {
+		let mut transcript = Transcript::new(b"CircuitTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let a = verifier.commit(commitments[0]);
+		let b = verifier.commit(commitments[1]);
+		circuit_gadget(&mut verifier, a, b).unwrap();
+		verifier.verify_precomputed(&proof, vk)
+	};
+
+	assert!(verify(&vk, [com_a, com_b]).is_ok());
+	assert_eq!(verify(&vk, [com_b, com_a]), Err(R1CSError::VerificationError));
+	// The circuit has 5 multipliers, padded to 8.
+	assert_eq!(
+		verify(&small_vk, [com_a, com_b]),
+		Err(R1CSError::InvalidGeneratorsLength)
+	);
+}
+
+#[test]
+fn vector_commitment_as_circuit_input() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let values = [Scalar::from(3u64), Scalar::from(4u64)];
+	let blinding = Scalar::from(7u64);
+	let value_blindings = [Scalar::from(10u64), Scalar::from(11u64)];
+
+	// Commit to the witness vector once, and link it to the circuit inputs.
+	let mut prover_transcript = Transcript::new(b"VectorInputTest");
+	let (link_proof, vector_commitment, commitments) = VectorLinkProof::prove(
+		&bp_gens,
+		&pc_gens,
+		&mut prover_transcript,
+		&values,
+		&blinding,
+		&value_blindings,
+	)
+	.unwrap();
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (prover_commitments, vars) = prover.commit_each(&values, &value_blindings);
+	assert_eq!(prover_commitments, commitments);
+	circuit_gadget(&mut prover, vars[0], vars[1]).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"VectorInputTest");
+	assert!(link_proof
+		.verify(
+			&bp_gens,
+			&pc_gens,
+			&mut verifier_transcript,
+			&vector_commitment,
+			&commitments
+		)
+		.is_ok());
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let vars = verifier.commit_each(&commitments);
+	circuit_gadget(&mut verifier, vars[0], vars[1]).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+fn vector_shuffle_proof(
+	bp_gens: &BulletproofGens,
+	input: &[Scalar],
+	output: &[Scalar],
+) -> Result<(R1CSProof, CompressedRistretto, CompressedRistretto), R1CSError> {
+	let pc_gens = PedersenGens::default();
+	let mut prover_transcript = Transcript::new(b"VectorShuffleTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (input_commitment, input_vars) = prover.commit_vec(bp_gens, input, Scalar::from(5u64))?;
+	let (output_commitment, output_vars) = prover.commit_vec(bp_gens, output, Scalar::from(6u64))?;
+	ShuffleProof::gadget(&mut prover, input_vars, output_vars)?;
+	let proof = prover.prove(bp_gens)?;
+	Ok((proof, input_commitment, output_commitment))
+}
+
+fn vector_shuffle_verifier(
+	transcript: &mut Transcript,
+	k: usize,
+	input_commitment: CompressedRistretto,
+	output_commitment: CompressedRistretto,
+) -> Verifier<'_> {
+	let mut verifier = Verifier::new(transcript);
+	let input_vars = verifier.commit_vec(input_commitment, k);
+	let output_vars = verifier.commit_vec(output_commitment, k);
+	ShuffleProof::gadget(&mut verifier, input_vars, output_vars).unwrap();
+	verifier
+}
+
+#[test]
+fn vector_committed_inputs() {
+	use rand::Rng;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 2);
+	let mut rng = rand::thread_rng();
+
+	for &k in [1, 3, 17].iter() {
+		let input: Vec<Scalar> = (0..k).map(|_| Scalar::from(rng.gen::<u64>())).collect();
+		let mut output = input.clone();
+		output.shuffle(&mut rng);
+		let (proof, C_in, C_out) = vector_shuffle_proof(&bp_gens, &input, &output).unwrap();
+
+		let mut transcript = Transcript::new(b"VectorShuffleTest");
+		let verifier = vector_shuffle_verifier(&mut transcript, k, C_in, C_out);
+		assert_eq!(verifier.metrics().vector_committed_variables, 2 * k);
+		assert_eq!(verifier.metrics().vector_commitments, 2);
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+		let vk = VerificationKey::new(&pc_gens, &bp_gens, 128, 2).unwrap();
+		let mut transcript = Transcript::new(b"VectorShuffleTest");
+		let verifier = vector_shuffle_verifier(&mut transcript, k, C_in, C_out);
+		assert!(verifier.verify_precomputed(&proof, &vk).is_ok());
+
+		// The commitments are bound to their order and length.
+		let mut transcript = Transcript::new(b"VectorShuffleTest");
+		let verifier = vector_shuffle_verifier(&mut transcript, k, C_out, C_in);
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+		let mut transcript = Transcript::new(b"VectorShuffleTest");
+		let verifier = vector_shuffle_verifier(&mut transcript, k + 1, C_in, C_out);
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+	}
+
+	// Proofs with and without vector commitments batch together.
+	let input: Vec<Scalar> = (0..8u64).map(Scalar::from).collect();
+	let mut output = input.clone();
+	output.reverse();
+	let (vector_proof, C_in, C_out) = vector_shuffle_proof(&bp_gens, &input, &output).unwrap();
+	let (proof, input_commitments, output_commitments) = ShuffleProof::prove(
+		&pc_gens,
+		&bp_gens,
+		&mut Transcript::new(b"ShuffleProofTest"),
+		&input,
+		&output,
+	)
+	.unwrap();
+	let mut transcripts = [
+		Transcript::new(b"VectorShuffleTest"),
+		Transcript::new(b"ShuffleProofTest"),
+	];
+	let (first, second) = transcripts.split_at_mut(1);
+	let batch = vec![
+		(vector_shuffle_verifier(&mut first[0], 8, C_in, C_out), &vector_proof),
+		(
+			ShuffleProof::verifier(&mut second[0], &input_commitments, &output_commitments).unwrap(),
+			&proof.0,
+		),
+	];
+	assert!(Verifier::batch_verify(batch, &pc_gens, &bp_gens).is_ok());
+
+	// The entries of vector commitments use the generators of a second party.
+	let single_party_gens = BulletproofGens::new(128, 1);
+	assert!(vector_shuffle_proof(&single_party_gens, &input, &output).is_err());
+	let mut transcript = Transcript::new(b"VectorShuffleTest");
+	let verifier = vector_shuffle_verifier(&mut transcript, 8, C_in, C_out);
+	assert_eq!(
+		verifier.verify(&vector_proof, &pc_gens, &single_party_gens),
+		Err(R1CSError::InvalidGeneratorsLength)
+	);
+}
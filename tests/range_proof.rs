@@ -6,7 +6,9 @@ use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 
 use merlin::Transcript;
 
-use webb_bulletproofs::{BulletproofGens, PedersenGens, RangeProof};
+use sha3::Keccak256;
+
+use webb_bulletproofs::{BulletproofGens, HashTranscript, PedersenGens, RangeProof};
 
 // Tests that proofs generated with v1.0.0 continue to verify in later versions.
 #[test]
@@ -83,6 +85,116 @@ fn deserialize_and_verify() {
 	}
 }
 
+#[test]
+fn serde_roundtrip() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 8);
+
+	let mut rng = ChaChaRng::from_seed([7u8; 32]);
+	let blinding = Scalar::random(&mut rng);
+	let mut transcript = Transcript::new(b"Serde Roundtrip Test");
+	let (proof, vc) = RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 1037578891, &blinding, 32).unwrap();
+	let proof_bytes = proof.to_bytes();
+
+	// Human-readable formats use a hex string.
+	let json = serde_json::to_string(&proof).unwrap();
+	assert_eq!(json, format!("\"{}\"", hex::encode(&proof_bytes)));
+	let proof: RangeProof = serde_json::from_str(&json).unwrap();
+	assert_eq!(proof.to_bytes(), proof_bytes);
+
+	// Proofs stored as an array of bytes by earlier versions still decode.
+	let legacy_json = serde_json::to_string(&proof_bytes).unwrap();
+	let proof: RangeProof = serde_json::from_str(&legacy_json).unwrap();
+	assert_eq!(proof.to_bytes(), proof_bytes);
+
+	// Binary formats use the raw byte encoding.
+	let encoded = bincode::serialize(&proof).unwrap();
+	assert_eq!(&encoded[8..], &proof_bytes[..]);
+	let proof: RangeProof = bincode::deserialize(&encoded).unwrap();
+	assert_eq!(proof.to_bytes(), proof_bytes);
+
+	let pc_gens: PedersenGens = serde_json::from_str(&serde_json::to_string(&pc_gens).unwrap()).unwrap();
+	let bp_gens: BulletproofGens = bincode::deserialize(&bincode::serialize(&bp_gens).unwrap()).unwrap();
+	assert_eq!(bp_gens.gens_capacity, 64);
+	assert_eq!(bp_gens.party_capacity, 8);
+
+	let mut transcript = Transcript::new(b"Serde Roundtrip Test");
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut transcript, &vc, 32)
+		.is_ok());
+
+	assert!(serde_json::from_str::<RangeProof>("\"not hex\"").is_err());
+	assert!(serde_json::from_str::<PedersenGens>("\"00\"").is_err());
+
+	// Encoded capacities are bounded, so a short payload cannot request an
+	// unbounded number of generators.
+	let huge = format!(
+		"\"{}{}\"",
+		hex::encode(&(1u64 << 60).to_le_bytes()),
+		hex::encode(&1u64.to_le_bytes())
+	);
+	assert!(serde_json::from_str::<BulletproofGens>(&huge).is_err());
+}
+
+#[test]
+fn seeded_rng_proofs_are_reproducible() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 2);
+	let values = [7u64, 1 << 20];
+
+	let prove = |seed: u8| {
+		let mut rng = ChaChaRng::from_seed([seed; 32]);
+		let blindings: Vec<_> = values.iter().map(|_| Scalar::random(&mut rng)).collect();
+		let mut transcript = Transcript::new(b"Seeded Rng Test");
+		let (proof, commitments) =
+			RangeProof::prove_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, 32, &mut rng)
+				.unwrap();
+		(proof.to_bytes(), commitments)
+	};
+
+	let (proof_bytes, commitments) = prove(3);
+	assert_eq!(prove(3), (proof_bytes.clone(), commitments.clone()));
+	assert_ne!(prove(4).0, proof_bytes);
+
+	let proof = RangeProof::from_bytes(&proof_bytes).unwrap();
+	let mut transcript = Transcript::new(b"Seeded Rng Test");
+	let mut rng = ChaChaRng::from_seed([5u8; 32]);
+	assert!(proof
+		.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &commitments, 32, &mut rng)
+		.is_ok());
+}
+
+#[test]
+fn hash_transcript_proofs() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 2);
+	let values = [7u64, 1 << 20];
+	let mut rng = ChaChaRng::from_seed([9u8; 32]);
+	let blindings: Vec<_> = values.iter().map(|_| Scalar::random(&mut rng)).collect();
+
+	let mut transcript = HashTranscript::<Keccak256>::new(b"Hash Transcript Test");
+	let (proof, commitments) =
+		RangeProof::prove_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, 32, &mut rng)
+			.unwrap();
+
+	let mut transcript = HashTranscript::<Keccak256>::new(b"Hash Transcript Test");
+	assert!(proof
+		.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &commitments, 32, &mut rng)
+		.is_ok());
+
+	// The challenges of a Merlin transcript differ, so the proof does not
+	// verify against one.
+	let mut transcript = Transcript::new(b"Hash Transcript Test");
+	assert!(proof
+		.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &commitments, 32, &mut rng)
+		.is_err());
+
+	let mut transcript = HashTranscript::<Keccak256>::new(b"Other Transcript Test");
+	assert!(proof
+		.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &commitments, 32, &mut rng)
+		.is_err());
+}
+
 // This function generates test vectors and dumps them to stdout.
 // It can be run by uncommenting the #[test] annotation.
 // We allow(dead_code) to ensure that it continues to compile.